@@ -0,0 +1,384 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+use crate::db::models::{Job, Scene, UsageRecord};
+use crate::db::operations::{JobOps, SceneOps, UsageOps};
+
+/// How many entries `most_used_models` returns
+const TOP_MODELS: usize = 10;
+
+/// Usage statistics computed locally from the job and scene history
+#[derive(Debug, Clone, Serialize)]
+pub struct PersonalStats {
+    pub total_jobs: usize,
+    pub completed_jobs: usize,
+    pub failed_jobs: usize,
+    pub most_used_models: Vec<ModelUsage>,
+    /// Job count per local hour of day (0-23)
+    pub jobs_by_hour: Vec<usize>,
+    pub busiest_hour: Option<u32>,
+    /// Average generations per workflow before a result was saved as a scene
+    pub average_iterations_to_approval: Option<f64>,
+    pub enhancements: usize,
+    pub average_tokens_per_enhancement: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub count: usize,
+}
+
+/// Recorded job costs aggregated by provider, model and time period
+#[derive(Debug, Clone, Serialize)]
+pub struct SpendSummary {
+    pub total_usd: f64,
+    pub jobs: usize,
+    pub by_provider: Vec<SpendBucket>,
+    /// Keyed by "provider/model"
+    pub by_model: Vec<SpendBucket>,
+    /// Keyed by local day ("2025-01-31"), ISO week ("2025-W05") or month ("2025-01"), oldest first
+    pub by_period: Vec<SpendBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SpendBucket {
+    pub key: String,
+    pub cost_usd: f64,
+    pub jobs: usize,
+}
+
+/// Compute personal stats without any network access
+pub async fn get_personal_stats(pool: &SqlitePool) -> Result<PersonalStats> {
+    let jobs = JobOps::list_all(pool).await?;
+    let scenes = SceneOps::list_all(pool).await?;
+    let usage = UsageOps::list(pool, None, None).await?;
+    Ok(compute_stats(&jobs, &scenes, &usage))
+}
+
+/// Summarise spend for jobs completed between `since` and `until` (RFC 3339, inclusive)
+pub async fn get_spend_summary(
+    pool: &SqlitePool,
+    period: &str,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<SpendSummary> {
+    if !matches!(period, "day" | "week" | "month") {
+        return Err(anyhow::anyhow!(
+            "Unknown period '{}', expected day, week or month",
+            period
+        ));
+    }
+    let since = since.map(crate::db::models::normalize_timestamp).transpose()?;
+    let until = until.map(crate::db::models::normalize_timestamp).transpose()?;
+
+    let jobs: Vec<Job> = JobOps::list_all(pool)
+        .await?
+        .into_iter()
+        .filter(|job| {
+            let at = job.completed_at.as_deref().unwrap_or(&job.created_at);
+            since.as_deref().is_none_or(|since| at >= since)
+                && until.as_deref().is_none_or(|until| at <= until)
+        })
+        .collect();
+
+    Ok(compute_spend(&jobs, period))
+}
+
+fn compute_spend(jobs: &[Job], period: &str) -> SpendSummary {
+    let mut by_provider: HashMap<String, SpendBucket> = HashMap::new();
+    let mut by_model: HashMap<String, SpendBucket> = HashMap::new();
+    let mut by_period: HashMap<String, SpendBucket> = HashMap::new();
+    let mut total_usd = 0.0;
+    let mut count = 0;
+
+    for job in jobs {
+        let Some(cost) = job.cost else {
+            continue;
+        };
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let provider = data.get("provider").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let model = data.get("model").and_then(|v| v.as_str()).unwrap_or("default");
+
+        let at = job.completed_at.as_deref().unwrap_or(&job.created_at);
+        let period_key = match DateTime::parse_from_rfc3339(at) {
+            Ok(at) => {
+                let at = at.with_timezone(&Local);
+                match period {
+                    "day" => at.format("%Y-%m-%d").to_string(),
+                    "week" => {
+                        let week = at.iso_week();
+                        format!("{}-W{:02}", week.year(), week.week())
+                    }
+                    _ => at.format("%Y-%m").to_string(),
+                }
+            }
+            Err(_) => "unknown".to_string(),
+        };
+
+        for (buckets, key) in [
+            (&mut by_provider, provider.to_string()),
+            (&mut by_model, format!("{}/{}", provider, model)),
+            (&mut by_period, period_key),
+        ] {
+            let bucket = buckets.entry(key.clone()).or_insert(SpendBucket {
+                key,
+                cost_usd: 0.0,
+                jobs: 0,
+            });
+            bucket.cost_usd += cost;
+            bucket.jobs += 1;
+        }
+
+        total_usd += cost;
+        count += 1;
+    }
+
+    let by_cost = |buckets: HashMap<String, SpendBucket>| {
+        let mut buckets: Vec<SpendBucket> = buckets.into_values().collect();
+        buckets.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then_with(|| a.key.cmp(&b.key)));
+        buckets
+    };
+    let mut by_period: Vec<SpendBucket> = by_period.into_values().collect();
+    by_period.sort_by(|a, b| a.key.cmp(&b.key));
+
+    SpendSummary {
+        total_usd,
+        jobs: count,
+        by_provider: by_cost(by_provider),
+        by_model: by_cost(by_model),
+        by_period,
+    }
+}
+
+fn compute_stats(jobs: &[Job], scenes: &[Scene], usage: &[UsageRecord]) -> PersonalStats {
+    let mut model_counts: HashMap<(String, String), usize> = HashMap::new();
+    let mut jobs_by_hour = vec![0usize; 24];
+
+    // Text usage comes from usage records; older jobs without one fall back to the result JSON
+    let mut enhancement_tokens: Vec<u64> = usage
+        .iter()
+        .filter(|record| matches!(record.provider.as_str(), "anthropic" | "groq" | "perplexity"))
+        .map(|record| (record.input_tokens.unwrap_or(0) + record.output_tokens.unwrap_or(0)) as u64)
+        .collect();
+    let recorded_jobs: HashSet<&str> =
+        usage.iter().map(|record| record.job_id.as_str()).collect();
+
+    for job in jobs {
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let provider = data.get("provider").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let model = data.get("model").and_then(|v| v.as_str()).unwrap_or("default");
+        *model_counts
+            .entry((provider.to_string(), model.to_string()))
+            .or_default() += 1;
+
+        if let Ok(created) = DateTime::parse_from_rfc3339(&job.created_at) {
+            jobs_by_hour[created.with_timezone(&Local).hour() as usize] += 1;
+        }
+
+        if recorded_jobs.contains(job.id.as_str()) {
+            continue;
+        }
+        if let Some(tokens) = job.result.as_deref().and_then(usage_tokens) {
+            enhancement_tokens.push(tokens);
+        }
+    }
+
+    let mut most_used_models: Vec<ModelUsage> = model_counts
+        .into_iter()
+        .map(|((provider, model), count)| ModelUsage {
+            provider,
+            model,
+            count,
+        })
+        .collect();
+    most_used_models.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.model.cmp(&b.model)));
+    most_used_models.truncate(TOP_MODELS);
+
+    let busiest_hour = jobs_by_hour
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .max_by_key(|(hour, count)| (**count, std::cmp::Reverse(*hour)))
+        .map(|(hour, _)| hour as u32);
+
+    PersonalStats {
+        total_jobs: jobs.len(),
+        completed_jobs: jobs.iter().filter(|j| j.status == "completed").count(),
+        failed_jobs: jobs.iter().filter(|j| j.status == "failed").count(),
+        most_used_models,
+        jobs_by_hour,
+        busiest_hour,
+        average_iterations_to_approval: average_iterations_to_approval(jobs, scenes),
+        enhancements: enhancement_tokens.len(),
+        average_tokens_per_enhancement: average(&enhancement_tokens),
+    }
+}
+
+/// Total tokens from a text job result's `metadata.usage` (Anthropic format)
+fn usage_tokens(result: &str) -> Option<u64> {
+    let result: serde_json::Value = serde_json::from_str(result).ok()?;
+    let usage = result.get("metadata")?.get("usage")?;
+    let input = usage.get("input_tokens").and_then(|v| v.as_u64());
+    let output = usage.get("output_tokens").and_then(|v| v.as_u64());
+
+    match (input, output) {
+        (None, None) => None,
+        (input, output) => Some(input.unwrap_or(0) + output.unwrap_or(0)),
+    }
+}
+
+/// For every scene saved from a job, count the generations in that workflow since the
+/// previously saved one (inclusive) and average the counts.
+fn average_iterations_to_approval(jobs: &[Job], scenes: &[Scene]) -> Option<f64> {
+    let jobs_by_id: HashMap<&str, &Job> = jobs.iter().map(|j| (j.id.as_str(), j)).collect();
+
+    // Approved job creation times per workflow, oldest first
+    let mut approvals: HashMap<&str, Vec<&str>> = HashMap::new();
+    for scene in scenes {
+        let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        let Some(job) = data
+            .get("metadata")
+            .and_then(|m| m.get("jobId"))
+            .and_then(|id| id.as_str())
+            .and_then(|id| jobs_by_id.get(id))
+        else {
+            continue;
+        };
+        approvals
+            .entry(job.workflow_id.as_str())
+            .or_default()
+            .push(job.created_at.as_str());
+    }
+
+    let mut iterations = Vec::new();
+    for (workflow_id, mut approved_at) in approvals {
+        approved_at.sort_unstable();
+        approved_at.dedup();
+
+        let mut created: Vec<&str> = jobs
+            .iter()
+            .filter(|j| j.workflow_id == workflow_id && j.job_type == "generation")
+            .map(|j| j.created_at.as_str())
+            .collect();
+        created.sort_unstable();
+
+        let mut previous: Option<&str> = None;
+        for approval in approved_at {
+            let count = created
+                .iter()
+                .filter(|c| **c <= approval && previous.is_none_or(|p| **c > p))
+                .count();
+            if count > 0 {
+                iterations.push(count as u64);
+            }
+            previous = Some(approval);
+        }
+    }
+
+    average(&iterations)
+}
+
+fn average(values: &[u64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<u64>() as f64 / values.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, created_at: &str, model: &str, result: Option<&str>) -> Job {
+        Job {
+            id: id.to_string(),
+            workflow_id: "wf".to_string(),
+            scene_id: None,
+            job_type: "generation".to_string(),
+            status: "completed".to_string(),
+            data: serde_json::json!({ "provider": "openai", "model": model }).to_string(),
+            result: result.map(|r| r.to_string()),
+            error: None,
+            created_at: created_at.to_string(),
+            started_at: None,
+            completed_at: None,
+            scheduled_at: None,
+            app_version: None,
+            retry_of: None,
+            cost: None,
+            lane: "interactive".to_string(),
+            error_kind: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let jobs = vec![
+            job("a", "2025-01-01T10:00:00+00:00", "gpt-image-1", None),
+            job("b", "2025-01-01T10:05:00+00:00", "gpt-image-1", None),
+            job("c", "2025-01-01T10:10:00+00:00", "gpt-image-1", None),
+            job("d", "2025-01-01T11:00:00+00:00", "sora-2", None),
+            job(
+                "e",
+                "2025-01-01T12:00:00+00:00",
+                "claude",
+                Some(r#"{"metadata":{"usage":{"input_tokens":100,"output_tokens":300}}}"#),
+            ),
+        ];
+        let scenes = vec![
+            Scene {
+                id: "s1".to_string(),
+                workflow_id: "wf".to_string(),
+                name: "first".to_string(),
+                data: r#"{"metadata":{"jobId":"c"}}"#.to_string(),
+                thumbnail: None,
+                created_at: "2025-01-01T10:11:00+00:00".to_string(),
+            },
+            Scene {
+                id: "s2".to_string(),
+                workflow_id: "wf".to_string(),
+                name: "second".to_string(),
+                data: r#"{"metadata":{"jobId":"d"}}"#.to_string(),
+                thumbnail: None,
+                created_at: "2025-01-01T11:01:00+00:00".to_string(),
+            },
+        ];
+
+        let stats = compute_stats(&jobs, &scenes, &[]);
+        assert_eq!(stats.total_jobs, 5);
+        assert_eq!(stats.most_used_models[0].model, "gpt-image-1");
+        assert_eq!(stats.most_used_models[0].count, 3);
+        assert_eq!(stats.jobs_by_hour.iter().sum::<usize>(), 5);
+        // 3 generations before the first save, 1 before the second
+        assert_eq!(stats.average_iterations_to_approval, Some(2.0));
+        assert_eq!(stats.enhancements, 1);
+        assert_eq!(stats.average_tokens_per_enhancement, Some(400.0));
+    }
+
+    #[test]
+    fn test_compute_spend() {
+        let mut jobs = vec![
+            job("a", "2025-01-01T10:00:00+00:00", "gpt-image-1", None),
+            job("b", "2025-01-01T10:05:00+00:00", "gpt-image-1", None),
+            job("c", "2025-03-01T10:00:00+00:00", "sora-2", None),
+            job("d", "2025-03-01T11:00:00+00:00", "sora-2", None),
+        ];
+        jobs[0].cost = Some(0.25);
+        jobs[1].cost = Some(0.25);
+        jobs[2].cost = Some(1.0);
+
+        let spend = compute_spend(&jobs, "month");
+        assert_eq!(spend.jobs, 3);
+        assert_eq!(spend.total_usd, 1.5);
+        assert_eq!(spend.by_provider.len(), 1);
+        assert_eq!(spend.by_model[0].key, "openai/sora-2");
+        assert_eq!(spend.by_period.len(), 2);
+        assert_eq!(spend.by_period[0].cost_usd, 0.5);
+    }
+}
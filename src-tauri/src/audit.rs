@@ -0,0 +1,25 @@
+//! Append-only record of configuration changes, deletions, overrides and exports.
+//!
+//! Studio machines are often shared, so every such action is stored with the OS
+//! account that ran it. Recording never fails the action itself.
+
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::db::operations::AuditOps;
+
+/// Store an audit entry; failures are only reported
+pub async fn record(pool: &SqlitePool, action: &str, target: Option<&str>, details: Value) {
+    let actor = current_user();
+    if let Err(e) = AuditOps::create(pool, action, target, &details, actor.as_deref()).await {
+        log_warn!("[Audit] Failed to record {}: {}", action, e);
+    }
+}
+
+/// Name of the logged-in OS account
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|user| !user.is_empty())
+}
@@ -1,5 +1,72 @@
+use crate::audit;
 use crate::db::{models::*, operations::*, Database};
-use crate::generation::GenerationService;
+use crate::export::{
+    archive::ZipExportSummary,
+    c2pa::{self, C2paSettings, C2PA_SETTINGS_KEY},
+    grid::GridSummary,
+    ics::ScheduleExportSummary,
+    markdown::MarkdownExportSummary,
+    settings::{SettingsExportSummary, SettingsImportSummary},
+    social::SocialExportSummary,
+    usage::UsageExportSummary,
+    watermark::{WatermarkReport, WatermarkSettings, WATERMARK_SETTINGS_KEY},
+};
+use crate::generation::budget::{BudgetSettings, BUDGET_SETTINGS_KEY};
+use crate::generation::cache::{ResultCacheSettings, RESULT_CACHE_SETTINGS_KEY};
+use crate::generation::clip::{ClipSettings, CLIP_SETTINGS_KEY};
+use crate::generation::confirmation::{
+    ConfirmationSettings, CONFIRMATION_SETTINGS_KEY, NEEDS_CONFIRMATION,
+};
+use crate::generation::duplicates::{
+    self, DuplicatePromptEvent, SimilarPrompt, DUPLICATE_PROMPT_EVENT,
+};
+use crate::generation::deprecation::{self, DeprecatedUsage};
+use crate::generation::endpoints::{self, BaseUrlSettings};
+use crate::generation::errors::GenerationError;
+use crate::generation::fallback::{FallbackSettings, FallbackTarget, FALLBACK_SETTINGS_KEY};
+use crate::generation::headers::{self, HeaderSettings};
+use crate::generation::health::ProviderTestResult;
+use crate::generation::http::{self, HttpSettings, HTTP_SETTINGS_KEY};
+use crate::generation::keys::{self, ApiKeyInfo, KeyRotation, ProviderKeys, DEFAULT_KEY_ID};
+use crate::generation::lint::{self, LintReviewer, PromptLintReport};
+use crate::generation::narration::{self, Narration};
+use crate::generation::pipeline::{PipelineData, PipelineStep, PIPELINE_JOB_TYPE};
+use crate::generation::presets;
+use crate::generation::pricing::GenerationEstimate;
+use crate::generation::processor::{JobProcessor, ProcessorSettings, PROCESSOR_SETTINGS_KEY};
+use crate::generation::providers::external::{
+    ExternalProvider, ExternalProviderConfig, PluginDescription, EXTERNAL_PROVIDERS_SETTINGS_KEY,
+};
+use crate::generation::providers::midjourney;
+use crate::generation::providers::mock::{
+    self, MockProviderSettings, MOCK_PROVIDER_SETTINGS_KEY,
+};
+use crate::generation::providers::openai_compatible::{
+    OpenAICompatibleConfig, OPENAI_COMPATIBLE_SETTINGS_KEY,
+};
+use crate::generation::refine::{self, RefineSessionDetail, RefineStep};
+use crate::generation::rerun::{self, RerunSummary};
+use crate::generation::sweep::{self, SweepAxis, SweepReport, SweepRequest, SweepSummary};
+use crate::generation::routing::{self, RoutingSettings, ROUTING_SETTINGS_KEY};
+use crate::generation::timeouts::{self, ProviderTimeouts};
+use crate::generation::validation::SubmitError;
+use crate::generation::{GenerationService, ModelInfo};
+use crate::guest::{GuestMode, GuestModeStatus};
+use crate::i18n::{self, LocaleInfo, Translations, LOCALE_SETTINGS_KEY};
+use crate::logs::{self, LogConsole, LogEntry, LogLevel};
+use crate::maintenance::expiry::{
+    self, AssetExpirySettings, ExpiryPreview, ASSET_EXPIRY_SETTINGS_KEY,
+};
+use crate::maintenance::integrity::IntegrityReport;
+use crate::maintenance::optimize::OptimizeReport;
+use crate::maintenance::thumbnails::{self, BackfillData, THUMBNAIL_BACKFILL_JOB_TYPE};
+use crate::maintenance::retention::{RetentionPolicy, RetentionReport, RETENTION_POLICY_KEY};
+use crate::notifications::audio::{AudioCue, AudioNotificationSettings, AUDIO_SETTINGS_KEY};
+use crate::notifications::JobNotification;
+use crate::permissions::{PermissionStatus, Permissions};
+use crate::secrets::{self, SecretLocation};
+use crate::storage::{StorageSettings, STORAGE_SETTINGS_KEY};
+use std::collections::BTreeMap;
 use std::net::{TcpStream, ToSocketAddrs};
 use std::sync::Arc;
 use std::time::Duration;
@@ -42,11 +109,39 @@ pub async fn update_workflow(
         .map_err(|e| e.to_string())
 }
 
+/// Providers a workflow may use; `None` when it is unrestricted
+#[tauri::command]
+pub async fn get_workflow_providers(
+    db: State<'_, Database>,
+    workflow_id: String,
+) -> Result<Option<Vec<String>>, String> {
+    WorkflowOps::allowed_providers(db.pool(), &workflow_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Restrict a workflow to the given providers, or lift the restriction with `None`
+#[tauri::command]
+pub async fn set_workflow_providers(
+    db: State<'_, Database>,
+    workflow_id: String,
+    providers: Option<Vec<String>>,
+) -> Result<(), String> {
+    WorkflowOps::set_allowed_providers(db.pool(), &workflow_id, providers.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "providers": providers });
+    audit::record(db.pool(), "workflow.set_providers", Some(&workflow_id), details).await;
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn delete_workflow(db: State<'_, Database>, id: String) -> Result<(), String> {
     WorkflowOps::delete(db.pool(), &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "workflow.delete", Some(&id), serde_json::json!({})).await;
+    Ok(())
 }
 
 /// Scene Commands
@@ -77,19 +172,58 @@ pub async fn list_all_scenes(db: State<'_, Database>) -> Result<Vec<Scene>, Stri
         .map_err(|e| e.to_string())
 }
 
+/// Speech and music generated for a scene, from generation jobs that name it
+#[tauri::command]
+pub async fn list_scene_narrations(
+    db: State<'_, Database>,
+    scene_id: String,
+) -> Result<Vec<Narration>, String> {
+    narration::for_scene(db.pool(), &scene_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn delete_scene(db: State<'_, Database>, id: String) -> Result<(), String> {
     SceneOps::delete(db.pool(), &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "scene.delete", Some(&id), serde_json::json!({})).await;
+    Ok(())
 }
 
 /// Job Commands
 #[tauri::command]
-pub async fn create_job(db: State<'_, Database>, input: CreateJobInput) -> Result<Job, String> {
+pub async fn create_job(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    mut input: CreateJobInput,
+) -> Result<Job, SubmitError> {
+    if input.job_type == "generation" {
+        let field = |name: &str| input.data.get(name).and_then(|v| v.as_str()).unwrap_or("");
+        let service = service.read().await;
+        let target = routing::resolve(
+            db.pool(),
+            &service,
+            Some(&input.workflow_id),
+            field("provider"),
+            field("model"),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        input.data["provider"] = serde_json::json!(target.provider);
+        input.data["model"] = serde_json::json!(target.model);
+
+        let parameters = input.data.get("parameters").unwrap_or(&serde_json::Value::Null);
+        let errors = service.validate_parameters(&target.provider, &target.model, parameters);
+        if !errors.is_empty() {
+            return Err(SubmitError::invalid_params(errors));
+        }
+    }
+
     JobOps::create(db.pool(), input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string().into())
 }
 
 #[tauri::command]
@@ -118,6 +252,22 @@ pub async fn update_job(
 #[tauri::command]
 pub async fn delete_job(db: State<'_, Database>, job_id: String) -> Result<(), String> {
     JobOps::delete(db.pool(), &job_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "job.delete", Some(&job_id), serde_json::json!({})).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn retry_job(db: State<'_, Database>, id: String) -> Result<Job, String> {
+    JobOps::retry(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_job_lineage(db: State<'_, Database>, id: String) -> Result<Vec<Job>, String> {
+    JobOps::lineage(db.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
@@ -146,115 +296,2214 @@ pub async fn list_versions(
 
 /// Generation Commands
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn submit_generation(
+    app_handle: tauri::AppHandle,
     db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
     workflow_id: String,
     provider: String,
     prompt: String,
     model: String,
     parameters: serde_json::Value,
-) -> Result<Job, String> {
-    let job_data = serde_json::json!({
+    scheduled_at: Option<String>,
+    timeout_seconds: Option<u64>,
+    lane: Option<String>,
+) -> Result<Job, SubmitError> {
+    let target = routing::resolve(
+        db.pool(),
+        &*service.read().await,
+        Some(&workflow_id),
+        &provider,
+        &model,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    let (provider, model) = (target.provider, target.model);
+
+    crate::generation::policy::check_provider(db.pool(), &workflow_id, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Caught here rather than minutes later when the processor reaches the job
+    let errors = service.read().await.validate_parameters(&provider, &model, &parameters);
+    if !errors.is_empty() {
+        return Err(SubmitError::invalid_params(errors));
+    }
+
+    // Expensive jobs wait for an explicit approve_job instead of running right away
+    let confirmation: ConfirmationSettings =
+        SettingsOps::get_or_default(db.pool(), CONFIRMATION_SETTINGS_KEY)
+            .await
+            .map_err(|e| e.to_string())?;
+    let status = if confirmation.requires_confirmation(&provider, &model, &parameters) {
+        NEEDS_CONFIRMATION
+    } else {
+        "pending"
+    };
+    let lane = lane.unwrap_or_else(|| crate::generation::processor::default_lane(&model).to_string());
+
+    let mut job_data = serde_json::json!({
         "provider": provider,
         "prompt": prompt,
         "model": model,
         "parameters": parameters,
     });
 
-    JobOps::create(
+    if let Some(timeout_seconds) = timeout_seconds {
+        job_data["timeout_seconds"] = serde_json::json!(timeout_seconds);
+    }
+
+    // Not fatal: the warning is only a hint to reuse earlier results
+    let duplicates = duplicates::find_similar(db.pool(), &provider, &model, &prompt)
+        .await
+        .unwrap_or_else(|e| {
+            log_warn!("[Duplicates] Failed to look for similar prompts: {}", e);
+            Vec::new()
+        });
+
+    let job = JobOps::create_with_status(
         db.pool(),
         CreateJobInput {
             workflow_id,
             scene_id: None,
             job_type: "generation".to_string(),
             data: job_data,
+            scheduled_at,
+            lane: Some(lane),
         },
+        status,
     )
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+
+    if !duplicates.is_empty() {
+        use tauri::Emitter;
+        let event = DuplicatePromptEvent {
+            job_id: job.id.clone(),
+            matches: duplicates,
+        };
+        if let Err(e) = app_handle.emit(DUPLICATE_PROMPT_EVENT, event) {
+            log_warn!("[Duplicates] Failed to emit warning: {}", e);
+        }
+    }
+    Ok(job)
 }
 
+/// Earlier completed generations whose prompt is nearly identical, with their results
 #[tauri::command]
-pub async fn configure_provider(
-    service: State<'_, Arc<RwLock<GenerationService>>>,
+pub async fn get_similar_prompts(
+    db: State<'_, Database>,
     provider: String,
-    api_key: String,
-) -> Result<(), String> {
-    let mut service = service.write().await;
-    service
-        .configure_provider(&provider, api_key)
+    model: String,
+    prompt: String,
+) -> Result<Vec<SimilarPrompt>, String> {
+    duplicates::find_similar(db.pool(), &provider, &model, &prompt)
+        .await
         .map_err(|e| e.to_string())
 }
 
+/// Queue every scene's latest completed generation again on another provider and model
 #[tauri::command]
-pub async fn list_providers(
+pub async fn rerun_workflow(
+    db: State<'_, Database>,
     service: State<'_, Arc<RwLock<GenerationService>>>,
-) -> Result<Vec<String>, String> {
+    workflow_id: String,
+    new_provider: String,
+    new_model: String,
+) -> Result<RerunSummary, String> {
     let service = service.read().await;
-    Ok(service.list_providers())
+    let summary =
+        rerun::rerun_workflow(db.pool(), &service, &workflow_id, &new_provider, &new_model)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({
+        "batch_id": summary.batch_id,
+        "provider": summary.provider,
+        "model": summary.model,
+        "jobs": summary.jobs.len(),
+    });
+    audit::record(db.pool(), "workflow.rerun", Some(&workflow_id), details).await;
+    Ok(summary)
 }
 
+/// Queue one generation per combination of the axes' values and a job that composes
+/// the results into a labeled grid
 #[tauri::command]
-pub async fn configure_local_provider(
+pub async fn submit_sweep(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    base_request: SweepRequest,
+    axes: Vec<SweepAxis>,
+) -> Result<SweepSummary, SubmitError> {
+    let mut request = base_request;
+    let service = service.read().await;
+    let target = routing::resolve(
+        db.pool(),
+        &service,
+        Some(&request.workflow_id),
+        &request.provider,
+        &request.model,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    (request.provider, request.model) = (target.provider, target.model);
+
+    crate::generation::policy::check_provider(db.pool(), &request.workflow_id, &request.provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let plan = sweep::plan(&request.parameters, &axes).map_err(|e| e.to_string())?;
+    for (_, parameters) in &plan.cells {
+        let errors = service.validate_parameters(&request.provider, &request.model, parameters);
+        if !errors.is_empty() {
+            return Err(SubmitError::invalid_params(errors));
+        }
+    }
+
+    let confirmation: ConfirmationSettings =
+        SettingsOps::get_or_default(db.pool(), CONFIRMATION_SETTINGS_KEY)
+            .await
+            .map_err(|e| e.to_string())?;
+    let summary = sweep::queue(db.pool(), &request, plan, &confirmation)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(summary)
+}
+
+/// Results of a sweep by axis value, with image metrics and any judged scores
+#[tauri::command]
+pub async fn get_sweep_report(
+    db: State<'_, Database>,
+    sweep_id: String,
+) -> Result<SweepReport, String> {
+    sweep::report(db.pool(), &sweep_id).await.map_err(|e| e.to_string())
+}
+
+/// Score a sweep's images with a vision model and return the updated report
+#[tauri::command]
+pub async fn judge_sweep(
+    db: State<'_, Database>,
     service: State<'_, Arc<RwLock<GenerationService>>>,
+    sweep_id: String,
     provider: String,
-    api_url: String,
-) -> Result<(), String> {
-    let mut service = service.write().await;
-    service
-        .configure_local_provider(&provider, api_url)
+    model: String,
+) -> Result<SweepReport, String> {
+    let service = service.read().await;
+    sweep::judge(db.pool(), &service, &sweep_id, &provider, &model)
+        .await
         .map_err(|e| e.to_string())
 }
 
+/// Lock the seed and settings of a completed generation for prompt-only follow-ups
 #[tauri::command]
-pub fn check_port(address: String) -> bool {
-    let timeout = Duration::from_secs(1);
+pub async fn start_refine_session(
+    db: State<'_, Database>,
+    job_id: String,
+) -> Result<RefineSession, String> {
+    refine::start(db.pool(), &job_id).await.map_err(|e| e.to_string())
+}
 
-    if let Ok(iter) = address.to_socket_addrs() {
-        for addr in iter {
-            if TcpStream::connect_timeout(&addr, timeout).is_ok() {
-                return true;
-            }
-        }
-    }
+/// Queue the next step of a refine session with a changed prompt
+#[tauri::command]
+pub async fn refine_prompt(
+    db: State<'_, Database>,
+    session_id: String,
+    prompt: String,
+) -> Result<RefineStep, String> {
+    refine::refine(db.pool(), &session_id, &prompt)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    false
+/// Queue a step with half of the prompt changes between two steps of a session
+#[tauri::command]
+pub async fn bisect_refine(
+    db: State<'_, Database>,
+    session_id: String,
+    from_step: u64,
+    to_step: u64,
+) -> Result<RefineStep, String> {
+    refine::bisect(db.pool(), &session_id, from_step, to_step)
+        .await
+        .map_err(|e| e.to_string())
 }
 
-/// Call AI for text generation (used by enhance feature)
+/// Queue an upscale or variations of image `index` (1 to 4) of a Midjourney grid
 #[tauri::command]
-pub async fn call_ai(
+pub async fn queue_midjourney_action(
+    db: State<'_, Database>,
+    job_id: String,
+    action: String,
+    index: u64,
+) -> Result<Job, String> {
+    let parent = JobOps::get(db.pool(), &job_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Job not found: {}", job_id))?;
+    let input = midjourney::follow_up_job(&parent, &action, index).map_err(|e| e.to_string())?;
+    crate::generation::policy::check_provider(db.pool(), &input.workflow_id, "midjourney")
+        .await
+        .map_err(|e| e.to_string())?;
+    JobOps::create(db.pool(), input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_refine_session(
+    db: State<'_, Database>,
+    session_id: String,
+) -> Result<RefineSessionDetail, String> {
+    refine::get(db.pool(), &session_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_refine_sessions(
+    db: State<'_, Database>,
+    workflow_id: String,
+) -> Result<Vec<RefineSession>, String> {
+    RefineOps::list_by_workflow(db.pool(), &workflow_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Schema of the parameters a model accepts, for building parameter forms
+#[tauri::command]
+pub async fn get_parameter_schema(
     service: State<'_, Arc<RwLock<GenerationService>>>,
     provider: String,
     model: String,
+) -> Result<serde_json::Value, String> {
+    let service = service.read().await;
+    let provider = service
+        .get_provider(&provider)
+        .ok_or_else(|| format!("Provider not found: {}", provider))?;
+    Ok(provider.parameter_schema(&model))
+}
+
+/// Queue a multi-step pipeline job whose steps run in order, each fed the previous output
+#[tauri::command]
+pub async fn submit_pipeline(
+    db: State<'_, Database>,
+    workflow_id: String,
     prompt: String,
-    max_tokens: Option<u32>,
-    temperature: Option<f64>,
-) -> Result<String, String> {
-    use crate::generation::GenerationRequest;
+    steps: Vec<PipelineStep>,
+    scheduled_at: Option<String>,
+    timeout_seconds: Option<u64>,
+    lane: Option<String>,
+) -> Result<Job, String> {
+    let data = PipelineData {
+        prompt,
+        steps,
+        step_results: Vec::new(),
+        timeout_seconds,
+    };
+    data.validate().map_err(|e| e.to_string())?;
+    for step in &data.steps {
+        crate::generation::policy::check_provider(db.pool(), &workflow_id, &step.provider)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
 
-    let service = service.read().await;
+    let confirmation: ConfirmationSettings =
+        SettingsOps::get_or_default(db.pool(), CONFIRMATION_SETTINGS_KEY)
+            .await
+            .map_err(|e| e.to_string())?;
+    let needs_confirmation = data.steps.iter().any(|step| {
+        confirmation.requires_confirmation(&step.provider, &step.model, &step.parameters)
+    });
+    let status = if needs_confirmation {
+        NEEDS_CONFIRMATION
+    } else {
+        "pending"
+    };
 
-    let params = serde_json::json!({
-        "max_tokens": max_tokens.unwrap_or(4096),
-        "temperature": temperature.unwrap_or(1.0),
+    // A pipeline with any video step belongs in the batch lane
+    let lane = lane.unwrap_or_else(|| {
+        let batch = data
+            .steps
+            .iter()
+            .any(|step| crate::generation::processor::default_lane(&step.model) == BATCH_LANE);
+        if batch { BATCH_LANE } else { INTERACTIVE_LANE }.to_string()
     });
 
-    let request = GenerationRequest {
-        prompt,
-        model,
-        parameters: params,
-    };
+    JobOps::create_with_status(
+        db.pool(),
+        CreateJobInput {
+            workflow_id,
+            scene_id: None,
+            job_type: PIPELINE_JOB_TYPE.to_string(),
+            data: serde_json::to_value(&data).map_err(|e| e.to_string())?,
+            scheduled_at,
+            lane: Some(lane),
+        },
+        status,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
 
-    let result = service
-        .generate(&provider, request)
+/// Estimate cost, duration and parameter problems without calling the provider or creating a job
+#[tauri::command]
+pub async fn estimate_generation(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    model: String,
+    parameters: serde_json::Value,
+) -> Result<GenerationEstimate, String> {
+    let target = routing::resolve(db.pool(), &*service.read().await, None, &provider, &model)
         .await
         .map_err(|e| e.to_string())?;
+    let (provider, model) = (target.provider, target.model);
 
-    // For text generation, the result is in output_data
-    result
-        .output_data
-        .ok_or_else(|| "No text output received".to_string())
+    let history = JobOps::recent_durations(db.pool(), &provider, &model, 20)
+        .await
+        .map_err(|e| e.to_string())?;
+    let parameters = presets::apply(db.pool(), &provider, &model, parameters)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut estimate =
+        crate::generation::pricing::estimate(&provider, &model, &parameters, &history);
+
+    let service = service.read().await;
+    if service.get_provider(&provider).is_none() {
+        estimate.errors.insert(0, format!("Provider not found: {}", provider));
+    }
+    for error in service.validate_parameters(&provider, &model, &parameters) {
+        estimate.errors.push(format!("{} {}", error.field, error.message));
+    }
+
+    Ok(estimate)
+}
+
+/// Waiting jobs and parameter presets that name a deprecated or retired model
+#[tauri::command]
+pub async fn list_deprecated_model_usage(
+    db: State<'_, Database>,
+) -> Result<Vec<DeprecatedUsage>, String> {
+    deprecation::scan(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Sanitized provider requests, responses and errors recorded for a job
+#[tauri::command]
+pub async fn get_job_logs(db: State<'_, Database>, job_id: String) -> Result<Vec<JobLog>, String> {
+    JobLogOps::list(db.pool(), &job_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Release a job held in `needs_confirmation` to the queue
+#[tauri::command]
+pub async fn approve_job(db: State<'_, Database>, id: String) -> Result<Job, String> {
+    let job = JobOps::approve(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Approval overrides the cost confirmation threshold
+    let details = serde_json::json!({ "estimated_cost": job.cost });
+    audit::record(db.pool(), "job.approve", Some(&id), details).await;
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn get_confirmation_settings(
+    db: State<'_, Database>,
+) -> Result<ConfirmationSettings, String> {
+    SettingsOps::get_or_default(db.pool(), CONFIRMATION_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_confirmation_settings(
+    db: State<'_, Database>,
+    settings: ConfirmationSettings,
+) -> Result<ConfirmationSettings, String> {
+    SettingsOps::set(db.pool(), CONFIRMATION_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.confirmation", None, details).await;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_budget_settings(db: State<'_, Database>) -> Result<BudgetSettings, String> {
+    SettingsOps::get_or_default(db.pool(), BUDGET_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save spending caps; jobs they paused are checked again against the new caps
+#[tauri::command]
+pub async fn update_budget_settings(
+    db: State<'_, Database>,
+    settings: BudgetSettings,
+) -> Result<BudgetSettings, String> {
+    SettingsOps::set(db.pool(), BUDGET_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    JobOps::resume_waiting_budget(db.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.budget", None, details).await;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_result_cache_settings(
+    db: State<'_, Database>,
+) -> Result<ResultCacheSettings, String> {
+    SettingsOps::get_or_default(db.pool(), RESULT_CACHE_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_result_cache_settings(
+    db: State<'_, Database>,
+    settings: ResultCacheSettings,
+) -> Result<ResultCacheSettings, String> {
+    SettingsOps::set(db.pool(), RESULT_CACHE_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+/// Drop cached results, for one provider or all of them; returns the number removed
+#[tauri::command]
+pub async fn clear_result_cache(
+    db: State<'_, Database>,
+    provider: Option<String>,
+) -> Result<u64, String> {
+    let removed = ResultCacheOps::clear(db.pool(), provider.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "removed": removed });
+    audit::record(db.pool(), "result_cache.clear", provider.as_deref(), details).await;
+    Ok(removed)
+}
+
+#[tauri::command]
+pub async fn get_storage_settings(db: State<'_, Database>) -> Result<StorageSettings, String> {
+    let mut settings: StorageSettings = SettingsOps::get_or_default(db.pool(), STORAGE_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())?;
+    // The S3 secret stays in the credential store
+    if let Some(s3) = settings.s3.as_mut() {
+        s3.secret_access_key.clear();
+    }
+    Ok(settings)
+}
+
+/// Switch where generated files are written; the backend is checked before it is saved
+///
+/// An empty S3 secret keeps the saved one.
+#[tauri::command]
+pub async fn update_storage_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    mut settings: StorageSettings,
+) -> Result<StorageSettings, String> {
+    if let Some(s3) = settings.s3.as_mut().filter(|s3| s3.secret_access_key.is_empty()) {
+        let saved = crate::storage::load_settings(db.pool(), db.data_dir())
+            .await
+            .map_err(|e| e.to_string())?;
+        s3.secret_access_key = saved.s3.map(|s3| s3.secret_access_key).unwrap_or_default();
+    }
+    let backend = crate::storage::build_backend(&settings).map_err(|e| e.to_string())?;
+    backend.check().await.map_err(|e| e.to_string())?;
+
+    crate::storage::save_settings(db.pool(), db.data_dir(), &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.read().await.set_storage(backend);
+
+    // Credentials stay out of the log
+    let details = serde_json::json!({
+        "backend": settings.backend,
+        "path": settings.path,
+        "bucket": settings.s3.as_ref().map(|s3| &s3.bucket),
+    });
+    audit::record(db.pool(), "settings.storage", None, details).await;
+    if let Some(s3) = settings.s3.as_mut() {
+        s3.secret_access_key.clear();
+    }
+    Ok(settings)
+}
+
+/// Configure a cloud provider and persist its API key in the OS keyring
+#[tauri::command]
+pub async fn configure_provider(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    api_key: String,
+) -> Result<SecretLocation, String> {
+    service
+        .write()
+        .await
+        .configure_provider(&provider, api_key.clone())
+        .map_err(|e| e.to_string())?;
+
+    // Credential store calls can block (Secret Service goes over D-Bus)
+    let data_dir = db.data_dir().to_path_buf();
+    let name = provider.clone();
+    let location =
+        tokio::task::spawn_blocking(move || secrets::store_api_key(&data_dir, &name, &api_key))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+    // This is the default key; any additional keys stay in rotation
+    let mut provider_keys = keys::provider_keys(db.pool(), &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+    provider_keys.ensure_default();
+    keys::save_provider_keys(db.pool(), &provider, provider_keys)
+        .await
+        .map_err(|e| e.to_string())?;
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "key_location": location });
+    audit::record(db.pool(), "provider.configure", Some(&provider), details).await;
+    Ok(location)
+}
+
+#[tauri::command]
+pub async fn get_openai_compatible_config(
+    db: State<'_, Database>,
+) -> Result<OpenAICompatibleConfig, String> {
+    SettingsOps::get_or_default(db.pool(), OPENAI_COMPATIBLE_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Point the generic provider at an OpenAI-compatible server (vLLM, LiteLLM, gateways)
+#[tauri::command]
+pub async fn configure_openai_compatible(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    config: OpenAICompatibleConfig,
+) -> Result<(), String> {
+    service
+        .write()
+        .await
+        .configure_openai_compatible(config.clone())
+        .map_err(|e| e.to_string())?;
+
+    // The key goes to the keyring; the rest is plain configuration
+    let api_key = config.api_key.clone().unwrap_or_default();
+    let stored = OpenAICompatibleConfig {
+        api_key: None,
+        ..config
+    };
+    let mut details = serde_json::to_value(&stored).unwrap_or_default();
+    details["has_api_key"] = serde_json::json!(!api_key.is_empty());
+    // Header values may carry gateway credentials
+    details["headers"] = serde_json::json!(stored.headers.keys().collect::<Vec<_>>());
+    SettingsOps::set(db.pool(), OPENAI_COMPATIBLE_SETTINGS_KEY, &stored)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let data_dir = db.data_dir().to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        if api_key.is_empty() {
+            secrets::delete_api_key(&data_dir, "openai_compatible")
+        } else {
+            secrets::store_api_key(&data_dir, "openai_compatible", &api_key).map(|_| ())
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    audit::record(db.pool(), "provider.configure", Some("openai_compatible"), details).await;
+    Ok(())
+}
+
+/// Add another API key to a cloud provider's rotation
+#[tauri::command]
+pub async fn add_provider_key(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    label: String,
+    api_key: String,
+) -> Result<ApiKeyInfo, String> {
+    if !secrets::API_KEY_PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("{} does not use API keys", provider));
+    }
+    let info = ApiKeyInfo {
+        id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+        label,
+        created_at: now(),
+    };
+
+    let data_dir = db.data_dir().to_path_buf();
+    let secret_name = keys::secret_name(&provider, &info.id);
+    tokio::task::spawn_blocking(move || secrets::store_api_key(&data_dir, &secret_name, &api_key))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut provider_keys = keys::provider_keys(db.pool(), &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+    provider_keys.keys.push(info.clone());
+    keys::save_provider_keys(db.pool(), &provider, provider_keys)
+        .await
+        .map_err(|e| e.to_string())?;
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "key_id": info.id, "label": info.label });
+    audit::record(db.pool(), "provider.add_key", Some(&provider), details).await;
+    Ok(info)
+}
+
+/// Forget one of a provider's saved API keys, the default key when `key_id` is omitted
+#[tauri::command]
+pub async fn remove_provider_key(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    key_id: Option<String>,
+) -> Result<(), String> {
+    let key_id = key_id.unwrap_or_else(|| DEFAULT_KEY_ID.to_string());
+    let data_dir = db.data_dir().to_path_buf();
+    let secret_name = keys::secret_name(&provider, &key_id);
+    tokio::task::spawn_blocking(move || secrets::delete_api_key(&data_dir, &secret_name))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    if secrets::API_KEY_PROVIDERS.contains(&provider.as_str()) {
+        let mut provider_keys = keys::provider_keys(db.pool(), &provider)
+            .await
+            .map_err(|e| e.to_string())?;
+        provider_keys.keys.retain(|k| k.id != key_id);
+        keys::save_provider_keys(db.pool(), &provider, provider_keys)
+            .await
+            .map_err(|e| e.to_string())?;
+        keys::reload(db.pool(), db.data_dir(), &service, &provider)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let details = serde_json::json!({ "key_id": key_id });
+    audit::record(db.pool(), "provider.remove_key", Some(&provider), details).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_provider_base_urls(db: State<'_, Database>) -> Result<BaseUrlSettings, String> {
+    endpoints::load(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Send a cloud provider's requests to another API root; `None` restores the public API
+#[tauri::command]
+pub async fn set_provider_base_url(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    base_url: Option<String>,
+) -> Result<(), String> {
+    if !secrets::API_KEY_PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("{} does not support a custom base URL", provider));
+    }
+    let base_url = endpoints::normalize(base_url.as_deref()).map_err(|e| e.to_string())?;
+    endpoints::save(db.pool(), &provider, base_url.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    service
+        .write()
+        .await
+        .set_base_url(&provider, base_url.clone());
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "base_url": base_url });
+    audit::record(db.pool(), "provider.set_base_url", Some(&provider), details).await;
+    Ok(())
+}
+
+/// Extra header names by provider; the values stay in the credential store
+#[tauri::command]
+pub async fn get_provider_headers(db: State<'_, Database>) -> Result<HeaderSettings, String> {
+    headers::load_names(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Send extra headers with every request of a cloud provider; an empty map removes them
+///
+/// A header with an empty value keeps its saved value. The OpenAI-compatible endpoint
+/// takes its headers through its config.
+#[tauri::command]
+pub async fn set_provider_headers(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    mut headers: BTreeMap<String, String>,
+) -> Result<(), String> {
+    if !secrets::API_KEY_PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("Set {} headers in its provider configuration", provider));
+    }
+    if headers.values().any(|value| value.trim().is_empty()) {
+        let saved = headers::load(db.pool(), db.data_dir())
+            .await
+            .map_err(|e| e.to_string())?;
+        let saved = saved.providers.get(&provider).cloned().unwrap_or_default();
+        for (name, value) in headers.iter_mut() {
+            if value.trim().is_empty() {
+                *value = saved.get(name).cloned().unwrap_or_default();
+            }
+        }
+    }
+    let headers = headers::normalize(&headers).map_err(|e| e.to_string())?;
+    headers::save(db.pool(), db.data_dir(), &provider, &headers)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let names: Vec<String> = headers.keys().cloned().collect();
+    service.write().await.set_headers(&provider, headers);
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Names only: values may carry gateway credentials
+    let details = serde_json::json!({ "headers": names });
+    audit::record(db.pool(), "provider.set_headers", Some(&provider), details).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_http_settings(db: State<'_, Database>) -> Result<HttpSettings, String> {
+    SettingsOps::get_or_default(db.pool(), HTTP_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save the shared HTTP client settings and rebuild the cloud providers with them
+///
+/// Local providers and the OpenAI-compatible endpoint pick them up when next configured.
+#[tauri::command]
+pub async fn update_http_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    settings: HttpSettings,
+) -> Result<HttpSettings, String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    SettingsOps::set(db.pool(), HTTP_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    http::configure(settings.clone());
+    for provider in secrets::API_KEY_PROVIDERS {
+        let provider_keys = keys::provider_keys(db.pool(), provider)
+            .await
+            .map_err(|e| e.to_string())?;
+        // Unconfigured providers have nothing to rebuild
+        if provider_keys.keys.is_empty() {
+            continue;
+        }
+        keys::reload(db.pool(), db.data_dir(), &service, provider)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.http", None, details).await;
+    Ok(settings)
+}
+
+/// Timeouts of every built-in provider, saved overrides included
+#[tauri::command]
+pub async fn get_provider_timeouts(
+    db: State<'_, Database>,
+) -> Result<BTreeMap<String, ProviderTimeouts>, String> {
+    let saved = timeouts::load(db.pool()).await.map_err(|e| e.to_string())?;
+    Ok(secrets::API_KEY_PROVIDERS
+        .iter()
+        .chain(["a1111", "comfyui", "invokeai"].iter())
+        .map(|provider| {
+            let timeouts = saved
+                .providers
+                .get(*provider)
+                .copied()
+                .unwrap_or_else(|| ProviderTimeouts::for_provider(provider));
+            (provider.to_string(), timeouts)
+        })
+        .collect())
+}
+
+/// Override a cloud provider's timeouts; `None` restores the defaults
+///
+/// Local providers take timeouts through `configure_local_provider` and the
+/// OpenAI-compatible endpoint through its config.
+#[tauri::command]
+pub async fn set_provider_timeouts(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    timeouts: Option<ProviderTimeouts>,
+) -> Result<(), String> {
+    if !secrets::API_KEY_PROVIDERS.contains(&provider.as_str()) {
+        return Err(format!("Set {} timeouts in its provider configuration", provider));
+    }
+    let timeouts = timeouts.map(ProviderTimeouts::normalized);
+    timeouts::save(db.pool(), &provider, timeouts)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    service.write().await.set_timeouts(&provider, timeouts);
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "timeouts": timeouts });
+    audit::record(db.pool(), "provider.set_timeouts", Some(&provider), details).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_provider_keys(
+    db: State<'_, Database>,
+    provider: String,
+) -> Result<ProviderKeys, String> {
+    keys::provider_keys(db.pool(), &provider)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Use a provider's keys in turn (`round_robin`) or switch only when one is limited
+#[tauri::command]
+pub async fn set_key_rotation(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    rotation: KeyRotation,
+) -> Result<ProviderKeys, String> {
+    let mut provider_keys = keys::provider_keys(db.pool(), &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+    provider_keys.rotation = rotation;
+    keys::save_provider_keys(db.pool(), &provider, provider_keys.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    keys::reload(db.pool(), db.data_dir(), &service, &provider)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let details = serde_json::json!({ "rotation": rotation });
+    audit::record(db.pool(), "provider.key_rotation", Some(&provider), details).await;
+    Ok(provider_keys)
+}
+
+/// Requests and spend per API key of a provider
+#[tauri::command]
+pub async fn get_key_usage(
+    db: State<'_, Database>,
+    provider: String,
+) -> Result<Vec<KeyUsage>, String> {
+    UsageOps::by_api_key(db.pool(), &provider)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_external_providers(
+    db: State<'_, Database>,
+) -> Result<Vec<ExternalProviderConfig>, String> {
+    SettingsOps::get_or_default(db.pool(), EXTERNAL_PROVIDERS_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Add (or replace) a provider implemented by an external executable
+#[tauri::command]
+pub async fn register_external_provider(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    config: ExternalProviderConfig,
+) -> Result<PluginDescription, String> {
+    let provider = ExternalProvider::connect(config.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    let description = provider.description().clone();
+
+    let mut configs: Vec<ExternalProviderConfig> =
+        SettingsOps::get_or_default(db.pool(), EXTERNAL_PROVIDERS_SETTINGS_KEY)
+            .await
+            .map_err(|e| e.to_string())?;
+    configs.retain(|c| c.name != config.name);
+    configs.push(config.clone());
+    SettingsOps::set(db.pool(), EXTERNAL_PROVIDERS_SETTINGS_KEY, &configs)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.write().await.register_provider(Box::new(provider));
+
+    let details = serde_json::json!({ "command": config.command, "args": config.args });
+    audit::record(db.pool(), "provider.register_external", Some(&config.name), details).await;
+    Ok(description)
+}
+
+#[tauri::command]
+pub async fn remove_external_provider(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    name: String,
+) -> Result<(), String> {
+    let mut configs: Vec<ExternalProviderConfig> =
+        SettingsOps::get_or_default(db.pool(), EXTERNAL_PROVIDERS_SETTINGS_KEY)
+            .await
+            .map_err(|e| e.to_string())?;
+    let count = configs.len();
+    configs.retain(|c| c.name != name);
+    if configs.len() == count {
+        return Err(format!("No external provider named {}", name));
+    }
+    SettingsOps::set(db.pool(), EXTERNAL_PROVIDERS_SETTINGS_KEY, &configs)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.write().await.remove_provider(&name);
+
+    audit::record(db.pool(), "provider.remove_external", Some(&name), serde_json::json!({})).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_providers(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+) -> Result<Vec<String>, String> {
+    let service = service.read().await;
+    Ok(service.list_providers())
+}
+
+/// Set or clear (`None`) a provider's requests-per-minute limit
+#[tauri::command]
+pub async fn set_provider_rate_limit(
+    db: State<'_, Database>,
+    processor: State<'_, JobProcessor>,
+    provider: String,
+    requests_per_minute: Option<u32>,
+) -> Result<(), String> {
+    let mut settings = processor.settings().await;
+    match requests_per_minute {
+        Some(rpm) if rpm > 0 => settings.provider_limits.insert(provider, rpm),
+        _ => settings.provider_limits.remove(&provider),
+    };
+    update_processor_settings(db, processor, settings).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_provider_rate_limits(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+) -> Result<std::collections::HashMap<String, u32>, String> {
+    let service = service.read().await;
+    Ok(service.rate_limits())
+}
+
+#[tauri::command]
+pub async fn get_processor_settings(
+    processor: State<'_, JobProcessor>,
+) -> Result<ProcessorSettings, String> {
+    Ok(processor.settings().await)
+}
+
+/// Save processor settings and apply them to the running processor
+#[tauri::command]
+pub async fn update_processor_settings(
+    db: State<'_, Database>,
+    processor: State<'_, JobProcessor>,
+    settings: ProcessorSettings,
+) -> Result<ProcessorSettings, String> {
+    let settings = settings.normalized();
+    SettingsOps::set(db.pool(), PROCESSOR_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    processor.apply_settings(settings).await;
+    Ok(processor.settings().await)
+}
+
+#[tauri::command]
+pub async fn get_fallback_settings(db: State<'_, Database>) -> Result<FallbackSettings, String> {
+    SettingsOps::get_or_default(db.pool(), FALLBACK_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save provider fallback chains and apply them to new generations
+#[tauri::command]
+pub async fn update_fallback_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    settings: FallbackSettings,
+) -> Result<FallbackSettings, String> {
+    SettingsOps::set(db.pool(), FALLBACK_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.read().await.set_fallbacks(settings.chains.clone());
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.fallback", None, details).await;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_clip_settings(db: State<'_, Database>) -> Result<ClipSettings, String> {
+    SettingsOps::get_or_default(db.pool(), CLIP_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save how prompts longer than a CLIP window are handled on local backends
+#[tauri::command]
+pub async fn update_clip_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    settings: ClipSettings,
+) -> Result<ClipSettings, String> {
+    SettingsOps::set(db.pool(), CLIP_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.read().await.set_clip_settings(settings.clone());
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.clip", None, details).await;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_mock_provider_settings(
+    db: State<'_, Database>,
+) -> Result<MockProviderSettings, String> {
+    SettingsOps::get_or_default(db.pool(), MOCK_PROVIDER_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Offer or withdraw the offline mock provider; debug builds always offer it
+#[tauri::command]
+pub async fn update_mock_provider_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    settings: MockProviderSettings,
+) -> Result<MockProviderSettings, String> {
+    SettingsOps::set(db.pool(), MOCK_PROVIDER_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    mock::apply(&mut *service.write().await, &settings);
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.mock_provider", None, details).await;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_routing_settings(db: State<'_, Database>) -> Result<RoutingSettings, String> {
+    SettingsOps::get_or_default(db.pool(), ROUTING_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save the providers `auto` requests try first
+#[tauri::command]
+pub async fn update_routing_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    settings: RoutingSettings,
+) -> Result<RoutingSettings, String> {
+    SettingsOps::set(db.pool(), ROUTING_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    service.read().await.set_routing_settings(settings.clone());
+
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.routing", None, details).await;
+    Ok(settings)
+}
+
+/// Provider and model an `auto` request for `hint` would be routed to right now
+#[tauri::command]
+pub async fn get_auto_route(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    hint: String,
+    workflow_id: Option<String>,
+) -> Result<FallbackTarget, String> {
+    let service = service.read().await;
+    routing::resolve(db.pool(), &service, workflow_id.as_deref(), routing::AUTO_PROVIDER, &hint)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Why local jobs are deferred by eco scheduling, or `None` while they may run
+#[tauri::command]
+pub async fn get_eco_status(processor: State<'_, JobProcessor>) -> Result<Option<String>, String> {
+    Ok(processor.eco_hold())
+}
+
+/// Whether the processor currently sees a network connection
+#[tauri::command]
+pub async fn get_network_status(processor: State<'_, JobProcessor>) -> Result<bool, String> {
+    Ok(processor.is_online())
+}
+
+#[tauri::command]
+pub async fn configure_local_provider(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    api_url: String,
+    timeouts: Option<ProviderTimeouts>,
+) -> Result<(), String> {
+    {
+        let mut service = service.write().await;
+        service.set_timeouts(&provider, timeouts);
+        service
+            .configure_local_provider(&provider, api_url.clone())
+            .map_err(|e| e.to_string())?;
+    }
+    let details = serde_json::json!({ "api_url": api_url, "timeouts": timeouts });
+    audit::record(db.pool(), "provider.configure", Some(&provider), details).await;
+
+    // The instance may not be running yet; capabilities can be refreshed later
+    if let Err(e) = detect_capabilities(&service, &provider).await {
+        log_warn!("Could not detect {} capabilities: {}", provider, e);
+    }
+    Ok(())
+}
+
+/// Re-detect optional features of a provider's backend within its health check timeout
+///
+/// Detection talks to the backend, so it runs without holding the service lock.
+async fn detect_capabilities(
+    service: &RwLock<GenerationService>,
+    provider: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let (handle, limit) = {
+        let service = service.read().await;
+        let handle = service
+            .provider_handle(provider)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider))?;
+        (handle, service.timeouts_for(provider).health_check())
+    };
+    tokio::time::timeout(limit, handle.detect_capabilities())
+        .await
+        .unwrap_or_else(|_| {
+            Err(anyhow::anyhow!(
+                "{} did not answer within {} seconds",
+                provider,
+                limit.as_secs()
+            ))
+        })
+}
+
+/// Optional features detected on a provider's backend (e.g. A1111 extensions)
+#[tauri::command]
+pub async fn get_provider_capabilities(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    refresh: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    if refresh.unwrap_or(false) {
+        detect_capabilities(&service, &provider).await
+    } else {
+        service.read().await.capabilities(&provider)
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Models a provider offers; cached for an hour unless `refresh` is set
+#[tauri::command]
+pub async fn list_models(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    refresh: Option<bool>,
+) -> Result<Vec<ModelInfo>, String> {
+    service
+        .read()
+        .await
+        .list_models(&provider, refresh.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Validate a provider's key or URL, reporting latency and the kind of failure
+#[tauri::command]
+pub async fn test_provider(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+) -> Result<ProviderTestResult, String> {
+    service
+        .read()
+        .await
+        .test_provider(&provider)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Checklist of the first-run wizard: data directory and output folder access, local
+/// backends and stored API keys; creates the output folder when missing
+#[tauri::command]
+pub async fn run_setup_checks(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+) -> Result<crate::setup::SetupChecklist, String> {
+    let service = service.read().await;
+    Ok(crate::setup::run(db.data_dir(), &service).await)
+}
+
+/// Latest connection test results, starting with the ones run at startup
+#[tauri::command]
+pub async fn get_provider_health(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+) -> Result<Vec<ProviderTestResult>, String> {
+    Ok(service.read().await.provider_health())
+}
+
+/// Configuration schema of a provider, for rendering its settings form
+#[tauri::command]
+pub async fn get_provider_schema(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+) -> Result<serde_json::Value, String> {
+    service
+        .read()
+        .await
+        .config_schema(&provider)
+        .map_err(|e| e.to_string())
+}
+
+/// Check whether this build can safely process the jobs in the database
+#[tauri::command]
+pub async fn get_compatibility_report(
+    db: State<'_, Database>,
+) -> Result<crate::db::compat::CompatibilityReport, String> {
+    crate::db::compat::check(db.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Notification Commands
+#[tauri::command]
+pub async fn get_audio_notification_settings(
+    db: State<'_, Database>,
+) -> Result<AudioNotificationSettings, String> {
+    SettingsOps::get_or_default(db.pool(), AUDIO_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Save which sound or announcement plays when jobs complete or fail
+#[tauri::command]
+pub async fn update_audio_notification_settings(
+    db: State<'_, Database>,
+    settings: AudioNotificationSettings,
+) -> Result<AudioNotificationSettings, String> {
+    SettingsOps::set(db.pool(), AUDIO_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+/// Play a cue as it would sound for a job with `status`, to try settings out
+#[tauri::command]
+pub async fn preview_audio_notification(cue: AudioCue, status: String) {
+    let notification = JobNotification {
+        job_id: "preview".to_string(),
+        workflow_id: String::new(),
+        status,
+        provider: None,
+        model: None,
+        duration_seconds: None,
+        error: None,
+        rule_id: None,
+    };
+    crate::notifications::audio::play(cue, &notification);
+}
+
+#[tauri::command]
+pub async fn create_notification_rule(
+    db: State<'_, Database>,
+    input: CreateNotificationRuleInput,
+) -> Result<NotificationRule, String> {
+    NotificationRuleOps::create(db.pool(), input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_notification_rules(
+    db: State<'_, Database>,
+) -> Result<Vec<NotificationRule>, String> {
+    NotificationRuleOps::list(db.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_notification_rule(
+    db: State<'_, Database>,
+    id: String,
+    input: UpdateNotificationRuleInput,
+) -> Result<NotificationRule, String> {
+    NotificationRuleOps::update(db.pool(), &id, input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_notification_rule(db: State<'_, Database>, id: String) -> Result<(), String> {
+    NotificationRuleOps::delete(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "notification_rule.delete", Some(&id), serde_json::json!({})).await;
+    Ok(())
+}
+
+/// Save default parameters for a provider, or one of its models
+///
+/// They are checked against the model's schema like request parameters.
+#[tauri::command]
+pub async fn create_parameter_preset(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    input: CreateParameterPresetInput,
+) -> Result<ParameterPreset, SubmitError> {
+    let model = input.model.as_deref().unwrap_or_default();
+    let errors = service
+        .read()
+        .await
+        .validate_parameters(&input.provider, model, &input.parameters);
+    if !errors.is_empty() {
+        return Err(SubmitError::invalid_params(errors));
+    }
+
+    let preset = ParameterPresetOps::create(db.pool(), input)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "provider": preset.provider, "model": preset.model });
+    audit::record(db.pool(), "parameter_preset.create", Some(&preset.id), details).await;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub async fn list_parameter_presets(
+    db: State<'_, Database>,
+) -> Result<Vec<ParameterPreset>, String> {
+    ParameterPresetOps::list(db.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_parameter_preset(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    id: String,
+    parameters: serde_json::Value,
+) -> Result<ParameterPreset, SubmitError> {
+    let existing = ParameterPresetOps::get(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Parameter preset not found".to_string())?;
+    let model = existing.model.as_deref().unwrap_or_default();
+    let errors = service.read().await.validate_parameters(&existing.provider, model, &parameters);
+    if !errors.is_empty() {
+        return Err(SubmitError::invalid_params(errors));
+    }
+
+    let preset = ParameterPresetOps::update(db.pool(), &id, &parameters)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "parameter_preset.update", Some(&id), serde_json::json!({})).await;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub async fn delete_parameter_preset(db: State<'_, Database>, id: String) -> Result<(), String> {
+    ParameterPresetOps::delete(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "parameter_preset.delete", Some(&id), serde_json::json!({})).await;
+    Ok(())
+}
+
+/// Smart Collection Commands
+#[tauri::command]
+pub async fn create_smart_collection(
+    db: State<'_, Database>,
+    input: SmartCollectionInput,
+) -> Result<SmartCollection, String> {
+    SmartCollectionOps::create(db.pool(), input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_smart_collections(
+    db: State<'_, Database>,
+) -> Result<Vec<SmartCollection>, String> {
+    SmartCollectionOps::list(db.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_smart_collection(
+    db: State<'_, Database>,
+    id: String,
+    input: SmartCollectionInput,
+) -> Result<SmartCollection, String> {
+    SmartCollectionOps::update(db.pool(), &id, input)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_smart_collection(db: State<'_, Database>, id: String) -> Result<(), String> {
+    SmartCollectionOps::delete(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?;
+    audit::record(db.pool(), "smart_collection.delete", Some(&id), serde_json::json!({})).await;
+    Ok(())
+}
+
+/// Completed generations currently matching a smart collection's filter, newest first
+#[tauri::command]
+pub async fn get_collection_items(db: State<'_, Database>, id: String) -> Result<Vec<Job>, String> {
+    SmartCollectionOps::items(db.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Maintenance Commands
+#[tauri::command]
+pub async fn get_retention_policy(db: State<'_, Database>) -> Result<RetentionPolicy, String> {
+    SettingsOps::get_or_default(db.pool(), RETENTION_POLICY_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_retention_policy(
+    db: State<'_, Database>,
+    policy: RetentionPolicy,
+) -> Result<RetentionPolicy, String> {
+    policy.validate().map_err(|e| e.to_string())?;
+    SettingsOps::set(db.pool(), RETENTION_POLICY_KEY, &policy)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::to_value(&policy).unwrap_or_default();
+    audit::record(db.pool(), "settings.retention", None, details).await;
+    Ok(policy)
+}
+
+/// Apply the saved retention policy immediately, even if automatic pruning is disabled
+#[tauri::command]
+pub async fn run_retention(db: State<'_, Database>) -> Result<RetentionReport, String> {
+    let policy: RetentionPolicy = SettingsOps::get_or_default(db.pool(), RETENTION_POLICY_KEY)
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::maintenance::retention::apply(db.pool(), &policy, db.data_dir())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_asset_expiry_settings(
+    db: State<'_, Database>,
+) -> Result<AssetExpirySettings, String> {
+    SettingsOps::get_or_default(db.pool(), ASSET_EXPIRY_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_asset_expiry_settings(
+    db: State<'_, Database>,
+    settings: AssetExpirySettings,
+) -> Result<AssetExpirySettings, String> {
+    settings.validate().map_err(|e| e.to_string())?;
+    SettingsOps::set(db.pool(), ASSET_EXPIRY_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::to_value(&settings).unwrap_or_default();
+    audit::record(db.pool(), "settings.asset_expiry", None, details).await;
+    Ok(settings)
+}
+
+/// Generations and files the saved expiry rules would delete now
+#[tauri::command]
+pub async fn get_asset_expiry_preview(db: State<'_, Database>) -> Result<ExpiryPreview, String> {
+    expiry::preview(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Queue a maintenance job deleting what `preview` lists, as far as it is still expired
+/// when the job runs; `None` when the preview is empty
+#[tauri::command]
+pub async fn start_asset_expiry(
+    db: State<'_, Database>,
+    preview: ExpiryPreview,
+) -> Result<Option<Job>, String> {
+    expiry::queue(db.pool(), &preview, None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Report schema state, pending migrations, integrity, row counts, orphaned rows and WAL
+/// size; with `fix`, apply migrations, remove orphans, reindex and checkpoint the WAL
+#[tauri::command]
+pub async fn db_doctor(
+    db: State<'_, Database>,
+    fix: Option<bool>,
+) -> Result<crate::db::doctor::DoctorReport, String> {
+    let fix = fix.unwrap_or(false);
+    let report = crate::db::doctor::run(&db, fix)
+        .await
+        .map_err(|e| e.to_string())?;
+    if fix {
+        let details = serde_json::json!({ "fixes": report.fixes });
+        audit::record(db.pool(), "database.fix", None, details).await;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_guest_mode(
+    db: State<'_, Database>,
+    guest: State<'_, GuestMode>,
+) -> Result<GuestModeStatus, String> {
+    guest.status(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Send log lines at or above `level` (default `info`) to the frontend as `log-entry`
+/// events; returns the recent lines so the console starts filled
+#[tauri::command]
+pub async fn subscribe_logs(
+    console: State<'_, LogConsole>,
+    level: Option<LogLevel>,
+) -> Result<Vec<LogEntry>, String> {
+    let level = level.unwrap_or(LogLevel::Info);
+    console.set_level(Some(level));
+    Ok(logs::history(level))
+}
+
+#[tauri::command]
+pub async fn unsubscribe_logs(console: State<'_, LogConsole>) -> Result<(), String> {
+    console.set_level(None);
+    Ok(())
+}
+
+/// Active permission profile and the tiers callable right now
+#[tauri::command]
+pub async fn get_permissions(
+    guest: State<'_, GuestMode>,
+    permissions: State<'_, Permissions>,
+) -> Result<PermissionStatus, String> {
+    Ok(permissions.status(guest.is_enabled()))
+}
+
+/// Enter read-only guest mode, optionally with a PIN, or leave it
+#[tauri::command]
+pub async fn set_guest_mode(
+    db: State<'_, Database>,
+    guest: State<'_, GuestMode>,
+    enabled: bool,
+    pin: Option<String>,
+) -> Result<GuestModeStatus, String> {
+    guest
+        .set(db.pool(), enabled, pin.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({ "enabled": enabled });
+    audit::record(db.pool(), "guest_mode.update", None, details).await;
+    guest.status(db.pool()).await.map_err(|e| e.to_string())
+}
+
+/// Vacuum, analyze and checkpoint the database now instead of waiting for an idle period
+#[tauri::command]
+pub async fn optimize_database(db: State<'_, Database>) -> Result<OptimizeReport, String> {
+    let report = crate::maintenance::optimize::optimize(db.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = serde_json::json!({
+        "size_before_bytes": report.size_before_bytes,
+        "size_after_bytes": report.size_after_bytes,
+    });
+    audit::record(db.pool(), "database.optimize", None, details).await;
+    Ok(report)
+}
+
+/// Check that asset files referenced by jobs and scenes still exist, optionally
+/// re-downloading missing ones that still have a remote URL
+#[tauri::command]
+pub async fn scan_asset_integrity(
+    db: State<'_, Database>,
+    redownload: Option<bool>,
+) -> Result<IntegrityReport, String> {
+    crate::maintenance::integrity::scan(db.pool(), redownload.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Queue a low-priority job that moves inline and remote scene thumbnails to local files
+///
+/// Returns `None` when no scene needs converting.
+#[tauri::command]
+pub async fn start_thumbnail_backfill(
+    db: State<'_, Database>,
+    batch_size: Option<u32>,
+) -> Result<Option<Job>, String> {
+    let scenes = thumbnails::legacy_scenes(db.pool(), 1)
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(scene) = scenes.first() else {
+        return Ok(None);
+    };
+
+    let data = BackfillData {
+        batch_size: batch_size.unwrap_or(20).max(1),
+        ..Default::default()
+    };
+
+    // Jobs belong to a workflow; attach it to the first scene's
+    let job = JobOps::create(
+        db.pool(),
+        CreateJobInput {
+            workflow_id: scene.workflow_id.clone(),
+            scene_id: None,
+            job_type: THUMBNAIL_BACKFILL_JOB_TYPE.to_string(),
+            data: serde_json::to_value(&data).map_err(|e| e.to_string())?,
+            scheduled_at: None,
+            lane: Some(BATCH_LANE.to_string()),
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(Some(job))
+}
+
+/// Local-only usage statistics (nothing leaves the machine)
+#[tauri::command]
+pub async fn get_personal_stats(
+    db: State<'_, Database>,
+) -> Result<crate::analytics::PersonalStats, String> {
+    crate::analytics::get_personal_stats(db.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Spend from recorded job costs, grouped by "day", "week" or "month"
+#[tauri::command]
+pub async fn get_spend_summary(
+    db: State<'_, Database>,
+    period: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<crate::analytics::SpendSummary, String> {
+    crate::analytics::get_spend_summary(
+        db.pool(),
+        period.as_deref().unwrap_or("month"),
+        since.as_deref(),
+        until.as_deref(),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Token, image and video usage per generation, kept after jobs are deleted
+#[tauri::command]
+pub async fn list_usage_records(
+    db: State<'_, Database>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<Vec<UsageRecord>, String> {
+    UsageOps::list(db.pool(), since.as_deref(), until.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Provider, reported model version and terms a job's result was produced under
+#[tauri::command]
+pub async fn get_asset_provenance(
+    db: State<'_, Database>,
+    job_id: String,
+) -> Result<Option<ProvenanceRecord>, String> {
+    ProvenanceOps::get_by_job(db.pool(), &job_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Provenance records, kept after jobs are deleted; `commercial_use` filters on the
+/// recorded licensing status
+#[tauri::command]
+pub async fn list_provenance(
+    db: State<'_, Database>,
+    since: Option<String>,
+    until: Option<String>,
+    provider: Option<String>,
+    commercial_use: Option<bool>,
+) -> Result<Vec<ProvenanceRecord>, String> {
+    ProvenanceOps::list(
+        db.pool(),
+        since.as_deref(),
+        until.as_deref(),
+        provider.as_deref(),
+        commercial_use,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Check GitHub for a newer release
+#[tauri::command]
+pub async fn check_for_updates() -> Result<crate::updates::UpdateInfo, String> {
+    crate::updates::check_for_updates()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Export Commands
+#[tauri::command]
+pub async fn export_markdown(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<MarkdownExportSummary, String> {
+    let summary = crate::export::markdown::export_markdown(db.pool(), std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())?;
+    record_export(&db, "export.markdown", &path, &summary).await;
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn export_schedule_ics(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<ScheduleExportSummary, String> {
+    let summary = crate::export::ics::export_schedule_ics(db.pool(), std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())?;
+    record_export(&db, "export.ics", &path, &summary).await;
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn export_usage_csv(
+    db: State<'_, Database>,
+    path: String,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<UsageExportSummary, String> {
+    let summary = crate::export::usage::export_usage_csv(
+        db.pool(),
+        std::path::Path::new(&path),
+        since.as_deref(),
+        until.as_deref(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    record_export(&db, "export.usage_csv", &path, &summary).await;
+    Ok(summary)
+}
+
+/// Write settings, parameter presets and smart collections to one file; API keys and
+/// credential-bearing settings are included, encrypted, only when a passphrase is given
+#[tauri::command]
+pub async fn export_settings(
+    db: State<'_, Database>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<SettingsExportSummary, String> {
+    let summary = crate::export::settings::export_settings(
+        db.pool(),
+        db.data_dir(),
+        std::path::Path::new(&path),
+        passphrase.as_deref(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    record_export(&db, "export.settings", &path, &summary).await;
+    Ok(summary)
+}
+
+/// Apply a file written by `export_settings`; imported API keys are used right away,
+/// other settings after a restart
+#[tauri::command]
+pub async fn import_settings(
+    db: State<'_, Database>,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<SettingsImportSummary, String> {
+    let summary = crate::export::settings::import_settings(
+        db.pool(),
+        db.data_dir(),
+        std::path::Path::new(&path),
+        passphrase.as_deref(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if summary.api_keys > 0 {
+        for provider in secrets::API_KEY_PROVIDERS {
+            if let Err(e) = keys::reload(db.pool(), db.data_dir(), &service, provider).await {
+                log_warn!("[Settings] Failed to reload {} keys: {}", provider, e);
+            }
+        }
+    }
+
+    let details = serde_json::to_value(&summary).unwrap_or_default();
+    audit::record(db.pool(), "settings.import", Some(&path), details).await;
+    Ok(summary)
+}
+
+/// Write a cropped, metadata-free copy of an asset for a social platform into `path`
+#[tauri::command]
+pub async fn export_social(
+    db: State<'_, Database>,
+    asset_id: String,
+    platform: String,
+    path: String,
+) -> Result<SocialExportSummary, String> {
+    let summary = crate::export::social::export_social(
+        db.pool(),
+        db.data_dir(),
+        &asset_id,
+        &platform,
+        std::path::Path::new(&path),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    record_export(&db, "export.social", &path, &summary).await;
+    Ok(summary)
+}
+
+/// Composite the images of jobs or scenes into one captioned PNG grid at `path`
+#[tauri::command]
+pub async fn compose_grid(
+    db: State<'_, Database>,
+    asset_ids: Vec<String>,
+    columns: u32,
+    path: String,
+) -> Result<GridSummary, String> {
+    let output = std::path::Path::new(&path);
+    let summary = crate::export::grid::compose_grid(db.pool(), &asset_ids, columns, output)
+        .await
+        .map_err(|e| e.to_string())?;
+    record_export(&db, "export.grid", &path, &summary).await;
+    Ok(summary)
+}
+
+/// Bundle the files of jobs or scenes with a provenance manifest into a zip at `path`
+#[tauri::command]
+pub async fn export_assets_zip(
+    db: State<'_, Database>,
+    asset_ids: Vec<String>,
+    path: String,
+) -> Result<ZipExportSummary, String> {
+    let output = std::path::Path::new(&path);
+    let summary =
+        crate::export::archive::export_assets_zip(db.pool(), db.data_dir(), &asset_ids, output)
+            .await
+            .map_err(|e| e.to_string())?;
+    record_export(&db, "export.zip", &path, &summary).await;
+    Ok(summary)
+}
+
+/// Add a finished export to the audit log
+async fn record_export(db: &Database, action: &str, path: &str, summary: &impl serde::Serialize) {
+    let details = serde_json::to_value(summary).unwrap_or_default();
+    audit::record(db.pool(), action, Some(path), details).await;
+}
+
+/// Audit log entries, newest first; `action` filters by prefix (e.g. `provider.`)
+#[tauri::command]
+pub async fn get_audit_log(
+    db: State<'_, Database>,
+    action: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<AuditEntry>, String> {
+    AuditOps::list(
+        db.pool(),
+        action.as_deref(),
+        limit.unwrap_or(200),
+        offset.unwrap_or(0),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_c2pa_settings(db: State<'_, Database>) -> Result<C2paSettings, String> {
+    let mut settings: C2paSettings = SettingsOps::get_or_default(db.pool(), C2PA_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())?;
+    // The signing key stays in the credential store
+    settings.private_key = None;
+    Ok(settings)
+}
+
+/// Save Content Credentials settings, checking that c2patool runs when enabling
+///
+/// An empty private key keeps the saved one.
+#[tauri::command]
+pub async fn update_c2pa_settings(
+    db: State<'_, Database>,
+    mut settings: C2paSettings,
+) -> Result<C2paSettings, String> {
+    if settings.private_key.as_deref().unwrap_or_default().is_empty() {
+        let saved = c2pa::load_settings(db.pool(), db.data_dir())
+            .await
+            .map_err(|e| e.to_string())?;
+        settings.private_key = saved.private_key;
+    }
+    if settings.enabled {
+        let version = c2pa::tool_version(&settings)
+            .await
+            .map_err(|e| e.to_string())?;
+        log_info!("[Export] Signing exports with {}", version);
+    }
+
+    c2pa::save_settings(db.pool(), db.data_dir(), &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    settings.private_key = None;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_watermark_settings(db: State<'_, Database>) -> Result<WatermarkSettings, String> {
+    SettingsOps::get_or_default(db.pool(), WATERMARK_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_watermark_settings(
+    db: State<'_, Database>,
+    settings: WatermarkSettings,
+) -> Result<WatermarkSettings, String> {
+    if settings.enabled && settings.tag.trim().is_empty() {
+        return Err("A watermark tag is required".to_string());
+    }
+    SettingsOps::set(db.pool(), WATERMARK_SETTINGS_KEY, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+/// Check an image file for the export watermark, comparing it with the configured tag
+#[tauri::command]
+pub async fn detect_watermark(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<WatermarkReport, String> {
+    let settings: WatermarkSettings = SettingsOps::get_or_default(db.pool(), WATERMARK_SETTINGS_KEY)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || -> anyhow::Result<WatermarkReport> {
+        let image = image::open(&path)?.to_rgb8();
+        let tag = Some(settings.tag.as_str()).filter(|tag| !tag.is_empty());
+        crate::export::watermark::detect(&image, tag)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Import Commands
+#[tauri::command]
+pub async fn list_invokeai_boards(
+    api_url: String,
+) -> Result<Vec<crate::import::invokeai::InvokeAIBoard>, String> {
+    crate::import::invokeai::list_boards(&api_url)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_invokeai_board(
+    db: State<'_, Database>,
+    api_url: String,
+    board_id: String,
+    workflow_id: Option<String>,
+) -> Result<crate::import::invokeai::InvokeAIImportSummary, String> {
+    crate::import::invokeai::import_board(db.pool(), &api_url, &board_id, workflow_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn check_port(address: String) -> bool {
+    let timeout = Duration::from_secs(1);
+
+    if let Ok(iter) = address.to_socket_addrs() {
+        for addr in iter {
+            if TcpStream::connect_timeout(&addr, timeout).is_ok() {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Call AI for text generation (used by enhance feature)
+#[tauri::command]
+pub async fn call_ai(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    model: String,
+    prompt: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+) -> Result<String, GenerationError> {
+    use crate::generation::GenerationRequest;
+
+    let service = service.read().await;
+
+    let params = serde_json::json!({
+        "max_tokens": max_tokens.unwrap_or(4096),
+        "temperature": temperature.unwrap_or(1.0),
+    });
+
+    let request = GenerationRequest {
+        prompt,
+        model,
+        parameters: params,
+    };
+
+    let result = service
+        .generate(&provider, request)
+        .await
+        .map_err(|e| GenerationError::classify(&e))?;
+
+    // For text generation, the result is in output_data
+    result.output_data().map(str::to_string).ok_or_else(|| GenerationError::Other {
+        message: "No text output received".to_string(),
+    })
+}
+
+/// Strings of a locale for the UI, the active locale by default
+#[tauri::command]
+pub fn get_translations(locale: Option<String>) -> Translations {
+    i18n::translations(&locale.unwrap_or_else(i18n::locale))
+}
+
+#[tauri::command]
+pub fn get_locale() -> LocaleInfo {
+    LocaleInfo {
+        locale: i18n::locale(),
+        detected: i18n::detect_locale(),
+        supported: i18n::supported_locales(),
+    }
+}
+
+/// Choose the language of backend messages; `None` follows the system locale
+#[tauri::command]
+pub async fn set_locale(
+    db: State<'_, Database>,
+    locale: Option<String>,
+) -> Result<LocaleInfo, String> {
+    SettingsOps::set(db.pool(), LOCALE_SETTINGS_KEY, &locale)
+        .await
+        .map_err(|e| e.to_string())?;
+    i18n::set_locale(locale.as_deref());
+    Ok(get_locale())
+}
+
+/// Generate while emitting partial outputs as `generation-stream` events
+///
+/// Text arrives token by token and local image backends send previews as they sample.
+/// The final result, with outputs saved like any generation, is both emitted and returned.
+#[tauri::command]
+pub async fn generate_stream(
+    app_handle: tauri::AppHandle,
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    provider: String,
+    model: String,
+    prompt: String,
+    parameters: serde_json::Value,
+    stream_id: String,
+) -> Result<crate::generation::GenerationResult, GenerationError> {
+    use crate::generation::{GenerationRequest, StreamChunk, StreamEvent, STREAM_EVENT};
+    use futures_util::StreamExt;
+    use tauri::Emitter;
+
+    let service = service.read().await;
+    let request = GenerationRequest {
+        prompt,
+        model,
+        parameters,
+    };
+    let classify = |e: anyhow::Error| GenerationError::classify(&e);
+    let mut stream = service.generate_stream(&provider, request).await.map_err(classify)?;
+
+    let emit = |chunk: &StreamChunk| {
+        let event = StreamEvent {
+            stream_id: &stream_id,
+            chunk,
+        };
+        if let Err(e) = app_handle.emit(STREAM_EVENT, event) {
+            log_warn!("Failed to emit chunk of stream {}: {}", stream_id, e);
+        }
+    };
+    while let Some(chunk) = stream.next().await {
+        match chunk.map_err(classify)? {
+            StreamChunk::Done { mut result } => {
+                // Saved outputs are file URLs by now, so the copy is small
+                service.save_outputs(&mut result).await;
+                emit(&StreamChunk::Done {
+                    result: result.clone(),
+                });
+                return Ok(result);
+            }
+            chunk => emit(&chunk),
+        }
+    }
+    Err(GenerationError::Other {
+        message: format!("{} stream ended without a result", provider),
+    })
+}
+
+/// Check a prompt for common problems with the target provider and model
+///
+/// With a `reviewer`, an LLM also comments on the prompt; if it fails the rule
+/// findings are still returned.
+#[tauri::command]
+pub async fn lint_prompt(
+    service: State<'_, Arc<RwLock<GenerationService>>>,
+    prompt: String,
+    provider: String,
+    target_model: String,
+    reviewer: Option<LintReviewer>,
+) -> Result<PromptLintReport, String> {
+    let mut report = lint::lint(&prompt, &provider, &target_model);
+
+    if let Some(reviewer) = reviewer {
+        let service = service.read().await;
+        match lint::review(&service, &reviewer, &prompt, &target_model).await {
+            Ok(review) => report.review = Some(review),
+            Err(e) => log_warn!("[Lint] Review by {} failed: {}", reviewer.provider, e),
+        }
+    }
+
+    Ok(report)
 }
 
 /// Open a file or URL in the system's default application
@@ -0,0 +1,135 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use super::models::{now, Job, APP_VERSION};
+use super::schema::SCHEMA_VERSION;
+
+/// Result of checking whether this build can safely work with the database
+#[derive(Debug, Clone, Serialize)]
+pub struct CompatibilityReport {
+    pub compatible: bool,
+    pub app_version: String,
+    pub supported_schema_version: i64,
+    pub schema_version: Option<i64>,
+    pub last_app_version: Option<String>,
+    pub issues: Vec<String>,
+}
+
+/// Read the schema version recorded in `app_meta`, if any
+pub async fn stored_schema_version(pool: &SqlitePool) -> Result<Option<i64>> {
+    let value = get_meta(pool, "schema_version").await?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+/// Record the schema and app version after a successful migration
+pub async fn record_versions(pool: &SqlitePool) -> Result<()> {
+    set_meta(pool, "schema_version", &SCHEMA_VERSION.to_string()).await?;
+    set_meta(pool, "last_app_version", APP_VERSION).await?;
+    set_meta(pool, "last_opened_at", &now()).await?;
+    Ok(())
+}
+
+/// Check the database for a newer schema or queued jobs this build cannot process
+pub async fn check(pool: &SqlitePool) -> Result<CompatibilityReport> {
+    let schema_version = stored_schema_version(pool).await?;
+    let last_app_version = get_meta(pool, "last_app_version").await?;
+    let mut issues = Vec::new();
+
+    if let Some(version) = schema_version.filter(|v| *v > SCHEMA_VERSION) {
+        issues.push(format!(
+            "The database uses schema version {} but this build (v{}) only supports up to version {}. \
+             It was probably opened by a newer PromptCraft release; update the app to continue processing jobs.",
+            version, APP_VERSION, SCHEMA_VERSION
+        ));
+    }
+
+    // Jobs waiting to run must have been queued by this or an older release
+    let queued_jobs: Vec<Job> = sqlx::query_as(
+        "SELECT * FROM jobs WHERE status IN \
+         ('pending', 'running', 'waiting_network', 'waiting_eco', 'waiting_budget')",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for job in &queued_jobs {
+        if let Some(job_version) = &job.app_version {
+            if is_newer_version(job_version, APP_VERSION) {
+                issues.push(format!(
+                    "Job {} was queued by PromptCraft v{}, which is newer than this build (v{}).",
+                    job.id, job_version, APP_VERSION
+                ));
+                continue;
+            }
+        }
+
+        if serde_json::from_str::<serde_json::Value>(&job.data)
+            .map(|data| !data.is_object())
+            .unwrap_or(true)
+        {
+            issues.push(format!(
+                "Job {} has a payload this build cannot read.",
+                job.id
+            ));
+        }
+    }
+
+    Ok(CompatibilityReport {
+        compatible: issues.is_empty(),
+        app_version: APP_VERSION.to_string(),
+        supported_schema_version: SCHEMA_VERSION,
+        schema_version,
+        last_app_version,
+        issues,
+    })
+}
+
+pub(crate) async fn get_meta(pool: &SqlitePool, key: &str) -> Result<Option<String>> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_meta WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(value)
+}
+
+pub(crate) async fn set_meta(pool: &SqlitePool, key: &str, value: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO app_meta (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Compare dotted numeric versions ("1.2.3"), ignoring pre-release suffixes
+pub fn is_newer_version(candidate: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .take(3)
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+
+    parse(candidate) > parse(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("0.2.0", "0.1.9"));
+        assert!(is_newer_version("1.0.0", "0.9.12"));
+        assert!(is_newer_version("v0.1.1", "0.1.0"));
+        assert!(!is_newer_version("0.1.0", "0.1.0"));
+        assert!(!is_newer_version("0.1.0-beta", "0.1.0"));
+        assert!(!is_newer_version("0.0.9", "0.1.0"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Database diagnostics for support requests about odd database states.
+//!
+//! Pending migrations are found by building the current schema in a scratch
+//! in-memory database and comparing it with the real one, so the preview never
+//! touches user data.
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::schema::{self, SCHEMA_VERSION};
+use super::{compat, Database};
+
+/// Rows whose foreign key points at a missing parent
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanedRows {
+    pub table: String,
+    pub parent: String,
+    pub count: usize,
+}
+
+/// Outcome of `db_doctor`
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub schema_version: Option<i64>,
+    pub supported_schema_version: i64,
+    /// Tables, columns, indexes and triggers the migrations would still create
+    pub pending_migrations: Vec<String>,
+    /// Problems reported by `PRAGMA quick_check`, including damaged indexes
+    pub integrity_issues: Vec<String>,
+    pub row_counts: BTreeMap<String, i64>,
+    pub orphaned_rows: Vec<OrphanedRows>,
+    pub wal_size_bytes: u64,
+    /// Repairs made by the fix pass; empty for a dry run
+    pub fixes: Vec<String>,
+}
+
+/// Diagnose the database, repairing what can be repaired safely when `fix` is set
+pub async fn run(db: &Database, fix: bool) -> Result<DoctorReport> {
+    let pool = db.pool();
+    if !fix {
+        return diagnose(pool).await;
+    }
+
+    let before = diagnose(pool).await?;
+    if before.schema_version.is_some_and(|v| v > SCHEMA_VERSION) {
+        return Err(anyhow::anyhow!(
+            "The database was written by a newer release (schema version {:?}); not repairing it",
+            before.schema_version
+        ));
+    }
+
+    let mut fixes = Vec::new();
+    if !before.pending_migrations.is_empty() || before.schema_version != Some(SCHEMA_VERSION) {
+        Database::run_migrations(pool).await?;
+        compat::record_versions(pool).await?;
+        fixes.push(format!("Applied {} pending migrations", before.pending_migrations.len()));
+    }
+
+    let orphans: Vec<(String, i64, String, i64)> = sqlx::query_as("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await?;
+    if !orphans.is_empty() {
+        let mut tx = pool.begin().await?;
+        for (table, rowid, _, _) in &orphans {
+            sqlx::query(&format!("DELETE FROM \"{}\" WHERE rowid = ?", table))
+                .bind(rowid)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        fixes.push(format!("Deleted {} orphaned rows", orphans.len()));
+    }
+
+    if !before.integrity_issues.is_empty() {
+        sqlx::query("REINDEX").execute(pool).await?;
+        fixes.push("Rebuilt all indexes".to_string());
+    }
+
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    fixes.push(format!("Checkpointed the write-ahead log ({} bytes)", before.wal_size_bytes));
+
+    let mut report = diagnose(pool).await?;
+    report.fixes = fixes;
+    Ok(report)
+}
+
+async fn diagnose(pool: &SqlitePool) -> Result<DoctorReport> {
+    let integrity_issues: Vec<String> = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter(|line: &String| line != "ok")
+        .collect();
+
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut row_counts = BTreeMap::new();
+    for table in &tables {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table))
+            .fetch_one(pool)
+            .await?;
+        row_counts.insert(table.clone(), count);
+    }
+
+    let mut orphans: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let rows: Vec<(String, i64, String, i64)> = sqlx::query_as("PRAGMA foreign_key_check")
+        .fetch_all(pool)
+        .await?;
+    for (table, _, parent, _) in rows {
+        *orphans.entry((table, parent)).or_default() += 1;
+    }
+    let orphaned_rows = orphans
+        .into_iter()
+        .map(|((table, parent), count)| OrphanedRows {
+            table,
+            parent,
+            count,
+        })
+        .collect();
+
+    let db_file: Option<String> =
+        sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+            .fetch_optional(pool)
+            .await?;
+    let wal_size_bytes = match db_file.filter(|file| !file.is_empty()) {
+        Some(file) => tokio::fs::metadata(format!("{}-wal", file))
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    Ok(DoctorReport {
+        schema_version: compat::stored_schema_version(pool).await?,
+        supported_schema_version: SCHEMA_VERSION,
+        pending_migrations: pending_migrations(pool).await?,
+        integrity_issues,
+        row_counts,
+        orphaned_rows,
+        wal_size_bytes,
+        fixes: Vec::new(),
+    })
+}
+
+/// Schema objects present after a fresh migration but missing from `pool`
+async fn pending_migrations(pool: &SqlitePool) -> Result<Vec<String>> {
+    // One connection, since every in-memory connection is its own database
+    let reference = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::query(schema::CREATE_APP_META_TABLE)
+        .execute(&reference)
+        .await?;
+    Database::run_migrations(&reference).await?;
+
+    let expected = schema_objects(&reference).await?;
+    reference.close().await;
+    let actual = schema_objects(pool).await?;
+    Ok(expected.difference(&actual).cloned().collect())
+}
+
+/// Tables, indexes, triggers and `table.column` names, prefixed with their kind
+async fn schema_objects(pool: &SqlitePool) -> Result<BTreeSet<String>> {
+    let objects: Vec<(String, String)> = sqlx::query_as(
+        "SELECT type, name FROM sqlite_master WHERE name NOT LIKE 'sqlite_%' AND type != 'view'",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut names = BTreeSet::new();
+    for (kind, name) in objects {
+        if kind == "table" {
+            let columns: Vec<String> =
+                sqlx::query_scalar(&format!("SELECT name FROM pragma_table_info('{}')", name))
+                    .fetch_all(pool)
+                    .await?;
+            for column in columns {
+                names.insert(format!("column {}.{}", name, column));
+            }
+        }
+        names.insert(format!("{} {}", kind, name));
+    }
+    Ok(names)
+}
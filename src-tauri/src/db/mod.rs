@@ -2,6 +2,8 @@ use anyhow::Result;
 use sqlx::sqlite::SqlitePool;
 use std::path::PathBuf;
 
+pub mod compat;
+pub mod doctor;
 pub mod models;
 pub mod operations;
 pub mod schema;
@@ -9,6 +11,7 @@ pub mod schema;
 #[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
+    data_dir: PathBuf,
 }
 
 impl Database {
@@ -52,38 +55,134 @@ impl Database {
         println!("[Database] WAL mode enabled");
         let _ = std::io::stdout().flush();
 
-        // Run migrations
-        Self::run_migrations(&pool).await?;
+        // Run migrations unless the database was written by a newer, incompatible build
+        sqlx::query(schema::CREATE_APP_META_TABLE)
+            .execute(&pool)
+            .await?;
+        let stored_version = compat::stored_schema_version(&pool).await?;
+        if stored_version.is_some_and(|v| v > schema::SCHEMA_VERSION) {
+            log_warn!(
+                "[Database] Schema version {:?} is newer than supported version {}, skipping migrations",
+                stored_version,
+                schema::SCHEMA_VERSION
+            );
+        } else {
+            Self::run_migrations(&pool).await?;
+            compat::record_versions(&pool).await?;
+        }
 
         println!("[Database] Database initialization complete at: {:?}", db_path);
         let _ = std::io::stdout().flush();
 
-        Ok(Self { pool })
+        let data_dir = db_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        Ok(Self { pool, data_dir })
     }
 
     /// Run database migrations
     async fn run_migrations(pool: &SqlitePool) -> Result<()> {
-        eprintln!("[Database] Running migrations...");
+        log_debug!("[Database] Running migrations...");
 
-        eprintln!("[Database] Creating workflows table...");
+        log_debug!("[Database] Creating workflows table...");
         sqlx::query(schema::CREATE_WORKFLOWS_TABLE)
             .execute(pool)
             .await?;
 
-        eprintln!("[Database] Creating workflow_versions table...");
+        log_debug!("[Database] Creating workflow_versions table...");
         sqlx::query(schema::CREATE_WORKFLOW_VERSIONS_TABLE)
             .execute(pool)
             .await?;
 
-        eprintln!("[Database] Creating scenes table...");
+        log_debug!("[Database] Creating scenes table...");
         sqlx::query(schema::CREATE_SCENES_TABLE)
             .execute(pool)
             .await?;
 
-        eprintln!("[Database] Creating jobs table...");
+        log_debug!("[Database] Creating jobs table...");
         sqlx::query(schema::CREATE_JOBS_TABLE).execute(pool).await?;
 
-        eprintln!("[Database] All migrations completed successfully!");
+        log_debug!("[Database] Creating settings table...");
+        sqlx::query(schema::CREATE_SETTINGS_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating notification_rules table...");
+        sqlx::query(schema::CREATE_NOTIFICATION_RULES_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating usage_records table...");
+        sqlx::query(schema::CREATE_USAGE_RECORDS_TABLE)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_USAGE_RECORDS_INDEX)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating job_logs table...");
+        sqlx::query(schema::CREATE_JOB_LOGS_TABLE)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_JOB_LOGS_INDEX)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating result_cache table...");
+        sqlx::query(schema::CREATE_RESULT_CACHE_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating parameter_presets table...");
+        sqlx::query(schema::CREATE_PARAMETER_PRESETS_TABLE)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_PARAMETER_PRESETS_INDEX)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating smart_collections table...");
+        sqlx::query(schema::CREATE_SMART_COLLECTIONS_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating refine_sessions table...");
+        sqlx::query(schema::CREATE_REFINE_SESSIONS_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating provenance table...");
+        sqlx::query(schema::CREATE_PROVENANCE_TABLE)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Creating audit_log table...");
+        sqlx::query(schema::CREATE_AUDIT_LOG_TABLE)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_AUDIT_LOG_INDEX)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_AUDIT_LOG_NO_UPDATE_TRIGGER)
+            .execute(pool)
+            .await?;
+        sqlx::query(schema::CREATE_AUDIT_LOG_NO_DELETE_TRIGGER)
+            .execute(pool)
+            .await?;
+
+        log_debug!("[Database] Adding columns introduced after initial release...");
+        Self::ensure_column(pool, "jobs", "scheduled_at", "TEXT").await?;
+        Self::ensure_column(pool, "jobs", "app_version", "TEXT").await?;
+        Self::ensure_column(pool, "jobs", "retry_of", "TEXT").await?;
+        Self::ensure_column(pool, "jobs", "cost", "REAL").await?;
+        Self::ensure_column(pool, "jobs", "lane", "TEXT NOT NULL DEFAULT 'interactive'").await?;
+        Self::ensure_column(pool, "jobs", "error_kind", "TEXT").await?;
+        Self::ensure_column(pool, "workflows", "allowed_providers", "TEXT").await?;
+        Self::ensure_column(pool, "usage_records", "api_key_id", "TEXT").await?;
+
+        log_debug!("[Database] All migrations completed successfully!");
 
         // Verify tables were created
         let tables: Vec<(String,)> = sqlx::query_as(
@@ -92,7 +191,33 @@ impl Database {
         .fetch_all(pool)
         .await?;
 
-        eprintln!("[Database] Tables in database: {:?}", tables.iter().map(|(name,)| name).collect::<Vec<_>>());
+        let names: Vec<_> = tables.iter().map(|(name,)| name).collect();
+        log_debug!("[Database] Tables in database: {:?}", names);
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table if it is not present yet
+    async fn ensure_column(
+        pool: &SqlitePool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let columns: Vec<(String,)> =
+            sqlx::query_as(&format!("SELECT name FROM pragma_table_info('{}')", table))
+                .fetch_all(pool)
+                .await?;
+
+        if !columns.iter().any(|(name,)| name == column) {
+            log_debug!("[Database] Adding column {}.{}", table, column);
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))
+            .execute(pool)
+            .await?;
+        }
 
         Ok(())
     }
@@ -100,4 +225,9 @@ impl Database {
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// Directory holding the database file and other app data
+    pub fn data_dir(&self) -> &std::path::Path {
+        &self.data_dir
+    }
 }
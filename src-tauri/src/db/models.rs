@@ -70,6 +70,17 @@ pub struct Job {
     pub created_at: String,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
+    pub scheduled_at: Option<String>,
+    /// Version of the app that created the job
+    pub app_version: Option<String>,
+    /// Id of the failed job this job retries
+    pub retry_of: Option<String>,
+    /// Actual cost in USD, recorded when the job completes
+    pub cost: Option<f64>,
+    /// Queue lane: "interactive" or "batch"
+    pub lane: String,
+    /// Kind of the failure, as in `GenerationError`, e.g. "auth_failed"
+    pub error_kind: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +91,10 @@ pub struct CreateJobInput {
     #[serde(rename = "type")]
     pub job_type: String,
     pub data: serde_json::Value,
+    /// Earliest time (RFC 3339) the processor may start the job
+    pub scheduled_at: Option<String>,
+    /// Queue lane, defaults to "interactive"
+    pub lane: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,11 +104,245 @@ pub struct UpdateJobInput {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationRule {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// "app" (Tauri event) or "discord" (webhook in `target`)
+    pub channel: String,
+    pub target: Option<String>,
+    /// "any", "completed" or "failed"
+    pub on_status: String,
+    pub workflow_id: Option<String>,
+    pub provider: Option<String>,
+    pub min_duration_seconds: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateNotificationRuleInput {
+    pub name: String,
+    pub channel: String,
+    pub target: Option<String>,
+    pub on_status: Option<String>,
+    pub workflow_id: Option<String>,
+    pub provider: Option<String>,
+    pub min_duration_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateNotificationRuleInput {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub target: Option<String>,
+    pub on_status: Option<String>,
+    pub min_duration_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UsageRecord {
+    pub id: String,
+    /// Job that produced the usage; the job may since have been deleted
+    pub job_id: String,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub images: i64,
+    pub video_seconds: f64,
+    pub cost: Option<f64>,
+    pub created_at: String,
+    /// Key the request was made with, for providers with several keys
+    pub api_key_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateUsageRecordInput {
+    pub job_id: String,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub images: i64,
+    pub video_seconds: f64,
+    pub cost: Option<f64>,
+    pub api_key_id: Option<String>,
+}
+
+/// Usage of one API key of a provider
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct KeyUsage {
+    /// `None` for requests made before the provider had several keys
+    pub api_key_id: Option<String>,
+    pub requests: i64,
+    pub cost: f64,
+    pub last_used_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct JobLog {
+    pub id: String,
+    pub job_id: String,
+    /// `request`, `response` or `error`
+    pub kind: String,
+    pub provider: Option<String>,
+    /// Sanitized JSON payload
+    pub payload: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CachedResult {
+    /// SHA-256 of provider, model, prompt and parameters
+    pub cache_key: String,
+    pub provider: String,
+    pub model: String,
+    /// Job whose generation was cached
+    pub job_id: String,
+    /// Serialized `GenerationResult`
+    pub result: String,
+    pub hits: i64,
+    pub created_at: String,
+    pub last_hit_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ParameterPreset {
+    pub id: String,
+    pub provider: String,
+    /// Model the preset applies to; `None` applies to every model of the provider
+    pub model: Option<String>,
+    /// JSON object merged under the parameters of each request
+    pub parameters: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateParameterPresetInput {
+    pub provider: String,
+    pub model: Option<String>,
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    /// JSON-encoded [`CollectionFilter`]
+    pub filter: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Which completed generations a smart collection shows; unset fields match everything
+///
+/// Tags and ratings are read from the `tags` array and `rating` number of the job data.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CollectionFilter {
+    /// Tags an item must all carry, compared case-insensitively
+    pub tags: Vec<String>,
+    pub min_rating: Option<f64>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub workflow_id: Option<String>,
+    /// Only items created in the last this many days
+    pub within_days: Option<u32>,
+    /// Text the prompt must contain, case-insensitively
+    pub prompt_contains: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartCollectionInput {
+    pub name: String,
+    pub filter: CollectionFilter,
+}
+
+/// A result whose seed, provider, model and parameters follow-up generations reuse
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RefineSession {
+    pub id: String,
+    pub workflow_id: String,
+    /// The chosen result the session started from
+    pub source_job_id: String,
+    pub provider: String,
+    pub model: String,
+    pub seed: i64,
+    /// Prompt of the source job, step 0 of the session
+    pub prompt: String,
+    /// JSON-encoded parameters of the source job, with the seed set
+    pub parameters: String,
+    pub created_at: String,
+}
+
+/// Which provider, model and terms of service produced a job's result
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProvenanceRecord {
+    pub id: String,
+    /// Job that produced the result; the job may since have been deleted
+    pub job_id: String,
+    /// Provider that actually produced the result, after any fallback
+    pub provider: String,
+    pub model: String,
+    /// Model build or version the provider reported, when it reports one
+    pub model_version: Option<String>,
+    pub terms_url: Option<String>,
+    /// Revision of the terms in effect when the result was produced
+    pub terms_revision: Option<String>,
+    /// Whether the terms allow commercial use of outputs; unknown when `None`
+    pub commercial_use: Option<bool>,
+    pub app_version: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProvenanceInput {
+    pub job_id: String,
+    pub provider: String,
+    pub model: String,
+    pub model_version: Option<String>,
+    pub terms_url: Option<String>,
+    pub terms_revision: Option<String>,
+    pub commercial_use: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: String,
+    /// Dotted action name, e.g. `provider.configure` or `job.delete`
+    pub action: String,
+    /// Provider name, record ID or file path the action applied to
+    pub target: Option<String>,
+    /// JSON object with action-specific details; never contains secrets
+    pub details: String,
+    /// OS account that ran the app
+    pub actor: Option<String>,
+    pub created_at: String,
+}
+
+/// Queue lane for short jobs such as text and image previews
+pub const INTERACTIVE_LANE: &str = "interactive";
+
+/// Queue lane for long-running jobs such as video
+pub const BATCH_LANE: &str = "batch";
+
+/// Version of the running app, recorded on rows it creates
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 /// Generate a UTC timestamp string
 pub fn now() -> String {
     Utc::now().to_rfc3339()
 }
 
+/// Parse an RFC 3339 timestamp and normalize it to UTC so stored values sort lexically
+pub fn normalize_timestamp(value: &str) -> anyhow::Result<String> {
+    let parsed = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| anyhow::anyhow!("Invalid timestamp '{}': {}", value, e))?;
+    Ok(parsed.with_timezone(&Utc).to_rfc3339())
+}
+
 /// Generate a UUID v4 string
 pub fn generate_id() -> String {
     uuid::Uuid::new_v4().to_string()
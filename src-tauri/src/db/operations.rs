@@ -1,4 +1,5 @@
 use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
 use sqlx::SqlitePool;
 
 use super::models::*;
@@ -82,6 +83,41 @@ impl WorkflowOps {
         Ok(workflow)
     }
 
+    /// Providers the workflow may use; `None` when it is unrestricted
+    pub async fn allowed_providers(pool: &SqlitePool, id: &str) -> Result<Option<Vec<String>>> {
+        let allowed: Option<Option<String>> =
+            sqlx::query_scalar("SELECT allowed_providers FROM workflows WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+        match allowed.flatten() {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Restrict the workflow to `providers`, or lift the restriction with `None`
+    pub async fn set_allowed_providers(
+        pool: &SqlitePool,
+        id: &str,
+        providers: Option<&[String]>,
+    ) -> Result<()> {
+        let json = providers.map(serde_json::to_string).transpose()?;
+        let result =
+            sqlx::query("UPDATE workflows SET allowed_providers = ?, updated_at = ? WHERE id = ?")
+                .bind(json)
+                .bind(now())
+                .bind(id)
+                .execute(pool)
+                .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Workflow not found"));
+        }
+
+        Ok(())
+    }
+
     pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         sqlx::query("DELETE FROM workflows WHERE id = ?")
             .bind(id)
@@ -120,6 +156,25 @@ impl SceneOps {
         Ok(scene)
     }
 
+    pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Scene>> {
+        let scene = sqlx::query_as::<_, Scene>("SELECT * FROM scenes WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(scene)
+    }
+
+    pub async fn set_thumbnail(pool: &SqlitePool, id: &str, thumbnail: &str) -> Result<()> {
+        sqlx::query("UPDATE scenes SET thumbnail = ? WHERE id = ?")
+            .bind(thumbnail)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn list_by_workflow(pool: &SqlitePool, workflow_id: &str) -> Result<Vec<Scene>> {
         let scenes = sqlx::query_as::<_, Scene>(
             "SELECT * FROM scenes WHERE workflow_id = ? ORDER BY created_at DESC",
@@ -131,6 +186,19 @@ impl SceneOps {
         Ok(scenes)
     }
 
+    /// Whether a scene was already imported from `source` with the given source ID
+    pub async fn exists_with_source(pool: &SqlitePool, source: &str, source_id: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM scenes WHERE json_extract(data, '$.metadata.source') = ? AND json_extract(data, '$.metadata.sourceId') = ?",
+        )
+        .bind(source)
+        .bind(source_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
     pub async fn list_all(pool: &SqlitePool) -> Result<Vec<Scene>> {
         let scenes = sqlx::query_as::<_, Scene>(
             "SELECT * FROM scenes ORDER BY created_at DESC",
@@ -156,14 +224,33 @@ pub struct JobOps;
 
 impl JobOps {
     pub async fn create(pool: &SqlitePool, input: CreateJobInput) -> Result<Job> {
+        Self::create_with_status(pool, input, "pending").await
+    }
+
+    /// Create a job in a status other than `pending`, e.g. held for confirmation
+    pub async fn create_with_status(
+        pool: &SqlitePool,
+        input: CreateJobInput,
+        status: &str,
+    ) -> Result<Job> {
+        let lane = input.lane.as_deref().unwrap_or(INTERACTIVE_LANE);
+        if lane != INTERACTIVE_LANE && lane != BATCH_LANE {
+            return Err(anyhow::anyhow!("Unknown queue lane: {}", lane));
+        }
+
         let id = generate_id();
         let now = now();
         let data = serde_json::to_string(&input.data)?;
+        let scheduled_at = input
+            .scheduled_at
+            .as_deref()
+            .map(normalize_timestamp)
+            .transpose()?;
 
         let job = sqlx::query_as::<_, Job>(
             r#"
-            INSERT INTO jobs (id, workflow_id, scene_id, type, status, data, created_at)
-            VALUES (?, ?, ?, ?, 'pending', ?, ?)
+            INSERT INTO jobs (id, workflow_id, scene_id, type, status, data, created_at, scheduled_at, app_version, lane)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING *
             "#,
         )
@@ -171,14 +258,132 @@ impl JobOps {
         .bind(&input.workflow_id)
         .bind(&input.scene_id)
         .bind(&input.job_type)
+        .bind(status)
         .bind(&data)
         .bind(&now)
+        .bind(&scheduled_at)
+        .bind(APP_VERSION)
+        .bind(lane)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Queue a new pending job with the same data as a failed job, linked via `retry_of`
+    pub async fn retry(pool: &SqlitePool, id: &str) -> Result<Job> {
+        Self::retry_with_data(pool, id, None).await
+    }
+
+    /// Like `retry`, but with replacement job data when `data` is given
+    pub async fn retry_with_data(pool: &SqlitePool, id: &str, data: Option<String>) -> Result<Job> {
+        let original = Self::get(pool, id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Job not found"))?;
+
+        if original.status != "failed" {
+            return Err(anyhow::anyhow!(
+                "Only failed jobs can be retried (job {} is {})",
+                id,
+                original.status
+            ));
+        }
+
+        let job = sqlx::query_as::<_, Job>(
+            r#"
+            INSERT INTO jobs (id, workflow_id, scene_id, type, status, data, created_at, app_version, retry_of, lane)
+            VALUES (?, ?, ?, ?, 'pending', ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&original.workflow_id)
+        .bind(&original.scene_id)
+        .bind(&original.job_type)
+        .bind(data.as_ref().unwrap_or(&original.data))
+        .bind(now())
+        .bind(APP_VERSION)
+        .bind(&original.id)
+        .bind(&original.lane)
         .fetch_one(pool)
         .await?;
 
         Ok(job)
     }
 
+    /// The newest retry of a job, following `retry_of` links forward; the job itself if
+    /// it was never retried
+    pub async fn latest_attempt(pool: &SqlitePool, id: &str) -> Result<Option<Job>> {
+        let mut latest = Self::get(pool, id).await?;
+        while let Some(job) = &latest {
+            let retry = sqlx::query_as::<_, Job>(
+                "SELECT * FROM jobs WHERE retry_of = ? ORDER BY created_at DESC LIMIT 1",
+            )
+            .bind(&job.id)
+            .fetch_optional(pool)
+            .await?;
+            match retry {
+                Some(retry) => latest = Some(retry),
+                None => break,
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Put a job back in the queue to be picked up again at `at` (RFC 3339)
+    pub async fn requeue_at(pool: &SqlitePool, id: &str, at: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET status = 'pending', scheduled_at = ? WHERE id = ?")
+            .bind(normalize_timestamp(at)?)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The first job of a type whose data has `value` at the JSON path `field`
+    pub async fn find_by_data(
+        pool: &SqlitePool,
+        job_type: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<Option<Job>> {
+        let job = sqlx::query_as::<_, Job>(
+            r#"
+            SELECT * FROM jobs WHERE type = ? AND json_extract(data, ?) = ?
+            ORDER BY created_at ASC LIMIT 1
+            "#,
+        )
+        .bind(job_type)
+        .bind(field)
+        .bind(value)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Follow `retry_of` links back to the first attempt, returning jobs oldest first
+    pub async fn lineage(pool: &SqlitePool, id: &str) -> Result<Vec<Job>> {
+        let mut chain = Vec::new();
+        let mut next = Some(id.to_string());
+
+        while let Some(current) = next {
+            let Some(job) = Self::get(pool, &current).await? else {
+                break;
+            };
+            // Guard against cycles from hand-edited rows
+            if chain.iter().any(|j: &Job| j.id == job.id) {
+                break;
+            }
+            next = job.retry_of.clone();
+            chain.push(job);
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
     pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Job>> {
         let job = sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE id = ?")
             .bind(id)
@@ -199,6 +404,207 @@ impl JobOps {
         Ok(jobs)
     }
 
+    pub async fn list_all(pool: &SqlitePool) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>("SELECT * FROM jobs ORDER BY created_at DESC")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(jobs)
+    }
+
+    /// Replace a job's data, e.g. to persist progress of a multi-step job
+    pub async fn update_data(pool: &SqlitePool, id: &str, data: &serde_json::Value) -> Result<()> {
+        sqlx::query("UPDATE jobs SET data = ? WHERE id = ?")
+            .bind(serde_json::to_string(data)?)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record what kind of failure a failed job hit
+    pub async fn set_error_kind(pool: &SqlitePool, id: &str, kind: &str) -> Result<()> {
+        sqlx::query("UPDATE jobs SET error_kind = ? WHERE id = ?")
+            .bind(kind)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record the actual cost of a finished job
+    pub async fn set_cost(pool: &SqlitePool, id: &str, cost: Option<f64>) -> Result<()> {
+        sqlx::query("UPDATE jobs SET cost = ? WHERE id = ?")
+            .bind(cost)
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The latest completed generation job of each scene in a workflow, oldest scene first
+    pub async fn latest_completed_by_scene(
+        pool: &SqlitePool,
+        workflow_id: &str,
+    ) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>(
+            r#"
+            SELECT jobs.* FROM jobs
+            JOIN scenes ON scenes.id = jobs.scene_id
+            WHERE jobs.workflow_id = ? AND jobs.type = 'generation' AND jobs.status = 'completed'
+            ORDER BY scenes.created_at ASC, jobs.completed_at DESC
+            "#,
+        )
+        .bind(workflow_id)
+        .fetch_all(pool)
+        .await?;
+
+        // Rows come newest first within each scene
+        let mut seen = std::collections::HashSet::new();
+        Ok(jobs
+            .into_iter()
+            .filter(|job| seen.insert(job.scene_id.clone()))
+            .collect())
+    }
+
+    /// Most recently completed generation jobs of a provider and model, newest first
+    pub async fn recent_completed(
+        pool: &SqlitePool,
+        provider: &str,
+        model: &str,
+        limit: i64,
+    ) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>(
+            r#"
+            SELECT * FROM jobs
+            WHERE type = 'generation' AND status = 'completed'
+              AND json_extract(data, '$.provider') = ?
+              AND json_extract(data, '$.model') = ?
+            ORDER BY completed_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(provider)
+        .bind(model)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    /// Run times in seconds of the most recent completed jobs for a provider and model
+    pub async fn recent_durations(
+        pool: &SqlitePool,
+        provider: &str,
+        model: &str,
+        limit: i64,
+    ) -> Result<Vec<u64>> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT started_at, completed_at FROM jobs
+            WHERE status = 'completed'
+              AND started_at IS NOT NULL AND completed_at IS NOT NULL
+              AND json_extract(data, '$.provider') = ?
+              AND json_extract(data, '$.model') = ?
+            ORDER BY completed_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(provider)
+        .bind(model)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        let durations = rows
+            .iter()
+            .filter_map(|(started, completed)| {
+                let started = chrono::DateTime::parse_from_rfc3339(started).ok()?;
+                let completed = chrono::DateTime::parse_from_rfc3339(completed).ok()?;
+                u64::try_from((completed - started).num_seconds()).ok()
+            })
+            .collect();
+
+        Ok(durations)
+    }
+
+    /// Release a job held for confirmation so the processor picks it up
+    pub async fn approve(pool: &SqlitePool, id: &str) -> Result<Job> {
+        let job = sqlx::query_as::<_, Job>(
+            "UPDATE jobs SET status = 'pending' WHERE id = ? AND status = 'needs_confirmation' RETURNING *",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Job not found or not awaiting confirmation"))?;
+
+        Ok(job)
+    }
+
+    /// Move jobs held for the network back to `pending`, returning how many were resumed
+    pub async fn resume_waiting_network(pool: &SqlitePool) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE jobs SET status = 'pending', error = NULL WHERE status = 'waiting_network'",
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Move local jobs deferred by eco scheduling back to `pending`, returning how many
+    pub async fn resume_waiting_eco(pool: &SqlitePool) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE jobs SET status = 'pending', error = NULL WHERE status = 'waiting_eco'",
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Move jobs paused by spending caps back to `pending`, returning how many
+    pub async fn resume_waiting_budget(pool: &SqlitePool) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE jobs SET status = 'pending', error = NULL WHERE status = 'waiting_budget'",
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Number of jobs that are running or due to run now
+    pub async fn count_active(pool: &SqlitePool) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM jobs
+            WHERE status = 'running'
+               OR (status = 'pending' AND (scheduled_at IS NULL OR scheduled_at <= ?))
+            "#,
+        )
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// List pending jobs that have a scheduled start time, soonest first
+    pub async fn list_scheduled(pool: &SqlitePool) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>(
+            "SELECT * FROM jobs WHERE status = 'pending' AND scheduled_at IS NOT NULL ORDER BY scheduled_at ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
     pub async fn update(pool: &SqlitePool, id: &str, input: UpdateJobInput) -> Result<Job> {
         let now = now();
 
@@ -223,6 +629,11 @@ impl JobOps {
             .bind(id)
             .execute(pool)
             .await?;
+
+            match (status.as_str(), &input.error) {
+                ("failed", Some(error)) => log_warn!("[Jobs] Job {} failed: {}", id, error),
+                _ => log_info!("[Jobs] Job {} is {}", id, status),
+            }
         }
 
         if let Some(result) = &input.result {
@@ -310,3 +721,828 @@ impl VersionOps {
         Ok(versions)
     }
 }
+
+/// Settings operations (JSON values stored by key)
+pub struct SettingsOps;
+
+impl SettingsOps {
+    /// Load a setting, returning `None` if it was never saved
+    pub async fn get<T: DeserializeOwned>(pool: &SqlitePool, key: &str) -> Result<Option<T>> {
+        let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await?;
+
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load a setting, falling back to its default if missing
+    pub async fn get_or_default<T: DeserializeOwned + Default>(
+        pool: &SqlitePool,
+        key: &str,
+    ) -> Result<T> {
+        Ok(Self::get(pool, key).await?.unwrap_or_default())
+    }
+
+    pub async fn set<T: Serialize>(pool: &SqlitePool, key: &str, value: &T) -> Result<()> {
+        let value = serde_json::to_string(value)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(&value)
+        .bind(now())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Every saved setting as `(key, JSON value)`, ordered by key
+    pub async fn list(pool: &SqlitePool) -> Result<Vec<(String, String)>> {
+        let settings = sqlx::query_as("SELECT key, value FROM settings ORDER BY key ASC")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(settings)
+    }
+}
+
+/// Notification rule CRUD operations
+pub struct NotificationRuleOps;
+
+impl NotificationRuleOps {
+    pub async fn create(
+        pool: &SqlitePool,
+        input: CreateNotificationRuleInput,
+    ) -> Result<NotificationRule> {
+        validate_notification_rule(&input.channel, input.target.as_deref(), input.on_status.as_deref())?;
+
+        let rule = sqlx::query_as::<_, NotificationRule>(
+            r#"
+            INSERT INTO notification_rules
+                (id, name, enabled, channel, target, on_status, workflow_id, provider, min_duration_seconds, created_at)
+            VALUES (?, ?, 1, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&input.name)
+        .bind(&input.channel)
+        .bind(&input.target)
+        .bind(input.on_status.as_deref().unwrap_or("any"))
+        .bind(&input.workflow_id)
+        .bind(&input.provider)
+        .bind(input.min_duration_seconds)
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(rule)
+    }
+
+    pub async fn list(pool: &SqlitePool) -> Result<Vec<NotificationRule>> {
+        let rules = sqlx::query_as::<_, NotificationRule>(
+            "SELECT * FROM notification_rules ORDER BY created_at ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    pub async fn update(
+        pool: &SqlitePool,
+        id: &str,
+        input: UpdateNotificationRuleInput,
+    ) -> Result<NotificationRule> {
+        let existing = sqlx::query_as::<_, NotificationRule>("SELECT * FROM notification_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Notification rule not found"))?;
+
+        let target = input.target.or(existing.target);
+        let on_status = input.on_status.unwrap_or(existing.on_status);
+        validate_notification_rule(&existing.channel, target.as_deref(), Some(&on_status))?;
+
+        let rule = sqlx::query_as::<_, NotificationRule>(
+            r#"
+            UPDATE notification_rules
+            SET name = ?, enabled = ?, target = ?, on_status = ?, min_duration_seconds = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(input.name.unwrap_or(existing.name))
+        .bind(input.enabled.unwrap_or(existing.enabled))
+        .bind(&target)
+        .bind(&on_status)
+        .bind(input.min_duration_seconds.or(existing.min_duration_seconds))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(rule)
+    }
+
+    pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM notification_rules WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn validate_notification_rule(channel: &str, target: Option<&str>, on_status: Option<&str>) -> Result<()> {
+    match channel {
+        "app" => {}
+        "discord" => {
+            if !target.is_some_and(|t| t.starts_with("https://")) {
+                return Err(anyhow::anyhow!("Discord rules need an https webhook URL as target"));
+            }
+        }
+        _ => return Err(anyhow::anyhow!("Unknown notification channel: {}", channel)),
+    }
+
+    match on_status {
+        None | Some("any") | Some("completed") | Some("failed") => Ok(()),
+        Some(other) => Err(anyhow::anyhow!("Unknown job status for notification rule: {}", other)),
+    }
+}
+
+/// Usage accounting operations
+pub struct UsageOps;
+
+impl UsageOps {
+    pub async fn create(pool: &SqlitePool, input: CreateUsageRecordInput) -> Result<UsageRecord> {
+        let record = sqlx::query_as::<_, UsageRecord>(
+            r#"
+            INSERT INTO usage_records
+                (id, job_id, provider, model, input_tokens, output_tokens, images, video_seconds, cost, created_at, api_key_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&input.job_id)
+        .bind(&input.provider)
+        .bind(&input.model)
+        .bind(input.input_tokens)
+        .bind(input.output_tokens)
+        .bind(input.images)
+        .bind(input.video_seconds)
+        .bind(input.cost)
+        .bind(now())
+        .bind(&input.api_key_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Requests and spend of each API key of a provider, busiest first
+    pub async fn by_api_key(pool: &SqlitePool, provider: &str) -> Result<Vec<KeyUsage>> {
+        let usage = sqlx::query_as::<_, KeyUsage>(
+            r#"
+            SELECT api_key_id, COUNT(*) AS requests, COALESCE(SUM(cost), 0.0) AS cost,
+                MAX(created_at) AS last_used_at
+            FROM usage_records
+            WHERE provider = ?
+            GROUP BY api_key_id
+            ORDER BY requests DESC
+            "#,
+        )
+        .bind(provider)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(usage)
+    }
+
+    /// Total cost of a provider's records created at or after `since`
+    pub async fn cost_since(pool: &SqlitePool, provider: &str, since: &str) -> Result<f64> {
+        let cost = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(cost), 0.0) FROM usage_records
+            WHERE provider = ? AND created_at >= ?
+            "#,
+        )
+        .bind(provider)
+        .bind(normalize_timestamp(since)?)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(cost)
+    }
+
+    /// Records created between `since` and `until` (inclusive, either may be open), oldest first
+    pub async fn list(
+        pool: &SqlitePool,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<UsageRecord>> {
+        let since = since.map(normalize_timestamp).transpose()?;
+        let until = until.map(normalize_timestamp).transpose()?;
+
+        let records = sqlx::query_as::<_, UsageRecord>(
+            r#"
+            SELECT * FROM usage_records
+            WHERE (? IS NULL OR created_at >= ?) AND (? IS NULL OR created_at <= ?)
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(&since)
+        .bind(&since)
+        .bind(&until)
+        .bind(&until)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(records)
+    }
+}
+
+/// Job request/response log operations
+pub struct JobLogOps;
+
+impl JobLogOps {
+    pub async fn create(
+        pool: &SqlitePool,
+        job_id: &str,
+        kind: &str,
+        provider: Option<&str>,
+        payload: &serde_json::Value,
+    ) -> Result<JobLog> {
+        let log = sqlx::query_as::<_, JobLog>(
+            r#"
+            INSERT INTO job_logs (id, job_id, kind, provider, payload, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(job_id)
+        .bind(kind)
+        .bind(provider)
+        .bind(serde_json::to_string(payload)?)
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(log)
+    }
+
+    /// Log entries of a job, oldest first
+    pub async fn list(pool: &SqlitePool, job_id: &str) -> Result<Vec<JobLog>> {
+        let logs = sqlx::query_as::<_, JobLog>(
+            "SELECT * FROM job_logs WHERE job_id = ? ORDER BY created_at ASC, rowid ASC",
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(logs)
+    }
+}
+
+/// Audit log operations; entries are append-only
+pub struct AuditOps;
+
+impl AuditOps {
+    pub async fn create(
+        pool: &SqlitePool,
+        action: &str,
+        target: Option<&str>,
+        details: &serde_json::Value,
+        actor: Option<&str>,
+    ) -> Result<AuditEntry> {
+        let entry = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            INSERT INTO audit_log (id, action, target, details, actor, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(action)
+        .bind(target)
+        .bind(serde_json::to_string(details)?)
+        .bind(actor)
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(entry)
+    }
+
+    /// Newest entries first, optionally only those whose action starts with `action`
+    pub async fn list(
+        pool: &SqlitePool,
+        action: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuditEntry>> {
+        let entries = sqlx::query_as::<_, AuditEntry>(
+            r#"
+            SELECT * FROM audit_log
+            WHERE ? IS NULL OR action LIKE ? || '%'
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ? OFFSET ?
+            "#,
+        )
+        .bind(action)
+        .bind(action)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+/// Result cache operations
+pub struct ResultCacheOps;
+
+impl ResultCacheOps {
+    pub async fn get(pool: &SqlitePool, cache_key: &str) -> Result<Option<CachedResult>> {
+        let entry = sqlx::query_as::<_, CachedResult>("SELECT * FROM result_cache WHERE cache_key = ?")
+            .bind(cache_key)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(entry)
+    }
+
+    /// Store a result, replacing any entry with the same key
+    pub async fn put(
+        pool: &SqlitePool,
+        cache_key: &str,
+        provider: &str,
+        model: &str,
+        job_id: &str,
+        result: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO result_cache (cache_key, provider, model, job_id, result, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(cache_key)
+        .bind(provider)
+        .bind(model)
+        .bind(job_id)
+        .bind(serde_json::to_string(result)?)
+        .bind(now())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn record_hit(pool: &SqlitePool, cache_key: &str) -> Result<()> {
+        sqlx::query("UPDATE result_cache SET hits = hits + 1, last_hit_at = ? WHERE cache_key = ?")
+            .bind(now())
+            .bind(cache_key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(pool: &SqlitePool, cache_key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM result_cache WHERE cache_key = ?")
+            .bind(cache_key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove all entries, or only one provider's, returning how many were removed
+    pub async fn clear(pool: &SqlitePool, provider: Option<&str>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM result_cache WHERE ? IS NULL OR provider = ?")
+            .bind(provider)
+            .bind(provider)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Per-provider and per-model default parameter CRUD operations
+pub struct ParameterPresetOps;
+
+impl ParameterPresetOps {
+    pub async fn create(
+        pool: &SqlitePool,
+        input: CreateParameterPresetInput,
+    ) -> Result<ParameterPreset> {
+        if !input.parameters.is_object() {
+            return Err(anyhow::anyhow!("Preset parameters must be a JSON object"));
+        }
+        if Self::find(pool, &input.provider, input.model.as_deref()).await?.is_some() {
+            return Err(anyhow::anyhow!(
+                "A preset for {} {} already exists",
+                input.provider,
+                input.model.as_deref().unwrap_or("(all models)")
+            ));
+        }
+
+        let now = now();
+        let preset = sqlx::query_as::<_, ParameterPreset>(
+            r#"
+            INSERT INTO parameter_presets (id, provider, model, parameters, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&input.provider)
+        .bind(&input.model)
+        .bind(serde_json::to_string(&input.parameters)?)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(preset)
+    }
+
+    pub async fn list(pool: &SqlitePool) -> Result<Vec<ParameterPreset>> {
+        let presets = sqlx::query_as::<_, ParameterPreset>(
+            "SELECT * FROM parameter_presets ORDER BY provider ASC, model ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(presets)
+    }
+
+    pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<ParameterPreset>> {
+        let preset =
+            sqlx::query_as::<_, ParameterPreset>("SELECT * FROM parameter_presets WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(preset)
+    }
+
+    /// The preset for exactly this provider and model (`None` for the provider-wide one)
+    pub async fn find(
+        pool: &SqlitePool,
+        provider: &str,
+        model: Option<&str>,
+    ) -> Result<Option<ParameterPreset>> {
+        let preset = sqlx::query_as::<_, ParameterPreset>(
+            "SELECT * FROM parameter_presets WHERE provider = ? AND IFNULL(model, '') = ?",
+        )
+        .bind(provider)
+        .bind(model.unwrap_or_default())
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(preset)
+    }
+
+    pub async fn update(
+        pool: &SqlitePool,
+        id: &str,
+        parameters: &serde_json::Value,
+    ) -> Result<ParameterPreset> {
+        if !parameters.is_object() {
+            return Err(anyhow::anyhow!("Preset parameters must be a JSON object"));
+        }
+
+        let preset = sqlx::query_as::<_, ParameterPreset>(
+            "UPDATE parameter_presets SET parameters = ?, updated_at = ? WHERE id = ? RETURNING *",
+        )
+        .bind(serde_json::to_string(parameters)?)
+        .bind(now())
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Parameter preset not found"))?;
+
+        Ok(preset)
+    }
+
+    pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM parameter_presets WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Smart collection operations
+pub struct SmartCollectionOps;
+
+impl SmartCollectionOps {
+    pub async fn create(pool: &SqlitePool, input: SmartCollectionInput) -> Result<SmartCollection> {
+        if input.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("A collection name is required"));
+        }
+
+        let now = now();
+        let collection = sqlx::query_as::<_, SmartCollection>(
+            r#"
+            INSERT INTO smart_collections (id, name, filter, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(input.name.trim())
+        .bind(serde_json::to_string(&input.filter)?)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(collection)
+    }
+
+    pub async fn list(pool: &SqlitePool) -> Result<Vec<SmartCollection>> {
+        let collections = sqlx::query_as::<_, SmartCollection>(
+            "SELECT * FROM smart_collections ORDER BY name COLLATE NOCASE ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(collections)
+    }
+
+    pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<SmartCollection>> {
+        let collection =
+            sqlx::query_as::<_, SmartCollection>("SELECT * FROM smart_collections WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(collection)
+    }
+
+    pub async fn update(
+        pool: &SqlitePool,
+        id: &str,
+        input: SmartCollectionInput,
+    ) -> Result<SmartCollection> {
+        if input.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("A collection name is required"));
+        }
+
+        let collection = sqlx::query_as::<_, SmartCollection>(
+            r#"
+            UPDATE smart_collections SET name = ?, filter = ?, updated_at = ?
+            WHERE id = ?
+            RETURNING *
+            "#,
+        )
+        .bind(input.name.trim())
+        .bind(serde_json::to_string(&input.filter)?)
+        .bind(now())
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Smart collection not found"))?;
+
+        Ok(collection)
+    }
+
+    pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM smart_collections WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Completed generation jobs matching a collection's filter, newest first
+    pub async fn items(pool: &SqlitePool, id: &str) -> Result<Vec<Job>> {
+        let collection = Self::get(pool, id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Smart collection not found"))?;
+        let filter: CollectionFilter = serde_json::from_str(&collection.filter)?;
+        Self::matching(pool, &filter).await
+    }
+
+    /// Completed generation jobs matching `filter`, newest first
+    pub async fn matching(pool: &SqlitePool, filter: &CollectionFilter) -> Result<Vec<Job>> {
+        let since = filter.within_days.map(|days| {
+            (chrono::Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339()
+        });
+        let tags = serde_json::to_string(&filter.tags)?;
+        let prompt = filter.prompt_contains.as_deref().map(str::to_lowercase);
+
+        // Every wanted tag must appear among the job's tags
+        let jobs = sqlx::query_as::<_, Job>(
+            r#"
+            SELECT * FROM jobs
+            WHERE type = 'generation' AND status = 'completed'
+              AND NOT EXISTS (
+                  SELECT 1 FROM json_each(?) AS wanted
+                  WHERE lower(wanted.value) NOT IN (
+                      SELECT lower(value) FROM json_each(jobs.data, '$.tags')
+                  )
+              )
+              AND (? IS NULL OR json_extract(data, '$.rating') >= ?)
+              AND (? IS NULL OR json_extract(data, '$.provider') = ?)
+              AND (? IS NULL OR json_extract(data, '$.model') = ?)
+              AND (? IS NULL OR workflow_id = ?)
+              AND (? IS NULL OR created_at >= ?)
+              AND (? IS NULL OR instr(lower(json_extract(data, '$.prompt')), ?) > 0)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(&tags)
+        .bind(filter.min_rating)
+        .bind(filter.min_rating)
+        .bind(&filter.provider)
+        .bind(&filter.provider)
+        .bind(&filter.model)
+        .bind(&filter.model)
+        .bind(&filter.workflow_id)
+        .bind(&filter.workflow_id)
+        .bind(&since)
+        .bind(&since)
+        .bind(&prompt)
+        .bind(&prompt)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(jobs)
+    }
+}
+
+pub struct RefineOps;
+
+impl RefineOps {
+    /// Start a session from `source`, locking `seed` into its parameters
+    pub async fn create(
+        pool: &SqlitePool,
+        source: &Job,
+        provider: &str,
+        model: &str,
+        seed: i64,
+        prompt: &str,
+        parameters: &serde_json::Value,
+    ) -> Result<RefineSession> {
+        let session = sqlx::query_as::<_, RefineSession>(
+            r#"
+            INSERT INTO refine_sessions
+                (id, workflow_id, source_job_id, provider, model, seed, prompt, parameters,
+                 created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&source.workflow_id)
+        .bind(&source.id)
+        .bind(provider)
+        .bind(model)
+        .bind(seed)
+        .bind(prompt)
+        .bind(serde_json::to_string(parameters)?)
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    pub async fn get(pool: &SqlitePool, id: &str) -> Result<Option<RefineSession>> {
+        let session =
+            sqlx::query_as::<_, RefineSession>("SELECT * FROM refine_sessions WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(session)
+    }
+
+    /// Sessions of a workflow, newest first
+    pub async fn list_by_workflow(
+        pool: &SqlitePool,
+        workflow_id: &str,
+    ) -> Result<Vec<RefineSession>> {
+        let sessions = sqlx::query_as::<_, RefineSession>(
+            "SELECT * FROM refine_sessions WHERE workflow_id = ? ORDER BY created_at DESC",
+        )
+        .bind(workflow_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Jobs queued in a session, in the order they were queued
+    pub async fn steps(pool: &SqlitePool, id: &str) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>(
+            r#"
+            SELECT * FROM jobs WHERE json_extract(data, '$.refine.session_id') = ?
+            ORDER BY json_extract(data, '$.refine.step') ASC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(jobs)
+    }
+}
+
+pub struct ProvenanceOps;
+
+impl ProvenanceOps {
+    /// Record the provenance of a job's result, replacing any earlier record for the job
+    pub async fn create(
+        pool: &SqlitePool,
+        input: CreateProvenanceInput,
+    ) -> Result<ProvenanceRecord> {
+        let record = sqlx::query_as::<_, ProvenanceRecord>(
+            r#"
+            INSERT OR REPLACE INTO provenance
+                (id, job_id, provider, model, model_version, terms_url, terms_revision,
+                 commercial_use, app_version, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING *
+            "#,
+        )
+        .bind(generate_id())
+        .bind(&input.job_id)
+        .bind(&input.provider)
+        .bind(&input.model)
+        .bind(&input.model_version)
+        .bind(&input.terms_url)
+        .bind(&input.terms_revision)
+        .bind(input.commercial_use)
+        .bind(APP_VERSION)
+        .bind(now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    pub async fn get_by_job(pool: &SqlitePool, job_id: &str) -> Result<Option<ProvenanceRecord>> {
+        let record =
+            sqlx::query_as::<_, ProvenanceRecord>("SELECT * FROM provenance WHERE job_id = ?")
+                .bind(job_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(record)
+    }
+
+    /// Records in a time range, optionally only those of one provider or with a known
+    /// commercial-use status, oldest first
+    pub async fn list(
+        pool: &SqlitePool,
+        since: Option<&str>,
+        until: Option<&str>,
+        provider: Option<&str>,
+        commercial_use: Option<bool>,
+    ) -> Result<Vec<ProvenanceRecord>> {
+        let since = since.map(normalize_timestamp).transpose()?;
+        let until = until.map(normalize_timestamp).transpose()?;
+
+        let records = sqlx::query_as::<_, ProvenanceRecord>(
+            r#"
+            SELECT * FROM provenance
+            WHERE (? IS NULL OR created_at >= ?) AND (? IS NULL OR created_at <= ?)
+                AND (? IS NULL OR provider = ?) AND (? IS NULL OR commercial_use = ?)
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(&since)
+        .bind(&since)
+        .bind(&until)
+        .bind(&until)
+        .bind(provider)
+        .bind(provider)
+        .bind(commercial_use)
+        .bind(commercial_use)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(records)
+    }
+}
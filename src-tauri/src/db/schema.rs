@@ -1,3 +1,17 @@
+/// Schema version written by this build
+///
+/// Only bump this for changes older builds cannot safely read or write (renamed or
+/// repurposed columns). Added tables and nullable columns are ignored by older builds.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// SQL schema for app metadata (schema version, last app version)
+pub const CREATE_APP_META_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS app_meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+)
+"#;
+
 /// SQL schema for workflows table
 pub const CREATE_WORKFLOWS_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS workflows (
@@ -49,7 +63,181 @@ CREATE TABLE IF NOT EXISTS jobs (
     created_at TEXT NOT NULL,
     started_at TEXT,
     completed_at TEXT,
+    scheduled_at TEXT,
+    app_version TEXT,
+    retry_of TEXT,
+    cost REAL,
+    lane TEXT NOT NULL DEFAULT 'interactive',
     FOREIGN KEY (workflow_id) REFERENCES workflows(id) ON DELETE CASCADE,
     FOREIGN KEY (scene_id) REFERENCES scenes(id) ON DELETE SET NULL
 )
 "#;
+
+/// SQL schema for app settings (JSON values keyed by setting name)
+pub const CREATE_SETTINGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+)
+"#;
+
+/// SQL schema for notification rules evaluated when jobs finish
+pub const CREATE_NOTIFICATION_RULES_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS notification_rules (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    channel TEXT NOT NULL,
+    target TEXT,
+    on_status TEXT NOT NULL DEFAULT 'any',
+    workflow_id TEXT,
+    provider TEXT,
+    min_duration_seconds INTEGER,
+    created_at TEXT NOT NULL
+)
+"#;
+
+/// SQL schema for per-generation usage, kept when the job itself is deleted
+pub const CREATE_USAGE_RECORDS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS usage_records (
+    id TEXT PRIMARY KEY,
+    job_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    images INTEGER NOT NULL DEFAULT 0,
+    video_seconds REAL NOT NULL DEFAULT 0,
+    cost REAL,
+    created_at TEXT NOT NULL
+)
+"#;
+
+pub const CREATE_USAGE_RECORDS_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_usage_records_created_at ON usage_records(created_at)
+"#;
+
+/// SQL schema for sanitized provider requests and responses of each job
+pub const CREATE_JOB_LOGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS job_logs (
+    id TEXT PRIMARY KEY,
+    job_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    provider TEXT,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (job_id) REFERENCES jobs(id) ON DELETE CASCADE
+)
+"#;
+
+pub const CREATE_JOB_LOGS_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_job_logs_job_id ON job_logs(job_id)
+"#;
+
+/// SQL schema for cached results of deterministic generations
+pub const CREATE_RESULT_CACHE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS result_cache (
+    cache_key TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    job_id TEXT NOT NULL,
+    result TEXT NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    last_hit_at TEXT
+)
+"#;
+
+/// SQL schema for default parameters saved per provider and, optionally, model
+pub const CREATE_PARAMETER_PRESETS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS parameter_presets (
+    id TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    model TEXT,
+    parameters TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+)
+"#;
+
+/// One preset per provider and model; a NULL model is the provider-wide preset
+pub const CREATE_PARAMETER_PRESETS_INDEX: &str = r#"
+CREATE UNIQUE INDEX IF NOT EXISTS idx_parameter_presets_target
+ON parameter_presets(provider, IFNULL(model, ''))
+"#;
+
+/// SQL schema for smart collections: named filters over generated outputs
+pub const CREATE_SMART_COLLECTIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS smart_collections (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    filter TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+)
+"#;
+
+/// SQL schema for refine sessions: a result's seed and settings, locked for follow-ups
+pub const CREATE_REFINE_SESSIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS refine_sessions (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    source_job_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    seed INTEGER NOT NULL,
+    prompt TEXT NOT NULL,
+    parameters TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflows(id) ON DELETE CASCADE
+)
+"#;
+
+/// SQL schema for which provider, model and terms produced each job's result, kept
+/// when the job itself is deleted
+pub const CREATE_PROVENANCE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS provenance (
+    id TEXT PRIMARY KEY,
+    job_id TEXT NOT NULL UNIQUE,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    model_version TEXT,
+    terms_url TEXT,
+    terms_revision TEXT,
+    commercial_use INTEGER,
+    app_version TEXT NOT NULL,
+    created_at TEXT NOT NULL
+)
+"#;
+
+/// SQL schema for the audit log of configuration changes and destructive actions
+pub const CREATE_AUDIT_LOG_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    action TEXT NOT NULL,
+    target TEXT,
+    details TEXT NOT NULL,
+    actor TEXT,
+    created_at TEXT NOT NULL
+)
+"#;
+
+pub const CREATE_AUDIT_LOG_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at)
+"#;
+
+/// Entries can only be appended; updates and deletes are rejected by the database
+pub const CREATE_AUDIT_LOG_NO_UPDATE_TRIGGER: &str = r#"
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END
+"#;
+
+pub const CREATE_AUDIT_LOG_NO_DELETE_TRIGGER: &str = r#"
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END
+"#;
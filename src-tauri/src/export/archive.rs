@@ -0,0 +1,270 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+
+use super::{c2pa, resolve_asset_file, watermark};
+use crate::db::operations::{JobOps, SceneOps};
+use crate::storage::paths::{self, sanitize_file_name};
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Provenance of one file in the archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    /// File name inside the archive
+    pub file: String,
+    pub asset_id: String,
+    /// `scene` or `job`
+    pub kind: String,
+    pub name: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub prompt: Option<String>,
+    pub seed: Option<serde_json::Value>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub generator: String,
+    pub exported_at: String,
+    pub assets: Vec<ManifestEntry>,
+}
+
+/// Summary of a written archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipExportSummary {
+    pub path: String,
+    pub files: usize,
+    /// Files that carry the invisible watermark
+    pub watermarked: usize,
+    /// Files that carry Content Credentials
+    pub signed: usize,
+}
+
+/// Bundle the selected assets' files and a provenance manifest into a zip archive
+pub async fn export_assets_zip(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    asset_ids: &[String],
+    output_path: &Path,
+) -> Result<ZipExportSummary> {
+    if asset_ids.is_empty() {
+        return Err(anyhow::anyhow!("No assets selected for export"));
+    }
+
+    let mut used_names = HashSet::new();
+    let mut files: Vec<(String, PathBuf)> = Vec::with_capacity(asset_ids.len());
+    let mut entries = Vec::with_capacity(asset_ids.len());
+    for asset_id in asset_ids {
+        let (name, path) = resolve_asset_file(pool, asset_id).await?;
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "png".to_string());
+        let file = archive_name(&name, &extension, &mut used_names);
+
+        let mut entry = manifest_entry(pool, asset_id).await?;
+        entry.file = file.clone();
+        entries.push(entry);
+        files.push((file, path));
+    }
+
+    // Watermark and sign copies in a scratch directory so the originals stay untouched
+    let staging_dir =
+        std::env::temp_dir().join(format!("promptcraft-export-{}", uuid::Uuid::new_v4()));
+    let mut watermarked = 0;
+    if let Some(tag) = watermark::enabled_tag(pool).await? {
+        tokio::fs::create_dir_all(&staging_dir).await?;
+        for (file, path) in files.iter_mut() {
+            let staged_path = staging_dir.join(file.as_str());
+            match watermark_copy(path, &staged_path, &tag).await {
+                Ok(()) => {
+                    *path = staged_path;
+                    watermarked += 1;
+                }
+                Err(e) => log_warn!("[Export] Watermark not added to {}: {}", file, e),
+            }
+        }
+    }
+
+    let mut signed = 0;
+    if let Some(settings) = c2pa::enabled_settings(pool, data_dir).await? {
+        tokio::fs::create_dir_all(&staging_dir).await?;
+        for ((file, path), entry) in files.iter_mut().zip(&entries) {
+            let signed_path = staging_dir.join(format!("signed-{}", file));
+            match c2pa::sign_file(&settings, entry, path, &signed_path).await {
+                Ok(()) => {
+                    *path = signed_path;
+                    signed += 1;
+                }
+                Err(e) => log_warn!("[Export] Content Credentials not added to {}: {}", file, e),
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        generator: format!("PromptCraft Desktop {}", env!("CARGO_PKG_VERSION")),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        assets: entries,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+
+    let archive = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+        // Images are already compressed, so they are stored as-is
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (file, path) in &files {
+            writer.start_file(file.as_str(), stored)?;
+            writer.write_all(&std::fs::read(path)?)?;
+        }
+
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file(MANIFEST_NAME, deflated)?;
+        writer.write_all(&manifest_json)?;
+
+        Ok(writer.finish()?.into_inner())
+    })
+    .await;
+    let _ = tokio::fs::remove_dir_all(&staging_dir).await;
+    let archive = archive??;
+
+    if let Some(parent) = output_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    paths::write_atomic(output_path, &archive).await?;
+
+    Ok(ZipExportSummary {
+        path: output_path.display().to_string(),
+        files: manifest.assets.len(),
+        watermarked,
+        signed,
+    })
+}
+
+/// Write a watermarked copy of an image, keeping its format
+async fn watermark_copy(source: &Path, output: &Path, tag: &str) -> Result<()> {
+    let (source, output, tag) = (source.to_path_buf(), output.to_path_buf(), tag.to_string());
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let format = image::ImageFormat::from_path(&source)?;
+        let image = image::open(&source)?;
+        // Transparent images keep their alpha channel
+        let image = if image.color().has_alpha() {
+            let mut image = image.to_rgba8();
+            watermark::embed_keeping_alpha(&mut image, &tag)?;
+            image::DynamicImage::ImageRgba8(image)
+        } else {
+            let mut image = image.to_rgb8();
+            watermark::embed(&mut image, &tag)?;
+            image::DynamicImage::ImageRgb8(image)
+        };
+
+        if format == image::ImageFormat::Jpeg {
+            let file = std::fs::File::create(&output)?;
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(file, 95);
+            image.write_with_encoder(encoder)?;
+        } else {
+            image.save_with_format(&output, format)?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// Provenance recorded for a scene or job; `file` is filled in by the caller
+pub async fn manifest_entry(pool: &SqlitePool, asset_id: &str) -> Result<ManifestEntry> {
+    if let Some(scene) = SceneOps::get(pool, asset_id).await? {
+        let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        let prompt = data.get("prompt").and_then(|p| {
+            p.as_str()
+                .or_else(|| p.get("main").and_then(|v| v.as_str()))
+                .map(str::to_string)
+        });
+
+        return Ok(ManifestEntry {
+            file: String::new(),
+            asset_id: scene.id,
+            kind: "scene".to_string(),
+            name: scene.name,
+            provider: string_field(&data, "provider"),
+            model: string_field(&data, "model"),
+            prompt,
+            seed: data.pointer("/prompt/params/seed").cloned().filter(is_fixed_seed),
+            created_at: scene.created_at,
+            completed_at: None,
+        });
+    }
+
+    if let Some(job) = JobOps::get(pool, asset_id).await? {
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let result: serde_json::Value = job
+            .result
+            .as_deref()
+            .and_then(|r| serde_json::from_str(r).ok())
+            .unwrap_or_default();
+
+        // The backend reports the seed it actually used when the request asked for a random one
+        let seed = result
+            .pointer("/metadata/seed")
+            .or_else(|| data.pointer("/parameters/seed"))
+            .cloned()
+            .filter(is_fixed_seed);
+
+        return Ok(ManifestEntry {
+            file: String::new(),
+            asset_id: job.id.clone(),
+            kind: "job".to_string(),
+            name: format!("job-{}", job.id),
+            provider: string_field(&data, "provider"),
+            model: string_field(&data, "model"),
+            prompt: string_field(&data, "prompt"),
+            seed,
+            created_at: job.created_at,
+            completed_at: job.completed_at,
+        });
+    }
+
+    Err(anyhow::anyhow!("Asset not found: {}", asset_id))
+}
+
+fn string_field(data: &serde_json::Value, key: &str) -> Option<String> {
+    data.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+fn is_fixed_seed(seed: &serde_json::Value) -> bool {
+    !seed.is_null() && seed.as_i64() != Some(-1)
+}
+
+/// Sanitized, unique file name for an archive entry
+fn archive_name(name: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let base = sanitize_file_name(name);
+    let mut candidate = format!("{}.{}", base, extension);
+    let mut n = 2;
+    while used.contains(&candidate.to_lowercase()) {
+        candidate = format!("{} ({}).{}", base, n, extension);
+        n += 1;
+    }
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_name_deduplicates() {
+        let mut used = HashSet::new();
+        assert_eq!(archive_name("Hero shot", "png", &mut used), "Hero shot.png");
+        assert_eq!(archive_name("hero shot", "png", &mut used), "hero shot (2).png");
+        assert_eq!(archive_name("Hero/shot", "jpg", &mut used), "Hero-shot.jpg");
+    }
+}
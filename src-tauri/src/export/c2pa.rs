@@ -0,0 +1,258 @@
+//! Optional C2PA Content Credentials for exported files.
+//!
+//! Signing is done by the Content Authenticity Initiative's `c2patool` CLI, which
+//! must be installed separately. The manifest records the generator, provider and
+//! model, and a SHA-256 hash of the prompt rather than the prompt itself, so client
+//! deliverables carry provenance without leaking prompt text.
+//!
+//! The signing key is kept in the credential store and only handed to `c2patool` in
+//! a file readable by the current user, removed as soon as the tool has run.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use super::archive::ManifestEntry;
+use crate::db::operations::SettingsOps;
+use crate::secrets;
+
+/// Settings key for Content Credentials signing
+pub const C2PA_SETTINGS_KEY: &str = "c2pa_settings";
+
+/// Credential store entry of the signing key
+pub const C2PA_SECRET: &str = "c2pa_signing_key";
+
+/// IPTC source type for media created by a generative model
+const TRAINED_ALGORITHMIC_MEDIA: &str =
+    "http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia";
+
+/// Content Credentials configuration, stored in the settings table without the key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct C2paSettings {
+    pub enabled: bool,
+    /// Path to `c2patool`; looked up on `PATH` by default
+    pub tool_path: String,
+    /// PEM certificate chain; c2patool's test certificate is used when unset
+    pub sign_cert: Option<String>,
+    /// PEM private key matching `sign_cert`, kept in the credential store
+    pub private_key: Option<String>,
+    /// Signing algorithm of the key (es256, es384, ps256, ed25519, ...)
+    pub signing_alg: String,
+    /// RFC 3161 timestamp authority
+    pub timestamp_url: Option<String>,
+}
+
+impl Default for C2paSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool_path: "c2patool".to_string(),
+            sign_cert: None,
+            private_key: None,
+            signing_alg: "es256".to_string(),
+            timestamp_url: None,
+        }
+    }
+}
+
+/// Load the signing settings with the private key from the credential store
+///
+/// A key still saved in the settings table is moved to the credential store.
+pub async fn load_settings(pool: &SqlitePool, data_dir: &Path) -> Result<C2paSettings> {
+    let mut settings: C2paSettings = SettingsOps::get_or_default(pool, C2PA_SETTINGS_KEY).await?;
+    if settings.private_key.is_some() {
+        save_settings(pool, data_dir, &settings).await?;
+        return Ok(settings);
+    }
+    // Credential store calls can block (Secret Service goes over D-Bus)
+    let dir = data_dir.to_path_buf();
+    settings.private_key =
+        tokio::task::spawn_blocking(move || secrets::load_api_key(&dir, C2PA_SECRET)).await??;
+    Ok(settings)
+}
+
+/// Save the signing settings, keeping the private key in the credential store
+///
+/// Without a certificate the key is unused, so a saved one is deleted.
+pub async fn save_settings(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    settings: &C2paSettings,
+) -> Result<()> {
+    let mut stored = settings.clone();
+    let dir = data_dir.to_path_buf();
+    match stored.private_key.take().filter(|key| !key.is_empty()) {
+        Some(key) => {
+            tokio::task::spawn_blocking(move || secrets::store_api_key(&dir, C2PA_SECRET, &key))
+                .await??;
+        }
+        None if stored.sign_cert.is_none() => {
+            tokio::task::spawn_blocking(move || secrets::delete_api_key(&dir, C2PA_SECRET))
+                .await??;
+        }
+        None => {}
+    }
+    SettingsOps::set(pool, C2PA_SETTINGS_KEY, &stored).await
+}
+
+/// Settings to sign exports with, or `None` when signing is turned off
+pub async fn enabled_settings(pool: &SqlitePool, data_dir: &Path) -> Result<Option<C2paSettings>> {
+    let settings = load_settings(pool, data_dir).await?;
+    Ok(settings.enabled.then_some(settings))
+}
+
+/// Version string of the configured `c2patool`, failing if it cannot be run
+pub async fn tool_version(settings: &C2paSettings) -> Result<String> {
+    let output = tokio::process::Command::new(&settings.tool_path)
+        .arg("--version")
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not run {}: {}", settings.tool_path, e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("{} --version failed", settings.tool_path));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Write a signed copy of `source` to `output`
+pub async fn sign_file(
+    settings: &C2paSettings,
+    entry: &ManifestEntry,
+    source: &Path,
+    output: &Path,
+) -> Result<()> {
+    // Dropping the directory removes the key on every exit path
+    let scratch = PrivateDir::create()?;
+    let mut definition = manifest_definition(settings, entry);
+    if let (Some(cert), Some(key)) = (&settings.sign_cert, &settings.private_key) {
+        let cert_path = scratch.0.join("cert.pem");
+        let key_path = scratch.0.join("key.pem");
+        secrets::write_private(&cert_path, cert.as_bytes())?;
+        secrets::write_private(&key_path, key.as_bytes())?;
+        definition["sign_cert"] = serde_json::json!(cert_path);
+        definition["private_key"] = serde_json::json!(key_path);
+        definition["alg"] = serde_json::json!(settings.signing_alg);
+    }
+    let manifest_path = scratch.0.join("manifest.json");
+    secrets::write_private(&manifest_path, &serde_json::to_vec(&definition)?)?;
+
+    let output = tokio::process::Command::new(&settings.tool_path)
+        .arg(source)
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .arg("--output")
+        .arg(output)
+        .arg("--force")
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not run {}: {}", settings.tool_path, e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "c2patool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Scratch directory only the current user can open, removed when dropped
+struct PrivateDir(PathBuf);
+
+impl PrivateDir {
+    fn create() -> Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("promptcraft-c2pa-{}", uuid::Uuid::new_v4()));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for PrivateDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Manifest definition in the format c2patool expects, without the signing key
+fn manifest_definition(settings: &C2paSettings, entry: &ManifestEntry) -> serde_json::Value {
+    let mut generation = serde_json::json!({
+        "provider": entry.provider,
+        "model": entry.model,
+        "created_at": entry.created_at,
+    });
+    if let Some(prompt) = &entry.prompt {
+        generation["prompt_sha256"] = serde_json::json!(hex_sha256(prompt));
+    }
+
+    let mut definition = serde_json::json!({
+        "claim_generator": format!("PromptCraft_Desktop/{}", env!("CARGO_PKG_VERSION")),
+        "title": entry.file,
+        "assertions": [
+            {
+                "label": "c2pa.actions",
+                "data": {
+                    "actions": [{
+                        "action": "c2pa.created",
+                        "digitalSourceType": TRAINED_ALGORITHMIC_MEDIA,
+                        "softwareAgent": entry.model.as_deref().unwrap_or("unknown"),
+                    }]
+                }
+            },
+            {
+                "label": "com.promptcraft.generation",
+                "data": generation,
+            }
+        ]
+    });
+
+    if let Some(url) = &settings.timestamp_url {
+        definition["ta_url"] = serde_json::json!(url);
+    }
+    definition
+}
+
+fn hex_sha256(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_hashes_prompt() {
+        let entry = ManifestEntry {
+            file: "hero.png".to_string(),
+            asset_id: "job-1".to_string(),
+            kind: "job".to_string(),
+            name: "hero".to_string(),
+            provider: Some("openai".to_string()),
+            model: Some("gpt-image-1".to_string()),
+            prompt: Some("a lighthouse at dusk".to_string()),
+            seed: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            completed_at: None,
+        };
+        let definition = manifest_definition(&C2paSettings::default(), &entry);
+        let serialized = definition.to_string();
+
+        assert!(!serialized.contains("lighthouse"));
+        assert_eq!(
+            definition.pointer("/assertions/1/data/prompt_sha256").and_then(|v| v.as_str()),
+            Some(hex_sha256("a lighthouse at dusk").as_str())
+        );
+        assert!(definition.get("private_key").is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use ab_glyph::{FontVec, PxScale};
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use super::resolve_asset_file;
+use crate::db::operations::{JobOps, SceneOps};
+
+/// Largest width or height of a single tile
+const TILE_SIZE: u32 = 512;
+/// Height of the caption strip below each tile
+const CAPTION_HEIGHT: u32 = 28;
+const GAP: u32 = 8;
+/// Most cells in one grid, enough for the largest parameter sweep
+const MAX_GRID_CELLS: usize = 100;
+const CAPTION_SCALE: f32 = 18.0;
+const BACKGROUND: Rgba<u8> = Rgba([24, 24, 27, 255]);
+const CAPTION_COLOR: Rgba<u8> = Rgba([228, 228, 231, 255]);
+
+/// Fonts tried for captions, in order; the first one found is used
+const FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+];
+
+/// Summary of a composed contact sheet
+#[derive(Debug, Clone, Serialize)]
+pub struct GridSummary {
+    pub path: String,
+    pub images: usize,
+    pub columns: u32,
+    pub rows: u32,
+    pub width: u32,
+    pub height: u32,
+    /// False when no system font was found and tiles were left unlabeled
+    pub captioned: bool,
+}
+
+/// A tile of the grid and its caption; tiles without an image are left empty
+///
+/// Images are scaled to fit a tile as soon as they are decoded, so only one full-size
+/// image is held at a time.
+struct GridTile {
+    image: Option<DynamicImage>,
+    caption: String,
+}
+
+/// A cell of a grid with given captions; `None` leaves the cell empty
+#[derive(Debug, Clone)]
+pub struct GridCell {
+    pub asset_id: Option<String>,
+    pub caption: String,
+}
+
+/// Composite jobs' or scenes' images into one labeled PNG contact sheet at `output_path`
+pub async fn compose_grid(
+    pool: &SqlitePool,
+    asset_ids: &[String],
+    columns: u32,
+    output_path: &Path,
+) -> Result<GridSummary> {
+    if asset_ids.is_empty() {
+        return Err(anyhow::anyhow!("No assets selected for the grid"));
+    }
+
+    let mut cells = Vec::with_capacity(asset_ids.len());
+    for asset_id in asset_ids {
+        cells.push(GridCell {
+            asset_id: Some(asset_id.clone()),
+            caption: asset_caption(pool, asset_id).await?,
+        });
+    }
+    compose_labeled_grid(pool, &cells, columns, output_path).await
+}
+
+/// Composite cells with the given captions into one PNG at `output_path`
+pub async fn compose_labeled_grid(
+    pool: &SqlitePool,
+    cells: &[GridCell],
+    columns: u32,
+    output_path: &Path,
+) -> Result<GridSummary> {
+    if cells.is_empty() {
+        return Err(anyhow::anyhow!("No cells for the grid"));
+    }
+    if cells.len() > MAX_GRID_CELLS {
+        return Err(anyhow::anyhow!(
+            "A grid holds at most {} images, {} were selected",
+            MAX_GRID_CELLS,
+            cells.len()
+        ));
+    }
+
+    let mut sources = Vec::with_capacity(cells.len());
+    for cell in cells {
+        let path = match &cell.asset_id {
+            Some(asset_id) => Some(resolve_asset_file(pool, asset_id).await?.1),
+            None => None,
+        };
+        sources.push((path, cell.caption.clone()));
+    }
+    let images = sources.iter().filter(|(path, _)| path.is_some()).count();
+
+    let (sheet, layout, captioned) = tokio::task::spawn_blocking(move || -> Result<_> {
+        let mut tiles = Vec::with_capacity(sources.len());
+        for (path, caption) in sources {
+            let image = match path {
+                Some(path) => {
+                    Some(image::open(path)?.resize(TILE_SIZE, TILE_SIZE, FilterType::Lanczos3))
+                }
+                None => None,
+            };
+            tiles.push(GridTile { image, caption });
+        }
+        let font = load_caption_font();
+        let layout = grid_layout(tiles.len(), columns);
+        let sheet = render_grid(&tiles, layout.0, font.as_ref());
+        Ok((sheet, layout, font.is_some()))
+    })
+    .await??;
+
+    if let Some(parent) = output_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let (width, height) = sheet.dimensions();
+    let output = output_path.to_path_buf();
+    tokio::task::spawn_blocking(move || sheet.save_with_format(&output, image::ImageFormat::Png))
+        .await??;
+
+    Ok(GridSummary {
+        path: output_path.display().to_string(),
+        images,
+        columns: layout.0,
+        rows: layout.1,
+        width,
+        height,
+        captioned,
+    })
+}
+
+/// Columns and rows for `count` tiles, keeping columns within 1..=count
+fn grid_layout(count: usize, columns: u32) -> (u32, u32) {
+    let count = count.max(1) as u32;
+    let columns = columns.clamp(1, count);
+    (columns, count.div_ceil(columns))
+}
+
+/// Caption for an asset: model and seed of the generation that produced it
+async fn asset_caption(pool: &SqlitePool, asset_id: &str) -> Result<String> {
+    let (model, params, metadata) = if let Some(scene) = SceneOps::get(pool, asset_id).await? {
+        let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        let params = data.pointer("/prompt/params").cloned().unwrap_or_default();
+        (data.get("model").cloned(), params, serde_json::Value::Null)
+    } else if let Some(job) = JobOps::get(pool, asset_id).await? {
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let result: serde_json::Value = job
+            .result
+            .as_deref()
+            .and_then(|r| serde_json::from_str(r).ok())
+            .unwrap_or_default();
+        (
+            data.get("model").cloned(),
+            data.get("parameters").cloned().unwrap_or_default(),
+            result.get("metadata").cloned().unwrap_or_default(),
+        )
+    } else {
+        return Err(anyhow::anyhow!("Asset not found: {}", asset_id));
+    };
+
+    // The backend reports the seed it actually used when the request asked for a random one
+    let seed = metadata
+        .get("seed")
+        .or_else(|| params.get("seed"))
+        .filter(|seed| seed.as_i64() != Some(-1));
+
+    let mut parts = Vec::new();
+    if let Some(model) = model.as_ref().and_then(|m| m.as_str()) {
+        parts.push(model.to_string());
+    }
+    if let Some(seed) = seed {
+        parts.push(format!("seed {}", seed));
+    }
+    Ok(parts.join(" · "))
+}
+
+/// First caption font available on this system
+fn load_caption_font() -> Option<FontVec> {
+    FONT_CANDIDATES.iter().find_map(|path| {
+        let data = std::fs::read(path).ok()?;
+        FontVec::try_from_vec(data).ok()
+    })
+}
+
+/// Draw tiles left-to-right, top-to-bottom, each centered in its cell above its caption
+fn render_grid(tiles: &[GridTile], columns: u32, font: Option<&FontVec>) -> RgbaImage {
+    let (columns, rows) = grid_layout(tiles.len(), columns);
+    let caption_height = if font.is_some() { CAPTION_HEIGHT } else { 0 };
+    let cell_width = TILE_SIZE;
+    let cell_height = TILE_SIZE + caption_height;
+
+    let mut sheet = RgbaImage::from_pixel(
+        GAP + columns * (cell_width + GAP),
+        GAP + rows * (cell_height + GAP),
+        BACKGROUND,
+    );
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let index = index as u32;
+        let cell_x = GAP + (index % columns) * (cell_width + GAP);
+        let cell_y = GAP + (index / columns) * (cell_height + GAP);
+
+        if let Some(image) = &tile.image {
+            let thumb = image.to_rgba8();
+            let x = cell_x + (TILE_SIZE - thumb.width()) / 2;
+            let y = cell_y + (TILE_SIZE - thumb.height()) / 2;
+            image::imageops::overlay(&mut sheet, &thumb, x as i64, y as i64);
+        }
+
+        if let Some(font) = font {
+            let caption = fit_caption(&tile.caption, font, cell_width);
+            imageproc::drawing::draw_text_mut(
+                &mut sheet,
+                CAPTION_COLOR,
+                cell_x as i32,
+                (cell_y + TILE_SIZE + 4) as i32,
+                PxScale::from(CAPTION_SCALE),
+                font,
+                &caption,
+            );
+        }
+    }
+
+    sheet
+}
+
+/// Shorten a caption with an ellipsis until it fits `max_width` pixels
+fn fit_caption(caption: &str, font: &FontVec, max_width: u32) -> String {
+    let scale = PxScale::from(CAPTION_SCALE);
+    let mut chars: Vec<char> = caption.chars().collect();
+    let mut text = caption.to_string();
+    while !chars.is_empty() && imageproc::drawing::text_size(scale, font, &text).0 > max_width {
+        chars.pop();
+        text = format!("{}…", chars.iter().collect::<String>());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_layout_and_render() {
+        assert_eq!(grid_layout(5, 2), (2, 3));
+        assert_eq!(grid_layout(3, 10), (3, 1));
+        assert_eq!(grid_layout(4, 0), (1, 4));
+
+        let tiles: Vec<GridTile> = (0..3)
+            .map(|i| GridTile {
+                image: (i != 1).then(|| DynamicImage::new_rgba8(64, 32)),
+                caption: String::new(),
+            })
+            .collect();
+        let sheet = render_grid(&tiles, 2, None);
+        assert_eq!(
+            sheet.dimensions(),
+            (GAP + 2 * (TILE_SIZE + GAP), GAP + 2 * (TILE_SIZE + GAP))
+        );
+    }
+}
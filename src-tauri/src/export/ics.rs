@@ -0,0 +1,184 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::db::models::Job;
+use crate::db::operations::{JobOps, WorkflowOps};
+
+/// Default calendar block for a job when no duration estimate is available
+const DEFAULT_EVENT_MINUTES: i64 = 15;
+
+/// Longest calendar block for a job (one week); larger estimates are capped
+const MAX_EVENT_MINUTES: i64 = 7 * 24 * 60;
+
+/// Summary of a schedule calendar export
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleExportSummary {
+    pub path: String,
+    pub events: usize,
+}
+
+/// Export pending scheduled jobs as an iCalendar (.ics) file
+pub async fn export_schedule_ics(pool: &SqlitePool, path: &Path) -> Result<ScheduleExportSummary> {
+    let jobs = JobOps::list_scheduled(pool).await?;
+    let workflow_names: HashMap<String, String> = WorkflowOps::list(pool)
+        .await?
+        .into_iter()
+        .map(|w| (w.id, w.name))
+        .collect();
+
+    let calendar = render_calendar(&jobs, &workflow_names, Utc::now());
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, calendar).await?;
+
+    Ok(ScheduleExportSummary {
+        path: path.display().to_string(),
+        events: jobs.iter().filter(|j| scheduled_start(j).is_some()).count(),
+    })
+}
+
+fn scheduled_start(job: &Job) -> Option<DateTime<Utc>> {
+    let scheduled_at = job.scheduled_at.as_deref()?;
+    DateTime::parse_from_rfc3339(scheduled_at)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn render_calendar(jobs: &[Job], workflow_names: &HashMap<String, String>, stamp: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//PromptCraft//Scheduled Jobs//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+
+    for job in jobs {
+        let Some(start) = scheduled_start(job) else {
+            continue;
+        };
+
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or(serde_json::Value::Null);
+        let provider = data.get("provider").and_then(|v| v.as_str()).unwrap_or("unknown");
+        let model = data.get("model").and_then(|v| v.as_str()).unwrap_or("default");
+        let prompt = data.get("prompt").and_then(|v| v.as_str()).unwrap_or("");
+        let minutes = data
+            .get("estimated_minutes")
+            .and_then(|v| v.as_i64())
+            .filter(|m| *m > 0)
+            .unwrap_or(DEFAULT_EVENT_MINUTES)
+            .min(MAX_EVENT_MINUTES);
+        let end = start.checked_add_signed(Duration::minutes(minutes)).unwrap_or(start);
+        let workflow = workflow_names
+            .get(&job.workflow_id)
+            .map(|s| s.as_str())
+            .unwrap_or(&job.workflow_id);
+
+        let summary = format!("PromptCraft {}: {} ({}/{})", job.job_type, workflow, provider, model);
+        let description = format!("Job {}\nWorkflow: {}\nPrompt: {}", job.id, workflow, prompt);
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@promptcraft", job.id));
+        lines.push(format!("DTSTAMP:{}", format_ics_time(stamp)));
+        lines.push(format!("DTSTART:{}", format_ics_time(start)));
+        lines.push(format!("DTEND:{}", format_ics_time(end)));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+        lines.push("CATEGORIES:PromptCraft".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn format_ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value per RFC 5545 section 3.3.11
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets per RFC 5545 section 3.1
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if octets + len > 75 {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += len;
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_event_length_is_capped() {
+        let job = Job {
+            id: "job-1".to_string(),
+            workflow_id: "wf-1".to_string(),
+            scene_id: None,
+            job_type: "image".to_string(),
+            status: "pending".to_string(),
+            data: serde_json::json!({ "estimated_minutes": i64::MAX }).to_string(),
+            result: None,
+            error: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            started_at: None,
+            completed_at: None,
+            scheduled_at: Some("2026-01-01T09:00:00Z".to_string()),
+            app_version: None,
+            retry_of: None,
+            cost: None,
+            lane: "batch".to_string(),
+            error_kind: None,
+        };
+        let calendar = render_calendar(&[job], &HashMap::new(), Utc::now());
+        assert!(calendar.contains("DTEND:20260108T090000Z"));
+    }
+
+    #[test]
+    fn test_fold_line() {
+        let line = "X".repeat(160);
+        let folded = fold_line(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), 75);
+        assert_eq!(parts[1].len(), 75);
+        assert!(parts[1].starts_with(' '));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::local_asset_path;
+use crate::storage::paths::{self, sanitize_file_name};
+use crate::db::models::{Scene, Workflow};
+use crate::db::operations::{SceneOps, WorkflowOps};
+use crate::generation::utils::extract_base64_from_data_url;
+
+/// Summary of a Markdown vault export
+#[derive(Debug, Clone, Serialize)]
+pub struct MarkdownExportSummary {
+    pub path: String,
+    pub workflows: usize,
+    pub scenes: usize,
+    pub attachments: usize,
+}
+
+/// Export all workflows and scenes as an Obsidian-compatible Markdown vault
+///
+/// Layout:
+/// * `Workflows/<name>.md` - one note per workflow linking to its scenes
+/// * `Scenes/<workflow>/<name>.md` - one note per scene with prompt and parameters
+/// * `attachments/<scene id>.<ext>` - scene thumbnails stored locally
+pub async fn export_markdown(pool: &SqlitePool, vault_dir: &Path) -> Result<MarkdownExportSummary> {
+    let workflows = WorkflowOps::list(pool).await?;
+    let scenes = SceneOps::list_all(pool).await?;
+
+    let workflows_dir = vault_dir.join("Workflows");
+    let scenes_dir = vault_dir.join("Scenes");
+    let attachments_dir = vault_dir.join("attachments");
+    tokio::fs::create_dir_all(&workflows_dir).await?;
+    tokio::fs::create_dir_all(&scenes_dir).await?;
+    tokio::fs::create_dir_all(&attachments_dir).await?;
+
+    // Assign unique note names up front so notes can link to each other
+    let mut used_workflow_names = HashSet::new();
+    let workflow_notes: HashMap<String, String> = workflows
+        .iter()
+        .map(|w| (w.id.clone(), unique_note_name(&w.name, &w.id, &mut used_workflow_names)))
+        .collect();
+
+    let mut used_scene_names = HashSet::new();
+    let mut scene_notes: HashMap<String, String> = HashMap::new();
+    for scene in &scenes {
+        let folder = workflow_notes
+            .get(&scene.workflow_id)
+            .cloned()
+            .unwrap_or_else(|| "Unsorted".to_string());
+        let name = unique_note_name(&scene.name, &scene.id, &mut used_scene_names);
+        scene_notes.insert(scene.id.clone(), format!("{}/{}", folder, name));
+    }
+
+    let mut attachments = 0;
+
+    for scene in &scenes {
+        let note_path = &scene_notes[&scene.id];
+        let thumbnail = match &scene.thumbnail {
+            Some(thumbnail) if !thumbnail.is_empty() => {
+                export_thumbnail(thumbnail, &scene.id, &attachments_dir).await
+            }
+            _ => None,
+        };
+        if matches!(thumbnail, Some(Thumbnail::Attachment(_))) {
+            attachments += 1;
+        }
+
+        let note = render_scene_note(
+            scene,
+            workflow_notes.get(&scene.workflow_id).map(|s| s.as_str()),
+            thumbnail.as_ref(),
+        );
+
+        // Note names are already sanitized; the folder is the workflow's note name
+        let (folder, name) = note_path.split_once('/').unwrap_or(("Unsorted", note_path));
+        let folder_dir = scenes_dir.join(folder);
+        tokio::fs::create_dir_all(&folder_dir).await?;
+        let file_path = paths::join_file(&folder_dir, &format!("{}.md", name))?;
+        paths::write_atomic(&file_path, note.as_bytes()).await?;
+    }
+
+    for workflow in &workflows {
+        let workflow_scenes: Vec<&String> = scenes
+            .iter()
+            .filter(|s| s.workflow_id == workflow.id)
+            .map(|s| &scene_notes[&s.id])
+            .collect();
+
+        let note = render_workflow_note(workflow, &workflow_scenes);
+        let file_path = paths::join_file(&workflows_dir, &format!("{}.md", workflow_notes[&workflow.id]))?;
+        paths::write_atomic(&file_path, note.as_bytes()).await?;
+    }
+
+    Ok(MarkdownExportSummary {
+        path: vault_dir.display().to_string(),
+        workflows: workflows.len(),
+        scenes: scenes.len(),
+        attachments,
+    })
+}
+
+/// How a scene thumbnail is referenced from its note
+enum Thumbnail {
+    /// File copied into the vault's attachments folder
+    Attachment(String),
+    /// Remote URL embedded as-is
+    Remote(String),
+}
+
+/// Copy or decode a scene thumbnail into the vault
+async fn export_thumbnail(thumbnail: &str, scene_id: &str, attachments_dir: &Path) -> Option<Thumbnail> {
+    if thumbnail.starts_with("http://") || thumbnail.starts_with("https://") {
+        return Some(Thumbnail::Remote(thumbnail.to_string()));
+    }
+
+    if thumbnail.starts_with("data:") {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let (mime, data) = extract_base64_from_data_url(thumbnail).ok()?;
+        let bytes = general_purpose::STANDARD.decode(data.trim()).ok()?;
+        let extension = mime.rsplit('/').next().unwrap_or("png").replace("jpeg", "jpg");
+        let file_name = format!("{}.{}", scene_id, extension);
+
+        if let Err(e) = tokio::fs::write(attachments_dir.join(&file_name), bytes).await {
+            log_warn!("Warning: Failed to write thumbnail for scene {}: {}", scene_id, e);
+            return None;
+        }
+        return Some(Thumbnail::Attachment(file_name));
+    }
+
+    let source = local_asset_path(thumbnail)?;
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png")
+        .to_string();
+    let file_name = format!("{}.{}", scene_id, extension);
+
+    match tokio::fs::copy(&source, attachments_dir.join(&file_name)).await {
+        Ok(_) => Some(Thumbnail::Attachment(file_name)),
+        Err(e) => {
+            log_warn!("Warning: Failed to copy thumbnail for scene {}: {}", scene_id, e);
+            None
+        }
+    }
+}
+
+/// Pick a sanitized note name, disambiguating duplicates with the record id
+fn unique_note_name(name: &str, id: &str, used: &mut HashSet<String>) -> String {
+    let base = sanitize_file_name(name);
+    let candidate = if used.contains(&base.to_lowercase()) {
+        format!("{} ({})", base, id.chars().take(8).collect::<String>())
+    } else {
+        base
+    };
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+/// Extract the main prompt text from scene data (`prompt` string or `prompt.main`)
+fn prompt_text(data: &serde_json::Value) -> Option<&str> {
+    let prompt = data.get("prompt")?;
+    prompt
+        .as_str()
+        .or_else(|| prompt.get("main").and_then(|v| v.as_str()))
+}
+
+fn render_scene_note(scene: &Scene, workflow_note: Option<&str>, thumbnail: Option<&Thumbnail>) -> String {
+    let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or(serde_json::Value::Null);
+    let provider = data.get("provider").and_then(|v| v.as_str());
+    let model = data.get("model").and_then(|v| v.as_str());
+    let category = data.get("category").and_then(|v| v.as_str());
+
+    let mut note = String::from("---\n");
+    note.push_str(&yaml_field("id", &scene.id));
+    note.push_str(&yaml_field("type", "scene"));
+    note.push_str(&yaml_field("workflow_id", &scene.workflow_id));
+    if let Some(provider) = provider {
+        note.push_str(&yaml_field("provider", provider));
+    }
+    if let Some(model) = model {
+        note.push_str(&yaml_field("model", model));
+    }
+    if let Some(category) = category {
+        note.push_str(&yaml_field("category", category));
+    }
+    note.push_str(&yaml_field("created", &scene.created_at));
+    note.push_str("tags:\n  - promptcraft/scene\n");
+    note.push_str("---\n\n");
+
+    note.push_str(&format!("# {}\n\n", scene.name));
+
+    if let Some(workflow_note) = workflow_note {
+        note.push_str(&format!("Workflow: [[Workflows/{}|{}]]\n\n", workflow_note, workflow_note));
+    }
+
+    match thumbnail {
+        Some(Thumbnail::Attachment(file_name)) => {
+            note.push_str(&format!("![[attachments/{}]]\n\n", file_name));
+        }
+        Some(Thumbnail::Remote(url)) => {
+            note.push_str(&format!("![thumbnail]({})\n\n", url));
+        }
+        None => {}
+    }
+
+    if let Some(prompt) = prompt_text(&data) {
+        note.push_str("## Prompt\n\n");
+        note.push_str(prompt.trim());
+        note.push_str("\n\n");
+    }
+
+    let params = data
+        .get("prompt")
+        .and_then(|p| p.get("params"))
+        .or_else(|| data.get("parameters"));
+    if let Some(params) = params.filter(|p| !p.is_null()) {
+        note.push_str("## Parameters\n\n");
+        note.push_str(&json_block(params));
+    }
+
+    note.push_str("## Scene Data\n\n");
+    note.push_str(&json_block(&data));
+
+    note
+}
+
+fn render_workflow_note(workflow: &Workflow, scene_notes: &[&String]) -> String {
+    let data: serde_json::Value = serde_json::from_str(&workflow.data).unwrap_or(serde_json::Value::Null);
+
+    let mut note = String::from("---\n");
+    note.push_str(&yaml_field("id", &workflow.id));
+    note.push_str(&yaml_field("type", "workflow"));
+    note.push_str(&yaml_field("workflow_type", &workflow.workflow_type));
+    note.push_str(&yaml_field("created", &workflow.created_at));
+    note.push_str(&yaml_field("updated", &workflow.updated_at));
+    note.push_str("tags:\n  - promptcraft/workflow\n");
+    note.push_str("---\n\n");
+
+    note.push_str(&format!("# {}\n\n", workflow.name));
+
+    if let Some(prompt) = prompt_text(&data) {
+        note.push_str("## Prompt\n\n");
+        note.push_str(prompt.trim());
+        note.push_str("\n\n");
+    }
+
+    note.push_str("## Scenes\n\n");
+    if scene_notes.is_empty() {
+        note.push_str("_No scenes yet._\n\n");
+    } else {
+        for scene_note in scene_notes {
+            let title = scene_note.rsplit('/').next().unwrap_or(scene_note);
+            note.push_str(&format!("- [[Scenes/{}|{}]]\n", scene_note, title));
+        }
+        note.push('\n');
+    }
+
+    note.push_str("## Workflow Data\n\n");
+    note.push_str(&json_block(&data));
+
+    note
+}
+
+/// Render a YAML frontmatter line; JSON string escaping is valid YAML
+fn yaml_field(key: &str, value: &str) -> String {
+    format!(
+        "{}: {}\n",
+        key,
+        serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+    )
+}
+
+fn json_block(value: &serde_json::Value) -> String {
+    format!(
+        "```json\n{}\n```\n\n",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    )
+}
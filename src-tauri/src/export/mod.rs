@@ -0,0 +1,110 @@
+use anyhow::Result;
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+use crate::db::operations::{JobOps, SceneOps};
+use crate::generation::GenerationResult;
+
+pub mod archive;
+pub mod c2pa;
+pub mod grid;
+pub mod ics;
+pub mod markdown;
+pub mod settings;
+pub mod social;
+pub mod usage;
+pub mod watermark;
+
+/// Resolve a stored asset reference (asset protocol URL or plain path) to a local file path
+pub fn local_asset_path(reference: &str) -> Option<PathBuf> {
+    local_asset_reference(reference).filter(|path| path.is_file())
+}
+
+/// Local path an asset reference points at, whether or not the file still exists;
+/// `None` for data URLs and remote URLs
+pub fn local_asset_reference(reference: &str) -> Option<PathBuf> {
+    let path = reference
+        .strip_prefix("asset://localhost/")
+        .or_else(|| reference.strip_prefix("file://"))
+        .unwrap_or(reference);
+
+    if path.starts_with("http://") || path.starts_with("https://") || path.starts_with("data:") {
+        return None;
+    }
+
+    Some(PathBuf::from(path))
+}
+
+/// Find the local image file behind an asset, returning a display name and the path
+///
+/// `asset_id` may be a scene (its thumbnail, imported `filePath`, or source job's
+/// output) or a job (its output file).
+pub async fn resolve_asset_file(pool: &SqlitePool, asset_id: &str) -> Result<(String, PathBuf)> {
+    if let Some(scene) = SceneOps::get(pool, asset_id).await? {
+        let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        let metadata = data.get("metadata");
+
+        let mut references: Vec<String> = scene.thumbnail.into_iter().collect();
+        if let Some(path) = metadata.and_then(|m| m.get("filePath")).and_then(|v| v.as_str()) {
+            references.push(path.to_string());
+        }
+        if let Some(job_id) = metadata.and_then(|m| m.get("jobId")).and_then(|v| v.as_str()) {
+            if let Some(job) = JobOps::get(pool, job_id).await? {
+                references.extend(job_output_references(&job));
+            }
+        }
+
+        return references
+            .iter()
+            .find_map(|r| local_asset_path(r))
+            .map(|path| (scene.name, path))
+            .ok_or_else(|| anyhow::anyhow!("Scene {} has no local image file", asset_id));
+    }
+
+    if let Some(job) = JobOps::get(pool, asset_id).await? {
+        return job_output_references(&job)
+            .iter()
+            .find_map(|r| local_asset_path(r))
+            .map(|path| (format!("job-{}", job.id), path))
+            .ok_or_else(|| anyhow::anyhow!("Job {} has no local output file", asset_id));
+    }
+
+    Err(anyhow::anyhow!("Asset not found: {}", asset_id))
+}
+
+/// Output file references stored in a job's result
+fn job_output_references(job: &crate::db::models::Job) -> Vec<String> {
+    let result: Option<GenerationResult> = job
+        .result
+        .as_deref()
+        .and_then(|r| serde_json::from_str(r).ok());
+
+    result
+        .into_iter()
+        .flat_map(|result| result.artifacts)
+        .flat_map(|artifact| [artifact.file_path, artifact.url])
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_asset_path_rejects_remote() {
+        assert!(local_asset_path("https://example.com/a.png").is_none());
+        assert!(local_asset_path("data:image/png;base64,AAAA").is_none());
+        assert!(local_asset_path("asset://localhost//definitely/missing.png").is_none());
+    }
+
+    #[test]
+    fn test_local_asset_reference_keeps_missing_files() {
+        assert_eq!(
+            local_asset_reference("asset://localhost//home/me/Pictures/Promptcraft/gen.png"),
+            Some(PathBuf::from("/home/me/Pictures/Promptcraft/gen.png"))
+        );
+        assert!(local_asset_reference("data:image/png;base64,AAAA").is_none());
+        assert!(local_asset_reference("https://cdn.example.com/a.png").is_none());
+    }
+}
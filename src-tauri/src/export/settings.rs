@@ -0,0 +1,437 @@
+//! Settings bundles for setting up another machine from one file.
+//!
+//! A bundle holds the settings table, parameter presets and smart collections. API keys
+//! and the settings that may carry credentials (provider headers, the OpenAI-compatible
+//! endpoint, storage, C2PA signing) are only included when a passphrase is given,
+//! encrypted with ChaCha20-Poly1305 under a key derived from it; without one they are
+//! left out.
+//!
+//! Programs the app runs are never taken from a bundle: plugins have to be registered
+//! again and C2PA signing keeps this machine's `c2patool`.
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::ChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::db::models::{now, CreateParameterPresetInput, SmartCollectionInput, APP_VERSION};
+use crate::db::operations::{ParameterPresetOps, SettingsOps, SmartCollectionOps};
+use crate::export::c2pa::{self, C2paSettings, C2PA_SECRET, C2PA_SETTINGS_KEY};
+use crate::generation::headers::{self, HeaderSettings, HEADERS_SETTINGS_KEY};
+use crate::generation::keys;
+use crate::generation::providers::external::EXTERNAL_PROVIDERS_SETTINGS_KEY;
+use crate::generation::providers::openai_compatible::OPENAI_COMPATIBLE_SETTINGS_KEY;
+use crate::guest::GUEST_MODE_SETTINGS_KEY;
+use crate::secrets;
+use crate::storage::{S3_SECRET, STORAGE_SETTINGS_KEY};
+
+/// `format` of every settings bundle
+pub const BUNDLE_FORMAT: &str = "promptcraft-settings";
+
+/// Newest bundle version this build reads and the one it writes
+const BUNDLE_VERSION: u32 = 1;
+
+/// PBKDF2 rounds for newly exported bundles
+const KDF_ITERATIONS: u32 = 600_000;
+
+/// PBKDF2 rounds an imported bundle may ask for; fewer is too weak, more stalls the import
+const KDF_ITERATION_RANGE: std::ops::RangeInclusive<u32> = 10_000..=10_000_000;
+
+/// Settings that belong to this machine rather than the user's configuration
+///
+/// Plugin commands are among them: importing them would run a shared file's programs.
+const LOCAL_SETTINGS: &[&str] = &[GUEST_MODE_SETTINGS_KEY, EXTERNAL_PROVIDERS_SETTINGS_KEY];
+
+/// Settings whose values may contain credentials, exported only with the API keys
+const SECRET_SETTINGS: &[&str] = &[
+    HEADERS_SETTINGS_KEY,
+    OPENAI_COMPATIBLE_SETTINGS_KEY,
+    STORAGE_SETTINGS_KEY,
+    C2PA_SETTINGS_KEY,
+];
+
+/// Credential store entry of the OpenAI-compatible provider's key
+const OPENAI_COMPATIBLE_SECRET: &str = "openai_compatible";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub exported_at: String,
+    pub settings: BTreeMap<String, Value>,
+    pub parameter_presets: Vec<CreateParameterPresetInput>,
+    pub smart_collections: Vec<SmartCollectionInput>,
+    /// Encrypted [`BundleSecrets`], present when exported with a passphrase
+    pub secrets: Option<EncryptedSecrets>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedSecrets {
+    /// Base64 PBKDF2 salt
+    pub salt: String,
+    pub iterations: u32,
+    /// Base64 nonce and ciphertext
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BundleSecrets {
+    /// API keys by credential store entry
+    api_keys: BTreeMap<String, String>,
+    settings: BTreeMap<String, Value>,
+}
+
+/// Summary of a settings export
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsExportSummary {
+    pub path: String,
+    pub settings: usize,
+    pub parameter_presets: usize,
+    pub smart_collections: usize,
+    /// API keys in the encrypted section; 0 without a passphrase
+    pub api_keys: usize,
+}
+
+/// Summary of a settings import
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsImportSummary {
+    pub settings: usize,
+    pub parameter_presets: usize,
+    pub smart_collections: usize,
+    /// Collections not imported because one with the same name exists
+    pub skipped_collections: Vec<String>,
+    pub api_keys: usize,
+    /// The bundle has encrypted secrets but no passphrase was given
+    pub secrets_skipped: bool,
+    /// Provider and app settings are read at startup; API keys apply immediately
+    pub restart_required: bool,
+}
+
+/// Credential store entries of every saved API key
+async fn secret_names(pool: &SqlitePool) -> Result<Vec<String>> {
+    let mut names = vec![
+        OPENAI_COMPATIBLE_SECRET.to_string(),
+        S3_SECRET.to_string(),
+        C2PA_SECRET.to_string(),
+    ];
+    let header_settings = headers::load_names(pool).await?;
+    names.extend(header_settings.providers.keys().map(|provider| headers::secret_name(provider)));
+    for provider in secrets::API_KEY_PROVIDERS {
+        let provider_keys = keys::provider_keys(pool, provider).await?;
+        names.extend(
+            provider_keys
+                .keys
+                .iter()
+                .map(|info| keys::secret_name(provider, &info.id)),
+        );
+    }
+    Ok(names)
+}
+
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Result<ChaCha20Poly1305> {
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("The passphrase must not be empty"));
+    }
+    Ok(ChaCha20Poly1305::new(&secrets::passphrase_key(passphrase, salt, iterations)))
+}
+
+/// Write the settings bundle to `path`, with API keys if a passphrase is given
+pub async fn export_settings(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<SettingsExportSummary> {
+    let mut settings = BTreeMap::new();
+    let mut secret = BundleSecrets::default();
+    for (key, value) in SettingsOps::list(pool).await? {
+        if LOCAL_SETTINGS.contains(&key.as_str()) {
+            continue;
+        }
+        let value: Value = serde_json::from_str(&value)?;
+        if SECRET_SETTINGS.contains(&key.as_str()) {
+            secret.settings.insert(key, value);
+        } else {
+            settings.insert(key, value);
+        }
+    }
+
+    let parameter_presets = ParameterPresetOps::list(pool)
+        .await?
+        .into_iter()
+        .map(|preset| {
+            Ok(CreateParameterPresetInput {
+                provider: preset.provider,
+                model: preset.model,
+                parameters: serde_json::from_str(&preset.parameters)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let smart_collections = SmartCollectionOps::list(pool)
+        .await?
+        .into_iter()
+        .map(|collection| {
+            Ok(SmartCollectionInput {
+                name: collection.name,
+                filter: serde_json::from_str(&collection.filter)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let encrypted = match passphrase {
+        Some(passphrase) => {
+            // Credential store calls can block (Secret Service goes over D-Bus)
+            let names = secret_names(pool).await?;
+            let dir = data_dir.to_path_buf();
+            secret.api_keys = tokio::task::spawn_blocking(move || {
+                names
+                    .into_iter()
+                    .filter_map(|name| {
+                        let key = secrets::load_api_key(&dir, &name).ok().flatten()?;
+                        Some((name, key))
+                    })
+                    .collect()
+            })
+            .await?;
+
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            // Hundreds of thousands of PBKDF2 rounds would hold up an async worker
+            let passphrase = passphrase.to_string();
+            let cipher =
+                tokio::task::spawn_blocking(move || cipher(&passphrase, &salt, KDF_ITERATIONS))
+                    .await??;
+            Some(EncryptedSecrets {
+                salt: general_purpose::STANDARD.encode(salt),
+                iterations: KDF_ITERATIONS,
+                data: secrets::encrypt(&cipher, &serde_json::to_vec(&secret)?)?,
+            })
+        }
+        None => None,
+    };
+
+    let summary = SettingsExportSummary {
+        path: path.display().to_string(),
+        settings: settings.len() + encrypted.as_ref().map_or(0, |_| secret.settings.len()),
+        parameter_presets: parameter_presets.len(),
+        smart_collections: smart_collections.len(),
+        api_keys: encrypted.as_ref().map_or(0, |_| secret.api_keys.len()),
+    };
+    let bundle = SettingsBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        app_version: APP_VERSION.to_string(),
+        exported_at: now(),
+        settings,
+        parameter_presets,
+        smart_collections,
+        secrets: encrypted,
+    };
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, serde_json::to_vec_pretty(&bundle)?).await?;
+    Ok(summary)
+}
+
+/// Read a bundle and its secrets, failing before anything is applied if either is unusable
+fn open_bundle(
+    contents: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(SettingsBundle, Option<BundleSecrets>)> {
+    let bundle: SettingsBundle = serde_json::from_slice(contents)?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(anyhow::anyhow!("Not a PromptCraft settings file"));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(anyhow::anyhow!(
+            "The settings file is from a newer version ({}); update the app to import it",
+            bundle.app_version
+        ));
+    }
+
+    let secrets = match (&bundle.secrets, passphrase) {
+        (Some(encrypted), Some(passphrase)) => {
+            if !KDF_ITERATION_RANGE.contains(&encrypted.iterations) {
+                return Err(anyhow::anyhow!(
+                    "The settings file asks for {} key derivation rounds; expected {} to {}",
+                    encrypted.iterations,
+                    KDF_ITERATION_RANGE.start(),
+                    KDF_ITERATION_RANGE.end()
+                ));
+            }
+            let salt = general_purpose::STANDARD.decode(&encrypted.salt)?;
+            let cipher = cipher(passphrase, &salt, encrypted.iterations)?;
+            let plaintext = secrets::decrypt(&cipher, &encrypted.data)
+                .map_err(|_| anyhow::anyhow!("Wrong passphrase for this settings file"))?;
+            Some(serde_json::from_slice(&plaintext)?)
+        }
+        _ => None,
+    };
+    Ok((bundle, secrets))
+}
+
+/// Save imported provider headers, keeping their values in the credential store
+///
+/// Values come with the API keys; bundles from older versions have them in the settings.
+async fn import_headers(pool: &SqlitePool, data_dir: &Path, value: &Value) -> Result<()> {
+    let settings: HeaderSettings = serde_json::from_value(value.clone())?;
+    for (provider, headers) in &settings.providers {
+        headers::save(pool, data_dir, provider, headers).await?;
+    }
+    Ok(())
+}
+
+/// Save imported C2PA settings with the `c2patool` already configured here
+///
+/// A signing key in settings from older bundles goes to the credential store.
+async fn import_c2pa(pool: &SqlitePool, data_dir: &Path, value: &Value) -> Result<()> {
+    let current: C2paSettings = SettingsOps::get_or_default(pool, C2PA_SETTINGS_KEY).await?;
+    let mut settings: C2paSettings = serde_json::from_value(value.clone())?;
+    settings.tool_path = current.tool_path;
+    c2pa::save_settings(pool, data_dir, &settings).await
+}
+
+/// Apply a settings bundle, replacing settings and presets with the same key
+pub async fn import_settings(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<SettingsImportSummary> {
+    let contents = tokio::fs::read(path).await?;
+    // Key derivation runs off the async workers, as on export
+    let owned_passphrase = passphrase.map(str::to_string);
+    let (bundle, secret) =
+        tokio::task::spawn_blocking(move || open_bundle(&contents, owned_passphrase.as_deref()))
+            .await??;
+
+    let mut settings = bundle.settings;
+    if let Some(secret) = &secret {
+        settings.extend(secret.settings.clone());
+    }
+    for (key, value) in &settings {
+        if LOCAL_SETTINGS.contains(&key.as_str()) {
+            continue;
+        }
+        if key == C2PA_SETTINGS_KEY {
+            import_c2pa(pool, data_dir, value).await?;
+        } else if key == HEADERS_SETTINGS_KEY {
+            import_headers(pool, data_dir, value).await?;
+        } else {
+            SettingsOps::set(pool, key, value).await?;
+        }
+    }
+
+    for preset in &bundle.parameter_presets {
+        match ParameterPresetOps::find(pool, &preset.provider, preset.model.as_deref()).await? {
+            Some(existing) => {
+                ParameterPresetOps::update(pool, &existing.id, &preset.parameters).await?;
+            }
+            None => {
+                ParameterPresetOps::create(pool, preset.clone()).await?;
+            }
+        }
+    }
+
+    let existing = SmartCollectionOps::list(pool).await?;
+    let mut smart_collections = 0;
+    let mut skipped_collections = Vec::new();
+    for collection in bundle.smart_collections {
+        let name = collection.name.trim();
+        if existing.iter().any(|c| c.name.eq_ignore_ascii_case(name)) {
+            skipped_collections.push(name.to_string());
+            continue;
+        }
+        SmartCollectionOps::create(pool, collection).await?;
+        smart_collections += 1;
+    }
+
+    let api_keys = secret.map(|secret| secret.api_keys).unwrap_or_default();
+    let imported_keys = api_keys.len();
+    let dir = data_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        for (name, key) in &api_keys {
+            secrets::store_api_key(&dir, name, key)?;
+        }
+        Ok(())
+    })
+    .await??;
+
+    log_info!(
+        "[Settings] Imported {} settings and {} API keys from {}",
+        settings.len(),
+        imported_keys,
+        path.display()
+    );
+    Ok(SettingsImportSummary {
+        settings: settings.len(),
+        parameter_presets: bundle.parameter_presets.len(),
+        smart_collections,
+        skipped_collections,
+        api_keys: imported_keys,
+        secrets_skipped: bundle.secrets.is_some() && passphrase.is_none(),
+        restart_required: !settings.is_empty(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(passphrase: Option<&str>, iterations: u32) -> Vec<u8> {
+        let secret = BundleSecrets {
+            api_keys: BTreeMap::from([("openai".to_string(), "sk-test".to_string())]),
+            settings: BTreeMap::new(),
+        };
+        let encrypted = passphrase.map(|passphrase| {
+            let cipher = cipher(passphrase, b"salt", iterations).unwrap();
+            EncryptedSecrets {
+                salt: general_purpose::STANDARD.encode(b"salt"),
+                iterations,
+                data: secrets::encrypt(&cipher, &serde_json::to_vec(&secret).unwrap()).unwrap(),
+            }
+        });
+        let bundle = SettingsBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            app_version: APP_VERSION.to_string(),
+            exported_at: now(),
+            settings: BTreeMap::from([("locale".to_string(), serde_json::json!("de"))]),
+            parameter_presets: Vec::new(),
+            smart_collections: Vec::new(),
+            secrets: encrypted,
+        };
+        serde_json::to_vec(&bundle).unwrap()
+    }
+
+    #[test]
+    fn test_open_bundle() {
+        let contents = bundle(Some("correct horse"), *KDF_ITERATION_RANGE.start());
+        let (_, secret) = open_bundle(&contents, Some("correct horse")).unwrap();
+        assert_eq!(secret.unwrap().api_keys["openai"], "sk-test");
+        assert!(open_bundle(&contents, Some("wrong")).is_err());
+
+        // Without a passphrase the rest of the bundle still imports
+        let (opened, secret) = open_bundle(&contents, None).unwrap();
+        assert!(secret.is_none());
+        assert_eq!(opened.settings["locale"], "de");
+
+        let text = String::from_utf8(contents).unwrap();
+        assert!(!text.contains("sk-test"));
+        let other = text.replace(BUNDLE_FORMAT, "other-app");
+        assert!(open_bundle(other.as_bytes(), None).is_err());
+
+        // Round counts from the file are not trusted
+        let weak = bundle(Some("correct horse"), 2);
+        assert!(open_bundle(&weak, Some("correct horse")).is_err());
+    }
+}
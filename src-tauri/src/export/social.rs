@@ -0,0 +1,213 @@
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use super::archive::manifest_entry;
+use super::{c2pa, resolve_asset_file, watermark};
+use crate::storage::paths;
+
+/// Output format and limits of a social media target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SocialTarget {
+    pub platform: &'static str,
+    pub width: u32,
+    pub height: u32,
+    /// Upload limit in bytes; JPEG quality is lowered until the file fits
+    pub max_bytes: usize,
+}
+
+/// Supported targets, matching each platform's recommended upload size
+pub const SOCIAL_TARGETS: &[SocialTarget] = &[
+    SocialTarget {
+        platform: "x",
+        width: 1600,
+        height: 900,
+        max_bytes: 5 * 1024 * 1024,
+    },
+    SocialTarget {
+        platform: "x_square",
+        width: 1080,
+        height: 1080,
+        max_bytes: 5 * 1024 * 1024,
+    },
+    SocialTarget {
+        platform: "instagram",
+        width: 1080,
+        height: 1080,
+        max_bytes: 8 * 1024 * 1024,
+    },
+    SocialTarget {
+        platform: "instagram_portrait",
+        width: 1080,
+        height: 1350,
+        max_bytes: 8 * 1024 * 1024,
+    },
+    SocialTarget {
+        platform: "instagram_story",
+        width: 1080,
+        height: 1920,
+        max_bytes: 8 * 1024 * 1024,
+    },
+];
+
+const JPEG_QUALITIES: [u8; 5] = [92, 85, 78, 70, 60];
+
+/// Summary of a social export
+#[derive(Debug, Clone, Serialize)]
+pub struct SocialExportSummary {
+    pub path: String,
+    pub platform: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+    /// Whether Content Credentials were embedded
+    pub signed: bool,
+}
+
+/// Look up a target by platform name (`twitter` is accepted for `x`)
+pub fn social_target(platform: &str) -> Option<SocialTarget> {
+    let platform = match platform {
+        "twitter" => "x",
+        other => other,
+    };
+    SOCIAL_TARGETS
+        .iter()
+        .copied()
+        .find(|t| t.platform == platform)
+}
+
+/// Write a ready-to-post copy of an asset for a social platform into `output_dir`
+///
+/// The image is center-cropped to the platform's aspect ratio, resized, and
+/// re-encoded as JPEG; re-encoding drops EXIF, XMP and PNG text chunks, so
+/// prompts and generation parameters embedded by local backends are not published.
+pub async fn export_social(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    asset_id: &str,
+    platform: &str,
+    output_dir: &Path,
+) -> Result<SocialExportSummary> {
+    let target = social_target(platform).ok_or_else(|| {
+        let known: Vec<&str> = SOCIAL_TARGETS.iter().map(|t| t.platform).collect();
+        anyhow::anyhow!(
+            "Unknown platform: {} (expected one of {})",
+            platform,
+            known.join(", ")
+        )
+    })?;
+
+    let (name, source) = resolve_asset_file(pool, asset_id).await?;
+    let bytes = tokio::fs::read(&source).await?;
+    let watermark_tag = watermark::enabled_tag(pool).await?;
+
+    let encoded = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let image = image::load_from_memory(&bytes)?;
+        render_for_target(&image, &target, watermark_tag.as_deref())
+    })
+    .await??;
+
+    tokio::fs::create_dir_all(output_dir).await?;
+    let file_path = paths::join_file(output_dir, &format!("{}-{}.jpg", name, target.platform))?;
+    paths::write_atomic(&file_path, &encoded).await?;
+
+    let signed = match c2pa::enabled_settings(pool, data_dir).await? {
+        Some(settings) => sign_in_place(pool, &settings, asset_id, &file_path).await,
+        None => false,
+    };
+    let bytes = tokio::fs::metadata(&file_path).await?.len() as usize;
+
+    Ok(SocialExportSummary {
+        path: file_path.display().to_string(),
+        platform: target.platform.to_string(),
+        width: target.width,
+        height: target.height,
+        bytes,
+        signed,
+    })
+}
+
+/// Replace an exported file with a signed copy; the unsigned file is kept on failure
+async fn sign_in_place(
+    pool: &SqlitePool,
+    settings: &c2pa::C2paSettings,
+    asset_id: &str,
+    file_path: &Path,
+) -> bool {
+    let signed_path = file_path.with_extension("signed.jpg");
+    let result = async {
+        let mut entry = manifest_entry(pool, asset_id).await?;
+        entry.file = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        c2pa::sign_file(settings, &entry, file_path, &signed_path).await?;
+        tokio::fs::rename(&signed_path, file_path).await?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&signed_path).await;
+            log_warn!("[Export] Content Credentials not added to {}: {}", file_path.display(), e);
+            false
+        }
+    }
+}
+
+/// Crop, resize and encode an image for a target, staying under its size limit
+///
+/// The watermark goes in after resizing so the platform's own re-encode is all it
+/// has to survive.
+fn render_for_target(
+    image: &DynamicImage,
+    target: &SocialTarget,
+    watermark_tag: Option<&str>,
+) -> Result<Vec<u8>> {
+    // JPEG has no alpha channel, so flatten before encoding
+    let mut resized = image
+        .resize_to_fill(target.width, target.height, FilterType::Lanczos3)
+        .to_rgb8();
+    if let Some(tag) = watermark_tag {
+        watermark::embed(&mut resized, tag)?;
+    }
+
+    let mut encoded = Vec::new();
+    for quality in JPEG_QUALITIES {
+        encoded.clear();
+        JpegEncoder::new_with_quality(&mut encoded, quality).encode_image(&resized)?;
+        if encoded.len() <= target.max_bytes {
+            return Ok(encoded);
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Could not fit the image into {} bytes for {}",
+        target.max_bytes,
+        target.platform
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_crops_to_target() {
+        let image = DynamicImage::new_rgba8(300, 100);
+        let target = social_target("instagram_portrait").unwrap();
+
+        let encoded = render_for_target(&image, &target, None).unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1080, 1350));
+
+        assert_eq!(social_target("twitter").map(|t| t.platform), Some("x"));
+        assert!(social_target("myspace").is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::Path;
+
+use crate::db::models::UsageRecord;
+use crate::db::operations::UsageOps;
+
+const CSV_HEADER: &str =
+    "created_at,job_id,provider,model,input_tokens,output_tokens,images,video_seconds,cost_usd,\
+     api_key_id";
+
+/// Summary of a usage CSV export
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageExportSummary {
+    pub path: String,
+    pub records: usize,
+}
+
+/// Export usage records as CSV, optionally limited to a time range
+pub async fn export_usage_csv(
+    pool: &SqlitePool,
+    path: &Path,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<UsageExportSummary> {
+    let records = UsageOps::list(pool, since, until).await?;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, render_csv(&records)).await?;
+
+    Ok(UsageExportSummary {
+        path: path.display().to_string(),
+        records: records.len(),
+    })
+}
+
+fn render_csv(records: &[UsageRecord]) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for record in records {
+        let row = [
+            csv_field(&record.created_at),
+            csv_field(&record.job_id),
+            csv_field(&record.provider),
+            csv_field(&record.model),
+            optional(record.input_tokens.map(|t| t.to_string())),
+            optional(record.output_tokens.map(|t| t.to_string())),
+            record.images.to_string(),
+            record.video_seconds.to_string(),
+            optional(record.cost.map(|c| format!("{:.4}", c))),
+            optional(record.api_key_id.as_deref().map(csv_field)),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote a field if it contains a delimiter, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("gpt-image-1"), "gpt-image-1");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
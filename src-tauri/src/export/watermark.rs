@@ -0,0 +1,212 @@
+//! Invisible watermark for exported images.
+//!
+//! A 64-bit payload (a fixed marker plus a hash of the studio's tag) is written by
+//! nudging the mean brightness of 8x8 blocks onto one of two interleaved lattices
+//! (quantization index modulation). The payload repeats across the whole image and
+//! is read back by majority vote, so it survives JPEG re-encoding and small edits,
+//! but not resizing or cropping that shifts the block grid.
+
+use anyhow::Result;
+use image::{Rgb, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::db::operations::SettingsOps;
+
+/// Settings key for export watermarking
+pub const WATERMARK_SETTINGS_KEY: &str = "watermark_settings";
+
+/// Marker in the first half of the payload ("PCWM")
+const MARKER: u32 = 0x5043_574D;
+const PAYLOAD_BITS: usize = 64;
+const BLOCK_SIZE: u32 = 8;
+/// Lattice spacing in brightness levels; blocks move by at most half of it
+const STEP: f32 = 8.0;
+
+/// Watermark configuration, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkSettings {
+    pub enabled: bool,
+    /// Studio identifier; only its hash is embedded
+    pub tag: String,
+}
+
+/// Outcome of reading a watermark from an image
+#[derive(Debug, Clone, Serialize)]
+pub struct WatermarkReport {
+    /// Whether a PromptCraft watermark was found
+    pub detected: bool,
+    /// Whether it carries the configured tag
+    pub matches_tag: bool,
+    /// Average share of blocks agreeing with each decoded bit (0.5 is noise)
+    pub confidence: f32,
+}
+
+/// Tag to embed in exports, or `None` when watermarking is turned off
+pub async fn enabled_tag(pool: &SqlitePool) -> Result<Option<String>> {
+    let settings: WatermarkSettings =
+        SettingsOps::get_or_default(pool, WATERMARK_SETTINGS_KEY).await?;
+    Ok(settings.enabled.then_some(settings.tag))
+}
+
+/// Embed the watermark for `tag` into an image in place
+pub fn embed(image: &mut RgbImage, tag: &str) -> Result<()> {
+    let bits = payload(tag);
+    let blocks = block_count(image)?;
+
+    for (index, (bx, by)) in blocks.enumerate() {
+        let mean = block_mean(image, bx, by);
+        let delta = quantize(mean, bits[index % PAYLOAD_BITS]) - mean;
+
+        // Equal shifts of R, G and B move luma by exactly `delta`
+        for y in by * BLOCK_SIZE..(by + 1) * BLOCK_SIZE {
+            for x in bx * BLOCK_SIZE..(bx + 1) * BLOCK_SIZE {
+                let pixel = image.get_pixel_mut(x, y);
+                for channel in pixel.0.iter_mut() {
+                    *channel = (*channel as f32 + delta).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Embed the watermark into an image with transparency, leaving the alpha channel as is
+pub fn embed_keeping_alpha(image: &mut RgbaImage, tag: &str) -> Result<()> {
+    let mut rgb = RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        Rgb([r, g, b])
+    });
+    embed(&mut rgb, tag)?;
+    for (pixel, marked) in image.pixels_mut().zip(rgb.pixels()) {
+        pixel.0[..3].copy_from_slice(&marked.0);
+    }
+    Ok(())
+}
+
+/// Read the watermark from an image, comparing it against `tag` if given
+pub fn detect(image: &RgbImage, tag: Option<&str>) -> Result<WatermarkReport> {
+    let mut votes = [0i32; PAYLOAD_BITS];
+    let mut counts = [0i32; PAYLOAD_BITS];
+    for (index, (bx, by)) in block_count(image)?.enumerate() {
+        let bit = read_bit(block_mean(image, bx, by));
+        votes[index % PAYLOAD_BITS] += if bit { 1 } else { -1 };
+        counts[index % PAYLOAD_BITS] += 1;
+    }
+
+    let bits: Vec<bool> = votes.iter().map(|v| *v > 0).collect();
+    let confidence = votes
+        .iter()
+        .zip(counts.iter())
+        .map(|(v, c)| (*c + v.abs()) as f32 / (2 * *c) as f32)
+        .sum::<f32>()
+        / PAYLOAD_BITS as f32;
+
+    let detected = bits[..32] == payload("")[..32];
+    let matches_tag = detected && tag.is_some_and(|tag| bits[32..] == payload(tag)[32..]);
+    Ok(WatermarkReport {
+        detected,
+        matches_tag,
+        confidence,
+    })
+}
+
+/// Marker bits followed by the first 32 bits of the tag's SHA-256
+fn payload(tag: &str) -> [bool; PAYLOAD_BITS] {
+    let hash = Sha256::digest(tag.as_bytes());
+    let tag_bits = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+    let value = ((MARKER as u64) << 32) | tag_bits as u64;
+
+    let mut bits = [false; PAYLOAD_BITS];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        *bit = (value >> (PAYLOAD_BITS - 1 - i)) & 1 == 1;
+    }
+    bits
+}
+
+/// Block coordinates in raster order; the image must hold the payload at least once
+fn block_count(image: &RgbImage) -> Result<impl Iterator<Item = (u32, u32)>> {
+    let columns = image.width() / BLOCK_SIZE;
+    let rows = image.height() / BLOCK_SIZE;
+    if ((columns * rows) as usize) < PAYLOAD_BITS {
+        return Err(anyhow::anyhow!("Image is too small to carry a watermark"));
+    }
+    Ok((0..rows).flat_map(move |by| (0..columns).map(move |bx| (bx, by))))
+}
+
+fn block_mean(image: &RgbImage, bx: u32, by: u32) -> f32 {
+    let mut sum = 0.0;
+    for y in by * BLOCK_SIZE..(by + 1) * BLOCK_SIZE {
+        for x in bx * BLOCK_SIZE..(bx + 1) * BLOCK_SIZE {
+            let [r, g, b] = image.get_pixel(x, y).0;
+            sum += 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        }
+    }
+    sum / (BLOCK_SIZE * BLOCK_SIZE) as f32
+}
+
+/// Nearest point of the lattice for `bit`: multiples of STEP for 0, offset by STEP/2 for 1
+fn quantize(mean: f32, bit: bool) -> f32 {
+    let offset = if bit { STEP / 2.0 } else { 0.0 };
+    let target = ((mean - offset) / STEP).round() * STEP + offset;
+    // Keep targets reachable without clipping at the ends of the range
+    if target < 0.0 {
+        target + STEP
+    } else if target > 255.0 {
+        target - STEP
+    } else {
+        target
+    }
+}
+
+fn read_bit(mean: f32) -> bool {
+    (mean - quantize(mean, true)).abs() < (mean - quantize(mean, false)).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+
+    fn gradient() -> RgbImage {
+        RgbImage::from_fn(256, 192, |x, y| {
+            image::Rgb([(x % 256) as u8, (y + 30) as u8, ((x + y) / 3) as u8])
+        })
+    }
+
+    #[test]
+    fn test_watermark_survives_jpeg() {
+        let mut image = gradient();
+        embed(&mut image, "studio-a").unwrap();
+
+        let report = detect(&image, Some("studio-a")).unwrap();
+        assert!(report.detected && report.matches_tag);
+
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, 85)
+            .encode_image(&image)
+            .unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap().to_rgb8();
+        let report = detect(&decoded, Some("studio-a")).unwrap();
+        assert!(report.detected && report.matches_tag);
+        assert!(!detect(&decoded, Some("studio-b")).unwrap().matches_tag);
+
+        assert!(!detect(&gradient(), None).unwrap().detected);
+    }
+
+    #[test]
+    fn test_embed_keeps_alpha() {
+        let gradient = gradient();
+        let mut image = RgbaImage::from_fn(256, 192, |x, y| {
+            let [r, g, b] = gradient.get_pixel(x, y).0;
+            image::Rgba([r, g, b, (x / 2) as u8])
+        });
+        embed_keeping_alpha(&mut image, "studio-a").unwrap();
+
+        assert!(image.enumerate_pixels().all(|(x, _, pixel)| pixel.0[3] == (x / 2) as u8));
+        let rgb = image::DynamicImage::ImageRgba8(image).to_rgb8();
+        assert!(detect(&rgb, Some("studio-a")).unwrap().matches_tag);
+    }
+}
@@ -0,0 +1,241 @@
+//! Daily and monthly spending caps per provider.
+//!
+//! Before a job runs, the processor adds its estimated cost to what the provider has cost
+//! so far in the current day and month (local time, from the usage records). Jobs that
+//! would go over a cap are paused as `waiting_budget` or failed, depending on the settings.
+//! Paused jobs are re-checked periodically and when the caps change, so they start by
+//! themselves once a new period begins or a cap is raised. A `budget-warning` event fires
+//! when spending crosses [`WARNING_RATIO`] of a cap.
+
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use super::pricing;
+use crate::db::models::UpdateJobInput;
+use crate::db::operations::{JobOps, SettingsOps, UsageOps};
+
+/// Settings key for spending caps
+pub const BUDGET_SETTINGS_KEY: &str = "budget_settings";
+
+/// Status of jobs held until their provider is back under its caps
+pub const WAITING_BUDGET: &str = "waiting_budget";
+
+/// Event emitted when a provider's spending crosses [`WARNING_RATIO`] of a cap
+pub const BUDGET_WARNING_EVENT: &str = "budget-warning";
+
+/// Share of a cap at which the warning is sent
+pub const WARNING_RATIO: f64 = 0.8;
+
+/// How often paused jobs are put back in the queue to be checked again
+pub const BUDGET_RECHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// What happens to a job that would go over a cap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverBudgetAction {
+    /// Hold the job until the provider is under its caps again
+    #[default]
+    Pause,
+    /// Fail the job
+    Refuse,
+}
+
+/// Caps in USD for one provider; `None` leaves a period unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderBudget {
+    pub daily_usd: Option<f64>,
+    pub monthly_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    /// Caps keyed by provider
+    pub providers: HashMap<String, ProviderBudget>,
+    pub action: OverBudgetAction,
+}
+
+/// Payload of the `budget-warning` event
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetWarningEvent {
+    pub provider: String,
+    /// `daily` or `monthly`
+    pub period: &'static str,
+    pub spent_usd: f64,
+    pub cap_usd: f64,
+}
+
+/// Spending of a provider in the current periods
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spend {
+    pub today: f64,
+    pub this_month: f64,
+}
+
+/// Why a job would go over a cap, if it would
+pub fn exceeded(budget: &ProviderBudget, spend: Spend, cost: f64) -> Option<String> {
+    let periods = [
+        ("daily", budget.daily_usd, spend.today),
+        ("monthly", budget.monthly_usd, spend.this_month),
+    ];
+    periods.into_iter().find_map(|(period, cap, spent)| {
+        let cap = cap?;
+        (spent + cost > cap).then(|| {
+            format!(
+                "Over the {} budget: ${:.2} spent of ${:.2}, this job costs about ${:.2}",
+                period, spent, cap, cost
+            )
+        })
+    })
+}
+
+/// Caps whose warning threshold a cost of `cost` just crossed
+pub fn crossed_warnings(
+    budget: &ProviderBudget,
+    spend: Spend,
+    cost: f64,
+) -> Vec<(&'static str, f64)> {
+    let periods = [
+        ("daily", budget.daily_usd, spend.today),
+        ("monthly", budget.monthly_usd, spend.this_month),
+    ];
+    periods
+        .into_iter()
+        .filter_map(|(period, cap, spent)| {
+            let threshold = cap? * WARNING_RATIO;
+            (spent - cost < threshold && spent >= threshold).then_some((period, cap?))
+        })
+        .collect()
+}
+
+/// Current day and month spending of a provider, in local time
+pub async fn spend(pool: &SqlitePool, provider: &str) -> Result<Spend> {
+    let today = Local::now().date_naive();
+    let month_start = today.with_day(1).unwrap_or(today);
+    Ok(Spend {
+        today: UsageOps::cost_since(pool, provider, &local_midnight(today)).await?,
+        this_month: UsageOps::cost_since(pool, provider, &local_midnight(month_start)).await?,
+    })
+}
+
+/// Start of a local day as an RFC 3339 timestamp
+fn local_midnight(date: NaiveDate) -> String {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|start| start.to_rfc3339())
+        .unwrap_or_else(|| midnight.and_utc().to_rfc3339())
+}
+
+/// Why a job must not run now, if its provider has caps it would go over
+pub async fn check(
+    pool: &SqlitePool,
+    provider: &str,
+    model: &str,
+    params: &serde_json::Value,
+) -> Result<Option<(OverBudgetAction, String)>> {
+    let settings: BudgetSettings = SettingsOps::get_or_default(pool, BUDGET_SETTINGS_KEY).await?;
+    let Some(budget) = settings.providers.get(provider) else {
+        return Ok(None);
+    };
+    let cost = pricing::estimate_cost(provider, model, params).unwrap_or(0.0);
+    let spend = spend(pool, provider).await?;
+    Ok(exceeded(budget, spend, cost).map(|reason| (settings.action, reason)))
+}
+
+/// Hold back a generation of `job_id` that would go over its provider's caps
+///
+/// Fails with the reason when over-budget jobs are refused. Returns `true` when the job
+/// was paused as `waiting_budget` instead and must not run now.
+pub async fn enforce(
+    pool: &SqlitePool,
+    job_id: &str,
+    provider: &str,
+    model: &str,
+    params: &serde_json::Value,
+) -> Result<bool> {
+    let Some((action, reason)) = check(pool, provider, model, params).await? else {
+        return Ok(false);
+    };
+    if action == OverBudgetAction::Refuse {
+        return Err(anyhow::anyhow!(reason));
+    }
+    log_info!("[Budget] Pausing job {}: {}", job_id, reason);
+    JobOps::update(
+        pool,
+        job_id,
+        UpdateJobInput {
+            status: Some(WAITING_BUDGET.to_string()),
+            result: None,
+            error: Some(reason),
+        },
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Send a warning for each cap that the cost just recorded pushed past the threshold
+pub async fn warn_if_crossed(
+    pool: &SqlitePool,
+    app_handle: &AppHandle,
+    provider: &str,
+    cost: f64,
+) -> Result<()> {
+    let settings: BudgetSettings = SettingsOps::get_or_default(pool, BUDGET_SETTINGS_KEY).await?;
+    let Some(budget) = settings.providers.get(provider) else {
+        return Ok(());
+    };
+    let spend = spend(pool, provider).await?;
+    for (period, cap) in crossed_warnings(budget, spend, cost) {
+        let spent_usd = if period == "daily" { spend.today } else { spend.this_month };
+        log_warn!(
+            "[Budget] {} has used ${:.2} of its {} budget of ${:.2}",
+            provider, spent_usd, period, cap
+        );
+        let event = BudgetWarningEvent {
+            provider: provider.to_string(),
+            period,
+            spent_usd,
+            cap_usd: cap,
+        };
+        if let Err(e) = app_handle.emit(BUDGET_WARNING_EVENT, event) {
+            log_warn!("[Budget] Failed to emit warning: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caps_and_warnings() {
+        let budget = ProviderBudget {
+            daily_usd: Some(10.0),
+            monthly_usd: Some(100.0),
+        };
+        let spend = Spend {
+            today: 9.5,
+            this_month: 50.0,
+        };
+        assert!(exceeded(&budget, spend, 0.4).is_none());
+        assert!(exceeded(&budget, spend, 0.6).unwrap().contains("daily"));
+        assert!(exceeded(&ProviderBudget::default(), spend, 1000.0).is_none());
+
+        // 7.5 -> 8.5 crosses 80% of the daily cap, 49 -> 50 stays under the monthly one
+        let spend = Spend {
+            today: 8.5,
+            this_month: 50.0,
+        };
+        assert_eq!(crossed_warnings(&budget, spend, 1.0), vec![("daily", 10.0)]);
+        assert!(crossed_warnings(&budget, Spend { today: 9.0, ..spend }, 0.5).is_empty());
+    }
+}
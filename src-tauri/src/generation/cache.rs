@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Settings key for the result cache
+pub const RESULT_CACHE_SETTINGS_KEY: &str = "result_cache_settings";
+
+/// Opt-in reuse of earlier results for identical deterministic requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResultCacheSettings {
+    pub enabled: bool,
+}
+
+/// Cache key for a generation, or `None` if the output is not reproducible
+///
+/// Only requests with a fixed, non-negative `seed` are deterministic; without one
+/// (or with `-1`, meaning random) every run is expected to differ.
+pub fn cache_key(
+    provider: &str,
+    model: &str,
+    prompt: &str,
+    params: &serde_json::Value,
+) -> Option<String> {
+    params.get("seed").and_then(|seed| seed.as_u64())?;
+
+    // Object keys serialize in sorted order, so equal parameters give equal input
+    let input = serde_json::json!({
+        "provider": provider,
+        "model": model,
+        "prompt": prompt,
+        "parameters": params,
+    });
+
+    let digest = Sha256::digest(input.to_string().as_bytes());
+    Some(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_key_requires_fixed_seed() {
+        let a = cache_key("a1111", "sdxl", "a cat", &json!({ "seed": 42, "steps": 20 }));
+        let b = cache_key("a1111", "sdxl", "a cat", &json!({ "steps": 20, "seed": 42 }));
+        assert!(a.is_some());
+        assert_eq!(a, b);
+
+        let other_seed = cache_key("a1111", "sdxl", "a cat", &json!({ "seed": 43, "steps": 20 }));
+        assert_ne!(a, other_seed);
+
+        assert!(cache_key("a1111", "sdxl", "a cat", &json!({ "seed": -1 })).is_none());
+        assert!(cache_key("openai", "gpt-image-1", "a cat", &json!({})).is_none());
+    }
+}
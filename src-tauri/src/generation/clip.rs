@@ -0,0 +1,279 @@
+//! Fitting long prompts into the CLIP window of Stable Diffusion backends.
+//!
+//! CLIP reads 75 tokens at a time. A1111 encodes later windows separately and starts a new
+//! one at each `BREAK`, ComfyUI splits wherever the count runs out, and InvokeAI drops
+//! everything past the first window. Prompts over the limit are split at phrase boundaries
+//! or summarized, and whatever still does not reach the model is noted in the result.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::lint::{estimate_clip_tokens, uses_clip, CLIP_TOKEN_LIMIT};
+use super::{weights, GenerationRequest, GenerationResult, GenerationService};
+
+/// Settings key for long prompt handling
+pub const CLIP_SETTINGS_KEY: &str = "clip_prompt_fitting";
+
+/// What to do with prompts longer than one CLIP window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipFitMode {
+    /// Send prompts as written
+    Off,
+    /// Start windows at phrase boundaries, or keep the phrases that fit on backends
+    /// reading a single window
+    #[default]
+    Break,
+    /// Have an LLM shorten the prompt, chunking whatever is still too long
+    Summarize,
+}
+
+/// Provider and model shortening prompts in [`ClipFitMode::Summarize`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summarizer {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Long prompt handling, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipSettings {
+    pub mode: ClipFitMode,
+    pub summarizer: Option<Summarizer>,
+}
+
+/// How a long prompt was fitted, recorded as `clip_fit` in the result metadata
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClipFit {
+    pub prompt: String,
+    /// Estimated tokens of the prompt as written
+    pub tokens: usize,
+    /// CLIP windows the sent prompt spans
+    pub chunks: usize,
+    /// Phrases that do not reach the model
+    pub truncated: Vec<String>,
+    pub summarized: bool,
+}
+
+impl ClipFit {
+    pub fn record(&self, result: &mut GenerationResult) {
+        if let Some(metadata) = result.metadata.as_object_mut() {
+            let fit = serde_json::to_value(self).unwrap_or_default();
+            metadata.insert("clip_fit".to_string(), fit);
+        }
+    }
+}
+
+/// Fit the prompt of a request for a CLIP backend, if it is too long
+///
+/// A failed summary falls back to chunking, so this never fails the generation.
+pub async fn fit_request(
+    service: &GenerationService,
+    provider: &str,
+    mut request: GenerationRequest,
+) -> (GenerationRequest, Option<ClipFit>) {
+    let settings = service.clip_settings();
+    let long = estimate_clip_tokens(&request.prompt) > CLIP_TOKEN_LIMIT;
+    if settings.mode == ClipFitMode::Off || !long || !uses_clip(provider, &request.model) {
+        return (request, None);
+    }
+
+    let fit = match (settings.mode, &settings.summarizer) {
+        (ClipFitMode::Summarize, Some(summarizer)) => {
+            match summarize(service, summarizer, &request.prompt, provider).await {
+                Ok(fit) => fit,
+                Err(e) => {
+                    let name = &summarizer.provider;
+                    log_warn!("[CLIP] Summary by {} failed, chunking instead: {}", name, e);
+                    chunk(&request.prompt, provider)
+                }
+            }
+        }
+        (ClipFitMode::Summarize, None) => {
+            log_warn!("[CLIP] No summarizer configured, chunking the prompt instead");
+            chunk(&request.prompt, provider)
+        }
+        _ => chunk(&request.prompt, provider),
+    };
+    if !fit.truncated.is_empty() {
+        log_info!("[CLIP] Prompt truncated for {}: {}", provider, fit.truncated.join(", "));
+    }
+    request.prompt = fit.prompt.clone();
+    (request, Some(fit))
+}
+
+/// Split a prompt into CLIP windows at phrase boundaries for `provider`
+///
+/// A1111 gets `BREAK` between windows, ComfyUI already reads every window, and other
+/// backends get the leading phrases that fit in one.
+pub fn chunk(prompt: &str, provider: &str) -> ClipFit {
+    let tokens = estimate_clip_tokens(prompt);
+    let mut fit = ClipFit {
+        prompt: prompt.to_string(),
+        tokens,
+        chunks: tokens.div_ceil(CLIP_TOKEN_LIMIT).max(1),
+        truncated: Vec::new(),
+        summarized: false,
+    };
+
+    match provider {
+        // Breaks the user placed are kept as they are
+        "a1111" if prompt.contains("BREAK") => {
+            fit.chunks = prompt.matches("BREAK").count() + 1;
+        }
+        "a1111" => {
+            let windows = windows(&split_phrases(prompt));
+            fit.chunks = windows.len();
+            fit.prompt = windows
+                .iter()
+                .map(|window| window.join(", "))
+                .collect::<Vec<_>>()
+                .join(" BREAK ");
+        }
+        "comfyui" => {}
+        _ => {
+            let mut windows = windows(&split_phrases(prompt)).into_iter();
+            let first = windows.next().unwrap_or_default();
+            fit.prompt = first.join(", ");
+            fit.chunks = 1;
+            fit.truncated = windows.flatten().map(str::to_string).collect();
+        }
+    }
+    fit
+}
+
+/// Ask an LLM to shorten the prompt, chunking the summary if it is still too long
+async fn summarize(
+    service: &GenerationService,
+    summarizer: &Summarizer,
+    prompt: &str,
+    provider: &str,
+) -> Result<ClipFit> {
+    let instructions = format!(
+        "Shorten this Stable Diffusion prompt to at most {} words. Keep the subject first, \
+         keep the most important descriptors and any (term:1.2) weights, and separate \
+         phrases with commas. Reply with the prompt only.\n\nPrompt: {}",
+        CLIP_TOKEN_LIMIT * 2 / 3,
+        prompt
+    );
+    let request = GenerationRequest {
+        prompt: instructions,
+        model: summarizer.model.clone(),
+        parameters: serde_json::json!({ "max_tokens": 300, "temperature": 0.2 }),
+    };
+    let llm = service
+        .get_provider(&summarizer.provider)
+        .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", summarizer.provider))?;
+    service.rate_limiter.acquire(&summarizer.provider).await;
+    let result = llm.generate(request).await?;
+    let summary = result
+        .output_data()
+        .map(|text| text.trim().trim_matches('"').trim().to_string())
+        .filter(|text| !text.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("No summary received from {}", summarizer.provider))?;
+
+    let mut fit = chunk(&summary, provider);
+    let lower = summary.to_lowercase();
+    let mut dropped: Vec<String> = split_phrases(prompt)
+        .into_iter()
+        .filter(|phrase| !is_kept(phrase, &lower))
+        .map(str::to_string)
+        .collect();
+    dropped.append(&mut fit.truncated);
+    fit.truncated = dropped;
+    fit.tokens = estimate_clip_tokens(prompt);
+    fit.summarized = true;
+    Ok(fit)
+}
+
+/// Comma separated phrases, not splitting inside weighting brackets
+fn split_phrases(prompt: &str) -> Vec<&str> {
+    let mut phrases = Vec::new();
+    let mut depth = 0usize;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in prompt.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                phrases.push(&prompt[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    phrases.push(&prompt[start..]);
+    phrases
+        .into_iter()
+        .map(str::trim)
+        .filter(|phrase| !phrase.is_empty())
+        .collect()
+}
+
+/// Group phrases into windows of at most one CLIP chunk each
+///
+/// A phrase longer than a window gets one of its own; the backend splits it further.
+fn windows<'a>(phrases: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut windows: Vec<Vec<&str>> = Vec::new();
+    let mut used = 0;
+    for phrase in phrases {
+        // One more for the comma joining it to the previous phrase
+        let tokens = estimate_clip_tokens(phrase) + 1;
+        match windows.last_mut() {
+            Some(window) if used + tokens <= CLIP_TOKEN_LIMIT => {
+                window.push(phrase);
+                used += tokens;
+            }
+            _ => {
+                windows.push(vec![phrase]);
+                used = tokens;
+            }
+        }
+    }
+    windows
+}
+
+/// Whether most words of a phrase made it into the summary
+fn is_kept(phrase: &str, summary: &str) -> bool {
+    let text: String = weights::parse(phrase).into_iter().map(|run| run.text).collect();
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2)
+        .collect();
+    let found = words.iter().filter(|word| summary.contains(*word)).count();
+    words.is_empty() || found * 2 >= words.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_long_prompts() {
+        assert_eq!(
+            split_phrases("a fox, (red, orange:1.2), snow\\, ice"),
+            vec!["a fox", "(red, orange:1.2)", "snow\\, ice"]
+        );
+
+        let prompt = (1..=30)
+            .map(|i| format!("the quick brown fox jumps over lazy dog{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let a1111 = chunk(&prompt, "a1111");
+        assert_eq!(a1111.chunks, 4);
+        assert!(a1111.truncated.is_empty());
+        assert!(a1111.prompt.contains("dog8 BREAK the quick"));
+
+        let invokeai = chunk(&prompt, "invokeai");
+        assert_eq!(invokeai.prompt.split(", ").count(), 8);
+        assert_eq!(invokeai.truncated.len(), 22);
+        assert_eq!(invokeai.truncated[0], "the quick brown fox jumps over lazy dog9");
+
+        assert_eq!(chunk(&prompt, "comfyui").prompt, prompt);
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::pricing;
+
+/// Settings key for the manual confirmation gate
+pub const CONFIRMATION_SETTINGS_KEY: &str = "confirmation_settings";
+
+/// Job status for jobs held until `approve_job` is called
+pub const NEEDS_CONFIRMATION: &str = "needs_confirmation";
+
+/// Which submitted jobs must be approved before the processor picks them up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmationSettings {
+    pub enabled: bool,
+    /// Hold jobs whose estimated cost exceeds this many USD
+    pub cost_threshold_usd: Option<f64>,
+    /// Hold every video job regardless of cost
+    pub confirm_video: bool,
+}
+
+impl Default for ConfirmationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cost_threshold_usd: Some(1.0),
+            confirm_video: true,
+        }
+    }
+}
+
+impl ConfirmationSettings {
+    /// Whether a generation with these inputs must be held for confirmation
+    pub fn requires_confirmation(
+        &self,
+        provider: &str,
+        model: &str,
+        params: &serde_json::Value,
+    ) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.confirm_video && pricing::is_video_model(model) {
+            return true;
+        }
+        match (self.cost_threshold_usd, pricing::estimate_cost(provider, model, params)) {
+            (Some(threshold), Some(cost)) => cost > threshold,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requires_confirmation() {
+        let settings = ConfirmationSettings {
+            enabled: true,
+            cost_threshold_usd: Some(0.5),
+            confirm_video: true,
+        };
+        let params = serde_json::json!({ "quality": "high", "n": 4 });
+
+        assert!(settings.requires_confirmation("openai", "gpt-image-1", &params));
+        assert!(!settings.requires_confirmation("openai", "gpt-image-1", &serde_json::json!({})));
+        assert!(settings.requires_confirmation("google", "veo-3.1", &serde_json::json!({ "duration": 4 })));
+        assert!(!ConfirmationSettings::default().requires_confirmation("openai", "sora-2-pro", &params));
+    }
+}
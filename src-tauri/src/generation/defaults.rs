@@ -0,0 +1,215 @@
+//! Built-in default parameters per known model.
+//!
+//! Providers read parameters through [`ModelParams`] so a missing (or mistyped) value
+//! falls back to the model's profile here instead of a literal at the call site.
+
+use serde_json::{json, Value};
+
+use super::providers::{elevenlabs, openai};
+
+/// Default parameters for a provider and model, as a JSON object
+///
+/// Local backends (`a1111`, `comfyui`, `invokeai`) pick a profile from the checkpoint
+/// name since the model family decides native resolution and CFG.
+pub fn model_defaults(provider: &str, model: &str) -> Value {
+    match provider {
+        "a1111" | "comfyui" | "invokeai" => {
+            let sampler = if provider == "a1111" { "Euler a" } else { "euler" };
+            let mut defaults = checkpoint_defaults(model);
+            defaults["sampler"] = json!(sampler);
+            defaults["negative_prompt"] = json!("");
+            defaults["seed"] = json!(-1);
+            defaults
+        }
+        "anthropic" => json!({ "max_tokens": 4096, "temperature": 1.0 }),
+        "groq" => json!({ "max_tokens": 1024, "temperature": 0.7 }),
+        "perplexity" => json!({
+            "max_tokens": 1024,
+            "temperature": 0.2,
+            "search_context_size": "low",
+        }),
+        "openai" if model.starts_with("sora") => json!({
+            "duration": 5,
+            "resolution": "1080p",
+            "aspect_ratio": "16:9",
+        }),
+        // DALL-E requests are redirected to gpt-image-1
+        "openai" if openai::SPEECH_MODELS.contains(&model) => json!({
+            "voice": "alloy",
+            "response_format": "mp3",
+            "speed": 1.0,
+        }),
+        "openai" if openai::TRANSCRIPTION_MODELS.contains(&model) => {
+            json!({ "response_format": "text" })
+        }
+        "openai" => json!({ "size": "auto", "quality": "high", "n": 1 }),
+        "google" if model.starts_with("veo") => json!({
+            "duration": 8,
+            "resolution": "720p",
+            "aspect_ratio": "16:9",
+        }),
+        "google" if model.starts_with("imagen") => json!({
+            "n": 1,
+            "aspect_ratio": "1:1",
+            "person_generation": "allow_adult",
+        }),
+        "google" => json!({ "n": 1, "google_search": false }),
+        "grok" => json!({ "n": 1, "response_format": "url" }),
+        "flux" if model.starts_with("flux-kontext") || model.ends_with("ultra") => json!({
+            "aspect_ratio": "1:1",
+            "output_format": "png",
+            "safety_tolerance": 2,
+            "seed": -1,
+        }),
+        "fal" if model.contains("video") => json!({ "duration": 5 }),
+        "fal" => json!({ "num_images": 1 }),
+        "recraft" => json!({ "style": "realistic_image", "size": "1024x1024", "n": 1 }),
+        "leonardo" => json!({ "width": 1024, "height": 1024, "num_images": 1 }),
+        "runway" if model == "gen3a_turbo" => json!({ "ratio": "1280:768", "duration": 5 }),
+        "runway" if model.starts_with("veo") => json!({ "ratio": "1280:720", "duration": 8 }),
+        "runway" => json!({ "ratio": "1280:720", "duration": 5 }),
+        "minimax" if model.starts_with("MiniMax-Hailuo") => json!({
+            "duration": 6,
+            "resolution": "768P",
+            "prompt_optimizer": true,
+        }),
+        "minimax" => json!({ "prompt_optimizer": true }),
+        "volcengine" if model.starts_with("doubao-seedance") => json!({
+            "resolution": "1080p",
+            "duration": 5,
+            "ratio": "16:9",
+            "watermark": false,
+        }),
+        "volcengine" if model.starts_with("doubao-seedream-3") => json!({
+            "size": "1024x1024",
+            "guidance_scale": 2.5,
+            "watermark": false,
+        }),
+        "volcengine" => json!({ "size": "2K", "watermark": false }),
+        "vertex" if model.starts_with("veo") => json!({
+            "aspect_ratio": "16:9",
+            "resolution": "720p",
+            "duration": 8,
+            "generate_audio": true,
+        }),
+        "vertex" => json!({ "n": 1, "aspect_ratio": "1:1", "person_generation": "allow_adult" }),
+        "stability" if model == "stable-audio-2" => json!({
+            "duration": 30,
+            "steps": 50,
+            "cfg_scale": 7.0,
+            "output_format": "mp3",
+        }),
+        "stability" => json!({
+            "duration": 30,
+            "steps": 8,
+            "cfg_scale": 1.0,
+            "output_format": "mp3",
+        }),
+        "elevenlabs" => json!({
+            "voice_id": elevenlabs::DEFAULT_VOICE_ID,
+            "stability": 0.5,
+            "similarity_boost": 0.75,
+            "style": 0.0,
+            "use_speaker_boost": true,
+            "output_format": "mp3_44100_128",
+        }),
+        "pika" => json!({ "aspect_ratio": "16:9", "resolution": "720p", "duration": 5 }),
+        "midjourney" => json!({ "aspect_ratio": "1:1", "process_mode": "fast" }),
+        "flux" => json!({
+            "width": 1024,
+            "height": 1024,
+            "steps": 28,
+            "guidance": 3.0,
+            "output_format": "png",
+            "safety_tolerance": 2,
+            "seed": -1,
+        }),
+        _ => json!({}),
+    }
+}
+
+/// Native resolution, steps and CFG for a Stable Diffusion family checkpoint
+fn checkpoint_defaults(checkpoint: &str) -> Value {
+    let name = checkpoint.to_lowercase();
+
+    if name.contains("flux") {
+        // Guidance-distilled; real CFG above 1 only slows it down
+        json!({ "steps": 20, "cfg_scale": 1.0, "width": 1024, "height": 1024 })
+    } else if name.contains("sd3") {
+        json!({ "steps": 28, "cfg_scale": 4.5, "width": 1024, "height": 1024 })
+    } else if name.contains("xl") {
+        json!({ "steps": 25, "cfg_scale": 7.0, "width": 1024, "height": 1024 })
+    } else {
+        json!({ "steps": 20, "cfg_scale": 7.0, "width": 512, "height": 512 })
+    }
+}
+
+/// Request parameters backed by the model's default profile
+pub struct ModelParams<'a> {
+    params: &'a Value,
+    defaults: Value,
+}
+
+impl<'a> ModelParams<'a> {
+    pub fn new(provider: &str, model: &str, params: &'a Value) -> Self {
+        Self {
+            params,
+            defaults: model_defaults(provider, model),
+        }
+    }
+
+    /// The first of the user's value or the default that satisfies `convert`
+    fn lookup<T>(&self, key: &str, convert: impl Fn(&Value) -> Option<T>) -> Option<T> {
+        self.params
+            .get(key)
+            .and_then(&convert)
+            .or_else(|| self.defaults.get(key).and_then(&convert))
+    }
+
+    pub fn u64(&self, key: &str) -> u64 {
+        self.lookup(key, Value::as_u64).unwrap_or_default()
+    }
+
+    pub fn i64(&self, key: &str) -> i64 {
+        self.lookup(key, Value::as_i64).unwrap_or_default()
+    }
+
+    pub fn f64(&self, key: &str) -> f64 {
+        self.lookup(key, Value::as_f64).unwrap_or_default()
+    }
+
+    pub fn bool(&self, key: &str) -> bool {
+        self.lookup(key, Value::as_bool).unwrap_or_default()
+    }
+
+    pub fn str(&self, key: &str) -> &str {
+        self.params
+            .get(key)
+            .and_then(Value::as_str)
+            .or_else(|| self.defaults.get(key).and_then(Value::as_str))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_params_fall_back_to_profile() {
+        let params = json!({ "steps": 40, "cfg_scale": "high" });
+
+        let sdxl = ModelParams::new("comfyui", "juggernautXL_v9.safetensors", &params);
+        assert_eq!(sdxl.u64("steps"), 40);
+        assert_eq!(sdxl.f64("cfg_scale"), 7.0);
+        assert_eq!(sdxl.u64("width"), 1024);
+        assert_eq!(sdxl.str("sampler"), "euler");
+
+        let sd15 = ModelParams::new("a1111", "v1-5-pruned.ckpt", &params);
+        assert_eq!(sd15.u64("width"), 512);
+        assert_eq!(sd15.str("sampler"), "Euler a");
+
+        let veo = ModelParams::new("google", "veo-3.1", &params);
+        assert_eq!(veo.u64("duration"), 8);
+    }
+}
@@ -0,0 +1,174 @@
+//! Models their vendors have deprecated or retired, with suggested replacements.
+//!
+//! Jobs and presets outlive the models they name, so estimates and a scan of waiting
+//! jobs warn about these before the vendor starts rejecting them. Update the table when
+//! a vendor announces a retirement.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::db::models::Job;
+use crate::db::operations::ParameterPresetOps;
+
+/// A deprecated model and what to use instead
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Deprecation {
+    pub provider: &'static str,
+    pub model: &'static str,
+    pub replacement: &'static str,
+    /// Date (`YYYY-MM-DD`) the vendor stops or stopped serving the model, when announced
+    pub sunset: Option<&'static str>,
+}
+
+const fn deprecated(
+    provider: &'static str,
+    model: &'static str,
+    replacement: &'static str,
+    sunset: Option<&'static str>,
+) -> Deprecation {
+    Deprecation {
+        provider,
+        model,
+        replacement,
+        sunset,
+    }
+}
+
+pub const DEPRECATIONS: &[Deprecation] = &[
+    // Requests for DALL-E are already sent to gpt-image-1 by the OpenAI provider
+    deprecated("openai", "dall-e-3", "gpt-image-1", None),
+    deprecated("openai", "dall-e-2", "gpt-image-1-mini", None),
+    deprecated("openai", "sora", "sora-2", None),
+    deprecated("google", "veo", "veo-3.1-generate-preview", None),
+    deprecated("google", "veo-2", "veo-3.1-generate-preview", None),
+    deprecated("google", "veo-2.0-generate-exp", "veo-3.1-generate-preview", None),
+    deprecated("google", "veo-3.0-generate-preview", "veo-3.1-generate-preview", None),
+    deprecated("google", "gemini-2.5-flash-image-preview", "gemini-2.5-flash-image", None),
+    deprecated("flux", "flux-pro", "flux-pro-1.1", None),
+    deprecated("anthropic", "claude-3-sonnet-20240229", "claude-sonnet-4-5", Some("2025-07-21")),
+    deprecated("anthropic", "claude-3-5-sonnet-20240620", "claude-sonnet-4-5", Some("2025-10-22")),
+    deprecated("anthropic", "claude-3-5-sonnet-20241022", "claude-sonnet-4-5", Some("2025-10-22")),
+    deprecated("anthropic", "claude-3-opus-20240229", "claude-opus-4-5", Some("2026-01-05")),
+];
+
+/// Warning about a model, as shown next to an estimate or a waiting job
+#[derive(Debug, Clone, Serialize)]
+pub struct DeprecationWarning {
+    pub provider: String,
+    pub model: String,
+    pub replacement: String,
+    pub sunset: Option<String>,
+    /// The sunset date has passed, so requests will fail
+    pub retired: bool,
+    pub message: String,
+}
+
+/// Something saved that names a deprecated model
+#[derive(Debug, Clone, Serialize)]
+pub struct DeprecatedUsage {
+    /// `job` or `preset`
+    pub kind: String,
+    pub id: String,
+    pub warning: DeprecationWarning,
+}
+
+pub fn lookup(provider: &str, model: &str) -> Option<&'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .find(|entry| entry.provider == provider && entry.model == model)
+}
+
+/// Warning for `model` as of `today` (`YYYY-MM-DD`), if it is deprecated
+pub fn warning_on(provider: &str, model: &str, today: &str) -> Option<DeprecationWarning> {
+    let entry = lookup(provider, model)?;
+    let retired = entry.sunset.is_some_and(|sunset| sunset <= today);
+    let message = match entry.sunset {
+        Some(sunset) if retired => format!(
+            "{} was retired on {}, use {} instead",
+            model, sunset, entry.replacement
+        ),
+        Some(sunset) => format!(
+            "{} is deprecated and will be retired on {}, use {} instead",
+            model, sunset, entry.replacement
+        ),
+        None => format!("{} is deprecated, use {} instead", model, entry.replacement),
+    };
+    Some(DeprecationWarning {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        replacement: entry.replacement.to_string(),
+        sunset: entry.sunset.map(str::to_string),
+        retired,
+        message,
+    })
+}
+
+pub fn warning(provider: &str, model: &str) -> Option<DeprecationWarning> {
+    warning_on(provider, model, &Utc::now().format("%Y-%m-%d").to_string())
+}
+
+/// Jobs that have not run yet and presets naming a deprecated model
+pub async fn scan(pool: &SqlitePool) -> Result<Vec<DeprecatedUsage>> {
+    let jobs: Vec<Job> = sqlx::query_as(
+        r#"
+        SELECT * FROM jobs
+        WHERE type = 'generation'
+          AND (status IN ('pending', 'needs_confirmation') OR status LIKE 'waiting_%')
+        ORDER BY created_at ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut usages = Vec::new();
+    for job in jobs {
+        let data: Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let field = |name: &str| data.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+        if let Some(warning) = warning(field("provider"), field("model")) {
+            usages.push(DeprecatedUsage {
+                kind: "job".to_string(),
+                id: job.id,
+                warning,
+            });
+        }
+    }
+
+    for preset in ParameterPresetOps::list(pool).await? {
+        let Some(model) = preset.model.as_deref() else {
+            continue;
+        };
+        if let Some(warning) = warning(&preset.provider, model) {
+            usages.push(DeprecatedUsage {
+                kind: "preset".to_string(),
+                id: preset.id,
+                warning,
+            });
+        }
+    }
+
+    Ok(usages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings() {
+        let dalle = warning_on("openai", "dall-e-3", "2026-01-01").unwrap();
+        assert_eq!(dalle.replacement, "gpt-image-1");
+        assert!(!dalle.retired);
+
+        let opus = "claude-3-opus-20240229";
+        assert!(!warning_on("anthropic", opus, "2026-01-04").unwrap().retired);
+        let retired = warning_on("anthropic", opus, "2026-01-05").unwrap();
+        assert!(retired.retired);
+        assert!(retired.message.contains("retired on 2026-01-05"));
+
+        assert!(warning_on("openai", "gpt-image-1", "2026-01-01").is_none());
+        assert!(warning_on("google", "dall-e-3", "2026-01-01").is_none());
+    }
+}
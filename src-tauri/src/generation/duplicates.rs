@@ -0,0 +1,109 @@
+//! Spotting prompts that were already generated, so their results can be reused.
+//!
+//! Prompts are compared after normalization (case, punctuation, whitespace and word
+//! order) by the overlap of their words, against recent completed jobs of the same
+//! provider and model. Matches never block a submission; they are reported alongside it
+//! with the earlier results attached.
+
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+
+use crate::db::operations::JobOps;
+
+/// Event emitted after submitting a prompt close to an earlier one
+pub const DUPLICATE_PROMPT_EVENT: &str = "duplicate-prompt";
+
+/// Share of words two prompts must have in common to count as near-identical
+pub const SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// Completed jobs searched for earlier prompts
+const HISTORY_LIMIT: i64 = 500;
+
+/// Most matches reported for one prompt
+const MAX_MATCHES: usize = 5;
+
+/// An earlier job with a near-identical prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarPrompt {
+    pub job_id: String,
+    pub prompt: String,
+    /// 1.0 for the same prompt after normalization
+    pub similarity: f64,
+    pub completed_at: Option<String>,
+    /// The earlier job's result, to reuse instead of generating again
+    pub result: Option<serde_json::Value>,
+}
+
+/// Payload of the `duplicate-prompt` event
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePromptEvent {
+    /// The job just submitted
+    pub job_id: String,
+    pub matches: Vec<SimilarPrompt>,
+}
+
+/// Words of a prompt, lowercased and without punctuation
+pub fn words(prompt: &str) -> BTreeSet<String> {
+    prompt
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Jaccard similarity of two prompts' words, from 0.0 to 1.0
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Recent completed jobs of `provider` and `model` whose prompt is close to `prompt`,
+/// closest first
+pub async fn find_similar(
+    pool: &SqlitePool,
+    provider: &str,
+    model: &str,
+    prompt: &str,
+) -> Result<Vec<SimilarPrompt>> {
+    let jobs = JobOps::recent_completed(pool, provider, model, HISTORY_LIMIT).await?;
+    let mut matches: Vec<SimilarPrompt> = jobs
+        .into_iter()
+        .filter_map(|job| {
+            let data: serde_json::Value = serde_json::from_str(&job.data).ok()?;
+            let earlier = data.get("prompt")?.as_str()?;
+            let similarity = similarity(prompt, earlier);
+            (similarity >= SIMILARITY_THRESHOLD).then(|| SimilarPrompt {
+                job_id: job.id,
+                prompt: earlier.to_string(),
+                similarity,
+                completed_at: job.completed_at,
+                result: job.result.and_then(|result| serde_json::from_str(&result).ok()),
+            })
+        })
+        .collect();
+    // Stable, so equally close matches stay newest first
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    matches.truncate(MAX_MATCHES);
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("A red fox, at dawn.", "a red  FOX at dawn"), 1.0);
+        assert_eq!(similarity("fox at dawn, red", "red fox at dawn"), 1.0);
+
+        let close = "a red fox in the snow at dawn, soft light";
+        assert!(similarity(close, "a red fox in the snow at dawn soft light film") >= 0.85);
+        assert!(similarity(close, "a blue whale in the deep ocean") < SIMILARITY_THRESHOLD);
+    }
+}
@@ -0,0 +1,252 @@
+//! Deferring local GPU jobs while the machine runs on battery, hot or busy.
+//!
+//! The processor checks the system every [`ECO_CHECK_INTERVAL`]. While a condition holds,
+//! local jobs are parked as `waiting_eco`; they go back to the queue once the machine is
+//! on AC power and cool or idle again. Readings come from the OS: `/sys` on Linux,
+//! `pmset` on macOS, CIM on Windows, and `nvidia-smi` for the GPU where it is installed.
+//! Readings that are unavailable never defer anything.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Status of local jobs held back by eco scheduling
+pub const WAITING_ECO: &str = "waiting_eco";
+
+/// Event emitted to the frontend when local jobs are deferred or resumed
+pub const ECO_STATUS_EVENT: &str = "eco-status";
+
+/// How often the processor re-reads the system state
+pub const ECO_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// When local jobs are deferred, part of the processor settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EcoSettings {
+    pub enabled: bool,
+    pub defer_on_battery: bool,
+    /// Hottest CPU or GPU sensor reading above which jobs wait
+    pub max_temperature_celsius: Option<f32>,
+    /// GPU utilization in percent, from other programs, above which jobs wait
+    pub max_gpu_utilization: Option<f32>,
+}
+
+impl Default for EcoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            defer_on_battery: true,
+            max_temperature_celsius: Some(85.0),
+            max_gpu_utilization: Some(80.0),
+        }
+    }
+}
+
+/// One reading of the machine; `None` where the OS does not tell
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SystemState {
+    pub on_battery: Option<bool>,
+    pub temperature_celsius: Option<f32>,
+    pub gpu_utilization: Option<f32>,
+}
+
+/// Payload of the `eco-status` event
+#[derive(Debug, Clone, Serialize)]
+pub struct EcoStatusEvent {
+    /// Why local jobs are deferred, or `None` once they may run
+    pub reason: Option<String>,
+    pub state: SystemState,
+    /// Jobs moved from `waiting_eco` back to `pending`
+    pub resumed_jobs: u64,
+}
+
+/// Deferral shared between the monitor and the processor lanes
+#[derive(Debug, Default)]
+pub struct EcoState {
+    hold: RwLock<Option<String>>,
+    local_jobs: AtomicUsize,
+}
+
+impl EcoState {
+    /// Why local jobs currently wait, if they do
+    pub fn hold(&self) -> Option<String> {
+        self.hold.read().unwrap().clone()
+    }
+
+    /// Replace the hold, returning whether it changed
+    pub fn set_hold(&self, reason: Option<String>) -> bool {
+        let mut hold = self.hold.write().unwrap();
+        let changed = *hold != reason;
+        *hold = reason;
+        changed
+    }
+
+    /// Count a local job as running until the guard is dropped
+    pub fn track_local_job(&self) -> LocalJobGuard<'_> {
+        self.local_jobs.fetch_add(1, Ordering::Relaxed);
+        LocalJobGuard(self)
+    }
+
+    pub fn local_jobs_running(&self) -> bool {
+        self.local_jobs.load(Ordering::Relaxed) > 0
+    }
+}
+
+pub struct LocalJobGuard<'a>(&'a EcoState);
+
+impl Drop for LocalJobGuard<'_> {
+    fn drop(&mut self) {
+        self.0.local_jobs.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Why local jobs should wait under `settings`, if they should
+///
+/// Utilization is ignored while our own jobs run, since they are what keeps the GPU busy.
+pub fn hold_reason(settings: &EcoSettings, state: &SystemState, own_jobs: bool) -> Option<String> {
+    if !settings.enabled {
+        return None;
+    }
+    if settings.defer_on_battery && state.on_battery == Some(true) {
+        return Some("Running on battery".to_string());
+    }
+    if let (Some(limit), Some(temperature)) =
+        (settings.max_temperature_celsius, state.temperature_celsius)
+    {
+        if temperature > limit {
+            return Some(format!("Temperature {:.0}°C is above {:.0}°C", temperature, limit));
+        }
+    }
+    if let (Some(limit), Some(utilization), false) =
+        (settings.max_gpu_utilization, state.gpu_utilization, own_jobs)
+    {
+        if utilization > limit {
+            return Some(format!("GPU is {:.0}% busy, above {:.0}%", utilization, limit));
+        }
+    }
+    None
+}
+
+/// Read power source, temperature and GPU load
+pub async fn read_system_state() -> SystemState {
+    let (gpu_temperature, gpu_utilization) = read_nvidia_gpu().await.unzip();
+    let temperature = match (read_cpu_temperature().await, gpu_temperature) {
+        (Some(cpu), Some(gpu)) => Some(cpu.max(gpu)),
+        (cpu, gpu) => cpu.or(gpu),
+    };
+    SystemState {
+        on_battery: read_on_battery().await,
+        temperature_celsius: temperature,
+        gpu_utilization,
+    }
+}
+
+/// Process creation flag keeping a console program from opening a window
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Standard output of a short-lived command, if it ran successfully in time
+async fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args).kill_on_drop(true);
+    // Polled over and over; a console flashing up each time would steal focus
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output();
+    match tokio::time::timeout(READ_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        _ => None,
+    }
+}
+
+async fn read_on_battery() -> Option<bool> {
+    match std::env::consts::OS {
+        "linux" => {
+            let mut supplies = tokio::fs::read_dir("/sys/class/power_supply").await.ok()?;
+            let mut batteries = Vec::new();
+            while let Ok(Some(supply)) = supplies.next_entry().await {
+                let read = |name: &str| std::fs::read_to_string(supply.path().join(name));
+                if read("type").is_ok_and(|kind| kind.trim() == "Battery") {
+                    batteries.push(read("status").unwrap_or_default().trim().to_string());
+                }
+            }
+            // Desktops have no battery at all
+            Some(batteries.iter().any(|status| status == "Discharging"))
+        }
+        "macos" => command_output("pmset", &["-g", "batt"])
+            .await
+            .map(|output| output.contains("'Battery Power'")),
+        "windows" => {
+            let query = "(Get-CimInstance Win32_Battery).BatteryStatus";
+            let output = command_output("powershell", &["-NoProfile", "-Command", query]).await?;
+            // 1 means the battery is discharging; no output means there is no battery
+            Some(output.lines().any(|status| status.trim() == "1"))
+        }
+        _ => None,
+    }
+}
+
+/// Hottest thermal zone; only Linux exposes these without extra tools
+async fn read_cpu_temperature() -> Option<f32> {
+    let mut zones = tokio::fs::read_dir("/sys/class/thermal").await.ok()?;
+    let mut hottest: Option<f32> = None;
+    while let Ok(Some(zone)) = zones.next_entry().await {
+        let Ok(millidegrees) = tokio::fs::read_to_string(zone.path().join("temp")).await else {
+            continue;
+        };
+        if let Ok(millidegrees) = millidegrees.trim().parse::<f32>() {
+            let celsius = millidegrees / 1000.0;
+            hottest = Some(hottest.map_or(celsius, |max| max.max(celsius)));
+        }
+    }
+    hottest
+}
+
+/// Temperature and utilization of the busiest NVIDIA GPU
+async fn read_nvidia_gpu() -> Option<(f32, f32)> {
+    let query = "--query-gpu=temperature.gpu,utilization.gpu";
+    let output = command_output("nvidia-smi", &[query, "--format=csv,noheader,nounits"]).await?;
+    output
+        .lines()
+        .filter_map(|line| {
+            let (temperature, utilization) = line.split_once(',')?;
+            Some((temperature.trim().parse().ok()?, utilization.trim().parse().ok()?))
+        })
+        .max_by(|a: &(f32, f32), b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hold_reason() {
+        let settings = EcoSettings {
+            enabled: true,
+            ..Default::default()
+        };
+        let state = SystemState {
+            on_battery: Some(false),
+            temperature_celsius: Some(60.0),
+            gpu_utilization: Some(95.0),
+        };
+        assert_eq!(
+            hold_reason(&settings, &state, false).as_deref(),
+            Some("GPU is 95% busy, above 80%")
+        );
+        assert_eq!(hold_reason(&settings, &state, true), None);
+
+        let on_battery = SystemState {
+            on_battery: Some(true),
+            ..Default::default()
+        };
+        assert!(hold_reason(&settings, &on_battery, false).is_some());
+        assert!(hold_reason(&EcoSettings::default(), &on_battery, false).is_none());
+        assert!(hold_reason(&settings, &SystemState::default(), false).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+//! Custom API roots for cloud providers, for regional endpoints, Azure-style
+//! deployments and AI gateways in front of the public hostnames.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+use crate::db::operations::SettingsOps;
+
+/// Settings key for the custom base URLs
+pub const BASE_URLS_SETTINGS_KEY: &str = "provider_base_urls";
+
+/// Base URLs keyed by provider; providers without an entry use their public API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaseUrlSettings {
+    pub providers: BTreeMap<String, String>,
+}
+
+pub async fn load(pool: &SqlitePool) -> Result<BaseUrlSettings> {
+    SettingsOps::get_or_default(pool, BASE_URLS_SETTINGS_KEY).await
+}
+
+/// Save or, with `None`, clear a provider's base URL
+pub async fn save(pool: &SqlitePool, provider: &str, base_url: Option<&str>) -> Result<()> {
+    let mut settings = load(pool).await?;
+    match base_url {
+        Some(base_url) => {
+            settings
+                .providers
+                .insert(provider.to_string(), base_url.to_string());
+        }
+        None => {
+            settings.providers.remove(provider);
+        }
+    }
+    SettingsOps::set(pool, BASE_URLS_SETTINGS_KEY, &settings).await
+}
+
+/// Normalize a user-entered base URL; blank input clears it
+pub fn normalize(base_url: Option<&str>) -> Result<Option<String>> {
+    let Some(base_url) = base_url.map(str::trim).filter(|url| !url.is_empty()) else {
+        return Ok(None);
+    };
+    let parsed = reqwest::Url::parse(base_url)
+        .map_err(|e| anyhow::anyhow!("Invalid base URL {:?}: {}", base_url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(anyhow::anyhow!("Base URL must use http or https"));
+    }
+    Ok(Some(base_url.trim_end_matches('/').to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Some(" https://gw.example.com/openai/v1/ ")).unwrap().as_deref(),
+            Some("https://gw.example.com/openai/v1")
+        );
+        assert_eq!(normalize(Some("")).unwrap(), None);
+        assert_eq!(normalize(None).unwrap(), None);
+        assert!(normalize(Some("ftp://example.com")).is_err());
+        assert!(normalize(Some("not a url")).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Provider failures sorted into the kinds the frontend and retry logic act on.
+//!
+//! Providers keep returning `anyhow` errors; [`GenerationError::classify`] turns them into
+//! a serializable error so the UI can show what to do next instead of a raw API message.
+
+use serde::{Deserialize, Serialize};
+
+use super::policy::ProviderPolicyError;
+use super::rate_limit::RateLimitedError;
+use super::utils::ApiStatusError;
+use crate::i18n;
+
+/// Phrases providers use when they refuse a prompt or an output
+const CONTENT_POLICY_MARKERS: [&str; 4] =
+    ["content_policy", "content policy", "safety", "moderation"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GenerationError {
+    /// API key missing, invalid or without access to the model
+    AuthFailed { message: String },
+    /// Too many requests; `retry_after` is the wait in seconds the provider asked for
+    RateLimited {
+        message: String,
+        retry_after: Option<u64>,
+    },
+    /// The provider refused the prompt or the output
+    ContentPolicy { message: String },
+    Timeout { message: String },
+    /// The provider could not be reached or failed on its side
+    ProviderUnavailable { message: String },
+    /// The request was rejected; `field` names the parameter when the provider says
+    InvalidParams {
+        message: String,
+        field: Option<String>,
+    },
+    Other { message: String },
+}
+
+impl GenerationError {
+    pub fn missing_key(provider: &str) -> Self {
+        Self::AuthFailed {
+            message: i18n::t("error.missing_key", &[("provider", provider)]),
+        }
+    }
+
+    pub fn invalid_param(field: &str, message: String) -> Self {
+        Self::InvalidParams {
+            message,
+            field: Some(field.to_string()),
+        }
+    }
+
+    /// Sort any provider error into a kind
+    pub fn classify(error: &anyhow::Error) -> Self {
+        let message = error.to_string();
+
+        if let Some(known) = error.chain().find_map(|e| e.downcast_ref::<GenerationError>()) {
+            return known.clone();
+        }
+        if let Some(rate_limited) = error.downcast_ref::<RateLimitedError>() {
+            return Self::RateLimited {
+                message,
+                retry_after: rate_limited.retry_after.map(|d| d.as_secs_f64().ceil() as u64),
+            };
+        }
+        if let Some(policy) = error.downcast_ref::<ProviderPolicyError>() {
+            return Self::invalid_param("provider", policy.to_string());
+        }
+        if let Some(api_error) = error.downcast_ref::<ApiStatusError>() {
+            return Self::from_status(api_error.status, message);
+        }
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                if e.is_timeout() {
+                    return Self::Timeout { message };
+                }
+                if e.is_connect() {
+                    return Self::ProviderUnavailable { message };
+                }
+            }
+        }
+        Self::Other { message }
+    }
+
+    fn from_status(status: u16, message: String) -> Self {
+        let is_policy = {
+            let lower = message.to_lowercase();
+            CONTENT_POLICY_MARKERS.iter().any(|marker| lower.contains(marker))
+        };
+        match status {
+            401 | 403 => Self::AuthFailed { message },
+            408 | 504 => Self::Timeout { message },
+            429 => Self::RateLimited {
+                message,
+                retry_after: None,
+            },
+            451 => Self::ContentPolicy { message },
+            400 | 422 if is_policy => Self::ContentPolicy { message },
+            400 | 404 | 413 | 422 => Self::InvalidParams {
+                field: error_param(&message),
+                message,
+            },
+            500.. => Self::ProviderUnavailable { message },
+            _ => Self::Other { message },
+        }
+    }
+
+    /// Name stored with failed jobs, matching the serialized `kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AuthFailed { .. } => "auth_failed",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::ContentPolicy { .. } => "content_policy",
+            Self::Timeout { .. } => "timeout",
+            Self::ProviderUnavailable { .. } => "provider_unavailable",
+            Self::InvalidParams { .. } => "invalid_params",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    /// The message with a summary of the kind, in the active locale
+    pub fn localized(&self) -> String {
+        i18n::t(&format!("error.{}", self.kind()), &[("message", self.message())])
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::AuthFailed { message }
+            | Self::RateLimited { message, .. }
+            | Self::ContentPolicy { message }
+            | Self::Timeout { message }
+            | Self::ProviderUnavailable { message }
+            | Self::InvalidParams { message, .. }
+            | Self::Other { message } => message,
+        }
+    }
+
+    /// Whether the same request may succeed later or on another provider
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited { .. } | Self::Timeout { .. } | Self::ProviderUnavailable { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for GenerationError {}
+
+/// Parameter named in a JSON error body, e.g. OpenAI's `{"error": {"param": "size"}}`
+fn error_param(message: &str) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_str(&message[message.find('{')?..]).ok()?;
+    let error = body.get("error").unwrap_or(&body);
+    error
+        .get("param")
+        .or_else(|| error.get("field"))
+        .and_then(|param| param.as_str())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_status_errors() {
+        let status = |status, message: &str| {
+            GenerationError::classify(&anyhow::Error::new(ApiStatusError {
+                status,
+                message: message.to_string(),
+            }))
+        };
+        assert_eq!(status(401, "bad key").kind(), "auth_failed");
+        assert_eq!(status(503, "down").kind(), "provider_unavailable");
+        let refused = r#"OpenAI API error (400): {"error": {"code": "content_policy_violation"}}"#;
+        assert_eq!(status(400, refused).kind(), "content_policy");
+        assert_eq!(
+            status(400, r#"OpenAI API error (400): {"error": {"param": "size"}}"#),
+            GenerationError::InvalidParams {
+                message: r#"OpenAI API error (400): {"error": {"param": "size"}}"#.to_string(),
+                field: Some("size".to_string()),
+            }
+        );
+
+        let missing = anyhow::Error::new(GenerationError::missing_key("OpenAI"));
+        assert_eq!(GenerationError::classify(&missing).kind(), "auth_failed");
+        assert!(!GenerationError::classify(&anyhow::anyhow!("boom")).is_retryable());
+    }
+}
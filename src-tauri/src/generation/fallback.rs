@@ -0,0 +1,95 @@
+//! Fallback chains: other providers to try when one fails for a transient reason.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::errors::GenerationError;
+use super::GenerationResult;
+
+/// Settings key for the configured fallback chains
+pub const FALLBACK_SETTINGS_KEY: &str = "fallback_chains";
+
+/// A provider to fall back to, with the model to request from it
+///
+/// Model names never carry over between providers, so each step names its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+/// Fallback chains, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallbackSettings {
+    /// Ordered fallbacks keyed by the provider they stand in for
+    pub chains: HashMap<String, Vec<FallbackTarget>>,
+}
+
+/// Whether a failure may succeed on another provider
+///
+/// Outages, timeouts and exhausted rate limits qualify; rejected keys, invalid
+/// requests and policy failures would not be fixed by switching providers.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    GenerationError::classify(error).is_retryable()
+}
+
+/// Note in the result which provider fulfilled it and which ones failed before it
+pub fn record_fulfillment(
+    result: &mut GenerationResult,
+    provider: &str,
+    model: &str,
+    failed: &[String],
+) {
+    let Some(metadata) = result.metadata.as_object_mut() else {
+        return;
+    };
+    metadata.insert(
+        "fulfilled_by".to_string(),
+        serde_json::json!({ "provider": provider, "model": model }),
+    );
+    if !failed.is_empty() {
+        metadata.insert("fallback_from".to_string(), serde_json::json!(failed));
+    }
+}
+
+/// Provider and model that fulfilled a result, if it came from a fallback
+pub fn fallback_provider(result: &GenerationResult) -> Option<(&str, &str)> {
+    result.metadata.get("fallback_from")?;
+    let fulfilled = result.metadata.get("fulfilled_by")?;
+    Some((
+        fulfilled.get("provider")?.as_str()?,
+        fulfilled.get("model")?.as_str()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::utils::ApiStatusError;
+
+    #[test]
+    fn test_only_transient_failures_fall_back() {
+        let status = |status| {
+            anyhow::Error::new(ApiStatusError {
+                status,
+                message: "failed".to_string(),
+            })
+        };
+        assert!(is_retryable(&status(503)));
+        assert!(is_retryable(&status(429)));
+        assert!(!is_retryable(&status(401)));
+        assert!(!is_retryable(&status(400)));
+        assert!(!is_retryable(&anyhow::anyhow!("Invalid prompt")));
+    }
+
+    #[test]
+    fn test_record_fulfillment() {
+        let mut result = GenerationResult::new(Vec::new(), serde_json::json!({}));
+        record_fulfillment(&mut result, "openai", "gpt-image-1", &[]);
+        assert_eq!(fallback_provider(&result), None);
+
+        record_fulfillment(&mut result, "grok", "grok-2-image", &["openai".to_string()]);
+        assert_eq!(fallback_provider(&result), Some(("grok", "grok-2-image")));
+    }
+}
@@ -0,0 +1,147 @@
+//! Extra HTTP headers sent with every request of a provider, for gateways and proxies
+//! that want their own authentication or tracing (`Helicone-Auth`, OpenRouter's
+//! `X-Title`, LiteLLM metadata headers).
+//!
+//! Only the header names are saved in the settings table. Values often carry gateway
+//! credentials, so they are kept in the credential store like API keys.
+
+use anyhow::Result;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::db::operations::SettingsOps;
+use crate::secrets;
+
+/// Settings key for the extra headers of cloud providers
+pub const HEADERS_SETTINGS_KEY: &str = "provider_headers";
+
+/// Headers the HTTP client manages itself
+const RESERVED: &[&str] = &["host", "content-length", "content-type", "transfer-encoding"];
+
+/// Extra headers keyed by cloud provider; saved with empty values
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderSettings {
+    pub providers: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Credential store entry holding a provider's header values
+pub fn secret_name(provider: &str) -> String {
+    format!("headers:{}", provider)
+}
+
+/// Header names by provider, as saved in the settings table
+pub async fn load_names(pool: &SqlitePool) -> Result<HeaderSettings> {
+    SettingsOps::get_or_default(pool, HEADERS_SETTINGS_KEY).await
+}
+
+/// Headers by provider with their values from the credential store
+///
+/// Values still saved in the settings table are moved to the credential store.
+pub async fn load(pool: &SqlitePool, data_dir: &Path) -> Result<HeaderSettings> {
+    let mut settings = load_names(pool).await?;
+    for (provider, headers) in settings.providers.iter_mut() {
+        if headers.values().any(|value| !value.is_empty()) {
+            save(pool, data_dir, provider, headers).await?;
+            continue;
+        }
+        // Credential store calls can block (Secret Service goes over D-Bus)
+        let (dir, name) = (data_dir.to_path_buf(), secret_name(provider));
+        let saved = tokio::task::spawn_blocking(move || secrets::load_api_key(&dir, &name))
+            .await??;
+        let values: BTreeMap<String, String> = match saved {
+            Some(saved) => serde_json::from_str(&saved)?,
+            None => BTreeMap::new(),
+        };
+        for (name, value) in headers.iter_mut() {
+            *value = values.get(name).cloned().unwrap_or_default();
+        }
+    }
+    Ok(settings)
+}
+
+/// Save or, with an empty map, clear a provider's headers
+pub async fn save(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    provider: &str,
+    headers: &BTreeMap<String, String>,
+) -> Result<()> {
+    let mut settings = load_names(pool).await?;
+    let (dir, name) = (data_dir.to_path_buf(), secret_name(provider));
+    if headers.is_empty() {
+        settings.providers.remove(provider);
+        tokio::task::spawn_blocking(move || secrets::delete_api_key(&dir, &name)).await??;
+    } else {
+        let values = serde_json::to_string(headers)?;
+        tokio::task::spawn_blocking(move || secrets::store_api_key(&dir, &name, &values))
+            .await??;
+        let names = headers.keys().map(|name| (name.clone(), String::new())).collect();
+        settings.providers.insert(provider.to_string(), names);
+    }
+    SettingsOps::set(pool, HEADERS_SETTINGS_KEY, &settings).await
+}
+
+/// Check user-entered headers, trimming names and values
+pub fn normalize(headers: &BTreeMap<String, String>) -> Result<BTreeMap<String, String>> {
+    let mut normalized = BTreeMap::new();
+    for (name, value) in headers {
+        let (name, value) = (name.trim(), value.trim());
+        let parsed = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid header name {:?}", name))?;
+        if RESERVED.contains(&parsed.as_str()) {
+            return Err(anyhow::anyhow!("The {} header cannot be overridden", name));
+        }
+        HeaderValue::from_str(value)
+            .map_err(|_| anyhow::anyhow!("Invalid value for header {}", name))?;
+        normalized.insert(name.to_string(), value.to_string());
+    }
+    Ok(normalized)
+}
+
+/// Headers for an HTTP client; invalid entries are skipped with a warning
+///
+/// Values are marked sensitive since gateways often take credentials this way.
+pub fn header_map(headers: &BTreeMap<String, String>) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        match (
+            HeaderName::from_bytes(name.trim().as_bytes()),
+            HeaderValue::from_str(value.trim()),
+        ) {
+            (Ok(name), Ok(mut value)) => {
+                value.set_sensitive(true);
+                map.insert(name, value);
+            }
+            _ => log_warn!("[Headers] Skipping invalid header {:?}", name),
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_map() {
+        let headers = BTreeMap::from([
+            (" X-Title ".to_string(), " PromptCraft ".to_string()),
+            ("Helicone-Auth".to_string(), "Bearer sk-test".to_string()),
+        ]);
+        let normalized = normalize(&headers).unwrap();
+        assert_eq!(normalized["X-Title"], "PromptCraft");
+
+        let map = header_map(&normalized);
+        assert_eq!(map["x-title"], "PromptCraft");
+        assert!(map["helicone-auth"].is_sensitive());
+
+        let reserved = BTreeMap::from([("Content-Type".to_string(), "text/plain".to_string())]);
+        assert!(normalize(&reserved).is_err());
+        let invalid = BTreeMap::from([("Bad Name".to_string(), "x".to_string())]);
+        assert!(normalize(&invalid).is_err());
+    }
+}
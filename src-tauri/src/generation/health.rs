@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+use super::errors::GenerationError;
+use super::network::is_network_error;
+
+/// Event emitted once the startup tests of all providers have finished
+pub const PROVIDERS_READY_EVENT: &str = "providers-ready";
+
+/// Payload of the `providers-ready` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvidersReadyEvent {
+    pub providers: Vec<ProviderTestResult>,
+}
+
+/// Why a provider connection test failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorKind {
+    /// No API key or URL configured
+    NotConfigured,
+    /// The key was rejected (401/403)
+    BadKey,
+    /// The backend could not be reached
+    Network,
+    RateLimited,
+    /// The backend answered with a 5xx status
+    Server,
+    Other,
+}
+
+/// Outcome of `test_provider`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderTestResult {
+    pub provider: String,
+    pub success: bool,
+    /// Round trip of the test request in milliseconds
+    pub latency_ms: u64,
+    pub error_kind: Option<ProviderErrorKind>,
+    pub message: Option<String>,
+    /// Models reported by the backend, when the test lists them
+    pub models: Option<usize>,
+}
+
+/// Sort a provider error into the categories shown to the user
+///
+/// Uses [`GenerationError::classify`], so tests and jobs agree on what went wrong.
+pub fn classify(error: &anyhow::Error) -> ProviderErrorKind {
+    match GenerationError::classify(error) {
+        GenerationError::AuthFailed { .. } => ProviderErrorKind::BadKey,
+        GenerationError::RateLimited { .. } => ProviderErrorKind::RateLimited,
+        GenerationError::Timeout { .. } => ProviderErrorKind::Network,
+        GenerationError::ProviderUnavailable { .. } if is_network_error(error) => {
+            ProviderErrorKind::Network
+        }
+        GenerationError::ProviderUnavailable { .. } => ProviderErrorKind::Server,
+        GenerationError::ContentPolicy { .. }
+        | GenerationError::InvalidParams { .. }
+        | GenerationError::Other { .. } => ProviderErrorKind::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::utils::ApiStatusError;
+
+    #[test]
+    fn test_classify_status_errors() {
+        let error = |status| {
+            anyhow::Error::new(ApiStatusError {
+                status,
+                message: "failed".to_string(),
+            })
+        };
+        assert_eq!(classify(&error(401)), ProviderErrorKind::BadKey);
+        assert_eq!(classify(&error(403)), ProviderErrorKind::BadKey);
+        assert_eq!(classify(&error(503)), ProviderErrorKind::Server);
+        assert_eq!(classify(&error(404)), ProviderErrorKind::Other);
+        assert_eq!(classify(&anyhow::anyhow!("boom")), ProviderErrorKind::Other);
+
+        let missing = anyhow::Error::from(GenerationError::missing_key("OpenAI"));
+        assert_eq!(classify(&missing), ProviderErrorKind::BadKey);
+    }
+}
@@ -0,0 +1,149 @@
+//! The HTTP client shared by providers.
+//!
+//! Every provider gets its client here, so connection pooling, HTTP/2, compression,
+//! keepalive, the user agent and the proxy are set in one place. Providers with the same
+//! timeouts and headers share one client and with it their pooled connections, which
+//! matters when many jobs (or several API keys of one provider) hit the same host.
+//! Changed settings apply to providers configured afterwards.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+use super::headers::header_map;
+use super::timeouts::ProviderTimeouts;
+use crate::db::models::APP_VERSION;
+
+/// Settings key for the HTTP client settings
+pub const HTTP_SETTINGS_KEY: &str = "http_client";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Replaces the default `PromptCraft-Desktop/<version>`
+    pub user_agent: Option<String>,
+    /// Proxy for all provider traffic, e.g. `http://proxy:3128`; the system proxy otherwise
+    pub proxy_url: Option<String>,
+    /// Idle connections kept open per host
+    pub max_idle_per_host: usize,
+    /// How long an idle pooled connection is kept
+    pub idle_timeout_seconds: u64,
+    /// TCP keepalive interval; 0 turns keepalive off
+    pub keepalive_seconds: u64,
+    /// Accept gzip-compressed responses
+    pub gzip: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            proxy_url: None,
+            max_idle_per_host: 16,
+            idle_timeout_seconds: 90,
+            keepalive_seconds: 60,
+            gzip: true,
+        }
+    }
+}
+
+impl HttpSettings {
+    /// Check the proxy URL before it is saved
+    pub fn validate(&self) -> Result<()> {
+        if let Some(proxy_url) = self.proxy_url.as_deref().filter(|url| !url.trim().is_empty()) {
+            reqwest::Proxy::all(proxy_url.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid proxy URL {:?}: {}", proxy_url, e))?;
+        }
+        Ok(())
+    }
+}
+
+type ClientKey = (ProviderTimeouts, BTreeMap<String, String>);
+
+struct HttpState {
+    settings: RwLock<HttpSettings>,
+    clients: Mutex<HashMap<ClientKey, reqwest::Client>>,
+}
+
+fn state() -> &'static HttpState {
+    static STATE: OnceLock<HttpState> = OnceLock::new();
+    STATE.get_or_init(|| HttpState {
+        settings: RwLock::new(HttpSettings::default()),
+        clients: Mutex::new(HashMap::new()),
+    })
+}
+
+/// Use new settings for clients built from now on
+pub fn configure(settings: HttpSettings) {
+    let http = state();
+    *http.settings.write().unwrap() = settings;
+    http.clients.lock().unwrap().clear();
+}
+
+pub fn settings() -> HttpSettings {
+    state().settings.read().unwrap().clone()
+}
+
+/// Client with `timeouts` that adds `headers` to every request, shared with other
+/// providers configured the same way
+pub fn client(timeouts: &ProviderTimeouts, headers: &BTreeMap<String, String>) -> reqwest::Client {
+    let key = (*timeouts, headers.clone());
+    let mut clients = state().clients.lock().unwrap();
+    if let Some(client) = clients.get(&key) {
+        return client.clone();
+    }
+
+    let client = build(timeouts, headers).unwrap_or_else(|e| {
+        log_warn!("[HTTP] Failed to build client, using defaults: {}", e);
+        reqwest::Client::new()
+    });
+    clients.insert(key, client.clone());
+    client
+}
+
+fn build(
+    timeouts: &ProviderTimeouts,
+    headers: &BTreeMap<String, String>,
+) -> Result<reqwest::Client> {
+    let settings = settings();
+    let user_agent = settings
+        .user_agent
+        .clone()
+        .filter(|agent| !agent.trim().is_empty())
+        .unwrap_or_else(|| format!("PromptCraft-Desktop/{}", APP_VERSION));
+    let keepalive = (settings.keepalive_seconds > 0)
+        .then(|| Duration::from_secs(settings.keepalive_seconds));
+
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .default_headers(header_map(headers))
+        .connect_timeout(Duration::from_secs(timeouts.connect_seconds))
+        .read_timeout(Duration::from_secs(timeouts.read_seconds))
+        .pool_max_idle_per_host(settings.max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(settings.idle_timeout_seconds))
+        .tcp_keepalive(keepalive)
+        .http2_adaptive_window(true)
+        .gzip(settings.gzip);
+    if let Some(proxy_url) = settings.proxy_url.as_deref().filter(|url| !url.trim().is_empty()) {
+        builder = builder.proxy(reqwest::Proxy::all(proxy_url.trim())?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_validation() {
+        assert!(HttpSettings::default().validate().is_ok());
+        let proxy = |url: &str| HttpSettings {
+            proxy_url: Some(url.to_string()),
+            ..Default::default()
+        };
+        assert!(proxy("http://proxy.local:3128").validate().is_ok());
+        assert!(proxy("not a url").validate().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Retries of failed local generations with slightly changed parameters.
+//!
+//! Some failures on local backends are deterministic for a given seed and sampler
+//! (NaN latents, a sampler crashing on a specific resolution, ...), so retrying the
+//! exact same request would fail again.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::network;
+use super::policy::ProviderPolicyError;
+use super::rate_limit::RateLimitedError;
+use super::utils::ApiStatusError;
+
+/// Parameter changed by a jittered retry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JitterChange {
+    pub parameter: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// Samplers tried in turn, per backend naming
+fn sampler_cycle(provider: &str) -> &'static [&'static str] {
+    match provider {
+        "a1111" => &["Euler a", "DPM++ 2M Karras", "Euler", "DDIM"],
+        "comfyui" => &["euler", "dpmpp_2m", "euler_ancestral", "ddim"],
+        "invokeai" => &["euler", "dpmpp_2m", "euler_a", "ddim"],
+        _ => &[],
+    }
+}
+
+/// Whether a failed generation is worth a jittered retry
+///
+/// Only local backends qualify, and only for failures on the backend's side:
+/// auth and other 4xx errors, rate limits and unreachable backends would fail the
+/// same way whatever the seed.
+pub fn should_jitter(provider: &str, error: &anyhow::Error) -> bool {
+    if !network::is_local_provider(provider) || network::is_network_error(error) {
+        return false;
+    }
+    if error.downcast_ref::<RateLimitedError>().is_some()
+        || error.downcast_ref::<ProviderPolicyError>().is_some()
+    {
+        return false;
+    }
+    match error.downcast_ref::<ApiStatusError>() {
+        Some(api_error) => api_error.status >= 500,
+        None => true,
+    }
+}
+
+/// Parameters for jittered retry number `attempt` (starting at 1), with what changed
+///
+/// Every attempt picks a new seed; from the second attempt on the sampler also moves
+/// to the next one in the backend's cycle.
+pub fn jitter_parameters(
+    provider: &str,
+    params: &Value,
+    attempt: u32,
+    new_seed: u32,
+) -> (Value, Vec<JitterChange>) {
+    let mut params = match params {
+        Value::Object(_) => params.clone(),
+        _ => serde_json::json!({}),
+    };
+    let mut changes = Vec::new();
+
+    let old_seed = params.get("seed").cloned().unwrap_or(Value::Null);
+    let seed = serde_json::json!(new_seed);
+    if old_seed != seed {
+        params["seed"] = seed.clone();
+        changes.push(JitterChange {
+            parameter: "seed".to_string(),
+            from: old_seed,
+            to: seed,
+        });
+    }
+
+    let samplers = sampler_cycle(provider);
+    if attempt >= 2 && !samplers.is_empty() {
+        let old_sampler = params.get("sampler").cloned().unwrap_or(Value::Null);
+        let current = old_sampler
+            .as_str()
+            .and_then(|s| samplers.iter().position(|candidate| *candidate == s));
+        let next = match current {
+            Some(index) => samplers[(index + 1) % samplers.len()],
+            None => samplers[(attempt as usize - 1) % samplers.len()],
+        };
+        let sampler = serde_json::json!(next);
+        if old_sampler != sampler {
+            params["sampler"] = sampler.clone();
+            changes.push(JitterChange {
+                parameter: "sampler".to_string(),
+                from: old_sampler,
+                to: sampler,
+            });
+        }
+    }
+
+    (params, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_jitter_parameters() {
+        let params = json!({ "seed": 42, "sampler": "euler", "steps": 20 });
+
+        let (first, changes) = jitter_parameters("comfyui", &params, 1, 7);
+        assert_eq!(first["seed"], 7);
+        assert_eq!(first["sampler"], "euler");
+        assert_eq!(changes.len(), 1);
+
+        let (second, changes) = jitter_parameters("comfyui", &first, 2, 8);
+        assert_eq!(second["sampler"], "dpmpp_2m");
+        assert_eq!(second["steps"], 20);
+        assert_eq!(changes[1].from, json!("euler"));
+    }
+
+    #[test]
+    fn test_should_jitter_only_backend_failures() {
+        let server_error = anyhow::Error::new(ApiStatusError {
+            status: 500,
+            message: "A1111 API error (500): NaN".to_string(),
+        });
+        let auth_error = anyhow::Error::new(ApiStatusError {
+            status: 401,
+            message: "A1111 API error (401)".to_string(),
+        });
+
+        assert!(should_jitter("a1111", &server_error));
+        assert!(should_jitter("comfyui", &anyhow::anyhow!("No images generated")));
+        assert!(!should_jitter("a1111", &auth_error));
+        assert!(!should_jitter("openai", &server_error));
+    }
+}
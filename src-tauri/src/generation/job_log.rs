@@ -0,0 +1,130 @@
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use super::{GenerationRequest, GenerationResult};
+use crate::db::operations::JobLogOps;
+
+/// Longest string kept verbatim in a log payload; longer ones (base64 images) are elided
+const MAX_LOGGED_STRING: usize = 2000;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Object keys whose values are always redacted
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "authorization",
+    "x-api-key",
+    "token",
+    "access_token",
+    "secret",
+    "password",
+];
+
+/// Copy of a payload that is safe to store: secrets redacted, huge strings elided
+pub fn sanitize_payload(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let sanitized = if SECRET_KEYS.contains(&key.to_lowercase().as_str()) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        sanitize_payload(value)
+                    };
+                    (key.clone(), sanitized)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_payload).collect()),
+        Value::String(text) => Value::String(sanitize_string(text)),
+        other => other.clone(),
+    }
+}
+
+fn sanitize_string(text: &str) -> String {
+    if let Some(rest) = text.strip_prefix("Bearer ") {
+        if !rest.is_empty() {
+            return format!("Bearer {}", REDACTED);
+        }
+    }
+    if text.len() > MAX_LOGGED_STRING {
+        let head: String = text.chars().take(64).collect();
+        return format!("{}... [{} bytes omitted]", head, text.len() - head.len());
+    }
+    text.to_string()
+}
+
+/// Store a log entry for a job; failures are only reported, never fail the job
+pub async fn record(
+    pool: &SqlitePool,
+    job_id: &str,
+    kind: &str,
+    provider: Option<&str>,
+    payload: Value,
+) {
+    let payload = sanitize_payload(&payload);
+    if let Err(e) = JobLogOps::create(pool, job_id, kind, provider, &payload).await {
+        log_warn!(
+            "[JobLog] Failed to store {} log for job {}: {}",
+            kind, job_id, e
+        );
+    }
+}
+
+/// Log the request sent to a provider
+pub async fn record_request(
+    pool: &SqlitePool,
+    job_id: &str,
+    provider: &str,
+    request: &GenerationRequest,
+) {
+    let payload = serde_json::to_value(request).unwrap_or_default();
+    record(pool, job_id, "request", Some(provider), payload).await;
+}
+
+/// Log a provider's response, or the error it failed with
+pub async fn record_outcome(
+    pool: &SqlitePool,
+    job_id: &str,
+    provider: &str,
+    outcome: Result<&GenerationResult, &anyhow::Error>,
+) {
+    match outcome {
+        Ok(result) => {
+            let payload = serde_json::to_value(result).unwrap_or_default();
+            record(pool, job_id, "response", Some(provider), payload).await;
+        }
+        Err(error) => {
+            let causes: Vec<String> = error.chain().map(|cause| cause.to_string()).collect();
+            let payload = serde_json::json!({ "error": error.to_string(), "causes": causes });
+            record(pool, job_id, "error", Some(provider), payload).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize_payload() {
+        let image = format!("data:image/png;base64,{}", "A".repeat(5000));
+        let payload = json!({
+            "prompt": "a fox",
+            "headers": { "Authorization": "Bearer sk-secret", "X-Api-Key": "abc" },
+            "parameters": { "reference_images": [{ "data": image }], "api_key": "sk-1" },
+        });
+
+        let sanitized = sanitize_payload(&payload);
+        assert_eq!(sanitized["prompt"], "a fox");
+        assert_eq!(sanitized["headers"]["Authorization"], REDACTED);
+        assert_eq!(sanitized["headers"]["X-Api-Key"], REDACTED);
+        assert_eq!(sanitized["parameters"]["api_key"], REDACTED);
+        let data = sanitized["parameters"]["reference_images"][0]["data"]
+            .as_str()
+            .unwrap();
+        assert!(data.len() < 200 && data.ends_with("bytes omitted]"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Several API keys per cloud provider, used in turn or switched when one hits its limits.
+//!
+//! The key saved by `configure_provider` is the provider's `default` key and keeps its
+//! original credential store entry; additional keys are stored as `<provider>:<id>`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::sync::RwLock;
+
+use super::rate_limit::RateLimitedError;
+use super::utils::ApiStatusError;
+use super::GenerationService;
+use crate::db::models::now;
+use crate::db::operations::SettingsOps;
+use crate::secrets;
+
+/// Settings key for the key lists and rotation mode of each provider
+pub const API_KEYS_SETTINGS_KEY: &str = "api_keys";
+
+/// ID of the key saved by `configure_provider`
+pub const DEFAULT_KEY_ID: &str = "default";
+
+/// How requests are spread over a provider's keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRotation {
+    /// Each request uses the next key
+    RoundRobin,
+    /// Keep using one key until it is rate limited or out of quota
+    #[default]
+    OnLimit,
+}
+
+/// A saved key; the secret itself stays in the credential store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    /// User-facing name, e.g. "Personal" or "Studio"
+    pub label: String,
+    pub created_at: String,
+}
+
+/// Keys of one provider, in rotation order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderKeys {
+    pub rotation: KeyRotation,
+    pub keys: Vec<ApiKeyInfo>,
+}
+
+impl ProviderKeys {
+    /// Add the default key to the list if it is not there yet
+    pub fn ensure_default(&mut self) {
+        if !self.keys.iter().any(|k| k.id == DEFAULT_KEY_ID) {
+            self.keys.insert(
+                0,
+                ApiKeyInfo {
+                    id: DEFAULT_KEY_ID.to_string(),
+                    label: "Default".to_string(),
+                    created_at: now(),
+                },
+            );
+        }
+    }
+}
+
+/// Key lists of every provider, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiKeySettings {
+    pub providers: BTreeMap<String, ProviderKeys>,
+}
+
+/// Saved key list of a provider
+///
+/// Installs from before multiple keys only have the default key, which is assumed.
+pub async fn provider_keys(pool: &SqlitePool, provider: &str) -> Result<ProviderKeys> {
+    let settings: ApiKeySettings = SettingsOps::get_or_default(pool, API_KEYS_SETTINGS_KEY).await?;
+    Ok(settings.providers.get(provider).cloned().unwrap_or_else(|| {
+        let mut keys = ProviderKeys::default();
+        keys.ensure_default();
+        keys
+    }))
+}
+
+pub async fn save_provider_keys(pool: &SqlitePool, provider: &str, keys: ProviderKeys) -> Result<()> {
+    let mut settings: ApiKeySettings =
+        SettingsOps::get_or_default(pool, API_KEYS_SETTINGS_KEY).await?;
+    settings.providers.insert(provider.to_string(), keys);
+    SettingsOps::set(pool, API_KEYS_SETTINGS_KEY, &settings).await
+}
+
+/// Credential store entry of a key
+pub fn secret_name(provider: &str, key_id: &str) -> String {
+    if key_id == DEFAULT_KEY_ID {
+        provider.to_string()
+    } else {
+        format!("{}:{}", provider, key_id)
+    }
+}
+
+/// `(key id, secret)` pairs in rotation order; keys missing from the store are skipped
+pub fn load_keys(data_dir: &Path, provider: &str, keys: &ProviderKeys) -> Vec<(String, String)> {
+    keys.keys
+        .iter()
+        .filter_map(|info| {
+            match secrets::load_api_key(data_dir, &secret_name(provider, &info.id)) {
+                Ok(secret) => secret.map(|secret| (info.id.clone(), secret)),
+                Err(e) => {
+                    log_warn!("[Keys] Failed to load {} key {}: {}", provider, info.id, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Re-read a provider's keys from the credential store and apply them to the service
+pub async fn reload(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    service: &RwLock<GenerationService>,
+    provider: &str,
+) -> Result<()> {
+    let keys = provider_keys(pool, provider).await?;
+    let rotation = keys.rotation;
+
+    // Credential store calls can block (Secret Service goes over D-Bus)
+    let (data_dir, name) = (data_dir.to_path_buf(), provider.to_string());
+    let loaded = tokio::task::spawn_blocking(move || load_keys(&data_dir, &name, &keys)).await?;
+    service
+        .write()
+        .await
+        .configure_provider_keys(provider, loaded, rotation)
+}
+
+/// Whether a failure means this key is spent for now and another key may work
+pub fn is_key_exhausted(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<RateLimitedError>().is_some()
+        || error
+            .downcast_ref::<ApiStatusError>()
+            .is_some_and(|e| e.status == 402)
+}
+
+/// Key indices to try for one request, starting at `start`
+pub fn rotation_order(start: usize, len: usize) -> Vec<usize> {
+    (0..len).map(|offset| (start + offset) % len).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_order_wraps() {
+        assert_eq!(rotation_order(0, 3), vec![0, 1, 2]);
+        assert_eq!(rotation_order(2, 3), vec![2, 0, 1]);
+        assert_eq!(rotation_order(4, 3), vec![1, 2, 0]);
+    }
+}
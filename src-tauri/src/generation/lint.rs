@@ -0,0 +1,304 @@
+//! Prompt linting: quick rule checks for prompts that will not do what the user expects.
+//!
+//! Rules only look at the text and the target provider and model, so they are cheap enough
+//! to run while the user types. An LLM review can be asked for on top of them.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{network, weights};
+use super::{GenerationRequest, GenerationService};
+
+/// Tokens a CLIP text encoder reads per chunk, not counting start and end tokens
+pub const CLIP_TOKEN_LIMIT: usize = 75;
+
+/// Words that describe quality or medium but not what the image shows
+const FILLER_WORDS: [&str; 26] = [
+    "masterpiece",
+    "best",
+    "quality",
+    "high",
+    "highly",
+    "ultra",
+    "detailed",
+    "uhd",
+    "hdr",
+    "sharp",
+    "focus",
+    "trending",
+    "artstation",
+    "award",
+    "winning",
+    "photorealistic",
+    "realistic",
+    "cinematic",
+    "dramatic",
+    "lighting",
+    "beautiful",
+    "intricate",
+    "epic",
+    "style",
+    "art",
+    "resolution",
+];
+
+/// Style terms that pull the image in opposite directions
+const STYLE_CONFLICTS: [(&[&str], &[&str]); 4] = [
+    (
+        &["photorealistic", "photograph", "dslr", "realistic photo"],
+        &["anime", "cartoon", "pixel art", "line art", "watercolor", "cel shaded"],
+    ),
+    (&["black and white", "monochrome", "grayscale"], &["vibrant colors", "colorful"]),
+    (&["minimalist", "simple background"], &["highly detailed background", "intricate"]),
+    (&["night", "moonlight"], &["midday", "bright daylight"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+}
+
+/// One finding, with a fix when there is an obvious one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintIssue {
+    /// Rule id, e.g. `conflicting_styles`
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+/// Provider and model asked to review a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintReviewer {
+    pub provider: String,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptLintReport {
+    pub issues: Vec<LintIssue>,
+    /// Rough CLIP token count, set for models with a CLIP text encoder
+    pub clip_tokens: Option<usize>,
+    /// Free-form feedback from the reviewer, when one was asked for and answered
+    pub review: Option<String>,
+}
+
+impl LintIssue {
+    fn new(
+        rule: &str,
+        severity: LintSeverity,
+        message: String,
+        suggestion: Option<String>,
+    ) -> Self {
+        Self {
+            rule: rule.to_string(),
+            severity,
+            message,
+            suggestion,
+        }
+    }
+}
+
+/// Whether prompts for this model go through a CLIP text encoder with its 75 token window
+///
+/// FLUX and SD3 checkpoints read the prompt mainly through T5, cloud models have their own
+/// encoders.
+pub fn uses_clip(provider: &str, model: &str) -> bool {
+    let model = model.to_lowercase();
+    network::is_local_provider(provider) && !model.contains("flux") && !model.contains("sd3")
+}
+
+/// Rough CLIP token count: weighting syntax is dropped, words count one token per
+/// eight characters and punctuation one each
+pub fn estimate_clip_tokens(prompt: &str) -> usize {
+    let text = strip_weights(prompt);
+    let mut tokens = 0;
+    let mut word_len = 0;
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        if word_len > 0 {
+            tokens += 1 + (word_len - 1) / 8;
+            word_len = 0;
+        }
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens
+}
+
+/// Run the rule checks
+pub fn lint(prompt: &str, provider: &str, model: &str) -> PromptLintReport {
+    let lower = prompt.to_lowercase();
+    let mut issues = Vec::new();
+
+    if !has_subject(&lower) {
+        issues.push(LintIssue::new(
+            "missing_subject",
+            LintSeverity::Warning,
+            "The prompt only describes quality or style, not what to show".to_string(),
+            Some("Start with the subject, e.g. \"a lighthouse on a cliff, ...\"".to_string()),
+        ));
+    }
+
+    for (left, right) in STYLE_CONFLICTS {
+        let (Some(a), Some(b)) = (find_term(&lower, left), find_term(&lower, right)) else {
+            continue;
+        };
+        issues.push(LintIssue::new(
+            "conflicting_styles",
+            LintSeverity::Warning,
+            format!("\"{}\" and \"{}\" pull the image in different directions", a, b),
+            Some(format!("Keep either \"{}\" or \"{}\"", a, b)),
+        ));
+    }
+
+    let clip_tokens = uses_clip(provider, model).then(|| estimate_clip_tokens(prompt));
+    if let Some(tokens) = clip_tokens.filter(|tokens| *tokens > CLIP_TOKEN_LIMIT) {
+        issues.push(LintIssue::new(
+            "clip_length",
+            LintSeverity::Info,
+            format!(
+                "About {} tokens; CLIP reads {} at a time, so later words carry less weight",
+                tokens, CLIP_TOKEN_LIMIT
+            ),
+            Some("Put the subject first and drop filler terms".to_string()),
+        ));
+    }
+
+    if let Some(issue) = check_weighting(prompt, provider) {
+        issues.push(issue);
+    }
+
+    PromptLintReport {
+        issues,
+        clip_tokens,
+        review: None,
+    }
+}
+
+/// Ask an LLM for feedback on the prompt
+pub async fn review(
+    service: &GenerationService,
+    reviewer: &LintReviewer,
+    prompt: &str,
+    target_model: &str,
+) -> Result<String> {
+    let instructions = format!(
+        "Review this image generation prompt for the model {}. List up to five concrete \
+         problems (ambiguity, conflicting details, missing subject or composition) and \
+         suggest an improved prompt. Be brief.\n\nPrompt: {}",
+        target_model, prompt
+    );
+    let request = GenerationRequest {
+        prompt: instructions,
+        model: reviewer.model.clone(),
+        parameters: serde_json::json!({ "max_tokens": 1024, "temperature": 0.3 }),
+    };
+    let result = service.generate(&reviewer.provider, request).await?;
+    result
+        .output_data()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No review received from {}", reviewer.provider))
+}
+
+/// Whether any word is left once filler, short words and numbers like `8k` are skipped
+fn has_subject(lower: &str) -> bool {
+    strip_weights(lower)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2 && !word.starts_with(|c: char| c.is_ascii_digit()))
+        .any(|word| !FILLER_WORDS.contains(&word))
+}
+
+/// First term of `terms` found as whole words in `lower`
+fn find_term<'a>(lower: &str, terms: &[&'a str]) -> Option<&'a str> {
+    terms.iter().copied().find(|term| {
+        lower.match_indices(term).any(|(start, _)| {
+            let before = lower[..start].chars().next_back();
+            let after = lower[start + term.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    })
+}
+
+/// Flag weighting syntax that will not reach the provider as meant
+///
+/// `(word:1.2)` weights are translated for every backend (see [`weights`]), but
+/// Compel's `word++` is only understood by InvokeAI.
+fn check_weighting(prompt: &str, provider: &str) -> Option<LintIssue> {
+    if has_compel_weights(prompt) && provider != "invokeai" {
+        return Some(LintIssue::new(
+            "unsupported_weighting",
+            LintSeverity::Warning,
+            format!("{} does not understand word++ weighting", provider),
+            Some("Use (word:1.2) to strengthen a term".to_string()),
+        ));
+    }
+    if !network::is_local_provider(provider) && weights::has_weights(prompt) {
+        return Some(LintIssue::new(
+            "plain_language_weights",
+            LintSeverity::Info,
+            format!("{} has no weighting; weights become a plain language note", provider),
+            Some("Describe emphasis in words, e.g. \"with a strong focus on ...\"".to_string()),
+        ));
+    }
+    None
+}
+
+/// Whether the prompt uses Compel's `word++`, `word--` or `(words)+` weighting
+///
+/// Doubled signs only count right after a word or `)`, so Midjourney parameters such as
+/// `--ar 16:9` are not taken for weights.
+fn has_compel_weights(prompt: &str) -> bool {
+    let chars: Vec<char> = prompt.chars().collect();
+    let doubled_sign = chars.windows(3).any(|w| {
+        (w[0].is_alphanumeric() || w[0] == ')') && w[1] == w[2] && matches!(w[1], '+' | '-')
+    });
+    doubled_sign || prompt.contains(")+")
+}
+
+/// Drop brackets and numeric weights so only the words remain
+fn strip_weights(prompt: &str) -> String {
+    weights::parse(prompt).into_iter().map(|run| run.text).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(report: &PromptLintReport) -> Vec<&str> {
+        report.issues.iter().map(|issue| issue.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let filler = lint("masterpiece, best quality, 8k, detailed", "openai", "gpt-image-1");
+        assert_eq!(rules(&filler), vec!["missing_subject"]);
+
+        let conflict = lint("a photorealistic anime girl", "openai", "gpt-image-1");
+        assert_eq!(rules(&conflict), vec!["conflicting_styles"]);
+
+        let weighted = lint("a (red:1.3) fox in snow", "openai", "gpt-image-1");
+        assert_eq!(rules(&weighted), vec!["plain_language_weights"]);
+        assert!(lint("a (red:1.3) fox in snow", "a1111", "sd15").issues.is_empty());
+        assert_eq!(rules(&lint("a red++ fox", "comfyui", "sd15")), vec!["unsupported_weighting"]);
+        let parameters = lint("a red fox in snow --ar 16:9 --no trees", "midjourney", "midjourney");
+        assert!(!rules(&parameters).contains(&"unsupported_weighting"));
+
+        let long = vec!["a small red fox"; 30].join(", ");
+        assert_eq!(rules(&lint(&long, "a1111", "v1-5-pruned")), vec!["clip_length"]);
+        assert_eq!(lint(&long, "comfyui", "flux1-dev").clip_tokens, None);
+    }
+
+    #[test]
+    fn test_estimate_clip_tokens() {
+        assert_eq!(estimate_clip_tokens("a red fox"), 3);
+        assert_eq!(estimate_clip_tokens("(red:1.3) fox, snow"), 4);
+    }
+}
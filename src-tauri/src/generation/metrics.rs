@@ -0,0 +1,92 @@
+//! Cheap quality heuristics for generated images, used to compare sweep results.
+//!
+//! Images are measured on their luma, scaled down to a common size so results of
+//! different resolutions compare fairly. Sharpness is the variance of the Laplacian
+//! (blurry or washed-out outputs score low), contrast the standard deviation of luma.
+
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::GrayImage;
+use serde::Serialize;
+use std::path::Path;
+
+/// Longest side images are scaled to before measuring
+const MEASURE_SIZE: u32 = 512;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageMetrics {
+    pub file_size: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Variance of the Laplacian of luma; higher is crisper
+    pub sharpness: f64,
+    /// Standard deviation of luma, 0 to 255
+    pub contrast: f64,
+}
+
+/// Measure an image file; blocking, so run it off the async runtime
+pub fn measure(path: &Path) -> Result<ImageMetrics> {
+    let file_size = std::fs::metadata(path)?.len();
+    let image = image::open(path)?;
+    let (width, height) = (image.width(), image.height());
+    let gray = image
+        .resize(MEASURE_SIZE, MEASURE_SIZE, FilterType::Triangle)
+        .to_luma8();
+
+    Ok(ImageMetrics {
+        file_size,
+        width,
+        height,
+        sharpness: sharpness(&gray),
+        contrast: contrast(&gray),
+    })
+}
+
+/// Variance of the 4-neighbour Laplacian over the image's interior
+pub fn sharpness(gray: &GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let at = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let laplacian: Vec<f64> = (1..height - 1)
+        .flat_map(|y| (1..width - 1).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y)
+        })
+        .collect();
+    variance(&laplacian)
+}
+
+/// Standard deviation of luma
+pub fn contrast(gray: &GrayImage) -> f64 {
+    let luma: Vec<f64> = gray.pixels().map(|pixel| pixel[0] as f64).collect();
+    variance(&luma).sqrt()
+}
+
+fn variance(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn test_flat_images_score_zero() {
+        let flat = GrayImage::from_pixel(16, 16, Luma([128]));
+        assert_eq!(sharpness(&flat), 0.0);
+        assert_eq!(contrast(&flat), 0.0);
+
+        let level = |on: bool| Luma([if on { 255 } else { 0 }]);
+        let checker = GrayImage::from_fn(16, 16, |x, y| level((x + y) % 2 == 1));
+        let stripes = GrayImage::from_fn(16, 16, |x, _| level(x >= 8));
+        assert!(sharpness(&checker) > sharpness(&stripes));
+        assert!((contrast(&checker) - 127.5).abs() < 1e-9);
+    }
+}
@@ -1,11 +1,63 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+pub mod budget;
+pub mod cache;
+pub mod clip;
+pub mod confirmation;
+pub mod defaults;
+pub mod deprecation;
+pub mod duplicates;
+pub mod eco;
+pub mod endpoints;
+pub mod errors;
+pub mod fallback;
+pub mod headers;
+pub mod health;
+pub mod http;
+pub mod jitter;
+pub mod job_log;
+pub mod keys;
+pub mod lint;
+pub mod metrics;
+pub mod narration;
+pub mod network;
+pub mod pipeline;
+pub mod policy;
+pub mod power;
+pub mod presets;
+pub mod pricing;
 pub mod processor;
+pub mod provenance;
 pub mod providers;
+pub mod rate_limit;
+pub mod refine;
+pub mod rerun;
+pub mod routing;
+pub mod sweep;
+pub mod timeouts;
+pub mod usage;
 pub mod utils;
+pub mod validation;
+pub mod weights;
+
+use crate::storage::{filesystem::FilesystemBackend, StorageBackend};
+use fallback::FallbackTarget;
+use keys::KeyRotation;
+use rate_limit::{RateLimitedError, RateLimiter};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use utils::MAX_RATE_LIMIT_WAIT;
+
+/// How many times a 429 response is retried before the generation fails, unless configured
+pub const DEFAULT_MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// How long a provider's model list is reused before it is fetched again
+const MODEL_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
 
 /// Generation request parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,23 +67,233 @@ pub struct GenerationRequest {
     pub parameters: serde_json::Value,
 }
 
+/// One output of a generation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Artifact {
+    /// Remote URL, or `asset://` URL once the output is saved locally
+    pub url: Option<String>,
+    /// Local file the output was saved to
+    pub file_path: Option<String>,
+    /// Base64 media until the output is saved, or the text of a text generation
+    pub data: Option<String>,
+    pub mime_type: Option<String>,
+    pub seed: Option<i64>,
+}
+
+impl Artifact {
+    pub fn from_url(url: String) -> Self {
+        Self {
+            url: Some(url),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_data(data: String) -> Self {
+        Self {
+            data: Some(data),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(text: String) -> Self {
+        Self {
+            data: Some(text),
+            mime_type: Some("text/plain".to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    pub fn with_seed(mut self, seed: Option<i64>) -> Self {
+        self.seed = seed.filter(|seed| *seed >= 0);
+        self
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.mime_type.as_deref().is_some_and(|mime| mime.starts_with("text/"))
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.mime_type.as_deref().is_some_and(|mime| mime.starts_with("audio/"))
+    }
+}
+
 /// Generation result
+///
+/// Serialized with the first artifact also in `output_url`, `output_data` and
+/// `file_path`, the shape of results from before they held several outputs, so
+/// stored jobs and the code reading them keep working.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredResult", into = "StoredResult")]
 pub struct GenerationResult {
-    pub output_url: Option<String>,
-    pub output_data: Option<String>,
-    pub file_path: Option<String>,
+    /// Outputs in the order the provider returned them
+    pub artifacts: Vec<Artifact>,
     pub metadata: serde_json::Value,
 }
 
+impl GenerationResult {
+    pub fn new(artifacts: Vec<Artifact>, metadata: serde_json::Value) -> Self {
+        Self {
+            artifacts,
+            metadata,
+        }
+    }
+
+    pub fn first(&self) -> Option<&Artifact> {
+        self.artifacts.first()
+    }
+
+    /// Inline data of the first output, which is the text of a text generation
+    pub fn output_data(&self) -> Option<&str> {
+        self.first()?.data.as_deref()
+    }
+
+    pub fn file_path(&self) -> Option<&str> {
+        self.first()?.file_path.as_deref()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredResult {
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+    #[serde(default)]
+    output_url: Option<String>,
+    #[serde(default)]
+    output_data: Option<String>,
+    #[serde(default)]
+    file_path: Option<String>,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+impl From<StoredResult> for GenerationResult {
+    fn from(stored: StoredResult) -> Self {
+        let mut artifacts = stored.artifacts;
+        let legacy = Artifact {
+            url: stored.output_url,
+            file_path: stored.file_path,
+            data: stored.output_data,
+            ..Artifact::default()
+        };
+        if artifacts.is_empty() && legacy != Artifact::default() {
+            artifacts.push(legacy);
+        }
+        Self::new(artifacts, stored.metadata)
+    }
+}
+
+impl From<GenerationResult> for StoredResult {
+    fn from(result: GenerationResult) -> Self {
+        let first = result.artifacts.first().cloned().unwrap_or_default();
+        Self {
+            output_url: first.url,
+            output_data: first.data,
+            file_path: first.file_path,
+            artifacts: result.artifacts,
+            metadata: result.metadata,
+        }
+    }
+}
+
 /// Progress update for streaming generation
-#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerationProgress {
+    /// Completion in the range 0-100; 0 when the provider cannot estimate it
     pub percentage: f32,
     pub message: String,
 }
 
+/// Channel providers use to report progress while a generation runs
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<GenerationProgress>;
+
+/// Partial output of a streamed generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamChunk {
+    /// Text to append to the output so far
+    Text { delta: String },
+    /// Intermediate image of a running generation, base64 encoded
+    Preview { data: String, percentage: f32 },
+    /// The finished result; always the last chunk of a successful stream
+    Done { result: GenerationResult },
+}
+
+/// Partial outputs of a generation, ending with [`StreamChunk::Done`] or an error
+pub type GenerationStream<'a> = futures_util::stream::BoxStream<'a, Result<StreamChunk>>;
+
+/// Event carrying the chunks of streamed generations
+pub const STREAM_EVENT: &str = "generation-stream";
+
+/// Payload of the `generation-stream` event
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamEvent<'a> {
+    /// Id chosen by the caller to tell concurrent streams apart
+    pub stream_id: &'a str,
+    pub chunk: &'a StreamChunk,
+}
+
+/// Send a progress update if a listener is attached
+pub fn report_progress(progress: Option<&ProgressSender>, percentage: f32, message: impl Into<String>) {
+    if let Some(tx) = progress {
+        // A closed receiver only means nobody is listening anymore
+        let _ = tx.send(GenerationProgress {
+            percentage: percentage.clamp(0.0, 100.0),
+            message: message.into(),
+        });
+    }
+}
+
+/// A model offered by a provider's backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Identifier to pass as the request's model
+    pub id: String,
+    /// Human-readable name, when the backend reports one
+    pub name: Option<String>,
+}
+
+impl ModelInfo {
+    /// Read models from a JSON array of objects, sorted by id
+    pub fn from_list(list: &serde_json::Value, id_key: &str, name_key: Option<&str>) -> Vec<Self> {
+        let mut models: Vec<Self> = list
+            .as_array()
+            .map(|items| items.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|item| {
+                Some(Self {
+                    id: item.get(id_key)?.as_str()?.to_string(),
+                    name: name_key
+                        .and_then(|key| item.get(key))
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                })
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        models
+    }
+
+    /// Models of a provider without a listing endpoint, from its `(id, name)` table
+    pub fn static_list(models: &[(&str, &str)]) -> Vec<Self> {
+        let mut models: Vec<Self> = models
+            .iter()
+            .map(|(id, name)| Self {
+                id: id.to_string(),
+                name: Some(name.to_string()),
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        models
+    }
+}
+
 /// Provider trait that all generation backends implement
 #[async_trait]
 pub trait GenerationProvider: Send + Sync {
@@ -39,80 +301,491 @@ pub trait GenerationProvider: Send + Sync {
     fn name(&self) -> &str;
 
     /// Check if provider is available (API key configured, etc.)
-    #[allow(dead_code)]
     async fn is_available(&self) -> bool;
 
     /// Generate content based on request
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult>;
 
+    /// Generate content while reporting progress updates
+    ///
+    /// Providers without progress information fall back to `generate`.
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        drop(progress);
+        self.generate(request).await
+    }
+
+    /// Generate content as a stream of partial outputs
+    ///
+    /// Providers that cannot stream keep the default, which only yields the result.
+    fn generate_stream(&self, request: GenerationRequest) -> GenerationStream<'_> {
+        Box::pin(futures_util::stream::once(async move {
+            self.generate(request)
+                .await
+                .map(|result| StreamChunk::Done { result })
+        }))
+    }
+
+    /// Models the backend currently offers
+    ///
+    /// Providers without a model listing keep the default, which lists nothing.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Make a cheap authenticated request, failing if the backend rejects the configuration
+    ///
+    /// Every provider implements this against its API; a static model list would report
+    /// a wrong key as working.
+    async fn test_connection(&self) -> Result<()>;
+
     /// Get provider-specific configuration schema
-    #[allow(dead_code)]
     fn config_schema(&self) -> serde_json::Value;
+
+    /// Schema of the request parameters `model` accepts, checked before jobs are queued
+    ///
+    /// Providers without one keep the default, which lists nothing and accepts anything.
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({ "type": "object", "properties": {} })
+    }
+
+    /// Query the backend for optional features (extensions, plugins, ...) and cache them
+    ///
+    /// Providers without optional features keep the default, which detects nothing.
+    async fn detect_capabilities(&self) -> Result<serde_json::Value> {
+        Ok(self.capabilities())
+    }
+
+    /// Capabilities found by the last `detect_capabilities` call
+    fn capabilities(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+}
+
+/// Provider instances for each API key of one provider
+struct KeyPool {
+    rotation: KeyRotation,
+    /// Key IDs with a provider configured for that key
+    keys: Vec<(String, Box<dyn GenerationProvider>)>,
+    /// Next key for round-robin rotation, current key for on-limit rotation
+    next: std::sync::atomic::AtomicUsize,
 }
 
 /// Generation service that manages all providers
 pub struct GenerationService {
-    providers: std::collections::HashMap<String, Box<dyn GenerationProvider>>,
+    providers: std::collections::HashMap<String, Arc<dyn GenerationProvider>>,
+    rate_limiter: RateLimiter,
+    max_rate_limit_retries: std::sync::atomic::AtomicU32,
+    storage: std::sync::RwLock<Arc<dyn StorageBackend>>,
+    /// Model lists by provider, with the time they were fetched
+    models: std::sync::RwLock<HashMap<String, (Instant, Vec<ModelInfo>)>>,
+    /// Ordered fallbacks keyed by the provider they stand in for
+    fallbacks: std::sync::RwLock<HashMap<String, Vec<FallbackTarget>>>,
+    /// Providers configured with API keys through `configure_provider_keys`
+    key_pools: HashMap<String, KeyPool>,
+    /// Custom API roots of cloud providers, applied when they are next configured
+    base_urls: HashMap<String, String>,
+    /// Timeouts overriding a provider's defaults, applied when it is next configured
+    timeouts: HashMap<String, timeouts::ProviderTimeouts>,
+    /// Extra request headers of cloud providers, applied when they are next configured
+    headers: HashMap<String, std::collections::BTreeMap<String, String>>,
+    /// Handling of prompts longer than a CLIP window
+    clip: std::sync::RwLock<clip::ClipSettings>,
+    /// Outcome of the latest connection test of each provider
+    health: std::sync::RwLock<HashMap<String, health::ProviderTestResult>>,
+    /// Preference order for `auto` requests
+    routing: std::sync::RwLock<routing::RoutingSettings>,
 }
 
 impl GenerationService {
     pub fn new() -> Self {
         Self {
             providers: std::collections::HashMap::new(),
+            rate_limiter: RateLimiter::new(),
+            max_rate_limit_retries: std::sync::atomic::AtomicU32::new(DEFAULT_MAX_RATE_LIMIT_RETRIES),
+            storage: std::sync::RwLock::new(Arc::new(FilesystemBackend::local())),
+            models: std::sync::RwLock::new(HashMap::new()),
+            fallbacks: std::sync::RwLock::new(HashMap::new()),
+            key_pools: HashMap::new(),
+            base_urls: HashMap::new(),
+            timeouts: HashMap::new(),
+            headers: HashMap::new(),
+            clip: std::sync::RwLock::new(clip::ClipSettings::default()),
+            health: std::sync::RwLock::new(HashMap::new()),
+            routing: std::sync::RwLock::new(routing::RoutingSettings::default()),
         }
     }
 
     /// Register a new provider
     pub fn register_provider(&mut self, provider: Box<dyn GenerationProvider>) {
         let name = provider.name().to_string();
-        self.providers.insert(name, provider);
+        // A new key or URL may expose different models
+        self.models.write().unwrap().remove(&name);
+        self.providers.insert(name, Arc::from(provider));
+    }
+
+    /// Unregister a provider; returns whether it was registered
+    pub fn remove_provider(&mut self, name: &str) -> bool {
+        self.models.write().unwrap().remove(name);
+        self.health.write().unwrap().remove(name);
+        self.key_pools.remove(name);
+        self.providers.remove(name).is_some()
     }
 
     /// Get provider by name
-    pub fn get_provider(&self, name: &str) -> Option<&Box<dyn GenerationProvider>> {
+    pub fn get_provider(&self, name: &str) -> Option<&Arc<dyn GenerationProvider>> {
         self.providers.get(name)
     }
 
+    /// Shared handle to a provider, for slow calls made without holding the service lock
+    pub fn provider_handle(&self, name: &str) -> Option<Arc<dyn GenerationProvider>> {
+        self.providers.get(name).cloned()
+    }
+
     /// List all available providers
     pub fn list_providers(&self) -> Vec<String> {
         self.providers.keys().cloned().collect()
     }
 
+    /// Limit a provider to `requests_per_minute`, or remove the limit with `None`
+    pub fn set_rate_limit(&self, provider_name: &str, requests_per_minute: Option<u32>) {
+        self.rate_limiter.set_limit(provider_name, requests_per_minute);
+    }
+
+    /// Configured rate limits in requests per minute, keyed by provider
+    pub fn rate_limits(&self) -> std::collections::HashMap<String, u32> {
+        self.rate_limiter.limits()
+    }
+
+    /// Set how many times a rate-limited request is retried
+    pub fn set_max_retries(&self, max_retries: u32) {
+        self.max_rate_limit_retries
+            .store(max_retries, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Replace the fallback chains walked when a provider fails
+    pub fn set_fallbacks(&self, chains: HashMap<String, Vec<FallbackTarget>>) {
+        *self.fallbacks.write().unwrap() = chains;
+    }
+
+    /// Check request parameters against the model's schema
+    ///
+    /// Unknown providers pass; the job fails with a clearer error when it runs.
+    pub fn validate_parameters(
+        &self,
+        provider_name: &str,
+        model: &str,
+        parameters: &serde_json::Value,
+    ) -> Vec<validation::FieldError> {
+        match self.get_provider(provider_name) {
+            Some(provider) => validation::validate(&provider.parameter_schema(model), parameters),
+            None => Vec::new(),
+        }
+    }
+
+    /// Change how prompts longer than a CLIP window are handled
+    pub fn set_clip_settings(&self, settings: clip::ClipSettings) {
+        *self.clip.write().unwrap() = settings;
+    }
+
+    pub fn clip_settings(&self) -> clip::ClipSettings {
+        self.clip.read().unwrap().clone()
+    }
+
+    /// Change the providers `auto` requests prefer
+    pub fn set_routing_settings(&self, settings: routing::RoutingSettings) {
+        *self.routing.write().unwrap() = settings;
+    }
+
+    /// Pick a configured provider for a modality or model family hint
+    ///
+    /// Providers the allowlist excludes are skipped, and those whose latest connection
+    /// test failed are only used when nothing else offers the hint.
+    pub async fn route(&self, hint: &str, allowed: Option<&[String]>) -> Result<FallbackTarget> {
+        let preference = self.routing.read().unwrap().preference.clone();
+        let mut unhealthy = None;
+        for route in routing::candidates(hint, &preference)? {
+            if !policy::is_allowed(allowed, route.provider) {
+                continue;
+            }
+            let Some(provider) = self.providers.get(route.provider) else {
+                continue;
+            };
+            if !provider.is_available().await {
+                continue;
+            }
+            let target = FallbackTarget {
+                provider: route.provider.to_string(),
+                model: route.model.to_string(),
+            };
+            let failed = self
+                .health
+                .read()
+                .unwrap()
+                .get(route.provider)
+                .is_some_and(|result| !result.success);
+            if !failed {
+                return Ok(target);
+            }
+            unhealthy.get_or_insert(target);
+        }
+        unhealthy.ok_or_else(|| {
+            anyhow::anyhow!("No configured provider can handle '{}'", hint)
+        })
+    }
+
+    /// Send generated files to a different storage backend
+    pub fn set_storage(&self, backend: Arc<dyn StorageBackend>) {
+        log_info!("[Storage] Using {} backend", backend.name());
+        *self.storage.write().unwrap() = backend;
+    }
+
     /// Configure a provider with an API key
     pub fn configure_provider(&mut self, provider_name: &str, api_key: String) -> Result<()> {
-        use providers::*;
+        let provider = self.build_cloud_provider(provider_name, api_key)?;
+        self.key_pools.remove(provider_name);
+        self.register_provider(provider);
+        Ok(())
+    }
 
-        // Remove old provider and register new one with config
-        self.providers.remove(provider_name);
+    /// Configure a provider with several API keys, rotated as `rotation` says
+    ///
+    /// `keys` holds `(key id, secret)` pairs; an empty list unregisters the provider.
+    pub fn configure_provider_keys(
+        &mut self,
+        provider_name: &str,
+        keys: Vec<(String, String)>,
+        rotation: KeyRotation,
+    ) -> Result<()> {
+        let Some((_, first_key)) = keys.first() else {
+            self.remove_provider(provider_name);
+            return Ok(());
+        };
 
-        match provider_name {
-            "anthropic" => {
-                let provider = anthropic::AnthropicProvider::with_config(anthropic::AnthropicConfig {
+        // The first key also serves model listing, connection tests and capabilities
+        let provider = self.build_cloud_provider(provider_name, first_key.clone())?;
+        let mut pooled = Vec::with_capacity(keys.len());
+        for (key_id, api_key) in keys {
+            pooled.push((key_id, self.build_cloud_provider(provider_name, api_key)?));
+        }
+
+        self.register_provider(provider);
+        self.key_pools.insert(
+            provider_name.to_string(),
+            KeyPool {
+                rotation,
+                keys: pooled,
+                next: std::sync::atomic::AtomicUsize::new(0),
+            },
+        );
+        Ok(())
+    }
+
+    /// Set or clear the API root of a cloud provider; reconfigure it to take effect
+    pub fn set_base_url(&mut self, provider_name: &str, base_url: Option<String>) {
+        match base_url {
+            Some(base_url) => self.base_urls.insert(provider_name.to_string(), base_url),
+            None => self.base_urls.remove(provider_name),
+        };
+    }
+
+    /// Set or reset the timeouts of a provider; reconfigure it to take effect
+    pub fn set_timeouts(
+        &mut self,
+        provider_name: &str,
+        timeouts: Option<timeouts::ProviderTimeouts>,
+    ) {
+        match timeouts {
+            Some(timeouts) => self
+                .timeouts
+                .insert(provider_name.to_string(), timeouts.normalized()),
+            None => self.timeouts.remove(provider_name),
+        };
+    }
+
+    /// Set or, with an empty map, clear the extra headers of a cloud provider; reconfigure
+    /// it to take effect
+    pub fn set_headers(
+        &mut self,
+        provider_name: &str,
+        headers: std::collections::BTreeMap<String, String>,
+    ) {
+        if headers.is_empty() {
+            self.headers.remove(provider_name);
+        } else {
+            self.headers.insert(provider_name.to_string(), headers);
+        }
+    }
+
+    pub fn timeouts_for(&self, provider_name: &str) -> timeouts::ProviderTimeouts {
+        self.timeouts
+            .get(provider_name)
+            .copied()
+            .unwrap_or_else(|| timeouts::ProviderTimeouts::for_provider(provider_name))
+    }
+
+    fn build_cloud_provider(
+        &self,
+        provider_name: &str,
+        api_key: String,
+    ) -> Result<Box<dyn GenerationProvider>> {
+        use providers::*;
+
+        let base_url = self.base_urls.get(provider_name).cloned();
+        let timeouts = self.timeouts_for(provider_name);
+        let headers = self.headers.get(provider_name).cloned().unwrap_or_default();
+        let provider: Box<dyn GenerationProvider> = match provider_name {
+            "anthropic" => Box::new(anthropic::AnthropicProvider::with_config(
+                anthropic::AnthropicConfig {
                     api_key,
-                });
-                self.register_provider(Box::new(provider));
-            }
-            "openai" => {
-                let provider = openai::OpenAIProvider::with_config(openai::OpenAIConfig {
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
+            "openai" => Box::new(openai::OpenAIProvider::with_config(openai::OpenAIConfig {
+                api_key,
+                organization: None,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "google" => Box::new(google::GoogleProvider::with_config(google::GoogleConfig {
+                api_key,
+                project_id: None,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "grok" => Box::new(grok::GrokProvider::with_config(grok::GrokConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "flux" => Box::new(flux::FluxProvider::with_config(flux::FluxConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "fal" => Box::new(fal::FalProvider::with_config(fal::FalConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "recraft" => Box::new(recraft::RecraftProvider::with_config(recraft::RecraftConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "leonardo" => {
+                Box::new(leonardo::LeonardoProvider::with_config(leonardo::LeonardoConfig {
                     api_key,
-                    organization: None,
-                });
-                self.register_provider(Box::new(provider));
+                    base_url,
+                    timeouts,
+                    headers,
+                }))
             }
-            "google" => {
-                let provider = google::GoogleProvider::with_config(google::GoogleConfig {
+            "runway" => Box::new(runway::RunwayProvider::with_config(runway::RunwayConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "pika" => Box::new(pika::PikaProvider::with_config(pika::PikaConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "minimax" => Box::new(minimax::MiniMaxProvider::with_config(minimax::MiniMaxConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "elevenlabs" => Box::new(elevenlabs::ElevenLabsProvider::with_config(
+                elevenlabs::ElevenLabsConfig {
                     api_key,
-                    project_id: None,
-                });
-                self.register_provider(Box::new(provider));
-            }
-            "grok" => {
-                let provider = grok::GrokProvider::with_config(grok::GrokConfig { api_key });
-                self.register_provider(Box::new(provider));
-            }
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
+            "stability" => Box::new(stability::StabilityProvider::with_config(
+                stability::StabilityConfig {
+                    api_key,
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
+            "vertex" => Box::new(vertex::VertexProvider::with_config(vertex::VertexConfig {
+                credentials: api_key,
+                project_id: None,
+                location: None,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "volcengine" => Box::new(volcengine::VolcengineProvider::with_config(
+                volcengine::VolcengineConfig {
+                    api_key,
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
+            "groq" => Box::new(groq::GroqProvider::with_config(groq::GroqConfig {
+                api_key,
+                base_url,
+                timeouts,
+                headers,
+            })),
+            "perplexity" => Box::new(perplexity::PerplexityProvider::with_config(
+                perplexity::PerplexityConfig {
+                    api_key,
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
+            "midjourney" => Box::new(midjourney::MidjourneyProvider::with_config(
+                midjourney::MidjourneyConfig {
+                    api_key,
+                    base_url,
+                    timeouts,
+                    headers,
+                },
+            )),
             _ => return Err(anyhow::anyhow!("Unknown provider: {}", provider_name)),
-        }
+        };
+        Ok(provider)
+    }
 
+    /// Configure the generic OpenAI-compatible provider
+    pub fn configure_openai_compatible(
+        &mut self,
+        config: providers::openai_compatible::OpenAICompatibleConfig,
+    ) -> Result<()> {
+        if config.base_url.trim().is_empty() {
+            return Err(anyhow::anyhow!("A base URL is required"));
+        }
+        let config = providers::openai_compatible::OpenAICompatibleConfig {
+            timeouts: config.timeouts.normalized(),
+            headers: headers::normalize(&config.headers)?,
+            ..config
+        };
+        self.timeouts
+            .insert("openai_compatible".to_string(), config.timeouts);
+        let provider = providers::openai_compatible::OpenAICompatibleProvider::with_config(config);
+        self.register_provider(Box::new(provider));
         Ok(())
     }
 
@@ -122,20 +795,28 @@ impl GenerationService {
 
         // Remove old provider and register new one with config
         self.providers.remove(provider_name);
+        let timeouts = self.timeouts_for(provider_name);
 
         match provider_name {
             "a1111" => {
-                let provider = a1111::A1111Provider::with_config(a1111::A1111Config { api_url });
+                let provider = a1111::A1111Provider::with_config(a1111::A1111Config {
+                    api_url,
+                    timeouts,
+                });
                 self.register_provider(Box::new(provider));
             }
             "comfyui" => {
-                let provider =
-                    comfyui::ComfyUIProvider::with_config(comfyui::ComfyUIConfig { api_url });
+                let provider = comfyui::ComfyUIProvider::with_config(comfyui::ComfyUIConfig {
+                    api_url,
+                    timeouts,
+                });
                 self.register_provider(Box::new(provider));
             }
             "invokeai" => {
-                let provider =
-                    invokeai::InvokeAIProvider::with_config(invokeai::InvokeAIConfig { api_url });
+                let provider = invokeai::InvokeAIProvider::with_config(invokeai::InvokeAIConfig {
+                    api_url,
+                    timeouts,
+                });
                 self.register_provider(Box::new(provider));
             }
             _ => return Err(anyhow::anyhow!("Unknown local provider: {}", provider_name)),
@@ -144,46 +825,426 @@ impl GenerationService {
         Ok(())
     }
 
+    /// Cached capabilities of a provider
+    pub fn capabilities(&self, provider_name: &str) -> Result<serde_json::Value> {
+        let provider = self
+            .providers
+            .get(provider_name)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_name))?;
+        Ok(provider.capabilities())
+    }
+
+    /// Models offered by a provider, cached for `MODEL_CACHE_TTL` unless `refresh` is set
+    pub async fn list_models(&self, provider_name: &str, refresh: bool) -> Result<Vec<ModelInfo>> {
+        if !refresh {
+            if let Some((fetched_at, models)) = self.models.read().unwrap().get(provider_name) {
+                if fetched_at.elapsed() < MODEL_CACHE_TTL {
+                    return Ok(models.clone());
+                }
+            }
+        }
+
+        let provider = self
+            .providers
+            .get(provider_name)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_name))?;
+        let models = provider.list_models().await?;
+
+        self.models
+            .write()
+            .unwrap()
+            .insert(provider_name.to_string(), (Instant::now(), models.clone()));
+        Ok(models)
+    }
+
+    /// Check a provider's configuration with a cheap authenticated call
+    pub async fn test_provider(&self, provider_name: &str) -> Result<health::ProviderTestResult> {
+        let provider = self
+            .providers
+            .get(provider_name)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_name))?;
+
+        let mut result = health::ProviderTestResult {
+            provider: provider_name.to_string(),
+            success: false,
+            latency_ms: 0,
+            error_kind: None,
+            message: None,
+            models: None,
+        };
+        if !provider.is_available().await {
+            result.error_kind = Some(health::ProviderErrorKind::NotConfigured);
+            result.message = Some(format!("{} is not configured", provider_name));
+            return Ok(self.record_health(result));
+        }
+
+        let started = Instant::now();
+        let limit = self.timeouts_for(provider_name).health_check();
+        let outcome = tokio::time::timeout(limit, provider.test_connection())
+            .await
+            .unwrap_or_else(|_| {
+                Err(anyhow::anyhow!(
+                    "{} did not answer within {} seconds",
+                    provider_name,
+                    limit.as_secs()
+                ))
+            });
+        result.latency_ms = started.elapsed().as_millis() as u64;
+
+        if let Err(e) = outcome {
+            result.error_kind = Some(health::classify(&e));
+            result.message = Some(e.to_string());
+            return Ok(self.record_health(result));
+        }
+        result.success = true;
+
+        // The count is informational; a listing failure does not fail the test
+        if let Ok(Ok(models)) = tokio::time::timeout(limit, provider.list_models()).await {
+            result.models = Some(models.len());
+            self.models
+                .write()
+                .unwrap()
+                .insert(provider_name.to_string(), (Instant::now(), models));
+        }
+        Ok(self.record_health(result))
+    }
+
+    fn record_health(&self, result: health::ProviderTestResult) -> health::ProviderTestResult {
+        let mut health = self.health.write().unwrap();
+        health.insert(result.provider.clone(), result.clone());
+        result
+    }
+
+    /// Test every registered provider concurrently, each within its health check timeout
+    pub async fn test_all_providers(&self) -> Vec<health::ProviderTestResult> {
+        let mut names = self.list_providers();
+        names.sort();
+        let tests = names.iter().map(|name| self.test_provider(name));
+        futures_util::future::join_all(tests)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    /// Latest test result of each provider tested so far, by name
+    pub fn provider_health(&self) -> Vec<health::ProviderTestResult> {
+        let mut results: Vec<_> = self.health.read().unwrap().values().cloned().collect();
+        results.sort_by(|a, b| a.provider.cmp(&b.provider));
+        results
+    }
+
+    /// Configuration schema (JSON Schema) of a provider
+    ///
+    /// Local providers are only registered once configured, so an unconfigured
+    /// instance answers for them.
+    pub fn config_schema(&self, provider_name: &str) -> Result<serde_json::Value> {
+        use providers::*;
+
+        if let Some(provider) = self.providers.get(provider_name) {
+            return Ok(provider.config_schema());
+        }
+
+        match provider_name {
+            "a1111" => Ok(a1111::A1111Provider::new().config_schema()),
+            "comfyui" => Ok(comfyui::ComfyUIProvider::new().config_schema()),
+            "invokeai" => Ok(invokeai::InvokeAIProvider::new().config_schema()),
+            "openai_compatible" => {
+                Ok(openai_compatible::OpenAICompatibleProvider::new().config_schema())
+            }
+            _ => Err(anyhow::anyhow!("Provider not found: {}", provider_name)),
+        }
+    }
+
     /// Generate using a specific provider
     pub async fn generate(
         &self,
         provider_name: &str,
         request: GenerationRequest,
     ) -> Result<GenerationResult> {
+        self.generate_with_progress(provider_name, request, None, None)
+            .await
+    }
+
+    /// Generate using a specific provider, forwarding progress updates if a sender is given
+    ///
+    /// If the provider fails with a retryable error, its fallback chain is tried in
+    /// order, skipping providers that are not configured or not in `allowed`. The result
+    /// metadata records which provider fulfilled the request.
+    pub async fn generate_with_progress(
+        &self,
+        provider_name: &str,
+        request: GenerationRequest,
+        progress: Option<ProgressSender>,
+        allowed: Option<&[String]>,
+    ) -> Result<GenerationResult> {
+        let mut attempts = vec![FallbackTarget {
+            provider: provider_name.to_string(),
+            model: request.model.clone(),
+        }];
+        if let Some(chain) = self.fallbacks.read().unwrap().get(provider_name) {
+            let usable = chain.iter().filter(|target| {
+                target.provider != provider_name
+                    && self.providers.contains_key(&target.provider)
+                    && policy::is_allowed(allowed, &target.provider)
+            });
+            attempts.extend(usable.cloned());
+        }
+
+        let mut failed = Vec::new();
+        let mut result = loop {
+            let target = &attempts[failed.len()];
+            let attempt_request = GenerationRequest {
+                model: target.model.clone(),
+                ..request.clone()
+            };
+            let error = match self
+                .generate_once(&target.provider, attempt_request, progress.as_ref())
+                .await
+            {
+                Ok(result) => break result,
+                Err(e) => e,
+            };
+
+            let next = match attempts.get(failed.len() + 1) {
+                Some(next) if fallback::is_retryable(&error) => next,
+                _ => return Err(error),
+            };
+            log_warn!(
+                "[Fallback] {} failed ({}), trying {}",
+                target.provider, error, next.provider
+            );
+            report_progress(
+                progress.as_ref(),
+                0.0,
+                format!("{} unavailable, falling back to {}", target.provider, next.provider),
+            );
+            failed.push(target.provider.clone());
+        };
+        let target = &attempts[failed.len()];
+        fallback::record_fulfillment(&mut result, &target.provider, &target.model, &failed);
+
+        self.save_outputs(&mut result).await;
+
+        Ok(result)
+    }
+
+    /// Stream a generation from one provider
+    ///
+    /// Streams do not fall back or rotate keys: once chunks reached the user, switching
+    /// to another provider midway would mix two outputs.
+    pub async fn generate_stream(
+        &self,
+        provider_name: &str,
+        request: GenerationRequest,
+    ) -> Result<GenerationStream<'_>> {
         let provider = self
             .get_provider(provider_name)
             .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_name))?;
+        let (request, clip_fit) = clip::fit_request(self, provider_name, request).await;
+        self.rate_limiter.acquire(provider_name).await;
 
-        let mut result = provider.generate(request).await?;
-
-        // Convert base64 output_data to file if present
-        if let Some(base64_data) = &result.output_data {
-            if !base64_data.is_empty() {
-                match save_base64_to_file(base64_data).await {
-                    Ok(file_path) => {
-                        // Convert to Tauri asset protocol URL (https://asset.localhost/...)
-                        // This format is required for Tauri v2 to load local files in the webview
-                        let file_path_str = file_path.display().to_string();
-                        result.output_url = Some(format!("asset://localhost/{}", file_path_str));
-                        // Store the actual file path for opening with system applications
-                        result.file_path = Some(file_path_str);
-                        // Clear the base64 data to save space
-                        result.output_data = None;
+        let request = weights::for_provider(provider_name, request);
+        let Some(clip_fit) = clip_fit else {
+            return Ok(provider.generate_stream(request));
+        };
+        Ok(Box::pin(provider.generate_stream(request).map(move |chunk| {
+            let mut chunk = chunk?;
+            if let StreamChunk::Done { result } = &mut chunk {
+                clip_fit.record(result);
+            }
+            Ok(chunk)
+        })))
+    }
+
+    /// Save base64 outputs to files, replacing the inline data with the file's URL
+    pub async fn save_outputs(&self, result: &mut GenerationResult) {
+        let storage = self.storage.read().unwrap().clone();
+        let audio_storage = FilesystemBackend::audio();
+        let mut storage_urls = Vec::new();
+        for artifact in result.artifacts.iter_mut().filter(|a| !a.is_text()) {
+            let Some(base64_data) = artifact.data.as_deref().filter(|d| !d.is_empty()) else {
+                continue;
+            };
+            // Audio always goes to the local audio folder, apart from the image store
+            let storage: &dyn StorageBackend = if artifact.is_audio() {
+                &audio_storage
+            } else {
+                storage.as_ref()
+            };
+            match save_base64_to_file(storage, base64_data, artifact.mime_type.as_deref()).await
+            {
+                Ok(stored) => {
+                    // Convert to Tauri asset protocol URL (https://asset.localhost/...)
+                    // This format is required for Tauri v2 to load local files in the webview
+                    let file_path_str = stored.file_path.display().to_string();
+                    artifact.url = Some(format!("asset://localhost/{}", file_path_str));
+                    // Store the actual file path for opening with system applications
+                    artifact.file_path = Some(file_path_str);
+                    // Clear the base64 data to save space
+                    artifact.data = None;
+                    storage_urls.extend(stored.remote_url);
+                }
+                Err(e) => {
+                    log_warn!("Warning: Failed to save base64 to file: {}", e);
+                    // Continue with base64 data in the artifact
+                }
+            }
+        }
+        if let (Some(url), Some(metadata)) =
+            (storage_urls.first(), result.metadata.as_object_mut())
+        {
+            metadata.insert("storage_url".to_string(), serde_json::json!(url));
+            if storage_urls.len() > 1 {
+                metadata.insert("storage_urls".to_string(), serde_json::json!(storage_urls));
+            }
+        }
+    }
+
+    /// One provider's generation, retrying rate-limited requests
+    async fn generate_once(
+        &self,
+        provider_name: &str,
+        request: GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let provider = self
+            .get_provider(provider_name)
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_name))?;
+        // Fallbacks land here too, so each provider gets the prompt in its own syntax and
+        // its own CLIP window
+        let (request, clip_fit) = clip::fit_request(self, provider_name, request).await;
+        let request = weights::for_provider(provider_name, request);
+
+        // With several keys, each one is tried before waiting out a rate limit
+        let pool = self.key_pools.get(provider_name);
+        let candidates: Vec<(usize, Option<&str>, &dyn GenerationProvider)> = match pool {
+            Some(pool) => {
+                let start = match pool.rotation {
+                    KeyRotation::RoundRobin => {
+                        pool.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
                     }
-                    Err(e) => {
-                        eprintln!("Warning: Failed to save base64 to file: {}", e);
-                        // Continue with base64 data in output_data
+                    KeyRotation::OnLimit => pool.next.load(std::sync::atomic::Ordering::Relaxed),
+                };
+                keys::rotation_order(start, pool.keys.len())
+                    .into_iter()
+                    .map(|i| (i, Some(pool.keys[i].0.as_str()), pool.keys[i].1.as_ref()))
+                    .collect()
+            }
+            None => vec![(0, None, provider.as_ref())],
+        };
+        let mut key_index = 0;
+
+        let max_retries = self
+            .max_rate_limit_retries
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.acquire(provider_name).await;
+
+            let (_, key_id, provider) = candidates[key_index];
+            log_debug!(
+                "[Generation] Sending {} request for model {}",
+                provider_name,
+                request.model
+            );
+            let outcome = match progress {
+                Some(tx) => {
+                    provider
+                        .generate_with_progress(request.clone(), tx.clone())
+                        .await
+                }
+                None => provider.generate(request.clone()).await,
+            };
+
+            let error = match outcome {
+                Ok(mut result) => {
+                    // Lets usage be accounted per key
+                    let metadata = result.metadata.as_object_mut();
+                    if let (Some(key_id), Some(metadata)) = (key_id, metadata) {
+                        metadata.insert("api_key_id".to_string(), serde_json::json!(key_id));
                     }
+                    if let Some(clip_fit) = &clip_fit {
+                        clip_fit.record(&mut result);
+                    }
+                    return Ok(result);
                 }
+                Err(e) => e,
+            };
+
+            if keys::is_key_exhausted(&error) && key_index + 1 < candidates.len() {
+                key_index += 1;
+                let (next_index, next_key, _) = candidates[key_index];
+                if let Some(pool) = pool.filter(|p| p.rotation == KeyRotation::OnLimit) {
+                    // Later requests start with the key that still works
+                    pool.next
+                        .store(next_index, std::sync::atomic::Ordering::Relaxed);
+                }
+                log_warn!(
+                    "[Keys] {} key {} is limited, switching to key {}",
+                    provider_name,
+                    key_id.unwrap_or_default(),
+                    next_key.unwrap_or_default()
+                );
+                continue;
             }
-        }
 
-        Ok(result)
+            // Only 429 responses are retried here; everything else fails the generation
+            let Some(rate_limited) = error.downcast_ref::<RateLimitedError>() else {
+                return Err(error);
+            };
+            if attempt >= max_retries {
+                return Err(error);
+            }
+            attempt += 1;
+
+            let wait = rate_limited
+                .retry_after
+                .unwrap_or_else(|| std::time::Duration::from_secs(5 << attempt))
+                .min(MAX_RATE_LIMIT_WAIT);
+            log_warn!(
+                "{} rate limited (attempt {}/{}), retrying in {}s",
+                provider_name,
+                attempt,
+                max_retries,
+                wait.as_secs()
+            );
+            report_progress(
+                progress,
+                0.0,
+                format!("Rate limited by {}, retrying in {}s", provider_name, wait.as_secs()),
+            );
+            tokio::time::sleep(wait).await;
+        }
     }
 }
 
-/// Save base64 image data to a file and return the path
-async fn save_base64_to_file(base64_data: &str) -> Result<PathBuf> {
+/// Local asset store for generated and imported images (`~/Pictures/Promptcraft`)
+pub fn images_dir() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not get home directory"))?;
+
+    let images_dir = home_dir.join("Pictures").join("Promptcraft");
+    std::fs::create_dir_all(&images_dir)?;
+    Ok(images_dir)
+}
+
+/// Local store for generated speech and music (`~/Music/Promptcraft`)
+pub fn audio_dir() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not get home directory"))?;
+
+    let audio_dir = home_dir.join("Music").join("Promptcraft");
+    std::fs::create_dir_all(&audio_dir)?;
+    Ok(audio_dir)
+}
+
+/// Save base64 image, video or audio data through the storage backend
+async fn save_base64_to_file(
+    storage: &dyn StorageBackend,
+    base64_data: &str,
+    mime_type: Option<&str>,
+) -> Result<crate::storage::StoredAsset> {
     use base64::{Engine as _, engine::general_purpose};
 
     // Strip data URL prefix if present (e.g., "data:image/png;base64,")
@@ -202,20 +1263,45 @@ async fn save_base64_to_file(base64_data: &str) -> Result<PathBuf> {
     // Decode base64
     let image_bytes = general_purpose::STANDARD.decode(&cleaned_data)?;
 
-    // Get Pictures/Promptcraft directory
-    let home_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not get home directory"))?;
-
-    let images_dir = home_dir.join("Pictures").join("Promptcraft");
-    std::fs::create_dir_all(&images_dir)?;
-
     // Generate unique filename with random UUID to avoid collisions
     let uuid = uuid::Uuid::new_v4();
-    let filename = format!("gen_{}.png", uuid);
-    let file_path = images_dir.join(filename);
+    let extension = match mime_type {
+        Some("image/jpeg") => "jpg",
+        Some("image/webp") => "webp",
+        Some("image/svg+xml") => "svg",
+        Some("video/mp4") => "mp4",
+        Some("audio/mpeg") => "mp3",
+        Some("audio/wav") => "wav",
+        Some("audio/ogg") => "ogg",
+        Some("audio/aac") => "aac",
+        Some("audio/flac") => "flac",
+        _ => "png",
+    };
+    let filename = format!("gen_{}.{}", uuid, extension);
 
-    // Write to file using tokio for async I/O
-    tokio::fs::write(&file_path, &image_bytes).await?;
+    storage.put(&filename, &image_bytes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(file_path)
+    #[test]
+    fn test_result_keeps_legacy_shape() {
+        let legacy: GenerationResult = serde_json::from_value(serde_json::json!({
+            "output_url": "asset://localhost//tmp/a.png",
+            "file_path": "/tmp/a.png",
+            "metadata": {}
+        }))
+        .unwrap();
+        assert_eq!(legacy.file_path(), Some("/tmp/a.png"));
+
+        let result = GenerationResult::new(
+            vec![Artifact::from_url("a".to_string()), Artifact::from_url("b".to_string())],
+            serde_json::json!({}),
+        );
+        let stored = serde_json::to_value(&result).unwrap();
+        assert_eq!(stored["output_url"], "a");
+        assert_eq!(stored["artifacts"].as_array().unwrap().len(), 2);
+    }
 }
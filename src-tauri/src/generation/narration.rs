@@ -0,0 +1,131 @@
+//! Narration and music of storyboard scenes: the audio outputs of a scene's jobs.
+//!
+//! Speech and background music are generated like any other output, by a generation job
+//! that names the scene, so a scene's audio is every audio artifact of its completed
+//! jobs.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use super::GenerationResult;
+use crate::db::models::Job;
+
+/// Providers generating music rather than speech
+const MUSIC_PROVIDERS: &[&str] = &["stability"];
+
+/// One audio output of a scene
+#[derive(Debug, Clone, Serialize)]
+pub struct Narration {
+    pub job_id: String,
+    pub scene_id: String,
+    pub provider: String,
+    pub model: String,
+    /// `speech` or `music`
+    pub kind: String,
+    /// Text that was spoken, or the description of the music
+    pub text: String,
+    pub url: Option<String>,
+    pub file_path: Option<String>,
+    pub mime_type: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+/// Audio outputs of a completed job
+pub fn from_job(job: &Job) -> Vec<Narration> {
+    let Some(scene_id) = job.scene_id.as_deref() else {
+        return Vec::new();
+    };
+    let result: Option<GenerationResult> = job
+        .result
+        .as_deref()
+        .and_then(|r| serde_json::from_str(r).ok());
+    let data: Value = serde_json::from_str(&job.data).unwrap_or_default();
+    let field = |name: &str| {
+        data.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+    };
+
+    result
+        .into_iter()
+        .flat_map(|result| result.artifacts)
+        .filter(|artifact| artifact.is_audio())
+        .map(|artifact| Narration {
+            job_id: job.id.clone(),
+            scene_id: scene_id.to_string(),
+            provider: field("provider"),
+            model: field("model"),
+            kind: if MUSIC_PROVIDERS.contains(&field("provider").as_str()) {
+                "music".to_string()
+            } else {
+                "speech".to_string()
+            },
+            text: field("prompt"),
+            url: artifact.url,
+            file_path: artifact.file_path,
+            mime_type: artifact.mime_type,
+            completed_at: job.completed_at.clone(),
+        })
+        .collect()
+}
+
+/// Audio outputs of a scene's completed generation jobs, oldest first
+pub async fn for_scene(pool: &SqlitePool, scene_id: &str) -> Result<Vec<Narration>> {
+    let jobs: Vec<Job> = sqlx::query_as(
+        r#"
+        SELECT * FROM jobs
+        WHERE scene_id = ? AND type = 'generation' AND status = 'completed'
+        ORDER BY completed_at ASC
+        "#,
+    )
+    .bind(scene_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(jobs.iter().flat_map(from_job).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::Artifact;
+
+    #[test]
+    fn test_from_job_keeps_audio() {
+        let result = GenerationResult::new(
+            vec![
+                Artifact::from_url("a.png".to_string()).with_mime_type("image/png"),
+                Artifact::from_url("a.mp3".to_string()).with_mime_type("audio/mpeg"),
+            ],
+            serde_json::json!({}),
+        );
+        let job = Job {
+            id: "job-1".to_string(),
+            workflow_id: "wf".to_string(),
+            scene_id: Some("scene-1".to_string()),
+            job_type: "generation".to_string(),
+            status: "completed".to_string(),
+            data: r#"{"provider":"elevenlabs","model":"eleven_v3","prompt":"Hello"}"#.to_string(),
+            result: Some(serde_json::to_string(&result).unwrap()),
+            error: None,
+            created_at: String::new(),
+            started_at: None,
+            completed_at: None,
+            scheduled_at: None,
+            app_version: None,
+            retry_of: None,
+            cost: None,
+            lane: "interactive".to_string(),
+            error_kind: None,
+        };
+
+        let narrations = from_job(&job);
+        assert_eq!(narrations.len(), 1);
+        assert_eq!(narrations[0].url.as_deref(), Some("a.mp3"));
+        assert_eq!(narrations[0].text, "Hello");
+        assert_eq!(narrations[0].kind, "speech");
+
+        let unattached = Job { scene_id: None, ..job };
+        assert!(from_job(&unattached).is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// Status of cloud jobs held back until the network is reachable again
+pub const WAITING_NETWORK: &str = "waiting_network";
+
+/// Event emitted to the frontend when connectivity changes
+pub const NETWORK_STATUS_EVENT: &str = "network-status";
+
+/// How often the processor re-checks connectivity
+pub const CONNECTIVITY_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Most times a job that failed to connect goes back to waiting for the network
+pub const MAX_NETWORK_HOLDS: u64 = 5;
+
+/// Hosts probed for connectivity; reaching any of them counts as online
+const PROBE_ADDRESSES: [&str; 2] = ["1.1.1.1:443", "8.8.8.8:443"];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Payload of the `network-status` event
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStatusEvent {
+    pub online: bool,
+    /// Jobs moved from `waiting_network` back to `pending`
+    pub resumed_jobs: u64,
+}
+
+/// Providers that run on the local machine or LAN and keep working offline
+pub const LOCAL_PROVIDERS: &[&str] = &["a1111", "comfyui", "invokeai"];
+
+/// Whether `provider` is one of the [`LOCAL_PROVIDERS`]
+pub fn is_local_provider(provider: &str) -> bool {
+    LOCAL_PROVIDERS.contains(&provider)
+}
+
+/// Check whether the internet is reachable
+pub async fn check_connectivity() -> bool {
+    for address in PROBE_ADDRESSES {
+        let connect = tokio::net::TcpStream::connect(address);
+        if let Ok(Ok(_)) = tokio::time::timeout(PROBE_TIMEOUT, connect).await {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether a generation failed because the provider could not be reached at all
+///
+/// Timeouts do not count: the provider may have received the request and billed it.
+pub fn is_network_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_providers_skip_network_wait() {
+        assert!(is_local_provider("a1111"));
+        assert!(is_local_provider("comfyui"));
+        assert!(!is_local_provider("openai"));
+        assert!(!is_network_error(&anyhow::anyhow!("API error: 500")));
+    }
+}
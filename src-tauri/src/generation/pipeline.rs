@@ -0,0 +1,300 @@
+//! Multi-step pipeline jobs.
+//!
+//! A pipeline job runs its steps in order, feeding each step's output into the next:
+//! text output (e.g. a prompt enhanced by Claude) becomes the next step's `{input}`,
+//! and an image output is attached as the next step's reference image. Each finished
+//! step is written back into the job data, so retrying a failed pipeline resumes
+//! after the last step that succeeded.
+//!
+//! Each step goes through the same routing, parameter presets and spending caps as a
+//! single generation job; a step over its cap pauses or fails the whole pipeline.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::RwLock;
+
+use super::{budget, fallback, job_log, policy, power, presets, routing, usage};
+use super::{
+    report_progress, GenerationRequest, GenerationResult, GenerationService, ProgressSender,
+};
+use crate::db::models::{now, Job};
+use crate::db::operations::JobOps;
+
+/// Job type of pipeline jobs
+pub const PIPELINE_JOB_TYPE: &str = "pipeline";
+
+/// Placeholder in a step prompt replaced by the previous step's text output
+const INPUT_PLACEHOLDER: &str = "{input}";
+
+fn default_true() -> bool {
+    true
+}
+
+/// One generation in a pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    pub name: Option<String>,
+    pub provider: String,
+    pub model: String,
+    /// Prompt template; `{input}` is replaced by the previous step's text output (or the
+    /// pipeline prompt for the first step). Without a template the input is used as is.
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub parameters: Value,
+    /// Attach the previous step's image as this step's reference image
+    #[serde(default = "default_true")]
+    pub use_previous_image: bool,
+}
+
+/// Persisted outcome of a finished step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    pub step: usize,
+    pub result: GenerationResult,
+    pub cost: Option<f64>,
+    pub completed_at: String,
+}
+
+/// Job data of a pipeline job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineData {
+    pub prompt: String,
+    pub steps: Vec<PipelineStep>,
+    #[serde(default)]
+    pub step_results: Vec<StepResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+}
+
+impl PipelineData {
+    pub fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            return Err(anyhow::anyhow!("A pipeline needs at least one step"));
+        }
+        Ok(())
+    }
+}
+
+/// Run the remaining steps of a pipeline job and return the final step's result
+///
+/// Returns `None` when a spending cap paused the job before a step.
+pub async fn run(
+    pool: &SqlitePool,
+    service: &Arc<RwLock<GenerationService>>,
+    app_handle: &AppHandle,
+    job: &Job,
+    mut data: PipelineData,
+    progress: ProgressSender,
+) -> Result<Option<(GenerationResult, Option<f64>)>> {
+    data.validate()?;
+    // Refuse before spending anything if a named provider is not allowed
+    for step in data.steps.iter().filter(|s| s.provider != routing::AUTO_PROVIDER) {
+        policy::check_provider(pool, &job.workflow_id, &step.provider).await?;
+    }
+    let total = data.steps.len();
+
+    // Results of earlier attempts stay valid as long as they are a prefix of the steps
+    data.step_results.retain(|r| r.step < total);
+    data.step_results.sort_by_key(|r| r.step);
+    let resumed = data
+        .step_results
+        .iter()
+        .enumerate()
+        .take_while(|(index, r)| r.step == *index)
+        .count();
+    data.step_results.truncate(resumed);
+    if resumed > 0 {
+        log_info!(
+            "[Pipeline] Resuming job {} at step {}/{}",
+            job.id,
+            resumed + 1,
+            total
+        );
+    }
+
+    for index in resumed..total {
+        let step = data.steps[index].clone();
+        let previous = data.step_results.last().map(|r| &r.result);
+
+        let prompt = step_prompt(&step, &data.prompt, previous);
+        let mut params = match &step.parameters {
+            Value::Object(_) => step.parameters.clone(),
+            _ => serde_json::json!({}),
+        };
+        if step.use_previous_image {
+            if let Some(image) = previous.and_then(previous_image) {
+                params["reference_image"] = serde_json::json!({ "data": image });
+            }
+        }
+
+        let target = routing::resolve(
+            pool,
+            &*service.read().await,
+            Some(&job.workflow_id),
+            &step.provider,
+            &step.model,
+        )
+        .await?;
+        let (provider, model) = (target.provider.as_str(), target.model.as_str());
+        // The same allowlist limits the step's fallbacks
+        let allowed = policy::check_provider(pool, &job.workflow_id, provider).await?;
+        let params = presets::apply(pool, provider, model, params).await?;
+        if budget::enforce(pool, &job.id, provider, model, &params).await? {
+            return Ok(None);
+        }
+
+        let label = step
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", step.provider, step.model));
+        report_progress(
+            Some(&progress),
+            (index as f32 / total as f32) * 100.0,
+            format!("Step {}/{}: {}", index + 1, total, label),
+        );
+
+        let request = GenerationRequest {
+            prompt,
+            model: model.to_string(),
+            parameters: params.clone(),
+        };
+        job_log::record_request(pool, &job.id, provider, &request).await;
+
+        let service_lock = service.read().await;
+        let generation = service_lock.generate_with_progress(
+            provider,
+            request,
+            Some(progress.clone()),
+            allowed.as_deref(),
+        );
+        let outcome = match data.timeout_seconds.filter(|secs| *secs > 0) {
+            Some(secs) => power::timeout(secs, generation).await,
+            None => generation.await,
+        };
+        drop(service_lock);
+
+        job_log::record_outcome(pool, &job.id, provider, outcome.as_ref()).await;
+        let result =
+            outcome.map_err(|e| anyhow::anyhow!("Step {} ({}) failed: {}", index + 1, label, e))?;
+
+        let (billed_provider, billed_model) =
+            fallback::fallback_provider(&result).unwrap_or((provider, model));
+        let cost =
+            usage::record_usage(pool, &job.id, billed_provider, billed_model, &params, &result)
+                .await;
+        if let Some(cost) = cost {
+            if let Err(e) = budget::warn_if_crossed(pool, app_handle, billed_provider, cost).await {
+                log_warn!("[Budget] Failed to check spending of {}: {}", billed_provider, e);
+            }
+        }
+        data.step_results.push(StepResult {
+            step: index,
+            result,
+            cost,
+            completed_at: now(),
+        });
+        JobOps::update_data(pool, &job.id, &serde_json::to_value(&data)?).await?;
+    }
+
+    let costs: Vec<f64> = data.step_results.iter().filter_map(|r| r.cost).collect();
+    let total_cost = (!costs.is_empty()).then(|| costs.iter().sum());
+
+    let mut result = data
+        .step_results
+        .pop()
+        .map(|r| r.result)
+        .ok_or_else(|| anyhow::anyhow!("Pipeline produced no result"))?;
+    if let Some(metadata) = result.metadata.as_object_mut() {
+        metadata.insert("pipeline_steps".to_string(), serde_json::json!(total));
+    }
+
+    Ok(Some((result, total_cost)))
+}
+
+/// Prompt for a step given the pipeline prompt and the previous step's result
+fn step_prompt(
+    step: &PipelineStep,
+    pipeline_prompt: &str,
+    previous: Option<&GenerationResult>,
+) -> String {
+    // Text steps keep their output as inline data without an output file
+    let input = previous
+        .filter(|r| r.file_path().is_none())
+        .and_then(|r| r.output_data())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .unwrap_or(pipeline_prompt);
+
+    match &step.prompt {
+        Some(template) if template.contains(INPUT_PLACEHOLDER) => {
+            template.replace(INPUT_PLACEHOLDER, input)
+        }
+        Some(template) => template.clone(),
+        None => input.to_string(),
+    }
+}
+
+/// The previous step's image as a data URL, if it produced one
+fn previous_image(result: &GenerationResult) -> Option<String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let path = result.file_path()?;
+    let bytes = std::fs::read(path).ok()?;
+    let mime = match std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    };
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::Artifact;
+
+    fn text_result(text: &str) -> GenerationResult {
+        GenerationResult::new(vec![Artifact::text(text.to_string())], serde_json::json!({}))
+    }
+
+    #[test]
+    fn test_step_prompt_chains_text_output() {
+        let step: PipelineStep = serde_json::from_value(serde_json::json!({
+            "provider": "openai",
+            "model": "gpt-image-1",
+            "prompt": "{input}, watercolor",
+        }))
+        .unwrap();
+        assert!(step.use_previous_image);
+
+        let enhanced = text_result("a lighthouse at dusk ");
+        assert_eq!(
+            step_prompt(&step, "lighthouse", Some(&enhanced)),
+            "a lighthouse at dusk, watercolor"
+        );
+        assert_eq!(
+            step_prompt(&step, "lighthouse", None),
+            "lighthouse, watercolor"
+        );
+
+        let untemplated = PipelineStep {
+            prompt: None,
+            ..step
+        };
+        assert_eq!(
+            step_prompt(&untemplated, "lighthouse", Some(&enhanced)),
+            "a lighthouse at dusk"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+use crate::db::operations::WorkflowOps;
+
+/// Error for a job whose provider is not on its workflow's allowlist
+///
+/// Policy failures are final: they are never retried or jittered.
+#[derive(Debug)]
+pub struct ProviderPolicyError {
+    pub provider: String,
+    pub allowed: Vec<String>,
+}
+
+impl std::fmt::Display for ProviderPolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Provider {} is not allowed for this workflow (allowed: {})",
+            self.provider,
+            if self.allowed.is_empty() {
+                "none".to_string()
+            } else {
+                self.allowed.join(", ")
+            }
+        )
+    }
+}
+
+impl std::error::Error for ProviderPolicyError {}
+
+/// Fail with a [`ProviderPolicyError`] if the workflow does not allow `provider`
+///
+/// Returns the allowlist so fallback providers can be checked against it too.
+pub async fn check_provider(
+    pool: &SqlitePool,
+    workflow_id: &str,
+    provider: &str,
+) -> Result<Option<Vec<String>>> {
+    let allowed = WorkflowOps::allowed_providers(pool, workflow_id).await?;
+    if is_allowed(allowed.as_deref(), provider) {
+        return Ok(allowed);
+    }
+
+    Err(ProviderPolicyError {
+        provider: provider.to_string(),
+        allowed: allowed.unwrap_or_default(),
+    }
+    .into())
+}
+
+/// Whether an allowlist permits a provider; `None` means the workflow is unrestricted
+pub fn is_allowed(allowed: Option<&[String]>, provider: &str) -> bool {
+    match allowed {
+        Some(allowed) => allowed.iter().any(|p| p.eq_ignore_ascii_case(provider)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed() {
+        let allowed = vec!["a1111".to_string(), "OpenAI".to_string()];
+        assert!(is_allowed(Some(&allowed), "openai"));
+        assert!(!is_allowed(Some(&allowed), "google"));
+        assert!(!is_allowed(Some(&[]), "openai"));
+        assert!(is_allowed(None, "google"));
+    }
+}
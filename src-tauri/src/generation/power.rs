@@ -0,0 +1,148 @@
+//! Noticing system sleep, so work resumes cleanly on wake.
+//!
+//! The process is frozen while the machine sleeps, so there is nothing to stop beforehand;
+//! what goes wrong is the first minute after wake, when the network is still coming up
+//! and a poll of a perfectly valid Veo or Sora operation fails. Sleep is detected from a
+//! heartbeat whose wall-clock gap far exceeds its interval, which works the same on every
+//! OS. After a wake, [`settle`] holds the processor and polling loops until the network
+//! answers again (or a grace period passes), and pollers re-check their operation instead
+//! of failing the job.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+use super::network;
+
+/// Event emitted to the frontend when the system wakes and once work resumes
+pub const POWER_EVENT: &str = "power-status";
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Heartbeat gap beyond its interval that counts as sleep rather than a busy scheduler
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Longest wait for the network after a wake before work resumes anyway
+const WAKE_GRACE: Duration = Duration::from_secs(90);
+
+/// Payload of the `power-status` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerStatusEvent {
+    /// `resumed` right after a wake, `ready` once work continues
+    pub state: &'static str,
+    pub suspended_seconds: u64,
+}
+
+/// Wakes seen since startup, and whether work is still waiting after the latest one
+struct PowerState {
+    wakes: AtomicU64,
+    settling: watch::Sender<bool>,
+}
+
+fn state() -> &'static PowerState {
+    static STATE: OnceLock<PowerState> = OnceLock::new();
+    STATE.get_or_init(|| PowerState {
+        wakes: AtomicU64::new(0),
+        settling: watch::channel(false).0,
+    })
+}
+
+/// Number of wakes from sleep so far; pollers compare it to spot one mid-operation
+pub fn wakes() -> u64 {
+    state().wakes.load(Ordering::Relaxed)
+}
+
+/// Run `future` with a limit of `secs` seconds that does not count time asleep
+///
+/// The limit starts over after a wake. Dropping the future on timeout also stops
+/// provider poll loops.
+pub async fn timeout<T>(secs: u64, future: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::pin!(future);
+    loop {
+        let before = wakes();
+        match tokio::time::timeout(Duration::from_secs(secs), &mut future).await {
+            Ok(outcome) => return outcome,
+            Err(_) if wakes() != before => continue,
+            Err(_) => return Err(anyhow::anyhow!("Timed out after {} seconds", secs)),
+        }
+    }
+}
+
+/// Wait until work may continue after a wake; returns at once otherwise
+pub async fn settle() {
+    let mut settling = state().settling.subscribe();
+    let _ = settling.wait_for(|settling| !settling).await;
+}
+
+/// Whether a gap between two heartbeats means the system was asleep
+fn slept(gap: Duration) -> bool {
+    gap > HEARTBEAT_INTERVAL + SLEEP_THRESHOLD
+}
+
+/// Watch for wakes from sleep for the lifetime of the app
+pub fn spawn_monitor(app_handle: AppHandle) {
+    tokio::spawn(async move {
+        let mut last_beat = SystemTime::now();
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            let now = SystemTime::now();
+            // Clock changes backwards show up as errors and are not sleep
+            let gap = now.duration_since(last_beat).unwrap_or_default();
+            last_beat = now;
+            if slept(gap) {
+                resume(&app_handle, gap.saturating_sub(HEARTBEAT_INTERVAL)).await;
+            }
+        }
+    });
+}
+
+/// Hold work until the network is back, then let it continue
+async fn resume(app_handle: &AppHandle, suspended: Duration) {
+    let power = state();
+    power.wakes.fetch_add(1, Ordering::Relaxed);
+    power.settling.send_replace(true);
+
+    let suspended_seconds = suspended.as_secs();
+    log_info!("[Power] Woke after about {}s asleep, waiting for the network", suspended_seconds);
+    emit(app_handle, "resumed", suspended_seconds);
+
+    let started = tokio::time::Instant::now();
+    while !network::check_connectivity().await {
+        if started.elapsed() >= WAKE_GRACE {
+            log_warn!("[Power] Network still unreachable after wake, resuming anyway");
+            break;
+        }
+        tokio::time::sleep(Duration::from_secs(3)).await;
+    }
+
+    power.settling.send_replace(false);
+    log_info!("[Power] Resuming work");
+    emit(app_handle, "ready", suspended_seconds);
+}
+
+fn emit(app_handle: &AppHandle, state: &'static str, suspended_seconds: u64) {
+    let event = PowerStatusEvent {
+        state,
+        suspended_seconds,
+    };
+    if let Err(e) = app_handle.emit(POWER_EVENT, event) {
+        log_warn!("[Power] Failed to emit status: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_detection() {
+        assert!(!slept(HEARTBEAT_INTERVAL));
+        assert!(!slept(Duration::from_secs(20)));
+        assert!(slept(Duration::from_secs(8 * 60 * 60)));
+    }
+}
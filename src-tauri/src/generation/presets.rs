@@ -0,0 +1,60 @@
+//! User-saved default parameters per provider and model.
+//!
+//! Before a generation runs, the provider-wide preset and then the model's preset are
+//! merged under the request's own parameters, so explicit values always win. Built-in
+//! model defaults still apply beneath both through [`super::defaults::ModelParams`].
+
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::db::operations::ParameterPresetOps;
+
+/// `params` with the saved presets for `provider` and `model` filled in underneath
+pub async fn apply(pool: &SqlitePool, provider: &str, model: &str, params: Value) -> Result<Value> {
+    let mut merged = params;
+    for model in [Some(model), None] {
+        if let Some(preset) = ParameterPresetOps::find(pool, provider, model).await? {
+            let preset: Value = serde_json::from_str(&preset.parameters)?;
+            merge_under(&mut merged, &preset);
+        }
+    }
+    Ok(merged)
+}
+
+/// Add the keys of `preset` that `params` does not set; null counts as unset
+pub fn merge_under(params: &mut Value, preset: &Value) {
+    let Some(preset) = preset.as_object() else {
+        return;
+    };
+    if params.is_null() {
+        *params = Value::Object(Default::default());
+    }
+    let Some(params) = params.as_object_mut() else {
+        return;
+    };
+    for (key, value) in preset {
+        let unset = params.get(key).is_none_or(Value::is_null);
+        if unset {
+            params.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_under_keeps_explicit_params() {
+        let mut params = json!({ "size": "1024x1536", "quality": null });
+        merge_under(&mut params, &json!({ "size": "1024x1024", "quality": "medium" }));
+        merge_under(&mut params, &json!({ "quality": "low", "n": 2 }));
+        assert_eq!(params, json!({ "size": "1024x1536", "quality": "medium", "n": 2 }));
+
+        let mut empty = Value::Null;
+        merge_under(&mut empty, &json!({ "steps": 30 }));
+        assert_eq!(empty, json!({ "steps": 30 }));
+    }
+}
@@ -0,0 +1,438 @@
+//! Approximate list prices and latencies used to estimate jobs before they run.
+//!
+//! Prices are in USD and only need to be close enough to tell a cent from a dollar;
+//! they are not a substitute for the provider's billing.
+
+use serde::Serialize;
+
+use super::defaults::ModelParams;
+use super::deprecation::{self, DeprecationWarning};
+use super::providers::{openai, recraft, runway};
+
+/// Dry-run estimate for a generation, computed without calling any API
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationEstimate {
+    pub provider: String,
+    pub model: String,
+    pub estimated_cost_usd: Option<f64>,
+    pub expected_duration_seconds: Option<u64>,
+    /// "history" when based on past jobs, "table" for the built-in latency table
+    pub duration_source: Option<String>,
+    /// Number of past jobs the duration is based on
+    pub history_samples: usize,
+    /// Problems that would make the provider reject the request
+    pub errors: Vec<String>,
+    /// Things worth knowing before submitting that do not block the request
+    pub warnings: Vec<String>,
+    /// Set when the vendor deprecated or retired the model
+    pub deprecation: Option<DeprecationWarning>,
+}
+
+/// Estimate cost and duration for a generation
+///
+/// `errors` is left for the caller, which checks the parameters against the provider's
+/// schema.
+///
+/// `history` holds run times in seconds of recent completed jobs for the same
+/// provider and model; when present their median replaces the built-in latency.
+pub fn estimate(
+    provider: &str,
+    model: &str,
+    params: &serde_json::Value,
+    history: &[u64],
+) -> GenerationEstimate {
+    let estimated_cost_usd = estimate_cost(provider, model, params);
+    let (expected_duration_seconds, duration_source) = match median(history) {
+        Some(seconds) => (Some(seconds), Some("history".to_string())),
+        None => {
+            let seconds = typical_duration_seconds(provider, model, params);
+            (seconds, seconds.map(|_| "table".to_string()))
+        }
+    };
+
+    let mut warnings = Vec::new();
+    if estimated_cost_usd.is_none() {
+        warnings.push(format!("No pricing data for {} / {}, cost is unknown", provider, model));
+    }
+    if is_video_model(model) && params.get("duration").is_none() && params.get("durationSeconds").is_none() {
+        warnings.push("No duration set, the provider default will be used".to_string());
+    }
+    let deprecation = deprecation::warning(provider, model);
+    if let Some(deprecation) = &deprecation {
+        warnings.push(deprecation.message.clone());
+    }
+
+    GenerationEstimate {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        estimated_cost_usd,
+        expected_duration_seconds,
+        duration_source,
+        history_samples: history.len(),
+        errors: Vec::new(),
+        warnings,
+        deprecation,
+    }
+}
+
+fn median(values: &[u64]) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    Some(if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2
+    } else {
+        sorted[mid]
+    })
+}
+
+/// Whether a model produces video (billed per second and usually the most expensive)
+pub fn is_video_model(model: &str) -> bool {
+    model.starts_with("sora")
+        || model.starts_with("veo")
+        || model.contains("video")
+        || model.starts_with("pika")
+        || model.starts_with("MiniMax-Hailuo")
+        || model.contains("2V-01")
+        || model.starts_with("doubao-seedance")
+        || runway::IMAGE_TO_VIDEO_MODELS.contains(&model)
+}
+
+/// Estimated cost in USD, or `None` when the model is unknown or billed by tokens
+pub fn estimate_cost(provider: &str, model: &str, params: &serde_json::Value) -> Option<f64> {
+    let values = ModelParams::new(provider, model, params);
+    let count = values.u64("n").max(1) as f64;
+    let duration = video_duration(provider, model, params) as f64;
+    // fal endpoints count images as `num_images`
+    let images = values.u64("num_images").max(1) as f64;
+
+    let cost = match (provider, model) {
+        // Local backends run on the user's own hardware
+        ("a1111" | "comfyui" | "invokeai", _) => 0.0,
+
+        ("openai", "gpt-image-1") => {
+            count
+                * match values.str("quality") {
+                    "low" => 0.011,
+                    "medium" | "auto" => 0.042,
+                    _ => 0.167,
+                }
+        }
+        ("openai", "gpt-image-1-mini") => {
+            count
+                * match values.str("quality") {
+                    "low" => 0.005,
+                    "medium" | "auto" => 0.011,
+                    _ => 0.036,
+                }
+        }
+        ("openai", "dall-e-3") => {
+            count * if values.str("quality") == "hd" { 0.08 } else { 0.04 }
+        }
+        ("openai", "dall-e-2") => count * 0.02,
+        ("openai", "sora-2" | "sora") => duration * 0.10,
+        ("openai", "sora-2-pro") => duration * 0.30,
+
+        ("google", "gemini-2.5-flash-image") => count * 0.039,
+        ("google", "gemini-3-pro-image-preview") => count * 0.134,
+        ("google", "veo" | "veo-2" | "veo-2.0-generate-exp") => duration * 0.35,
+        ("google", m) if m.starts_with("veo-3") => duration * 0.40,
+        ("google", m) if m.starts_with("imagen-4") && m.contains("fast") => count * 0.02,
+        ("google", m) if m.starts_with("imagen-4") && m.contains("ultra") => count * 0.06,
+        ("google", m) if m.starts_with("imagen") => count * 0.04,
+
+        ("vertex", m) if m.contains("fast") && m.starts_with("imagen") => count * 0.02,
+        ("vertex", m) if m.contains("ultra") => count * 0.06,
+        ("vertex", m) if m.starts_with("imagen") => count * 0.04,
+        ("vertex", m) if m.starts_with("veo-2") => duration * 0.50,
+        ("vertex", m) if m.contains("fast") => duration * 0.15,
+        ("vertex", m) if m.starts_with("veo") => duration * 0.40,
+
+        ("grok", "grok-2-image" | "grok-2-image-1212" | "grok-image" | "aurora") => count * 0.07,
+
+        ("flux", "flux-pro-1.1" | "flux-kontext-pro" | "flux-kontext") => 0.04,
+        ("flux", "flux-pro-1.1-ultra" | "flux-ultra") => 0.06,
+        ("flux", "flux-pro") => 0.05,
+        ("flux", "flux-dev") => 0.025,
+        ("flux", "flux-kontext-max") => 0.08,
+
+        ("fal", "fal-ai/flux/schnell") => images * 0.003,
+        ("fal", "fal-ai/flux/dev") => images * 0.025,
+        ("fal", "fal-ai/flux-pro/v1.1" | "fal-ai/flux-pro/kontext") => images * 0.04,
+        ("fal", "fal-ai/flux-pro/v1.1-ultra") => images * 0.06,
+        ("fal", "fal-ai/ltx-video") => 0.02,
+        ("fal", "fal-ai/kling-video/v2.1/standard/text-to-video") => duration * 0.05,
+
+        // Vector outputs cost twice as much as raster ones
+        ("recraft", "recraftv3" | "recraftv2") => {
+            let raster = if model == "recraftv3" { 0.04 } else { 0.022 };
+            let vector = recraft::VECTOR_STYLES.contains(&values.str("style"));
+            count * if vector { raster * 2.0 } else { raster }
+        }
+
+        // Billed in API credits; at plan rates a standard image is about two cents
+        ("leonardo", _) => images * 0.02,
+
+        ("runway", "gen4_turbo" | "gen3a_turbo") => duration * 0.05,
+        ("runway", "veo3.1") => duration * 0.40,
+        ("runway", "veo3.1_fast") => duration * 0.15,
+
+        // Billed per five seconds of video
+        ("pika", "pika-2.2") => {
+            let clip = if values.str("resolution") == "1080p" { 0.45 } else { 0.20 };
+            clip * (duration / 5.0).ceil()
+        }
+        ("pika", "pika-2-turbo") => 0.20 * (duration / 5.0).ceil(),
+        ("pika", "pikadditions" | "pikaswaps") => 0.30,
+
+        ("minimax", m) if m.starts_with("MiniMax-Hailuo") => {
+            match (values.str("resolution"), duration as u64) {
+                ("1080P", _) => 0.49,
+                ("512P", 10) => 0.15,
+                ("512P", _) => 0.10,
+                (_, 10) => 0.56,
+                _ => 0.28,
+            }
+        }
+        ("minimax", _) => 0.43,
+
+        // Seedance is billed by output tokens, which scale with resolution and length
+        ("volcengine", m) if m.starts_with("doubao-seedance") => {
+            let per_second = match values.str("resolution") {
+                "480p" => 0.02,
+                "720p" => 0.05,
+                _ => 0.12,
+            };
+            let per_second = if m.contains("lite") { per_second * 0.7 } else { per_second };
+            duration * per_second
+        }
+        ("volcengine", _) => 0.03,
+
+        // Proxy services charge per task by the speed it runs at
+        ("midjourney", _) => match values.str("process_mode") {
+            "relax" => 0.02,
+            "turbo" => 0.10,
+            _ => 0.045,
+        },
+
+        // 20 credits per track, whatever its length
+        ("stability", m) if m.starts_with("stable-audio") => 0.20,
+
+        _ => return None,
+    };
+
+    Some(cost)
+}
+
+/// Actual cost in USD of a finished generation
+///
+/// Token-billed requests use the usage reported by the provider; everything else is
+/// priced from the submitted parameters (image size/quality, video duration).
+pub fn actual_cost(
+    provider: &str,
+    model: &str,
+    params: &serde_json::Value,
+    metadata: &serde_json::Value,
+) -> Option<f64> {
+    let usage = metadata.get("usage");
+    let tokens = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as f64 / 1_000_000.0)
+    };
+    let (input, output) = (tokens("input_tokens"), tokens("output_tokens"));
+
+    match provider {
+        "anthropic" => {
+            let model = metadata.get("model").and_then(|v| v.as_str()).unwrap_or(model);
+            let (input_rate, output_rate) = anthropic_token_rates(model);
+            if input.is_none() && output.is_none() {
+                return None;
+            }
+            Some(input.unwrap_or(0.0) * input_rate + output.unwrap_or(0.0) * output_rate)
+        }
+        "openai" if input.is_some() || output.is_some() => {
+            // gpt-image models report token usage, billed per million tokens
+            let (input_rate, output_rate) = match model {
+                "gpt-image-1-mini" => (2.0, 8.0),
+                "gpt-image-1" => (5.0, 40.0),
+                _ => return estimate_cost(provider, model, params),
+            };
+            Some(input.unwrap_or(0.0) * input_rate + output.unwrap_or(0.0) * output_rate)
+        }
+        "groq" => {
+            // Chat completions report prompt and completion tokens
+            let (input, output) = (tokens("prompt_tokens")?, tokens("completion_tokens")?);
+            let (input_rate, output_rate) = match model {
+                "llama-3.1-8b-instant" => (0.05, 0.08),
+                "openai/gpt-oss-20b" => (0.075, 0.30),
+                "openai/gpt-oss-120b" => (0.15, 0.60),
+                _ => (0.59, 0.79),
+            };
+            Some(input * input_rate + output * output_rate)
+        }
+        "perplexity" => {
+            // Newer responses price the request themselves, search fees included
+            if let Some(total) = usage.and_then(|u| u.pointer("/cost/total_cost")) {
+                return total.as_f64();
+            }
+            let (input, output) = (tokens("prompt_tokens")?, tokens("completion_tokens")?);
+            let (input_rate, output_rate) = match model {
+                "sonar" => (1.0, 1.0),
+                "sonar-pro" => (3.0, 15.0),
+                _ => (2.0, 8.0),
+            };
+            // Plus a fee per thousand requests by how much search context was used
+            let context = ModelParams::new(provider, model, params);
+            let fee = match (model, context.str("search_context_size")) {
+                ("sonar", "low") => 5.0,
+                ("sonar", "medium") => 8.0,
+                ("sonar", _) => 12.0,
+                (_, "low") => 6.0,
+                (_, "medium") => 10.0,
+                _ => 14.0,
+            };
+            Some(input * input_rate + output * output_rate + fee / 1000.0)
+        }
+        _ => estimate_cost(provider, model, params),
+    }
+}
+
+/// Input and output USD per million tokens for Claude models
+fn anthropic_token_rates(model: &str) -> (f64, f64) {
+    if model.contains("opus-4-5") {
+        (5.0, 25.0)
+    } else if model.contains("opus") {
+        (15.0, 75.0)
+    } else if model.contains("3-haiku") {
+        (0.25, 1.25)
+    } else if model.contains("haiku-4") {
+        (1.0, 5.0)
+    } else if model.contains("haiku") {
+        (0.8, 4.0)
+    } else {
+        (3.0, 15.0)
+    }
+}
+
+/// Typical wall-clock time in seconds, including provider queueing
+pub fn typical_duration_seconds(provider: &str, model: &str, params: &serde_json::Value) -> Option<u64> {
+    let duration = video_duration(provider, model, params);
+    // Durations come from user parameters, so a huge one must not overflow
+    let video =
+        |base: u64, per_second: u64| base.saturating_add(duration.saturating_mul(per_second));
+
+    let seconds = match (provider, model) {
+        ("a1111" | "comfyui" | "invokeai", _) => 20,
+        ("anthropic", _) => 10,
+        ("groq", _) => 2,
+        ("perplexity", "sonar-deep-research") => 180,
+        ("perplexity", _) => 8,
+
+        ("openai", "gpt-image-1" | "gpt-image-1-mini") => 45,
+        ("openai", "dall-e-3") => 15,
+        ("openai", "dall-e-2") => 8,
+        ("openai", "sora-2" | "sora") => video(60, 10),
+        ("openai", "sora-2-pro") => video(120, 20),
+        ("openai", m) if openai::SPEECH_MODELS.contains(&m) => 5,
+        ("openai", m) if openai::TRANSCRIPTION_MODELS.contains(&m) => 15,
+
+        ("google", "gemini-2.5-flash-image") => 10,
+        ("google", "gemini-3-pro-image-preview") => 25,
+        ("google", m) if m.starts_with("veo") => video(60, 8),
+        ("google", m) if m.starts_with("imagen") => 10,
+        ("vertex", m) if m.starts_with("veo") => video(60, 8),
+        ("vertex", _) => 10,
+
+        ("grok", _) => 8,
+        ("flux", m) if m.ends_with("ultra") => 15,
+        ("flux", _) => 10,
+        ("fal", m) if is_video_model(m) => video(60, 6),
+        ("fal", _) => 5,
+        ("recraft", _) => 10,
+        ("leonardo", _) => 20,
+        ("runway", _) => video(30, 6),
+        ("pika", _) => video(60, 6),
+        ("minimax", _) => video(120, 10),
+        ("volcengine", m) if m.starts_with("doubao-seedance") => video(40, 8),
+        ("volcengine", _) => 10,
+        ("elevenlabs", _) => 5,
+        ("stability", _) => 10_u64.saturating_add(duration / 6),
+        ("midjourney", _) => match ModelParams::new(provider, model, params).str("process_mode") {
+            "relax" => 300,
+            "turbo" => 30,
+            _ => 60,
+        },
+
+        _ => return None,
+    };
+
+    Some(seconds)
+}
+
+/// Requested video length in seconds, falling back to the model default
+pub fn video_duration(provider: &str, model: &str, params: &serde_json::Value) -> u64 {
+    params
+        .get("duration")
+        .or_else(|| params.get("durationSeconds"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or_else(|| ModelParams::new(provider, model, params).u64("duration"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost() {
+        let params = serde_json::json!({ "quality": "low", "n": 2 });
+        assert_eq!(estimate_cost("openai", "gpt-image-1", &params), Some(0.022));
+
+        let params = serde_json::json!({ "duration": 12 });
+        let sora = estimate_cost("openai", "sora-2-pro", &params).unwrap();
+        assert!((sora - 3.6).abs() < 1e-9);
+
+        assert_eq!(estimate_cost("comfyui", "sdxl", &serde_json::json!({})), Some(0.0));
+        assert_eq!(estimate_cost("anthropic", "claude-sonnet-4-5", &serde_json::json!({})), None);
+
+        let params = serde_json::json!({ "duration": u64::MAX });
+        assert_eq!(typical_duration_seconds("minimax", "video-01", &params), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_actual_cost() {
+        let metadata = serde_json::json!({
+            "model": "claude-sonnet-4-5",
+            "usage": { "input_tokens": 1_000_000, "output_tokens": 100_000 }
+        });
+        let cost = actual_cost("anthropic", "claude", &serde_json::json!({}), &metadata).unwrap();
+        assert!((cost - 4.5).abs() < 1e-9);
+
+        let metadata = serde_json::json!({
+            "usage": { "prompt_tokens": 2_000_000, "completion_tokens": 1_000_000 }
+        });
+        let model = "llama-3.1-8b-instant";
+        let cost = actual_cost("groq", model, &serde_json::json!({}), &metadata).unwrap();
+        assert!((cost - 0.18).abs() < 1e-9);
+
+        let params = serde_json::json!({ "duration": 4 });
+        let veo = actual_cost("google", "veo-3.1", &params, &serde_json::json!({})).unwrap();
+        assert!((veo - 1.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_uses_history() {
+        let params = serde_json::json!({});
+        let from_table = estimate("openai", "gpt-image-1", &params, &[]);
+        assert_eq!(from_table.expected_duration_seconds, Some(45));
+        assert_eq!(from_table.duration_source.as_deref(), Some("table"));
+
+        let from_history = estimate("openai", "gpt-image-1", &params, &[20, 90, 30, 25]);
+        assert_eq!(from_history.expected_duration_seconds, Some(27));
+        assert_eq!(from_history.history_samples, 4);
+    }
+}
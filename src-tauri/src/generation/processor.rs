@@ -1,27 +1,235 @@
 use anyhow::Result;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Notify, RwLock};
 
-use super::{GenerationRequest, GenerationService};
-use crate::db::{models::*, operations::JobOps};
+use super::budget;
+use super::cache::{self, ResultCacheSettings, RESULT_CACHE_SETTINGS_KEY};
+use super::eco::{self, EcoSettings, EcoState, EcoStatusEvent, ECO_STATUS_EVENT, WAITING_ECO};
+use super::errors::GenerationError;
+use super::fallback;
+use super::jitter;
+use super::job_log;
+use super::network::{self, NetworkStatusEvent, NETWORK_STATUS_EVENT, WAITING_NETWORK};
+use super::pipeline::{self, PipelineData, PIPELINE_JOB_TYPE};
+use super::policy;
+use super::power;
+use super::presets;
+use super::provenance;
+use super::routing;
+use super::sweep::{self, SWEEP_GRID_JOB_TYPE};
+use super::usage;
+use super::{
+    GenerationProgress, GenerationRequest, GenerationResult, GenerationService, ProgressSender,
+    DEFAULT_MAX_RATE_LIMIT_RETRIES,
+};
+use crate::db::{
+    models::*,
+    operations::{JobOps, ResultCacheOps, SettingsOps},
+};
+use crate::maintenance::optimize;
+use crate::maintenance::expiry::{self, ASSET_EXPIRY_JOB_TYPE};
+use crate::maintenance::thumbnails::{self, THUMBNAIL_BACKFILL_JOB_TYPE};
+
+/// Settings key for the processor configuration
+pub const PROCESSOR_SETTINGS_KEY: &str = "processor_settings";
+
+/// Upper bound on concurrent jobs, to keep a typo from flooding providers
+const MAX_WORKERS: usize = 16;
+
+/// Event emitted to the frontend while a job is generating
+pub const PROGRESS_EVENT: &str = "generation-progress";
+
+/// Payload of the `generation-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgressEvent {
+    pub job_id: String,
+    #[serde(flatten)]
+    pub progress: GenerationProgress,
+}
+
+/// Runtime configuration of the job processor, stored in the settings table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessorSettings {
+    /// Seconds between checks for pending jobs
+    pub poll_interval_seconds: u64,
+    /// Number of jobs processed concurrently in the interactive lane
+    pub worker_count: usize,
+    /// Number of jobs processed concurrently in the batch lane
+    pub batch_worker_count: usize,
+    /// Retries after a provider answers 429 Too Many Requests
+    pub max_retries: u32,
+    /// Retries of failed local generations with a new seed/sampler; 0 disables them
+    pub jitter_retries: u32,
+    /// Requests-per-minute limits keyed by provider
+    pub provider_limits: HashMap<String, u32>,
+    /// Deferral of local jobs on battery, heat or GPU load
+    pub eco: EcoSettings,
+}
+
+impl Default for ProcessorSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval_seconds: 5,
+            worker_count: 1,
+            batch_worker_count: 1,
+            max_retries: DEFAULT_MAX_RATE_LIMIT_RETRIES,
+            jitter_retries: 0,
+            provider_limits: HashMap::new(),
+            eco: EcoSettings::default(),
+        }
+    }
+}
+
+impl ProcessorSettings {
+    /// Clamp values into a range the processor can run with
+    pub fn normalized(mut self) -> Self {
+        self.poll_interval_seconds = self.poll_interval_seconds.max(1);
+        self.worker_count = self.worker_count.clamp(1, MAX_WORKERS);
+        self.batch_worker_count = self.batch_worker_count.clamp(1, MAX_WORKERS);
+        self.provider_limits.retain(|_, rpm| *rpm > 0);
+        self
+    }
+
+    fn workers_for(&self, lane: &str) -> usize {
+        if lane == BATCH_LANE {
+            self.batch_worker_count
+        } else {
+            self.worker_count
+        }
+    }
+}
+
+/// Lane for a generation when the caller does not pick one: video goes to batch
+pub fn default_lane(model: &str) -> &'static str {
+    if super::pricing::is_video_model(model) {
+        BATCH_LANE
+    } else {
+        INTERACTIVE_LANE
+    }
+}
+
+/// Early wake-ups of the lane loops and the eco monitor
+///
+/// Each listener has its own `Notify`: `notify_one` stores a permit for a listener that
+/// is busy processing, so a wake-up sent meanwhile is not lost.
+#[derive(Default)]
+struct Wakeups {
+    interactive: Notify,
+    batch: Notify,
+    eco: Notify,
+}
+
+impl Wakeups {
+    fn lane(&self, lane: &str) -> &Notify {
+        if lane == BATCH_LANE {
+            &self.batch
+        } else {
+            &self.interactive
+        }
+    }
+
+    /// Let both lanes look for runnable jobs now
+    fn wake_lanes(&self) {
+        self.interactive.notify_one();
+        self.batch.notify_one();
+    }
+
+    /// Wake every listener, e.g. after a settings change
+    fn wake_all(&self) {
+        self.wake_lanes();
+        self.eco.notify_one();
+    }
+}
 
 /// Job processor that consumes jobs from the database queue
 pub struct JobProcessor {
     db_pool: SqlitePool,
     generation_service: Arc<RwLock<GenerationService>>,
+    app_handle: AppHandle,
     is_running: Arc<RwLock<bool>>,
+    settings: Arc<RwLock<ProcessorSettings>>,
+    wakeups: Arc<Wakeups>,
+    online: Arc<AtomicBool>,
+    eco: Arc<EcoState>,
 }
 
 impl JobProcessor {
-    pub fn new(db_pool: SqlitePool, generation_service: Arc<RwLock<GenerationService>>) -> Self {
+    pub fn new(
+        db_pool: SqlitePool,
+        generation_service: Arc<RwLock<GenerationService>>,
+        app_handle: AppHandle,
+    ) -> Self {
         Self {
             db_pool,
             generation_service,
+            app_handle,
             is_running: Arc::new(RwLock::new(false)),
+            settings: Arc::new(RwLock::new(ProcessorSettings::default())),
+            wakeups: Arc::new(Wakeups::default()),
+            online: Arc::new(AtomicBool::new(true)),
+            eco: Arc::new(EcoState::default()),
         }
     }
 
+    /// Current processor settings
+    pub async fn settings(&self) -> ProcessorSettings {
+        self.settings.read().await.clone()
+    }
+
+    /// Whether the last connectivity check reached the internet
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    /// Why local jobs are deferred by eco scheduling, if they are
+    pub fn eco_hold(&self) -> Option<String> {
+        self.eco.hold()
+    }
+
+    /// Apply settings to the running processor and generation service
+    ///
+    /// Takes effect immediately: idle lanes wake up and the next batch uses the new
+    /// worker counts.
+    pub async fn apply_settings(&self, settings: ProcessorSettings) {
+        let settings = settings.normalized();
+
+        {
+            let service = self.generation_service.read().await;
+            service.set_max_retries(settings.max_retries);
+
+            let current_limits = service.rate_limits();
+            for provider in current_limits.keys() {
+                if !settings.provider_limits.contains_key(provider) {
+                    service.set_rate_limit(provider, None);
+                }
+            }
+            for (provider, rpm) in &settings.provider_limits {
+                // Re-creating an unchanged bucket would reset its tokens
+                if current_limits.get(provider) != Some(rpm) {
+                    service.set_rate_limit(provider, Some(*rpm));
+                }
+            }
+        }
+
+        *self.settings.write().await = settings;
+        self.wakeups.wake_all();
+    }
+
+    /// Load the stored settings and apply them
+    pub async fn load_settings(&self) -> Result<()> {
+        let settings: ProcessorSettings =
+            SettingsOps::get_or_default(&self.db_pool, PROCESSOR_SETTINGS_KEY).await?;
+        self.apply_settings(settings).await;
+        Ok(())
+    }
+
     /// Start the job processor
     pub async fn start(&self) {
         let mut is_running = self.is_running.write().await;
@@ -31,16 +239,203 @@ impl JobProcessor {
         *is_running = true;
         drop(is_running);
 
+        if let Err(e) = self.load_settings().await {
+            log_warn!("Failed to load processor settings, using defaults: {}", e);
+        }
+
+        power::spawn_monitor(self.app_handle.clone());
+        self.spawn_connectivity_monitor();
+        self.spawn_eco_monitor();
+        self.spawn_budget_monitor();
+        self.spawn_idle_maintenance();
+
+        // Each lane polls independently so long batch jobs never hold up interactive ones
+        for lane in [INTERACTIVE_LANE, BATCH_LANE] {
+            let db_pool = self.db_pool.clone();
+            let service = self.generation_service.clone();
+            let app_handle = self.app_handle.clone();
+            let is_running = self.is_running.clone();
+            let settings = self.settings.clone();
+            let wakeups = self.wakeups.clone();
+            let online = self.online.clone();
+            let eco = self.eco.clone();
+
+            tokio::spawn(async move {
+                while *is_running.read().await {
+                    let (poll_interval, worker_count, jitter_retries) = {
+                        let settings = settings.read().await;
+                        (
+                            settings.poll_interval_seconds,
+                            settings.workers_for(lane),
+                            settings.jitter_retries,
+                        )
+                    };
+
+                    // After a wake, start nothing until the network is back
+                    power::settle().await;
+                    if let Err(e) = Self::process_pending_jobs(
+                        &db_pool,
+                        &service,
+                        &app_handle,
+                        lane,
+                        worker_count,
+                        jitter_retries,
+                        &online,
+                        &eco,
+                    )
+                    .await
+                    {
+                        log_error!("Error processing {} jobs: {}", lane, e);
+                    }
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(tokio::time::Duration::from_secs(poll_interval)) => {}
+                        _ = wakeups.lane(lane).notified() => {}
+                    }
+                }
+            });
+        }
+    }
+
+    /// Periodically check connectivity and resume cloud jobs once the network is back
+    fn spawn_connectivity_monitor(&self) {
+        let db_pool = self.db_pool.clone();
+        let app_handle = self.app_handle.clone();
+        let is_running = self.is_running.clone();
+        let online = self.online.clone();
+        let wakeups = self.wakeups.clone();
+
+        tokio::spawn(async move {
+            while *is_running.read().await {
+                power::settle().await;
+                let is_online = network::check_connectivity().await;
+                let was_online = online.swap(is_online, Ordering::Relaxed);
+
+                let mut resumed_jobs = 0;
+                if is_online {
+                    match JobOps::resume_waiting_network(&db_pool).await {
+                        Ok(count) => resumed_jobs = count,
+                        Err(e) => log_warn!("[Network] Failed to resume waiting jobs: {}", e),
+                    }
+                }
+
+                if was_online != is_online || resumed_jobs > 0 {
+                    log_info!(
+                        "[Network] {} ({} jobs resumed)",
+                        if is_online { "Online" } else { "Offline" },
+                        resumed_jobs
+                    );
+                    let event = NetworkStatusEvent {
+                        online: is_online,
+                        resumed_jobs,
+                    };
+                    if let Err(e) = app_handle.emit(NETWORK_STATUS_EVENT, event) {
+                        log_warn!("[Network] Failed to emit status: {}", e);
+                    }
+                }
+
+                // Wake the lanes so resumed jobs start without waiting for the next poll
+                if resumed_jobs > 0 {
+                    wakeups.wake_lanes();
+                }
+
+                tokio::time::sleep(network::CONNECTIVITY_CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Periodically read the system state, deferring or resuming local jobs
+    fn spawn_eco_monitor(&self) {
+        let db_pool = self.db_pool.clone();
+        let app_handle = self.app_handle.clone();
+        let is_running = self.is_running.clone();
+        let settings = self.settings.clone();
+        let wakeups = self.wakeups.clone();
+        let eco = self.eco.clone();
+
+        tokio::spawn(async move {
+            while *is_running.read().await {
+                let eco_settings = settings.read().await.eco.clone();
+                let state = if eco_settings.enabled {
+                    eco::read_system_state().await
+                } else {
+                    eco::SystemState::default()
+                };
+                let reason = eco::hold_reason(&eco_settings, &state, eco.local_jobs_running());
+                let changed = eco.set_hold(reason.clone());
+
+                let mut resumed_jobs = 0;
+                if reason.is_none() {
+                    match JobOps::resume_waiting_eco(&db_pool).await {
+                        Ok(count) => resumed_jobs = count,
+                        Err(e) => log_warn!("[Eco] Failed to resume deferred jobs: {}", e),
+                    }
+                }
+
+                if changed || resumed_jobs > 0 {
+                    match &reason {
+                        Some(reason) => log_info!("[Eco] Deferring local jobs: {}", reason),
+                        None => log_info!("[Eco] Local jobs may run ({} resumed)", resumed_jobs),
+                    }
+                    let event = EcoStatusEvent {
+                        reason,
+                        state,
+                        resumed_jobs,
+                    };
+                    if let Err(e) = app_handle.emit(ECO_STATUS_EVENT, event) {
+                        log_warn!("[Eco] Failed to emit status: {}", e);
+                    }
+                }
+                if resumed_jobs > 0 {
+                    wakeups.wake_lanes();
+                }
+
+                // Settings changes re-check at once, so disabling eco resumes jobs right away
+                tokio::select! {
+                    _ = tokio::time::sleep(eco::ECO_CHECK_INTERVAL) => {}
+                    _ = wakeups.eco.notified() => {}
+                }
+            }
+        });
+    }
+
+    /// Periodically return jobs paused by spending caps to the queue to be checked again
+    fn spawn_budget_monitor(&self) {
         let db_pool = self.db_pool.clone();
-        let service = self.generation_service.clone();
         let is_running = self.is_running.clone();
+        let wakeups = self.wakeups.clone();
 
         tokio::spawn(async move {
             while *is_running.read().await {
-                if let Err(e) = Self::process_pending_jobs(&db_pool, &service).await {
-                    eprintln!("Error processing jobs: {}", e);
+                tokio::time::sleep(budget::BUDGET_RECHECK_INTERVAL).await;
+                match JobOps::resume_waiting_budget(&db_pool).await {
+                    Ok(0) => {}
+                    Ok(count) => {
+                        log_debug!("[Budget] Re-checking {} paused jobs", count);
+                        wakeups.wake_lanes();
+                    }
+                    Err(e) => log_warn!("[Budget] Failed to resume paused jobs: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Compact the database while no jobs are running, at most every few hours
+    fn spawn_idle_maintenance(&self) {
+        let db_pool = self.db_pool.clone();
+        let is_running = self.is_running.clone();
+
+        tokio::spawn(async move {
+            while *is_running.read().await {
+                tokio::time::sleep(optimize::IDLE_CHECK_INTERVAL).await;
+                match optimize::run_if_idle(&db_pool).await {
+                    Ok(Some(report)) => log_info!(
+                        "[Maintenance] Optimized database: {} -> {} bytes",
+                        report.size_before_bytes, report.size_after_bytes
+                    ),
+                    Ok(None) => {}
+                    Err(e) => log_warn!("[Maintenance] Failed to optimize database: {}", e),
                 }
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
             }
         });
     }
@@ -52,36 +447,187 @@ impl JobProcessor {
         *is_running = false;
     }
 
-    /// Process all pending jobs
+    /// Process pending jobs in one lane
+    #[allow(clippy::too_many_arguments)]
     async fn process_pending_jobs(
         pool: &SqlitePool,
         service: &Arc<RwLock<GenerationService>>,
+        app_handle: &AppHandle,
+        lane: &str,
+        worker_count: usize,
+        jitter_retries: u32,
+        online: &AtomicBool,
+        eco: &EcoState,
     ) -> Result<()> {
-        // Get all pending jobs
+        // Get the lane's pending jobs that are not scheduled for later, maintenance last
         let pending_jobs: Vec<Job> = sqlx::query_as(
-            "SELECT * FROM jobs WHERE status = 'pending' ORDER BY created_at ASC LIMIT 10",
+            r#"
+            SELECT * FROM jobs
+            WHERE status = 'pending' AND lane = ? AND (scheduled_at IS NULL OR scheduled_at <= ?)
+            ORDER BY CASE type WHEN ? THEN 1 ELSE 0 END, created_at ASC
+            LIMIT ?
+            "#,
         )
+        .bind(lane)
+        .bind(now())
+        .bind(THUMBNAIL_BACKFILL_JOB_TYPE)
+        .bind(worker_count.max(10) as i64)
         .fetch_all(pool)
         .await?;
 
+        // Local jobs keep running offline unless eco scheduling defers them; cloud jobs
+        // wait for the network
+        let eco_hold = eco.hold();
+        let mut runnable_jobs = Vec::with_capacity(pending_jobs.len());
         for job in pending_jobs {
-            if let Err(e) = Self::process_job(pool, service, &job).await {
-                eprintln!("Error processing job {}: {}", job.id, e);
-
-                // Mark job as failed
-                let _ = JobOps::update(
-                    pool,
-                    &job.id,
-                    UpdateJobInput {
-                        status: Some("failed".to_string()),
-                        result: None,
-                        error: Some(e.to_string()),
-                    },
-                )
-                .await;
+            let local = network::is_local_provider(&job_provider(&job));
+            match &eco_hold {
+                Some(reason) if local => Self::hold_for_eco(pool, &job.id, reason).await?,
+                _ if local || online.load(Ordering::Relaxed) => runnable_jobs.push(job),
+                _ => Self::hold_for_network(pool, &job.id, None).await?,
             }
         }
 
+        futures_util::stream::iter(runnable_jobs)
+            .for_each_concurrent(worker_count, |job| async move {
+                let _local_job = network::is_local_provider(&job_provider(&job))
+                    .then(|| eco.track_local_job());
+                if let Err(e) = Self::process_job(pool, service, app_handle, &job).await {
+                    log_error!("Error processing job {}: {}", job.id, e);
+
+                    // Only an unreachable internet holds the job; one provider down fails it
+                    if network::is_network_error(&e)
+                        && !network::is_local_provider(&job_provider(&job))
+                        && !network::check_connectivity().await
+                    {
+                        // Retried by the connectivity monitor instead of failing
+                        online.store(false, Ordering::Relaxed);
+                        match Self::hold_failed_for_network(pool, &job.id, &e).await {
+                            Ok(true) => return,
+                            Ok(false) => log_warn!("Job {} held for network too often", job.id),
+                            Err(e) => log_warn!("Error holding job {} for network: {}", job.id, e),
+                        }
+                    }
+
+                    // Mark job as failed
+                    let failure = GenerationError::classify(&e);
+                    let _ = JobOps::update(
+                        pool,
+                        &job.id,
+                        UpdateJobInput {
+                            status: Some("failed".to_string()),
+                            result: None,
+                            error: Some(failure.localized()),
+                        },
+                    )
+                    .await;
+                    let kind = failure.kind();
+                    if let Err(e) = JobOps::set_error_kind(pool, &job.id, kind).await {
+                        log_warn!("Error recording failure kind of job {}: {}", job.id, e);
+                    }
+
+                    if let Err(e) = Self::retry_with_jitter(pool, &job, &e, jitter_retries).await {
+                        log_warn!("Error queueing jittered retry of job {}: {}", job.id, e);
+                    }
+                }
+
+                if let Err(e) = crate::notifications::notify_job_finished(pool, app_handle, &job.id).await {
+                    log_warn!("Error sending notifications for job {}: {}", job.id, e);
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// Queue a retry with a new seed (and sampler) if the failure qualifies
+    async fn retry_with_jitter(
+        pool: &SqlitePool,
+        job: &Job,
+        error: &anyhow::Error,
+        jitter_retries: u32,
+    ) -> Result<()> {
+        let mut job_data: serde_json::Value = serde_json::from_str(&job.data)?;
+        let provider = job_provider(job);
+        if !jitter::should_jitter(&provider, error) {
+            return Ok(());
+        }
+
+        let attempt = job_data
+            .pointer("/jitter/attempt")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32
+            + 1;
+        if attempt > jitter_retries {
+            return Ok(());
+        }
+
+        let params = job_data.get("parameters").cloned().unwrap_or_default();
+        let new_seed = uuid::Uuid::new_v4().as_u128() as u32;
+        let (params, changes) = jitter::jitter_parameters(&provider, &params, attempt, new_seed);
+        job_data["parameters"] = params;
+        job_data["jitter"] = serde_json::json!({
+            "attempt": attempt,
+            "changes": changes,
+        });
+
+        let retry = JobOps::retry_with_data(pool, &job.id, Some(job_data.to_string())).await?;
+        log_info!(
+            "[Jitter] Retrying job {} as {} (attempt {}/{})",
+            job.id, retry.id, attempt, jitter_retries
+        );
+        Ok(())
+    }
+
+    /// Park a job until the connectivity monitor sees the network again
+    async fn hold_for_network(pool: &SqlitePool, job_id: &str, error: Option<String>) -> Result<()> {
+        JobOps::update(
+            pool,
+            job_id,
+            UpdateJobInput {
+                status: Some(WAITING_NETWORK.to_string()),
+                result: None,
+                error,
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Park a job whose attempt could not connect, unless it was parked too often already
+    ///
+    /// Returns `false` once the job used up [`network::MAX_NETWORK_HOLDS`]; it fails then.
+    async fn hold_failed_for_network(
+        pool: &SqlitePool,
+        job_id: &str,
+        error: &anyhow::Error,
+    ) -> Result<bool> {
+        let Some(job) = JobOps::get(pool, job_id).await? else {
+            return Ok(false);
+        };
+        let mut data: serde_json::Value = serde_json::from_str(&job.data)?;
+        let holds = data.get("network_holds").and_then(|v| v.as_u64()).unwrap_or(0);
+        if holds >= network::MAX_NETWORK_HOLDS {
+            return Ok(false);
+        }
+        data["network_holds"] = serde_json::json!(holds + 1);
+        JobOps::update_data(pool, job_id, &data).await?;
+        Self::hold_for_network(pool, job_id, Some(error.to_string())).await?;
+        Ok(true)
+    }
+
+    /// Park a local job until eco scheduling lets it run
+    async fn hold_for_eco(pool: &SqlitePool, job_id: &str, reason: &str) -> Result<()> {
+        JobOps::update(
+            pool,
+            job_id,
+            UpdateJobInput {
+                status: Some(WAITING_ECO.to_string()),
+                result: None,
+                error: Some(reason.to_string()),
+            },
+        )
+        .await?;
         Ok(())
     }
 
@@ -89,6 +635,7 @@ impl JobProcessor {
     async fn process_job(
         pool: &SqlitePool,
         service: &Arc<RwLock<GenerationService>>,
+        app_handle: &AppHandle,
         job: &Job,
     ) -> Result<()> {
         // Mark job as running
@@ -103,6 +650,19 @@ impl JobProcessor {
         )
         .await?;
 
+        if job.job_type == PIPELINE_JOB_TYPE {
+            return Self::process_pipeline(pool, service, app_handle, job).await;
+        }
+        if job.job_type == THUMBNAIL_BACKFILL_JOB_TYPE {
+            return Self::process_thumbnail_backfill(pool, job).await;
+        }
+        if job.job_type == SWEEP_GRID_JOB_TYPE {
+            return Self::process_sweep_grid(pool, job).await;
+        }
+        if job.job_type == ASSET_EXPIRY_JOB_TYPE {
+            return Self::process_asset_expiry(pool, job).await;
+        }
+
         // Parse job data
         let job_data: serde_json::Value = serde_json::from_str(&job.data)?;
 
@@ -121,23 +681,175 @@ impl JobProcessor {
             .and_then(|v| v.as_str())
             .unwrap_or("default");
 
+        // Jobs are normally routed when queued; this covers ones inserted directly
+        let target = routing::resolve(
+            pool,
+            &*service.read().await,
+            Some(&job.workflow_id),
+            provider,
+            model,
+        )
+        .await?;
+        let (provider, model) = (target.provider.as_str(), target.model.as_str());
+
         let parameters = job_data
             .get("parameters")
             .cloned()
             .unwrap_or(serde_json::json!({}));
+        let parameters = presets::apply(pool, provider, model, parameters).await?;
+
+        // The allowlist may have changed since the job was queued
+        let allowed = policy::check_provider(pool, &job.workflow_id, provider).await?;
+
+        let cache_settings: ResultCacheSettings =
+            SettingsOps::get_or_default(pool, RESULT_CACHE_SETTINGS_KEY).await?;
+        let cache_key = if cache_settings.enabled {
+            cache::cache_key(provider, model, prompt, &parameters)
+        } else {
+            None
+        };
+
+        if let Some(key) = &cache_key {
+            if let Some(cached) = Self::cached_result(pool, key).await? {
+                log_info!("[Cache] Reusing result for job {}", job.id);
+                ResultCacheOps::record_hit(pool, key).await?;
+                if let Err(e) = JobOps::set_cost(pool, &job.id, Some(0.0)).await {
+                    log_warn!("Error recording cost of job {}: {}", job.id, e);
+                }
+                JobOps::update(
+                    pool,
+                    &job.id,
+                    UpdateJobInput {
+                        status: Some("completed".to_string()),
+                        result: Some(cached),
+                        error: None,
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+
+        if budget::enforce(pool, &job.id, provider, model, &parameters).await? {
+            return Ok(());
+        }
 
         let request = GenerationRequest {
             prompt: prompt.to_string(),
             model: model.to_string(),
-            parameters,
+            parameters: parameters.clone(),
         };
 
+        let progress_tx = Self::forward_progress(app_handle, &job.id);
+
+        // Optional per-job limit; dropping the future also stops provider poll loops
+        let timeout_seconds = job_data
+            .get("timeout_seconds")
+            .and_then(|v| v.as_u64())
+            .filter(|secs| *secs > 0);
+
+        job_log::record_request(pool, &job.id, provider, &request).await;
+
         // Execute generation
         let service_lock = service.read().await;
-        let result = service_lock.generate(provider, request).await?;
+        let generation = service_lock.generate_with_progress(
+            provider,
+            request,
+            Some(progress_tx),
+            allowed.as_deref(),
+        );
+        let outcome = match timeout_seconds {
+            Some(secs) => power::timeout(secs, generation).await,
+            None => generation.await,
+        };
         drop(service_lock);
 
+        job_log::record_outcome(pool, &job.id, provider, outcome.as_ref()).await;
+        let result = outcome?;
+
+        // Bill the provider that actually produced the result
+        let (billed_provider, billed_model) =
+            fallback::fallback_provider(&result).unwrap_or((provider, model));
+        let cost =
+            usage::record_usage(pool, &job.id, billed_provider, billed_model, &parameters, &result)
+                .await;
+        // The result is already paid for; a missing cost must not fail the job
+        if let Err(e) = JobOps::set_cost(pool, &job.id, cost).await {
+            log_warn!("Error recording cost of job {}: {}", job.id, e);
+        }
+        if let Err(e) =
+            provenance::record(pool, &job.id, billed_provider, billed_model, &result).await
+        {
+            log_warn!("[Provenance] Failed to record provenance of job {}: {}", job.id, e);
+        }
+        if let Some(cost) = cost {
+            if let Err(e) = budget::warn_if_crossed(pool, app_handle, billed_provider, cost).await {
+                log_warn!("[Budget] Failed to check spending of {}: {}", billed_provider, e);
+            }
+        }
+
+        // A fallback's result does not answer the cache key of the requested provider
+        let cache_key = cache_key.filter(|_| fallback::fallback_provider(&result).is_none());
+        let result = serde_json::to_value(result)?;
+        if let Some(key) = &cache_key {
+            if let Err(e) = ResultCacheOps::put(pool, key, provider, model, &job.id, &result).await {
+                log_warn!("[Cache] Failed to store result of job {}: {}", job.id, e);
+            }
+        }
+
         // Mark job as completed
+        JobOps::update(
+            pool,
+            &job.id,
+            UpdateJobInput {
+                status: Some("completed".to_string()),
+                result: Some(result),
+                error: None,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forward provider progress to the frontend until the returned sender is dropped
+    fn forward_progress(app_handle: &AppHandle, job_id: &str) -> ProgressSender {
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let progress_app = app_handle.clone();
+        let progress_job_id = job_id.to_string();
+        tokio::spawn(async move {
+            while let Some(progress) = progress_rx.recv().await {
+                let event = JobProgressEvent {
+                    job_id: progress_job_id.clone(),
+                    progress,
+                };
+                if let Err(e) = progress_app.emit(PROGRESS_EVENT, event) {
+                    log_warn!("Failed to emit progress for job {}: {}", progress_job_id, e);
+                }
+            }
+        });
+        progress_tx
+    }
+
+    /// Run a pipeline job's remaining steps and complete it with the last step's result
+    async fn process_pipeline(
+        pool: &SqlitePool,
+        service: &Arc<RwLock<GenerationService>>,
+        app_handle: &AppHandle,
+        job: &Job,
+    ) -> Result<()> {
+        let data: PipelineData = serde_json::from_str(&job.data)?;
+        let progress_tx = Self::forward_progress(app_handle, &job.id);
+        let Some((result, cost)) =
+            pipeline::run(pool, service, app_handle, job, data, progress_tx).await?
+        else {
+            // Paused by a spending cap before one of its steps
+            return Ok(());
+        };
+        if let Err(e) = JobOps::set_cost(pool, &job.id, cost).await {
+            log_warn!("Error recording cost of job {}: {}", job.id, e);
+        }
+
         JobOps::update(
             pool,
             &job.id,
@@ -151,4 +863,97 @@ impl JobProcessor {
 
         Ok(())
     }
+
+    /// Run one backfill pass, putting the job back in the queue while scenes remain
+    async fn process_thumbnail_backfill(pool: &SqlitePool, job: &Job) -> Result<()> {
+        let pass = thumbnails::run_pass(pool, job).await?;
+        let status = if pass.remaining { "pending" } else { "completed" };
+
+        JobOps::update(
+            pool,
+            &job.id,
+            UpdateJobInput {
+                status: Some(status.to_string()),
+                result: Some(serde_json::to_value(&pass)?),
+                error: None,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete the generations an expiry job previewed that are still expired
+    async fn process_asset_expiry(pool: &SqlitePool, job: &Job) -> Result<()> {
+        let report = expiry::run(pool, job).await?;
+
+        JobOps::update(
+            pool,
+            &job.id,
+            UpdateJobInput {
+                status: Some("completed".to_string()),
+                result: Some(serde_json::to_value(&report)?),
+                error: None,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Compose a sweep's grid, checking back later while its generations are unfinished
+    async fn process_sweep_grid(pool: &SqlitePool, job: &Job) -> Result<()> {
+        let Some(result) = sweep::compose_grid(pool, job).await? else {
+            let interval = chrono::Duration::from_std(sweep::GRID_RECHECK_INTERVAL)?;
+            let at = (chrono::Utc::now() + interval).to_rfc3339();
+            return JobOps::requeue_at(pool, &job.id, &at).await;
+        };
+
+        JobOps::update(
+            pool,
+            &job.id,
+            UpdateJobInput {
+                status: Some("completed".to_string()),
+                result: Some(result),
+                error: None,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Cached result for a key, marked as such, unless its output file is gone
+    async fn cached_result(pool: &SqlitePool, key: &str) -> Result<Option<serde_json::Value>> {
+        let Some(entry) = ResultCacheOps::get(pool, key).await? else {
+            return Ok(None);
+        };
+
+        let mut result: serde_json::Value = serde_json::from_str(&entry.result)?;
+        let outputs: GenerationResult = serde_json::from_value(result.clone())?;
+        let file_missing = outputs
+            .artifacts
+            .iter()
+            .filter_map(|artifact| artifact.file_path.as_deref())
+            .any(|path| !std::path::Path::new(path).exists());
+        if file_missing {
+            // The asset was deleted, so the entry can no longer be served
+            ResultCacheOps::delete(pool, key).await?;
+            return Ok(None);
+        }
+
+        if let Some(metadata) = result.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+            metadata.insert("cached".to_string(), serde_json::json!(true));
+            metadata.insert("cached_from_job".to_string(), serde_json::json!(entry.job_id));
+        }
+        Ok(Some(result))
+    }
+}
+
+/// Provider named in a job's data, empty if missing
+fn job_provider(job: &Job) -> String {
+    serde_json::from_str::<serde_json::Value>(&job.data)
+        .ok()
+        .and_then(|data| data.get("provider")?.as_str().map(str::to_string))
+        .unwrap_or_default()
 }
@@ -0,0 +1,219 @@
+//! Provenance of generated results, for proving which assets came from which models.
+//!
+//! Each completed generation records the provider and model that actually produced it,
+//! the model build the provider reported (where it reports one) and a snapshot of the
+//! provider's terms at the time. Records outlive their jobs, like usage records.
+//!
+//! Local backends have no entry in [`TERMS`]: whether their outputs may be used
+//! commercially depends on the license of the checkpoint loaded, which the app cannot
+//! know, so it is recorded as unknown.
+
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use super::GenerationResult;
+use crate::db::models::{CreateProvenanceInput, ProvenanceRecord};
+use crate::db::operations::ProvenanceOps;
+
+/// The terms of a provider as last reviewed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TermsSnapshot {
+    pub provider: &'static str,
+    pub url: &'static str,
+    /// Date the terms at `url` were last reviewed for this table
+    pub revision: &'static str,
+    /// Whether the terms grant commercial use of outputs; `None` when it varies by model
+    /// or plan
+    pub commercial_use: Option<bool>,
+}
+
+/// Terms of the built-in cloud providers; update `revision` when re-reviewing an entry
+pub const TERMS: &[TermsSnapshot] = &[
+    TermsSnapshot {
+        provider: "anthropic",
+        url: "https://www.anthropic.com/legal/commercial-terms",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "openai",
+        url: "https://openai.com/policies/services-agreement",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "google",
+        url: "https://ai.google.dev/gemini-api/terms",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "grok",
+        url: "https://x.ai/legal/terms-of-service-enterprise",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "flux",
+        url: "https://bfl.ai/legal/terms-of-service",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    // Ownership of outputs depends on the account's plan
+    TermsSnapshot {
+        provider: "recraft",
+        url: "https://www.recraft.ai/terms",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    TermsSnapshot {
+        provider: "runway",
+        url: "https://runwayml.com/terms-of-use",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    // Outputs of free plans are public and licensed differently from paid ones
+    TermsSnapshot {
+        provider: "leonardo",
+        url: "https://leonardo.ai/terms-of-service",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    // Commercial use requires a paid plan
+    TermsSnapshot {
+        provider: "elevenlabs",
+        url: "https://elevenlabs.io/terms-of-use",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    // Commercial use is free below $1M annual revenue, an enterprise license above
+    TermsSnapshot {
+        provider: "stability",
+        url: "https://stability.ai/terms-of-use",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    TermsSnapshot {
+        provider: "vertex",
+        url: "https://cloud.google.com/terms/service-terms",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    // Companies above $1M annual revenue need a Pro or Mega plan
+    TermsSnapshot {
+        provider: "midjourney",
+        url: "https://docs.midjourney.com/hc/en-us/articles/32083055291277-Terms-of-Service",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    TermsSnapshot {
+        provider: "perplexity",
+        url: "https://www.perplexity.ai/hub/legal/perplexity-api-terms-of-service",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "groq",
+        url: "https://groq.com/terms-of-use",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    TermsSnapshot {
+        provider: "volcengine",
+        url: "https://www.volcengine.com/docs/6256/64903",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    TermsSnapshot {
+        provider: "minimax",
+        url: "https://www.minimax.io/platform/protocol/terms-of-service",
+        revision: "2026-10-17",
+        commercial_use: Some(true),
+    },
+    // Commercial use requires a paid plan
+    TermsSnapshot {
+        provider: "pika",
+        url: "https://pika.art/terms-of-service",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+    // fal hosts models under their own licenses
+    TermsSnapshot {
+        provider: "fal",
+        url: "https://fal.ai/terms",
+        revision: "2026-10-17",
+        commercial_use: None,
+    },
+];
+
+pub fn terms(provider: &str) -> Option<&'static TermsSnapshot> {
+    TERMS.iter().find(|terms| terms.provider == provider)
+}
+
+/// The model build or version a provider reported in its response
+pub fn reported_model_version(metadata: &Value) -> Option<String> {
+    let string = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    // Gemini reports `modelVersion`; Anthropic and OpenAI echo the dated model ID
+    if let Some(version) = string("modelVersion").or_else(|| string("model")) {
+        return Some(version);
+    }
+
+    // A1111 reports the checkpoint inside its JSON-encoded `info`
+    let info: Value = serde_json::from_str(metadata.get("info")?.as_str()?).ok()?;
+    let name = info.get("sd_model_name").and_then(|v| v.as_str());
+    match (name, info.get("sd_model_hash").and_then(|v| v.as_str())) {
+        (Some(name), Some(hash)) => Some(format!("{} [{}]", name, hash)),
+        (name, hash) => name.or(hash).map(str::to_string),
+    }
+}
+
+/// Record the provenance of a finished generation
+pub async fn record(
+    pool: &SqlitePool,
+    job_id: &str,
+    provider: &str,
+    model: &str,
+    result: &GenerationResult,
+) -> Result<ProvenanceRecord> {
+    let terms = terms(provider);
+    ProvenanceOps::create(
+        pool,
+        CreateProvenanceInput {
+            job_id: job_id.to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            model_version: reported_model_version(&result.metadata),
+            terms_url: terms.map(|terms| terms.url.to_string()),
+            terms_revision: terms.map(|terms| terms.revision.to_string()),
+            commercial_use: terms.and_then(|terms| terms.commercial_use),
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reported_model_version() {
+        let gemini = serde_json::json!({ "modelVersion": "gemini-2.5-flash-image-001" });
+        assert_eq!(reported_model_version(&gemini).as_deref(), Some("gemini-2.5-flash-image-001"));
+
+        let info = r#"{"sd_model_name": "sdxl_base", "sd_model_hash": "31e35c80fc"}"#;
+        let a1111 = serde_json::json!({ "provider": "a1111", "info": info });
+        assert_eq!(reported_model_version(&a1111).as_deref(), Some("sdxl_base [31e35c80fc]"));
+
+        assert_eq!(reported_model_version(&serde_json::json!({ "data": [] })), None);
+        assert!(terms("a1111").is_none());
+        assert_eq!(terms("fal").unwrap().commercial_use, None);
+    }
+}
@@ -1,20 +1,160 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use futures_util::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
-use crate::generation::utils::{extract_reference_image, get_reference_image_params};
+use std::sync::RwLock;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    GenerationStream, ModelInfo, ProgressSender, StreamChunk,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, extract_reference_image, get_reference_image_params, send_with_retry,
+};
+use crate::generation::validation::stable_diffusion_schema;
 
 /// Automatic1111 provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct A1111Config {
     pub api_url: String,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+}
+
+/// Extensions and scripts detected on the WebUI instance
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct A1111Capabilities {
+    /// False until detection has succeeded once
+    pub detected: bool,
+    pub controlnet: bool,
+    pub adetailer: bool,
+    pub regional_prompter: bool,
+    /// Enabled extension names
+    pub extensions: Vec<String>,
+    /// txt2img and img2img script names
+    pub scripts: Vec<String>,
+}
+
+impl A1111Capabilities {
+    fn from_lists(extensions: Vec<String>, scripts: Vec<String>) -> Self {
+        let normalized: Vec<String> = extensions
+            .iter()
+            .chain(scripts.iter())
+            .map(|name| name.to_lowercase().replace(['-', '_'], " "))
+            .collect();
+        let has = |needle: &str| normalized.iter().any(|name| name.contains(needle));
+
+        Self {
+            detected: true,
+            controlnet: has("controlnet"),
+            adetailer: has("adetailer"),
+            regional_prompter: has("regional prompter"),
+            extensions,
+            scripts,
+        }
+    }
+
+    /// Whether an always-on script with this name is installed
+    pub fn has_script(&self, name: &str) -> bool {
+        self.scripts.iter().any(|script| script.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Always-on script name registered by the ADetailer extension
+const ADETAILER_SCRIPT: &str = "ADetailer";
+
+/// Detector used for the `face` and `hands` shorthands
+const ADETAILER_FACE_MODEL: &str = "face_yolov8n.pt";
+const ADETAILER_HAND_MODEL: &str = "hand_yolov8n.pt";
+
+/// Build the ADetailer `alwayson_scripts` entry from an `adetailer` parameters block
+///
+/// Accepts `true` (one face pass), `{ "face": true, "hands": true }`, or
+/// `{ "passes": [{ "model": ..., "prompt": ..., "denoising_strength": ... }] }`.
+/// Pass keys are mapped to ADetailer's `ad_*` arguments; `ad_*` keys are kept as-is.
+fn adetailer_script(block: &serde_json::Value) -> Option<serde_json::Value> {
+    let face = || serde_json::json!({ "ad_model": ADETAILER_FACE_MODEL });
+
+    let passes: Vec<serde_json::Value> = match block {
+        serde_json::Value::Bool(true) => vec![face()],
+        serde_json::Value::Object(options) => {
+            if options.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+                return None;
+            }
+            match options.get("passes").and_then(|v| v.as_array()) {
+                Some(passes) => passes.iter().filter_map(adetailer_pass).collect(),
+                None => {
+                    let mut passes = Vec::new();
+                    if options.get("face").and_then(|v| v.as_bool()).unwrap_or(true) {
+                        passes.push(face());
+                    }
+                    if options.get("hands").and_then(|v| v.as_bool()).unwrap_or(false) {
+                        passes.push(serde_json::json!({ "ad_model": ADETAILER_HAND_MODEL }));
+                    }
+                    passes
+                }
+            }
+        }
+        _ => return None,
+    };
+
+    if passes.is_empty() {
+        return None;
+    }
+
+    // ADetailer args: [enable, skip_img2img, pass...]
+    let mut args = vec![serde_json::json!(true), serde_json::json!(false)];
+    args.extend(passes);
+    Some(serde_json::json!({ "args": args }))
+}
+
+fn adetailer_pass(pass: &serde_json::Value) -> Option<serde_json::Value> {
+    let pass = pass.as_object()?;
+    let mut args = serde_json::Map::new();
+
+    for (key, value) in pass {
+        let mapped = match key.as_str() {
+            "model" => "ad_model",
+            "prompt" => "ad_prompt",
+            "negative_prompt" => "ad_negative_prompt",
+            "confidence" => "ad_confidence",
+            "denoising_strength" => "ad_denoising_strength",
+            "mask_blur" => "ad_mask_blur",
+            "inpaint_only_masked" => "ad_inpaint_only_masked",
+            "steps" => {
+                args.insert("ad_use_steps".to_string(), serde_json::json!(true));
+                "ad_steps"
+            }
+            key if key.starts_with("ad_") => key,
+            _ => continue,
+        };
+        args.insert(mapped.to_string(), value.clone());
+    }
+
+    args.entry("ad_model")
+        .or_insert_with(|| serde_json::json!(ADETAILER_FACE_MODEL));
+    Some(serde_json::Value::Object(args))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtensionInfo {
+    name: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 /// Automatic1111 Stable Diffusion WebUI provider
 pub struct A1111Provider {
     config: Option<A1111Config>,
     client: reqwest::Client,
+    capabilities: RwLock<A1111Capabilities>,
 }
 
 impl A1111Provider {
@@ -22,15 +162,58 @@ impl A1111Provider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("a1111").client(),
+            capabilities: RwLock::new(A1111Capabilities::default()),
         }
     }
 
     pub fn with_config(config: A1111Config) -> Self {
         Self {
+            client: config.timeouts.client(),
             config: Some(config),
-            client: reqwest::Client::new(),
+            capabilities: RwLock::new(A1111Capabilities::default()),
+        }
+    }
+
+    /// Cached capabilities from the last detection
+    fn cached_capabilities(&self) -> A1111Capabilities {
+        self.capabilities
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Query installed extensions and scripts
+    async fn fetch_capabilities(&self, config: &A1111Config) -> Result<A1111Capabilities> {
+        let request = self.client.get(format!("{}/sdapi/v1/extensions", config.api_url));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("A1111 API", response).await);
         }
+        let extensions: Vec<ExtensionInfo> = response.json().await?;
+
+        let request = self.client.get(format!("{}/sdapi/v1/scripts", config.api_url));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("A1111 API", response).await);
+        }
+        let scripts: serde_json::Value = response.json().await?;
+
+        let extensions = extensions
+            .into_iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.name)
+            .collect();
+        let mut script_names: Vec<String> = ["txt2img", "img2img"]
+            .iter()
+            .filter_map(|mode| scripts.get(*mode).and_then(|v| v.as_array()))
+            .flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect();
+        script_names.sort();
+        script_names.dedup();
+
+        Ok(A1111Capabilities::from_lists(extensions, script_names))
     }
 
     /// Generate image using A1111 txt2img endpoint
@@ -38,6 +221,7 @@ impl A1111Provider {
         &self,
         prompt: &str,
         params: &serde_json::Value,
+        progress: Option<&ProgressSender>,
     ) -> Result<GenerationResult> {
         let config = self
             .config
@@ -45,28 +229,25 @@ impl A1111Provider {
             .ok_or_else(|| anyhow::anyhow!("A1111 API URL not configured"))?;
 
         // Extract parameters with defaults
-        let negative_prompt = params
-            .get("negative_prompt")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+        let values = ModelParams::new(
+            "a1111",
+            params.get("model").and_then(|v| v.as_str()).unwrap_or(""),
+            params,
+        );
 
-        let steps = params.get("steps").and_then(|v| v.as_u64()).unwrap_or(20) as u32;
+        let negative_prompt = values.str("negative_prompt");
 
-        let cfg_scale = params
-            .get("cfg_scale")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(7.0) as f32;
+        let steps = values.u64("steps") as u32;
 
-        let width = params.get("width").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let cfg_scale = values.f64("cfg_scale") as f32;
 
-        let height = params.get("height").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let width = values.u64("width") as u32;
 
-        let sampler_name = params
-            .get("sampler")
-            .and_then(|v| v.as_str())
-            .unwrap_or("Euler a");
+        let height = values.u64("height") as u32;
+
+        let sampler_name = values.str("sampler");
 
-        let seed = params.get("seed").and_then(|v| v.as_i64()).unwrap_or(-1) as i32;
+        let seed = values.i64("seed") as i32;
 
         let model = params.get("model").and_then(|v| v.as_str());
 
@@ -104,7 +285,7 @@ impl A1111Provider {
                 };
                 request_body["resize_mode"] = serde_json::json!(resize_mode_int);
 
-                eprintln!("Using A1111 img2img with denoising_strength={}", denoising_strength);
+                log_debug!("Using A1111 img2img with denoising_strength={}", denoising_strength);
             }
             "/sdapi/v1/img2img"
         } else {
@@ -114,6 +295,30 @@ impl A1111Provider {
             "/sdapi/v1/txt2img"
         };
 
+        // Pass extension scripts through, dropping any the instance is known not to have
+        let mut scripts = params
+            .get("alwayson_scripts")
+            .and_then(|v| v.as_object())
+            .cloned()
+            .unwrap_or_default();
+        if let Some(adetailer) = params.get("adetailer").and_then(adetailer_script) {
+            scripts.insert(ADETAILER_SCRIPT.to_string(), adetailer);
+        }
+        if !scripts.is_empty() {
+            let capabilities = self.cached_capabilities();
+            let mut enabled = serde_json::Map::new();
+            for (name, args) in scripts {
+                if capabilities.detected && !capabilities.has_script(&name) {
+                    log_warn!("A1111 script '{}' is not installed, skipping it", name);
+                    continue;
+                }
+                enabled.insert(name, args);
+            }
+            if !enabled.is_empty() {
+                request_body["alwayson_scripts"] = serde_json::Value::Object(enabled);
+            }
+        }
+
         // If model specified, set override_settings
         if let Some(model_name) = model {
             request_body["override_settings"] = serde_json::json!({
@@ -123,23 +328,31 @@ impl A1111Provider {
 
         // Send request to A1111 API
         let url = format!("{}{}", config.api_url, endpoint);
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "A1111 API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let send = send_with_retry(request);
+
+        // A1111 blocks until the image is done, so poll /progress alongside the request
+        let response = match progress {
+            Some(tx) => {
+                tokio::pin!(send);
+                loop {
+                    tokio::select! {
+                        response = &mut send => break response?,
+                        _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {
+                            self.report_sampling_progress(config, tx).await;
+                        }
+                    }
+                }
+            }
+            None => send.await?,
+        };
+
+        if !response.status().is_success() {
+            return Err(api_error("A1111 API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -150,22 +363,37 @@ impl A1111Provider {
             .and_then(|v| v.as_array())
             .ok_or_else(|| anyhow::anyhow!("No images in response"))?;
 
-        let first_image = images
-            .first()
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid image data"))?;
-
         // Get generation info
         let info = response_data
             .get("info")
             .and_then(|v| v.as_str())
             .unwrap_or("");
 
-        Ok(GenerationResult {
-            output_url: None,
-            output_data: Some(first_image.to_string()),
-            file_path: None,
-            metadata: serde_json::json!({
+        // `info` is a JSON string listing the seed actually used for each image
+        let seeds: Vec<i64> = serde_json::from_str::<serde_json::Value>(info)
+            .ok()
+            .and_then(|info| info.get("all_seeds")?.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|seed| seed.as_i64())
+            .collect();
+        let artifacts: Vec<Artifact> = images
+            .iter()
+            .filter_map(|image| image.as_str())
+            .enumerate()
+            .map(|(i, image)| {
+                Artifact::from_data(image.to_string()).with_seed(seeds.get(i).copied())
+            })
+            .collect();
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("Invalid image data"));
+        }
+
+        report_progress(progress, 100.0, "Generation complete");
+
+        Ok(GenerationResult::new(
+            artifacts,
+            serde_json::json!({
                 "provider": "a1111",
                 "mode": if has_reference_image { "img2img" } else { "txt2img" },
                 "info": info,
@@ -179,9 +407,56 @@ impl A1111Provider {
                     "sampler": sampler_name,
                     "seed": seed,
                     "has_reference_image": has_reference_image,
+                    "scripts": request_body
+                        .get("alwayson_scripts")
+                        .and_then(|v| v.as_object())
+                        .map(|scripts| scripts.keys().cloned().collect::<Vec<_>>())
+                        .unwrap_or_default(),
                 }
             }),
-        })
+        ))
+    }
+
+    /// Query A1111's /progress endpoint and forward the sampling state
+    async fn report_sampling_progress(&self, config: &A1111Config, tx: &ProgressSender) {
+        let url = format!("{}/sdapi/v1/progress?skip_current_image=true", config.api_url);
+        let Ok(response) = self.client.get(&url).send().await else {
+            return;
+        };
+        let Ok(data) = response.json::<serde_json::Value>().await else {
+            return;
+        };
+
+        let fraction = data.get("progress").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let state = data.get("state");
+        let step = state
+            .and_then(|s| s.get("sampling_step"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let steps = state
+            .and_then(|s| s.get("sampling_steps"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let eta = data.get("eta_relative").and_then(|v| v.as_f64()).unwrap_or(0.0);
+
+        let message = if steps > 0 {
+            format!("Sampling step {}/{} (~{:.0}s remaining)", step, steps, eta)
+        } else {
+            "Waiting for A1111".to_string()
+        };
+
+        report_progress(Some(tx), (fraction * 100.0) as f32, message);
+    }
+
+    /// The image being sampled and the percentage done, if A1111 has one to show yet
+    async fn current_preview(&self) -> Option<(String, f32)> {
+        let config = self.config.as_ref()?;
+        let url = format!("{}/sdapi/v1/progress?skip_current_image=false", config.api_url);
+        let data: serde_json::Value = self.client.get(&url).send().await.ok()?.json().await.ok()?;
+
+        let image = data.get("current_image").and_then(|v| v.as_str())?;
+        let fraction = data.get("progress").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        Some((image.to_string(), (fraction * 100.0) as f32))
     }
 }
 
@@ -195,11 +470,97 @@ impl GenerationProvider for A1111Provider {
         self.config.is_some()
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("A1111 API URL not configured"))?;
+
+        let request = self.client.get(format!("{}/sdapi/v1/sd-models", config.api_url));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("A1111 API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+
+        // `title` is what `sd_model_checkpoint` expects
+        Ok(ModelInfo::from_list(&body, "title", Some("model_name")))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the server
+        self.list_models().await.map(|_| ())
+    }
+
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
-        self.generate_image(&request.prompt, &request.parameters)
+        self.generate_image(&request.prompt, &request.parameters, None)
+            .await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_image(&request.prompt, &request.parameters, Some(&progress))
             .await
     }
 
+    fn generate_stream(&self, request: GenerationRequest) -> GenerationStream<'_> {
+        struct State<'a> {
+            generation: BoxFuture<'a, Result<GenerationResult>>,
+            last_preview: Option<String>,
+            finished: bool,
+        }
+        enum Step {
+            Finished(Result<GenerationResult>),
+            Tick,
+        }
+
+        let state = State {
+            generation: Box::pin(async move {
+                self.generate_image(&request.prompt, &request.parameters, None)
+                    .await
+            }),
+            last_preview: None,
+            finished: false,
+        };
+
+        // Like progress reporting, poll /progress while the request blocks, yielding each
+        // new intermediate image
+        Box::pin(futures_util::stream::unfold(state, move |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            loop {
+                let step = tokio::select! {
+                    result = &mut state.generation => Step::Finished(result),
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => Step::Tick,
+                };
+                match step {
+                    Step::Finished(result) => {
+                        state.finished = true;
+                        let chunk = result.map(|result| StreamChunk::Done { result });
+                        return Some((chunk, state));
+                    }
+                    Step::Tick => {
+                        let Some((data, percentage)) = self.current_preview().await else {
+                            continue;
+                        };
+                        if state.last_preview.as_ref() != Some(&data) {
+                            state.last_preview = Some(data.clone());
+                            return Some((Ok(StreamChunk::Preview { data, percentage }), state));
+                        }
+                    }
+                }
+            }
+        }))
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        stable_diffusion_schema()
+    }
+
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
@@ -214,4 +575,61 @@ impl GenerationProvider for A1111Provider {
             "required": ["api_url"]
         })
     }
+
+    async fn detect_capabilities(&self) -> Result<serde_json::Value> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("A1111 API URL not configured"))?;
+
+        let capabilities = self.fetch_capabilities(config).await?;
+        *self.capabilities.write().unwrap_or_else(|e| e.into_inner()) = capabilities.clone();
+        Ok(serde_json::to_value(capabilities)?)
+    }
+
+    fn capabilities(&self) -> serde_json::Value {
+        serde_json::to_value(self.cached_capabilities()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_from_lists() {
+        let capabilities = A1111Capabilities::from_lists(
+            vec!["sd-webui-controlnet".to_string(), "sd-webui-regional-prompter".to_string()],
+            vec!["adetailer".to_string(), "x/y/z plot".to_string()],
+        );
+        assert!(capabilities.detected);
+        assert!(capabilities.controlnet);
+        assert!(capabilities.adetailer);
+        assert!(capabilities.regional_prompter);
+
+        let bare = A1111Capabilities::from_lists(vec![], vec!["prompt matrix".to_string()]);
+        assert!(!bare.controlnet && !bare.adetailer && !bare.regional_prompter);
+    }
+
+    #[test]
+    fn test_adetailer_script() {
+        let shorthand = adetailer_script(&serde_json::json!({ "hands": true })).unwrap();
+        let args = shorthand["args"].as_array().unwrap();
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[2]["ad_model"], ADETAILER_FACE_MODEL);
+        assert_eq!(args[3]["ad_model"], ADETAILER_HAND_MODEL);
+
+        let custom = adetailer_script(&serde_json::json!({
+            "passes": [{ "prompt": "detailed eyes", "denoising_strength": 0.35, "steps": 30 }]
+        }))
+        .unwrap();
+        let pass = &custom["args"][2];
+        assert_eq!(pass["ad_prompt"], "detailed eyes");
+        assert_eq!(pass["ad_denoising_strength"], 0.35);
+        assert_eq!(pass["ad_use_steps"], true);
+        assert_eq!(pass["ad_model"], ADETAILER_FACE_MODEL);
+
+        assert!(adetailer_script(&serde_json::json!(false)).is_none());
+        assert!(adetailer_script(&serde_json::json!({ "enabled": false })).is_none());
+    }
 }
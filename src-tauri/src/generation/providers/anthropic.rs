@@ -1,13 +1,42 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, GenerationStream,
+    ModelInfo, StreamChunk,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, endpoint_url, extract_reference_images, send_with_retry, SseBuffer,
+};
 
 /// Anthropic provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnthropicConfig {
     pub api_key: String,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+impl AnthropicConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
 }
 
 /// Anthropic text generation response
@@ -37,71 +66,90 @@ impl AnthropicProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("anthropic").client(),
         }
     }
 
     pub fn with_config(config: AnthropicConfig) -> Self {
         Self {
+            client: config.timeouts.client_with_headers(&config.headers),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
-    /// Generate text using Claude models
-    async fn generate_text(
+    /// Send a Messages API request, as server-sent events with `stream`
+    async fn send_messages(
         &self,
         prompt: &str,
         model: &str,
         params: &serde_json::Value,
-    ) -> Result<GenerationResult> {
+        stream: bool,
+    ) -> Result<reqwest::Response> {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Anthropic API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("Anthropic"))?;
+
+        let values = ModelParams::new("anthropic", model, params);
+
+        let max_tokens = values.u64("max_tokens") as u32;
 
-        let max_tokens = params
-            .get("max_tokens")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(4096) as u32;
+        let temperature = values.f64("temperature");
 
-        let temperature = params
-            .get("temperature")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(1.0);
+        // Reference images are sent ahead of the prompt, for vision models
+        let content = match extract_reference_images(params) {
+            Some(images) => {
+                let mut blocks: Vec<serde_json::Value> = images
+                    .into_iter()
+                    .map(|(media_type, data)| {
+                        serde_json::json!({
+                            "type": "image",
+                            "source": { "type": "base64", "media_type": media_type, "data": data }
+                        })
+                    })
+                    .collect();
+                blocks.push(serde_json::json!({ "type": "text", "text": prompt }));
+                serde_json::Value::Array(blocks)
+            }
+            None => serde_json::json!(prompt),
+        };
 
         let request_body = serde_json::json!({
             "model": model,
             "max_tokens": max_tokens,
             "temperature": temperature,
+            "stream": stream,
             "messages": [
                 {
                     "role": "user",
-                    "content": prompt
+                    "content": content
                 }
             ]
         });
 
-        let response = self
+        let request = self
             .client
-            .post("https://api.anthropic.com/v1/messages")
+            .post(config.url("messages"))
             .header("x-api-key", &config.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "Anthropic API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("Anthropic API", response).await);
         }
+        Ok(response)
+    }
+
+    /// Generate text using Claude models
+    async fn generate_text(
+        &self,
+        prompt: &str,
+        model: &str,
+        params: &serde_json::Value,
+    ) -> Result<GenerationResult> {
+        let response = self.send_messages(prompt, model, params, false).await?;
 
         let response_data: AnthropicResponse = response.json().await?;
 
@@ -113,19 +161,142 @@ impl AnthropicProvider {
             .collect::<Vec<_>>()
             .join("");
 
-        // For text generation, we return the text in output_data
-        Ok(GenerationResult {
-            output_url: None,
-            output_data: Some(text),
-            file_path: None,
-            metadata: serde_json::json!({
+        // For text generation, we return the text as the only artifact
+        Ok(GenerationResult::new(
+            vec![Artifact::text(text)],
+            serde_json::json!({
                 "id": response_data.id,
                 "model": response_data.model,
                 "stop_reason": response_data.stop_reason,
                 "usage": response_data.usage,
             }),
-        })
+        ))
+    }
+}
+
+/// A message being received as server-sent events
+#[derive(Debug, Default)]
+struct StreamedMessage {
+    id: serde_json::Value,
+    model: serde_json::Value,
+    stop_reason: serde_json::Value,
+    usage: serde_json::Map<String, serde_json::Value>,
+    text: String,
+}
+
+impl StreamedMessage {
+    /// Apply one event, returning the chunk it produced, if any
+    fn apply(&mut self, payload: &str) -> Option<Result<StreamChunk>> {
+        let event: serde_json::Value = match serde_json::from_str(payload) {
+            Ok(event) => event,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let mut take_usage = |usage: Option<&serde_json::Value>| {
+            if let Some(usage) = usage.and_then(|u| u.as_object()) {
+                self.usage.extend(usage.clone());
+            }
+        };
+
+        match event.get("type").and_then(|t| t.as_str()) {
+            Some("message_start") => {
+                take_usage(event.pointer("/message/usage"));
+                self.id = event.pointer("/message/id").cloned().unwrap_or_default();
+                self.model = event.pointer("/message/model").cloned().unwrap_or_default();
+                None
+            }
+            Some("content_block_delta") => {
+                let delta = event.pointer("/delta/text").and_then(|t| t.as_str())?;
+                self.text.push_str(delta);
+                Some(Ok(StreamChunk::Text {
+                    delta: delta.to_string(),
+                }))
+            }
+            Some("message_delta") => {
+                take_usage(event.get("usage"));
+                self.stop_reason = event.pointer("/delta/stop_reason").cloned().unwrap_or_default();
+                None
+            }
+            Some("message_stop") => Some(Ok(StreamChunk::Done {
+                result: self.result(),
+            })),
+            Some("error") => {
+                let message = event.pointer("/error/message").and_then(|m| m.as_str());
+                Some(Err(anyhow::anyhow!(
+                    "Anthropic API error: {}",
+                    message.unwrap_or("stream failed")
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    /// The message so far, shaped like a non-streamed result
+    fn result(&self) -> GenerationResult {
+        GenerationResult::new(
+            vec![Artifact::text(self.text.clone())],
+            serde_json::json!({
+                "id": self.id,
+                "model": self.model,
+                "stop_reason": self.stop_reason,
+                "usage": self.usage,
+            }),
+        )
+    }
+}
+
+/// Chunks of a streamed Messages API response
+fn message_stream(response: reqwest::Response) -> GenerationStream<'static> {
+    struct State {
+        response: reqwest::Response,
+        buffer: SseBuffer,
+        message: StreamedMessage,
+        queued: VecDeque<Result<StreamChunk>>,
+        finished: bool,
     }
+    let state = State {
+        response,
+        buffer: SseBuffer::default(),
+        message: StreamedMessage::default(),
+        queued: VecDeque::new(),
+        finished: false,
+    };
+
+    Box::pin(futures_util::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.queued.pop_front() {
+                return Some((item, state));
+            }
+            if state.finished {
+                return None;
+            }
+            match state.response.chunk().await {
+                Ok(Some(bytes)) => {
+                    for payload in state.buffer.push(&bytes) {
+                        let Some(item) = state.message.apply(&payload) else {
+                            continue;
+                        };
+                        // Nothing follows the final message or an error
+                        let last = !matches!(item, Ok(StreamChunk::Text { .. }));
+                        state.queued.push_back(item);
+                        if last {
+                            state.finished = true;
+                            break;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    state.finished = true;
+                    state.queued.push_back(Err(anyhow::anyhow!(
+                        "Anthropic stream ended before the message was complete"
+                    )));
+                }
+                Err(e) => {
+                    state.finished = true;
+                    state.queued.push_back(Err(e.into()));
+                }
+            }
+        }
+    }))
 }
 
 #[async_trait]
@@ -138,12 +309,59 @@ impl GenerationProvider for AnthropicProvider {
         self.config.is_some()
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Anthropic"))?;
+
+        let request = self
+            .client
+            .get(config.url("models?limit=1000"))
+            .header("x-api-key", &config.api_key)
+            .header("anthropic-version", "2023-06-01");
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Anthropic API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(ModelInfo::from_list(&body["data"], "id", Some("display_name")))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
         // Support Claude models for text generation
         self.generate_text(&request.prompt, &request.model, &request.parameters)
             .await
     }
 
+    fn generate_stream(&self, request: GenerationRequest) -> GenerationStream<'_> {
+        let send = async move {
+            self.send_messages(&request.prompt, &request.model, &request.parameters, true)
+                .await
+        };
+        Box::pin(
+            futures_util::stream::once(send).flat_map(|response| match response {
+                Ok(response) => message_stream(response),
+                Err(e) => Box::pin(futures_util::stream::once(async { Err(e) })),
+            }),
+        )
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "max_tokens": { "type": "integer", "minimum": 1 },
+                "temperature": { "type": "number", "minimum": 0, "maximum": 1 }
+            }
+        })
+    }
+
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
@@ -152,9 +370,48 @@ impl GenerationProvider for AnthropicProvider {
                     "type": "string",
                     "title": "API Key",
                     "description": "Your Anthropic API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint or gateway to use instead of the public API"
                 }
             },
             "required": ["api_key"]
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENTS: [&str; 4] = [
+        r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":5}}}"#,
+        r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"Hi"}}"#,
+        concat!(
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"#,
+            r#""usage":{"output_tokens":2}}"#
+        ),
+        r#"{"type":"message_stop"}"#,
+    ];
+
+    #[test]
+    fn test_streamed_message_events() {
+        let mut message = StreamedMessage::default();
+        let mut chunks: Vec<StreamChunk> = EVENTS
+            .iter()
+            .filter_map(|event| message.apply(event))
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(&chunks[0], StreamChunk::Text { delta } if delta == "Hi"));
+        let Some(StreamChunk::Done { result }) = chunks.pop() else {
+            panic!("expected the final result");
+        };
+        assert_eq!(result.output_data(), Some("Hi"));
+        assert_eq!(result.metadata["usage"]["output_tokens"], 2);
+        assert_eq!(result.metadata["stop_reason"], "end_turn");
+    }
+}
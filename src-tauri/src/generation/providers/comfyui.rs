@@ -4,13 +4,33 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::time::sleep;
 
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
-use crate::generation::utils::{extract_reference_image, get_reference_image_params};
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, extract_reference_image, get_reference_image_params, send_with_retry,
+};
+use crate::generation::validation::stable_diffusion_schema;
 
 /// ComfyUI provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComfyUIConfig {
     pub api_url: String,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+}
+
+/// Aborts the wrapped background task when dropped
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 /// ComfyUI provider
@@ -24,14 +44,14 @@ impl ComfyUIProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("comfyui").client(),
         }
     }
 
     pub fn with_config(config: ComfyUIConfig) -> Self {
         Self {
+            client: config.timeouts.client(),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
@@ -40,6 +60,7 @@ impl ComfyUIProvider {
         &self,
         prompt: &str,
         params: &serde_json::Value,
+        progress: Option<&ProgressSender>,
     ) -> Result<GenerationResult> {
         let config = self
             .config
@@ -47,28 +68,25 @@ impl ComfyUIProvider {
             .ok_or_else(|| anyhow::anyhow!("ComfyUI API URL not configured"))?;
 
         // Extract parameters
-        let negative_prompt = params
-            .get("negative_prompt")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+        let values = ModelParams::new(
+            "comfyui",
+            params.get("model").and_then(|v| v.as_str()).unwrap_or(""),
+            params,
+        );
 
-        let steps = params.get("steps").and_then(|v| v.as_u64()).unwrap_or(20) as u32;
+        let negative_prompt = values.str("negative_prompt");
 
-        let cfg_scale = params
-            .get("cfg_scale")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(7.0) as f32;
+        let steps = values.u64("steps") as u32;
 
-        let width = params.get("width").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let cfg_scale = values.f64("cfg_scale") as f32;
 
-        let height = params.get("height").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let width = values.u64("width") as u32;
 
-        let sampler = params
-            .get("sampler")
-            .and_then(|v| v.as_str())
-            .unwrap_or("euler");
+        let height = values.u64("height") as u32;
 
-        let seed = params.get("seed").and_then(|v| v.as_i64()).unwrap_or(-1);
+        let sampler = values.str("sampler");
+
+        let seed = values.i64("seed");
 
         let model = params
             .get("model")
@@ -82,7 +100,7 @@ impl ComfyUIProvider {
 
             if let Some(cn_type) = controlnet_type {
                 // Build ControlNet workflow
-                eprintln!("Building ComfyUI ControlNet workflow (type: {})", cn_type);
+                log_debug!("Building ComfyUI ControlNet workflow (type: {})", cn_type);
                 self.build_controlnet_workflow(
                     prompt,
                     negative_prompt,
@@ -98,7 +116,7 @@ impl ComfyUIProvider {
                 )
             } else {
                 // Build img2img workflow
-                eprintln!("Building ComfyUI img2img workflow");
+                log_debug!("Building ComfyUI img2img workflow");
                 self.build_img2img_workflow(
                     prompt,
                     negative_prompt,
@@ -126,26 +144,30 @@ impl ComfyUIProvider {
             )
         };
 
+        // ComfyUI only streams progress to the websocket of the submitting client
+        let client_id = uuid::Uuid::new_v4().to_string();
+        let _listener = match progress {
+            Some(tx) => {
+                self.spawn_progress_listener(config, &client_id, tx.clone())
+                    .await
+            }
+            None => None,
+        };
+
         // Submit workflow to ComfyUI
         let prompt_url = format!("{}/prompt", config.api_url);
-        let response = self
+        let request = self
             .client
             .post(&prompt_url)
             .header("Content-Type", "application/json")
             .json(&serde_json::json!({
-                "prompt": workflow
-            }))
-            .send()
-            .await?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "ComfyUI API error ({}): {}",
-                status,
-                error_text
-            ));
+                "prompt": workflow,
+                "client_id": client_id
+            }));
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("ComfyUI API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -157,15 +179,19 @@ impl ComfyUIProvider {
         // Poll for completion
         let output_images = self.poll_for_completion(config, prompt_id).await?;
 
-        let first_image = output_images
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No images generated"))?;
+        if output_images.is_empty() {
+            return Err(anyhow::anyhow!("No images generated"));
+        }
+        let artifacts = output_images
+            .into_iter()
+            .map(|url| Artifact::from_url(url).with_seed(Some(seed)))
+            .collect();
 
-        Ok(GenerationResult {
-            output_url: Some(first_image.clone()),
-            output_data: None,
-            file_path: None,
-            metadata: serde_json::json!({
+        report_progress(progress, 100.0, "Generation complete");
+
+        Ok(GenerationResult::new(
+            artifacts,
+            serde_json::json!({
                 "provider": "comfyui",
                 "prompt_id": prompt_id,
                 "parameters": {
@@ -180,7 +206,7 @@ impl ComfyUIProvider {
                     "seed": seed,
                 }
             }),
-        })
+        ))
     }
 
     /// Build a basic txt2img workflow for ComfyUI
@@ -451,6 +477,69 @@ impl ComfyUIProvider {
         })
     }
 
+    /// Listen on ComfyUI's websocket and forward sampling progress until dropped
+    async fn spawn_progress_listener(
+        &self,
+        config: &ComfyUIConfig,
+        client_id: &str,
+        tx: ProgressSender,
+    ) -> Option<AbortOnDrop> {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // http:// -> ws://, https:// -> wss://
+        let ws_url = format!(
+            "{}/ws?clientId={}",
+            config.api_url.replacen("http", "ws", 1),
+            client_id
+        );
+
+        let (mut socket, _) = match tokio_tungstenite::connect_async(ws_url.as_str()).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                log_warn!("ComfyUI progress websocket unavailable: {}", e);
+                return None;
+            }
+        };
+
+        let handle = tokio::spawn(async move {
+            let mut percentage = 0.0f32;
+
+            while let Some(Ok(message)) = socket.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let Ok(event) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    continue;
+                };
+                let data = event.get("data");
+
+                match event.get("type").and_then(|t| t.as_str()) {
+                    Some("progress") => {
+                        let value = data.and_then(|d| d.get("value")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                        let max = data.and_then(|d| d.get("max")).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                        if max > 0.0 {
+                            percentage = (value / max * 100.0) as f32;
+                        }
+                        report_progress(
+                            Some(&tx),
+                            percentage,
+                            format!("Sampling step {}/{}", value, max),
+                        );
+                    }
+                    Some("executing") => {
+                        if let Some(node) = data.and_then(|d| d.get("node")).and_then(|n| n.as_str()) {
+                            report_progress(Some(&tx), percentage, format!("Executing node {}", node));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Some(AbortOnDrop(handle))
+    }
+
     /// Poll ComfyUI for workflow completion
     async fn poll_for_completion(
         &self,
@@ -469,7 +558,7 @@ impl ComfyUIProvider {
             sleep(Duration::from_secs(1)).await;
             attempts += 1;
 
-            let response = self.client.get(&history_url).send().await?;
+            let response = send_with_retry(self.client.get(&history_url)).await?;
 
             if !response.status().is_success() {
                 continue;
@@ -522,11 +611,60 @@ impl GenerationProvider for ComfyUIProvider {
         self.config.is_some()
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("ComfyUI API URL not configured"))?;
+
+        let request = self
+            .client
+            .get(format!("{}/object_info/CheckpointLoaderSimple", config.api_url));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("ComfyUI API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+
+        // The checkpoint loader's `ckpt_name` input lists every installed checkpoint
+        let checkpoints = body
+            .pointer("/CheckpointLoaderSimple/input/required/ckpt_name/0")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+        Ok(checkpoints
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|name| ModelInfo {
+                id: name.to_string(),
+                name: None,
+            })
+            .collect())
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the server
+        self.list_models().await.map(|_| ())
+    }
+
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
-        self.generate_image(&request.prompt, &request.parameters)
+        self.generate_image(&request.prompt, &request.parameters, None)
             .await
     }
 
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_image(&request.prompt, &request.parameters, Some(&progress))
+            .await
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        stable_diffusion_schema()
+    }
+
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
@@ -0,0 +1,299 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, check_connection, endpoint_url, send_with_retry};
+
+/// ElevenLabs configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElevenLabsConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.elevenlabs.io/v1";
+
+/// Voice used when a request names none ("George", a premade voice every account has)
+pub const DEFAULT_VOICE_ID: &str = "JBFqnCBsd6RMkjVDRZzb";
+
+const MODELS: &[(&str, &str)] = &[
+    ("eleven_multilingual_v2", "Multilingual v2"),
+    ("eleven_v3", "Eleven v3"),
+    ("eleven_flash_v2_5", "Flash v2.5"),
+    ("eleven_turbo_v2_5", "Turbo v2.5"),
+];
+
+const OUTPUT_FORMATS: &[&str] = &[
+    "mp3_44100_128",
+    "mp3_44100_192",
+    "mp3_22050_32",
+    "wav_44100",
+    "wav_22050",
+];
+
+/// A voice of the account
+#[derive(Debug, Clone, Serialize)]
+pub struct ElevenLabsVoice {
+    pub id: String,
+    pub name: String,
+    /// `premade`, `cloned`, `generated` or `professional`
+    pub category: Option<String>,
+}
+
+/// Voices found by the last `detect_capabilities` call
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ElevenLabsCapabilities {
+    pub detected: bool,
+    pub voices: Vec<ElevenLabsVoice>,
+}
+
+impl ElevenLabsConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// MIME type of an output format such as `mp3_44100_128`
+pub fn mime_type(output_format: &str) -> &'static str {
+    if output_format.starts_with("wav") {
+        "audio/wav"
+    } else {
+        "audio/mpeg"
+    }
+}
+
+/// Voice, output format and body of a speech request
+pub fn request_body(model: &str, text: &str, params: &Value) -> Result<(String, String, Value)> {
+    let values = ModelParams::new("elevenlabs", model, params);
+    let output_format = values.str("output_format");
+    if !OUTPUT_FORMATS.contains(&output_format) {
+        return Err(GenerationError::invalid_param(
+            "output_format",
+            format!("ElevenLabs output format must be one of {}", OUTPUT_FORMATS.join(", ")),
+        )
+        .into());
+    }
+    if text.trim().is_empty() {
+        let message = "There is no text to speak".to_string();
+        return Err(GenerationError::invalid_param("prompt", message).into());
+    }
+
+    let mut body = serde_json::json!({
+        "text": text,
+        "model_id": model,
+        "voice_settings": {
+            "stability": values.f64("stability"),
+            "similarity_boost": values.f64("similarity_boost"),
+            "style": values.f64("style"),
+            "use_speaker_boost": values.bool("use_speaker_boost"),
+        },
+    });
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        body["seed"] = serde_json::json!(seed);
+    }
+    if let Some(language) = params.get("language_code").and_then(|v| v.as_str()) {
+        body["language_code"] = serde_json::json!(language);
+    }
+    Ok((values.str("voice_id").to_string(), output_format.to_string(), body))
+}
+
+/// ElevenLabs provider (text to speech, e.g. narration of storyboard scenes)
+pub struct ElevenLabsProvider {
+    config: Option<ElevenLabsConfig>,
+    client: reqwest::Client,
+    capabilities: RwLock<ElevenLabsCapabilities>,
+}
+
+impl ElevenLabsProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("elevenlabs").client(),
+            capabilities: RwLock::new(ElevenLabsCapabilities::default()),
+        }
+    }
+
+    pub fn with_config(config: ElevenLabsConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+            capabilities: RwLock::new(ElevenLabsCapabilities::default()),
+        }
+    }
+
+    fn config(&self) -> Result<&ElevenLabsConfig> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("ElevenLabs").into())
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for ElevenLabsProvider {
+    fn name(&self) -> &str {
+        "elevenlabs"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self.config()?;
+        let request = self
+            .client
+            .get(config.url("user"))
+            .header("xi-api-key", &config.api_key);
+        check_connection("ElevenLabs API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let config = self.config()?;
+        let (voice_id, output_format, body) =
+            request_body(&request.model, &request.prompt, &request.parameters)?;
+
+        let speak = self
+            .client
+            .post(config.url(&format!("text-to-speech/{}", voice_id)))
+            .query(&[("output_format", output_format.as_str())])
+            .header("xi-api-key", &config.api_key)
+            .json(&body);
+        let response = send_with_retry(speak).await?;
+        if !response.status().is_success() {
+            return Err(api_error("ElevenLabs API", response).await);
+        }
+        let audio = response.bytes().await?;
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&audio))
+            .with_mime_type(mime_type(&output_format));
+
+        Ok(GenerationResult::new(
+            vec![artifact],
+            serde_json::json!({
+                "voice_id": voice_id,
+                "output_format": output_format,
+                "characters": request.prompt.chars().count(),
+            }),
+        ))
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "voice_id": { "type": "string" },
+                "stability": { "type": "number", "minimum": 0, "maximum": 1 },
+                "similarity_boost": { "type": "number", "minimum": 0, "maximum": 1 },
+                "style": { "type": "number", "minimum": 0, "maximum": 1 },
+                "use_speaker_boost": { "type": "boolean" },
+                "output_format": { "type": "string", "enum": OUTPUT_FORMATS },
+                "language_code": { "type": "string" },
+                "seed": { "type": "integer" }
+            }
+        })
+    }
+
+    /// Fetch the account's voices, for picking a `voice_id`
+    async fn detect_capabilities(&self) -> Result<serde_json::Value> {
+        let config = self.config()?;
+        let request = self
+            .client
+            .get(config.url("voices"))
+            .header("xi-api-key", &config.api_key);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("ElevenLabs API", response).await);
+        }
+        let body: Value = response.json().await?;
+
+        let string = |voice: &Value, key: &str| {
+            voice.get(key).and_then(|v| v.as_str()).map(str::to_string)
+        };
+        let capabilities = ElevenLabsCapabilities {
+            detected: true,
+            voices: body
+                .get("voices")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|voice| {
+                    let id = string(voice, "voice_id")?;
+                    Some(ElevenLabsVoice {
+                        name: string(voice, "name").unwrap_or_else(|| id.clone()),
+                        category: string(voice, "category"),
+                        id,
+                    })
+                })
+                .collect(),
+        };
+        *self.capabilities.write().unwrap_or_else(|e| e.into_inner()) = capabilities.clone();
+        Ok(serde_json::to_value(capabilities)?)
+    }
+
+    fn capabilities(&self) -> serde_json::Value {
+        let capabilities = self.capabilities.read().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_value(&*capabilities).unwrap_or_default()
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your ElevenLabs API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body() {
+        let params = json!({ "stability": 0.3, "output_format": "wav_44100" });
+        let (voice_id, format, body) =
+            request_body("eleven_multilingual_v2", "Once upon a time", &params).unwrap();
+        assert_eq!(voice_id, DEFAULT_VOICE_ID);
+        assert_eq!(mime_type(&format), "audio/wav");
+        assert_eq!(body["voice_settings"]["stability"], 0.3);
+        assert_eq!(body["voice_settings"]["similarity_boost"], 0.75);
+
+        let params = json!({ "output_format": "ogg" });
+        assert!(request_body("eleven_multilingual_v2", "Hi", &params).is_err());
+        assert!(request_body("eleven_multilingual_v2", " ", &json!({})).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Providers implemented by external executables, so new backends can be added
+//! without rebuilding the app.
+//!
+//! The executable is started for every call. It receives one JSON line on stdin,
+//! `{"method": "describe" | "list_models" | "generate", "params": ...}`, and answers
+//! with JSON lines on stdout: any number of `{"progress": {"percentage", "message"}}`
+//! updates while generating, then `{"result": ...}` or `{"error": "..."}`.
+//! `generate` results use the shape of [`GenerationResult`]; `list_models` returns
+//! `[{"id", "name"}]`; `describe` returns `{"config_schema", "capabilities"}` and
+//! optionally a `parameter_schema` applying to all of its models.
+//! Lines written to stderr end up in the app log.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use super::super::{
+    GenerationProgress, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+    ProgressSender,
+};
+use crate::generation::network;
+use crate::secrets;
+
+/// Settings key for the registered external providers
+pub const EXTERNAL_PROVIDERS_SETTINGS_KEY: &str = "external_providers";
+
+/// How long a plugin may take to describe itself or list models
+const QUICK_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Built-in providers without an API key entry or a local server
+const OTHER_BUILT_INS: &[&str] = &["openai_compatible", "mock"];
+
+/// Names of built-in providers, which plugins cannot take over
+fn is_reserved(name: &str) -> bool {
+    secrets::API_KEY_PROVIDERS.contains(&name)
+        || network::LOCAL_PROVIDERS.contains(&name)
+        || OTHER_BUILT_INS.contains(&name)
+}
+
+/// How to start an external provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalProviderConfig {
+    /// Provider name used in jobs and workflows
+    pub name: String,
+    /// Path of the executable
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// A plugin's answer to `describe`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginDescription {
+    pub config_schema: Value,
+    pub capabilities: Value,
+    pub parameter_schema: Value,
+}
+
+/// One line a plugin writes to stdout
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PluginMessage {
+    Progress(GenerationProgress),
+    Result(Value),
+    Error(String),
+}
+
+/// Provider backed by an external executable
+pub struct ExternalProvider {
+    config: ExternalProviderConfig,
+    description: PluginDescription,
+}
+
+impl ExternalProvider {
+    /// Start the plugin once to check that it speaks the protocol and read its schema
+    pub async fn connect(config: ExternalProviderConfig) -> Result<Self> {
+        validate_name(&config.name)?;
+        let mut provider = Self {
+            config,
+            description: PluginDescription::default(),
+        };
+        let description = provider.quick_call("describe", Value::Null).await?;
+        provider.description = serde_json::from_value(description)?;
+        Ok(provider)
+    }
+
+    pub fn description(&self) -> &PluginDescription {
+        &self.description
+    }
+
+    async fn quick_call(&self, method: &str, params: Value) -> Result<Value> {
+        tokio::time::timeout(QUICK_CALL_TIMEOUT, self.call(method, params, None))
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!("Plugin {} did not answer {} in time", self.config.name, method)
+            })?
+    }
+
+    /// Run the executable for one request and wait for its result
+    async fn call(
+        &self,
+        method: &str,
+        params: Value,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let name = &self.config.name;
+        let mut child = tokio::process::Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // Timeouts and cancelled jobs drop the future; the plugin must not outlive it
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start plugin {}: {}", name, e))?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let name = name.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    log_info!("[Plugin {}] {}", name, line);
+                }
+            });
+        }
+
+        let mut request = serde_json::to_vec(&serde_json::json!({
+            "method": method,
+            "params": params,
+        }))?;
+        request.push(b'\n');
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} has no stdin", name))?;
+        stdin.write_all(&request).await?;
+        drop(stdin);
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} has no stdout", name))?;
+        let mut lines = BufReader::new(stdout).lines();
+        let outcome = loop {
+            let Some(line) = lines.next_line().await? else {
+                break Err(anyhow::anyhow!("Plugin {} exited without a result", name));
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<PluginMessage>(&line) {
+                Ok(PluginMessage::Progress(update)) => {
+                    if let Some(tx) = progress {
+                        let _ = tx.send(update);
+                    }
+                }
+                Ok(PluginMessage::Result(result)) => break Ok(result),
+                Ok(PluginMessage::Error(message)) => {
+                    break Err(anyhow::anyhow!("Plugin {} error: {}", name, message))
+                }
+                Err(e) => break Err(anyhow::anyhow!("Plugin {} sent invalid output: {}", name, e)),
+            }
+        };
+
+        let _ = child.wait().await;
+        outcome
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for ExternalProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn is_available(&self) -> bool {
+        command_exists(&self.config.command)
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let result = self
+            .call("generate", serde_json::to_value(&request)?, None)
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        let result = self
+            .call("generate", serde_json::to_value(&request)?, Some(&progress))
+            .await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let models = self.quick_call("list_models", Value::Null).await?;
+        Ok(ModelInfo::from_list(&models, "id", Some("name")))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.quick_call("describe", Value::Null).await.map(|_| ())
+    }
+
+    fn config_schema(&self) -> Value {
+        self.description.config_schema.clone()
+    }
+
+    fn parameter_schema(&self, _model: &str) -> Value {
+        self.description.parameter_schema.clone()
+    }
+
+    fn capabilities(&self) -> Value {
+        self.description.capabilities.clone()
+    }
+}
+
+/// Plugin names are lowercase identifiers that do not shadow built-in providers
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Invalid provider name {:?}: use lowercase letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    if is_reserved(name) {
+        return Err(anyhow::anyhow!("{} is a built-in provider", name));
+    }
+    Ok(())
+}
+
+/// Whether `command` names an existing file, looked up on `PATH` when it is a bare name
+fn command_exists(command: &str) -> bool {
+    let path = Path::new(command);
+    if path.is_absolute() || path.components().count() > 1 {
+        return path.is_file();
+    }
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        // Windows finds `plugin` as `plugin.exe` and the like
+        #[cfg(windows)]
+        {
+            let extensions =
+                std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+            let found = extensions
+                .split(';')
+                .any(|extension| dir.join(format!("{}{}", command, extension)).is_file());
+            if found {
+                return true;
+            }
+        }
+        dir.join(command).is_file()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("my-flux_2").is_ok());
+        assert!(validate_name("openai").is_err());
+        assert!(validate_name("comfyui").is_err());
+        assert!(validate_name("mock").is_err());
+        assert!(validate_name("My Plugin").is_err());
+        assert!(validate_name("").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_exists() {
+        assert!(command_exists("sh"));
+        assert!(command_exists("/bin/sh"));
+        assert!(!command_exists("promptcraft-no-such-plugin"));
+    }
+
+    #[test]
+    fn test_parse_plugin_messages() {
+        let progress: PluginMessage =
+            serde_json::from_str(r#"{"progress": {"percentage": 40.0, "message": "Sampling"}}"#)
+                .unwrap();
+        assert!(matches!(progress, PluginMessage::Progress(p) if p.percentage == 40.0));
+        let error: PluginMessage = serde_json::from_str(r#"{"error": "no GPU"}"#).unwrap();
+        assert!(matches!(error, PluginMessage::Error(message) if message == "no GPU"));
+    }
+}
@@ -0,0 +1,383 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_authenticated, endpoint_url, extract_reference_image, send_with_retry,
+    PLACEHOLDER_TASK_ID,
+};
+
+/// fal.ai configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FalConfig {
+    pub api_key: String,
+    /// Queue root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Queue root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://queue.fal.run";
+
+/// Popular endpoints; any other `owner/app` endpoint ID works too
+const MODELS: &[(&str, &str)] = &[
+    ("fal-ai/flux/schnell", "FLUX.1 [schnell]"),
+    ("fal-ai/flux/dev", "FLUX.1 [dev]"),
+    ("fal-ai/flux-pro/v1.1", "FLUX1.1 [pro]"),
+    ("fal-ai/flux-pro/v1.1-ultra", "FLUX1.1 [pro] Ultra"),
+    ("fal-ai/flux-pro/kontext", "FLUX.1 Kontext [pro]"),
+    ("fal-ai/ltx-video", "LTX Video"),
+    ("fal-ai/kling-video/v2.1/standard/text-to-video", "Kling 2.1 Standard"),
+];
+
+impl FalConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Whether `model` looks like a fal endpoint ID (`owner/app`, optionally with a path)
+fn is_endpoint_id(model: &str) -> bool {
+    let mut segments = model.split('/');
+    let valid = |segment: Option<&str>| segment.is_some_and(|s| !s.trim().is_empty());
+    valid(segments.next()) && valid(segments.next()) && segments.all(|s| !s.is_empty())
+}
+
+/// Endpoint input: the parameters as given plus the prompt
+///
+/// A reference image becomes the `image_url` most image-to-image and Kontext endpoints
+/// take. `webhook_url` is not part of the input; it is sent as the queue's webhook.
+pub fn request_input(prompt: &str, params: &Value) -> Value {
+    let mut input = match params {
+        Value::Object(params) => params.clone(),
+        _ => serde_json::Map::new(),
+    };
+    for key in ["reference_image", "reference_images", "webhook_url"] {
+        input.remove(key);
+    }
+    input.insert("prompt".to_string(), serde_json::json!(prompt));
+    if let Some((mime_type, data)) = extract_reference_image(params) {
+        let data_url = format!("data:{};base64,{}", mime_type, data);
+        input.insert("image_url".to_string(), serde_json::json!(data_url));
+    }
+    Value::Object(input)
+}
+
+/// Images, video or audio in an endpoint's result
+pub fn result_artifacts(result: &Value) -> Vec<Artifact> {
+    let seed = result.get("seed").and_then(|v| v.as_i64());
+    let file = |file: &Value, default_mime: &str| {
+        let url = file.get("url")?.as_str()?;
+        let mime_type = file
+            .get("content_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or(default_mime);
+        Some(Artifact::from_url(url.to_string()).with_mime_type(mime_type))
+    };
+
+    let mut artifacts: Vec<Artifact> = result
+        .get("images")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .chain(result.get("image"))
+        .filter_map(|image| file(image, "image/png"))
+        .map(|artifact| artifact.with_seed(seed))
+        .collect();
+    artifacts.extend(result.get("video").and_then(|video| file(video, "video/mp4")));
+    artifacts.extend(result.get("audio").and_then(|audio| file(audio, "audio/mpeg")));
+    artifacts
+}
+
+/// fal.ai provider (hosted FLUX, video and other endpoints through fal's queue)
+pub struct FalProvider {
+    config: Option<FalConfig>,
+    client: reqwest::Client,
+}
+
+impl FalProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("fal").client(),
+        }
+    }
+
+    pub fn with_config(config: FalConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    /// Queue a request on an endpoint, then poll its status until the result is ready
+    async fn generate_queued(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("fal.ai"))?;
+        if !is_endpoint_id(&request.model) {
+            return Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported fal.ai model: {}. Use an endpoint ID such as 'fal-ai/flux/dev'.",
+                    request.model
+                ),
+            )
+            .into());
+        }
+
+        let mut submit = self
+            .client
+            .post(config.url(&request.model))
+            .header("Authorization", format!("Key {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request_input(&request.prompt, &request.parameters));
+        // fal calls the webhook when the request finishes; the job still polls, as the
+        // app itself cannot receive it
+        if let Some(webhook) = request.parameters.get("webhook_url").and_then(|v| v.as_str()) {
+            submit = submit.query(&[("fal_webhook", webhook)]);
+        }
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("fal.ai API", response).await);
+        }
+        let queued: Value = response.json().await?;
+
+        let request_id = queued
+            .get("request_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No request ID in fal.ai response"))?;
+        let url = |key: &str, suffix: &str| {
+            queued
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    config.url(&format!("{}/requests/{}{}", request.model, request_id, suffix))
+                })
+        };
+        let (status_url, response_url) = (url("status_url", "/status"), url("response_url", ""));
+
+        report_progress(progress, 0.0, "fal.ai request queued");
+        self.wait_for_completion(config, &status_url, progress).await?;
+
+        let request = self
+            .client
+            .get(&response_url)
+            .header("Authorization", format!("Key {}", config.api_key));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("fal.ai result", response).await);
+        }
+        let mut result: Value = response.json().await?;
+        let artifacts = result_artifacts(&result);
+        if let Some(metadata) = result.as_object_mut() {
+            metadata.insert("request_id".to_string(), serde_json::json!(request_id));
+        }
+        Ok(GenerationResult::new(artifacts, result))
+    }
+
+    /// Poll a queued request's status until it completes
+    async fn wait_for_completion(
+        &self,
+        config: &FalConfig,
+        status_url: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<()> {
+        let mut delay_ms = 500u64;
+        let max_delay_ms = 5000u64;
+        let max_attempts = 360; // ~30 minutes max wait time, for video endpoints
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let request = self
+                .client
+                .get(status_url)
+                .query(&[("logs", "1")])
+                .header("Authorization", format!("Key {}", config.api_key));
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The request kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("fal.ai poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+
+            // Completed requests answer 200, queued and running ones 202
+            if !response.status().is_success() {
+                return Err(api_error("fal.ai status", response).await);
+            }
+            let status: Value = response.json().await?;
+
+            match status.get("status").and_then(|v| v.as_str()) {
+                Some("COMPLETED") => {
+                    if let Some(error) = status.get("error").filter(|e| !e.is_null()) {
+                        return Err(anyhow::anyhow!("fal.ai generation failed: {}", error));
+                    }
+                    return Ok(());
+                }
+                Some("IN_QUEUE") => {
+                    let position = status.get("queue_position").and_then(|v| v.as_u64());
+                    let message = match position {
+                        Some(position) => format!("Queued at fal.ai (position {})", position),
+                        None => "Queued at fal.ai".to_string(),
+                    };
+                    report_progress(progress, 0.0, message);
+                }
+                _ => {
+                    let last_log = status
+                        .get("logs")
+                        .and_then(|logs| logs.as_array())
+                        .and_then(|logs| logs.last())
+                        .and_then(|log| log.get("message"))
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("Generating at fal.ai");
+                    report_progress(progress, 0.0, last_log);
+                }
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 500, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("fal.ai generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "fal.ai generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for FalProvider {
+    fn name(&self) -> &str {
+        "fal"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // fal hosts hundreds of endpoints without a listing; offer the common ones
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("fal.ai"))?;
+
+        // The queue has no account endpoint; look up a request that does not exist
+        let path = format!("{}/requests/{}/status", MODELS[0].0, PLACEHOLDER_TASK_ID);
+        let request = self
+            .client
+            .get(config.url(&path))
+            .header("Authorization", format!("Key {}", config.api_key));
+        check_authenticated("fal.ai API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_queued(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_queued(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        // Endpoints validate their own input; these are the fields most of them share
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "num_images": { "type": "integer", "minimum": 1, "maximum": 4 },
+                "seed": { "type": "integer" },
+                "num_inference_steps": { "type": "integer", "minimum": 1, "maximum": 50 },
+                "guidance_scale": { "type": "number", "minimum": 0, "maximum": 20 },
+                "enable_safety_checker": { "type": "boolean" },
+                "webhook_url": { "type": "string" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your fal.ai API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public queue"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_input_and_artifacts() {
+        assert!(is_endpoint_id("fal-ai/flux/dev"));
+        assert!(!is_endpoint_id("flux-dev"));
+        assert!(!is_endpoint_id("fal-ai//dev"));
+
+        let params = json!({ "num_images": 2, "webhook_url": "https://hooks.example/fal" });
+        let input = request_input("a fox", &params);
+        assert_eq!(input, json!({ "num_images": 2, "prompt": "a fox" }));
+
+        let result = json!({
+            "images": [{ "url": "https://fal.media/a.jpg", "content_type": "image/jpeg" }],
+            "seed": 42
+        });
+        let artifacts = result_artifacts(&result);
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].mime_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(artifacts[0].seed, Some(42));
+
+        let video = json!({ "video": { "url": "https://fal.media/v.mp4" } });
+        assert_eq!(result_artifacts(&video)[0].mime_type.as_deref(), Some("video/mp4"));
+    }
+}
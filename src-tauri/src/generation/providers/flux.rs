@@ -0,0 +1,376 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_connection, endpoint_url, extract_reference_image, send_with_retry,
+};
+
+/// Black Forest Labs configuration (hosted FLUX API)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FluxConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.bfl.ai/v1";
+
+/// Models offered by the API, which double as their endpoint paths
+const MODELS: &[(&str, &str)] = &[
+    ("flux-pro-1.1", "FLUX1.1 [pro]"),
+    ("flux-pro-1.1-ultra", "FLUX1.1 [pro] Ultra"),
+    ("flux-pro", "FLUX.1 [pro]"),
+    ("flux-dev", "FLUX.1 [dev]"),
+    ("flux-kontext-pro", "FLUX.1 Kontext [pro]"),
+    ("flux-kontext-max", "FLUX.1 Kontext [max]"),
+];
+
+impl FluxConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Endpoint of a model, resolving the short `flux-kontext` name
+fn endpoint(model: &str) -> Option<&'static str> {
+    match model {
+        "flux-kontext" => Some("flux-kontext-pro"),
+        "flux-ultra" => Some("flux-pro-1.1-ultra"),
+        _ => MODELS.iter().map(|(id, _)| *id).find(|id| *id == model),
+    }
+}
+
+/// Whether a model takes an aspect ratio rather than a width and height
+fn uses_aspect_ratio(endpoint: &str) -> bool {
+    endpoint.starts_with("flux-kontext") || endpoint.ends_with("ultra")
+}
+
+/// Request body of a generation on `endpoint`
+pub fn request_body(endpoint: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("flux", endpoint, params);
+    let mut body = serde_json::json!({
+        "prompt": prompt,
+        "output_format": values.str("output_format"),
+        "safety_tolerance": values.u64("safety_tolerance"),
+    });
+
+    let seed = values.i64("seed");
+    if seed >= 0 {
+        body["seed"] = serde_json::json!(seed);
+    }
+
+    if uses_aspect_ratio(endpoint) {
+        body["aspect_ratio"] = serde_json::json!(values.str("aspect_ratio"));
+        if endpoint.ends_with("ultra") {
+            body["raw"] = serde_json::json!(values.bool("raw"));
+        }
+    } else {
+        // Dimensions must be multiples of 32
+        body["width"] = serde_json::json!(values.u64("width") / 32 * 32);
+        body["height"] = serde_json::json!(values.u64("height") / 32 * 32);
+        body["prompt_upsampling"] = serde_json::json!(values.bool("prompt_upsampling"));
+        if matches!(endpoint, "flux-pro" | "flux-dev") {
+            body["steps"] = serde_json::json!(values.u64("steps"));
+            body["guidance"] = serde_json::json!(values.f64("guidance"));
+        }
+    }
+
+    // Kontext edits the reference image; the others use it as an image prompt
+    if let Some((_, data)) = extract_reference_image(params) {
+        let field = if endpoint.starts_with("flux-kontext") {
+            "input_image"
+        } else {
+            "image_prompt"
+        };
+        body[field] = serde_json::json!(data);
+    }
+    body
+}
+
+/// Black Forest Labs provider (FLUX pro, dev and Kontext without a local ComfyUI)
+pub struct FluxProvider {
+    config: Option<FluxConfig>,
+    client: reqwest::Client,
+}
+
+impl FluxProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("flux").client(),
+        }
+    }
+
+    pub fn with_config(config: FluxConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    /// Submit a generation task, then poll it until the image is ready
+    async fn generate_image(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Black Forest Labs"))?;
+
+        let endpoint = endpoint(&request.model).ok_or_else(|| {
+            GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported FLUX model: {}. Use 'flux-pro-1.1', 'flux-pro', 'flux-dev' or \
+                     'flux-kontext-pro'.",
+                    request.model
+                ),
+            )
+        })?;
+        let body = request_body(endpoint, &request.prompt, &request.parameters);
+
+        let submit = self
+            .client
+            .post(config.url(endpoint))
+            .header("x-key", &config.api_key)
+            .header("Content-Type", "application/json")
+            .json(&body);
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Black Forest Labs API", response).await);
+        }
+        let task: Value = response.json().await?;
+
+        let id = task
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No task ID in FLUX response"))?;
+        // Tasks may run in another region; its polling URL points there
+        let polling_url = task
+            .get("polling_url")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| config.url(&format!("get_result?id={}", id)));
+
+        report_progress(progress, 0.0, "FLUX task submitted");
+        let mime_type = match body["output_format"].as_str() {
+            Some("jpeg") => "image/jpeg",
+            _ => "image/png",
+        };
+        self.poll_result(config, &polling_url, mime_type, progress).await
+    }
+
+    /// Poll a task until it is ready, refused or failed
+    async fn poll_result(
+        &self,
+        config: &FluxConfig,
+        polling_url: &str,
+        mime_type: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let mut delay_ms = 1000u64;
+        let max_delay_ms = 5000u64;
+        let max_attempts = 180; // ~15 minutes max wait time
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let request = self.client.get(polling_url).header("x-key", &config.api_key);
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The task kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("FLUX poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+
+            if !response.status().is_success() {
+                return Err(api_error("Black Forest Labs poll", response).await);
+            }
+            let response_data: Value = response.json().await?;
+
+            let status = response_data
+                .get("status")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default();
+            match status {
+                "Ready" => {
+                    let result = &response_data["result"];
+                    let artifacts = result
+                        .get("sample")
+                        .and_then(|v| v.as_str())
+                        .map(|url| {
+                            Artifact::from_url(url.to_string())
+                                .with_mime_type(mime_type)
+                                .with_seed(result.get("seed").and_then(|v| v.as_i64()))
+                        })
+                        .into_iter()
+                        .collect();
+                    return Ok(GenerationResult::new(artifacts, response_data));
+                }
+                "Request Moderated" | "Content Moderated" => {
+                    return Err(GenerationError::ContentPolicy {
+                        message: format!("FLUX refused the request: {}", status),
+                    }
+                    .into());
+                }
+                "Error" | "Task not found" => {
+                    return Err(anyhow::anyhow!("FLUX generation failed: {}", response_data));
+                }
+                _ => {}
+            }
+
+            // Progress is reported from 0 to 1 once the task is running
+            let fraction = response_data
+                .get("progress")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0);
+            report_progress(progress, fraction as f32 * 100.0, format!("FLUX task {}", status));
+
+            delay_ms = std::cmp::min(delay_ms + 500, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("FLUX generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "FLUX generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for FluxProvider {
+    fn name(&self) -> &str {
+        "flux"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // The API has no model listing
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Black Forest Labs"))?;
+        let request = self.client.get(config.url("credits")).header("x-key", &config.api_key);
+        check_connection("Black Forest Labs API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_image(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_image(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        let endpoint = endpoint(model).unwrap_or(model);
+        let mut properties = serde_json::json!({
+            "seed": { "type": "integer", "minimum": -1 },
+            "output_format": { "type": "string", "enum": ["jpeg", "png"] },
+            "safety_tolerance": { "type": "integer", "minimum": 0, "maximum": 6 }
+        });
+        if uses_aspect_ratio(endpoint) {
+            properties["aspect_ratio"] = serde_json::json!({ "type": "string" });
+            if endpoint.ends_with("ultra") {
+                properties["raw"] = serde_json::json!({ "type": "boolean" });
+            }
+        } else {
+            let dimension =
+                serde_json::json!({ "type": "integer", "minimum": 256, "maximum": 1440 });
+            properties["width"] = dimension.clone();
+            properties["height"] = dimension;
+            properties["prompt_upsampling"] = serde_json::json!({ "type": "boolean" });
+            if matches!(endpoint, "flux-pro" | "flux-dev") {
+                properties["steps"] =
+                    serde_json::json!({ "type": "integer", "minimum": 1, "maximum": 50 });
+                properties["guidance"] =
+                    serde_json::json!({ "type": "number", "minimum": 1.5, "maximum": 5 });
+            }
+        }
+        serde_json::json!({ "type": "object", "properties": properties })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Black Forest Labs API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint or gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body_per_model() {
+        let params = json!({ "width": 1000, "steps": 40, "seed": 7 });
+        let dev = request_body("flux-dev", "a fox", &params);
+        assert_eq!(dev["width"], 992);
+        assert_eq!(dev["height"], 1024);
+        assert_eq!(dev["steps"], 40);
+        assert_eq!(dev["seed"], 7);
+
+        let pro = request_body("flux-pro-1.1", "a fox", &params);
+        assert!(pro.get("steps").is_none());
+
+        let kontext = request_body(endpoint("flux-kontext").unwrap(), "a fox", &json!({}));
+        assert_eq!(kontext["aspect_ratio"], "1:1");
+        assert!(kontext.get("width").is_none());
+        assert!(kontext.get("seed").is_none());
+    }
+}
@@ -1,15 +1,87 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
-use crate::generation::utils::extract_reference_images;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, endpoint_url, extract_reference_images, send_with_retry,
+};
 
 /// Google AI configuration (for Veo video generation and Nano Banana image generation)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GoogleConfig {
     pub api_key: String,
     pub project_id: Option<String>,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+impl GoogleConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+const IMAGEN_ASPECT_RATIOS: &[&str] = &["1:1", "3:4", "4:3", "9:16", "16:9"];
+
+/// Whether a model is served by the Imagen `:predict` endpoint rather than Gemini
+pub fn is_imagen_model(model: &str) -> bool {
+    model.starts_with("imagen-3") || model.starts_with("imagen-4")
+}
+
+/// Body of an Imagen request
+///
+/// The Gemini API takes neither a negative prompt nor a seed for Imagen; only the
+/// standard and Ultra Imagen 4 models take an image size.
+pub fn imagen_body(
+    model: &str,
+    prompt: &str,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let values = ModelParams::new("google", model, params);
+    let n = values.u64("n");
+    if !(1..=4).contains(&n) {
+        let message = "Imagen makes 1 to 4 images per request".to_string();
+        return Err(GenerationError::invalid_param("n", message).into());
+    }
+    let aspect_ratio = values.str("aspect_ratio");
+    if !IMAGEN_ASPECT_RATIOS.contains(&aspect_ratio) {
+        return Err(GenerationError::invalid_param(
+            "aspect_ratio",
+            format!("Imagen aspect ratio must be one of {}", IMAGEN_ASPECT_RATIOS.join(", ")),
+        )
+        .into());
+    }
+
+    let mut parameters = serde_json::json!({
+        "sampleCount": n,
+        "aspectRatio": aspect_ratio,
+        "personGeneration": values.str("person_generation"),
+    });
+    if model.starts_with("imagen-4") && !model.contains("fast") {
+        if let Some(size) = params.get("image_size").and_then(|v| v.as_str()) {
+            parameters["imageSize"] = serde_json::json!(size);
+        }
+    }
+    Ok(serde_json::json!({ "instances": [{ "prompt": prompt }], "parameters": parameters }))
 }
 
 /// Google provider (Veo for video generation, Nano Banana for image generation via Gemini API)
@@ -22,14 +94,14 @@ impl GoogleProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("google").client(),
         }
     }
 
     pub fn with_config(config: GoogleConfig) -> Self {
         Self {
+            client: config.timeouts.client_with_headers(&config.headers),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
@@ -43,10 +115,12 @@ impl GoogleProvider {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Google API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("Google"))?;
+
+        let values = ModelParams::new("google", model, params);
 
         // Number of images to generate
-        let n = params.get("n").and_then(|v| v.as_u64()).unwrap_or(1);
+        let n = values.u64("n");
 
         // Resolution (for Gemini 3 Pro Image only): 1K, 2K, 4K
         let resolution = params
@@ -54,10 +128,7 @@ impl GoogleProvider {
             .and_then(|v| v.as_str());
 
         // Google Search tool
-        let use_google_search = params
-            .get("google_search")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        let use_google_search = values.bool("google_search");
 
         // Build the request body
         // When Google Search is enabled, we need to support both TEXT and IMAGE modalities
@@ -87,10 +158,10 @@ impl GoogleProvider {
         // Add reference images if present (Gemini supports up to 14)
         if let Some(images) = extract_reference_images(params) {
             let image_count = images.len().min(14); // Limit to 14 images
-            eprintln!("Adding {} reference images to Gemini request", image_count);
+            log_debug!("Adding {} reference images to Gemini request", image_count);
 
             for (index, (mime_type, base64_data)) in images.iter().take(image_count).enumerate() {
-                eprintln!("  Image {}: MIME={}", index + 1, mime_type);
+                log_debug!("  Image {}: MIME={}", index + 1, mime_type);
                 parts.push(serde_json::json!({
                     "inlineData": {
                         "mimeType": mime_type,
@@ -116,29 +187,18 @@ impl GoogleProvider {
         }
 
         // Use the Gemini API endpoint
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-            model
-        );
+        let url = config.url(&format!("models/{}:generateContent", model));
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("x-goog-api-key", &config.api_key)
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "Google Nano Banana API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("Google Nano Banana API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -170,26 +230,82 @@ impl GoogleProvider {
         // Log any text parts (e.g., from Google Search results)
         for part in parts.iter() {
             if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
-                eprintln!("Gemini text response: {}", text);
+                log_debug!("Gemini text response: {}", text);
             }
         }
 
-        // Find the first part with inlineData (the generated image)
-        let image_data = parts
+        // Every part with inlineData is a generated image (base64 encoded)
+        let artifacts: Vec<Artifact> = parts
             .iter()
-            .find_map(|part| {
-                part.get("inlineData")
-                    .and_then(|inline| inline.get("data"))
-                    .and_then(|data| data.as_str())
+            .filter_map(|part| {
+                let inline = part.get("inlineData")?;
+                let data = inline.get("data")?.as_str()?;
+                let artifact = Artifact::from_data(data.to_string());
+                Some(match inline.get("mimeType").and_then(|m| m.as_str()) {
+                    Some(mime) => artifact.with_mime_type(mime),
+                    None => artifact,
+                })
             })
-            .ok_or_else(|| anyhow::anyhow!("No inlineData.data found in response parts"))?;
+            .collect();
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("No inlineData.data found in response parts"));
+        }
 
-        Ok(GenerationResult {
-            output_url: None,
-            output_data: Some(image_data.to_string()), // Base64 encoded image
-            file_path: None,
-            metadata: response_data,
-        })
+        Ok(GenerationResult::new(artifacts, response_data))
+    }
+
+    /// Generate images with Imagen through its `:predict` endpoint
+    async fn generate_imagen(
+        &self,
+        model: &str,
+        prompt: &str,
+        params: &serde_json::Value,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Google"))?;
+        let body = imagen_body(model, prompt, params)?;
+
+        let request = self
+            .client
+            .post(config.url(&format!("models/{}:predict", model)))
+            .header("x-goog-api-key", &config.api_key)
+            .json(&body);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Google Imagen API", response).await);
+        }
+        let mut response_data: serde_json::Value = response.json().await?;
+
+        let artifacts: Vec<Artifact> = response_data
+            .get("predictions")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|prediction| {
+                let data = prediction.get("bytesBase64Encoded")?.as_str()?;
+                let mime_type = prediction
+                    .get("mimeType")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("image/png");
+                Some(Artifact::from_data(data.to_string()).with_mime_type(mime_type))
+            })
+            .collect();
+        if artifacts.is_empty() {
+            // Prompts or outputs blocked by safety filters come back without images
+            return Err(anyhow::anyhow!("No images in Imagen response: {}", response_data));
+        }
+
+        // The images now live in the artifacts; keep the metadata small
+        if let Some(predictions) =
+            response_data.get_mut("predictions").and_then(|p| p.as_array_mut())
+        {
+            for prediction in predictions.iter_mut().filter_map(|p| p.as_object_mut()) {
+                prediction.remove("bytesBase64Encoded");
+            }
+        }
+        Ok(GenerationResult::new(artifacts, response_data))
     }
 
     /// Generate video using Veo via Gemini API
@@ -197,32 +313,30 @@ impl GoogleProvider {
         &self,
         prompt: &str,
         params: &serde_json::Value,
+        progress: Option<&ProgressSender>,
     ) -> Result<GenerationResult> {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Google API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("Google"))?;
 
-        // Aspect ratio: 16:9 (default) or 9:16
-        let aspect_ratio = params
-            .get("aspect_ratio")
-            .or_else(|| params.get("aspectRatio"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("16:9");
+        let values = ModelParams::new("google", "veo", params);
+
+        // Aspect ratio: 16:9 (default) or 9:16; camelCase spelling accepted too
+        let aspect_ratio = match params.get("aspectRatio").and_then(|v| v.as_str()) {
+            Some(ratio) if params.get("aspect_ratio").is_none() => ratio,
+            _ => values.str("aspect_ratio"),
+        };
 
         // Resolution: 720p (default) or 1080p
-        let resolution = params
-            .get("resolution")
-            .and_then(|v| v.as_str())
-            .unwrap_or("720p");
+        let resolution = values.str("resolution");
 
         // Duration in seconds: 4, 6, or 8
-        let duration_seconds = params
-            .get("duration")
-            .or_else(|| params.get("durationSeconds"))
-            .and_then(|v| v.as_u64())
-            .unwrap_or(8)
-            .to_string();
+        let duration_seconds = match params.get("durationSeconds").and_then(|v| v.as_u64()) {
+            Some(secs) if params.get("duration").is_none() => secs,
+            _ => values.u64("duration"),
+        }
+        .to_string();
 
         // Build the request body for Gemini API
         let request_body = serde_json::json!({
@@ -242,29 +356,18 @@ impl GoogleProvider {
             .and_then(|v| v.as_str())
             .unwrap_or("veo-3.1-generate-preview");
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:predictLongRunning",
-            model
-        );
+        let url = config.url(&format!("models/{}:predictLongRunning", model));
 
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("x-goog-api-key", &config.api_key)
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "Google Veo API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("Google Veo API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -277,47 +380,59 @@ impl GoogleProvider {
             .ok_or_else(|| anyhow::anyhow!("No operation name in Veo response"))?;
 
         // Poll for completion
-        let result = self.poll_video_generation(operation_name).await?;
+        report_progress(progress, 0.0, "Veo operation submitted");
+        let result = self.poll_video_generation(operation_name, progress).await?;
 
         Ok(result)
     }
 
     /// Poll for video generation completion
-    async fn poll_video_generation(&self, operation_name: &str) -> Result<GenerationResult> {
+    async fn poll_video_generation(
+        &self,
+        operation_name: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Google API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("Google"))?;
 
         let mut delay_ms = 10000u64; // Start with 10 seconds (video generation is slower)
         let max_delay_ms = 60000u64; // Max 60 seconds between polls
         let max_attempts = 60; // ~30 minutes max wait time
+        let started = std::time::Instant::now();
+        let mut wakes = power::wakes();
 
         for attempt in 0..max_attempts {
             tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+            report_progress(
+                progress,
+                0.0,
+                format!("Veo generation in progress ({}s elapsed)", started.elapsed().as_secs()),
+            );
 
             // Poll the operation status
-            let url = format!(
-                "https://generativelanguage.googleapis.com/v1beta/models/{}",
-                operation_name
-            );
+            let url = config.url(&format!("models/{}", operation_name));
 
-            let response = self
+            let request = self
                 .client
                 .get(&url)
-                .header("x-goog-api-key", &config.api_key)
-                .send()
-                .await?;
-
-            let status = response.status();
-
-            if !status.is_success() {
-                let error_text = response.text().await?;
-                return Err(anyhow::anyhow!(
-                    "Google Veo poll error ({}): {}",
-                    status,
-                    error_text
-                ));
+                .header("x-goog-api-key", &config.api_key);
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The operation kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Veo poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+
+            if !response.status().is_success() {
+                return Err(api_error("Google Veo poll", response).await);
             }
 
             let response_data: serde_json::Value = response.json().await?;
@@ -344,7 +459,7 @@ impl GoogleProvider {
                     .or_else(|| response_data.get("result"));
 
                 // Try to find the video URL in various possible locations
-                let output_url = result
+                let artifacts = result
                     .and_then(|r| {
                         // Try predictions array first
                         r.get("predictions")
@@ -355,21 +470,18 @@ impl GoogleProvider {
                             .or_else(|| r.get("output"))
                     })
                     .and_then(|url| url.as_str())
-                    .map(|s| s.to_string());
-
-                return Ok(GenerationResult {
-                    output_url,
-                    output_data: None,
-                    file_path: None,
-                    metadata: response_data,
-                });
+                    .map(|url| Artifact::from_url(url.to_string()).with_mime_type("video/mp4"))
+                    .into_iter()
+                    .collect();
+
+                return Ok(GenerationResult::new(artifacts, response_data));
             }
 
             // Not done yet, continue polling with exponential backoff
             delay_ms = std::cmp::min(delay_ms + 5000, max_delay_ms);
 
             if attempt % 6 == 0 {
-                eprintln!("Veo generation in progress... (attempt {})", attempt);
+                log_debug!("Veo generation in progress... (attempt {})", attempt);
             }
         }
 
@@ -378,6 +490,39 @@ impl GoogleProvider {
             max_attempts
         ))
     }
+
+    /// Route a request to the image or video endpoint based on model name
+    async fn dispatch(
+        &self,
+        request: GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        match request.model.as_str() {
+            // Nano Banana image generation models
+            "gemini-2.5-flash-image" | "gemini-3-pro-image-preview" => {
+                self.generate_image(&request.model, &request.prompt, &request.parameters).await
+            }
+            // Imagen models, which have their own endpoint and parameters
+            model if is_imagen_model(model) => {
+                self.generate_imagen(model, &request.prompt, &request.parameters).await
+            }
+            // Veo video generation models
+            "veo" | "veo-2" | "veo-2.0-generate-exp" |
+            "veo-3" | "veo-3.1" | "veo-3.1-generate-preview" => {
+                self.generate_video(&request.prompt, &request.parameters, progress).await
+            }
+            _ => Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Google model: {}. Use 'gemini-2.5-flash-image', \
+                     'gemini-3-pro-image-preview' or 'imagen-4.0-generate-001' for images, or \
+                     'veo-3.1-generate-preview' for video generation.",
+                    request.model
+                ),
+            )
+            .into()),
+        }
+    }
 }
 
 #[async_trait]
@@ -390,22 +535,83 @@ impl GenerationProvider for GoogleProvider {
         self.config.is_some()
     }
 
-    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
-        match request.model.as_str() {
-            // Nano Banana image generation models
-            "gemini-2.5-flash-image" | "gemini-3-pro-image-preview" => {
-                self.generate_image(&request.model, &request.prompt, &request.parameters).await
-            }
-            // Veo video generation models
-            "veo" | "veo-2" | "veo-2.0-generate-exp" |
-            "veo-3" | "veo-3.1" | "veo-3.1-generate-preview" => {
-                self.generate_video(&request.prompt, &request.parameters).await
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Google"))?;
+
+        let request = self
+            .client
+            .get(config.url("models?pageSize=1000"))
+            .header("x-goog-api-key", &config.api_key);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Google API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+
+        // Names come back as "models/<id>"; generation requests use the bare id
+        let mut models = ModelInfo::from_list(&body["models"], "name", Some("displayName"));
+        for model in &mut models {
+            if let Some(id) = model.id.strip_prefix("models/") {
+                model.id = id.to_string();
             }
-            _ => Err(anyhow::anyhow!(
-                "Unsupported Google model: {}. Use 'gemini-2.5-flash-image' or 'gemini-3-pro-image-preview' for images, or 'veo-3.1-generate-preview' for video generation.",
-                request.model
-            )),
         }
+        Ok(models)
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.dispatch(request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.dispatch(request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if model.starts_with("veo") {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "duration": { "type": "integer", "enum": [4, 6, 8] },
+                    "durationSeconds": { "type": "integer", "enum": [4, 6, 8] },
+                    "resolution": { "type": "string", "enum": ["720p", "1080p"] },
+                    "aspect_ratio": { "type": "string", "enum": ["16:9", "9:16"] }
+                }
+            });
+        }
+        if is_imagen_model(model) {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "n": { "type": "integer", "minimum": 1, "maximum": 4 },
+                    "aspect_ratio": { "type": "string", "enum": IMAGEN_ASPECT_RATIOS },
+                    "person_generation": {
+                        "type": "string",
+                        "enum": ["dont_allow", "allow_adult", "allow_all"]
+                    },
+                    "image_size": { "type": "string", "enum": ["1K", "2K"] }
+                }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "n": { "type": "integer", "minimum": 1, "maximum": 4 },
+                "google_search": { "type": "boolean" },
+                "resolution": { "type": "string", "enum": ["1K", "2K", "4K"] }
+            }
+        })
     }
 
     fn config_schema(&self) -> serde_json::Value {
@@ -420,9 +626,39 @@ impl GenerationProvider for GoogleProvider {
                 "project_id": {
                     "type": "string",
                     "title": "Project ID (optional)"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint or gateway to use instead of the public API"
                 }
             },
             "required": ["api_key"]
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_imagen_body() {
+        let params = json!({ "n": 2, "aspect_ratio": "16:9", "image_size": "2K" });
+        let body = imagen_body("imagen-4.0-generate-001", "a fox", &params).unwrap();
+        assert_eq!(body["instances"][0]["prompt"], "a fox");
+        assert_eq!(body["parameters"]["sampleCount"], 2);
+        assert_eq!(body["parameters"]["aspectRatio"], "16:9");
+        assert_eq!(body["parameters"]["personGeneration"], "allow_adult");
+        assert_eq!(body["parameters"]["imageSize"], "2K");
+
+        let fast = imagen_body("imagen-4.0-fast-generate-001", "a fox", &params).unwrap();
+        assert!(fast["parameters"].get("imageSize").is_none());
+        assert!(imagen_body("imagen-3.0-generate-002", "x", &json!({ "n": 8 })).is_err());
+        assert!(imagen_body("imagen-3.0-generate-002", "x", &json!({ "aspect_ratio": "2:1" }))
+            .is_err());
+        assert!(is_imagen_model("imagen-3.0-generate-002"));
+        assert!(!is_imagen_model("gemini-2.5-flash-image"));
+    }
+}
@@ -1,13 +1,36 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
+use super::super::{GenerationProvider, GenerationRequest, GenerationResult, ModelInfo};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, endpoint_url, image_artifacts, send_with_retry};
 
 /// Grok configuration (xAI)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrokConfig {
     pub api_key: String,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.x.ai/v1";
+
+impl GrokConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
 }
 
 /// Grok provider (xAI's Aurora image generation)
@@ -20,14 +43,14 @@ impl GrokProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("grok").client(),
         }
     }
 
     pub fn with_config(config: GrokConfig) -> Self {
         Self {
+            client: config.timeouts.client_with_headers(&config.headers),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
@@ -40,20 +63,15 @@ impl GrokProvider {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("xAI API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("xAI"))?;
 
         // Number of images to generate (1-10)
-        let n = params
-            .get("n")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(1)
-            .min(10) as usize;
+        let values = ModelParams::new("grok", "grok-2-image", params);
+
+        let n = values.u64("n").min(10) as usize;
 
         // Response format: url (default) or b64_json
-        let response_format = params
-            .get("response_format")
-            .and_then(|v| v.as_str())
-            .unwrap_or("url");
+        let response_format = values.str("response_format");
 
         let request_body = serde_json::json!({
             "model": "grok-2-image",
@@ -62,49 +80,24 @@ impl GrokProvider {
             "response_format": response_format
         });
 
-        let response = self
+        let request = self
             .client
-            .post("https://api.x.ai/v1/images/generations")
+            .post(config.url("images/generations"))
             .header("Authorization", format!("Bearer {}", config.api_key))
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "xAI Grok API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("xAI Grok API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
 
-        // Extract the image URL or base64 data
-        let output_url = response_data
-            .get("data")
-            .and_then(|data| data.get(0))
-            .and_then(|item| item.get("url"))
-            .and_then(|url| url.as_str())
-            .map(|s| s.to_string());
-
-        let output_data = response_data
-            .get("data")
-            .and_then(|data| data.get(0))
-            .and_then(|item| item.get("b64_json"))
-            .and_then(|b64| b64.as_str())
-            .map(|s| s.to_string());
-
-        Ok(GenerationResult {
-            output_url,
-            output_data,
-            file_path: None,
-            metadata: response_data,
-        })
+        // Extract the image URLs or base64 data
+        let artifacts = image_artifacts(&response_data);
+
+        Ok(GenerationResult::new(artifacts, response_data))
     }
 }
 
@@ -118,6 +111,29 @@ impl GenerationProvider for GrokProvider {
         self.config.is_some()
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Grok"))?;
+
+        let request = self
+            .client
+            .get(config.url("models"))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("xAI API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(ModelInfo::from_list(&body["data"], "id", None))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
         match request.model.as_str() {
             // Image generation models
@@ -127,17 +143,31 @@ impl GenerationProvider for GrokProvider {
             }
             // Legacy/alias support
             "grok-1" | "grok" | "flux" => {
-                eprintln!("Note: Using grok-2-image for Grok image generation");
+                log_info!("Note: Using grok-2-image for Grok image generation");
                 self.generate_image(&request.prompt, &request.parameters)
                     .await
             }
-            _ => Err(anyhow::anyhow!(
-                "Unsupported Grok model: {}. Use 'grok-2-image' for image generation.",
-                request.model
-            )),
+            _ => Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Grok model: {}. Use 'grok-2-image' for image generation.",
+                    request.model
+                ),
+            )
+            .into()),
         }
     }
 
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "n": { "type": "integer", "minimum": 1, "maximum": 10 },
+                "response_format": { "type": "string", "enum": ["url", "b64_json"] }
+            }
+        })
+    }
+
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
@@ -146,6 +176,11 @@ impl GenerationProvider for GrokProvider {
                     "type": "string",
                     "title": "API Key",
                     "description": "Your xAI API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint or gateway to use instead of the public API"
                 }
             },
             "required": ["api_key"]
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{GenerationProvider, GenerationRequest, GenerationResult, ModelInfo};
+use super::openai_compatible::{OpenAICompatibleConfig, OpenAICompatibleProvider};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+
+/// Groq configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroqConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.groq.com/openai/v1";
+
+/// Model used when a request names none, e.g. from prompt enhancement
+pub const DEFAULT_MODEL: &str = "llama-3.3-70b-versatile";
+
+/// Chat parameters with Groq's defaults filled in
+///
+/// Groq only serves chat, so a stray `mode` is dropped to keep it off the images endpoint.
+pub fn request_parameters(model: &str, params: &Value) -> Value {
+    let values = ModelParams::new("groq", model, params);
+    let mut params = params.clone();
+    if let Some(object) = params.as_object_mut() {
+        object.remove("mode");
+        object.insert("max_tokens".to_string(), serde_json::json!(values.u64("max_tokens")));
+        object.insert("temperature".to_string(), serde_json::json!(values.f64("temperature")));
+    }
+    params
+}
+
+/// Groq provider (low-latency chat on open models through its OpenAI-compatible API)
+pub struct GroqProvider {
+    /// Chat client doing the requests
+    chat: Option<OpenAICompatibleProvider>,
+}
+
+impl GroqProvider {
+    pub fn new() -> Self {
+        Self { chat: None }
+    }
+
+    pub fn with_config(config: GroqConfig) -> Self {
+        Self {
+            chat: Some(OpenAICompatibleProvider::with_config(OpenAICompatibleConfig {
+                base_url: config.base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+                api_key: Some(config.api_key),
+                default_model: Some(DEFAULT_MODEL.to_string()),
+                timeouts: config.timeouts,
+                headers: config.headers,
+            })),
+        }
+    }
+
+    fn chat(&self) -> Result<&OpenAICompatibleProvider> {
+        self.chat
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Groq").into())
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for GroqProvider {
+    fn name(&self) -> &str {
+        "groq"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.chat.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.chat()?.list_models().await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.chat()?.test_connection().await
+    }
+
+    async fn generate(&self, mut request: GenerationRequest) -> Result<GenerationResult> {
+        request.parameters = request_parameters(&request.model, &request.parameters);
+        self.chat()?.generate(request).await
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "max_tokens": { "type": "integer", "minimum": 1 },
+                "temperature": { "type": "number", "minimum": 0, "maximum": 2 },
+                "top_p": { "type": "number", "minimum": 0, "maximum": 1 }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Groq API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_parameters() {
+        let params = json!({ "mode": "image", "temperature": 0.2, "top_p": 0.9 });
+        let params = request_parameters(DEFAULT_MODEL, &params);
+        assert!(params.get("mode").is_none());
+        assert_eq!(params["max_tokens"], 1024);
+        assert_eq!(params["temperature"], 0.2);
+        assert_eq!(params["top_p"], 0.9);
+
+        let defaults = request_parameters(DEFAULT_MODEL, &json!({}));
+        assert_eq!(defaults["temperature"], 0.7);
+    }
+}
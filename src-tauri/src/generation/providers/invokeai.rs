@@ -2,12 +2,21 @@ use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, send_with_retry};
+use crate::generation::validation::stable_diffusion_schema;
 
 /// InvokeAI provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvokeAIConfig {
     pub api_url: String,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
 }
 
 /// InvokeAI provider
@@ -21,14 +30,14 @@ impl InvokeAIProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("invokeai").client(),
         }
     }
 
     pub fn with_config(config: InvokeAIConfig) -> Self {
         Self {
+            client: config.timeouts.client(),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
@@ -44,28 +53,25 @@ impl InvokeAIProvider {
             .ok_or_else(|| anyhow::anyhow!("InvokeAI API URL not configured"))?;
 
         // Extract parameters
-        let negative_prompt = params
-            .get("negative_prompt")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
+        let values = ModelParams::new(
+            "invokeai",
+            params.get("model").and_then(|v| v.as_str()).unwrap_or(""),
+            params,
+        );
 
-        let steps = params.get("steps").and_then(|v| v.as_u64()).unwrap_or(20) as u32;
+        let negative_prompt = values.str("negative_prompt");
 
-        let cfg_scale = params
-            .get("cfg_scale")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(7.0) as f32;
+        let steps = values.u64("steps") as u32;
 
-        let width = params.get("width").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let cfg_scale = values.f64("cfg_scale") as f32;
 
-        let height = params.get("height").and_then(|v| v.as_u64()).unwrap_or(512) as u32;
+        let width = values.u64("width") as u32;
 
-        let sampler = params
-            .get("sampler")
-            .and_then(|v| v.as_str())
-            .unwrap_or("euler");
+        let height = values.u64("height") as u32;
+
+        let sampler = values.str("sampler");
 
-        let seed = params.get("seed").and_then(|v| v.as_i64()).unwrap_or(-1);
+        let seed = values.i64("seed");
 
         let model = params
             .get("model")
@@ -87,23 +93,15 @@ impl InvokeAIProvider {
 
         // Send request to InvokeAI API
         let url = format!("{}/api/v1/generate", config.api_url);
-        let response = self
+        let request = self
             .client
             .post(&url)
             .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "InvokeAI API error ({}): {}",
-                status,
-                error_text
-            ));
+            .json(&request_body);
+        let response = send_with_retry(request).await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("InvokeAI API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -120,12 +118,16 @@ impl InvokeAIProvider {
             .and_then(|v| v.get("data"))
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
+        let artifact = Artifact {
+            url: image_url,
+            data: image_data,
+            ..Artifact::default()
+        }
+        .with_seed(Some(seed));
 
-        Ok(GenerationResult {
-            output_url: image_url,
-            output_data: image_data,
-            file_path: None,
-            metadata: serde_json::json!({
+        Ok(GenerationResult::new(
+            vec![artifact],
+            serde_json::json!({
                 "provider": "invokeai",
                 "parameters": {
                     "prompt": prompt,
@@ -139,7 +141,7 @@ impl InvokeAIProvider {
                     "seed": seed,
                 }
             }),
-        })
+        ))
     }
 }
 
@@ -153,11 +155,37 @@ impl GenerationProvider for InvokeAIProvider {
         self.config.is_some()
     }
 
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("InvokeAI API URL not configured"))?;
+
+        let request = self
+            .client
+            .get(format!("{}/api/v2/models/?model_type=main", config.api_url));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("InvokeAI API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(ModelInfo::from_list(&body["models"], "name", None))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the server
+        self.list_models().await.map(|_| ())
+    }
+
     async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
         self.generate_image(&request.prompt, &request.parameters)
             .await
     }
 
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        stable_diffusion_schema()
+    }
+
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
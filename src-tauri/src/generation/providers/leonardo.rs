@@ -0,0 +1,439 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, endpoint_url, send_with_retry};
+
+/// Leonardo.ai configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeonardoConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://cloud.leonardo.ai/api/rest/v1";
+
+/// Leonardo Phoenix 1.0, the model used when routing to Leonardo
+pub const PHOENIX_MODEL_ID: &str = "de7d3faf-762f-48e0-b3b7-9d0ac3a3fcf3";
+
+impl LeonardoConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// An element (Leonardo's name for a LoRA) that can be applied to a generation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LeonardoElement {
+    pub id: String,
+    pub name: String,
+    /// Base model family the element was trained for, e.g. `SDXL_1_0`
+    pub base_model: Option<String>,
+    pub description: Option<String>,
+    pub default_weight: Option<f64>,
+}
+
+impl LeonardoElement {
+    fn from_api(element: &Value) -> Option<Self> {
+        let string = |key: &str| element.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        Some(Self {
+            id: string("akUUID")?,
+            name: string("name").unwrap_or_default(),
+            base_model: string("baseModel"),
+            description: string("description"),
+            default_weight: element.get("weightDefault").and_then(|v| v.as_f64()),
+        })
+    }
+}
+
+/// Elements found by the last `detect_capabilities` call
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LeonardoCapabilities {
+    pub detected: bool,
+    pub elements: Vec<LeonardoElement>,
+}
+
+/// Whether `model` looks like a Leonardo model ID (a UUID)
+fn is_model_id(model: &str) -> bool {
+    let lengths: Vec<usize> = model.split('-').map(str::len).collect();
+    lengths == [8, 4, 4, 4, 12] && model.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+}
+
+/// Elements of a request, given as IDs or as `{ "id", "weight" }` objects
+fn request_elements(params: &Value) -> Vec<Value> {
+    params
+        .get("elements")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|element| {
+            let (id, weight) = match element {
+                Value::String(id) => (id.as_str(), None),
+                _ => (
+                    element
+                        .get("id")
+                        .or_else(|| element.get("akUUID"))
+                        .and_then(|v| v.as_str())?,
+                    element.get("weight").and_then(|v| v.as_f64()),
+                ),
+            };
+            Some(serde_json::json!({ "akUUID": id, "weight": weight.unwrap_or(1.0) }))
+        })
+        .collect()
+}
+
+/// Request body of a generation
+pub fn request_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("leonardo", model, params);
+    let mut body = serde_json::json!({
+        "prompt": prompt,
+        "modelId": model,
+        "width": values.u64("width"),
+        "height": values.u64("height"),
+        "num_images": values.u64("num_images").clamp(1, 8),
+    });
+
+    if let Some(negative) = params.get("negative_prompt").and_then(|v| v.as_str()) {
+        if !negative.trim().is_empty() {
+            body["negative_prompt"] = serde_json::json!(negative);
+        }
+    }
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        body["seed"] = serde_json::json!(seed);
+    }
+    for (param, field) in [
+        ("guidance_scale", "guidance_scale"),
+        ("alchemy", "alchemy"),
+        ("preset_style", "presetStyle"),
+        ("contrast", "contrast"),
+        ("enhance_prompt", "enhancePrompt"),
+    ] {
+        if let Some(value) = params.get(param).filter(|v| !v.is_null()) {
+            body[field] = value.clone();
+        }
+    }
+    let elements = request_elements(params);
+    if !elements.is_empty() {
+        body["elements"] = Value::Array(elements);
+    }
+    body
+}
+
+/// Images of a finished generation
+pub fn result_artifacts(generation: &Value) -> Vec<Artifact> {
+    let seed = generation.get("seed").and_then(|v| v.as_i64());
+    generation
+        .get("generated_images")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|image| {
+            let url = image.get("url")?.as_str()?;
+            let mime_type = if url.ends_with(".png") { "image/png" } else { "image/jpeg" };
+            Some(Artifact::from_url(url.to_string()).with_mime_type(mime_type))
+        })
+        .map(|artifact| artifact.with_seed(seed))
+        .collect()
+}
+
+/// Leonardo.ai provider (platform models with elements, through Leonardo's job API)
+pub struct LeonardoProvider {
+    config: Option<LeonardoConfig>,
+    client: reqwest::Client,
+    capabilities: RwLock<LeonardoCapabilities>,
+}
+
+impl LeonardoProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("leonardo").client(),
+            capabilities: RwLock::new(LeonardoCapabilities::default()),
+        }
+    }
+
+    pub fn with_config(config: LeonardoConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+            capabilities: RwLock::new(LeonardoCapabilities::default()),
+        }
+    }
+
+    fn config(&self) -> Result<&LeonardoConfig> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Leonardo").into())
+    }
+
+    async fn get(&self, config: &LeonardoConfig, path: &str) -> Result<Value> {
+        let request = self
+            .client
+            .get(config.url(path))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Leonardo API", response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Start a generation, then poll it until its images are ready
+    async fn generate_polled(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self.config()?;
+        if !is_model_id(&request.model) {
+            return Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Leonardo model: {}. Use a model ID from the model list.",
+                    request.model
+                ),
+            )
+            .into());
+        }
+
+        let submit = self
+            .client
+            .post(config.url("generations"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request_body(&request.model, &request.prompt, &request.parameters));
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Leonardo API", response).await);
+        }
+        let started: Value = response.json().await?;
+        let job = started.get("sdGenerationJob");
+        let generation_id = job
+            .and_then(|job| job.get("generationId"))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No generation ID in Leonardo response"))?;
+
+        report_progress(progress, 0.0, "Leonardo generation started");
+        let mut generation = self.wait_for_completion(config, generation_id, progress).await?;
+
+        let artifacts = result_artifacts(&generation);
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("No images in Leonardo response"));
+        }
+        if let Some(metadata) = generation.as_object_mut() {
+            metadata.insert("generationId".to_string(), serde_json::json!(generation_id));
+            if let Some(credits) = job.and_then(|job| job.get("apiCreditCost")) {
+                metadata.insert("apiCreditCost".to_string(), credits.clone());
+            }
+        }
+        Ok(GenerationResult::new(artifacts, generation))
+    }
+
+    /// Poll a generation until it is complete, returning it
+    async fn wait_for_completion(
+        &self,
+        config: &LeonardoConfig,
+        generation_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let mut delay_ms = 1000u64;
+        let max_delay_ms = 5000u64;
+        let max_attempts = 120; // ~10 minutes max wait time
+        let path = format!("generations/{}", generation_id);
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let status = match self.get(config, &path).await {
+                Ok(status) => status,
+                // The generation kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Leonardo poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            wakes = power::wakes();
+
+            // The generation can be missing for a moment right after it was started
+            let generation = status.get("generations_by_pk").filter(|g| !g.is_null());
+            match generation.and_then(|g| g.get("status")).and_then(|v| v.as_str()) {
+                Some("COMPLETE") => return Ok(generation.cloned().unwrap_or_default()),
+                Some("FAILED") => {
+                    return Err(anyhow::anyhow!(
+                        "Leonardo generation {} failed",
+                        generation_id
+                    ));
+                }
+                _ => report_progress(progress, 0.0, "Generating at Leonardo"),
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 500, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("Leonardo generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Leonardo generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for LeonardoProvider {
+    fn name(&self) -> &str {
+        "leonardo"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self.config()?;
+        let models = self.get(config, "platformModels").await?;
+        let list = models.get("custom_models").cloned().unwrap_or_default();
+        Ok(ModelInfo::from_list(&list, "id", Some("name")))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_polled(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_polled(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "width": { "type": "integer", "minimum": 32, "maximum": 1536, "multipleOf": 8 },
+                "height": { "type": "integer", "minimum": 32, "maximum": 1536, "multipleOf": 8 },
+                "num_images": { "type": "integer", "minimum": 1, "maximum": 8 },
+                "negative_prompt": { "type": "string" },
+                "seed": { "type": "integer" },
+                "guidance_scale": { "type": "number", "minimum": 1, "maximum": 20 },
+                "alchemy": { "type": "boolean" },
+                "preset_style": { "type": "string" },
+                "contrast": { "type": "number" },
+                "enhance_prompt": { "type": "boolean" },
+                "elements": { "type": "array" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Leonardo.ai API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+
+    /// List the elements the account can apply
+    async fn detect_capabilities(&self) -> Result<serde_json::Value> {
+        let config = self.config()?;
+        let elements = self.get(config, "elements").await?;
+        let capabilities = LeonardoCapabilities {
+            detected: true,
+            elements: elements
+                .get("loras")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(LeonardoElement::from_api)
+                .collect(),
+        };
+        *self.capabilities.write().unwrap_or_else(|e| e.into_inner()) = capabilities.clone();
+        Ok(serde_json::to_value(capabilities)?)
+    }
+
+    fn capabilities(&self) -> serde_json::Value {
+        let capabilities = self.capabilities.read().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_value(&*capabilities).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body_and_artifacts() {
+        assert!(is_model_id(PHOENIX_MODEL_ID));
+        assert!(!is_model_id("phoenix"));
+
+        let params = json!({
+            "num_images": 12,
+            "seed": -1,
+            "preset_style": "CINEMATIC",
+            "elements": ["a1b2", { "id": "c3d4", "weight": 0.4 }, { "weight": 1 }]
+        });
+        let body = request_body(PHOENIX_MODEL_ID, "a knight", &params);
+        assert_eq!(body["num_images"], 8);
+        assert_eq!(body["width"], 1024);
+        assert_eq!(body["presetStyle"], "CINEMATIC");
+        assert!(body.get("seed").is_none());
+        assert_eq!(
+            body["elements"],
+            json!([{ "akUUID": "a1b2", "weight": 1.0 }, { "akUUID": "c3d4", "weight": 0.4 }])
+        );
+
+        let generation = json!({
+            "status": "COMPLETE",
+            "seed": 7,
+            "generated_images": [{ "url": "https://cdn.leonardo.ai/a.jpg" }]
+        });
+        let artifacts = result_artifacts(&generation);
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].mime_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(artifacts[0].seed, Some(7));
+    }
+}
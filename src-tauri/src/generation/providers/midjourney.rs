@@ -0,0 +1,469 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::db::models::{CreateJobInput, Job};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_authenticated, endpoint_url, send_with_retry, PLACEHOLDER_TASK_ID,
+};
+
+/// Midjourney proxy configuration
+///
+/// Midjourney has no public API, so requests go through a proxy service speaking the
+/// GoAPI task API (`POST task`, `GET task/{id}`); `base_url` points at another one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidjourneyConfig {
+    pub api_key: String,
+    /// API root of the proxy service replacing GoAPI
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.goapi.ai/api/v1";
+
+pub const MODEL: &str = "midjourney";
+
+/// Job type of an upscale of one image of a grid
+pub const UPSCALE_JOB_TYPE: &str = "midjourney_upscale";
+/// Job type of variations of one image of a grid
+pub const VARIATION_JOB_TYPE: &str = "midjourney_variation";
+
+const PROCESS_MODES: &[&str] = &["relax", "fast", "turbo"];
+
+/// Prompt flags set from parameters unless the prompt already has them
+const FLAGS: &[(&str, &str)] = &[
+    ("version", "--v"),
+    ("stylize", "--stylize"),
+    ("chaos", "--chaos"),
+];
+
+impl MidjourneyConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Body of a task: an `imagine` grid, or an `upscale` or `variation` of one of its images
+///
+/// Follow-ups name the imagine task in `task_id` and the image of its grid in `index`,
+/// counted from 1 at the top left.
+pub fn task_body(prompt: &str, params: &Value) -> Result<Value> {
+    let values = ModelParams::new("midjourney", MODEL, params);
+    let action = params.get("action").and_then(|v| v.as_str()).unwrap_or("imagine");
+
+    let input = match action {
+        "imagine" => {
+            if prompt.trim().is_empty() {
+                let message = "Midjourney needs a prompt".to_string();
+                return Err(GenerationError::invalid_param("prompt", message).into());
+            }
+            let process_mode = values.str("process_mode");
+            if !PROCESS_MODES.contains(&process_mode) {
+                return Err(GenerationError::invalid_param(
+                    "process_mode",
+                    format!("Process mode must be one of {}", PROCESS_MODES.join(", ")),
+                )
+                .into());
+            }
+
+            let mut text = prompt.trim().to_string();
+            for (key, flag) in FLAGS {
+                let value = match params.get(*key) {
+                    Some(Value::String(value)) if !value.is_empty() => value.clone(),
+                    Some(Value::Number(value)) => value.to_string(),
+                    _ => continue,
+                };
+                if !text.contains(&format!("{} ", flag)) {
+                    text = format!("{} {} {}", text, flag, value);
+                }
+            }
+            serde_json::json!({
+                "prompt": text,
+                "aspect_ratio": values.str("aspect_ratio"),
+                "process_mode": process_mode,
+            })
+        }
+        "upscale" | "variation" => {
+            let task_id = params
+                .get("task_id")
+                .and_then(|v| v.as_str())
+                .filter(|id| !id.is_empty())
+                .ok_or_else(|| {
+                    GenerationError::invalid_param(
+                        "task_id",
+                        format!("A {} needs the task ID of a Midjourney grid", action),
+                    )
+                })?;
+            let index = params
+                .get("index")
+                .and_then(|v| v.as_u64())
+                .filter(|index| (1..=4).contains(index))
+                .ok_or_else(|| {
+                    let message = "Pick image 1 to 4 of the grid".to_string();
+                    GenerationError::invalid_param("index", message)
+                })?;
+            serde_json::json!({ "origin_task_id": task_id, "index": index.to_string() })
+        }
+        other => {
+            return Err(GenerationError::invalid_param(
+                "action",
+                format!("Unknown Midjourney action: {}. Use imagine, upscale or variation.", other),
+            )
+            .into());
+        }
+    };
+
+    Ok(serde_json::json!({ "model": MODEL, "task_type": action, "input": input }))
+}
+
+/// Job upscaling or varying image `index` of a completed Midjourney grid
+pub fn follow_up_job(parent: &Job, action: &str, index: u64) -> Result<CreateJobInput> {
+    let job_type = match action {
+        "upscale" => UPSCALE_JOB_TYPE,
+        "variation" => VARIATION_JOB_TYPE,
+        other => return Err(anyhow::anyhow!("Unknown Midjourney follow-up: {}", other)),
+    };
+    let data: Value = serde_json::from_str(&parent.data)?;
+    if parent.status != "completed" || data.get("provider") != Some(&Value::from("midjourney")) {
+        return Err(anyhow::anyhow!("Only completed Midjourney jobs can be followed up"));
+    }
+    let result: GenerationResult = parent
+        .result
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()?
+        .ok_or_else(|| anyhow::anyhow!("Job {} has no result", parent.id))?;
+    let task_id = result
+        .metadata
+        .get("task_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Job {} has no Midjourney task ID", parent.id))?;
+
+    let mut parameters = serde_json::json!({
+        "action": action,
+        "task_id": task_id,
+        "index": index,
+    });
+    if let Some(mode) = data.pointer("/parameters/process_mode") {
+        parameters["process_mode"] = mode.clone();
+    }
+    Ok(CreateJobInput {
+        workflow_id: parent.workflow_id.clone(),
+        scene_id: parent.scene_id.clone(),
+        job_type: job_type.to_string(),
+        data: serde_json::json!({
+            "provider": "midjourney",
+            "model": MODEL,
+            "prompt": data.get("prompt").cloned().unwrap_or_default(),
+            "parameters": parameters,
+            "parent_job_id": parent.id,
+        }),
+        scheduled_at: None,
+        lane: None,
+    })
+}
+
+/// Midjourney provider (imagine grids, upscales and variations through a proxy service)
+pub struct MidjourneyProvider {
+    config: Option<MidjourneyConfig>,
+    client: reqwest::Client,
+}
+
+impl MidjourneyProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("midjourney").client(),
+        }
+    }
+
+    pub fn with_config(config: MidjourneyConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    /// Submit a task, poll it, then download its image
+    async fn generate_task(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Midjourney"))?;
+        let body = task_body(&request.prompt, &request.parameters)?;
+
+        let submit = self
+            .client
+            .post(config.url("task"))
+            .header("x-api-key", &config.api_key)
+            .json(&body);
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Midjourney proxy", response).await);
+        }
+        let started: Value = response.json().await?;
+        let task_id = started
+            .pointer("/data/task_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No task ID in Midjourney proxy response"))?;
+
+        report_progress(progress, 0.0, "Midjourney task queued");
+        let task = self.wait_for_completion(config, task_id, progress).await?;
+        let output = task.get("output").cloned().unwrap_or_default();
+        // Imagine tasks return the 2x2 grid, follow-ups the single image
+        let url = output
+            .get("image_url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No image URL in Midjourney proxy response"))?;
+
+        report_progress(progress, 100.0, "Downloading image");
+        let response = send_with_retry(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Midjourney download", response).await);
+        }
+        let mime_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| v.starts_with("image/"))
+            .unwrap_or("image/png")
+            .to_string();
+        let image = response.bytes().await?;
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&image))
+            .with_mime_type(&mime_type);
+
+        Ok(GenerationResult::new(
+            vec![artifact],
+            serde_json::json!({
+                "task_id": task_id,
+                "task_type": body["task_type"],
+                "origin_task_id": body["input"].get("origin_task_id"),
+                "index": body["input"].get("index"),
+                "image_url": url,
+                "image_urls": output.get("image_urls"),
+            }),
+        ))
+    }
+
+    /// Poll a task until it completes, returning its `data`
+    async fn wait_for_completion(
+        &self,
+        config: &MidjourneyConfig,
+        task_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let mut delay_ms = 5000u64;
+        let max_delay_ms = 10000u64;
+        let max_attempts = 200; // ~30 minutes max wait time, relax mode can queue long
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let request = self
+                .client
+                .get(config.url(&format!("task/{}", task_id)))
+                .header("x-api-key", &config.api_key);
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The task kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Midjourney poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+            if !response.status().is_success() {
+                return Err(api_error("Midjourney proxy", response).await);
+            }
+            let body: Value = response.json().await?;
+            let task = body.get("data").cloned().unwrap_or_default();
+
+            let status = task.get("status").and_then(|v| v.as_str()).unwrap_or_default();
+            match status.to_ascii_lowercase().as_str() {
+                "completed" => return Ok(task),
+                "failed" => {
+                    let message = task
+                        .pointer("/error/message")
+                        .and_then(|v| v.as_str())
+                        .filter(|m| !m.is_empty())
+                        .unwrap_or("no reason given");
+                    return Err(anyhow::anyhow!(
+                        "Midjourney task {} failed: {}",
+                        task_id,
+                        message
+                    ));
+                }
+                "pending" | "staged" => report_progress(progress, 0.0, "Queued at Midjourney"),
+                _ => {
+                    // The proxy reports the percentage Discord shows
+                    let percent = task
+                        .pointer("/output/progress")
+                        .and_then(|v| v.as_f64())
+                        .unwrap_or(0.0) as f32;
+                    report_progress(progress, percent, "Generating at Midjourney");
+                }
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 1000, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("Midjourney task in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Midjourney task timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for MidjourneyProvider {
+    fn name(&self) -> &str {
+        "midjourney"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(&[(MODEL, "Midjourney")]))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Midjourney"))?;
+
+        // The proxy has no account endpoint; look up a task that does not exist
+        let request = self
+            .client
+            .get(config.url(&format!("task/{}", PLACEHOLDER_TASK_ID)))
+            .header("x-api-key", &config.api_key);
+        check_authenticated("Midjourney proxy", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_task(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_task(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["imagine", "upscale", "variation"] },
+                "aspect_ratio": { "type": "string" },
+                "process_mode": { "type": "string", "enum": PROCESS_MODES },
+                "version": { "type": "string" },
+                "stylize": { "type": "integer", "minimum": 0, "maximum": 1000 },
+                "chaos": { "type": "integer", "minimum": 0, "maximum": 100 },
+                "task_id": { "type": "string" },
+                "index": { "type": "integer", "minimum": 1, "maximum": 4 }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "API key of your Midjourney proxy service"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Proxy service speaking the GoAPI task API instead of GoAPI"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_task_body_and_follow_up() {
+        let params = json!({ "aspect_ratio": "16:9", "version": "7", "stylize": 250 });
+        let body = task_body("a lighthouse --chaos 5", &params).unwrap();
+        assert_eq!(body["task_type"], "imagine");
+        assert_eq!(body["input"]["prompt"], "a lighthouse --chaos 5 --v 7 --stylize 250");
+        assert_eq!(body["input"]["process_mode"], "fast");
+        assert!(task_body(" ", &json!({})).is_err());
+        assert!(task_body("x", &json!({ "process_mode": "slow" })).is_err());
+
+        let upscale = json!({ "action": "upscale", "task_id": "t-1", "index": 2 });
+        let body = task_body("", &upscale).unwrap();
+        assert_eq!(body["input"], json!({ "origin_task_id": "t-1", "index": "2" }));
+        assert!(task_body("", &json!({ "action": "variation", "task_id": "t-1" })).is_err());
+
+        let result = GenerationResult::new(vec![], json!({ "task_id": "t-1" }));
+        let parent = Job {
+            id: "job-1".to_string(),
+            workflow_id: "wf".to_string(),
+            scene_id: None,
+            job_type: "generation".to_string(),
+            status: "completed".to_string(),
+            data: r#"{"provider":"midjourney","model":"midjourney","prompt":"a lighthouse"}"#
+                .to_string(),
+            result: Some(serde_json::to_string(&result).unwrap()),
+            error: None,
+            created_at: String::new(),
+            started_at: None,
+            completed_at: None,
+            scheduled_at: None,
+            app_version: None,
+            retry_of: None,
+            cost: None,
+            lane: "interactive".to_string(),
+            error_kind: None,
+        };
+        let input = follow_up_job(&parent, "variation", 3).unwrap();
+        assert_eq!(input.job_type, VARIATION_JOB_TYPE);
+        assert_eq!(input.data["prompt"], "a lighthouse");
+        assert_eq!(input.data["parameters"]["task_id"], "t-1");
+        assert!(follow_up_job(&parent, "zoom", 1).is_err());
+    }
+}
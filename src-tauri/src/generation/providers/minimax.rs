@@ -0,0 +1,399 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::rate_limit::RateLimitedError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, endpoint_url, extract_reference_image, send_with_retry, ApiStatusError,
+};
+
+/// MiniMax configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MiniMaxConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. `https://api.minimaxi.com/v1` in China
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.minimax.io/v1";
+
+const MODELS: &[(&str, &str)] = &[
+    ("MiniMax-Hailuo-02", "Hailuo 02"),
+    ("MiniMax-Hailuo-2.3", "Hailuo 2.3"),
+    ("T2V-01-Director", "T2V-01 Director"),
+    ("I2V-01-Director", "I2V-01 Director"),
+    ("I2V-01", "I2V-01"),
+];
+
+/// Models that only animate a first frame
+const IMAGE_TO_VIDEO_MODELS: &[&str] = &["I2V-01-Director", "I2V-01"];
+
+impl MiniMaxConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Request body of a generation
+///
+/// A reference image becomes the first frame. Only the Hailuo models take a duration
+/// and resolution; the 01 models always make six seconds at 720p.
+pub fn request_body(model: &str, prompt: &str, params: &Value) -> Result<Value> {
+    let values = ModelParams::new("minimax", model, params);
+    let mut body = serde_json::json!({
+        "model": model,
+        "prompt": prompt,
+        "prompt_optimizer": values.bool("prompt_optimizer"),
+    });
+
+    match extract_reference_image(params) {
+        Some((mime_type, data)) => {
+            body["first_frame_image"] =
+                serde_json::json!(format!("data:{};base64,{}", mime_type, data));
+        }
+        None if IMAGE_TO_VIDEO_MODELS.contains(&model) => {
+            return Err(GenerationError::invalid_param(
+                "reference_image",
+                format!("MiniMax {} needs a first frame image", model),
+            )
+            .into());
+        }
+        None => {}
+    }
+
+    if model.starts_with("MiniMax-Hailuo") {
+        let (duration, resolution) = (values.u64("duration"), values.str("resolution"));
+        if resolution == "1080P" && duration != 6 {
+            return Err(GenerationError::invalid_param(
+                "duration",
+                "MiniMax makes 1080P videos of 6 seconds only".to_string(),
+            )
+            .into());
+        }
+        body["duration"] = serde_json::json!(duration);
+        body["resolution"] = serde_json::json!(resolution);
+    }
+    Ok(body)
+}
+
+/// Error reported in a response's `base_resp`, which MiniMax sends with status 200
+pub fn base_resp_error(response: &Value) -> Option<anyhow::Error> {
+    let base = response.get("base_resp")?;
+    let code = base.get("status_code").and_then(|v| v.as_i64()).unwrap_or(0);
+    if code == 0 {
+        return None;
+    }
+    let message = format!(
+        "MiniMax API error ({}): {}",
+        code,
+        base.get("status_msg").and_then(|v| v.as_str()).unwrap_or_default()
+    );
+    // Sorted like the equivalent HTTP statuses so health checks and retries see them
+    Some(match code {
+        1002 | 1039 => anyhow::Error::new(RateLimitedError {
+            message,
+            retry_after: None,
+        }),
+        1004 | 2049 => anyhow::Error::new(ApiStatusError {
+            status: 401,
+            message,
+        }),
+        1008 => anyhow::Error::new(ApiStatusError {
+            status: 402,
+            message,
+        }),
+        _ => anyhow::anyhow!(message),
+    })
+}
+
+/// MiniMax provider (Hailuo text-to-video and image-to-video)
+pub struct MiniMaxProvider {
+    config: Option<MiniMaxConfig>,
+    client: reqwest::Client,
+}
+
+impl MiniMaxProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("minimax").client(),
+        }
+    }
+
+    pub fn with_config(config: MiniMaxConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    async fn get(
+        &self,
+        config: &MiniMaxConfig,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Value> {
+        let request = self
+            .client
+            .get(config.url(path))
+            .query(query)
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("MiniMax API", response).await);
+        }
+        let body: Value = response.json().await?;
+        match base_resp_error(&body) {
+            Some(error) => Err(error),
+            None => Ok(body),
+        }
+    }
+
+    /// Start a task, poll it, then retrieve and download the video file
+    async fn generate_task(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("MiniMax"))?;
+        if !MODELS.iter().any(|(id, _)| *id == request.model) {
+            return Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported MiniMax model: {}. Use 'MiniMax-Hailuo-02' or \
+                     'MiniMax-Hailuo-2.3'.",
+                    request.model
+                ),
+            )
+            .into());
+        }
+
+        let body = request_body(&request.model, &request.prompt, &request.parameters)?;
+        let submit = self
+            .client
+            .post(config.url("video_generation"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body);
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("MiniMax API", response).await);
+        }
+        let started: Value = response.json().await?;
+        if let Some(error) = base_resp_error(&started) {
+            return Err(error);
+        }
+        let task_id = started
+            .get("task_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No task ID in MiniMax response"))?;
+
+        report_progress(progress, 0.0, "MiniMax task queued");
+        let task = self.wait_for_completion(config, task_id, progress).await?;
+        // File IDs are strings, though older responses sent numbers
+        let file_id = task
+            .get("file_id")
+            .and_then(|v| match v {
+                Value::String(id) => Some(id.clone()),
+                Value::Number(id) => Some(id.to_string()),
+                _ => None,
+            })
+            .ok_or_else(|| anyhow::anyhow!("No file ID in MiniMax response"))?;
+
+        // Download URLs of finished videos expire after a few hours
+        let file = self.get(config, "files/retrieve", &[("file_id", &file_id)]).await?;
+        let url = file
+            .pointer("/file/download_url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No download URL in MiniMax response"))?;
+        report_progress(progress, 100.0, "Downloading video");
+        let response = send_with_retry(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(api_error("MiniMax download", response).await);
+        }
+        let video = response.bytes().await?;
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&video))
+            .with_mime_type("video/mp4");
+
+        let mut metadata = task;
+        if let Some(metadata) = metadata.as_object_mut() {
+            metadata.insert("file".to_string(), file["file"].clone());
+        }
+        Ok(GenerationResult::new(vec![artifact], metadata))
+    }
+
+    /// Poll a task until it succeeds, returning its status response
+    async fn wait_for_completion(
+        &self,
+        config: &MiniMaxConfig,
+        task_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let mut delay_ms = 5000u64;
+        let max_delay_ms = 10000u64;
+        let max_attempts = 200; // ~30 minutes max wait time
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let query = [("task_id", task_id)];
+            let task = match self.get(config, "query/video_generation", &query).await {
+                Ok(task) => task,
+                // The task kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("MiniMax poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            wakes = power::wakes();
+
+            match task.get("status").and_then(|v| v.as_str()) {
+                Some("Success") => return Ok(task),
+                Some("Fail") => {
+                    return Err(anyhow::anyhow!("MiniMax generation {} failed", task_id));
+                }
+                Some("Preparing" | "Queueing") => {
+                    report_progress(progress, 0.0, "Queued at MiniMax");
+                }
+                _ => report_progress(progress, 0.0, "Generating at MiniMax"),
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 1000, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("MiniMax generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "MiniMax generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for MiniMaxProvider {
+    fn name(&self) -> &str {
+        "minimax"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // The API has no model listing
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("MiniMax"))?;
+        self.get(config, "files/list", &[("purpose", "video_generation")])
+            .await
+            .map(|_| ())
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_task(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_task(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if !model.starts_with("MiniMax-Hailuo") {
+            return serde_json::json!({
+                "type": "object",
+                "properties": { "prompt_optimizer": { "type": "boolean" } }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "duration": { "type": "integer", "enum": [6, 10] },
+                "resolution": { "type": "string", "enum": ["512P", "768P", "1080P"] },
+                "prompt_optimizer": { "type": "boolean" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your MiniMax API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint or gateway instead of the global API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body_and_errors() {
+        let hailuo = "MiniMax-Hailuo-02";
+        let body = request_body(hailuo, "a cat", &json!({ "duration": 10 })).unwrap();
+        assert_eq!(body["duration"], 10);
+        assert_eq!(body["resolution"], "768P");
+        assert_eq!(body["prompt_optimizer"], true);
+
+        let too_long = json!({ "duration": 10, "resolution": "1080P" });
+        assert!(request_body(hailuo, "a cat", &too_long).is_err());
+        assert!(request_body("I2V-01", "a cat", &json!({})).is_err());
+        let director = request_body("T2V-01-Director", "a cat", &json!({})).unwrap();
+        assert!(director.get("duration").is_none());
+
+        let ok = json!({ "task_id": "1", "base_resp": { "status_code": 0, "status_msg": "ok" } });
+        assert!(base_resp_error(&ok).is_none());
+        let bad_key = json!({ "base_resp": { "status_code": 1004, "status_msg": "login fail" } });
+        let error = base_resp_error(&bad_key).unwrap();
+        assert_eq!(error.downcast_ref::<ApiStatusError>().unwrap().status, 401);
+    }
+}
@@ -0,0 +1,264 @@
+//! Offline provider returning canned outputs, for demos, development and integration
+//! tests without API keys or network access.
+//!
+//! Parameters drive its behaviour: `delay_ms` sets how long a generation takes (with
+//! progress and streamed words along the way), `fail` forces an error of the given kind
+//! and `failure_rate` fails that share of requests at random.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    GenerationService, GenerationStream, ModelInfo, ProgressSender, StreamChunk,
+};
+use crate::generation::errors::GenerationError;
+use crate::generation::rate_limit::RateLimitedError;
+
+/// Settings key for enabling the mock provider in release builds
+pub const MOCK_PROVIDER_SETTINGS_KEY: &str = "mock_provider";
+
+/// Steps a mock generation reports progress for
+const PROGRESS_STEPS: u64 = 5;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockProviderSettings {
+    /// Offer the provider outside debug builds
+    pub enabled: bool,
+}
+
+/// Register the provider in debug builds or when enabled, remove it otherwise
+pub fn apply(service: &mut GenerationService, settings: &MockProviderSettings) {
+    if settings.enabled || cfg!(debug_assertions) {
+        if service.get_provider("mock").is_none() {
+            service.register_provider(Box::new(MockProvider));
+        }
+    } else {
+        service.remove_provider("mock");
+    }
+}
+
+/// The mock provider; `mock-text` answers with text, `mock-image` with generated PNGs
+pub struct MockProvider;
+
+impl MockProvider {
+    async fn run(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let params = &request.parameters;
+        let delay = Duration::from_millis(params["delay_ms"].as_u64().unwrap_or(500));
+
+        for step in 1..=PROGRESS_STEPS {
+            tokio::time::sleep(delay / PROGRESS_STEPS as u32).await;
+            let percentage = (step * 100 / PROGRESS_STEPS) as f32;
+            report_progress(progress, percentage, format!("Mock step {}/{}", step, PROGRESS_STEPS));
+        }
+
+        simulated_failure(params)?;
+        self.output(request)
+    }
+
+    fn output(&self, request: &GenerationRequest) -> Result<GenerationResult> {
+        let params = &request.parameters;
+        let artifacts = match request.model.as_str() {
+            "mock-text" => vec![Artifact::text(reply(&request.prompt))],
+            "mock-image" => {
+                let n = params["n"].as_u64().unwrap_or(1).clamp(1, 4);
+                let seed = params["seed"].as_i64().filter(|seed| *seed >= 0).unwrap_or(0);
+                (0..n as i64)
+                    .map(|i| {
+                        let data = render_image(&request.prompt, seed + i, params)?;
+                        Ok(Artifact::from_data(data)
+                            .with_mime_type("image/png")
+                            .with_seed(Some(seed + i)))
+                    })
+                    .collect::<Result<_>>()?
+            }
+            _ => {
+                return Err(GenerationError::invalid_param(
+                    "model",
+                    format!(
+                        "Unsupported mock model: {}. Use 'mock-text' or 'mock-image'.",
+                        request.model
+                    ),
+                )
+                .into())
+            }
+        };
+
+        Ok(GenerationResult::new(
+            artifacts,
+            serde_json::json!({ "provider": "mock", "model": request.model }),
+        ))
+    }
+}
+
+/// Canned answer for text requests
+fn reply(prompt: &str) -> String {
+    format!("This is a mock response to: {}", prompt)
+}
+
+/// Error requested by `fail`, or drawn with `failure_rate`
+fn simulated_failure(params: &serde_json::Value) -> Result<()> {
+    let rate = params["failure_rate"].as_f64().unwrap_or(0.0);
+    let roll = (uuid::Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+    let kind = match params["fail"].as_str() {
+        Some(kind) => kind,
+        None if roll < rate => "provider_unavailable",
+        None => return Ok(()),
+    };
+
+    let message = format!("Simulated {} failure", kind);
+    let error = match kind {
+        // A real rate limit error so the service's retry path is exercised
+        "rate_limited" => anyhow::Error::new(RateLimitedError {
+            message,
+            retry_after: Some(Duration::from_secs(1)),
+        }),
+        "auth_failed" => GenerationError::AuthFailed { message }.into(),
+        "content_policy" => GenerationError::ContentPolicy { message }.into(),
+        "timeout" => GenerationError::Timeout { message }.into(),
+        "invalid_params" => GenerationError::invalid_param("fail", message).into(),
+        "provider_unavailable" => GenerationError::ProviderUnavailable { message }.into(),
+        _ => anyhow::anyhow!(message),
+    };
+    Err(error)
+}
+
+/// A gradient PNG whose colors follow from the prompt and seed, base64 encoded
+fn render_image(prompt: &str, seed: i64, params: &serde_json::Value) -> Result<String> {
+    let size = |key: &str| params[key].as_u64().unwrap_or(256).clamp(16, 1024) as u32;
+    let (width, height) = (size("width"), size("height"));
+    let hash = Sha256::digest(format!("{}:{}", prompt, seed).as_bytes());
+    let (from, to) = ([hash[0], hash[1], hash[2]], [hash[3], hash[4], hash[5]]);
+
+    let image = image::RgbImage::from_fn(width, height, |x, y| {
+        let t = (x + y) as f32 / (width + height) as f32;
+        let mix = |i: usize| (from[i] as f32 * (1.0 - t) + to[i] as f32 * t) as u8;
+        image::Rgb([mix(0), mix(1), mix(2)])
+    });
+    let mut bytes = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+    Ok(general_purpose::STANDARD.encode(bytes))
+}
+
+#[async_trait]
+impl GenerationProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.run(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.run(&request, Some(&progress)).await
+    }
+
+    /// Text is streamed word by word over the delay; images only send the result
+    fn generate_stream(&self, request: GenerationRequest) -> GenerationStream<'_> {
+        if request.model != "mock-text" {
+            return Box::pin(futures_util::stream::once(async move {
+                self.run(&request, None).await.map(|result| StreamChunk::Done { result })
+            }));
+        }
+
+        let text = reply(&request.prompt);
+        let words: Vec<String> = text.split_inclusive(' ').map(str::to_string).collect();
+        let delay_ms = request.parameters["delay_ms"].as_u64().unwrap_or(500);
+        let pause = Duration::from_millis(delay_ms / words.len().max(1) as u64);
+
+        let deltas = futures_util::stream::iter(words).then(move |delta| async move {
+            tokio::time::sleep(pause).await;
+            Ok(StreamChunk::Text { delta })
+        });
+        let done = futures_util::stream::once(async move {
+            simulated_failure(&request.parameters)?;
+            self.output(&request).map(|result| StreamChunk::Done { result })
+        });
+        Box::pin(deltas.chain(done))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(&[
+            ("mock-text", "Mock text"),
+            ("mock-image", "Mock image"),
+        ]))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({ "type": "object", "properties": {} })
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        let kinds = [
+            "auth_failed",
+            "rate_limited",
+            "content_policy",
+            "timeout",
+            "provider_unavailable",
+            "invalid_params",
+            "other",
+        ];
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "delay_ms": { "type": "integer", "minimum": 0, "maximum": 600000 },
+                "fail": { "type": "string", "enum": kinds },
+                "failure_rate": { "type": "number", "minimum": 0, "maximum": 1 },
+                "n": { "type": "integer", "minimum": 1, "maximum": 4 },
+                "width": { "type": "integer", "minimum": 16, "maximum": 1024 },
+                "height": { "type": "integer", "minimum": 16, "maximum": 1024 },
+                "seed": { "type": "integer", "minimum": -1 }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_outputs_and_failures() {
+        let request = |model: &str, parameters| GenerationRequest {
+            prompt: "a red fox".to_string(),
+            model: model.to_string(),
+            parameters,
+        };
+
+        let text = MockProvider.output(&request("mock-text", serde_json::json!({}))).unwrap();
+        assert_eq!(text.output_data(), Some("This is a mock response to: a red fox"));
+
+        let params = serde_json::json!({ "n": 2, "width": 32, "height": 32, "seed": 7 });
+        let images = MockProvider.output(&request("mock-image", params)).unwrap();
+        assert_eq!(images.artifacts.len(), 2);
+        assert_eq!(images.artifacts[1].seed, Some(8));
+        assert_ne!(images.artifacts[0].data, images.artifacts[1].data);
+
+        let failed = simulated_failure(&serde_json::json!({ "fail": "timeout" })).unwrap_err();
+        assert_eq!(GenerationError::classify(&failed).kind(), "timeout");
+        assert!(simulated_failure(&serde_json::json!({ "failure_rate": 0.0 })).is_ok());
+    }
+}
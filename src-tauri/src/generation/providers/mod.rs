@@ -1,9 +1,28 @@
 pub mod anthropic;
+pub mod elevenlabs;
+pub mod external;
+pub mod fal;
+pub mod flux;
 pub mod google;
 pub mod grok;
+pub mod groq;
+pub mod leonardo;
+pub mod midjourney;
+pub mod minimax;
 pub mod openai;
+pub mod openai_compatible;
+pub mod perplexity;
+pub mod pika;
+pub mod recraft;
+pub mod runway;
+pub mod stability;
+pub mod vertex;
+pub mod volcengine;
 
 // Local generation providers
 pub mod a1111;
 pub mod comfyui;
 pub mod invokeai;
+
+// Offline provider for demos, development and tests
+pub mod mock;
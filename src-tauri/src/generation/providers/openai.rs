@@ -1,17 +1,111 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
-
-use super::super::{GenerationProvider, GenerationRequest, GenerationResult};
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, endpoint_url, image_artifacts, send_rebuilt_with_retry, send_with_retry,
+};
 
 /// OpenAI provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIConfig {
     pub api_key: String,
     pub organization: Option<String>,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+impl OpenAIConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Speech synthesis models
+pub const SPEECH_MODELS: &[&str] = &["gpt-4o-mini-tts", "tts-1", "tts-1-hd"];
+
+/// Transcription models
+pub const TRANSCRIPTION_MODELS: &[&str] =
+    &["whisper-1", "gpt-4o-transcribe", "gpt-4o-mini-transcribe"];
+
+const VOICES: &[&str] = &[
+    "alloy", "ash", "ballad", "coral", "echo", "fable", "nova", "onyx", "sage", "shimmer",
+];
+
+const SPEECH_FORMATS: &[&str] = &["mp3", "wav", "opus", "aac", "flac"];
+
+/// MIME type of a speech `response_format`
+fn speech_mime_type(format: &str) -> &'static str {
+    match format {
+        "wav" => "audio/wav",
+        "opus" => "audio/ogg",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        _ => "audio/mpeg",
+    }
 }
 
-/// OpenAI provider (gpt-image-1 for images, Sora for video)
+/// Body of a speech request, speaking `text`
+pub fn speech_body(
+    model: &str,
+    text: &str,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let values = ModelParams::new("openai", model, params);
+    let voice = values.str("voice");
+    if !VOICES.contains(&voice) {
+        return Err(GenerationError::invalid_param(
+            "voice",
+            format!("OpenAI has no voice '{}'", voice),
+        )
+        .into());
+    }
+    let format = values.str("response_format");
+    if !SPEECH_FORMATS.contains(&format) {
+        return Err(GenerationError::invalid_param(
+            "response_format",
+            format!("Speech format must be one of {}", SPEECH_FORMATS.join(", ")),
+        )
+        .into());
+    }
+
+    let mut body = serde_json::json!({
+        "model": model,
+        "input": text,
+        "voice": voice,
+        "response_format": format,
+        "speed": values.f64("speed"),
+    });
+    // Only gpt-4o-mini-tts takes directions on tone and delivery
+    if let Some(instructions) = params.get("instructions").and_then(|v| v.as_str()) {
+        if model == "gpt-4o-mini-tts" {
+            body["instructions"] = serde_json::json!(instructions);
+        }
+    }
+    Ok(body)
+}
+
+/// OpenAI provider (gpt-image-1 for images, Sora for video, speech and transcription)
 pub struct OpenAIProvider {
     config: Option<OpenAIConfig>,
     client: reqwest::Client,
@@ -21,14 +115,14 @@ impl OpenAIProvider {
     pub fn new() -> Self {
         Self {
             config: None,
-            client: reqwest::Client::new(),
+            client: ProviderTimeouts::for_provider("openai").client(),
         }
     }
 
     pub fn with_config(config: OpenAIConfig) -> Self {
         Self {
+            client: config.timeouts.client_with_headers(&config.headers),
             config: Some(config),
-            client: reqwest::Client::new(),
         }
     }
 
@@ -41,21 +135,17 @@ impl OpenAIProvider {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("OpenAI API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
+
+        let values = ModelParams::new("openai", "gpt-image-1", params);
 
         // Size options: 1024x1024, 1536x1024 (landscape), 1024x1536 (portrait), auto
-        let size = params
-            .get("size")
-            .and_then(|v| v.as_str())
-            .unwrap_or("auto");
+        let size = values.str("size");
 
         // Quality options: low, medium, high
-        let quality = params
-            .get("quality")
-            .and_then(|v| v.as_str())
-            .unwrap_or("high");
+        let quality = values.str("quality");
 
-        let n = params.get("n").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+        let n = values.u64("n") as usize;
 
         let request_body = serde_json::json!({
             "model": "gpt-image-1",
@@ -67,7 +157,7 @@ impl OpenAIProvider {
 
         let mut request = self
             .client
-            .post("https://api.openai.com/v1/images/generations")
+            .post(config.url("images/generations"))
             .header("Authorization", format!("Bearer {}", config.api_key))
             .header("Content-Type", "application/json")
             .json(&request_body);
@@ -76,41 +166,17 @@ impl OpenAIProvider {
             request = request.header("OpenAI-Organization", org);
         }
 
-        let response = request.send().await?;
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "OpenAI API error ({}): {}",
-                status,
-                error_text
-            ));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
 
         // gpt-image-1 returns base64 data by default, check for both url and b64_json
-        let output_url = response_data
-            .get("data")
-            .and_then(|data| data.get(0))
-            .and_then(|item| item.get("url"))
-            .and_then(|url| url.as_str())
-            .map(|s| s.to_string());
-
-        let output_data = response_data
-            .get("data")
-            .and_then(|data| data.get(0))
-            .and_then(|item| item.get("b64_json"))
-            .and_then(|b64| b64.as_str())
-            .map(|s| s.to_string());
-
-        Ok(GenerationResult {
-            output_url,
-            output_data,
-            file_path: None,
-            metadata: response_data,
-        })
+        let artifacts = image_artifacts(&response_data);
+
+        Ok(GenerationResult::new(artifacts, response_data))
     }
 
     /// Generate video using Sora
@@ -118,26 +184,23 @@ impl OpenAIProvider {
         &self,
         prompt: &str,
         params: &serde_json::Value,
+        progress: Option<&ProgressSender>,
     ) -> Result<GenerationResult> {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("OpenAI API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
 
         // Duration in seconds (typically 4, 5, 8, 10, or 12)
-        let duration = params.get("duration").and_then(|v| v.as_u64()).unwrap_or(5);
+        let values = ModelParams::new("openai", "sora-2", params);
+
+        let duration = values.u64("duration");
 
         // Resolution: 720p or 1080p
-        let resolution = params
-            .get("resolution")
-            .and_then(|v| v.as_str())
-            .unwrap_or("1080p");
+        let resolution = values.str("resolution");
 
         // Aspect ratio: 16:9 or 9:16
-        let aspect_ratio = params
-            .get("aspect_ratio")
-            .and_then(|v| v.as_str())
-            .unwrap_or("16:9");
+        let aspect_ratio = values.str("aspect_ratio");
 
         let request_body = serde_json::json!({
             "model": "sora-2",
@@ -149,7 +212,7 @@ impl OpenAIProvider {
 
         let mut request = self
             .client
-            .post("https://api.openai.com/v1/videos")
+            .post(config.url("videos"))
             .header("Authorization", format!("Bearer {}", config.api_key))
             .header("Content-Type", "application/json")
             .json(&request_body);
@@ -158,16 +221,9 @@ impl OpenAIProvider {
             request = request.header("OpenAI-Organization", org);
         }
 
-        let response = request.send().await?;
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "OpenAI Sora API error ({}): {}",
-                status,
-                error_text
-            ));
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI Sora API", response).await);
         }
 
         let response_data: serde_json::Value = response.json().await?;
@@ -180,44 +236,54 @@ impl OpenAIProvider {
             .ok_or_else(|| anyhow::anyhow!("No operation ID in Sora response"))?;
 
         // Poll for completion with exponential backoff
-        let result = self.poll_video_generation(operation_id).await?;
+        report_progress(progress, 0.0, "Sora job submitted");
+        let result = self.poll_video_generation(operation_id, progress).await?;
 
         Ok(result)
     }
 
     /// Poll for video generation completion
-    async fn poll_video_generation(&self, operation_id: &str) -> Result<GenerationResult> {
+    async fn poll_video_generation(
+        &self,
+        operation_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
         let config = self
             .config
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("OpenAI API key not configured"))?;
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
 
         let mut delay_ms = 5000u64; // Start with 5 seconds
         let max_delay_ms = 60000u64; // Max 60 seconds between polls
         let max_attempts = 60; // ~30 minutes max wait time
+        let mut wakes = power::wakes();
 
         for attempt in 0..max_attempts {
             tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
 
             let mut request = self
                 .client
-                .get(format!("https://api.openai.com/v1/videos/{}", operation_id))
+                .get(config.url(&format!("videos/{}", operation_id)))
                 .header("Authorization", format!("Bearer {}", config.api_key));
 
             if let Some(org) = &config.organization {
                 request = request.header("OpenAI-Organization", org);
             }
 
-            let response = request.send().await?;
-            let status = response.status();
-
-            if !status.is_success() {
-                let error_text = response.text().await?;
-                return Err(anyhow::anyhow!(
-                    "OpenAI Sora poll error ({}): {}",
-                    status,
-                    error_text
-                ));
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The operation kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Sora poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+            if !response.status().is_success() {
+                return Err(api_error("OpenAI Sora poll", response).await);
             }
 
             let response_data: serde_json::Value = response.json().await?;
@@ -226,21 +292,24 @@ impl OpenAIProvider {
                 .and_then(|s| s.as_str())
                 .unwrap_or("unknown");
 
+            let percentage = response_data
+                .get("progress")
+                .and_then(|p| p.as_f64())
+                .unwrap_or(0.0) as f32;
+            report_progress(progress, percentage, format!("Sora status: {}", gen_status));
+
             match gen_status {
                 "completed" | "succeeded" => {
-                    let output_url = response_data
+                    let artifacts = response_data
                         .get("output")
                         .or_else(|| response_data.get("video_url"))
                         .or_else(|| response_data.get("result").and_then(|r| r.get("url")))
                         .and_then(|url| url.as_str())
-                        .map(|s| s.to_string());
-
-                    return Ok(GenerationResult {
-                        output_url,
-                        output_data: None,
-                        file_path: None,
-                        metadata: response_data,
-                    });
+                        .map(|url| Artifact::from_url(url.to_string()).with_mime_type("video/mp4"))
+                        .into_iter()
+                        .collect();
+
+                    return Ok(GenerationResult::new(artifacts, response_data));
                 }
                 "failed" | "error" => {
                     let error_msg = response_data
@@ -255,7 +324,7 @@ impl OpenAIProvider {
                 }
                 _ => {
                     // Unknown status, log and continue
-                    eprintln!("Unknown Sora status: {} (attempt {})", gen_status, attempt);
+                    log_warn!("Unknown Sora status: {} (attempt {})", gen_status, attempt);
                 }
             }
         }
@@ -265,19 +334,129 @@ impl OpenAIProvider {
             max_attempts
         ))
     }
-}
 
-#[async_trait]
-impl GenerationProvider for OpenAIProvider {
-    fn name(&self) -> &str {
-        "openai"
+    /// Speak the prompt with a TTS model
+    async fn generate_speech(
+        &self,
+        model: &str,
+        text: &str,
+        params: &serde_json::Value,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
+        let body = speech_body(model, text, params)?;
+
+        let mut request = self
+            .client
+            .post(config.url("audio/speech"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .json(&body);
+        if let Some(org) = &config.organization {
+            request = request.header("OpenAI-Organization", org);
+        }
+
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI speech API", response).await);
+        }
+        let audio = response.bytes().await?;
+        let format = body["response_format"].as_str().unwrap_or("mp3");
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&audio))
+            .with_mime_type(speech_mime_type(format));
+
+        Ok(GenerationResult::new(
+            vec![artifact],
+            serde_json::json!({
+                "voice": body["voice"],
+                "response_format": format,
+                "characters": text.chars().count(),
+            }),
+        ))
     }
 
-    async fn is_available(&self) -> bool {
-        self.config.is_some()
+    /// Transcribe the audio file at `audio_path`, with the prompt as a hint on spelling
+    /// and context
+    async fn transcribe(
+        &self,
+        model: &str,
+        prompt: &str,
+        params: &serde_json::Value,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
+        let values = ModelParams::new("openai", model, params);
+        let path = params
+            .get("audio_path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                GenerationError::invalid_param(
+                    "audio_path",
+                    "Transcription needs an audio file".to_string(),
+                )
+            })?;
+        let audio = tokio::fs::read(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not read audio file {}: {}", path, e))?;
+        let file_name = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "audio.mp3".to_string());
+
+        // The gpt-4o models only return plain text or JSON
+        let format = values.str("response_format").to_string();
+        let language = params.get("language").and_then(|v| v.as_str());
+
+        // The multipart body cannot be cloned, so every attempt builds its own
+        let response = send_rebuilt_with_retry(|| {
+            let file = reqwest::multipart::Part::bytes(audio.clone()).file_name(file_name.clone());
+            let mut form = reqwest::multipart::Form::new()
+                .text("model", model.to_string())
+                .text("response_format", format.clone())
+                .part("file", file);
+            if !prompt.trim().is_empty() {
+                form = form.text("prompt", prompt.to_string());
+            }
+            if let Some(language) = language {
+                form = form.text("language", language.to_string());
+            }
+
+            let mut request = self
+                .client
+                .post(config.url("audio/transcriptions"))
+                .header("Authorization", format!("Bearer {}", config.api_key))
+                .multipart(form);
+            if let Some(org) = &config.organization {
+                request = request.header("OpenAI-Organization", org);
+            }
+            request
+        })
+        .await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI transcription API", response).await);
+        }
+        let transcript = if format == "json" {
+            let body: serde_json::Value = response.json().await?;
+            body.get("text").and_then(|v| v.as_str()).unwrap_or_default().to_string()
+        } else {
+            response.text().await?
+        };
+
+        Ok(GenerationResult::new(
+            vec![Artifact::text(transcript)],
+            serde_json::json!({ "audio_path": path, "response_format": format }),
+        ))
     }
 
-    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+    /// Route a request to the image, video or audio endpoint based on model name
+    async fn dispatch(
+        &self,
+        request: GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
         match request.model.as_str() {
             // Image generation models
             "gpt-image-1" | "gpt-image-1-mini" => {
@@ -285,16 +464,126 @@ impl GenerationProvider for OpenAIProvider {
             }
             // Video generation models
             "sora-2" | "sora-2-pro" | "sora" => {
-                self.generate_video(&request.prompt, &request.parameters).await
+                self.generate_video(&request.prompt, &request.parameters, progress).await
+            }
+            model if SPEECH_MODELS.contains(&model) => {
+                self.generate_speech(model, &request.prompt, &request.parameters).await
+            }
+            model if TRANSCRIPTION_MODELS.contains(&model) => {
+                self.transcribe(model, &request.prompt, &request.parameters).await
             }
             // Legacy support - redirect to new model
             "dall-e-3" | "dall-e-2" => {
-                eprintln!("Warning: DALL-E models are deprecated, using gpt-image-1 instead");
+                log_warn!("Warning: DALL-E models are deprecated, using gpt-image-1 instead");
                 self.generate_image(&request.prompt, &request.parameters).await
             }
-            _ => Err(anyhow::anyhow!("Unsupported OpenAI model: {}. Use 'gpt-image-1' for images or 'sora-2' for videos.", request.model)),
+            _ => Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported OpenAI model: {}. Use 'gpt-image-1' for images, 'sora-2' for \
+                     videos, 'gpt-4o-mini-tts' for speech or 'whisper-1' for transcripts.",
+                    request.model
+                ),
+            )
+            .into()),
         }
     }
+}
+
+#[async_trait]
+impl GenerationProvider for OpenAIProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("OpenAI"))?;
+
+        let mut request = self
+            .client
+            .get(config.url("models"))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        if let Some(org) = &config.organization {
+            request = request.header("OpenAI-Organization", org);
+        }
+
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(ModelInfo::from_list(&body["data"], "id", None))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.dispatch(request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.dispatch(request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if SPEECH_MODELS.contains(&model) {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "voice": { "type": "string", "enum": VOICES },
+                    "response_format": { "type": "string", "enum": SPEECH_FORMATS },
+                    "speed": { "type": "number", "minimum": 0.25, "maximum": 4.0 },
+                    "instructions": { "type": "string" }
+                }
+            });
+        }
+        if TRANSCRIPTION_MODELS.contains(&model) {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "audio_path": { "type": "string" },
+                    "language": { "type": "string" },
+                    "response_format": { "type": "string", "enum": ["text", "json", "srt", "vtt"] }
+                },
+                "required": ["audio_path"]
+            });
+        }
+        if model.starts_with("sora") {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "duration": { "type": "integer", "enum": [4, 5, 8, 10, 12] },
+                    "resolution": { "type": "string", "enum": ["720p", "1080p"] },
+                    "aspect_ratio": { "type": "string", "enum": ["16:9", "9:16"] }
+                }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "size": {
+                    "type": "string",
+                    "enum": ["auto", "1024x1024", "1536x1024", "1024x1536"]
+                },
+                "quality": { "type": "string", "enum": ["auto", "low", "medium", "high"] },
+                "n": { "type": "integer", "minimum": 1, "maximum": 10 }
+            }
+        })
+    }
 
     fn config_schema(&self) -> serde_json::Value {
         serde_json::json!({
@@ -309,9 +598,34 @@ impl GenerationProvider for OpenAIProvider {
                     "type": "string",
                     "title": "Organization ID (optional)",
                     "description": "Your OpenAI organization ID"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint, Azure deployment or gateway replacing the public API"
                 }
             },
             "required": ["api_key"]
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_speech_body() {
+        let params = json!({ "voice": "coral", "instructions": "Whisper" });
+        let body = speech_body("gpt-4o-mini-tts", "Hello", &params).unwrap();
+        assert_eq!(body["voice"], "coral");
+        assert_eq!(body["response_format"], "mp3");
+        assert_eq!(body["instructions"], "Whisper");
+
+        let body = speech_body("tts-1", "Hello", &params).unwrap();
+        assert!(body.get("instructions").is_none());
+
+        assert!(speech_body("tts-1", "Hello", &json!({ "voice": "bob" })).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, image_artifacts, send_with_retry};
+
+/// Settings key for the endpoint configuration; the API key lives in the keyring
+pub const OPENAI_COMPATIBLE_SETTINGS_KEY: &str = "openai_compatible_settings";
+
+/// Configuration of an OpenAI-compatible endpoint (vLLM, LiteLLM, OpenRouter, gateways)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAICompatibleConfig {
+    /// API root including the version, e.g. `http://localhost:8000/v1`
+    pub base_url: String,
+    /// Sent as a bearer token; self-hosted servers often need none
+    pub api_key: Option<String>,
+    /// Model used when a request does not name one
+    pub default_model: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request, e.g. OpenRouter's `X-Title`
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Provider for any server speaking the OpenAI chat completions and images API
+pub struct OpenAICompatibleProvider {
+    config: Option<OpenAICompatibleConfig>,
+    client: reqwest::Client,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("openai_compatible").client(),
+        }
+    }
+
+    pub fn with_config(config: OpenAICompatibleConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    fn config(&self) -> Result<&OpenAICompatibleConfig> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("OpenAI-compatible endpoint not configured"))
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder> {
+        let config = self.config()?;
+        let url = format!("{}/{}", config.base_url.trim_end_matches('/'), path);
+        let mut request = self.client.request(method, url);
+        if let Some(api_key) = config.api_key.as_deref().filter(|k| !k.is_empty()) {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        Ok(request)
+    }
+
+    /// Request model, falling back to the configured default
+    fn model<'a>(&'a self, request: &'a GenerationRequest) -> Result<&'a str> {
+        if !request.model.is_empty() {
+            return Ok(&request.model);
+        }
+        self.config()?
+            .default_model
+            .as_deref()
+            .filter(|m| !m.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("No model given and no default model configured"))
+    }
+
+    /// Text generation through `/chat/completions`
+    async fn generate_text(&self, request: &GenerationRequest) -> Result<GenerationResult> {
+        let params = &request.parameters;
+        let mut body = serde_json::json!({
+            "model": self.model(request)?,
+            "messages": [{ "role": "user", "content": request.prompt }],
+        });
+        for key in ["temperature", "max_tokens", "top_p"] {
+            if let Some(value) = params.get(key) {
+                body[key] = value.clone();
+            }
+        }
+
+        let request = self
+            .request(reqwest::Method::POST, "chat/completions")?
+            .json(&body);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI-compatible API", response).await);
+        }
+        let response_data: serde_json::Value = response.json().await?;
+
+        let text = response_data
+            .pointer("/choices/0/message/content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        Ok(GenerationResult::new(
+            vec![Artifact::text(text)],
+            serde_json::json!({
+                "id": response_data.get("id"),
+                "model": response_data.get("model"),
+                "finish_reason": response_data.pointer("/choices/0/finish_reason"),
+                "usage": response_data.get("usage"),
+            }),
+        ))
+    }
+
+    /// Image generation through `/images/generations`
+    async fn generate_image(&self, request: &GenerationRequest) -> Result<GenerationResult> {
+        let params = &request.parameters;
+        let mut body = serde_json::json!({
+            "model": self.model(request)?,
+            "prompt": request.prompt,
+            "n": 1,
+        });
+        for key in ["n", "size", "quality", "style", "response_format"] {
+            if let Some(value) = params.get(key) {
+                body[key] = value.clone();
+            }
+        }
+
+        let request = self
+            .request(reqwest::Method::POST, "images/generations")?
+            .json(&body);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI-compatible API", response).await);
+        }
+        let response_data: serde_json::Value = response.json().await?;
+
+        let artifacts = image_artifacts(&response_data);
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("OpenAI-compatible API returned no image"));
+        }
+
+        Ok(GenerationResult::new(artifacts, response_data))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for OpenAICompatibleProvider {
+    fn name(&self) -> &str {
+        "openai_compatible"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = send_with_retry(self.request(reqwest::Method::GET, "models")?).await?;
+        if !response.status().is_success() {
+            return Err(api_error("OpenAI-compatible API", response).await);
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(ModelInfo::from_list(&body["data"], "id", None))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // The model listing is a request to the authenticated API
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        // Chat is the default; image endpoints are opt-in since many servers lack them
+        match request.parameters.get("mode").and_then(|v| v.as_str()) {
+            Some("image") => self.generate_image(&request).await,
+            Some("chat") | None => self.generate_text(&request).await,
+            Some(other) => Err(anyhow::anyhow!(
+                "Unsupported mode for OpenAI-compatible provider: {}",
+                other
+            )),
+        }
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL",
+                    "description": "API root including the version, e.g. http://localhost:8000/v1"
+                },
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key (optional)",
+                    "description": "Bearer token, if the server requires one"
+                },
+                "default_model": {
+                    "type": "string",
+                    "title": "Default Model",
+                    "description": "Model used when a request does not name one"
+                },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "title": "Extra Headers (optional)",
+                    "description": "Sent with every request, e.g. X-Title for OpenRouter"
+                }
+            },
+            "required": ["base_url"]
+        })
+    }
+}
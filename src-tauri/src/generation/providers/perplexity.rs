@@ -0,0 +1,310 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, check_connection, endpoint_url, send_with_retry};
+
+/// Perplexity configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerplexityConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.perplexity.ai";
+
+const MODELS: &[(&str, &str)] = &[
+    ("sonar", "Sonar"),
+    ("sonar-pro", "Sonar Pro"),
+    ("sonar-reasoning-pro", "Sonar Reasoning Pro"),
+    ("sonar-deep-research", "Sonar Deep Research"),
+];
+
+const SEARCH_CONTEXT_SIZES: &[&str] = &["low", "medium", "high"];
+const RECENCY_FILTERS: &[&str] = &["hour", "day", "week", "month", "year"];
+
+/// Most domains a search can be limited to
+const MAX_SEARCH_DOMAINS: usize = 20;
+
+/// A web page an answer is grounded in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Citation {
+    pub url: String,
+    pub title: Option<String>,
+    /// Publication date, when the page has one
+    pub date: Option<String>,
+}
+
+impl PerplexityConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Body of a chat completion with web search
+///
+/// `system_prompt` steers the answer, e.g. to return a rewritten prompt only;
+/// `search_domains` limits the search to those sites, or excludes ones starting with `-`.
+pub fn request_body(model: &str, prompt: &str, params: &Value) -> Result<Value> {
+    if !MODELS.iter().any(|(id, _)| *id == model) {
+        return Err(GenerationError::invalid_param(
+            "model",
+            format!("Unsupported Perplexity model: {}. Use 'sonar' or 'sonar-pro'.", model),
+        )
+        .into());
+    }
+    let values = ModelParams::new("perplexity", model, params);
+    let context_size = values.str("search_context_size");
+    if !SEARCH_CONTEXT_SIZES.contains(&context_size) {
+        return Err(GenerationError::invalid_param(
+            "search_context_size",
+            format!("Search context size must be one of {}", SEARCH_CONTEXT_SIZES.join(", ")),
+        )
+        .into());
+    }
+
+    let mut messages = Vec::new();
+    if let Some(system) = params.get("system_prompt").and_then(|v| v.as_str()) {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+
+    let mut body = serde_json::json!({
+        "model": model,
+        "messages": messages,
+        "max_tokens": values.u64("max_tokens"),
+        "temperature": values.f64("temperature"),
+        "web_search_options": { "search_context_size": context_size },
+    });
+    if let Some(recency) = params.get("search_recency").and_then(|v| v.as_str()) {
+        if !RECENCY_FILTERS.contains(&recency) {
+            return Err(GenerationError::invalid_param(
+                "search_recency",
+                format!("Search recency must be one of {}", RECENCY_FILTERS.join(", ")),
+            )
+            .into());
+        }
+        body["search_recency_filter"] = serde_json::json!(recency);
+    }
+    if let Some(domains) = params.get("search_domains").and_then(|v| v.as_array()) {
+        if domains.len() > MAX_SEARCH_DOMAINS {
+            return Err(GenerationError::invalid_param(
+                "search_domains",
+                format!("Searches take at most {} domains", MAX_SEARCH_DOMAINS),
+            )
+            .into());
+        }
+        body["search_domain_filter"] = serde_json::json!(domains);
+    }
+    Ok(body)
+}
+
+/// Pages an answer cites, in the order of its `[n]` markers
+///
+/// `search_results` has titles and dates; older responses only list URLs in `citations`.
+pub fn citations(response: &Value) -> Vec<Citation> {
+    let string = |value: &Value, key: &str| {
+        value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+    if let Some(results) = response.get("search_results").and_then(|v| v.as_array()) {
+        return results
+            .iter()
+            .filter_map(|result| {
+                Some(Citation {
+                    url: string(result, "url")?,
+                    title: string(result, "title"),
+                    date: string(result, "date"),
+                })
+            })
+            .collect();
+    }
+    response
+        .get("citations")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|url| {
+            Some(Citation {
+                url: url.as_str()?.to_string(),
+                title: None,
+                date: None,
+            })
+        })
+        .collect()
+}
+
+/// Answer text without the `<think>` section reasoning models start with
+pub fn answer_text(content: &str) -> &str {
+    match content.split_once("</think>") {
+        Some((_, answer)) => answer.trim_start(),
+        None => content,
+    }
+}
+
+/// Perplexity provider (Sonar answers grounded in live web search, e.g. prompt research)
+pub struct PerplexityProvider {
+    config: Option<PerplexityConfig>,
+    client: reqwest::Client,
+}
+
+impl PerplexityProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("perplexity").client(),
+        }
+    }
+
+    pub fn with_config(config: PerplexityConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for PerplexityProvider {
+    fn name(&self) -> &str {
+        "perplexity"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Perplexity"))?;
+        let request = self
+            .client
+            .get(config.url("async/chat/completions"))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        check_connection("Perplexity API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Perplexity"))?;
+        let body = request_body(&request.model, &request.prompt, &request.parameters)?;
+
+        let chat = self
+            .client
+            .post(config.url("chat/completions"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .json(&body);
+        let response = send_with_retry(chat).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Perplexity API", response).await);
+        }
+        let response_data: Value = response.json().await?;
+
+        let content = response_data
+            .pointer("/choices/0/message/content")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+
+        Ok(GenerationResult::new(
+            vec![Artifact::text(answer_text(content).to_string())],
+            serde_json::json!({
+                "id": response_data.get("id"),
+                "model": response_data.get("model"),
+                "finish_reason": response_data.pointer("/choices/0/finish_reason"),
+                "usage": response_data.get("usage"),
+                "citations": citations(&response_data),
+            }),
+        ))
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "system_prompt": { "type": "string" },
+                "max_tokens": { "type": "integer", "minimum": 1 },
+                "temperature": { "type": "number", "minimum": 0, "maximum": 2 },
+                "search_context_size": { "type": "string", "enum": SEARCH_CONTEXT_SIZES },
+                "search_recency": { "type": "string", "enum": RECENCY_FILTERS },
+                "search_domains": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "maxItems": MAX_SEARCH_DOMAINS
+                }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Perplexity API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body_and_citations() {
+        let params = json!({ "system_prompt": "Be brief", "search_recency": "week" });
+        let body = request_body("sonar", "Trending art styles", &params).unwrap();
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Trending art styles");
+        assert_eq!(body["web_search_options"]["search_context_size"], "low");
+        assert_eq!(body["search_recency_filter"], "week");
+        assert!(request_body("sonar", "x", &json!({ "search_recency": "decade" })).is_err());
+        assert!(request_body("llama-3", "x", &json!({})).is_err());
+
+        let response = json!({
+            "citations": ["https://a.example"],
+            "search_results": [{ "title": "A", "url": "https://a.example", "date": null }]
+        });
+        let found = citations(&response);
+        assert_eq!(found[0].title.as_deref(), Some("A"));
+        let urls_only = citations(&json!({ "citations": ["https://b.example"] }));
+        assert_eq!(urls_only[0].url, "https://b.example");
+
+        assert_eq!(answer_text("<think>search first</think>\n\nUse rim light"), "Use rim light");
+        assert_eq!(answer_text("Use rim light"), "Use rim light");
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    GenerationProvider, GenerationRequest, GenerationResult, ModelInfo, ProgressSender,
+};
+use super::fal::{FalConfig, FalProvider};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::extract_reference_image;
+
+/// Pika configuration
+///
+/// Pika's developer API is served through fal.ai's queue, so the key is a fal.ai key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PikaConfig {
+    pub api_key: String,
+    /// Queue root replacing fal.ai's public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+const MODELS: &[(&str, &str)] = &[
+    ("pika-2.2", "Pika 2.2"),
+    ("pika-2-turbo", "Pika 2 Turbo"),
+    ("pikadditions", "Pikadditions (add to a video)"),
+    ("pikaswaps", "Pikaswaps (replace part of a video)"),
+];
+
+/// Models that modify an existing video given as `video_url` or `video_path`
+pub const MODIFY_MODELS: &[&str] = &["pikadditions", "pikaswaps"];
+
+const ASPECT_RATIOS: &[&str] = &["16:9", "9:16", "1:1", "4:5", "5:4", "3:2", "2:3"];
+
+/// fal.ai endpoint serving `model`, which depends on whether an image is animated
+fn endpoint(model: &str, has_image: bool) -> Option<&'static str> {
+    let endpoint = match (model, has_image) {
+        ("pika-2.2", false) => "fal-ai/pika/v2.2/text-to-video",
+        ("pika-2.2", true) => "fal-ai/pika/v2.2/image-to-video",
+        ("pika-2-turbo", false) => "fal-ai/pika/v2/turbo/text-to-video",
+        ("pika-2-turbo", true) => "fal-ai/pika/v2/turbo/image-to-video",
+        ("pikadditions", _) => "fal-ai/pika/v2/pikadditions",
+        ("pikaswaps", _) => "fal-ai/pika/v2/pikaswaps",
+        _ => return None,
+    };
+    Some(endpoint)
+}
+
+/// Video to modify: a URL as given, or a local file sent inline
+fn source_video(params: &Value) -> Result<Option<String>> {
+    if let Some(url) = params.get("video_url").and_then(|v| v.as_str()) {
+        return Ok(Some(url.to_string()));
+    }
+    let Some(path) = params.get("video_path").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Could not read source video {}: {}", path, e))?;
+    Ok(Some(format!("data:video/mp4;base64,{}", general_purpose::STANDARD.encode(bytes))))
+}
+
+/// fal.ai endpoint and input parameters of a request
+///
+/// Only the fields the endpoint takes are passed on; a reference image stays in the
+/// parameters and becomes the endpoint's `image_url`.
+pub fn fal_request(model: &str, params: &Value) -> Result<(&'static str, Value)> {
+    let values = ModelParams::new("pika", model, params);
+    let has_image = extract_reference_image(params).is_some();
+    let endpoint = endpoint(model, has_image).ok_or_else(|| {
+        GenerationError::invalid_param(
+            "model",
+            format!(
+                "Unsupported Pika model: {}. Use 'pika-2.2', 'pika-2-turbo', \
+                 'pikadditions' or 'pikaswaps'.",
+                model
+            ),
+        )
+    })?;
+
+    let mut input = serde_json::Map::new();
+    for key in ["reference_image", "reference_images", "negative_prompt", "seed"] {
+        if let Some(value) = params.get(key).filter(|v| !v.is_null()) {
+            input.insert(key.to_string(), value.clone());
+        }
+    }
+
+    if MODIFY_MODELS.contains(&model) {
+        let video = source_video(params)?.ok_or_else(|| {
+            GenerationError::invalid_param("video_url", format!("Pika {} needs a video", model))
+        })?;
+        input.insert("video_url".to_string(), serde_json::json!(video));
+        if model == "pikadditions" && !has_image {
+            return Err(GenerationError::invalid_param(
+                "reference_image",
+                "Pikadditions needs an image of what to add".to_string(),
+            )
+            .into());
+        }
+        if let Some(region) = params.get("modify_region").and_then(|v| v.as_str()) {
+            input.insert("modify_region".to_string(), serde_json::json!(region));
+        }
+        return Ok((endpoint, Value::Object(input)));
+    }
+
+    // Image-to-video follows the image's aspect ratio
+    if !has_image {
+        let aspect_ratio = values.str("aspect_ratio");
+        if !ASPECT_RATIOS.contains(&aspect_ratio) {
+            return Err(GenerationError::invalid_param(
+                "aspect_ratio",
+                format!("Pika does not offer the aspect ratio {}", aspect_ratio),
+            )
+            .into());
+        }
+        input.insert("aspect_ratio".to_string(), serde_json::json!(aspect_ratio));
+    }
+    input.insert("resolution".to_string(), serde_json::json!(values.str("resolution")));
+    input.insert("duration".to_string(), serde_json::json!(values.u64("duration")));
+    Ok((endpoint, Value::Object(input)))
+}
+
+/// Pika provider (text-to-video, image-to-video and video edits through fal.ai's queue)
+pub struct PikaProvider {
+    /// Queue client doing the submitting and polling
+    queue: Option<FalProvider>,
+}
+
+impl PikaProvider {
+    pub fn new() -> Self {
+        Self { queue: None }
+    }
+
+    pub fn with_config(config: PikaConfig) -> Self {
+        Self {
+            queue: Some(FalProvider::with_config(FalConfig {
+                api_key: config.api_key,
+                base_url: config.base_url,
+                timeouts: config.timeouts,
+                headers: config.headers,
+            })),
+        }
+    }
+
+    /// The queue client and the request translated to its endpoint
+    fn queued_request(
+        &self,
+        request: &GenerationRequest,
+    ) -> Result<(&FalProvider, GenerationRequest)> {
+        let queue = self
+            .queue
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Pika"))?;
+        let (endpoint, parameters) = fal_request(&request.model, &request.parameters)?;
+        let request = GenerationRequest {
+            prompt: request.prompt.clone(),
+            model: endpoint.to_string(),
+            parameters,
+        };
+        Ok((queue, request))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for PikaProvider {
+    fn name(&self) -> &str {
+        "pika"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.queue
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Pika"))?
+            .test_connection()
+            .await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let (queue, request) = self.queued_request(&request)?;
+        queue.generate(request).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        let (queue, request) = self.queued_request(&request)?;
+        queue.generate_with_progress(request, progress).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if MODIFY_MODELS.contains(&model) {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "video_url": { "type": "string" },
+                    "video_path": { "type": "string" },
+                    "modify_region": { "type": "string" },
+                    "negative_prompt": { "type": "string" },
+                    "seed": { "type": "integer" }
+                }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "aspect_ratio": { "type": "string", "enum": ASPECT_RATIOS },
+                "resolution": { "type": "string", "enum": ["720p", "1080p"] },
+                "duration": { "type": "integer", "enum": [5, 10] },
+                "negative_prompt": { "type": "string" },
+                "seed": { "type": "integer" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your fal.ai API key, which Pika's API is billed through"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of fal.ai's public queue"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fal_request() {
+        let params = json!({ "aspect_ratio": "9:16", "negative_prompt": "blur", "style": "x" });
+        let (endpoint, input) = fal_request("pika-2.2", &params).unwrap();
+        assert_eq!(endpoint, "fal-ai/pika/v2.2/text-to-video");
+        assert_eq!(
+            input,
+            json!({
+                "aspect_ratio": "9:16",
+                "negative_prompt": "blur",
+                "resolution": "720p",
+                "duration": 5
+            })
+        );
+
+        assert!(fal_request("pika-2.2", &json!({ "aspect_ratio": "7:3" })).is_err());
+        assert!(fal_request("pikaswaps", &json!({})).is_err());
+
+        let params = json!({ "video_url": "https://example.com/v.mp4", "modify_region": "hat" });
+        let (endpoint, input) = fal_request("pikaswaps", &params).unwrap();
+        assert_eq!(endpoint, "fal-ai/pika/v2/pikaswaps");
+        assert_eq!(input["video_url"], "https://example.com/v.mp4");
+        assert!(input.get("duration").is_none());
+    }
+}
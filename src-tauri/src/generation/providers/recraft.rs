@@ -0,0 +1,283 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, check_connection, endpoint_url, send_with_retry};
+
+/// Recraft configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecraftConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a regional endpoint or gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://external.api.recraft.ai/v1";
+
+const MODELS: &[(&str, &str)] = &[("recraftv3", "Recraft V3"), ("recraftv2", "Recraft V2")];
+
+/// Styles whose outputs are SVG documents rather than raster images
+pub const VECTOR_STYLES: &[&str] = &["vector_illustration", "icon"];
+
+/// Sizes the API accepts
+const SIZES: &[&str] = &[
+    "1024x1024", "1365x1024", "1024x1365", "1536x1024", "1024x1536", "1820x1024", "1024x1820",
+    "1024x2048", "2048x1024", "1434x1024", "1024x1434", "1024x1280", "1280x1024", "1024x1707",
+    "1707x1024",
+];
+
+impl RecraftConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Request body of a generation
+///
+/// A custom `style_id` replaces the built-in style. Outputs are always requested as
+/// base64 so vector results reach the asset pipeline instead of an expiring URL.
+pub fn request_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("recraft", model, params);
+    let mut body = serde_json::json!({
+        "prompt": prompt,
+        "model": model,
+        "size": values.str("size"),
+        "n": values.u64("n").clamp(1, 6),
+        "response_format": "b64_json",
+    });
+
+    match params.get("style_id").and_then(|v| v.as_str()) {
+        Some(style_id) => body["style_id"] = serde_json::json!(style_id),
+        None => {
+            body["style"] = serde_json::json!(values.str("style"));
+            if let Some(substyle) = params.get("substyle").and_then(|v| v.as_str()) {
+                body["substyle"] = serde_json::json!(substyle);
+            }
+        }
+    }
+    if let Some(negative) = params.get("negative_prompt").and_then(|v| v.as_str()) {
+        if !negative.trim().is_empty() {
+            body["negative_prompt"] = serde_json::json!(negative);
+        }
+    }
+    // Palette and background, e.g. `{ "colors": [{ "rgb": [255, 0, 0] }] }`
+    if let Some(controls) = params.get("controls").filter(|v| v.is_object()) {
+        body["controls"] = controls.clone();
+    }
+    body
+}
+
+/// MIME type of base64 output, told apart by its first bytes
+pub fn sniff_mime_type(data: &str) -> &'static str {
+    // Whole base64 quanta only, enough for any of the signatures
+    let prefix: String = data.chars().filter(|c| !c.is_whitespace()).take(64).collect();
+    let bytes = general_purpose::STANDARD
+        .decode(&prefix[..prefix.len() / 4 * 4])
+        .unwrap_or_default();
+    let text = String::from_utf8_lossy(&bytes);
+    let text = text.trim_start();
+
+    if text.starts_with("<svg") || text.starts_with("<?xml") {
+        "image/svg+xml"
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        "image/jpeg"
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else {
+        "image/png"
+    }
+}
+
+/// Recraft provider (raster and vector image generation)
+pub struct RecraftProvider {
+    config: Option<RecraftConfig>,
+    client: reqwest::Client,
+}
+
+impl RecraftProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("recraft").client(),
+        }
+    }
+
+    pub fn with_config(config: RecraftConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for RecraftProvider {
+    fn name(&self) -> &str {
+        "recraft"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // The API has no model listing
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Recraft"))?;
+        let request = self
+            .client
+            .get(config.url("users/me"))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        check_connection("Recraft API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Recraft"))?;
+        if !MODELS.iter().any(|(id, _)| *id == request.model) {
+            return Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Recraft model: {}. Use 'recraftv3' or 'recraftv2'.",
+                    request.model
+                ),
+            )
+            .into());
+        }
+
+        let body = request_body(&request.model, &request.prompt, &request.parameters);
+        let size = body["size"].as_str().unwrap_or_default();
+        if !SIZES.contains(&size) {
+            return Err(GenerationError::invalid_param(
+                "size",
+                format!("Recraft does not offer the size {}", size),
+            )
+            .into());
+        }
+
+        let submit = self
+            .client
+            .post(config.url("images/generations"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body);
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Recraft API", response).await);
+        }
+        let mut response_data: Value = response.json().await?;
+
+        let artifacts = response_data
+            .get("data")
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let data = item.get("b64_json")?.as_str()?;
+                let mime_type = sniff_mime_type(data);
+                Some(Artifact::from_data(data.to_string()).with_mime_type(mime_type))
+            })
+            .collect::<Vec<_>>();
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("No images in Recraft response"));
+        }
+
+        // The images now live in the artifacts; keep the metadata small
+        if let Some(items) = response_data.get_mut("data").and_then(|d| d.as_array_mut()) {
+            for item in items.iter_mut().filter_map(|item| item.as_object_mut()) {
+                item.remove("b64_json");
+            }
+        }
+
+        Ok(GenerationResult::new(artifacts, response_data))
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        let mut styles =
+            vec!["realistic_image", "digital_illustration", "vector_illustration", "icon"];
+        if model == "recraftv3" {
+            styles.insert(0, "any");
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "style": { "type": "string", "enum": styles },
+                "substyle": { "type": "string" },
+                "style_id": { "type": "string" },
+                "size": { "type": "string", "enum": SIZES },
+                "n": { "type": "integer", "minimum": 1, "maximum": 6 },
+                "negative_prompt": { "type": "string" },
+                "controls": { "type": "object" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Recraft API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body_and_output_type() {
+        let params = json!({ "style": "vector_illustration", "substyle": "line_art", "n": 9 });
+        let body = request_body("recraftv3", "a fox", &params);
+        assert_eq!(body["style"], "vector_illustration");
+        assert_eq!(body["substyle"], "line_art");
+        assert_eq!(body["n"], 6);
+        assert_eq!(body["size"], "1024x1024");
+
+        let custom = request_body("recraftv3", "a fox", &json!({ "style_id": "abc" }));
+        assert_eq!(custom["style_id"], "abc");
+        assert!(custom.get("style").is_none());
+
+        let svg = general_purpose::STANDARD.encode(r#"<svg xmlns="http://www.w3.org/2000/svg">"#);
+        assert_eq!(sniff_mime_type(&svg), "image/svg+xml");
+        let png = general_purpose::STANDARD.encode(b"\x89PNG\r\n\x1a\n0000");
+        assert_eq!(sniff_mime_type(&png), "image/png");
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_connection, endpoint_url, extract_reference_image, send_with_retry,
+};
+
+/// Runway configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunwayConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.dev.runwayml.com/v1";
+
+/// API version sent with every request, as Runway requires
+const API_VERSION: &str = "2024-11-06";
+
+/// Models animating a reference image
+pub const IMAGE_TO_VIDEO_MODELS: &[&str] = &["gen4_turbo", "gen3a_turbo"];
+
+/// Models generating video from the prompt alone
+const TEXT_TO_VIDEO_MODELS: &[&str] = &["veo3.1", "veo3.1_fast"];
+
+const MODELS: &[(&str, &str)] = &[
+    ("gen4_turbo", "Gen-4 Turbo"),
+    ("gen3a_turbo", "Gen-3 Alpha Turbo"),
+    ("veo3.1", "Veo 3.1"),
+    ("veo3.1_fast", "Veo 3.1 Fast"),
+];
+
+impl RunwayConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Output ratios (`width:height`) and durations in seconds a model accepts
+pub fn model_limits(model: &str) -> (&'static [&'static str], &'static [u64]) {
+    match model {
+        "gen3a_turbo" => (&["1280:768", "768:1280"], &[5, 10]),
+        "veo3.1" | "veo3.1_fast" => {
+            (&["1280:720", "720:1280", "1920:1080", "1080:1920"], &[4, 6, 8])
+        }
+        _ => (
+            &["1280:720", "720:1280", "1104:832", "832:1104", "960:960", "1584:672"],
+            &[5, 10],
+        ),
+    }
+}
+
+/// Endpoint and body of a generation
+///
+/// Gen-3 and Gen-4 animate the reference image (or a `prompt_image` URL) and go to
+/// `image_to_video`; the other models take the prompt alone.
+pub fn request_body(model: &str, prompt: &str, params: &Value) -> Result<(&'static str, Value)> {
+    let values = ModelParams::new("runway", model, params);
+    let mut body = serde_json::json!({
+        "model": model,
+        "promptText": prompt,
+        "ratio": values.str("ratio"),
+        "duration": values.u64("duration"),
+    });
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        body["seed"] = serde_json::json!(seed);
+    }
+
+    if TEXT_TO_VIDEO_MODELS.contains(&model) {
+        return Ok(("text_to_video", body));
+    }
+    let image = match extract_reference_image(params) {
+        Some((mime_type, data)) => format!("data:{};base64,{}", mime_type, data),
+        None => params
+            .get("prompt_image")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                GenerationError::invalid_param(
+                    "reference_image",
+                    format!("Runway {} needs a reference image to animate", model),
+                )
+            })?,
+    };
+    body["promptImage"] = serde_json::json!(image);
+    Ok(("image_to_video", body))
+}
+
+/// Runway provider (Gen-3 and Gen-4 video through Runway's task API)
+pub struct RunwayProvider {
+    config: Option<RunwayConfig>,
+    client: reqwest::Client,
+}
+
+impl RunwayProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("runway").client(),
+        }
+    }
+
+    pub fn with_config(config: RunwayConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    /// Start a task, poll it until it finishes and download its video
+    async fn generate_task(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Runway"))?;
+        if !MODELS.iter().any(|(id, _)| *id == request.model) {
+            return Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Runway model: {}. Use 'gen4_turbo' or 'gen3a_turbo'.",
+                    request.model
+                ),
+            )
+            .into());
+        }
+
+        let (endpoint, body) =
+            request_body(&request.model, &request.prompt, &request.parameters)?;
+        let (ratios, durations) = model_limits(&request.model);
+        let ratio = body["ratio"].as_str().unwrap_or_default();
+        if !ratios.contains(&ratio) {
+            return Err(GenerationError::invalid_param(
+                "ratio",
+                format!("Runway {} does not offer the ratio {}", request.model, ratio),
+            )
+            .into());
+        }
+        let duration = body["duration"].as_u64().unwrap_or_default();
+        if !durations.contains(&duration) {
+            return Err(GenerationError::invalid_param(
+                "duration",
+                format!("Runway {} cannot make {} second videos", request.model, duration),
+            )
+            .into());
+        }
+
+        let submit = self
+            .client
+            .post(config.url(endpoint))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("X-Runway-Version", API_VERSION)
+            .json(&body);
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Runway API", response).await);
+        }
+        let started: Value = response.json().await?;
+        let task_id = started
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No task ID in Runway response"))?;
+
+        report_progress(progress, 0.0, "Runway task started");
+        let task = self.wait_for_completion(config, task_id, progress).await?;
+
+        // Output URLs expire after a day or two, so the video is kept locally
+        let url = task
+            .get("output")
+            .and_then(|v| v.as_array())
+            .and_then(|output| output.first())
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No video in Runway response"))?;
+        report_progress(progress, 100.0, "Downloading video");
+        let response = send_with_retry(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Runway download", response).await);
+        }
+        let video = response.bytes().await?;
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&video))
+            .with_mime_type("video/mp4");
+
+        Ok(GenerationResult::new(vec![artifact], task))
+    }
+
+    /// Poll a task until it succeeds, returning it
+    async fn wait_for_completion(
+        &self,
+        config: &RunwayConfig,
+        task_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let mut delay_ms = 2000u64;
+        let max_delay_ms = 10000u64;
+        let max_attempts = 240; // ~30 minutes max wait time
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let request = self
+                .client
+                .get(config.url(&format!("tasks/{}", task_id)))
+                .header("Authorization", format!("Bearer {}", config.api_key))
+                .header("X-Runway-Version", API_VERSION);
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The task kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Runway poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+            if !response.status().is_success() {
+                return Err(api_error("Runway task", response).await);
+            }
+            let task: Value = response.json().await?;
+
+            match task.get("status").and_then(|v| v.as_str()) {
+                Some("SUCCEEDED") => return Ok(task),
+                Some("FAILED") => {
+                    let failure = task
+                        .get("failure")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown error");
+                    return Err(anyhow::anyhow!("Runway generation failed: {}", failure));
+                }
+                Some("CANCELLED") => {
+                    return Err(anyhow::anyhow!("Runway task {} was cancelled", task_id));
+                }
+                Some("THROTTLED") => {
+                    report_progress(progress, 0.0, "Waiting for a Runway slot");
+                }
+                _ => {
+                    let fraction = task.get("progress").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                    let percentage = (fraction * 100.0) as f32;
+                    report_progress(progress, percentage, "Generating at Runway");
+                }
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 1000, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("Runway generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Runway generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for RunwayProvider {
+    fn name(&self) -> &str {
+        "runway"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // The API has no model listing
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Runway"))?;
+        let request = self
+            .client
+            .get(config.url("organization"))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("X-Runway-Version", API_VERSION);
+        check_connection("Runway API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_task(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_task(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        let (ratios, durations) = model_limits(model);
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "ratio": { "type": "string", "enum": ratios },
+                "duration": { "type": "integer", "enum": durations },
+                "seed": { "type": "integer" },
+                "prompt_image": { "type": "string" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Runway API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_body() {
+        let params = json!({ "prompt_image": "https://example.com/a.png", "duration": 10 });
+        let (endpoint, body) = request_body("gen4_turbo", "waves", &params).unwrap();
+        assert_eq!(endpoint, "image_to_video");
+        assert_eq!(body["promptImage"], "https://example.com/a.png");
+        assert_eq!(body["ratio"], "1280:720");
+        assert_eq!(body["duration"], 10);
+
+        assert!(request_body("gen3a_turbo", "waves", &json!({})).is_err());
+
+        let (endpoint, body) = request_body("veo3.1", "waves", &json!({})).unwrap();
+        assert_eq!(endpoint, "text_to_video");
+        assert_eq!(body["duration"], 8);
+        assert!(body.get("promptImage").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::super::{
+    Artifact, GenerationProvider, GenerationRequest, GenerationResult, ModelInfo,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{api_error, check_connection, endpoint_url, send_rebuilt_with_retry};
+
+/// Stability AI configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StabilityConfig {
+    pub api_key: String,
+    /// API root replacing the public one, e.g. a gateway
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.stability.ai/v2beta";
+
+const MODELS: &[(&str, &str)] = &[
+    ("stable-audio-2.5", "Stable Audio 2.5"),
+    ("stable-audio-2", "Stable Audio 2"),
+];
+
+/// Longest track in seconds
+pub const MAX_DURATION: u64 = 190;
+
+impl StabilityConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// Form fields of a text-to-audio request
+///
+/// Stable Audio has no separate style or tempo inputs, so `style` and `bpm` are
+/// appended to the prompt the way its prompting guide suggests.
+pub fn form_fields(model: &str, prompt: &str, params: &Value) -> Result<Vec<(String, String)>> {
+    if !MODELS.iter().any(|(id, _)| *id == model) {
+        return Err(GenerationError::invalid_param(
+            "model",
+            format!("Unsupported Stability model: {}. Use 'stable-audio-2.5'.", model),
+        )
+        .into());
+    }
+    let values = ModelParams::new("stability", model, params);
+    let duration = values.u64("duration");
+    if !(1..=MAX_DURATION).contains(&duration) {
+        return Err(GenerationError::invalid_param(
+            "duration",
+            format!("Tracks last 1 to {} seconds", MAX_DURATION),
+        )
+        .into());
+    }
+
+    let mut text = prompt.trim().to_string();
+    if let Some(style) = params.get("style").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        text = format!("{}, {}", text, style);
+    }
+    if let Some(bpm) = params.get("bpm").and_then(|v| v.as_u64()) {
+        text = format!("{}, {} BPM", text, bpm);
+    }
+
+    let mut fields = vec![
+        ("prompt".to_string(), text),
+        ("model".to_string(), model.to_string()),
+        ("duration".to_string(), duration.to_string()),
+        ("steps".to_string(), values.u64("steps").to_string()),
+        ("cfg_scale".to_string(), values.f64("cfg_scale").to_string()),
+        ("output_format".to_string(), values.str("output_format").to_string()),
+    ];
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        fields.push(("seed".to_string(), seed.to_string()));
+    }
+    Ok(fields)
+}
+
+/// Stability AI provider (Stable Audio music and sound, e.g. background tracks for scenes)
+pub struct StabilityProvider {
+    config: Option<StabilityConfig>,
+    client: reqwest::Client,
+}
+
+impl StabilityProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("stability").client(),
+        }
+    }
+
+    pub fn with_config(config: StabilityConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for StabilityProvider {
+    fn name(&self) -> &str {
+        "stability"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Stability AI"))?;
+
+        // The balance lives in the v1 API, next to the v2beta root
+        let request = self
+            .client
+            .get(config.url("../v1/user/balance"))
+            .header("Authorization", format!("Bearer {}", config.api_key));
+        check_connection("Stability AI API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Stability AI"))?;
+        let fields = form_fields(&request.model, &request.prompt, &request.parameters)?;
+        let format = fields
+            .iter()
+            .find(|(name, _)| name == "output_format")
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default();
+
+        // The multipart body cannot be cloned, so every attempt builds its own
+        let response = send_rebuilt_with_retry(|| {
+            let form = fields
+                .iter()
+                .fold(reqwest::multipart::Form::new(), |form, (name, value)| {
+                    form.text(name.clone(), value.clone())
+                });
+            self.client
+                .post(config.url("audio/stable-audio-2/text-to-audio"))
+                .header("Authorization", format!("Bearer {}", config.api_key))
+                .header("Accept", "audio/*")
+                .multipart(form)
+        })
+        .await?;
+        if !response.status().is_success() {
+            return Err(api_error("Stability AI API", response).await);
+        }
+        let seed = response
+            .headers()
+            .get("seed")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let audio = response.bytes().await?;
+        let mime_type = if format == "wav" { "audio/wav" } else { "audio/mpeg" };
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&audio))
+            .with_mime_type(mime_type)
+            .with_seed(seed);
+
+        let metadata: serde_json::Map<String, Value> = fields
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+        Ok(GenerationResult::new(vec![artifact], Value::Object(metadata)))
+    }
+
+    fn parameter_schema(&self, _model: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "duration": { "type": "integer", "minimum": 1, "maximum": MAX_DURATION },
+                "style": { "type": "string" },
+                "bpm": { "type": "integer", "minimum": 40, "maximum": 240 },
+                "steps": { "type": "integer", "minimum": 4, "maximum": 100 },
+                "cfg_scale": { "type": "number", "minimum": 1, "maximum": 25 },
+                "output_format": { "type": "string", "enum": ["mp3", "wav"] },
+                "seed": { "type": "integer" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description": "Your Stability AI API key"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Gateway to use instead of the public API"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_form_fields() {
+        let params = json!({ "duration": 45, "style": "lo-fi hip hop", "bpm": 80 });
+        let fields = form_fields("stable-audio-2.5", "Rainy night", &params).unwrap();
+        let field = |name: &str| {
+            fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str()).unwrap()
+        };
+        assert_eq!(field("prompt"), "Rainy night, lo-fi hip hop, 80 BPM");
+        assert_eq!(field("duration"), "45");
+        assert_eq!(field("steps"), "8");
+        assert_eq!(field("output_format"), "mp3");
+
+        assert!(form_fields("stable-audio-2.5", "x", &json!({ "duration": 300 })).is_err());
+        assert!(form_fields("stable-audio-1", "x", &json!({})).is_err());
+    }
+}
@@ -0,0 +1,627 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_connection, extract_reference_image, send_with_retry, ApiStatusError,
+};
+
+/// Vertex AI configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VertexConfig {
+    /// Service account key or `gcloud auth application-default login` credentials (JSON)
+    pub credentials: String,
+    /// Project billed for requests; defaults to the one named in the credentials
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Region serving requests; defaults to the credentials' `location` or `us-central1`
+    #[serde(default)]
+    pub location: Option<String>,
+    /// API root replacing the regional one, e.g. a Private Service Connect endpoint
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Region used when neither the config nor the credentials name one
+const DEFAULT_LOCATION: &str = "us-central1";
+
+/// OAuth scope covering Vertex AI
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+const MODELS: &[(&str, &str)] = &[
+    ("imagen-4.0-generate-001", "Imagen 4"),
+    ("imagen-4.0-fast-generate-001", "Imagen 4 Fast"),
+    ("imagen-4.0-ultra-generate-001", "Imagen 4 Ultra"),
+    ("imagen-3.0-generate-002", "Imagen 3"),
+    ("veo-3.1-generate-preview", "Veo 3.1"),
+    ("veo-3.0-generate-001", "Veo 3"),
+    ("veo-3.0-fast-generate-001", "Veo 3 Fast"),
+    ("veo-2.0-generate-001", "Veo 2"),
+];
+
+/// How access tokens are obtained
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    /// Tokens are requested with a JWT signed by the account's key
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        token_uri: String,
+    },
+    /// OAuth user credentials, exchanged for tokens with their refresh token
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+}
+
+/// Credentials with the project and location they name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCredentials {
+    pub credentials: Credentials,
+    pub project_id: Option<String>,
+    pub location: Option<String>,
+}
+
+/// Parse a service account key or application-default credentials file
+pub fn parse_credentials(json: &str) -> Result<ParsedCredentials> {
+    let value: Value = serde_json::from_str(json.trim()).map_err(|_| {
+        GenerationError::invalid_param(
+            "credentials",
+            "Vertex AI needs a service account key or application-default credentials \
+             file (JSON)"
+                .to_string(),
+        )
+    })?;
+    let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let required = |name: &str| {
+        field(name).ok_or_else(|| {
+            GenerationError::invalid_param(
+                "credentials",
+                format!("Vertex AI credentials are missing '{}'", name),
+            )
+        })
+    };
+
+    let credentials = match field("type").as_deref() {
+        Some("service_account") => Credentials::ServiceAccount {
+            client_email: required("client_email")?,
+            private_key: required("private_key")?,
+            token_uri: field("token_uri").unwrap_or_else(|| TOKEN_URI.to_string()),
+        },
+        Some("authorized_user") => Credentials::AuthorizedUser {
+            client_id: required("client_id")?,
+            client_secret: required("client_secret")?,
+            refresh_token: required("refresh_token")?,
+        },
+        other => {
+            return Err(GenerationError::invalid_param(
+                "credentials",
+                format!("Unsupported Google credentials type: {}", other.unwrap_or("none")),
+            )
+            .into())
+        }
+    };
+    Ok(ParsedCredentials {
+        credentials,
+        project_id: field("project_id").or_else(|| field("quota_project_id")),
+        location: field("location"),
+    })
+}
+
+/// Regional API root of a project
+pub fn api_root(project_id: &str, location: &str) -> String {
+    let host = if location == "global" {
+        "aiplatform.googleapis.com".to_string()
+    } else {
+        format!("{}-aiplatform.googleapis.com", location)
+    };
+    format!("https://{}/v1/projects/{}/locations/{}", host, project_id, location)
+}
+
+/// JWT asserting the service account's identity, signed with its RSA key
+fn signed_assertion(client_email: &str, private_key: &str, token_uri: &str) -> Result<String> {
+    let der: String = private_key
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = general_purpose::STANDARD.decode(der.trim())?;
+    let key_pair = ring::signature::RsaKeyPair::from_pkcs8(&der)
+        .map_err(|e| anyhow::anyhow!("Invalid service account key: {}", e))?;
+
+    let now = chrono::Utc::now().timestamp();
+    let encode = |value: &Value| general_purpose::URL_SAFE_NO_PAD.encode(value.to_string());
+    let header = encode(&serde_json::json!({ "alg": "RS256", "typ": "JWT" }));
+    let claims = encode(&serde_json::json!({
+        "iss": client_email,
+        "scope": SCOPE,
+        "aud": token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    }));
+    let message = format!("{}.{}", header, claims);
+
+    let mut signature = vec![0; key_pair.public().modulus_len()];
+    key_pair
+        .sign(
+            &ring::signature::RSA_PKCS1_SHA256,
+            &ring::rand::SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )
+        .map_err(|_| anyhow::anyhow!("Could not sign the service account assertion"))?;
+    Ok(format!("{}.{}", message, general_purpose::URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// Body of an Imagen request
+pub fn imagen_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("vertex", model, params);
+    let mut parameters = serde_json::json!({
+        "sampleCount": values.u64("n"),
+        "aspectRatio": values.str("aspect_ratio"),
+        "personGeneration": values.str("person_generation"),
+    });
+    if let Some(negative) = params.get("negative_prompt").and_then(|v| v.as_str()) {
+        parameters["negativePrompt"] = serde_json::json!(negative);
+    }
+    // A seed only takes effect with the watermark off
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        parameters["seed"] = serde_json::json!(seed);
+        parameters["addWatermark"] = serde_json::json!(false);
+    }
+    serde_json::json!({ "instances": [{ "prompt": prompt }], "parameters": parameters })
+}
+
+/// Body of a Veo request, animating the reference image when there is one
+pub fn veo_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("vertex", model, params);
+    let mut instance = serde_json::json!({ "prompt": prompt });
+    if let Some((mime_type, data)) = extract_reference_image(params) {
+        instance["image"] =
+            serde_json::json!({ "bytesBase64Encoded": data, "mimeType": mime_type });
+    }
+    let mut parameters = serde_json::json!({
+        "aspectRatio": values.str("aspect_ratio"),
+        "durationSeconds": values.u64("duration"),
+        "sampleCount": 1,
+    });
+    // Veo 2 has neither a resolution setting nor audio
+    if !model.starts_with("veo-2") {
+        parameters["resolution"] = serde_json::json!(values.str("resolution"));
+        parameters["generateAudio"] = serde_json::json!(values.bool("generate_audio"));
+    }
+    if let Some(negative) = params.get("negative_prompt").and_then(|v| v.as_str()) {
+        parameters["negativePrompt"] = serde_json::json!(negative);
+    }
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        parameters["seed"] = serde_json::json!(seed);
+    }
+    serde_json::json!({ "instances": [instance], "parameters": parameters })
+}
+
+/// Vertex AI provider (Imagen and Veo under a Google Cloud project's billing)
+pub struct VertexProvider {
+    config: Option<(VertexConfig, ParsedCredentials)>,
+    client: reqwest::Client,
+    /// Access token and when it stops being valid
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl VertexProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("vertex").client(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Provider for the config; credentials that cannot be parsed fail each request
+    pub fn with_config(config: VertexConfig) -> Self {
+        let parsed = parse_credentials(&config.credentials);
+        if let Err(e) = &parsed {
+            log_warn!("Vertex AI credentials rejected: {}", e);
+        }
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: parsed.ok().map(|parsed| (config, parsed)),
+            token: Mutex::new(None),
+        }
+    }
+
+    fn config(&self) -> Result<&(VertexConfig, ParsedCredentials)> {
+        self.config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Vertex AI").into())
+    }
+
+    /// API root for the configured project and location
+    fn root(&self) -> Result<String> {
+        let (config, parsed) = self.config()?;
+        if let Some(base_url) = &config.base_url {
+            return Ok(base_url.trim_end_matches('/').to_string());
+        }
+        let project_id = config
+            .project_id
+            .as_deref()
+            .or(parsed.project_id.as_deref())
+            .ok_or_else(|| {
+                GenerationError::invalid_param(
+                    "project_id",
+                    "Vertex AI credentials do not name a project".to_string(),
+                )
+            })?;
+        let location = config
+            .location
+            .as_deref()
+            .or(parsed.location.as_deref())
+            .unwrap_or(DEFAULT_LOCATION);
+        Ok(api_root(project_id, location))
+    }
+
+    /// A valid access token, requesting a new one shortly before the last expires
+    async fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some((access_token, expires)) = token.as_ref() {
+            if Instant::now() + Duration::from_secs(60) < *expires {
+                return Ok(access_token.clone());
+            }
+        }
+
+        let (_, parsed) = self.config()?;
+        let request = match &parsed.credentials {
+            Credentials::ServiceAccount {
+                client_email,
+                private_key,
+                token_uri,
+            } => {
+                let assertion = signed_assertion(client_email, private_key, token_uri)?;
+                self.client.post(token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            }
+            Credentials::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => self.client.post(TOKEN_URI).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ]),
+        };
+        let response = send_with_retry(request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            // A revoked key or refresh token reads as a bad key, whatever status Google uses
+            let message = response.text().await.unwrap_or_default();
+            return Err(anyhow::Error::new(ApiStatusError {
+                status: 401,
+                message: format!("Google token request failed ({}): {}", status, message),
+            }));
+        }
+        let body: Value = response.json().await?;
+        let access_token = body
+            .get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No access token in Google token response"))?
+            .to_string();
+        let lifetime = body.get("expires_in").and_then(|v| v.as_u64()).unwrap_or(3600);
+        *token = Some((access_token.clone(), Instant::now() + Duration::from_secs(lifetime)));
+        Ok(access_token)
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        let url = format!("{}/{}", self.root()?, path);
+        let request = self
+            .client
+            .post(url)
+            .bearer_auth(self.access_token().await?)
+            .json(body);
+        let response = send_with_retry(request).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Vertex AI", response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn generate_image(&self, request: &GenerationRequest) -> Result<GenerationResult> {
+        let body = imagen_body(&request.model, &request.prompt, &request.parameters);
+        let path = format!("publishers/google/models/{}:predict", request.model);
+        let mut response = self.post(&path, &body).await?;
+
+        let artifacts: Vec<Artifact> = response
+            .get("predictions")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|prediction| {
+                let data = prediction.get("bytesBase64Encoded")?.as_str()?;
+                let mime_type = prediction
+                    .get("mimeType")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("image/png");
+                Some(Artifact::from_data(data.to_string()).with_mime_type(mime_type))
+            })
+            .collect();
+        if artifacts.is_empty() {
+            // Prompts or outputs blocked by safety filters come back without images
+            return Err(anyhow::anyhow!("No images in Vertex AI response: {}", response));
+        }
+
+        // The images now live in the artifacts; keep the metadata small
+        if let Some(predictions) = response.get_mut("predictions").and_then(|p| p.as_array_mut()) {
+            for prediction in predictions.iter_mut().filter_map(|p| p.as_object_mut()) {
+                prediction.remove("bytesBase64Encoded");
+            }
+        }
+        Ok(GenerationResult::new(artifacts, response))
+    }
+
+    /// Start a Veo operation and poll it until the video is ready
+    async fn generate_video(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let body = veo_body(&request.model, &request.prompt, &request.parameters);
+        let model_path = format!("publishers/google/models/{}", request.model);
+        let started = self
+            .post(&format!("{}:predictLongRunning", model_path), &body)
+            .await?;
+        let operation = started
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No operation name in Vertex AI response"))?;
+        report_progress(progress, 0.0, "Veo operation submitted");
+
+        let mut delay_ms = 10000u64;
+        let max_delay_ms = 30000u64;
+        let max_attempts = 90; // ~40 minutes max wait time
+        let poll_body = serde_json::json!({ "operationName": operation });
+        let started_at = Instant::now();
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+            report_progress(
+                progress,
+                0.0,
+                format!("Veo generation in progress ({}s elapsed)", started_at.elapsed().as_secs()),
+            );
+
+            let poll = self
+                .post(&format!("{}:fetchPredictOperation", model_path), &poll_body)
+                .await;
+            let mut operation = match poll {
+                Ok(operation) => operation,
+                // The operation kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Vertex AI poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            wakes = power::wakes();
+
+            if operation.get("done").and_then(|v| v.as_bool()) == Some(true) {
+                if let Some(error) = operation.get("error") {
+                    let message = error
+                        .get("message")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("Video generation failed");
+                    return Err(anyhow::anyhow!("Veo generation failed: {}", message));
+                }
+                let artifacts: Vec<Artifact> = operation
+                    .pointer("/response/videos")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|video| {
+                        let artifact = match video.get("bytesBase64Encoded") {
+                            Some(data) => Artifact::from_data(data.as_str()?.to_string()),
+                            None => Artifact::from_url(video.get("gcsUri")?.as_str()?.to_string()),
+                        };
+                        Some(artifact.with_mime_type("video/mp4"))
+                    })
+                    .collect();
+                if artifacts.is_empty() {
+                    return Err(anyhow::anyhow!("No videos in Vertex AI response"));
+                }
+                if let Some(videos) = operation
+                    .pointer_mut("/response/videos")
+                    .and_then(|v| v.as_array_mut())
+                {
+                    for video in videos.iter_mut().filter_map(|v| v.as_object_mut()) {
+                        video.remove("bytesBase64Encoded");
+                    }
+                }
+                return Ok(GenerationResult::new(artifacts, operation));
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 5000, max_delay_ms);
+            if attempt % 6 == 0 {
+                log_debug!("Vertex AI Veo generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Video generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+
+    async fn dispatch(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        self.config()?;
+        if request.model.starts_with("imagen") {
+            self.generate_image(request).await
+        } else if request.model.starts_with("veo") {
+            self.generate_video(request, progress).await
+        } else {
+            Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Vertex AI model: {}. Use an Imagen or Veo model.",
+                    request.model
+                ),
+            )
+            .into())
+        }
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for VertexProvider {
+    fn name(&self) -> &str {
+        "vertex"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // Publisher models are not listed per project; offer the generation models
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // Fetching a token checks the credentials, listing endpoints the project access
+        let request = self
+            .client
+            .get(format!("{}/endpoints?pageSize=1", self.root()?))
+            .bearer_auth(self.access_token().await?);
+        check_connection("Vertex AI", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.dispatch(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.dispatch(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if model.starts_with("veo") {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "duration": { "type": "integer", "minimum": 4, "maximum": 8 },
+                    "resolution": { "type": "string", "enum": ["720p", "1080p"] },
+                    "aspect_ratio": { "type": "string", "enum": ["16:9", "9:16"] },
+                    "generate_audio": { "type": "boolean" },
+                    "negative_prompt": { "type": "string" },
+                    "seed": { "type": "integer" }
+                }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "n": { "type": "integer", "minimum": 1, "maximum": 4 },
+                "aspect_ratio": {
+                    "type": "string",
+                    "enum": ["1:1", "3:4", "4:3", "9:16", "16:9"]
+                },
+                "person_generation": {
+                    "type": "string",
+                    "enum": ["dont_allow", "allow_adult", "allow_all"]
+                },
+                "negative_prompt": { "type": "string" },
+                "seed": { "type": "integer" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "Credentials (JSON)",
+                    "description": "Service account key or application-default credentials \
+                                    file; add \"location\" to use a region other than \
+                                    us-central1"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Full API root including project and location, e.g. a \
+                                    Private Service Connect endpoint"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_credentials_and_routing() {
+        let user = json!({
+            "type": "authorized_user",
+            "client_id": "id",
+            "client_secret": "secret",
+            "refresh_token": "token",
+            "quota_project_id": "film-studio",
+        });
+        let parsed = parse_credentials(&user.to_string()).unwrap();
+        assert_eq!(parsed.project_id.as_deref(), Some("film-studio"));
+        assert!(matches!(parsed.credentials, Credentials::AuthorizedUser { .. }));
+
+        let key = json!({ "type": "service_account", "project_id": "p" });
+        assert!(parse_credentials(&key.to_string()).is_err());
+        assert!(parse_credentials("AIzaSy-plain-api-key").is_err());
+
+        assert_eq!(
+            api_root("film-studio", "europe-west4"),
+            "https://europe-west4-aiplatform.googleapis.com/v1/projects/film-studio/\
+             locations/europe-west4"
+        );
+        assert!(api_root("p", "global").starts_with("https://aiplatform.googleapis.com/"));
+
+        let body = veo_body("veo-2.0-generate-001", "waves", &json!({}));
+        assert_eq!(body["parameters"]["durationSeconds"], 8);
+        assert!(body["parameters"].get("generateAudio").is_none());
+    }
+}
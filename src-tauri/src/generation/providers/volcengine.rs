@@ -0,0 +1,568 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use super::super::{
+    report_progress, Artifact, GenerationProvider, GenerationRequest, GenerationResult,
+    ModelInfo, ProgressSender,
+};
+use crate::generation::defaults::ModelParams;
+use crate::generation::errors::GenerationError;
+use crate::generation::power;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::{
+    api_error, check_connection, endpoint_url, extract_reference_image, send_with_retry,
+};
+
+/// Volcano Engine (Ark) configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolcengineConfig {
+    /// An Ark API key, or an access key as `ACCESS_KEY_ID:SECRET_ACCESS_KEY` to sign
+    /// requests with instead
+    pub api_key: String,
+    /// API root replacing the public one, e.g. BytePlus ModelArk outside China
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Connect and read timeouts of the HTTP client
+    #[serde(default)]
+    pub timeouts: ProviderTimeouts,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// API root used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://ark.cn-beijing.volces.com/api/v3";
+
+/// Service name in the credential scope of signed requests
+const SIGNING_SERVICE: &str = "ark";
+
+const MODELS: &[(&str, &str)] = &[
+    ("doubao-seedream-4-0-250828", "Seedream 4.0"),
+    ("doubao-seedream-3-0-t2i-250415", "Seedream 3.0"),
+    ("doubao-seedance-1-0-pro-250528", "Seedance 1.0 Pro"),
+    ("doubao-seedance-1-0-lite-t2v-250428", "Seedance 1.0 Lite (text to video)"),
+    ("doubao-seedance-1-0-lite-i2v-250428", "Seedance 1.0 Lite (image to video)"),
+];
+
+impl VolcengineConfig {
+    fn url(&self, path: &str) -> String {
+        endpoint_url(self.base_url.as_deref(), DEFAULT_BASE_URL, path)
+    }
+}
+
+/// How requests are authorized
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    ApiKey(String),
+    /// Volcano Engine access key, used to sign each request
+    AccessKey { id: String, secret: String },
+}
+
+impl Credentials {
+    pub fn parse(api_key: &str) -> Self {
+        match api_key.split_once(':') {
+            Some((id, secret)) if !id.is_empty() && !secret.is_empty() => Credentials::AccessKey {
+                id: id.trim().to_string(),
+                secret: secret.trim().to_string(),
+            },
+            _ => Credentials::ApiKey(api_key.trim().to_string()),
+        }
+    }
+}
+
+/// Region of an Ark host, e.g. `cn-beijing` for `ark.cn-beijing.volces.com`
+fn region_of(host: &str) -> &str {
+    host.split('.').nth(1).unwrap_or("cn-beijing")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Headers of a request signed with Volcano Engine's HMAC-SHA256 scheme
+///
+/// The scheme follows AWS Signature Version 4, with its own algorithm name, `X-Date`
+/// and `X-Content-Sha256` headers and a key derived without the `AWS4` prefix.
+#[allow(clippy::too_many_arguments)]
+pub fn sign(
+    access_key_id: &str,
+    secret: &str,
+    method: &str,
+    host: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let x_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let region = region_of(host);
+    let scope = format!("{}/{}/{}/request", date, region, SIGNING_SERVICE);
+    let payload_hash = hex(&Sha256::digest(body));
+    let signed_headers = "content-type;host;x-content-sha256;x-date";
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\ncontent-type:application/json\nhost:{}\nx-content-sha256:{}\n\
+         x-date:{}\n\n{}\n{}",
+        method, path, query, host, payload_hash, x_date, signed_headers, payload_hash
+    );
+    let string_to_sign = format!(
+        "HMAC-SHA256\n{}\n{}\n{}",
+        x_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let k_date = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, SIGNING_SERVICE.as_bytes());
+    let key = hmac_sha256(&k_service, b"request");
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    vec![
+        ("X-Date", x_date),
+        ("X-Content-Sha256", payload_hash),
+        (
+            "Authorization",
+            format!(
+                "HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                access_key_id, scope, signed_headers, signature
+            ),
+        ),
+    ]
+}
+
+/// Request body of a Seedream image generation
+///
+/// Outputs are requested as base64, since the URLs the API returns expire within a day.
+pub fn image_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("volcengine", model, params);
+    let mut body = serde_json::json!({
+        "model": model,
+        "prompt": prompt,
+        "size": values.str("size"),
+        "watermark": values.bool("watermark"),
+        "response_format": "b64_json",
+    });
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        body["seed"] = serde_json::json!(seed);
+    }
+    if let Some(guidance) = params.get("guidance_scale").and_then(|v| v.as_f64()) {
+        body["guidance_scale"] = serde_json::json!(guidance);
+    }
+    // Seedream 4.0 edits the reference image instead of generating from scratch
+    if let Some((mime_type, data)) = extract_reference_image(params) {
+        body["image"] = serde_json::json!(format!("data:{};base64,{}", mime_type, data));
+    }
+    body
+}
+
+/// Request body of a Seedance video task
+///
+/// Seedance takes its settings as `--flag value` commands after the prompt text, and a
+/// reference image as the first frame.
+pub fn video_body(model: &str, prompt: &str, params: &Value) -> Value {
+    let values = ModelParams::new("volcengine", model, params);
+    let mut text = format!(
+        "{} --resolution {} --duration {} --watermark {}",
+        prompt,
+        values.str("resolution"),
+        values.u64("duration"),
+        values.bool("watermark")
+    );
+    let image = extract_reference_image(params);
+    // Image-to-video follows the image's aspect ratio
+    if image.is_none() {
+        text.push_str(&format!(" --ratio {}", values.str("ratio")));
+    }
+    if let Some(seed) = params.get("seed").and_then(|v| v.as_i64()).filter(|s| *s >= 0) {
+        text.push_str(&format!(" --seed {}", seed));
+    }
+    if let Some(fixed) = params.get("camera_fixed").and_then(|v| v.as_bool()) {
+        text.push_str(&format!(" --camerafixed {}", fixed));
+    }
+
+    let mut content = vec![serde_json::json!({ "type": "text", "text": text })];
+    if let Some((mime_type, data)) = image {
+        let url = format!("data:{};base64,{}", mime_type, data);
+        content.push(serde_json::json!({ "type": "image_url", "image_url": { "url": url } }));
+    }
+    serde_json::json!({ "model": model, "content": content })
+}
+
+/// Volcano Engine provider (ByteDance Seedream images and Seedance videos through Ark)
+pub struct VolcengineProvider {
+    config: Option<VolcengineConfig>,
+    client: reqwest::Client,
+}
+
+impl VolcengineProvider {
+    pub fn new() -> Self {
+        Self {
+            config: None,
+            client: ProviderTimeouts::for_provider("volcengine").client(),
+        }
+    }
+
+    pub fn with_config(config: VolcengineConfig) -> Self {
+        Self {
+            client: config.timeouts.client_with_headers(&config.headers),
+            config: Some(config),
+        }
+    }
+
+    /// A request to the Ark API, authorized with the configured credentials
+    fn request(
+        &self,
+        config: &VolcengineConfig,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<reqwest::RequestBuilder> {
+        let url = reqwest::Url::parse(&config.url(path))?;
+        let body = match body {
+            Some(body) => serde_json::to_vec(body)?,
+            None => Vec::new(),
+        };
+
+        let mut request = self
+            .client
+            .request(method.clone(), url.clone())
+            .header("Content-Type", "application/json");
+        match Credentials::parse(&config.api_key) {
+            Credentials::ApiKey(key) => {
+                request = request.header("Authorization", format!("Bearer {}", key));
+            }
+            Credentials::AccessKey { id, secret } => {
+                let host = url.host_str().unwrap_or_default();
+                let query = url.query().unwrap_or_default();
+                let headers =
+                    sign(&id, &secret, method.as_str(), host, url.path(), query, &body, Utc::now());
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+            }
+        }
+        if !body.is_empty() {
+            request = request.body(body);
+        }
+        Ok(request)
+    }
+
+    async fn generate_image(
+        &self,
+        config: &VolcengineConfig,
+        request: &GenerationRequest,
+    ) -> Result<GenerationResult> {
+        let body = image_body(&request.model, &request.prompt, &request.parameters);
+        let path = "images/generations";
+        let submit = self.request(config, reqwest::Method::POST, path, Some(&body))?;
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Volcano Engine API", response).await);
+        }
+        let mut response_data: Value = response.json().await?;
+
+        let artifacts = response_data
+            .get("data")
+            .and_then(|d| d.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let data = item.get("b64_json")?.as_str()?;
+                Some(Artifact::from_data(data.to_string()).with_mime_type("image/jpeg"))
+            })
+            .collect::<Vec<_>>();
+        if artifacts.is_empty() {
+            return Err(anyhow::anyhow!("No images in Volcano Engine response"));
+        }
+
+        // The images now live in the artifacts; keep the metadata small
+        if let Some(items) = response_data.get_mut("data").and_then(|d| d.as_array_mut()) {
+            for item in items.iter_mut().filter_map(|item| item.as_object_mut()) {
+                item.remove("b64_json");
+            }
+        }
+        Ok(GenerationResult::new(artifacts, response_data))
+    }
+
+    /// Create a video task, poll it until it finishes and download the video
+    async fn generate_video(
+        &self,
+        config: &VolcengineConfig,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        if request.model.contains("i2v") && extract_reference_image(&request.parameters).is_none() {
+            return Err(GenerationError::invalid_param(
+                "reference_image",
+                format!("{} needs a first frame image", request.model),
+            )
+            .into());
+        }
+
+        let body = video_body(&request.model, &request.prompt, &request.parameters);
+        let path = "contents/generations/tasks";
+        let submit = self.request(config, reqwest::Method::POST, path, Some(&body))?;
+        let response = send_with_retry(submit).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Volcano Engine API", response).await);
+        }
+        let started: Value = response.json().await?;
+        let task_id = started
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No task ID in Volcano Engine response"))?;
+
+        report_progress(progress, 0.0, "Seedance task queued");
+        let task = self.wait_for_completion(config, task_id, progress).await?;
+
+        // Video URLs expire after a day, so the video is kept locally
+        let url = task
+            .pointer("/content/video_url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No video in Volcano Engine response"))?;
+        report_progress(progress, 100.0, "Downloading video");
+        let response = send_with_retry(self.client.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(api_error("Volcano Engine download", response).await);
+        }
+        let video = response.bytes().await?;
+        let artifact = Artifact::from_data(general_purpose::STANDARD.encode(&video))
+            .with_mime_type("video/mp4");
+
+        Ok(GenerationResult::new(vec![artifact], task))
+    }
+
+    /// Poll a video task until it succeeds, returning it
+    async fn wait_for_completion(
+        &self,
+        config: &VolcengineConfig,
+        task_id: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Value> {
+        let mut delay_ms = 3000u64;
+        let max_delay_ms = 10000u64;
+        let max_attempts = 200; // ~30 minutes max wait time
+        let path = format!("contents/generations/tasks/{}", task_id);
+        let mut wakes = power::wakes();
+
+        for attempt in 0..max_attempts {
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+            power::settle().await;
+
+            let request = self.request(config, reqwest::Method::GET, &path, None)?;
+            let response = match send_with_retry(request).await {
+                Ok(response) => response,
+                // The task kept running while the machine slept; check it again
+                Err(e) if power::wakes() != wakes => {
+                    log_warn!("Volcano Engine poll failed after wake, retrying: {}", e);
+                    wakes = power::wakes();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            wakes = power::wakes();
+            if !response.status().is_success() {
+                return Err(api_error("Volcano Engine task", response).await);
+            }
+            let task: Value = response.json().await?;
+
+            match task.get("status").and_then(|v| v.as_str()) {
+                Some("succeeded") => return Ok(task),
+                Some("failed" | "cancelled") => {
+                    let error = task
+                        .pointer("/error/message")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown error");
+                    return Err(anyhow::anyhow!("Seedance generation failed: {}", error));
+                }
+                Some("queued") => report_progress(progress, 0.0, "Queued at Volcano Engine"),
+                _ => report_progress(progress, 0.0, "Generating at Volcano Engine"),
+            }
+
+            delay_ms = std::cmp::min(delay_ms + 1000, max_delay_ms);
+            if attempt % 20 == 0 {
+                log_debug!("Seedance generation in progress... (attempt {})", attempt);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Seedance generation timed out after {} attempts",
+            max_attempts
+        ))
+    }
+
+    async fn generate_any(
+        &self,
+        request: &GenerationRequest,
+        progress: Option<&ProgressSender>,
+    ) -> Result<GenerationResult> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Volcano Engine"))?;
+        if request.model.starts_with("doubao-seedream") {
+            self.generate_image(config, request).await
+        } else if request.model.starts_with("doubao-seedance") {
+            self.generate_video(config, request, progress).await
+        } else {
+            Err(GenerationError::invalid_param(
+                "model",
+                format!(
+                    "Unsupported Volcano Engine model: {}. Use a Seedream or Seedance model.",
+                    request.model
+                ),
+            )
+            .into())
+        }
+    }
+}
+
+#[async_trait]
+impl GenerationProvider for VolcengineProvider {
+    fn name(&self) -> &str {
+        "volcengine"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.config.is_some()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        // Ark lists models only in its management API; offer the Seed models
+        Ok(ModelInfo::static_list(MODELS))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| GenerationError::missing_key("Volcano Engine"))?;
+        let request = self.request(
+            config,
+            reqwest::Method::GET,
+            "contents/generations/tasks?page_size=1",
+            None,
+        )?;
+        check_connection("Volcano Engine API", request).await
+    }
+
+    async fn generate(&self, request: GenerationRequest) -> Result<GenerationResult> {
+        self.generate_any(&request, None).await
+    }
+
+    async fn generate_with_progress(
+        &self,
+        request: GenerationRequest,
+        progress: ProgressSender,
+    ) -> Result<GenerationResult> {
+        self.generate_any(&request, Some(&progress)).await
+    }
+
+    fn parameter_schema(&self, model: &str) -> serde_json::Value {
+        if model.starts_with("doubao-seedance") {
+            return serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "resolution": { "type": "string", "enum": ["480p", "720p", "1080p"] },
+                    "duration": { "type": "integer", "minimum": 3, "maximum": 12 },
+                    "ratio": {
+                        "type": "string",
+                        "enum": ["16:9", "4:3", "1:1", "3:4", "9:16", "21:9", "adaptive"]
+                    },
+                    "seed": { "type": "integer" },
+                    "camera_fixed": { "type": "boolean" },
+                    "watermark": { "type": "boolean" }
+                }
+            });
+        }
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "size": { "type": "string" },
+                "seed": { "type": "integer" },
+                "guidance_scale": { "type": "number", "minimum": 1, "maximum": 10 },
+                "watermark": { "type": "boolean" }
+            }
+        })
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "api_key": {
+                    "type": "string",
+                    "title": "API Key",
+                    "description":
+                        "Ark API key, or ACCESS_KEY_ID:SECRET_ACCESS_KEY to sign requests"
+                },
+                "base_url": {
+                    "type": "string",
+                    "title": "Base URL (optional)",
+                    "description": "Regional endpoint, e.g. BytePlus ModelArk, or a gateway"
+                }
+            },
+            "required": ["api_key"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_signing_and_bodies() {
+        assert_eq!(Credentials::parse("abc"), Credentials::ApiKey("abc".to_string()));
+        assert_eq!(
+            Credentials::parse("AKLT1:c2VjcmV0"),
+            Credentials::AccessKey {
+                id: "AKLT1".to_string(),
+                secret: "c2VjcmV0".to_string()
+            }
+        );
+
+        let now = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let host = "ark.cn-beijing.volces.com";
+        let path = "/api/v3/images/generations";
+        let headers = sign("AKLT1", "secret", "POST", host, path, "", b"{}", now);
+        assert_eq!(headers[0], ("X-Date", "20260102T030405Z".to_string()));
+        let authorization = &headers[2].1;
+        assert!(authorization.starts_with(
+            "HMAC-SHA256 Credential=AKLT1/20260102/cn-beijing/ark/request, \
+             SignedHeaders=content-type;host;x-content-sha256;x-date, Signature="
+        ));
+        // Deterministic for the same request and time
+        let again = sign("AKLT1", "secret", "POST", host, path, "", b"{}", now);
+        assert_eq!(again[2].1, *authorization);
+
+        let image = image_body("doubao-seedream-4-0-250828", "a fox", &json!({ "seed": 3 }));
+        assert_eq!(image["size"], "2K");
+        assert_eq!(image["seed"], 3);
+        assert_eq!(image["response_format"], "b64_json");
+
+        let params = json!({ "duration": 10 });
+        let video = video_body("doubao-seedance-1-0-pro-250528", "waves", &params);
+        assert_eq!(
+            video["content"][0]["text"],
+            "waves --resolution 1080p --duration 10 --watermark false --ratio 16:9"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Error returned by providers when the API answers 429 Too Many Requests
+#[derive(Debug)]
+pub struct RateLimitedError {
+    pub message: String,
+    /// Delay requested by the `Retry-After` header, if present
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RateLimitedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RateLimitedError {}
+
+/// Token bucket allowing `requests_per_minute` with bursts up to the same amount
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = requests_per_minute.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: now,
+        }
+    }
+
+    /// Take a token, returning how long the caller must wait before using it
+    ///
+    /// Tokens may go negative so concurrent callers queue up behind each other
+    /// instead of all waking at the same instant.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.refill_per_sec)
+        }
+    }
+}
+
+/// Per-provider request rate limiter
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the requests-per-minute limit for a provider, or remove it with `None`
+    pub fn set_limit(&self, provider: &str, requests_per_minute: Option<u32>) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        match requests_per_minute {
+            Some(rpm) if rpm > 0 => {
+                buckets.insert(provider.to_string(), TokenBucket::new(rpm, Instant::now()));
+            }
+            _ => {
+                buckets.remove(provider);
+            }
+        }
+    }
+
+    /// Current limits in requests per minute, keyed by provider
+    pub fn limits(&self) -> HashMap<String, u32> {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets
+            .iter()
+            .map(|(name, bucket)| (name.clone(), bucket.capacity as u32))
+            .collect()
+    }
+
+    /// Wait until the provider's bucket allows another request
+    pub async fn acquire(&self, provider: &str) {
+        let wait = {
+            let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+            match buckets.get_mut(provider) {
+                Some(bucket) => bucket.reserve(Instant::now()),
+                None => Duration::ZERO,
+            }
+        };
+
+        if !wait.is_zero() {
+            log_warn!("Rate limit reached for {}, waiting {:.1}s", provider, wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_reserve() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(60, start);
+
+        // Full bucket allows a burst of 60 requests
+        for _ in 0..60 {
+            assert_eq!(bucket.reserve(start), Duration::ZERO);
+        }
+
+        // The next two queue up one second apart
+        assert_eq!(bucket.reserve(start), Duration::from_secs(1));
+        assert_eq!(bucket.reserve(start), Duration::from_secs(2));
+
+        // Refill catches up after waiting
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+    }
+}
@@ -0,0 +1,270 @@
+//! Seed-locked refinement: iterating on a prompt while everything else stays fixed.
+//!
+//! A session starts from a chosen result and locks its seed, provider, model and
+//! parameters. Each follow-up only changes the prompt and is queued as the session's next
+//! step, with the phrases added and removed relative to the session's starting prompt.
+//! When a step looks better (or worse) than an earlier one, bisecting queues a prompt
+//! with half of the phrase changes between them, narrowing down which wording made the
+//! difference.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use super::confirmation::{ConfirmationSettings, CONFIRMATION_SETTINGS_KEY, NEEDS_CONFIRMATION};
+use super::{policy, processor};
+use crate::db::models::{CreateJobInput, Job, RefineSession};
+use crate::db::operations::{JobOps, RefineOps, SettingsOps};
+
+/// Phrases a prompt gained and lost, compared case-insensitively
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PromptDelta {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// A generation queued in a session
+#[derive(Debug, Clone, Serialize)]
+pub struct RefineStep {
+    pub step: u64,
+    pub prompt: String,
+    /// Change from the session's starting prompt
+    pub delta: PromptDelta,
+    /// Steps this one bisects, if it was queued by [`bisect`]
+    pub bisects: Option<(u64, u64)>,
+    pub job: Job,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RefineSessionDetail {
+    pub session: RefineSession,
+    pub steps: Vec<RefineStep>,
+}
+
+/// Comma-separated phrases of a prompt, trimmed and without empty ones
+fn phrases(prompt: &str) -> Vec<String> {
+    prompt
+        .split(',')
+        .map(str::trim)
+        .filter(|phrase| !phrase.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn contains(phrases: &[String], phrase: &str) -> bool {
+    phrases.iter().any(|p| p.eq_ignore_ascii_case(phrase))
+}
+
+/// Phrases added and removed going from `from` to `to`
+pub fn delta(from: &str, to: &str) -> PromptDelta {
+    let (from, to) = (phrases(from), phrases(to));
+    PromptDelta {
+        added: to.iter().filter(|p| !contains(&from, p)).cloned().collect(),
+        removed: from.iter().filter(|p| !contains(&to, p)).cloned().collect(),
+    }
+}
+
+/// `from` with the first half of the changes towards `to` applied, removals first
+///
+/// Fails when the prompts differ by fewer than two changes, as there is nothing left to
+/// split.
+pub fn midpoint(from: &str, to: &str) -> Result<String> {
+    let change = delta(from, to);
+    let total = change.removed.len() + change.added.len();
+    if total < 2 {
+        return Err(anyhow::anyhow!(
+            "The prompts differ by {} phrase change(s); there is nothing to bisect",
+            total
+        ));
+    }
+
+    let half = total.div_ceil(2);
+    let removing = &change.removed[..half.min(change.removed.len())];
+    let adding = &change.added[..half - removing.len()];
+    let mut result: Vec<String> = phrases(from)
+        .into_iter()
+        .filter(|phrase| !contains(removing, phrase))
+        .collect();
+    result.extend(adding.iter().cloned());
+    Ok(result.join(", "))
+}
+
+/// The seed a result was generated with, as reported by the provider or as requested
+pub fn result_seed(job: &Job) -> Option<i64> {
+    let reported = job
+        .result
+        .as_deref()
+        .and_then(|result| serde_json::from_str::<Value>(result).ok())
+        .and_then(|result| {
+            let artifacts = result.get("artifacts")?.as_array()?.clone();
+            artifacts.iter().find_map(|artifact| artifact.get("seed")?.as_i64())
+        });
+    let requested = serde_json::from_str::<Value>(&job.data)
+        .ok()
+        .and_then(|data| data.pointer("/parameters/seed")?.as_i64());
+    reported.or(requested).filter(|seed| *seed >= 0)
+}
+
+/// Start a session from a completed generation, locking its seed
+pub async fn start(pool: &SqlitePool, job_id: &str) -> Result<RefineSession> {
+    let job = JobOps::get(pool, job_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Job not found: {}", job_id))?;
+    if job.job_type != "generation" || job.status != "completed" {
+        return Err(anyhow::anyhow!("Only completed generations can be refined"));
+    }
+    let seed = result_seed(&job).ok_or_else(|| {
+        anyhow::anyhow!("Job {} has no known seed; its provider does not report one", job_id)
+    })?;
+
+    let data: Value = serde_json::from_str(&job.data)?;
+    let field = |key: &str| data.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let mut parameters = match data.get("parameters") {
+        Some(Value::Object(parameters)) => Value::Object(parameters.clone()),
+        _ => serde_json::json!({}),
+    };
+    parameters["seed"] = serde_json::json!(seed);
+
+    let session = RefineOps::create(
+        pool,
+        &job,
+        field("provider"),
+        field("model"),
+        seed,
+        field("prompt"),
+        &parameters,
+    )
+    .await?;
+    log_info!("[Refine] Started session {} from job {} (seed {})", session.id, job_id, seed);
+    Ok(session)
+}
+
+async fn session(pool: &SqlitePool, session_id: &str) -> Result<RefineSession> {
+    RefineOps::get(pool, session_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Refine session not found: {}", session_id))
+}
+
+/// Queue the next step of a session with a new prompt
+pub async fn refine(pool: &SqlitePool, session_id: &str, prompt: &str) -> Result<RefineStep> {
+    let session = session(pool, session_id).await?;
+    queue_step(pool, &session, prompt, None).await
+}
+
+/// Queue a step halfway between the prompts of two earlier steps (0 is the start)
+pub async fn bisect(
+    pool: &SqlitePool,
+    session_id: &str,
+    from_step: u64,
+    to_step: u64,
+) -> Result<RefineStep> {
+    let detail = get(pool, session_id).await?;
+    let prompt_of = |step: u64| {
+        if step == 0 {
+            return Ok(detail.session.prompt.clone());
+        }
+        detail
+            .steps
+            .iter()
+            .find(|s| s.step == step)
+            .map(|s| s.prompt.clone())
+            .ok_or_else(|| anyhow::anyhow!("Session has no step {}", step))
+    };
+    let prompt = midpoint(&prompt_of(from_step)?, &prompt_of(to_step)?)?;
+    queue_step(pool, &detail.session, &prompt, Some((from_step, to_step))).await
+}
+
+async fn queue_step(
+    pool: &SqlitePool,
+    session: &RefineSession,
+    prompt: &str,
+    bisects: Option<(u64, u64)>,
+) -> Result<RefineStep> {
+    if prompt.trim().is_empty() {
+        return Err(anyhow::anyhow!("A prompt is required"));
+    }
+    policy::check_provider(pool, &session.workflow_id, &session.provider).await?;
+
+    let parameters: Value = serde_json::from_str(&session.parameters)?;
+    let confirmation: ConfirmationSettings =
+        SettingsOps::get_or_default(pool, CONFIRMATION_SETTINGS_KEY).await?;
+    let (provider, model) = (&session.provider, &session.model);
+    let status = if confirmation.requires_confirmation(provider, model, &parameters) {
+        NEEDS_CONFIRMATION
+    } else {
+        "pending"
+    };
+
+    let step = RefineOps::steps(pool, &session.id).await?.len() as u64 + 1;
+    let delta = delta(&session.prompt, prompt);
+    let data = serde_json::json!({
+        "provider": session.provider,
+        "prompt": prompt,
+        "model": session.model,
+        "parameters": parameters,
+        "refine": {
+            "session_id": session.id,
+            "step": step,
+            "delta": delta,
+            "bisects": bisects,
+        },
+    });
+    let input = CreateJobInput {
+        workflow_id: session.workflow_id.clone(),
+        scene_id: None,
+        job_type: "generation".to_string(),
+        data,
+        scheduled_at: None,
+        lane: Some(processor::default_lane(&session.model).to_string()),
+    };
+    let job = JobOps::create_with_status(pool, input, status).await?;
+
+    Ok(RefineStep {
+        step,
+        prompt: prompt.to_string(),
+        delta,
+        bisects,
+        job,
+    })
+}
+
+/// A session and its steps in order
+pub async fn get(pool: &SqlitePool, session_id: &str) -> Result<RefineSessionDetail> {
+    let session = session(pool, session_id).await?;
+    let steps = RefineOps::steps(pool, session_id)
+        .await?
+        .into_iter()
+        .filter_map(|job| {
+            let data: Value = serde_json::from_str(&job.data).ok()?;
+            let prompt = data.get("prompt")?.as_str()?.to_string();
+            let refine = data.get("refine")?;
+            Some(RefineStep {
+                step: refine.get("step")?.as_u64()?,
+                delta: delta(&session.prompt, &prompt),
+                bisects: serde_json::from_value(refine["bisects"].clone()).ok().flatten(),
+                prompt,
+                job,
+            })
+        })
+        .collect();
+    Ok(RefineSessionDetail { session, steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_and_midpoint() {
+        let from = "a red fox, snow, dawn light";
+        let to = "a red fox, Snow, golden hour, film grain, bokeh";
+        let change = delta(from, to);
+        assert_eq!(change.removed, vec!["dawn light"]);
+        assert_eq!(change.added, vec!["golden hour", "film grain", "bokeh"]);
+
+        // Four changes: the removal and the first added phrase
+        assert_eq!(midpoint(from, to).unwrap(), "a red fox, snow, golden hour");
+        assert!(midpoint(from, "a red fox, snow").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Re-running a whole workflow against another provider and model.
+//!
+//! Each scene's latest completed generation is queued again with the same prompt and
+//! parameters but the new target, all in the batch lane and tagged with one batch ID so
+//! the results can be compared as a set. Parameters the new model would reject are left
+//! out so its defaults apply; the job data records which ones.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+use super::confirmation::{ConfirmationSettings, CONFIRMATION_SETTINGS_KEY, NEEDS_CONFIRMATION};
+use super::{policy, routing, validation, GenerationService};
+use crate::db::models::{generate_id, CreateJobInput, Job, BATCH_LANE};
+use crate::db::operations::{JobOps, SceneOps, SettingsOps};
+
+/// Jobs queued by [`rerun_workflow`]
+#[derive(Debug, Clone, Serialize)]
+pub struct RerunSummary {
+    /// Shared by the queued jobs under `rerun.batch_id` in their data
+    pub batch_id: String,
+    pub provider: String,
+    pub model: String,
+    pub jobs: Vec<Job>,
+    /// Scenes with no completed generation to re-run
+    pub skipped_scenes: Vec<String>,
+}
+
+/// `params` without the fields `schema` rejects, and the names of those fields
+pub fn retarget(params: &Value, schema: &Value) -> (Value, Vec<String>) {
+    let mut params = params.clone();
+    let rejected: Vec<String> = validation::validate(schema, &params)
+        .into_iter()
+        .map(|error| error.field)
+        .collect();
+    if let Some(object) = params.as_object_mut() {
+        for field in &rejected {
+            object.remove(field);
+        }
+    }
+    (params, rejected)
+}
+
+/// Queue each scene's latest completed generation again with a new provider and model
+pub async fn rerun_workflow(
+    pool: &SqlitePool,
+    service: &GenerationService,
+    workflow_id: &str,
+    provider: &str,
+    model: &str,
+) -> Result<RerunSummary> {
+    let target = routing::resolve(pool, service, Some(workflow_id), provider, model).await?;
+    let (provider, model) = (target.provider, target.model);
+    policy::check_provider(pool, workflow_id, &provider).await?;
+    let schema = service
+        .get_provider(&provider)
+        .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider))?
+        .parameter_schema(&model);
+    let confirmation: ConfirmationSettings =
+        SettingsOps::get_or_default(pool, CONFIRMATION_SETTINGS_KEY).await?;
+
+    let sources = JobOps::latest_completed_by_scene(pool, workflow_id).await?;
+    let covered: HashSet<&str> = sources.iter().filter_map(|job| job.scene_id.as_deref()).collect();
+    let skipped_scenes = SceneOps::list_by_workflow(pool, workflow_id)
+        .await?
+        .into_iter()
+        .map(|scene| scene.id)
+        .filter(|id| !covered.contains(id.as_str()))
+        .collect();
+
+    let batch_id = generate_id();
+    let mut jobs = Vec::with_capacity(sources.len());
+    for source in &sources {
+        let data: Value = serde_json::from_str(&source.data)?;
+        let params = data.get("parameters").cloned().unwrap_or(Value::Null);
+        let (parameters, dropped) = retarget(&params, &schema);
+
+        let status = if confirmation.requires_confirmation(&provider, &model, &parameters) {
+            NEEDS_CONFIRMATION
+        } else {
+            "pending"
+        };
+        let mut job_data = serde_json::json!({
+            "provider": provider,
+            "prompt": data.get("prompt").cloned().unwrap_or_default(),
+            "model": model,
+            "parameters": parameters,
+            "rerun": {
+                "batch_id": batch_id,
+                "source_job_id": source.id,
+                "dropped_parameters": dropped,
+            },
+        });
+        if let Some(timeout_seconds) = data.get("timeout_seconds") {
+            job_data["timeout_seconds"] = timeout_seconds.clone();
+        }
+
+        let input = CreateJobInput {
+            workflow_id: workflow_id.to_string(),
+            scene_id: source.scene_id.clone(),
+            job_type: "generation".to_string(),
+            data: job_data,
+            scheduled_at: None,
+            lane: Some(BATCH_LANE.to_string()),
+        };
+        jobs.push(JobOps::create_with_status(pool, input, status).await?);
+    }
+
+    log_info!(
+        "[Rerun] Queued {} jobs of workflow {} on {} ({})",
+        jobs.len(),
+        workflow_id,
+        provider,
+        model
+    );
+    Ok(RerunSummary {
+        batch_id,
+        provider,
+        model,
+        jobs,
+        skipped_scenes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_retarget_drops_rejected_parameters() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "size": { "type": "string", "enum": ["1024x1024", "1536x1024"] },
+                "n": { "type": "integer", "minimum": 1, "maximum": 4 }
+            }
+        });
+        let params = json!({ "size": "512x768", "n": 2, "cfg_scale": 7 });
+        let (params, dropped) = retarget(&params, &schema);
+        assert_eq!(params, json!({ "n": 2, "cfg_scale": 7 }));
+        assert_eq!(dropped, vec!["size"]);
+    }
+}
@@ -0,0 +1,176 @@
+//! The `auto` provider: picking a configured provider for what a request asks for.
+//!
+//! A request to `auto` names a modality (`text`, `image`, `video`, `audio`) or a model family
+//! (`veo`, `sora`, `claude`, ...) as its model. It is routed to the first configured
+//! provider offering it, in the user's preference order and then the order of
+//! [`ROUTES`], skipping providers the workflow does not allow. Routing happens when the
+//! job is queued, so the job records the provider and model that actually ran.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::fallback::FallbackTarget;
+use super::providers::{groq, leonardo};
+use super::GenerationService;
+use crate::db::operations::WorkflowOps;
+
+/// Provider name that asks for routing
+pub const AUTO_PROVIDER: &str = "auto";
+
+/// Settings key for the routing preference order
+pub const ROUTING_SETTINGS_KEY: &str = "provider_routing";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Image,
+    Video,
+    /// Speech and music
+    Audio,
+}
+
+impl Modality {
+    fn parse(hint: &str) -> Option<Self> {
+        match hint {
+            "text" | "chat" => Some(Self::Text),
+            "image" | "images" => Some(Self::Image),
+            "video" | "videos" => Some(Self::Video),
+            "audio" | "speech" | "music" => Some(Self::Audio),
+            _ => None,
+        }
+    }
+}
+
+/// A provider's offering for one model family, with the model requested from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub provider: &'static str,
+    pub model: &'static str,
+    pub family: &'static str,
+    pub modality: Modality,
+}
+
+const fn route(
+    provider: &'static str,
+    model: &'static str,
+    family: &'static str,
+    modality: Modality,
+) -> Route {
+    Route {
+        provider,
+        model,
+        family,
+        modality,
+    }
+}
+
+/// What each provider offers, in the default order of preference
+pub const ROUTES: &[Route] = &[
+    route("anthropic", "claude-sonnet-4-5", "claude", Modality::Text),
+    route("groq", groq::DEFAULT_MODEL, "llama", Modality::Text),
+    route("perplexity", "sonar", "sonar", Modality::Text),
+    route("openai", "gpt-image-1", "gpt-image", Modality::Image),
+    route("google", "gemini-2.5-flash-image", "gemini", Modality::Image),
+    route("grok", "grok-2-image", "grok", Modality::Image),
+    route("flux", "flux-pro-1.1", "flux", Modality::Image),
+    route("fal", "fal-ai/flux/dev", "flux", Modality::Image),
+    route("recraft", "recraftv3", "recraft", Modality::Image),
+    route("leonardo", leonardo::PHOENIX_MODEL_ID, "phoenix", Modality::Image),
+    route("vertex", "imagen-4.0-generate-001", "imagen", Modality::Image),
+    route("google", "veo-3.1-generate-preview", "veo", Modality::Video),
+    route("openai", "sora-2", "sora", Modality::Video),
+    route("fal", "fal-ai/kling-video/v2.1/standard/text-to-video", "kling", Modality::Video),
+    route("minimax", "MiniMax-Hailuo-02", "hailuo", Modality::Video),
+    route("elevenlabs", "eleven_multilingual_v2", "elevenlabs", Modality::Audio),
+    route("openai", "gpt-4o-mini-tts", "tts", Modality::Audio),
+    route("stability", "stable-audio-2.5", "stable-audio", Modality::Audio),
+    route("mock", "mock-text", "mock", Modality::Text),
+    route("mock", "mock-image", "mock", Modality::Image),
+];
+
+/// Providers to try first, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingSettings {
+    /// Provider names, most preferred first; unlisted providers follow in default order
+    pub preference: Vec<String>,
+}
+
+/// Routes matching a modality or family hint, most preferred first
+pub fn candidates(hint: &str, preference: &[String]) -> Result<Vec<&'static Route>> {
+    let hint = hint.trim().to_ascii_lowercase();
+    let modality = Modality::parse(&hint);
+    let mut routes: Vec<&Route> = ROUTES
+        .iter()
+        .filter(|route| match modality {
+            Some(modality) => route.modality == modality,
+            None => route.family == hint,
+        })
+        .collect();
+    if routes.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot route '{}': use text, image, video, audio or a model family such as \
+             veo or sora",
+            hint
+        ));
+    }
+
+    let rank = |route: &Route| {
+        preference
+            .iter()
+            .position(|p| p.eq_ignore_ascii_case(route.provider))
+            .unwrap_or(preference.len())
+    };
+    // Stable, so the table order breaks ties
+    routes.sort_by_key(|route| rank(route));
+    Ok(routes)
+}
+
+/// Provider and model to queue a request under, routing it now if it asks for `auto`
+pub async fn resolve(
+    pool: &SqlitePool,
+    service: &GenerationService,
+    workflow_id: Option<&str>,
+    provider: &str,
+    model: &str,
+) -> Result<FallbackTarget> {
+    if provider != AUTO_PROVIDER {
+        return Ok(FallbackTarget {
+            provider: provider.to_string(),
+            model: model.to_string(),
+        });
+    }
+    let allowed = match workflow_id {
+        Some(workflow_id) => WorkflowOps::allowed_providers(pool, workflow_id).await?,
+        None => None,
+    };
+    let target = service.route(model, allowed.as_deref()).await?;
+    log_info!(
+        "[Routing] Routed '{}' to {} ({})",
+        model, target.provider, target.model
+    );
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_follow_preference() {
+        let providers = |routes: Vec<&Route>| routes.iter().map(|r| r.provider).collect::<Vec<_>>();
+
+        let video = candidates("Video", &[]).unwrap();
+        assert_eq!(providers(video), vec!["google", "openai", "fal", "minimax"]);
+
+        let preference = vec!["openai".to_string()];
+        let video = candidates("video", &preference).unwrap();
+        assert_eq!(video[0].model, "sora-2");
+        assert_eq!(providers(candidates("image", &preference).unwrap())[0], "openai");
+
+        assert_eq!(candidates("veo", &preference).unwrap()[0].model, "veo-3.1-generate-preview");
+        assert!(candidates("hologram", &[]).is_err());
+    }
+}
@@ -0,0 +1,632 @@
+//! Parameter sweeps: one generation per combination of axis values, the way A1111's
+//! XYZ plot script works, but as ordinary queued jobs on any provider.
+//!
+//! Up to three axes each list values or a numeric range. Every combination becomes a
+//! generation job tagged with the sweep ID and its axis values; a final `sweep_grid` job
+//! waits for them and composes a labeled grid with the first axis as columns and the
+//! others as rows. Failed cells stay empty in the grid.
+//!
+//! Reports lay the results out by axis value with image metrics and, once a vision
+//! model has judged them, scores, so the best settings stand out.
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use super::confirmation::{ConfirmationSettings, NEEDS_CONFIRMATION};
+use super::metrics::{self, ImageMetrics};
+use super::{GenerationRequest, GenerationService};
+use crate::db::models::{generate_id, CreateJobInput, Job, BATCH_LANE};
+use crate::db::operations::JobOps;
+use crate::export::grid::{self, GridCell};
+use crate::export::resolve_asset_file;
+
+/// Job type of the job that composes a sweep's grid
+pub const SWEEP_GRID_JOB_TYPE: &str = "sweep_grid";
+
+pub const MAX_SWEEP_AXES: usize = 3;
+
+/// Most generations one sweep may queue
+pub const MAX_SWEEP_JOBS: usize = 100;
+
+/// How long the grid job waits before checking on unfinished cells again
+pub const GRID_RECHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Longest side of the copy of an image sent to the judge
+const JUDGE_IMAGE_SIZE: u32 = 1024;
+
+/// Numeric values from `start` to `end` inclusive, `step` apart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRange {
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+}
+
+/// A parameter to vary and its values, listed or as a range (or both)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepAxis {
+    pub parameter: String,
+    #[serde(default)]
+    pub values: Vec<Value>,
+    #[serde(default)]
+    pub range: Option<SweepRange>,
+}
+
+impl SweepAxis {
+    /// The listed values followed by the range's; ranges of whole numbers give integers
+    pub fn expand(&self) -> Result<Vec<Value>> {
+        let mut values = self.values.clone();
+        if let Some(range) = &self.range {
+            if range.step.is_nan() || range.step <= 0.0 || range.end < range.start {
+                return Err(anyhow::anyhow!(
+                    "Range of {} needs a positive step and an end after its start",
+                    self.parameter
+                ));
+            }
+            // Checked as a float; huge or non-finite ranges would overflow the cast
+            let steps = ((range.end - range.start) / range.step + 1e-9).floor();
+            if !steps.is_finite() || steps >= MAX_SWEEP_JOBS as f64 {
+                return Err(anyhow::anyhow!("Range of {} has too many values", self.parameter));
+            }
+            let count = steps as usize + 1;
+            let integral = [range.start, range.step].iter().all(|v| v.fract() == 0.0);
+            values.extend((0..count).map(|i| {
+                let value = range.start + i as f64 * range.step;
+                if integral {
+                    serde_json::json!(value as i64)
+                } else {
+                    // Keeps 0.1 steps from showing up as 0.30000000000000004
+                    serde_json::json!((value * 1e6).round() / 1e6)
+                }
+            }));
+        }
+        if values.is_empty() {
+            return Err(anyhow::anyhow!("Axis {} has no values", self.parameter));
+        }
+        Ok(values)
+    }
+}
+
+/// The request every cell starts from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRequest {
+    pub workflow_id: String,
+    pub provider: String,
+    pub model: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub parameters: Value,
+    #[serde(default)]
+    pub scene_id: Option<String>,
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// An axis with its expanded values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepAxisValues {
+    pub parameter: String,
+    pub values: Vec<Value>,
+}
+
+/// A generation of the sweep and the axis values it was given, in axis order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepCell {
+    pub job_id: String,
+    pub values: Vec<Value>,
+}
+
+/// Data of a `sweep_grid` job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepGridData {
+    pub sweep_id: String,
+    pub axes: Vec<SweepAxisValues>,
+    pub cells: Vec<SweepCell>,
+}
+
+/// Expanded axes and the parameters of each cell, first axis varying fastest
+#[derive(Debug, Clone)]
+pub struct SweepPlan {
+    pub axes: Vec<SweepAxisValues>,
+    pub cells: Vec<(Vec<Value>, Value)>,
+}
+
+/// Jobs queued by a sweep
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepSummary {
+    pub sweep_id: String,
+    pub jobs: Vec<Job>,
+    /// Composes the grid once the generations are done
+    pub grid_job: Job,
+}
+
+/// Expand `axes` over `base` parameters into one parameter set per combination
+pub fn plan(base: &Value, axes: &[SweepAxis]) -> Result<SweepPlan> {
+    if axes.is_empty() || axes.len() > MAX_SWEEP_AXES {
+        return Err(anyhow::anyhow!("A sweep needs 1 to {} axes", MAX_SWEEP_AXES));
+    }
+    let axes = axes
+        .iter()
+        .map(|axis| {
+            Ok(SweepAxisValues {
+                parameter: axis.parameter.clone(),
+                values: axis.expand()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let total: usize = axes.iter().map(|axis| axis.values.len()).product();
+    if total > MAX_SWEEP_JOBS {
+        return Err(anyhow::anyhow!(
+            "The sweep would queue {} generations, more than the limit of {}",
+            total,
+            MAX_SWEEP_JOBS
+        ));
+    }
+
+    let base = match base {
+        Value::Object(_) => base.clone(),
+        _ => Value::Object(Default::default()),
+    };
+    let cells = (0..total)
+        .map(|index| {
+            let mut rest = index;
+            let mut params = base.clone();
+            let values: Vec<Value> = axes
+                .iter()
+                .map(|axis| {
+                    let value = axis.values[rest % axis.values.len()].clone();
+                    rest /= axis.values.len();
+                    params[axis.parameter.as_str()] = value.clone();
+                    value
+                })
+                .collect();
+            (values, params)
+        })
+        .collect();
+    Ok(SweepPlan { axes, cells })
+}
+
+/// Queue a sweep's generations and the job that composes their grid
+pub async fn queue(
+    pool: &SqlitePool,
+    request: &SweepRequest,
+    plan: SweepPlan,
+    confirmation: &ConfirmationSettings,
+) -> Result<SweepSummary> {
+    let sweep_id = generate_id();
+    let mut jobs = Vec::with_capacity(plan.cells.len());
+    let mut cells = Vec::with_capacity(plan.cells.len());
+    for (values, parameters) in plan.cells {
+        let status =
+            if confirmation.requires_confirmation(&request.provider, &request.model, &parameters) {
+                NEEDS_CONFIRMATION
+            } else {
+                "pending"
+            };
+        let labels: serde_json::Map<String, Value> = plan
+            .axes
+            .iter()
+            .zip(&values)
+            .map(|(axis, value)| (axis.parameter.clone(), value.clone()))
+            .collect();
+        let mut data = serde_json::json!({
+            "provider": request.provider,
+            "prompt": request.prompt,
+            "model": request.model,
+            "parameters": parameters,
+            "sweep": { "sweep_id": sweep_id, "values": labels },
+        });
+        if let Some(timeout_seconds) = request.timeout_seconds {
+            data["timeout_seconds"] = serde_json::json!(timeout_seconds);
+        }
+
+        let input = cell_input(request, "generation", data);
+        let job = JobOps::create_with_status(pool, input, status).await?;
+        cells.push(SweepCell {
+            job_id: job.id.clone(),
+            values,
+        });
+        jobs.push(job);
+    }
+
+    let grid_data = SweepGridData {
+        sweep_id: sweep_id.clone(),
+        axes: plan.axes,
+        cells,
+    };
+    let data = serde_json::to_value(&grid_data)?;
+    let input = cell_input(request, SWEEP_GRID_JOB_TYPE, data);
+    let grid_job = JobOps::create_with_status(pool, input, "pending").await?;
+
+    log_info!("[Sweep] Queued sweep {} with {} generations", sweep_id, jobs.len());
+    Ok(SweepSummary {
+        sweep_id,
+        jobs,
+        grid_job,
+    })
+}
+
+fn cell_input(request: &SweepRequest, job_type: &str, data: Value) -> CreateJobInput {
+    CreateJobInput {
+        workflow_id: request.workflow_id.clone(),
+        scene_id: request.scene_id.clone(),
+        job_type: job_type.to_string(),
+        data,
+        scheduled_at: None,
+        lane: Some(BATCH_LANE.to_string()),
+    }
+}
+
+/// Caption of a cell: each axis parameter with its value
+pub fn caption(axes: &[SweepAxisValues], values: &[Value]) -> String {
+    axes.iter()
+        .zip(values)
+        .map(|(axis, value)| match value {
+            Value::String(text) => format!("{} {}", axis.parameter, text),
+            other => format!("{} {}", axis.parameter, other),
+        })
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Compose the grid of a `sweep_grid` job, or `None` while generations are unfinished
+///
+/// Cells that were retried show their latest attempt.
+pub async fn compose_grid(pool: &SqlitePool, job: &Job) -> Result<Option<Value>> {
+    let data: SweepGridData = serde_json::from_str(&job.data)?;
+    let mut cells = Vec::with_capacity(data.cells.len());
+    for cell in &data.cells {
+        let attempt = JobOps::latest_attempt(pool, &cell.job_id).await?;
+        let mut caption = caption(&data.axes, &cell.values);
+        let asset_id = match attempt {
+            Some(attempt) if attempt.status == "completed" => Some(attempt.id),
+            Some(attempt) if attempt.status != "failed" => return Ok(None),
+            _ => {
+                caption.push_str(" (failed)");
+                None
+            }
+        };
+        cells.push(GridCell { asset_id, caption });
+    }
+    if cells.iter().all(|cell| cell.asset_id.is_none()) {
+        return Err(anyhow::anyhow!("Every generation of the sweep failed"));
+    }
+
+    let columns = data.axes.first().map_or(1, |axis| axis.values.len()) as u32;
+    let output = super::images_dir()?.join(format!("sweep-{}.png", data.sweep_id));
+    let summary = grid::compose_labeled_grid(pool, &cells, columns, &output).await?;
+    let mut result = serde_json::to_value(&summary)?;
+    result["sweep_id"] = serde_json::json!(data.sweep_id);
+    Ok(Some(result))
+}
+
+/// A generation of the sweep as reported, showing its latest attempt
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepReportCell {
+    pub job_id: String,
+    pub values: Vec<Value>,
+    pub status: String,
+    pub path: Option<String>,
+    pub metrics: Option<ImageMetrics>,
+    /// Judged score from 0 to 10
+    pub score: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Averages over the cells sharing one value of an axis
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisValueSummary {
+    pub value: Value,
+    pub completed: usize,
+    pub failed: usize,
+    pub mean_sharpness: Option<f64>,
+    pub mean_contrast: Option<f64>,
+    pub mean_file_size: Option<f64>,
+    pub mean_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisReport {
+    pub parameter: String,
+    pub values: Vec<AxisValueSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepReport {
+    pub sweep_id: String,
+    pub axes: Vec<AxisReport>,
+    pub cells: Vec<SweepReportCell>,
+    /// Highest scored cell, or the sharpest while nothing is scored
+    pub best_job_id: Option<String>,
+}
+
+/// Average of the values present, `None` without any
+fn mean(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let present: Vec<f64> = values.flatten().collect();
+    (!present.is_empty()).then(|| present.iter().sum::<f64>() / present.len() as f64)
+}
+
+/// Per-axis summaries of `cells`, one entry per axis value
+pub fn summarize_axes(axes: &[SweepAxisValues], cells: &[SweepReportCell]) -> Vec<AxisReport> {
+    axes.iter()
+        .enumerate()
+        .map(|(index, axis)| AxisReport {
+            parameter: axis.parameter.clone(),
+            values: axis
+                .values
+                .iter()
+                .map(|value| {
+                    let matching: Vec<&SweepReportCell> = cells
+                        .iter()
+                        .filter(|cell| cell.values.get(index) == Some(value))
+                        .collect();
+                    let metric = |read: fn(&ImageMetrics) -> f64| {
+                        mean(matching.iter().map(|cell| cell.metrics.as_ref().map(read)))
+                    };
+                    AxisValueSummary {
+                        value: value.clone(),
+                        completed: matching.iter().filter(|c| c.status == "completed").count(),
+                        failed: matching.iter().filter(|c| c.status == "failed").count(),
+                        mean_sharpness: metric(|m| m.sharpness),
+                        mean_contrast: metric(|m| m.contrast),
+                        mean_file_size: metric(|m| m.file_size as f64),
+                        mean_score: mean(matching.iter().map(|cell| cell.score)),
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// The highest scored cell, falling back to the sharpest when none is scored
+pub fn best_cell(cells: &[SweepReportCell]) -> Option<&SweepReportCell> {
+    let by = |key: fn(&SweepReportCell) -> Option<f64>| {
+        cells
+            .iter()
+            .filter_map(|cell| Some((cell, key(cell)?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(cell, _)| cell)
+    };
+    by(|cell| cell.score).or_else(|| by(|cell| cell.metrics.as_ref().map(|m| m.sharpness)))
+}
+
+async fn grid_data(pool: &SqlitePool, sweep_id: &str) -> Result<SweepGridData> {
+    let job = JobOps::find_by_data(pool, SWEEP_GRID_JOB_TYPE, "$.sweep_id", sweep_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Sweep not found: {}", sweep_id))?;
+    Ok(serde_json::from_str(&job.data)?)
+}
+
+/// Results of a sweep by axis value, with metrics of every finished image
+pub async fn report(pool: &SqlitePool, sweep_id: &str) -> Result<SweepReport> {
+    let data = grid_data(pool, sweep_id).await?;
+    let mut cells = Vec::with_capacity(data.cells.len());
+    for cell in &data.cells {
+        let Some(job) = JobOps::latest_attempt(pool, &cell.job_id).await? else {
+            continue;
+        };
+        let job_data: Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let score = job_data.pointer("/sweep/judge/score").and_then(|v| v.as_f64());
+
+        let (mut path, mut measured, mut error) = (None, None, job.error.clone());
+        if job.status == "completed" {
+            match resolve_asset_file(pool, &job.id).await {
+                Ok((_, file)) => {
+                    path = Some(file.display().to_string());
+                    match tokio::task::spawn_blocking(move || metrics::measure(&file)).await? {
+                        Ok(result) => measured = Some(result),
+                        Err(e) => error = Some(format!("Could not measure the image: {}", e)),
+                    }
+                }
+                Err(e) => error = Some(e.to_string()),
+            }
+        }
+
+        cells.push(SweepReportCell {
+            job_id: job.id,
+            values: cell.values.clone(),
+            status: job.status,
+            path,
+            metrics: measured,
+            score,
+            error,
+        });
+    }
+
+    Ok(SweepReport {
+        sweep_id: data.sweep_id,
+        axes: summarize_axes(&data.axes, &cells),
+        best_job_id: best_cell(&cells).map(|cell| cell.job_id.clone()),
+        cells,
+    })
+}
+
+/// Have a vision model score every finished image of a sweep against its prompt
+///
+/// Scores are kept in each job's data under `sweep.judge`, so later reports include
+/// them without asking again.
+pub async fn judge(
+    pool: &SqlitePool,
+    service: &GenerationService,
+    sweep_id: &str,
+    provider: &str,
+    model: &str,
+) -> Result<SweepReport> {
+    let report = report(pool, sweep_id).await?;
+    for cell in report.cells.iter().filter(|cell| cell.status == "completed") {
+        let (Some(path), Some(job)) = (&cell.path, JobOps::get(pool, &cell.job_id).await?) else {
+            continue;
+        };
+        let mut data: Value = serde_json::from_str(&job.data)?;
+        let prompt = data.get("prompt").and_then(|v| v.as_str()).unwrap_or_default();
+
+        let image = downscaled_data_url(path.into()).await?;
+        let request = GenerationRequest {
+            prompt: format!(
+                "Rate this image from 0 to 10 for how well it matches the prompt and for its \
+                 technical quality (sharpness, anatomy, artifacts). Reply with the number \
+                 only.\n\nPrompt: {}",
+                prompt
+            ),
+            model: model.to_string(),
+            parameters: serde_json::json!({
+                "max_tokens": 10,
+                "temperature": 0.0,
+                "reference_images": [{ "data": image }],
+            }),
+        };
+        let reply = match service.generate(provider, request).await {
+            Ok(result) => result.output_data().map(str::to_string).unwrap_or_default(),
+            Err(e) => {
+                log_warn!("[Sweep] Failed to judge job {}: {}", cell.job_id, e);
+                continue;
+            }
+        };
+        let Some(score) = parse_score(&reply) else {
+            log_warn!("[Sweep] No score in the judge's reply for job {}", cell.job_id);
+            continue;
+        };
+
+        data["sweep"]["judge"] = serde_json::json!({
+            "score": score,
+            "provider": provider,
+            "model": model,
+        });
+        JobOps::update_data(pool, &job.id, &data).await?;
+    }
+    self::report(pool, sweep_id).await
+}
+
+/// First number in a reply, clamped to the 0 to 10 scale
+pub fn parse_score(reply: &str) -> Option<f64> {
+    reply
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .find_map(|token| token.trim_matches('.').parse::<f64>().ok())
+        .map(|score| score.clamp(0.0, 10.0))
+}
+
+/// An image as a JPEG data URL, scaled down to keep the judge's input small
+async fn downscaled_data_url(path: std::path::PathBuf) -> Result<String> {
+    tokio::task::spawn_blocking(move || -> Result<String> {
+        let image = image::open(&path)?
+            .resize(JUDGE_IMAGE_SIZE, JUDGE_IMAGE_SIZE, FilterType::Triangle)
+            .to_rgb8();
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut jpeg, image::ImageFormat::Jpeg)?;
+        let encoded = general_purpose::STANDARD.encode(jpeg.into_inner());
+        Ok(format!("data:image/jpeg;base64,{}", encoded))
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plan_expands_matrix() {
+        let axes = vec![
+            SweepAxis {
+                parameter: "cfg_scale".to_string(),
+                values: Vec::new(),
+                range: Some(SweepRange {
+                    start: 4.0,
+                    end: 10.0,
+                    step: 3.0,
+                }),
+            },
+            SweepAxis {
+                parameter: "sampler_name".to_string(),
+                values: vec![json!("Euler"), json!("DPM++ 2M")],
+                range: None,
+            },
+        ];
+        let matrix = plan(&json!({ "steps": 30 }), &axes).unwrap();
+        assert_eq!(matrix.axes[0].values, vec![json!(4), json!(7), json!(10)]);
+        assert_eq!(matrix.cells.len(), 6);
+        let expected = json!({ "steps": 30, "cfg_scale": 7, "sampler_name": "Euler" });
+        assert_eq!(matrix.cells[1].1, expected);
+        assert_eq!(matrix.cells[3].0, vec![json!(4), json!("DPM++ 2M")]);
+        let label = caption(&matrix.axes, &matrix.cells[3].0);
+        assert_eq!(label, "cfg_scale 4 · sampler_name DPM++ 2M");
+
+        let fractional = SweepRange {
+            start: 0.1,
+            end: 0.3,
+            step: 0.1,
+        };
+        let axis = SweepAxis {
+            parameter: "denoising_strength".to_string(),
+            values: Vec::new(),
+            range: Some(fractional),
+        };
+        assert_eq!(axis.expand().unwrap(), vec![json!(0.1), json!(0.2), json!(0.3)]);
+        for (end, step) in [(1.0, 1e-300), (f64::INFINITY, 1.0)] {
+            let axis = SweepAxis {
+                range: Some(SweepRange { start: 0.0, end, step }),
+                ..axis.clone()
+            };
+            assert!(axis.expand().is_err());
+        }
+
+        assert!(plan(&Value::Null, &vec![axes[0].clone(); 4]).is_err());
+    }
+
+    #[test]
+    fn test_report_summaries() {
+        let cell = |values: Vec<Value>, status: &str, sharpness: f64, score: Option<f64>| {
+            SweepReportCell {
+                job_id: format!("{}-{}", values[0], values[1]),
+                values,
+                status: status.to_string(),
+                path: None,
+                metrics: (status == "completed").then_some(ImageMetrics {
+                    file_size: 1000,
+                    width: 64,
+                    height: 64,
+                    sharpness,
+                    contrast: 40.0,
+                }),
+                score,
+                error: None,
+            }
+        };
+        let axes = vec![
+            SweepAxisValues {
+                parameter: "cfg_scale".to_string(),
+                values: vec![json!(4), json!(7)],
+            },
+            SweepAxisValues {
+                parameter: "steps".to_string(),
+                values: vec![json!(20), json!(30)],
+            },
+        ];
+        let mut cells = vec![
+            cell(vec![json!(4), json!(20)], "completed", 10.0, None),
+            cell(vec![json!(7), json!(20)], "completed", 30.0, None),
+            cell(vec![json!(4), json!(30)], "failed", 0.0, None),
+            cell(vec![json!(7), json!(30)], "completed", 50.0, None),
+        ];
+
+        let summary = summarize_axes(&axes, &cells);
+        assert_eq!(summary[0].values[0].completed, 1);
+        assert_eq!(summary[0].values[0].failed, 1);
+        assert_eq!(summary[0].values[1].mean_sharpness, Some(40.0));
+        assert_eq!(summary[1].values[0].mean_score, None);
+        assert_eq!(best_cell(&cells).unwrap().job_id, "7-30");
+
+        cells[1].score = Some(8.0);
+        cells[3].score = Some(6.5);
+        assert_eq!(best_cell(&cells).unwrap().job_id, "7-20");
+
+        assert_eq!(parse_score("8"), Some(8.0));
+        assert_eq!(parse_score("Score: 7.5/10."), Some(7.5));
+        assert_eq!(parse_score("no idea"), None);
+    }
+}
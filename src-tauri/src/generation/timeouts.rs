@@ -0,0 +1,125 @@
+//! Connect and read timeouts of provider HTTP clients.
+//!
+//! Generation requests can legitimately run for minutes (local diffusion, video), while
+//! connection tests should give up quickly, so each provider carries its own limits.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::db::operations::SettingsOps;
+
+/// Settings key for the timeouts of cloud providers
+pub const TIMEOUTS_SETTINGS_KEY: &str = "provider_timeouts";
+
+/// Timeouts applied when a provider's client is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderTimeouts {
+    /// Time allowed to open a connection
+    pub connect_seconds: u64,
+    /// Time allowed between reads of a response, so slow generations are fine while data flows
+    pub read_seconds: u64,
+    /// Time allowed for a whole connection test
+    pub health_check_seconds: u64,
+}
+
+impl Default for ProviderTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_seconds: 10,
+            read_seconds: 300,
+            health_check_seconds: 15,
+        }
+    }
+}
+
+impl ProviderTimeouts {
+    /// Defaults suited to a provider: local backends hold the connection while rendering
+    pub fn for_provider(provider: &str) -> Self {
+        match provider {
+            "a1111" | "comfyui" | "invokeai" => Self {
+                connect_seconds: 5,
+                read_seconds: 900,
+                health_check_seconds: 5,
+            },
+            "grok" => Self {
+                read_seconds: 120,
+                ..Self::default()
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Clamp values to at least a second so a zero cannot fail every request
+    pub fn normalized(self) -> Self {
+        Self {
+            connect_seconds: self.connect_seconds.max(1),
+            read_seconds: self.read_seconds.max(1),
+            health_check_seconds: self.health_check_seconds.max(1),
+        }
+    }
+
+    pub fn health_check(&self) -> Duration {
+        Duration::from_secs(self.health_check_seconds)
+    }
+
+    /// HTTP client honoring these timeouts
+    pub fn client(&self) -> reqwest::Client {
+        super::http::client(self, &BTreeMap::new())
+    }
+
+    /// HTTP client honoring these timeouts that adds `headers` to every request
+    pub fn client_with_headers(&self, headers: &BTreeMap<String, String>) -> reqwest::Client {
+        super::http::client(self, headers)
+    }
+}
+
+/// Timeouts keyed by cloud provider; providers without an entry use their defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutSettings {
+    pub providers: BTreeMap<String, ProviderTimeouts>,
+}
+
+pub async fn load(pool: &SqlitePool) -> Result<TimeoutSettings> {
+    SettingsOps::get_or_default(pool, TIMEOUTS_SETTINGS_KEY).await
+}
+
+/// Save or, with `None`, reset a provider's timeouts
+pub async fn save(
+    pool: &SqlitePool,
+    provider: &str,
+    timeouts: Option<ProviderTimeouts>,
+) -> Result<()> {
+    let mut settings = load(pool).await?;
+    match timeouts {
+        Some(timeouts) => {
+            settings.providers.insert(provider.to_string(), timeouts);
+        }
+        None => {
+            settings.providers.remove(provider);
+        }
+    }
+    SettingsOps::set(pool, TIMEOUTS_SETTINGS_KEY, &settings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_defaults_and_normalize() {
+        assert_eq!(ProviderTimeouts::for_provider("a1111").read_seconds, 900);
+        assert_eq!(ProviderTimeouts::for_provider("openai"), ProviderTimeouts::default());
+
+        let zero = ProviderTimeouts {
+            connect_seconds: 0,
+            read_seconds: 0,
+            health_check_seconds: 0,
+        };
+        assert_eq!(zero.normalized().read_seconds, 1);
+    }
+}
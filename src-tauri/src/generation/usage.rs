@@ -0,0 +1,128 @@
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use super::defaults::ModelParams;
+use super::{pricing, GenerationResult};
+use crate::db::models::CreateUsageRecordInput;
+use crate::db::operations::UsageOps;
+
+/// Usage figures extracted from a finished generation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageMetrics {
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub images: i64,
+    pub video_seconds: f64,
+}
+
+/// Read token counts from the provider response and derive image/video output from the request
+pub fn extract_usage(
+    provider: &str,
+    model: &str,
+    params: &Value,
+    result: &GenerationResult,
+) -> UsageMetrics {
+    let metadata = &result.metadata;
+    let token = |keys: &[(&str, &str)]| {
+        keys.iter()
+            .find_map(|(object, key)| metadata.get(*object)?.get(*key)?.as_i64())
+    };
+
+    // Anthropic and OpenAI use `usage`, Gemini uses `usageMetadata`, and chat completions
+    // (Groq, Perplexity) name them prompt and completion tokens
+    let input_tokens = token(&[
+        ("usage", "input_tokens"),
+        ("usageMetadata", "promptTokenCount"),
+        ("usage", "prompt_tokens"),
+    ]);
+    let output_tokens = token(&[
+        ("usage", "output_tokens"),
+        ("usageMetadata", "candidatesTokenCount"),
+        ("usage", "completion_tokens"),
+    ]);
+
+    let (images, video_seconds) = if matches!(provider, "anthropic" | "groq" | "perplexity") {
+        (0, 0.0)
+    } else if pricing::is_video_model(model) {
+        (0, pricing::video_duration(provider, model, params) as f64)
+    } else {
+        let outputs = result.artifacts.iter().filter(|a| !a.is_text()).count() as u64;
+        let images = metadata
+            .get("data")
+            .and_then(|d| d.as_array())
+            .map(|d| d.len() as u64)
+            .or_else(|| (outputs > 0).then_some(outputs))
+            .unwrap_or_else(|| ModelParams::new(provider, model, params).u64("n").max(1));
+        (images as i64, 0.0)
+    };
+
+    UsageMetrics {
+        input_tokens,
+        output_tokens,
+        images,
+        video_seconds,
+    }
+}
+
+/// Store a usage record for a finished generation and return its cost
+///
+/// A failed write is logged; the generation already succeeded and must still complete.
+pub async fn record_usage(
+    pool: &SqlitePool,
+    job_id: &str,
+    provider: &str,
+    model: &str,
+    params: &Value,
+    result: &GenerationResult,
+) -> Option<f64> {
+    let cost = pricing::actual_cost(provider, model, params, &result.metadata);
+    let usage = extract_usage(provider, model, params, result);
+    let recorded = UsageOps::create(
+        pool,
+        CreateUsageRecordInput {
+            job_id: job_id.to_string(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            images: usage.images,
+            video_seconds: usage.video_seconds,
+            cost,
+            api_key_id: result
+                .metadata
+                .get("api_key_id")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        },
+    )
+    .await;
+    if let Err(e) = recorded {
+        log_warn!("[Usage] Failed to record usage of job {}: {}", job_id, e);
+    }
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(metadata: Value) -> GenerationResult {
+        GenerationResult::new(Vec::new(), metadata)
+    }
+
+    #[test]
+    fn test_extract_usage() {
+        let text = result(serde_json::json!({ "usage": { "input_tokens": 12, "output_tokens": 340 } }));
+        let usage = extract_usage("anthropic", "claude-sonnet-4-5", &Value::Null, &text);
+        assert_eq!(usage.input_tokens, Some(12));
+        assert_eq!(usage.output_tokens, Some(340));
+        assert_eq!(usage.images, 0);
+
+        let images = result(serde_json::json!({ "data": [{}, {}] }));
+        assert_eq!(extract_usage("openai", "gpt-image-1", &Value::Null, &images).images, 2);
+
+        let video = extract_usage("google", "veo-3.1", &serde_json::json!({ "duration": 6 }), &result(Value::Null));
+        assert_eq!(video.video_seconds, 6.0);
+        assert_eq!(video.images, 0);
+    }
+}
@@ -1,4 +1,252 @@
 use serde_json::Value;
+use std::time::Duration;
+
+use super::rate_limit::RateLimitedError;
+use super::Artifact;
+
+/// Upper bound on how long a single `Retry-After` wait may take
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(300);
+
+/// Error for any other non-success provider response, keeping the HTTP status
+#[derive(Debug)]
+pub struct ApiStatusError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiStatusError {}
+
+/// Build an error from a non-success provider response
+///
+/// 429 responses become a [`RateLimitedError`] carrying the `Retry-After` delay so the
+/// generation service can wait and retry instead of failing the job. Everything else
+/// becomes an [`ApiStatusError`].
+///
+/// # Arguments
+/// * `label` - Human readable API name used in the message (e.g., "OpenAI API")
+/// * `response` - The failed HTTP response
+pub async fn api_error(label: &str, response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let error_text = response.text().await.unwrap_or_default();
+    let message = format!("{} error ({}): {}", label, status, error_text);
+
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        anyhow::Error::new(RateLimitedError {
+            message,
+            retry_after,
+        })
+    } else {
+        anyhow::Error::new(ApiStatusError {
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
+
+/// Send a connection test request, failing with the backend's error unless it succeeds
+pub async fn check_connection(label: &str, request: reqwest::RequestBuilder) -> anyhow::Result<()> {
+    let response = send_with_retry(request).await?;
+    if !response.status().is_success() {
+        return Err(api_error(label, response).await);
+    }
+    Ok(())
+}
+
+/// Task ID no backend hands out, looked up by [`check_authenticated`]
+pub const PLACEHOLDER_TASK_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Like [`check_connection`], for a lookup of a placeholder resource
+///
+/// Backends without an account endpoint are tested by asking for a task that does not
+/// exist. Getting past authentication to a 404 proves the key works.
+pub async fn check_authenticated(
+    label: &str,
+    request: reqwest::RequestBuilder,
+) -> anyhow::Result<()> {
+    let response = send_with_retry(request).await?;
+    let status = response.status();
+    if !status.is_success() && status != reqwest::StatusCode::NOT_FOUND {
+        return Err(api_error(label, response).await);
+    }
+    Ok(())
+}
+
+/// Outputs of an OpenAI style images response, one per `data[]` item with a `url`
+/// or `b64_json`
+pub fn image_artifacts(response: &Value) -> Vec<Artifact> {
+    let items = response.get("data").and_then(|d| d.as_array());
+    items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let field = |key: &str| item.get(key).and_then(|v| v.as_str()).map(str::to_string);
+            match (field("url"), field("b64_json")) {
+                (Some(url), _) => Some(Artifact::from_url(url)),
+                (None, Some(data)) => Some(Artifact::from_data(data)),
+                (None, None) => None,
+            }
+        })
+        .collect()
+}
+
+/// Splits a server-sent events body into the `data:` payloads of complete events
+///
+/// Bytes are kept until an event is complete, so characters split across network
+/// chunks decode correctly.
+#[derive(Debug, Default)]
+pub struct SseBuffer {
+    pending: Vec<u8>,
+}
+
+impl SseBuffer {
+    /// Add a chunk of the body, returning the payloads of the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend(chunk.iter().filter(|byte| **byte != b'\r'));
+
+        let mut payloads = Vec::new();
+        while let Some(end) = self.pending.windows(2).position(|pair| pair == b"\n\n") {
+            let event: Vec<u8> = self.pending.drain(..end + 2).collect();
+            let event = String::from_utf8_lossy(&event);
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            if !data.is_empty() {
+                payloads.push(data.join("\n"));
+            }
+        }
+        payloads
+    }
+}
+
+/// Join an API path onto the configured base URL, or `default` when none is set
+pub fn endpoint_url(base_url: Option<&str>, default: &str, path: &str) -> String {
+    let base = base_url
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(default);
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Attempts [`send_with_retry`] makes before giving up on a network failure
+const NETWORK_ATTEMPTS: u32 = 3;
+
+/// Wait before the first network retry, doubled for each further one
+const NETWORK_RETRY_DELAY: Duration = Duration::from_millis(750);
+
+/// Send a request, retrying network blips with jittered backoff
+///
+/// Failed connections (refused, DNS), connections reset mid-request and 502/503
+/// responses are retried up to [`NETWORK_ATTEMPTS`] times in total; the last response
+/// or error is returned as is. Timeouts are not retried since the provider may still
+/// be working on the request. Requests with a streamed body cannot be cloned and are
+/// sent once; see [`send_rebuilt_with_retry`] for multipart forms.
+pub async fn send_with_retry(
+    request: reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    send_attempts(request, |request| request.try_clone()).await
+}
+
+/// Like [`send_with_retry`], calling `build` for a fresh request on every attempt
+///
+/// For bodies that cannot be cloned, such as multipart forms.
+pub async fn send_rebuilt_with_retry(
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> reqwest::Result<reqwest::Response> {
+    send_attempts(build(), |_| Some(build())).await
+}
+
+/// Send attempts made by `retry` until one succeeds, then `request` as the last one
+async fn send_attempts(
+    request: reqwest::RequestBuilder,
+    retry: impl Fn(&reqwest::RequestBuilder) -> Option<reqwest::RequestBuilder>,
+) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 1;
+    loop {
+        let Some(retry) = retry(&request).filter(|_| attempt < NETWORK_ATTEMPTS) else {
+            return request.send().await;
+        };
+        let reason = match retry.send().await {
+            Ok(response) if !is_transient_status(response.status()) => return Ok(response),
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) if is_transient_error(&e) => e.to_string(),
+            Err(e) => return Err(e),
+        };
+
+        let jitter = (uuid::Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+        let delay = network_retry_delay(attempt, jitter);
+        log_warn!(
+            "[Network] Attempt {} failed ({}), retrying in {:.1}s",
+            attempt,
+            reason,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Backoff before retry `attempt` (from 1), scaled by `jitter` in 0..1 to 50-150%
+fn network_retry_delay(attempt: u32, jitter: f64) -> Duration {
+    let backoff = NETWORK_RETRY_DELAY * 2u32.pow(attempt.saturating_sub(1));
+    backoff.mul_f64(0.5 + jitter)
+}
+
+/// Gateway errors from proxies and load balancers in front of the provider
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502 | 503)
+}
+
+/// Failures to connect, and connections dropped by the network while sending
+fn is_transient_error(error: &reqwest::Error) -> bool {
+    if error.is_connect() {
+        return true;
+    }
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind;
+            return matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe
+            );
+        }
+        source = cause.source();
+    }
+    false
+}
+
+/// Parses a `Retry-After` header value (delay in seconds or an HTTP date)
+///
+/// Delays are capped at [`MAX_RATE_LIMIT_WAIT`].
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<f64>() {
+        // Rejects nan and inf; huge finite values are clamped instead
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+        let delay = Duration::try_from_secs_f64(seconds).unwrap_or(MAX_RATE_LIMIT_WAIT);
+        return Some(delay.min(MAX_RATE_LIMIT_WAIT));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO).min(MAX_RATE_LIMIT_WAIT))
+}
 
 /// Extracts base64 data and MIME type from a data URL
 ///
@@ -71,7 +319,7 @@ pub fn extract_reference_image(parameters: &Value) -> Option<(String, String)> {
     // Extract base64 and MIME type
     match extract_base64_from_data_url(data_url) {
         Ok((mime, base64)) => {
-            eprintln!(
+            log_debug!(
                 "Extracted reference image: MIME={}, size={}KB",
                 mime,
                 base64.len() / 1024
@@ -79,7 +327,7 @@ pub fn extract_reference_image(parameters: &Value) -> Option<(String, String)> {
             Some((mime, base64))
         }
         Err(e) => {
-            eprintln!("Failed to extract reference image: {}", e);
+            log_warn!("Failed to extract reference image: {}", e);
             None
         }
     }
@@ -106,14 +354,14 @@ pub fn extract_reference_images(parameters: &Value) -> Option<Vec<(String, Strin
         let data_url = match ref_img.get("data").and_then(|v| v.as_str()) {
             Some(url) => url,
             None => {
-                eprintln!("Warning: reference image {} missing 'data' field", index);
+                log_warn!("Warning: reference image {} missing 'data' field", index);
                 continue;
             }
         };
 
         match extract_base64_from_data_url(data_url) {
             Ok((mime, base64)) => {
-                eprintln!(
+                log_debug!(
                     "Extracted reference image {}: MIME={}, size={}KB",
                     index + 1,
                     mime,
@@ -122,7 +370,7 @@ pub fn extract_reference_images(parameters: &Value) -> Option<Vec<(String, Strin
                 images.push((mime, base64));
             }
             Err(e) => {
-                eprintln!("Failed to extract reference image {}: {}", index, e);
+                log_warn!("Failed to extract reference image {}: {}", index, e);
             }
         }
     }
@@ -196,6 +444,25 @@ pub fn get_reference_image_params(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sse_buffer_splits_events() {
+        let mut buffer = SseBuffer::default();
+        assert!(buffer.push(b"event: ping\ndata: {\"a\"").is_empty());
+        assert_eq!(buffer.push(b": 1}\r\n\r\ndata: x\n\n"), vec!["{\"a\": 1}", "x"]);
+        assert!(buffer.push(b"event: ping\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_url() {
+        let default = "https://api.x.ai/v1";
+        assert_eq!(endpoint_url(None, default, "models"), "https://api.x.ai/v1/models");
+        assert_eq!(
+            endpoint_url(Some("https://gw.example.com/xai/v1/"), default, "/models"),
+            "https://gw.example.com/xai/v1/models"
+        );
+        assert_eq!(endpoint_url(Some(" "), default, "models"), "https://api.x.ai/v1/models");
+    }
+
     #[test]
     fn test_extract_base64_from_data_url() {
         // Valid PNG data URL
@@ -225,6 +492,31 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_network_retry_delay() {
+        assert_eq!(network_retry_delay(1, 0.5), Duration::from_millis(750));
+        assert_eq!(network_retry_delay(2, 0.0), Duration::from_millis(750));
+        assert_eq!(network_retry_delay(2, 1.0), Duration::from_millis(2250));
+        assert!(is_transient_status(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(!is_transient_status(reqwest::StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after(" 1.5 "), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("nan"), None);
+        assert_eq!(parse_retry_after("inf"), None);
+        assert_eq!(parse_retry_after("1e30"), Some(MAX_RATE_LIMIT_WAIT));
+        // Dates in the past mean "retry now"
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
     #[test]
     fn test_extract_reference_image() {
         // Valid reference image
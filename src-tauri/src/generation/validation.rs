@@ -0,0 +1,188 @@
+//! Checking request parameters against the target model's schema before a job is queued.
+//!
+//! Providers describe their parameters with a JSON Schema subset: `type`, `enum`, `minimum`,
+//! `maximum` and `multipleOf` per property. Parameters a schema does not list pass through,
+//! since workflows also carry extras such as reference images and ADetailer blocks.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::i18n;
+
+/// A parameter that the model would reject or ignore
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Error of commands that queue generations
+///
+/// Serializes as a plain string like other command errors, or with the failing fields
+/// when parameters were rejected.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SubmitError {
+    InvalidParams {
+        message: String,
+        fields: Vec<FieldError>,
+    },
+    Other(String),
+}
+
+impl SubmitError {
+    pub fn invalid_params(fields: Vec<FieldError>) -> Self {
+        let summary = fields
+            .iter()
+            .map(|error| format!("{} {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self::InvalidParams {
+            message: i18n::t("validation.invalid_parameters", &[("fields", &summary)]),
+            fields,
+        }
+    }
+}
+
+impl From<String> for SubmitError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+/// Check `params` against `schema`, listing every field that fails
+pub fn validate(schema: &Value, params: &Value) -> Vec<FieldError> {
+    let params = match params {
+        Value::Object(params) => params,
+        Value::Null => return Vec::new(),
+        _ => {
+            return vec![FieldError {
+                field: "parameters".to_string(),
+                message: i18n::t("validation.not_object", &[]),
+            }]
+        }
+    };
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    let mut errors = Vec::new();
+    for (field, value) in params {
+        let Some(rules) = properties.get(field) else {
+            continue;
+        };
+        if let Some(message) = check(rules, value) {
+            errors.push(FieldError {
+                field: field.clone(),
+                message,
+            });
+        }
+    }
+    errors
+}
+
+/// Parameters shared by the Stable Diffusion backends
+pub fn stable_diffusion_schema() -> Value {
+    let dimension = json!({ "type": "integer", "minimum": 64, "maximum": 4096, "multipleOf": 8 });
+    json!({
+        "type": "object",
+        "properties": {
+            "negative_prompt": { "type": "string" },
+            "steps": { "type": "integer", "minimum": 1, "maximum": 150 },
+            "cfg_scale": { "type": "number", "minimum": 0, "maximum": 30 },
+            "width": dimension,
+            "height": dimension,
+            "sampler": { "type": "string" },
+            "seed": { "type": "integer", "minimum": -1 },
+        }
+    })
+}
+
+/// Why `value` breaks `rules`, if it does
+fn check(rules: &Value, value: &Value) -> Option<String> {
+    // Unset values fall back to the model's defaults
+    if value.is_null() {
+        return None;
+    }
+
+    let expected = rules.get("type").and_then(Value::as_str);
+    let type_ok = match expected {
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("number") => value.is_number(),
+        Some("string") => value.is_string(),
+        Some("boolean") => value.is_boolean(),
+        _ => true,
+    };
+    if !type_ok {
+        let expected = expected.unwrap_or_default();
+        return Some(i18n::t("validation.type", &[("type", expected)]));
+    }
+
+    if let Some(allowed) = rules.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            return Some(i18n::t("validation.one_of", &[("values", &allowed.join(", "))]));
+        }
+    }
+
+    let number = value.as_f64()?;
+    if let Some(minimum) = rules.get("minimum").and_then(Value::as_f64) {
+        if number < minimum {
+            return Some(i18n::t("validation.minimum", &[("value", &minimum.to_string())]));
+        }
+    }
+    if let Some(maximum) = rules.get("maximum").and_then(Value::as_f64) {
+        if number > maximum {
+            return Some(i18n::t("validation.maximum", &[("value", &maximum.to_string())]));
+        }
+    }
+    if let Some(step) = rules.get("multipleOf").and_then(Value::as_f64) {
+        if (number / step).fract() != 0.0 {
+            return Some(i18n::t("validation.multiple_of", &[("value", &step.to_string())]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::providers::{google::GoogleProvider, openai::OpenAIProvider};
+    use crate::generation::GenerationProvider;
+
+    #[test]
+    fn test_validate_parameters() {
+        let schema = json!({
+            "properties": {
+                "size": { "type": "string", "enum": ["1024x1024", "auto"] },
+                "n": { "type": "integer", "minimum": 1, "maximum": 10 },
+            }
+        });
+        let params = json!({ "size": "512x512", "n": 1, "reference_image": "data:..." });
+        assert_eq!(
+            validate(&schema, &params),
+            vec![FieldError {
+                field: "size".to_string(),
+                message: "must be one of \"1024x1024\", \"auto\"".to_string(),
+            }]
+        );
+        assert_eq!(validate(&schema, &json!({ "n": 2.5 }))[0].message, "must be of type integer");
+        assert!(validate(&schema, &json!({ "n": null })).is_empty());
+
+        let sd = stable_diffusion_schema();
+        let errors = validate(&sd, &json!({ "width": 1020, "steps": 0, "cfg_scale": 7 }));
+        let mut fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        fields.sort();
+        assert_eq!(fields, vec!["steps", "width"]);
+    }
+
+    #[test]
+    fn test_provider_schemas() {
+        let veo = GoogleProvider::new().parameter_schema("veo-3.1");
+        assert!(validate(&veo, &json!({ "duration": 8, "resolution": "1080p" })).is_empty());
+        assert_eq!(validate(&veo, &json!({ "duration": 5, "aspect_ratio": "1:1" })).len(), 2);
+
+        let image = OpenAIProvider::new().parameter_schema("gpt-image-1");
+        assert_eq!(validate(&image, &json!({ "n": 11, "size": "1792x1024" })).len(), 2);
+    }
+}
@@ -0,0 +1,268 @@
+//! Translation of prompt emphasis syntax between backends.
+//!
+//! Prompts are written in the A1111 syntax, which is treated as canonical: `(word:1.3)`,
+//! `(word)` for 1.1, `[word]` for 1/1.1, nested brackets multiplying. Before a request
+//! goes out the prompt is rewritten for the target: ComfyUI understands the same weights
+//! but not `[word]`, InvokeAI uses Compel's `(word)1.3`, and cloud models get plain text.
+
+use serde_json::Value;
+
+use super::GenerationRequest;
+
+/// Multiplier of one pair of round brackets without an explicit weight
+const ROUND_WEIGHT: f64 = 1.1;
+
+/// Emphasis above which a term is called out in plain language prompts
+const PLAIN_EMPHASIS: f64 = 1.15;
+
+/// A run of prompt text with the weight it carries
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedText {
+    pub text: String,
+    pub weight: f64,
+}
+
+/// Split a prompt in A1111 syntax into weighted runs
+///
+/// Follows A1111's rules: `\(` escapes a bracket, unclosed brackets apply to the rest of
+/// the prompt and `BREAK` stays in the text.
+pub fn parse(prompt: &str) -> Vec<WeightedText> {
+    let mut runs: Vec<WeightedText> = Vec::new();
+    let mut round: Vec<usize> = Vec::new();
+    let mut square: Vec<usize> = Vec::new();
+    let mut text = String::new();
+
+    fn flush(runs: &mut Vec<WeightedText>, text: &mut String) {
+        if !text.is_empty() {
+            runs.push(WeightedText {
+                text: std::mem::take(text),
+                weight: 1.0,
+            });
+        }
+    }
+    fn multiply(runs: &mut [WeightedText], from: usize, factor: f64) {
+        for run in &mut runs[from..] {
+            run.weight *= factor;
+        }
+    }
+
+    let mut chars = prompt.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => text.push(escaped),
+                None => text.push('\\'),
+            },
+            '(' => {
+                flush(&mut runs, &mut text);
+                round.push(runs.len());
+            }
+            '[' => {
+                flush(&mut runs, &mut text);
+                square.push(runs.len());
+            }
+            ':' if !round.is_empty() => match explicit_weight(&prompt[i + 1..]) {
+                Some((weight, consumed)) => {
+                    flush(&mut runs, &mut text);
+                    let from = round.pop().unwrap_or_default();
+                    multiply(&mut runs, from, weight);
+                    // Skip the number and the closing bracket
+                    for _ in 0..consumed {
+                        chars.next();
+                    }
+                }
+                None => text.push(c),
+            },
+            ')' if !round.is_empty() => {
+                flush(&mut runs, &mut text);
+                let from = round.pop().unwrap_or_default();
+                multiply(&mut runs, from, ROUND_WEIGHT);
+            }
+            ']' if !square.is_empty() => {
+                flush(&mut runs, &mut text);
+                let from = square.pop().unwrap_or_default();
+                multiply(&mut runs, from, 1.0 / ROUND_WEIGHT);
+            }
+            _ => text.push(c),
+        }
+    }
+    flush(&mut runs, &mut text);
+    for from in round {
+        multiply(&mut runs, from, ROUND_WEIGHT);
+    }
+    for from in square {
+        multiply(&mut runs, from, 1.0 / ROUND_WEIGHT);
+    }
+
+    merge(runs)
+}
+
+/// Whether the prompt uses any emphasis syntax
+pub fn has_weights(prompt: &str) -> bool {
+    parse(prompt).iter().any(|run| !is_neutral(run.weight))
+}
+
+/// Write weighted runs in the syntax of `provider`
+pub fn render(runs: &[WeightedText], provider: &str) -> String {
+    match provider {
+        "a1111" | "comfyui" => join(runs, |run| {
+            format!("({}:{})", escape(run.text.trim()), format_weight(run.weight))
+        }),
+        "invokeai" => join(runs, |run| {
+            format!("({}){}", escape(run.text.trim()), format_weight(run.weight))
+        }),
+        _ => plain(runs),
+    }
+}
+
+/// Rewrite a prompt written in A1111 syntax for `provider`
+pub fn translate(prompt: &str, provider: &str) -> String {
+    // A1111 reads the canonical syntax as is, including `BREAK`
+    if provider == "a1111" || (!has_weights(prompt) && !prompt.contains('\\')) {
+        return prompt.to_string();
+    }
+    render(&parse(prompt), provider)
+}
+
+/// Translate the prompt and negative prompt of an image request for its provider
+///
+/// Text requests are left alone since brackets there are just punctuation, as are
+/// requests with `"prompt_syntax": "raw"` for prompts already written for the backend.
+pub fn for_provider(provider: &str, mut request: GenerationRequest) -> GenerationRequest {
+    let params = &request.parameters;
+    let raw = params.get("prompt_syntax").and_then(Value::as_str) == Some("raw");
+    let text = match provider {
+        "anthropic" | "groq" | "perplexity" => true,
+        "openai_compatible" => params.get("mode").and_then(Value::as_str) != Some("image"),
+        _ => false,
+    };
+    if raw || text {
+        return request;
+    }
+
+    request.prompt = translate(&request.prompt, provider);
+    if let Some(Value::String(negative)) = request.parameters.get_mut("negative_prompt") {
+        *negative = translate(negative, provider);
+    }
+    request
+}
+
+fn is_neutral(weight: f64) -> bool {
+    (weight - 1.0).abs() < 0.005
+}
+
+/// Parse `1.3)` after a colon, returning the weight and the characters it spans
+fn explicit_weight(rest: &str) -> Option<(f64, usize)> {
+    let number: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ' ')
+        .collect();
+    if !rest[number.len()..].starts_with(')') {
+        return None;
+    }
+    let weight = number.trim().parse().ok()?;
+    Some((weight, number.chars().count() + 1))
+}
+
+/// Join adjacent runs of the same weight
+fn merge(runs: Vec<WeightedText>) -> Vec<WeightedText> {
+    let mut merged: Vec<WeightedText> = Vec::with_capacity(runs.len());
+    for run in runs {
+        match merged.last_mut() {
+            Some(last) if is_neutral(last.weight / run.weight) => last.text.push_str(&run.text),
+            _ => merged.push(run),
+        }
+    }
+    merged
+}
+
+/// Render neutral runs as text and weighted ones with `weighted`, keeping the spacing
+fn join(runs: &[WeightedText], weighted: impl Fn(&WeightedText) -> String) -> String {
+    let mut out = String::new();
+    for run in runs {
+        if is_neutral(run.weight) {
+            out.push_str(&escape(&run.text));
+            continue;
+        }
+        let leading = &run.text[..run.text.len() - run.text.trim_start().len()];
+        let trailing = &run.text[run.text.trim_end().len()..];
+        out.push_str(leading);
+        out.push_str(&weighted(run));
+        out.push_str(trailing);
+    }
+    out.replace(" BREAK ", ", ")
+}
+
+/// Plain language for models without weighting: the words, with strong terms repeated
+/// at the end as the focus
+fn plain(runs: &[WeightedText]) -> String {
+    let text: String = runs.iter().map(|run| run.text.as_str()).collect();
+    let text = text.replace(" BREAK ", ", ");
+    let emphasized: Vec<&str> = runs
+        .iter()
+        .filter(|run| run.weight >= PLAIN_EMPHASIS)
+        .map(|run| run.text.trim().trim_matches(','))
+        .filter(|term| !term.is_empty())
+        .collect();
+
+    if emphasized.is_empty() {
+        text
+    } else {
+        format!(
+            "{}. Put particular emphasis on {}.",
+            text.trim_end().trim_end_matches(['.', ',']),
+            emphasized.join(", ")
+        )
+    }
+}
+
+fn format_weight(weight: f64) -> String {
+    let formatted = format!("{:.2}", weight);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('(', "\\(")
+        .replace(')', "\\)")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_weights() {
+        let runs = parse("a (red:1.3) fox, ((snow)), [blurry]");
+        let weights: Vec<(&str, String)> = runs
+            .iter()
+            .map(|run| (run.text.as_str(), format_weight(run.weight)))
+            .collect();
+        assert_eq!(
+            weights,
+            vec![
+                ("a ", "1".to_string()),
+                ("red", "1.3".to_string()),
+                (" fox, ", "1".to_string()),
+                ("snow", "1.21".to_string()),
+                (", ", "1".to_string()),
+                ("blurry", "0.91".to_string()),
+            ]
+        );
+        assert!(!has_weights("a fox \\(red\\)"));
+    }
+
+    #[test]
+    fn test_translate_per_backend() {
+        let prompt = "a (red:1.3) fox, [blurry]";
+        assert_eq!(translate(prompt, "a1111"), prompt);
+        assert_eq!(translate(prompt, "comfyui"), "a (red:1.3) fox, (blurry:0.91)");
+        assert_eq!(translate(prompt, "invokeai"), "a (red)1.3 fox, (blurry)0.91");
+        assert_eq!(
+            translate(prompt, "openai"),
+            "a red fox, blurry. Put particular emphasis on red."
+        );
+        assert_eq!(translate("a fox (on the left)", "openai"), "a fox on the left");
+    }
+}
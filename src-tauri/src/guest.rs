@@ -0,0 +1,115 @@
+//! Read-only guest mode, so a client at the workstation can browse galleries and
+//! workflows without deleting, editing or queueing anything.
+//!
+//! While it is on, only commands of the `read` permission tier run (see
+//! [`crate::permissions`]). The mode is turned on by the `--guest` launch flag or the
+//! stored setting; leaving it may require a PIN.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::db::operations::SettingsOps;
+
+/// Settings key for the stored guest mode
+pub const GUEST_MODE_SETTINGS_KEY: &str = "guest_mode";
+
+/// Launch flag that forces guest mode for the session
+pub const GUEST_FLAG: &str = "--guest";
+
+/// Stored guest mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuestModeSettings {
+    pub enabled: bool,
+    /// SHA-256 of the PIN needed to leave guest mode
+    pub pin_hash: Option<String>,
+}
+
+/// Guest mode as shown to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct GuestModeStatus {
+    pub enabled: bool,
+    /// Set by the launch flag, so it cannot be turned off until restart
+    pub forced: bool,
+    pub has_pin: bool,
+}
+
+/// Managed state consulted by the invoke handler
+pub struct GuestMode {
+    enabled: AtomicBool,
+    forced: bool,
+}
+
+impl GuestMode {
+    pub fn from_args() -> Self {
+        let forced = std::env::args().any(|arg| arg == GUEST_FLAG);
+        Self {
+            enabled: AtomicBool::new(forced),
+            forced,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Apply the stored setting; the launch flag always wins
+    pub async fn load(&self, pool: &SqlitePool) -> Result<()> {
+        let settings: GuestModeSettings =
+            SettingsOps::get_or_default(pool, GUEST_MODE_SETTINGS_KEY).await?;
+        self.enabled
+            .store(self.forced || settings.enabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub async fn status(&self, pool: &SqlitePool) -> Result<GuestModeStatus> {
+        let settings: GuestModeSettings =
+            SettingsOps::get_or_default(pool, GUEST_MODE_SETTINGS_KEY).await?;
+        Ok(GuestModeStatus {
+            enabled: self.is_enabled(),
+            forced: self.forced,
+            has_pin: settings.pin_hash.is_some(),
+        })
+    }
+
+    /// Enter guest mode, optionally protected by `pin`, or leave it with the stored PIN
+    pub async fn set(&self, pool: &SqlitePool, enabled: bool, pin: Option<&str>) -> Result<()> {
+        // Entering again must not let a guest replace the PIN
+        if enabled && self.is_enabled() {
+            return Ok(());
+        }
+        let mut settings: GuestModeSettings =
+            SettingsOps::get_or_default(pool, GUEST_MODE_SETTINGS_KEY).await?;
+        if enabled {
+            settings.enabled = true;
+            settings.pin_hash = pin.filter(|pin| !pin.is_empty()).map(hash_pin);
+        } else {
+            if self.forced {
+                return Err(anyhow::anyhow!(
+                    "Guest mode was requested at launch; restart without {} to leave it",
+                    GUEST_FLAG
+                ));
+            }
+            if let Some(expected) = &settings.pin_hash {
+                if pin.map(hash_pin).as_ref() != Some(expected) {
+                    return Err(anyhow::anyhow!("Incorrect PIN"));
+                }
+            }
+            settings = GuestModeSettings::default();
+        }
+
+        SettingsOps::set(pool, GUEST_MODE_SETTINGS_KEY, &settings).await?;
+        self.enabled.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn hash_pin(pin: &str) -> String {
+    Sha256::digest(pin.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
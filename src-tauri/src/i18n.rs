@@ -0,0 +1,164 @@
+//! Translated strings for messages produced in the backend.
+//!
+//! Catalogs are flat JSON maps in `locales/<locale>.json`, compiled into the binary.
+//! English is complete and stands in for keys missing from other catalogs. Values use
+//! `{name}` placeholders. The active locale is the one saved in the settings, or the
+//! system's when none is.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{OnceLock, RwLock};
+
+/// Settings key for the locale chosen in the app; unset follows the system
+pub const LOCALE_SETTINGS_KEY: &str = "locale";
+
+/// Locale of the complete catalog, used when nothing better matches
+pub const DEFAULT_LOCALE: &str = "en";
+
+const CATALOGS: [(&str, &str); 4] = [
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("es", include_str!("../locales/es.json")),
+    ("fr", include_str!("../locales/fr.json")),
+];
+
+/// A locale's strings as sent to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct Translations {
+    pub locale: String,
+    pub strings: BTreeMap<String, String>,
+}
+
+/// Active, system and supported locales
+#[derive(Debug, Clone, Serialize)]
+pub struct LocaleInfo {
+    pub locale: String,
+    pub detected: String,
+    pub supported: Vec<String>,
+}
+
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    static CATALOG_MAP: OnceLock<HashMap<&'static str, HashMap<String, String>>> =
+        OnceLock::new();
+    CATALOG_MAP.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(locale, json)| {
+                let strings = serde_json::from_str(json).unwrap_or_else(|e| {
+                    log_error!("[i18n] Catalog {} is invalid: {}", locale, e);
+                    HashMap::new()
+                });
+                (*locale, strings)
+            })
+            .collect()
+    })
+}
+
+fn active() -> &'static RwLock<String> {
+    static ACTIVE: OnceLock<RwLock<String>> = OnceLock::new();
+    ACTIVE.get_or_init(|| RwLock::new(detect_locale()))
+}
+
+pub fn supported_locales() -> Vec<String> {
+    CATALOGS.iter().map(|(locale, _)| locale.to_string()).collect()
+}
+
+/// Supported locale closest to a tag like `de-AT` or `fr_FR.UTF-8`
+pub fn resolve(tag: &str) -> String {
+    let language = tag
+        .split(['-', '_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if catalogs().contains_key(language.as_str()) {
+        language
+    } else {
+        DEFAULT_LOCALE.to_string()
+    }
+}
+
+/// Locale of the system, from the POSIX locale variables
+///
+/// Where none is set (e.g. apps started from the Windows shell) English is used.
+pub fn detect_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+        .map(|value| resolve(&value))
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+pub fn locale() -> String {
+    active().read().unwrap().clone()
+}
+
+/// Switch backend messages to `tag`, or back to the system locale with `None`
+///
+/// Returns the locale actually used.
+pub fn set_locale(tag: Option<&str>) -> String {
+    let locale = tag.map(resolve).unwrap_or_else(detect_locale);
+    *active().write().unwrap() = locale.clone();
+    locale
+}
+
+/// All strings of a locale, English filling the gaps
+pub fn translations(locale: &str) -> Translations {
+    let locale = resolve(locale);
+    let mut strings: BTreeMap<String, String> = catalogs()[DEFAULT_LOCALE]
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if let Some(catalog) = catalogs().get(locale.as_str()) {
+        strings.extend(catalog.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+    Translations { locale, strings }
+}
+
+/// Message `key` in the active locale with its placeholders filled in
+pub fn t(key: &str, args: &[(&str, &str)]) -> String {
+    translate(&locale(), key, args)
+}
+
+/// Message `key` in `locale`; unknown keys come back as the key itself
+pub fn translate(locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+    let template = catalogs()
+        .get(locale)
+        .and_then(|catalog| catalog.get(key))
+        .or_else(|| catalogs()[DEFAULT_LOCALE].get(key));
+    let Some(template) = template else {
+        log_debug!("[i18n] Missing string {}", key);
+        return key.to_string();
+    };
+    args.iter().fold(template.clone(), |message, (name, value)| {
+        message.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_with_fallback() {
+        assert_eq!(resolve("de_DE.UTF-8"), "de");
+        assert_eq!(resolve("pt-BR"), "en");
+
+        let args = [("provider", "OpenAI")];
+        assert_eq!(translate("en", "error.missing_key", &args), "OpenAI API key not configured");
+        assert_eq!(
+            translate("de", "error.missing_key", &args),
+            "Kein API-Schlüssel für OpenAI konfiguriert"
+        );
+        // Not in the German catalog, so English is used
+        assert_eq!(translate("de", "error.other", &[("message", "boom")]), "boom");
+        assert_eq!(translate("en", "no.such.key", &[]), "no.such.key");
+
+        // Every catalog only uses keys English defines
+        let english = &catalogs()[DEFAULT_LOCALE];
+        for (locale, catalog) in catalogs() {
+            assert!(!catalog.is_empty(), "{} catalog is empty", locale);
+            assert!(catalog.keys().all(|key| english.contains_key(key)), "{}", locale);
+        }
+    }
+}
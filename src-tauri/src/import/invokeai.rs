@@ -0,0 +1,345 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::db::models::{CreateSceneInput, CreateWorkflowInput};
+use crate::db::operations::{SceneOps, WorkflowOps};
+use crate::generation::images_dir;
+use crate::generation::utils::api_error;
+use crate::storage::paths;
+
+/// Value of `metadata.source` on scenes created by this importer
+const SOURCE: &str = "invokeai";
+
+/// Board ID InvokeAI uses for images that are not on any board
+pub const UNCATEGORIZED_BOARD: &str = "none";
+
+/// A board on an InvokeAI instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvokeAIBoard {
+    pub board_id: String,
+    pub board_name: String,
+    #[serde(default)]
+    pub image_count: u64,
+    pub created_at: Option<String>,
+}
+
+/// Outcome of importing a board
+#[derive(Debug, Clone, Serialize)]
+pub struct InvokeAIImportSummary {
+    pub workflow_id: String,
+    pub imported: usize,
+    /// Images already imported by an earlier run
+    pub skipped: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageDto {
+    image_name: String,
+    created_at: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+struct InvokeAIClient {
+    api_url: String,
+    client: reqwest::Client,
+}
+
+impl InvokeAIClient {
+    fn new(api_url: &str) -> Result<Self> {
+        Ok(Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .build()?,
+        })
+    }
+
+    async fn get(&self, path: &str) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/{}", self.api_url, path))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error("InvokeAI API", response).await);
+        }
+
+        Ok(response)
+    }
+
+    async fn image_names(&self, board_id: &str) -> Result<Vec<String>> {
+        let value: serde_json::Value = self
+            .get(&format!("boards/{}/image_names", board_id))
+            .await?
+            .json()
+            .await?;
+
+        Ok(parse_image_names(&value))
+    }
+}
+
+/// Image names from a board listing; older releases return a bare array, newer ones wrap it
+fn parse_image_names(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .or_else(|| value.get("image_names").and_then(|v| v.as_array()))
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|n| n.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// List boards on the instance, including a pseudo-board for uncategorized images
+pub async fn list_boards(api_url: &str) -> Result<Vec<InvokeAIBoard>> {
+    let client = InvokeAIClient::new(api_url)?;
+    let mut boards: Vec<InvokeAIBoard> = client.get("boards/?all=true").await?.json().await?;
+
+    let uncategorized = client.image_names(UNCATEGORIZED_BOARD).await?;
+    if !uncategorized.is_empty() {
+        boards.push(InvokeAIBoard {
+            board_id: UNCATEGORIZED_BOARD.to_string(),
+            board_name: "Uncategorized".to_string(),
+            image_count: uncategorized.len() as u64,
+            created_at: None,
+        });
+    }
+
+    Ok(boards)
+}
+
+/// Download a board's images into the local asset store and save each as a scene
+///
+/// Without a `workflow_id` a new workflow named after the board is created.
+/// Images imported earlier are skipped, so the import can be re-run safely.
+pub async fn import_board(
+    pool: &SqlitePool,
+    api_url: &str,
+    board_id: &str,
+    workflow_id: Option<String>,
+) -> Result<InvokeAIImportSummary> {
+    let client = InvokeAIClient::new(api_url)?;
+
+    let workflow_id = match workflow_id {
+        Some(id) => id,
+        None => {
+            let board_name = list_boards(api_url)
+                .await?
+                .into_iter()
+                .find(|board| board.board_id == board_id)
+                .map(|board| board.board_name)
+                .ok_or_else(|| anyhow::anyhow!("InvokeAI board not found: {}", board_id))?;
+
+            WorkflowOps::create(
+                pool,
+                CreateWorkflowInput {
+                    name: format!("InvokeAI: {}", board_name),
+                    workflow_type: "image".to_string(),
+                    data: serde_json::json!({ "source": SOURCE, "boardId": board_id }),
+                },
+            )
+            .await?
+            .id
+        }
+    };
+
+    let images_dir = images_dir()?;
+    let mut summary = InvokeAIImportSummary {
+        workflow_id: workflow_id.clone(),
+        imported: 0,
+        skipped: 0,
+        failed: Vec::new(),
+    };
+
+    for image_name in client.image_names(board_id).await? {
+        if SceneOps::exists_with_source(pool, SOURCE, &image_name).await? {
+            summary.skipped += 1;
+            continue;
+        }
+
+        match import_image(pool, &client, &images_dir, &workflow_id, board_id, &image_name).await {
+            Ok(()) => summary.imported += 1,
+            Err(e) => {
+                log_warn!("[InvokeAI Import] Failed to import {}: {}", image_name, e);
+                summary.failed.push(image_name);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+async fn import_image(
+    pool: &SqlitePool,
+    client: &InvokeAIClient,
+    images_dir: &std::path::Path,
+    workflow_id: &str,
+    board_id: &str,
+    image_name: &str,
+) -> Result<()> {
+    let image: ImageDto = client
+        .get(&format!("images/i/{}", image_name))
+        .await?
+        .json()
+        .await?;
+    let metadata: serde_json::Value = client
+        .get(&format!("images/i/{}/metadata", image_name))
+        .await?
+        .json()
+        .await
+        .unwrap_or_default();
+    let bytes = client
+        .get(&format!("images/i/{}/full", image_name))
+        .await?
+        .bytes()
+        .await?;
+
+    let file_path = paths::unique_file(images_dir, &format!("invokeai_{}", image.image_name))?;
+    paths::write_atomic(&file_path, &bytes).await?;
+
+    SceneOps::create(pool, scene_input(workflow_id, board_id, &image, metadata, &file_path)).await?;
+
+    Ok(())
+}
+
+/// Map an InvokeAI image and its generation metadata onto a scene
+///
+/// Metadata fields that are missing or of an unexpected type are left empty rather
+/// than failing the import.
+fn scene_input(
+    workflow_id: &str,
+    board_id: &str,
+    image: &ImageDto,
+    metadata: serde_json::Value,
+    file_path: &std::path::Path,
+) -> CreateSceneInput {
+    let prompt = metadata
+        .get("positive_prompt")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let model = metadata
+        .get("model")
+        .and_then(|m| m.get("name").or(Some(m)))
+        .and_then(|v| v.as_str());
+
+    let name = if prompt.is_empty() {
+        image.image_name.clone()
+    } else if prompt.chars().count() > 50 {
+        format!("{}...", prompt.chars().take(50).collect::<String>())
+    } else {
+        prompt.to_string()
+    };
+
+    CreateSceneInput {
+        workflow_id: workflow_id.to_string(),
+        name,
+        data: serde_json::json!({
+            "category": "image",
+            "model": model,
+            "provider": SOURCE,
+            "prompt": {
+                "main": prompt,
+                "params": {
+                    "negative_prompt": metadata.get("negative_prompt"),
+                    "seed": metadata.get("seed"),
+                    "steps": metadata.get("steps"),
+                    "cfg_scale": metadata.get("cfg_scale"),
+                    "scheduler": metadata.get("scheduler"),
+                    "width": image.width,
+                    "height": image.height,
+                },
+            },
+            "metadata": {
+                "source": SOURCE,
+                "sourceId": image.image_name,
+                "boardId": board_id,
+                "createdAt": image.created_at,
+                "filePath": file_path.display().to_string(),
+                "invokeai": metadata,
+            },
+        }),
+        thumbnail: Some(format!("asset://localhost/{}", file_path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn image(value: serde_json::Value) -> ImageDto {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_scene_input_from_metadata() {
+        let image = image(json!({
+            "image_name": "3f2a.png",
+            "created_at": "2024-05-01 10:00:00",
+            "width": 1024,
+            "height": 768,
+            "board_id": "b1",
+            "starred": false
+        }));
+        let metadata = json!({
+            "generation_mode": "txt2img",
+            "positive_prompt": "a lighthouse on a cliff at dusk",
+            "negative_prompt": "blurry",
+            "seed": 1234,
+            "steps": 30,
+            "cfg_scale": 7.5,
+            "scheduler": "euler_a",
+            "model": { "key": "abc", "name": "sdxl-base", "base": "sdxl", "type": "main" }
+        });
+
+        let input = scene_input("wf", "b1", &image, metadata, Path::new("/tmp/invokeai_3f2a.png"));
+        assert_eq!(input.name, "a lighthouse on a cliff at dusk");
+        assert_eq!(input.thumbnail.as_deref(), Some("asset://localhost//tmp/invokeai_3f2a.png"));
+        assert_eq!(input.data["model"], "sdxl-base");
+        assert_eq!(input.data["prompt"]["params"]["seed"], 1234);
+        assert_eq!(input.data["prompt"]["params"]["negative_prompt"], "blurry");
+        assert_eq!(input.data["prompt"]["params"]["width"], 1024);
+        assert_eq!(input.data["metadata"]["sourceId"], "3f2a.png");
+        assert_eq!(input.data["metadata"]["invokeai"]["generation_mode"], "txt2img");
+    }
+
+    #[test]
+    fn test_scene_input_from_malformed_metadata() {
+        let image = image(json!({ "image_name": "9c1d.png" }));
+        let metadata = json!({ "positive_prompt": 42, "model": { "key": "abc" }, "seed": "x" });
+
+        let input = scene_input("wf", "none", &image, metadata, Path::new("/tmp/9c1d.png"));
+        assert_eq!(input.name, "9c1d.png");
+        assert!(input.data["model"].is_null());
+        assert_eq!(input.data["prompt"]["main"], "");
+        assert!(input.data["prompt"]["params"]["width"].is_null());
+
+        // Older releases store the model as a bare string
+        let metadata = json!({ "model": "sd-1.5", "positive_prompt": "p".repeat(60) });
+        let input = scene_input("wf", "none", &image, metadata, Path::new("/tmp/9c1d.png"));
+        assert_eq!(input.data["model"], "sd-1.5");
+        assert_eq!(input.name, format!("{}...", "p".repeat(50)));
+
+        // The metadata endpoint can fail, leaving a null blob
+        let input = scene_input("wf", "none", &image, json!(null), Path::new("/tmp/9c1d.png"));
+        assert_eq!(input.name, "9c1d.png");
+    }
+
+    #[test]
+    fn test_parse_image_names() {
+        assert_eq!(parse_image_names(&json!(["a.png", "b.png"])), vec!["a.png", "b.png"]);
+        assert_eq!(
+            parse_image_names(&json!({ "image_names": ["a.png", 3], "starred_count": 0 })),
+            vec!["a.png"]
+        );
+        assert!(parse_image_names(&json!({ "detail": "Not Found" })).is_empty());
+    }
+}
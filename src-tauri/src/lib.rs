@@ -1,14 +1,34 @@
+#[macro_use]
+mod logs;
+
+mod analytics;
+mod audit;
 mod commands;
 mod db;
+mod export;
 mod generation;
+mod guest;
+mod i18n;
+mod import;
+mod maintenance;
+mod notifications;
+mod permissions;
+mod secrets;
+mod setup;
+mod storage;
+mod updates;
 
 use std::sync::Arc;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use tokio::sync::RwLock;
 
 use generation::providers::{
-    anthropic::AnthropicProvider, google::GoogleProvider, grok::GrokProvider,
-    openai::OpenAIProvider,
+    anthropic::AnthropicProvider, fal::FalProvider, flux::FluxProvider,
+    google::GoogleProvider, grok::GrokProvider, openai::OpenAIProvider,
+    leonardo::LeonardoProvider, minimax::MiniMaxProvider, pika::PikaProvider,
+    recraft::RecraftProvider, runway::RunwayProvider, volcengine::VolcengineProvider,
+    elevenlabs::ElevenLabsProvider, stability::StabilityProvider, vertex::VertexProvider,
+    groq::GroqProvider, midjourney::MidjourneyProvider, perplexity::PerplexityProvider,
 };
 use generation::{processor::JobProcessor, GenerationService};
 
@@ -21,31 +41,181 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_sql::Builder::default().build())
+        .manage(guest::GuestMode::from_args())
+        .manage(permissions::Permissions::default())
+        .manage(logs::LogConsole::default())
         .setup(|app| {
             // Initialize database and generation service synchronously in setup
             let app_handle = app.handle().clone();
+            logs::spawn_forwarder(app_handle.clone());
             tauri::async_runtime::block_on(async move {
-                eprintln!("[Setup] Starting database initialization...");
+                log_info!("[Setup] Starting database initialization...");
                 // Initialize database
                 let db = match init_database(&app_handle).await {
                     Ok(db) => {
-                        eprintln!("[Setup] Database initialized successfully");
+                        log_info!("[Setup] Database initialized successfully");
                         db
                     },
                     Err(e) => {
-                        eprintln!("[Setup] FAILED to initialize database: {}", e);
-                        eprintln!("[Setup] Error details: {:?}", e);
+                        log_error!("[Setup] FAILED to initialize database: {}", e);
+                        log_error!("[Setup] Error details: {:?}", e);
                         panic!("Cannot continue without database: {}", e);
                     }
                 };
 
+                app_handle
+                    .state::<permissions::Permissions>()
+                    .load(db.data_dir());
+                if let Err(e) = app_handle.state::<guest::GuestMode>().load(db.pool()).await {
+                    log_warn!("[Setup] Failed to load guest mode: {}", e);
+                }
+
+                match db::operations::SettingsOps::get_or_default::<Option<String>>(
+                    db.pool(),
+                    i18n::LOCALE_SETTINGS_KEY,
+                )
+                .await
+                {
+                    Ok(locale) => {
+                        let locale = i18n::set_locale(locale.as_deref());
+                        log_info!("[Setup] Using locale {}", locale);
+                    }
+                    Err(e) => log_warn!("[Setup] Failed to load the locale: {}", e),
+                }
+
+                // Provider clients are built from these settings
+                match db::operations::SettingsOps::get_or_default::<generation::http::HttpSettings>(
+                    db.pool(),
+                    generation::http::HTTP_SETTINGS_KEY,
+                )
+                .await
+                {
+                    Ok(settings) => generation::http::configure(settings),
+                    Err(e) => log_warn!("[Setup] Failed to load HTTP client settings: {}", e),
+                }
+
                 // Initialize generation service
-                let generation_service = init_generation_service();
+                let mut generation_service = init_generation_service();
+                restore_api_keys(&mut generation_service, &db).await;
+                restore_openai_compatible(&mut generation_service, &db).await;
+                restore_external_providers(&mut generation_service, &db).await;
+                restore_mock_provider(&mut generation_service, &db).await;
                 let service_arc = Arc::new(RwLock::new(generation_service));
 
-                // Initialize and start job processor
-                let processor = JobProcessor::new(db.pool().clone(), service_arc.clone());
-                processor.start().await;
+                match db::operations::SettingsOps::get_or_default::<
+                    generation::fallback::FallbackSettings,
+                >(db.pool(), generation::fallback::FALLBACK_SETTINGS_KEY)
+                .await
+                {
+                    Ok(settings) => service_arc.read().await.set_fallbacks(settings.chains),
+                    Err(e) => log_warn!("[Setup] Failed to load fallback chains: {}", e),
+                }
+                match db::operations::SettingsOps::get_or_default::<generation::clip::ClipSettings>(
+                    db.pool(),
+                    generation::clip::CLIP_SETTINGS_KEY,
+                )
+                .await
+                {
+                    Ok(settings) => service_arc.read().await.set_clip_settings(settings),
+                    Err(e) => log_warn!("[Setup] Failed to load CLIP prompt settings: {}", e),
+                }
+                match db::operations::SettingsOps::get_or_default::<
+                    generation::routing::RoutingSettings,
+                >(db.pool(), generation::routing::ROUTING_SETTINGS_KEY)
+                .await
+                {
+                    Ok(settings) => service_arc.read().await.set_routing_settings(settings),
+                    Err(e) => log_warn!("[Setup] Failed to load provider routing: {}", e),
+                }
+
+                // Write outputs to the configured storage backend, falling back to local files
+                match storage::load_settings(db.pool(), db.data_dir())
+                    .await
+                    .and_then(|settings| storage::build_backend(&settings))
+                {
+                    Ok(backend) => service_arc.read().await.set_storage(backend),
+                    Err(e) => log_warn!(
+                        "[Setup] Using local storage, configured backend is invalid: {}",
+                        e
+                    ),
+                }
+
+                // Test every provider in the background so the UI knows which ones work
+                let health_service = service_arc.clone();
+                let health_app = app_handle.clone();
+                tokio::spawn(async move {
+                    let providers = health_service.read().await.test_all_providers().await;
+                    let usable = providers.iter().filter(|result| result.success).count();
+                    log_info!("[Setup] {} of {} providers usable", usable, providers.len());
+                    let event = generation::health::ProvidersReadyEvent { providers };
+                    if let Err(e) =
+                        health_app.emit(generation::health::PROVIDERS_READY_EVENT, event)
+                    {
+                        log_warn!("[Setup] Failed to emit provider status: {}", e);
+                    }
+                });
+
+                // Initialize job processor, only starting it if the queue is safe to process
+                let processor = JobProcessor::new(
+                    db.pool().clone(),
+                    service_arc.clone(),
+                    app_handle.clone(),
+                );
+                match db::compat::check(db.pool()).await {
+                    Ok(report) if report.compatible => processor.start().await,
+                    Ok(report) => {
+                        log_error!(
+                            "[Setup] Job processor NOT started, database is incompatible:"
+                        );
+                        for issue in &report.issues {
+                            log_error!("[Setup]   - {}", issue);
+                        }
+                    }
+                    Err(e) => {
+                        log_error!(
+                            "[Setup] Job processor NOT started, compatibility check failed: {}",
+                            e
+                        );
+                    }
+                }
+
+                // Periodically prune job history and queue expiry of unkept assets
+                let retention_db = db.clone();
+                tokio::spawn(async move {
+                    loop {
+                        if let Err(e) = maintenance::retention::run_scheduled(
+                            retention_db.pool(),
+                            retention_db.data_dir(),
+                        )
+                        .await
+                        {
+                            log_warn!("[Retention] Failed to apply retention policy: {}", e);
+                        }
+                        if let Err(e) =
+                            maintenance::expiry::run_scheduled(retention_db.pool()).await
+                        {
+                            log_warn!("[Expiry] Failed to queue asset expiry: {}", e);
+                        }
+                        tokio::time::sleep(maintenance::retention::RETENTION_INTERVAL).await;
+                    }
+                });
+
+                // Report asset files that went missing since the last run
+                let integrity_db = db.clone();
+                let integrity_app = app_handle.clone();
+                tokio::spawn(async move {
+                    match maintenance::integrity::scan(integrity_db.pool(), false).await {
+                        Ok(report) if !report.missing.is_empty() => {
+                            if let Err(e) = integrity_app
+                                .emit(maintenance::integrity::INTEGRITY_REPORT_EVENT, &report)
+                            {
+                                log_warn!("[Integrity] Failed to emit report: {}", e);
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => log_warn!("[Integrity] Asset scan failed: {}", e),
+                    }
+                });
 
                 // Store services in app state
                 app_handle.manage(service_arc);
@@ -53,50 +223,180 @@ pub fn run() {
             });
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(permissions::guard(tauri::generate_handler![
             commands::create_workflow,
             commands::get_workflow,
             commands::list_workflows,
             commands::update_workflow,
+            commands::get_workflow_providers,
+            commands::set_workflow_providers,
             commands::delete_workflow,
             commands::create_scene,
             commands::list_scenes,
             commands::list_all_scenes,
+            commands::list_scene_narrations,
             commands::delete_scene,
             commands::create_job,
             commands::get_job,
             commands::list_jobs,
             commands::update_job,
             commands::delete_job,
+            commands::retry_job,
+            commands::get_job_lineage,
             commands::create_version,
             commands::list_versions,
             commands::submit_generation,
+            commands::get_similar_prompts,
+            commands::rerun_workflow,
+            commands::submit_sweep,
+            commands::get_sweep_report,
+            commands::judge_sweep,
+            commands::start_refine_session,
+            commands::refine_prompt,
+            commands::bisect_refine,
+            commands::queue_midjourney_action,
+            commands::get_refine_session,
+            commands::list_refine_sessions,
+            commands::get_parameter_schema,
+            commands::submit_pipeline,
+            commands::estimate_generation,
+            commands::list_deprecated_model_usage,
+            commands::approve_job,
+            commands::get_job_logs,
+            commands::get_confirmation_settings,
+            commands::update_confirmation_settings,
+            commands::get_budget_settings,
+            commands::update_budget_settings,
+            commands::get_storage_settings,
+            commands::update_storage_settings,
+            commands::get_result_cache_settings,
+            commands::update_result_cache_settings,
+            commands::clear_result_cache,
             commands::configure_provider,
+            commands::add_provider_key,
+            commands::remove_provider_key,
+            commands::get_provider_base_urls,
+            commands::set_provider_base_url,
+            commands::get_provider_timeouts,
+            commands::set_provider_timeouts,
+            commands::get_provider_headers,
+            commands::get_http_settings,
+            commands::update_http_settings,
+            commands::set_provider_headers,
+            commands::list_provider_keys,
+            commands::set_key_rotation,
+            commands::get_key_usage,
+            commands::get_openai_compatible_config,
+            commands::configure_openai_compatible,
             commands::list_providers,
+            commands::list_external_providers,
+            commands::register_external_provider,
+            commands::remove_external_provider,
+            commands::set_provider_rate_limit,
+            commands::get_provider_rate_limits,
+            commands::get_processor_settings,
+            commands::update_processor_settings,
+            commands::get_fallback_settings,
+            commands::update_fallback_settings,
+            commands::get_clip_settings,
+            commands::update_clip_settings,
+            commands::get_mock_provider_settings,
+            commands::update_mock_provider_settings,
+            commands::get_routing_settings,
+            commands::update_routing_settings,
+            commands::get_auto_route,
+            commands::get_network_status,
+            commands::get_eco_status,
             commands::configure_local_provider,
+            commands::get_provider_capabilities,
+            commands::get_provider_schema,
+            commands::list_models,
+            commands::test_provider,
+            commands::get_provider_health,
+            commands::run_setup_checks,
+            commands::get_audio_notification_settings,
+            commands::update_audio_notification_settings,
+            commands::preview_audio_notification,
+            commands::create_notification_rule,
+            commands::list_notification_rules,
+            commands::update_notification_rule,
+            commands::delete_notification_rule,
+            commands::create_parameter_preset,
+            commands::list_parameter_presets,
+            commands::update_parameter_preset,
+            commands::delete_parameter_preset,
+            commands::create_smart_collection,
+            commands::list_smart_collections,
+            commands::update_smart_collection,
+            commands::delete_smart_collection,
+            commands::get_collection_items,
+            commands::get_compatibility_report,
+            commands::get_retention_policy,
+            commands::update_retention_policy,
+            commands::run_retention,
+            commands::get_asset_expiry_settings,
+            commands::update_asset_expiry_settings,
+            commands::get_asset_expiry_preview,
+            commands::start_asset_expiry,
+            commands::db_doctor,
+            commands::optimize_database,
+            commands::scan_asset_integrity,
+            commands::start_thumbnail_backfill,
+            commands::get_personal_stats,
+            commands::get_spend_summary,
+            commands::list_usage_records,
+            commands::get_asset_provenance,
+            commands::list_provenance,
+            commands::check_for_updates,
+            commands::export_markdown,
+            commands::export_schedule_ics,
+            commands::export_usage_csv,
+            commands::export_settings,
+            commands::import_settings,
+            commands::export_social,
+            commands::compose_grid,
+            commands::export_assets_zip,
+            commands::get_audit_log,
+            commands::get_c2pa_settings,
+            commands::update_c2pa_settings,
+            commands::get_watermark_settings,
+            commands::update_watermark_settings,
+            commands::detect_watermark,
+            commands::list_invokeai_boards,
+            commands::import_invokeai_board,
             commands::check_port,
             commands::call_ai,
+            commands::lint_prompt,
+            commands::generate_stream,
+            commands::get_translations,
+            commands::get_locale,
+            commands::set_locale,
             commands::open_in_default_app,
-            commands::open_with_app
-        ])
+            commands::open_with_app,
+            commands::get_guest_mode,
+            commands::set_guest_mode,
+            commands::get_permissions,
+            commands::subscribe_logs,
+            commands::unsubscribe_logs
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
 async fn init_database(app: &tauri::AppHandle) -> anyhow::Result<db::Database> {
-    eprintln!("[init_database] Function called");
+    log_debug!("[init_database] Function called");
     // Check for snap environment first, fallback to home directory
     let app_data_dir = if let Ok(snap_user_common) = std::env::var("SNAP_USER_COMMON") {
         // Use snap's unversioned user data directory (persists across updates)
-        eprintln!("Running in snap environment, using SNAP_USER_COMMON");
+        log_debug!("Running in snap environment, using SNAP_USER_COMMON");
         std::path::PathBuf::from(snap_user_common).join("promptcraft")
     } else if let Ok(snap_user_data) = std::env::var("SNAP_USER_DATA") {
         // Use snap's versioned user data directory
-        eprintln!("Running in snap environment, using SNAP_USER_DATA");
+        log_debug!("Running in snap environment, using SNAP_USER_DATA");
         std::path::PathBuf::from(snap_user_data).join("promptcraft")
     } else {
         // Fallback for non-snap environments - use home directory
-        eprintln!("Not in snap environment, using home directory");
+        log_debug!("Not in snap environment, using home directory");
         let home = std::env::var("HOME")
             .map(std::path::PathBuf::from)
             .unwrap_or_else(|_| std::path::PathBuf::from("/tmp"));
@@ -104,11 +404,11 @@ async fn init_database(app: &tauri::AppHandle) -> anyhow::Result<db::Database> {
     };
 
     std::fs::create_dir_all(&app_data_dir)?;
-    eprintln!("Using database directory: {:?}", app_data_dir);
+    log_debug!("Using database directory: {:?}", app_data_dir);
 
     // Create database path
     let db_path = app_data_dir.join("promptcraft.db");
-    eprintln!("Database path: {:?}", db_path);
+    log_debug!("Database path: {:?}", db_path);
 
     // Initialize database
     let database = db::Database::new(db_path).await?;
@@ -127,6 +427,140 @@ fn init_generation_service() -> GenerationService {
     service.register_provider(Box::new(OpenAIProvider::new()));
     service.register_provider(Box::new(GoogleProvider::new()));
     service.register_provider(Box::new(GrokProvider::new()));
+    service.register_provider(Box::new(FluxProvider::new()));
+    service.register_provider(Box::new(FalProvider::new()));
+    service.register_provider(Box::new(RecraftProvider::new()));
+    service.register_provider(Box::new(LeonardoProvider::new()));
+    service.register_provider(Box::new(RunwayProvider::new()));
+    service.register_provider(Box::new(PikaProvider::new()));
+    service.register_provider(Box::new(MiniMaxProvider::new()));
+    service.register_provider(Box::new(VolcengineProvider::new()));
+    service.register_provider(Box::new(ElevenLabsProvider::new()));
+    service.register_provider(Box::new(StabilityProvider::new()));
+    service.register_provider(Box::new(VertexProvider::new()));
+    service.register_provider(Box::new(GroqProvider::new()));
+    service.register_provider(Box::new(MidjourneyProvider::new()));
+    service.register_provider(Box::new(PerplexityProvider::new()));
 
     service
 }
+
+/// Configure cloud providers with their saved API keys, rotation modes, base URLs, timeouts
+/// and extra headers
+async fn restore_api_keys(service: &mut GenerationService, db: &db::Database) {
+    match generation::endpoints::load(db.pool()).await {
+        Ok(settings) => {
+            for (provider, base_url) in settings.providers {
+                service.set_base_url(&provider, Some(base_url));
+            }
+        }
+        Err(e) => log_warn!("[Setup] Failed to load provider base URLs: {}", e),
+    }
+    match generation::timeouts::load(db.pool()).await {
+        Ok(settings) => {
+            for (provider, timeouts) in settings.providers {
+                service.set_timeouts(&provider, Some(timeouts));
+            }
+        }
+        Err(e) => log_warn!("[Setup] Failed to load provider timeouts: {}", e),
+    }
+    match generation::headers::load(db.pool(), db.data_dir()).await {
+        Ok(settings) => {
+            for (provider, headers) in settings.providers {
+                service.set_headers(&provider, headers);
+            }
+        }
+        Err(e) => log_warn!("[Setup] Failed to load provider headers: {}", e),
+    }
+
+    for provider in secrets::API_KEY_PROVIDERS {
+        let keys = match generation::keys::provider_keys(db.pool(), provider).await {
+            Ok(keys) => keys,
+            Err(e) => {
+                log_warn!("[Setup] Failed to load key list for {}: {}", provider, e);
+                continue;
+            }
+        };
+        let loaded = generation::keys::load_keys(db.data_dir(), provider, &keys);
+        if loaded.is_empty() {
+            continue;
+        }
+        if let Err(e) = service.configure_provider_keys(provider, loaded, keys.rotation) {
+            log_warn!("[Setup] Failed to configure {}: {}", provider, e);
+        }
+    }
+}
+
+/// Register the external providers saved in settings; broken plugins are skipped
+async fn restore_external_providers(service: &mut GenerationService, db: &db::Database) {
+    use generation::providers::external::{
+        ExternalProvider, ExternalProviderConfig, EXTERNAL_PROVIDERS_SETTINGS_KEY,
+    };
+
+    let configs = match db::operations::SettingsOps::get_or_default::<Vec<ExternalProviderConfig>>(
+        db.pool(),
+        EXTERNAL_PROVIDERS_SETTINGS_KEY,
+    )
+    .await
+    {
+        Ok(configs) => configs,
+        Err(e) => {
+            log_warn!("[Setup] Failed to load external providers: {}", e);
+            return;
+        }
+    };
+
+    for config in configs {
+        let name = config.name.clone();
+        match ExternalProvider::connect(config).await {
+            Ok(provider) => service.register_provider(Box::new(provider)),
+            Err(e) => log_warn!("[Setup] Failed to start external provider {}: {}", name, e),
+        }
+    }
+}
+
+/// Offer the mock provider in debug builds, or in release builds when enabled
+async fn restore_mock_provider(service: &mut GenerationService, db: &db::Database) {
+    use generation::providers::mock::{self, MockProviderSettings, MOCK_PROVIDER_SETTINGS_KEY};
+
+    let settings = db::operations::SettingsOps::get_or_default::<MockProviderSettings>(
+        db.pool(),
+        MOCK_PROVIDER_SETTINGS_KEY,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        log_warn!("[Setup] Failed to load mock provider settings: {}", e);
+        MockProviderSettings::default()
+    });
+    mock::apply(service, &settings);
+}
+
+/// Configure the OpenAI-compatible provider from its saved endpoint and key
+async fn restore_openai_compatible(service: &mut GenerationService, db: &db::Database) {
+    use generation::providers::openai_compatible::{
+        OpenAICompatibleConfig, OPENAI_COMPATIBLE_SETTINGS_KEY,
+    };
+
+    let config = match db::operations::SettingsOps::get_or_default::<OpenAICompatibleConfig>(
+        db.pool(),
+        OPENAI_COMPATIBLE_SETTINGS_KEY,
+    )
+    .await
+    {
+        Ok(config) if !config.base_url.is_empty() => config,
+        Ok(_) => return,
+        Err(e) => {
+            log_warn!("[Setup] Failed to load OpenAI-compatible settings: {}", e);
+            return;
+        }
+    };
+
+    let api_key = secrets::load_api_key(db.data_dir(), "openai_compatible").unwrap_or_else(|e| {
+        log_warn!("[Setup] Failed to load API key for openai_compatible: {}", e);
+        None
+    });
+    let config = OpenAICompatibleConfig { api_key, ..config };
+    if let Err(e) = service.configure_openai_compatible(config) {
+        log_warn!("[Setup] Failed to configure openai_compatible: {}", e);
+    }
+}
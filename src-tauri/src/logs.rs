@@ -0,0 +1,170 @@
+//! App log shared by the terminal and the in-app log console.
+//!
+//! The `log_*!` macros print to stderr like `eprintln!` did and also keep the line in a
+//! short in-memory history and a broadcast channel, which `subscribe_logs` forwards to
+//! the frontend as `log-entry` events. A leading `[Tag]` becomes the entry's target.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock, RwLock};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast;
+
+/// Event emitted to the frontend for each log line at or above the subscribed level
+pub const LOG_EVENT: &str = "log-entry";
+
+/// Lines kept for a console opened after they were logged
+const HISTORY_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// One log line
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// Tag of the line, e.g. `Network` for `[Network] Online`
+    pub target: Option<String>,
+    pub message: String,
+}
+
+struct LogHub {
+    sender: broadcast::Sender<LogEntry>,
+    history: Mutex<VecDeque<LogEntry>>,
+}
+
+fn hub() -> &'static LogHub {
+    static HUB: OnceLock<LogHub> = OnceLock::new();
+    HUB.get_or_init(|| LogHub {
+        sender: broadcast::channel(HISTORY_LEN).0,
+        history: Mutex::new(VecDeque::with_capacity(HISTORY_LEN)),
+    })
+}
+
+/// Print a line to stderr and hand it to log subscribers; used by the `log_*!` macros
+pub fn record(level: LogLevel, message: String) {
+    eprintln!("{}", message);
+
+    let (target, message) = split_target(&message);
+    let entry = LogEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        level,
+        target: target.map(str::to_string),
+        message: message.to_string(),
+    };
+    let hub = hub();
+    {
+        let mut history = hub.history.lock().unwrap();
+        if history.len() == HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(entry.clone());
+    }
+    // Nobody listening is fine
+    let _ = hub.sender.send(entry);
+}
+
+/// Recent lines at or above `level`, oldest first
+pub fn history(level: LogLevel) -> Vec<LogEntry> {
+    hub()
+        .history
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|entry| entry.level >= level)
+        .cloned()
+        .collect()
+}
+
+pub fn subscribe() -> broadcast::Receiver<LogEntry> {
+    hub().sender.subscribe()
+}
+
+/// Managed state holding the level the frontend console subscribed to
+#[derive(Default)]
+pub struct LogConsole {
+    level: RwLock<Option<LogLevel>>,
+}
+
+impl LogConsole {
+    /// Start sending lines at or above `level`; `None` stops
+    pub fn set_level(&self, level: Option<LogLevel>) {
+        *self.level.write().unwrap() = level;
+    }
+
+    fn wants(&self, level: LogLevel) -> bool {
+        self.level.read().unwrap().is_some_and(|min| level >= min)
+    }
+}
+
+/// Forward new lines to the frontend while a console is subscribed
+pub fn spawn_forwarder(app: AppHandle) {
+    let mut receiver = subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let entry = match receiver.recv().await {
+                Ok(entry) => entry,
+                // A burst overflowed the channel; the history still has the lines
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if !app.state::<LogConsole>().wants(entry.level) {
+                continue;
+            }
+            // Not logged on failure, which would feed back into this loop
+            let _ = app.emit(LOG_EVENT, &entry);
+        }
+    });
+}
+
+/// Split `[Tag] message` into its tag and message
+fn split_target(line: &str) -> (Option<&str>, &str) {
+    line.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(target, message)| (Some(target), message.trim_start()))
+        .unwrap_or((None, line))
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::logs::record($crate::logs::LogLevel::Debug, format!($($arg)*))
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::logs::record($crate::logs::LogLevel::Info, format!($($arg)*))
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::logs::record($crate::logs::LogLevel::Warn, format!($($arg)*))
+    };
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::logs::record($crate::logs::LogLevel::Error, format!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_target() {
+        assert_eq!(split_target("[Network] Online"), (Some("Network"), "Online"));
+        assert_eq!(split_target("Rate limit reached"), (None, "Rate limit reached"));
+        assert_eq!(split_target("[Plugin my-flux] ready"), (Some("Plugin my-flux"), "ready"));
+    }
+}
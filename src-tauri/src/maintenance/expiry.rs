@@ -0,0 +1,372 @@
+//! Expiry of generated asset files that were never kept.
+//!
+//! Rules apply per workflow, with a rule without a workflow covering all others. A
+//! completed generation older than its rule's age has its local output files deleted,
+//! together with the job, unless it was rated or approved (saved as a scene). Files a
+//! scene's thumbnail or a job that does not expire still points at are never deleted;
+//! result cache hits share the file of the job they copied.
+//!
+//! Deletion runs as a maintenance job whose data is the preview it was queued with, and
+//! only generations that are in the preview and still expired when the job runs are
+//! deleted. Scheduled runs queue that job a grace period ahead, so the preview can be
+//! reviewed and the job cancelled before anything is removed.
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use super::integrity;
+use crate::audit;
+use crate::db::models::{CreateJobInput, Job, BATCH_LANE};
+use crate::db::operations::{JobOps, SceneOps, SettingsOps};
+use crate::export::local_asset_reference;
+
+/// Settings key for asset expiry rules
+pub const ASSET_EXPIRY_SETTINGS_KEY: &str = "asset_expiry";
+
+/// Job type of the asset expiry maintenance job
+pub const ASSET_EXPIRY_JOB_TYPE: &str = "asset_expiry";
+
+/// Longest age a rule can set, about a century
+pub const MAX_AGE_DAYS: u32 = 36_500;
+
+/// When generations of a workflow expire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpiryRule {
+    /// Workflow the rule applies to; `None` applies to workflows without a rule of their own
+    pub workflow_id: Option<String>,
+    /// Delete generations older than this many days; `None` keeps them forever
+    pub max_age_days: Option<u32>,
+    /// Keep generations that have a rating
+    pub keep_rated: bool,
+    /// Keep generations saved as a scene
+    pub keep_approved: bool,
+}
+
+impl Default for ExpiryRule {
+    fn default() -> Self {
+        Self {
+            workflow_id: None,
+            max_age_days: Some(90),
+            keep_rated: true,
+            keep_approved: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetExpirySettings {
+    /// Queue an expiry job automatically
+    pub enabled: bool,
+    pub rules: Vec<ExpiryRule>,
+    /// How far ahead automatic expiry jobs are scheduled, leaving time to cancel them
+    pub grace_hours: u32,
+}
+
+impl Default for AssetExpirySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rules: vec![ExpiryRule::default()],
+            grace_hours: 24,
+        }
+    }
+}
+
+impl AssetExpirySettings {
+    /// Check the rules before they are saved
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            if let Some(days) = rule.max_age_days.filter(|d| !(1..=MAX_AGE_DAYS).contains(d)) {
+                return Err(anyhow::anyhow!(
+                    "Expiry age must be 1 to {} days, got {}",
+                    MAX_AGE_DAYS,
+                    days
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The workflow's own rule, or the rule for all workflows
+    pub fn rule_for(&self, workflow_id: &str) -> Option<&ExpiryRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.workflow_id.as_deref() == Some(workflow_id))
+            .or_else(|| self.rules.iter().find(|rule| rule.workflow_id.is_none()))
+    }
+}
+
+/// A generation whose files would be deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringAsset {
+    pub job_id: String,
+    pub workflow_id: String,
+    pub created_at: String,
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// What an expiry run would delete; also the data of an expiry job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExpiryPreview {
+    pub assets: Vec<ExpiringAsset>,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Outcome of an expiry job
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiryReport {
+    pub deleted_jobs: usize,
+    pub deleted_files: usize,
+    pub freed_bytes: u64,
+    /// Previewed generations that were rated, approved or removed since, or whose files
+    /// could not all be deleted
+    pub skipped: Vec<String>,
+}
+
+/// Whether a generation with `data` is past its rule and not kept by it
+pub fn is_expired(rule: &ExpiryRule, created_at: &str, data: &Value, approved: bool) -> bool {
+    let Some(days) = rule.max_age_days else {
+        return false;
+    };
+    // An age reaching before the earliest representable date never expires
+    let Some(cutoff) = Utc::now().checked_sub_signed(Duration::days(days as i64)) else {
+        return false;
+    };
+    let cutoff = cutoff.to_rfc3339();
+    let rated = data.get("rating").is_some_and(Value::is_number);
+    created_at < cutoff.as_str()
+        && !(rule.keep_rated && rated)
+        && !(rule.keep_approved && approved)
+}
+
+/// Generations the saved rules would expire now, oldest first
+pub async fn preview(pool: &SqlitePool) -> Result<ExpiryPreview> {
+    let settings: AssetExpirySettings =
+        SettingsOps::get_or_default(pool, ASSET_EXPIRY_SETTINGS_KEY).await?;
+
+    let mut approved = HashSet::new();
+    let mut thumbnails = HashSet::new();
+    for scene in SceneOps::list_all(pool).await? {
+        let data: Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        if let Some(job_id) = data.pointer("/metadata/jobId").and_then(|v| v.as_str()) {
+            approved.insert(job_id.to_string());
+        }
+        if let Some(path) = scene.thumbnail.as_deref().and_then(local_asset_reference) {
+            thumbnails.insert(path);
+        }
+    }
+
+    let jobs: Vec<Job> = sqlx::query_as(
+        "SELECT * FROM jobs WHERE result IS NOT NULL ORDER BY created_at ASC",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Every other job with a result keeps its files
+    let mut expiring = Vec::new();
+    let mut kept = HashSet::new();
+    for job in jobs {
+        let expired = job.job_type == "generation"
+            && job.status == "completed"
+            && settings.rule_for(&job.workflow_id).is_some_and(|rule| {
+                let data: Value = serde_json::from_str(&job.data).unwrap_or_default();
+                is_expired(rule, &job.created_at, &data, approved.contains(&job.id))
+            });
+        if expired {
+            expiring.push(job);
+        } else {
+            kept.extend(integrity::job_outputs(&job).into_iter().map(|(path, _)| path));
+        }
+    }
+
+    let mut preview = ExpiryPreview::default();
+    for job in expiring {
+        let files: Vec<(PathBuf, u64)> = integrity::job_outputs(&job)
+            .into_iter()
+            .filter(|(path, _)| !thumbnails.contains(path) && !kept.contains(path))
+            .filter_map(|(path, _)| Some((path.clone(), std::fs::metadata(&path).ok()?.len())))
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+
+        let bytes = files.iter().map(|(_, size)| size).sum();
+        preview.files += files.len();
+        preview.bytes += bytes;
+        preview.assets.push(ExpiringAsset {
+            job_id: job.id,
+            workflow_id: job.workflow_id,
+            created_at: job.created_at,
+            files: files.iter().map(|(path, _)| path.display().to_string()).collect(),
+            bytes,
+        });
+    }
+
+    Ok(preview)
+}
+
+/// Queue an expiry job for `preview`; `None` when there is nothing to delete
+pub async fn queue(
+    pool: &SqlitePool,
+    preview: &ExpiryPreview,
+    scheduled_at: Option<String>,
+) -> Result<Option<Job>> {
+    // Jobs belong to a workflow; attach it to the first expiring generation's
+    let Some(first) = preview.assets.first() else {
+        return Ok(None);
+    };
+    let job = JobOps::create(
+        pool,
+        CreateJobInput {
+            workflow_id: first.workflow_id.clone(),
+            scene_id: None,
+            job_type: ASSET_EXPIRY_JOB_TYPE.to_string(),
+            data: serde_json::to_value(preview)?,
+            scheduled_at,
+            lane: Some(BATCH_LANE.to_string()),
+        },
+    )
+    .await?;
+
+    Ok(Some(job))
+}
+
+/// Queue an expiry job a grace period ahead if automatic expiry is enabled and none is
+/// waiting already
+pub async fn run_scheduled(pool: &SqlitePool) -> Result<Option<Job>> {
+    let settings: AssetExpirySettings =
+        SettingsOps::get_or_default(pool, ASSET_EXPIRY_SETTINGS_KEY).await?;
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let (waiting,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM jobs WHERE type = ? AND status IN ('pending', 'running')",
+    )
+    .bind(ASSET_EXPIRY_JOB_TYPE)
+    .fetch_one(pool)
+    .await?;
+    if waiting > 0 {
+        return Ok(None);
+    }
+
+    let at = (Utc::now() + Duration::hours(settings.grace_hours as i64)).to_rfc3339();
+    let job = queue(pool, &preview(pool).await?, Some(at)).await?;
+    if let Some(job) = &job {
+        log_info!("[Expiry] Queued asset expiry job {}", job.id);
+    }
+    Ok(job)
+}
+
+/// Delete the previewed generations of an expiry job that are still expired
+pub async fn run(pool: &SqlitePool, job: &Job) -> Result<ExpiryReport> {
+    let previewed: ExpiryPreview = serde_json::from_str(&job.data)?;
+    let current = preview(pool).await?;
+
+    let mut deleted = Vec::new();
+    let mut report = ExpiryReport {
+        deleted_jobs: 0,
+        deleted_files: 0,
+        freed_bytes: 0,
+        skipped: Vec::new(),
+    };
+    for asset in &previewed.assets {
+        let Some(still) = current.assets.iter().find(|a| a.job_id == asset.job_id) else {
+            report.skipped.push(asset.job_id.clone());
+            continue;
+        };
+
+        // Only files that were previewed, in case the generation gained new ones
+        let mut complete = true;
+        for file in still.files.iter().filter(|file| asset.files.contains(file)) {
+            let size = tokio::fs::metadata(file).await.map(|m| m.len()).unwrap_or(0);
+            match tokio::fs::remove_file(file).await {
+                Ok(()) => {
+                    report.deleted_files += 1;
+                    report.freed_bytes += size;
+                }
+                // Another expired cache hit sharing the file deleted it already
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    log_warn!("[Expiry] Could not delete {}: {}", file, e);
+                    complete = false;
+                }
+            }
+        }
+
+        // Keep the job while it still references files on disk
+        if complete {
+            JobOps::delete(pool, &asset.job_id).await?;
+            report.deleted_jobs += 1;
+            deleted.push(asset.job_id.as_str());
+        } else {
+            report.skipped.push(asset.job_id.clone());
+        }
+    }
+
+    log_info!(
+        "[Expiry] Deleted {} generations ({} files, {} bytes)",
+        report.deleted_jobs,
+        report.deleted_files,
+        report.freed_bytes
+    );
+    audit::record(
+        pool,
+        "assets.expire",
+        Some(&job.id),
+        serde_json::json!({ "deleted": deleted, "freed_bytes": report.freed_bytes }),
+    )
+    .await;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let settings = AssetExpirySettings {
+            rules: vec![
+                ExpiryRule::default(),
+                ExpiryRule {
+                    workflow_id: Some("keep".to_string()),
+                    max_age_days: None,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let rule = settings.rule_for("other").unwrap();
+        let old = "2020-01-01T00:00:00+00:00";
+        let unrated = serde_json::json!({ "prompt": "a fox" });
+        let rated = serde_json::json!({ "prompt": "a fox", "rating": 4 });
+
+        assert!(is_expired(rule, old, &unrated, false));
+        assert!(!is_expired(rule, old, &rated, false));
+        assert!(!is_expired(rule, old, &unrated, true));
+        assert!(!is_expired(rule, &Utc::now().to_rfc3339(), &unrated, false));
+        assert!(!is_expired(settings.rule_for("keep").unwrap(), old, &unrated, false));
+
+        let forever = ExpiryRule {
+            max_age_days: Some(u32::MAX),
+            ..Default::default()
+        };
+        assert!(!is_expired(&forever, old, &unrated, false));
+        let settings = AssetExpirySettings {
+            rules: vec![forever],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+        assert!(AssetExpirySettings::default().validate().is_ok());
+    }
+}
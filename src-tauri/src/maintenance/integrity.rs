@@ -0,0 +1,150 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use crate::db::models::Job;
+use crate::db::operations::{JobOps, SceneOps};
+use crate::export::local_asset_reference;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::GenerationResult;
+use crate::storage::paths;
+
+/// Event emitted when the startup scan finds missing asset files
+pub const INTEGRITY_REPORT_EVENT: &str = "asset-integrity-report";
+
+/// An asset file referenced in the database that no longer exists on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingAsset {
+    /// `job` or `scene`
+    pub kind: String,
+    pub id: String,
+    pub path: String,
+    /// Remote URL the file was originally downloaded from, if known
+    pub remote_url: Option<String>,
+    /// Whether the file was downloaded again during this scan
+    pub restored: bool,
+    pub error: Option<String>,
+}
+
+/// Outcome of an integrity scan
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub checked: usize,
+    pub missing: Vec<MissingAsset>,
+    pub restored: usize,
+}
+
+/// Check that every local file referenced by jobs and scenes still exists
+///
+/// With `redownload`, missing files whose result still has a remote URL are fetched
+/// again into their original location.
+pub async fn scan(pool: &SqlitePool, redownload: bool) -> Result<IntegrityReport> {
+    let jobs = JobOps::list_all(pool).await?;
+    let scenes = SceneOps::list_all(pool).await?;
+    let client = ProviderTimeouts::default().client();
+
+    let mut checked = 0;
+    let mut missing = Vec::new();
+
+    for job in &jobs {
+        for (path, remote_url) in job_outputs(job) {
+            checked += 1;
+            if !path.exists() {
+                missing.push(missing_asset("job", &job.id, &path, remote_url));
+            }
+        }
+    }
+
+    for scene in &scenes {
+        let Some(path) = scene.thumbnail.as_deref().and_then(local_asset_reference) else {
+            continue;
+        };
+        checked += 1;
+        if path.exists() {
+            continue;
+        }
+
+        // Thumbnails saved from a job can be restored from that job's remote output
+        let data: serde_json::Value = serde_json::from_str(&scene.data).unwrap_or_default();
+        let remote_url = match data.pointer("/metadata/jobId").and_then(|v| v.as_str()) {
+            Some(job_id) => jobs
+                .iter()
+                .find(|job| job.id == job_id)
+                .and_then(|job| job_outputs(job).into_iter().next())
+                .and_then(|(_, url)| url),
+            None => None,
+        };
+        missing.push(missing_asset("scene", &scene.id, &path, remote_url));
+    }
+
+    if redownload {
+        for asset in missing.iter_mut() {
+            let Some(url) = asset.remote_url.clone() else {
+                continue;
+            };
+            match download(&client, &url, Path::new(&asset.path)).await {
+                Ok(()) => asset.restored = true,
+                Err(e) => asset.error = Some(e.to_string()),
+            }
+        }
+    }
+
+    let restored = missing.iter().filter(|asset| asset.restored).count();
+    if !missing.is_empty() {
+        log_info!(
+            "[Integrity] {} of {} asset files missing, {} restored",
+            missing.len(),
+            checked,
+            restored
+        );
+    }
+
+    Ok(IntegrityReport {
+        checked,
+        missing,
+        restored,
+    })
+}
+
+fn missing_asset(kind: &str, id: &str, path: &Path, remote_url: Option<String>) -> MissingAsset {
+    MissingAsset {
+        kind: kind.to_string(),
+        id: id.to_string(),
+        path: path.display().to_string(),
+        remote_url,
+        restored: false,
+        error: None,
+    }
+}
+
+/// Local output files of a completed job, each with the remote URL it came from, if any
+pub fn job_outputs(job: &Job) -> Vec<(PathBuf, Option<String>)> {
+    let result: Option<GenerationResult> = job
+        .result
+        .as_deref()
+        .and_then(|r| serde_json::from_str(r).ok());
+
+    result
+        .into_iter()
+        .flat_map(|result| result.artifacts)
+        .filter_map(|artifact| {
+            let path = artifact.file_path.filter(|p| !p.is_empty())?;
+            let remote_url = artifact
+                .url
+                .filter(|url| url.starts_with("http://") || url.starts_with("https://"));
+            Some((PathBuf::from(path), remote_url))
+        })
+        .collect()
+}
+
+async fn download(client: &reqwest::Client, url: &str, path: &Path) -> Result<()> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    let bytes = response.bytes().await?;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    paths::write_atomic(path, &bytes).await?;
+    Ok(())
+}
@@ -0,0 +1,5 @@
+pub mod expiry;
+pub mod integrity;
+pub mod optimize;
+pub mod retention;
+pub mod thumbnails;
@@ -0,0 +1,143 @@
+//! Database compaction after heavy job churn: incremental vacuum, WAL checkpoint
+//! and refreshed query planner statistics.
+//!
+//! The processor runs it while the queue is idle; `optimize_database` runs it on demand.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+use crate::db::compat;
+use crate::db::models::now;
+use crate::db::operations::JobOps;
+
+/// Minimum time between idle maintenance runs
+pub const OPTIMIZE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+
+/// How often the processor checks whether the queue is idle
+pub const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// `app_meta` key holding the time of the last run
+const LAST_OPTIMIZED_KEY: &str = "last_optimized_at";
+
+/// `PRAGMA auto_vacuum` value for incremental mode
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+/// Outcome of a maintenance run
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizeReport {
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub wal_before_bytes: u64,
+    pub wal_after_bytes: u64,
+    /// Steps taken, in order
+    pub steps: Vec<String>,
+    pub finished_at: String,
+}
+
+/// Vacuum, analyze and checkpoint the database
+pub async fn optimize(pool: &SqlitePool) -> Result<OptimizeReport> {
+    let file = db_file(pool).await?;
+    let (size_before_bytes, wal_before_bytes) = file_sizes(file.as_deref()).await;
+    let mut steps = Vec::new();
+
+    // auto_vacuum is per connection until a VACUUM writes it to the header
+    let mut conn = pool.acquire().await?;
+    let auto_vacuum: i64 = sqlx::query_scalar("PRAGMA auto_vacuum")
+        .fetch_one(&mut *conn)
+        .await?;
+    if auto_vacuum == AUTO_VACUUM_INCREMENTAL {
+        sqlx::query("PRAGMA incremental_vacuum")
+            .execute(&mut *conn)
+            .await?;
+        steps.push("Released free pages".to_string());
+    } else {
+        // Databases created before incremental mode need one full rebuild to switch
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("VACUUM").execute(&mut *conn).await?;
+        steps.push("Rebuilt the database with incremental vacuum enabled".to_string());
+    }
+
+    sqlx::query("ANALYZE").execute(&mut *conn).await?;
+    sqlx::query("PRAGMA optimize").execute(&mut *conn).await?;
+    steps.push("Refreshed query planner statistics".to_string());
+
+    // Last, so the statistics written above do not leave the log grown again
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&mut *conn)
+        .await?;
+    steps.push("Checkpointed the write-ahead log".to_string());
+    drop(conn);
+
+    let finished_at = now();
+    compat::set_meta(pool, LAST_OPTIMIZED_KEY, &finished_at).await?;
+
+    let (size_after_bytes, wal_after_bytes) = file_sizes(file.as_deref()).await;
+    Ok(OptimizeReport {
+        size_before_bytes,
+        size_after_bytes,
+        wal_before_bytes,
+        wal_after_bytes,
+        steps,
+        finished_at,
+    })
+}
+
+/// Optimize if the queue is idle and the last run is older than [`OPTIMIZE_INTERVAL`]
+pub async fn run_if_idle(pool: &SqlitePool) -> Result<Option<OptimizeReport>> {
+    let last_run = compat::get_meta(pool, LAST_OPTIMIZED_KEY).await?;
+    if !is_due(last_run.as_deref(), Utc::now()) {
+        return Ok(None);
+    }
+    if JobOps::count_active(pool).await? > 0 {
+        return Ok(None);
+    }
+    optimize(pool).await.map(Some)
+}
+
+/// Whether a run is due; unreadable timestamps count as never run
+fn is_due(last_run: Option<&str>, now: DateTime<Utc>) -> bool {
+    let Some(last_run) = last_run.and_then(|t| DateTime::parse_from_rfc3339(t).ok()) else {
+        return true;
+    };
+    let elapsed = now.signed_duration_since(last_run.with_timezone(&Utc));
+    elapsed.to_std().is_ok_and(|elapsed| elapsed >= OPTIMIZE_INTERVAL)
+}
+
+async fn db_file(pool: &SqlitePool) -> Result<Option<String>> {
+    let file: Option<String> =
+        sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+            .fetch_optional(pool)
+            .await?;
+    Ok(file.filter(|file| !file.is_empty()))
+}
+
+/// Sizes of the database file and its write-ahead log; 0 when missing
+async fn file_sizes(file: Option<&str>) -> (u64, u64) {
+    let Some(file) = file else {
+        return (0, 0);
+    };
+    let size = |path: String| async move {
+        tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0)
+    };
+    (size(file.to_string()).await, size(format!("{}-wal", file)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        let recent = (now - chrono::Duration::hours(1)).to_rfc3339();
+        let old = (now - chrono::Duration::hours(7)).to_rfc3339();
+        assert!(!is_due(Some(&recent), now));
+        assert!(is_due(Some(&old), now));
+        assert!(is_due(None, now));
+        assert!(is_due(Some("garbage"), now));
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::audit;
+use crate::db::models::Job;
+use crate::db::operations::SettingsOps;
+
+/// Settings key for the job retention policy
+pub const RETENTION_POLICY_KEY: &str = "retention_policy";
+
+/// How often the background task applies the retention policy
+pub const RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Longest age the policy can set, about a century
+pub const MAX_AGE_DAYS: u32 = 36_500;
+
+/// Job history retention policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub enabled: bool,
+    /// Archive finished jobs older than this many days
+    pub max_age_days: Option<u32>,
+    /// Keep at most this many finished jobs
+    pub max_jobs: Option<u32>,
+    /// Write archived jobs (including result JSON) to a JSONL file before deleting
+    pub archive_results: bool,
+    /// Directory for archive files (defaults to `<data dir>/archive`)
+    pub archive_dir: Option<String>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_days: Some(90),
+            max_jobs: Some(5000),
+            archive_results: true,
+            archive_dir: None,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(days) = self.max_age_days.filter(|d| !(1..=MAX_AGE_DAYS).contains(d)) {
+            return Err(anyhow::anyhow!(
+                "Retention age must be 1 to {} days, got {}",
+                MAX_AGE_DAYS,
+                days
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a retention pass
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub deleted: usize,
+    pub archive_file: Option<String>,
+}
+
+/// Apply the stored policy if it is enabled
+pub async fn run_scheduled(pool: &SqlitePool, data_dir: &Path) -> Result<Option<RetentionReport>> {
+    let policy: RetentionPolicy = SettingsOps::get_or_default(pool, RETENTION_POLICY_KEY).await?;
+    if !policy.enabled {
+        return Ok(None);
+    }
+    apply(pool, &policy, data_dir).await.map(Some)
+}
+
+/// Archive and delete finished jobs that fall outside the policy
+pub async fn apply(pool: &SqlitePool, policy: &RetentionPolicy, data_dir: &Path) -> Result<RetentionReport> {
+    let mut expired: Vec<Job> = Vec::new();
+
+    // An age reaching before the earliest representable date keeps every job
+    let cutoff = policy
+        .max_age_days
+        .and_then(|days| Utc::now().checked_sub_signed(Duration::days(days as i64)));
+    if let Some(cutoff) = cutoff {
+        let cutoff = cutoff.to_rfc3339();
+        let jobs: Vec<Job> = sqlx::query_as(
+            "SELECT * FROM jobs WHERE status IN ('completed', 'failed') AND COALESCE(completed_at, created_at) < ?",
+        )
+        .bind(&cutoff)
+        .fetch_all(pool)
+        .await?;
+        expired.extend(jobs);
+    }
+
+    if let Some(max_jobs) = policy.max_jobs {
+        let jobs: Vec<Job> = sqlx::query_as(
+            "SELECT * FROM jobs WHERE status IN ('completed', 'failed') ORDER BY COALESCE(completed_at, created_at) DESC LIMIT -1 OFFSET ?",
+        )
+        .bind(max_jobs as i64)
+        .fetch_all(pool)
+        .await?;
+        expired.extend(jobs);
+    }
+
+    let mut seen = HashSet::new();
+    expired.retain(|job| seen.insert(job.id.clone()));
+
+    if expired.is_empty() {
+        return Ok(RetentionReport {
+            deleted: 0,
+            archive_file: None,
+        });
+    }
+
+    // Archive first so a failed write never loses data
+    let archive_file = if policy.archive_results {
+        let dir = policy
+            .archive_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| data_dir.join("archive"));
+        Some(write_archive(&dir, &expired).await?)
+    } else {
+        None
+    };
+
+    let mut tx = pool.begin().await?;
+    for job in &expired {
+        sqlx::query("DELETE FROM jobs WHERE id = ?")
+            .bind(&job.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    log_info!("[Retention] Removed {} old jobs from history", expired.len());
+
+    let report = RetentionReport {
+        deleted: expired.len(),
+        archive_file: archive_file.map(|p| p.display().to_string()),
+    };
+    let job_ids: Vec<&str> = expired.iter().map(|job| job.id.as_str()).collect();
+    audit::record(
+        pool,
+        "retention.apply",
+        None,
+        serde_json::json!({ "deleted": job_ids, "archive_file": report.archive_file }),
+    )
+    .await;
+    Ok(report)
+}
+
+/// Write jobs as JSON lines to a timestamped archive file
+async fn write_archive(dir: &Path, jobs: &[Job]) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    let file_path = dir.join(format!("jobs-{}.jsonl", Utc::now().format("%Y%m%d-%H%M%S")));
+    let mut contents = String::new();
+    for job in jobs {
+        contents.push_str(&serde_json::to_string(job)?);
+        contents.push('\n');
+    }
+
+    tokio::fs::write(&file_path, contents).await?;
+    Ok(file_path)
+}
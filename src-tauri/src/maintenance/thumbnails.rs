@@ -0,0 +1,140 @@
+//! Backfill of local thumbnail files for legacy scenes.
+//!
+//! Older scenes store their thumbnail inline as a base64 data URL (often several MB
+//! per row) or as a provider URL that eventually expires. The backfill runs as a
+//! low-priority job in the batch lane and converts a few scenes per pass, requeueing
+//! itself until no legacy thumbnails are left.
+
+use anyhow::Result;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+use crate::db::models::{Job, Scene};
+use crate::db::operations::{JobOps, SceneOps};
+use crate::generation::images_dir;
+use crate::generation::timeouts::ProviderTimeouts;
+use crate::generation::utils::extract_base64_from_data_url;
+
+/// Job type of the thumbnail backfill
+pub const THUMBNAIL_BACKFILL_JOB_TYPE: &str = "thumbnail_backfill";
+
+/// Longest edge of generated thumbnails
+const THUMBNAIL_SIZE: u32 = 512;
+
+fn default_batch_size() -> u32 {
+    20
+}
+
+/// Job data of a backfill job, updated after every pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillData {
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+    #[serde(default)]
+    pub converted: u32,
+    /// Scenes whose thumbnail could not be converted; skipped in later passes
+    #[serde(default)]
+    pub failed_scene_ids: Vec<String>,
+}
+
+/// Result of one backfill pass
+#[derive(Debug, Clone, Serialize)]
+pub struct BackfillPass {
+    pub converted: u32,
+    /// Whether legacy scenes remain for a later pass
+    pub remaining: bool,
+}
+
+/// Scenes whose thumbnail is still an inline data URL or a remote URL
+pub async fn legacy_scenes(pool: &SqlitePool, limit: u32) -> Result<Vec<Scene>> {
+    let scenes = sqlx::query_as::<_, Scene>(
+        r#"
+        SELECT * FROM scenes
+        WHERE thumbnail LIKE 'data:%' OR thumbnail LIKE 'http://%' OR thumbnail LIKE 'https://%'
+        ORDER BY created_at ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(scenes)
+}
+
+/// Convert the next batch of legacy thumbnails and persist progress in the job data
+pub async fn run_pass(pool: &SqlitePool, job: &Job) -> Result<BackfillPass> {
+    let mut data: BackfillData = serde_json::from_str(&job.data)?;
+    let batch_size = data.batch_size.max(1);
+
+    // Fetch extra rows so earlier failures do not starve the batch
+    let limit = batch_size + data.failed_scene_ids.len() as u32;
+    let candidates: Vec<Scene> = legacy_scenes(pool, limit + 1)
+        .await?
+        .into_iter()
+        .filter(|scene| !data.failed_scene_ids.contains(&scene.id))
+        .collect();
+
+    let client = ProviderTimeouts::default().client();
+    let mut converted = 0;
+    for scene in candidates.iter().take(batch_size as usize) {
+        match convert_thumbnail(&client, scene).await {
+            Ok(path) => {
+                let thumbnail = format!("asset://localhost/{}", path.display());
+                SceneOps::set_thumbnail(pool, &scene.id, &thumbnail).await?;
+                converted += 1;
+            }
+            Err(e) => {
+                log_warn!("[Thumbnails] Could not convert thumbnail of scene {}: {}", scene.id, e);
+                data.failed_scene_ids.push(scene.id.clone());
+            }
+        }
+    }
+
+    data.converted += converted;
+    JobOps::update_data(pool, &job.id, &serde_json::to_value(&data)?).await?;
+
+    Ok(BackfillPass {
+        converted,
+        remaining: candidates.len() > batch_size as usize,
+    })
+}
+
+/// Write a downscaled local copy of a scene's thumbnail
+async fn convert_thumbnail(client: &reqwest::Client, scene: &Scene) -> Result<PathBuf> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let thumbnail = scene.thumbnail.as_deref().unwrap_or_default();
+    let bytes = if thumbnail.starts_with("data:") {
+        let (_, data) = extract_base64_from_data_url(thumbnail).map_err(anyhow::Error::msg)?;
+        general_purpose::STANDARD.decode(data.trim())?
+    } else {
+        client
+            .get(thumbnail)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec()
+    };
+
+    let dir = images_dir()?.join("thumbnails");
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(format!("{}.png", scene.id));
+
+    let output = path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut image = image::load_from_memory(&bytes)?;
+        if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+            image = image.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle);
+        }
+        image.save_with_format(&output, image::ImageFormat::Png)?;
+        Ok(())
+    })
+    .await??;
+
+    Ok(path)
+}
@@ -0,0 +1,258 @@
+use anyhow::Result;
+use chrono::DateTime;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+pub mod audio;
+
+use crate::db::models::{Job, NotificationRule};
+use crate::db::operations::{JobOps, NotificationRuleOps};
+use crate::i18n;
+
+/// Event emitted to the frontend when a job finishes and a rule allows it
+pub const NOTIFICATION_EVENT: &str = "job-notification";
+
+/// Payload of the `job-notification` event
+#[derive(Debug, Clone, Serialize)]
+pub struct JobNotification {
+    pub job_id: String,
+    pub workflow_id: String,
+    pub status: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub duration_seconds: Option<i64>,
+    pub error: Option<String>,
+    /// Rule that triggered this notification, `None` when no rules are configured
+    pub rule_id: Option<String>,
+}
+
+impl JobNotification {
+    fn from_job(job: &Job) -> Self {
+        let data: serde_json::Value = serde_json::from_str(&job.data).unwrap_or_default();
+        let field = |key: &str| data.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+        Self {
+            job_id: job.id.clone(),
+            workflow_id: job.workflow_id.clone(),
+            status: job.status.clone(),
+            provider: field("provider"),
+            model: field("model"),
+            duration_seconds: duration_seconds(job),
+            error: job.error.clone(),
+            rule_id: None,
+        }
+    }
+
+    fn message(&self) -> String {
+        let status = i18n::t(&format!("status.{}", self.status), &[]);
+        let provider = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => i18n::t("notification.unknown_provider", &[]),
+        };
+        let model = match &self.model {
+            Some(model) => model.clone(),
+            None => i18n::t("notification.default_model", &[]),
+        };
+        let mut message = i18n::t(
+            "notification.job_finished",
+            &[
+                ("job_id", &self.job_id),
+                ("status", &status),
+                ("provider", &provider),
+                ("model", &model),
+            ],
+        );
+        if let Some(secs) = self.duration_seconds {
+            let seconds = secs.to_string();
+            message.push_str(&i18n::t("notification.after_seconds", &[("seconds", &seconds)]));
+        }
+        if let Some(error) = &self.error {
+            message.push_str(&format!(": {}", error));
+        }
+        message
+    }
+}
+
+/// Evaluate notification rules for a finished job and deliver matching notifications
+///
+/// With no rules configured every finished job is sent to the app, matching the
+/// behaviour before rules existed.
+pub async fn notify_job_finished(
+    pool: &SqlitePool,
+    app_handle: &AppHandle,
+    job_id: &str,
+) -> Result<()> {
+    let Some(job) = JobOps::get(pool, job_id).await? else {
+        return Ok(());
+    };
+    if job.status != "completed" && job.status != "failed" {
+        return Ok(());
+    }
+
+    let notification = JobNotification::from_job(&job);
+    audio::announce(pool, &notification).await;
+    let rules = NotificationRuleOps::list(pool).await?;
+
+    if rules.is_empty() {
+        app_handle.emit(NOTIFICATION_EVENT, &notification)?;
+        return Ok(());
+    }
+
+    for rule in rules
+        .iter()
+        .filter(|rule| rule_matches(rule, &notification))
+    {
+        let notification = JobNotification {
+            rule_id: Some(rule.id.clone()),
+            ..notification.clone()
+        };
+
+        let delivered = match (rule.channel.as_str(), rule.target.as_deref()) {
+            ("discord", Some(webhook_url)) => {
+                send_discord(webhook_url, &notification.message()).await
+            }
+            _ => app_handle
+                .emit(NOTIFICATION_EVENT, &notification)
+                .map_err(Into::into),
+        };
+
+        if let Err(e) = delivered {
+            log_warn!(
+                "[Notifications] Rule '{}' failed to deliver for job {}: {}",
+                rule.name, job.id, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether every condition set on the rule holds for the notification
+fn rule_matches(rule: &NotificationRule, notification: &JobNotification) -> bool {
+    if !rule.enabled {
+        return false;
+    }
+    if rule.on_status != "any" && rule.on_status != notification.status {
+        return false;
+    }
+    if rule
+        .workflow_id
+        .as_ref()
+        .is_some_and(|id| *id != notification.workflow_id)
+    {
+        return false;
+    }
+    if rule.provider.is_some() && rule.provider != notification.provider {
+        return false;
+    }
+    if let Some(min) = rule.min_duration_seconds {
+        if notification.duration_seconds.is_none_or(|secs| secs < min) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Wall-clock run time from `started_at` to `completed_at`
+fn duration_seconds(job: &Job) -> Option<i64> {
+    let started = DateTime::parse_from_rfc3339(job.started_at.as_deref()?).ok()?;
+    let completed = DateTime::parse_from_rfc3339(job.completed_at.as_deref()?).ok()?;
+    Some((completed - started).num_seconds())
+}
+
+async fn send_discord(webhook_url: &str, message: &str) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?;
+
+    let response = client
+        .post(webhook_url)
+        .json(&serde_json::json!({ "content": message }))
+        .send()
+        .await?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow::anyhow!(
+            "Discord webhook error ({}): {}",
+            status,
+            error_text
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        on_status: &str,
+        workflow_id: Option<&str>,
+        min_duration_seconds: Option<i64>,
+    ) -> NotificationRule {
+        NotificationRule {
+            id: "rule".to_string(),
+            name: "rule".to_string(),
+            enabled: true,
+            channel: "app".to_string(),
+            target: None,
+            on_status: on_status.to_string(),
+            workflow_id: workflow_id.map(str::to_string),
+            provider: None,
+            min_duration_seconds,
+            created_at: String::new(),
+        }
+    }
+
+    fn notification(status: &str, duration_seconds: Option<i64>) -> JobNotification {
+        JobNotification {
+            job_id: "job".to_string(),
+            workflow_id: "wf".to_string(),
+            status: status.to_string(),
+            provider: Some("openai".to_string()),
+            model: None,
+            duration_seconds,
+            error: None,
+            rule_id: None,
+        }
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        // Only jobs longer than 5 minutes
+        let long_jobs = rule("any", None, Some(300));
+        assert!(rule_matches(
+            &long_jobs,
+            &notification("completed", Some(301))
+        ));
+        assert!(!rule_matches(
+            &long_jobs,
+            &notification("completed", Some(12))
+        ));
+        assert!(!rule_matches(&long_jobs, &notification("failed", None)));
+
+        // Only failures in a given workflow
+        let failures = rule("failed", Some("wf"), None);
+        assert!(rule_matches(&failures, &notification("failed", Some(1))));
+        assert!(!rule_matches(
+            &failures,
+            &notification("completed", Some(1))
+        ));
+        assert!(!rule_matches(
+            &rule("failed", Some("other"), None),
+            &notification("failed", None)
+        ));
+
+        let disabled = NotificationRule {
+            enabled: false,
+            ..rule("any", None, None)
+        };
+        assert!(!rule_matches(&disabled, &notification("completed", None)));
+    }
+}
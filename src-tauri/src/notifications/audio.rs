@@ -0,0 +1,155 @@
+//! Sounds and spoken announcements when jobs finish, for users away from the screen.
+//!
+//! Playback goes through the platform's own tools (`afplay`/`say` on macOS,
+//! `paplay`/`spd-say` on Linux, PowerShell on Windows) so no audio stack is bundled.
+//! A missing tool only logs a warning.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::JobNotification;
+use crate::db::operations::SettingsOps;
+
+/// Settings key for audio notifications
+pub const AUDIO_SETTINGS_KEY: &str = "audio_notifications";
+
+/// What to play for a finished job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioCue {
+    #[default]
+    Off,
+    /// The system's success or error sound, depending on the outcome
+    Sound,
+    /// The notification message read out by the system voice
+    Speech,
+}
+
+/// Cues per outcome, stored in the settings table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioNotificationSettings {
+    pub completed: AudioCue,
+    pub failed: AudioCue,
+    /// Stay quiet for jobs that finished faster than this, so only long renders are announced
+    pub min_duration_seconds: Option<i64>,
+}
+
+impl AudioNotificationSettings {
+    fn cue_for(&self, notification: &JobNotification) -> AudioCue {
+        let long_enough = match (self.min_duration_seconds, notification.duration_seconds) {
+            (Some(min), Some(secs)) => secs >= min,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        match notification.status.as_str() {
+            _ if !long_enough => AudioCue::Off,
+            "completed" => self.completed,
+            "failed" => self.failed,
+            _ => AudioCue::Off,
+        }
+    }
+}
+
+/// Play the configured cue for a finished job
+pub async fn announce(pool: &SqlitePool, notification: &JobNotification) {
+    let settings: AudioNotificationSettings =
+        match SettingsOps::get_or_default(pool, AUDIO_SETTINGS_KEY).await {
+            Ok(settings) => settings,
+            Err(e) => {
+                log_warn!("[Notifications] Failed to load audio settings: {}", e);
+                return;
+            }
+        };
+    play(settings.cue_for(notification), notification);
+}
+
+/// Start playing `cue` without waiting for it to finish
+///
+/// Must run inside the async runtime, which reaps the player once it exits.
+pub fn play(cue: AudioCue, notification: &JobNotification) {
+    let failed = notification.status == "failed";
+    let message = notification.message();
+    let Some((program, args)) = playback_command(std::env::consts::OS, cue, failed, &message)
+    else {
+        return;
+    };
+    let mut command = tokio::process::Command::new(&program);
+    command.args(&args).env(SPEECH_ENV, &message);
+    // PowerShell would otherwise open a console window for every cue
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    if let Err(e) = command.spawn() {
+        log_warn!("[Notifications] Could not run {} for an audio cue: {}", program, e);
+    }
+}
+
+/// Windows process flag for starting PowerShell without a console
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Environment variable handing the spoken text to PowerShell
+///
+/// Keeps the message out of the script, so no quoting of it can end the string early.
+const SPEECH_ENV: &str = "PC_TTS";
+
+/// Program and arguments playing `cue` on `os`
+///
+/// The Windows speech script reads the message from [`SPEECH_ENV`] instead.
+fn playback_command(
+    os: &str,
+    cue: AudioCue,
+    failed: bool,
+    message: &str,
+) -> Option<(String, Vec<String>)> {
+    let (program, args): (&str, Vec<String>) = match (os, cue) {
+        (_, AudioCue::Off) => return None,
+        ("macos", AudioCue::Sound) => {
+            let sound = if failed { "Basso" } else { "Glass" };
+            ("afplay", vec![format!("/System/Library/Sounds/{}.aiff", sound)])
+        }
+        ("macos", AudioCue::Speech) => ("say", vec![message.to_string()]),
+        ("windows", cue) => {
+            let script = match cue {
+                AudioCue::Sound if failed => "[System.Media.SystemSounds]::Hand.Play()".to_string(),
+                AudioCue::Sound => "[System.Media.SystemSounds]::Asterisk.Play()".to_string(),
+                _ => format!(
+                    "Add-Type -AssemblyName System.Speech; \
+                     (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:{})",
+                    SPEECH_ENV
+                ),
+            };
+            let args = ["-NoProfile", "-NonInteractive", "-Command", &script];
+            ("powershell", args.iter().map(|arg| arg.to_string()).collect())
+        }
+        (_, AudioCue::Sound) => {
+            let sound = if failed { "dialog-error" } else { "complete" };
+            ("paplay", vec![format!("/usr/share/sounds/freedesktop/stereo/{}.oga", sound)])
+        }
+        (_, AudioCue::Speech) => ("spd-say", vec![message.to_string()]),
+    };
+    Some((program.to_string(), args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_command() {
+        assert_eq!(playback_command("linux", AudioCue::Off, false, "done"), None);
+
+        let (program, args) = playback_command("macos", AudioCue::Sound, true, "done").unwrap();
+        assert_eq!(program, "afplay");
+        assert_eq!(args, vec!["/System/Library/Sounds/Basso.aiff"]);
+
+        let (program, args) = playback_command("windows", AudioCue::Speech, false, "it’s done')")
+            .unwrap();
+        assert_eq!(program, "powershell");
+        assert!(args[3].ends_with(".Speak($env:PC_TTS)"));
+        assert!(!args.iter().any(|arg| arg.contains("done")));
+
+        let (program, _) = playback_command("linux", AudioCue::Speech, false, "done").unwrap();
+        assert_eq!(program, "spd-say");
+    }
+}
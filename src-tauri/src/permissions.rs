@@ -0,0 +1,316 @@
+//! Permission tiers of Tauri commands and the workspace profile allowing them.
+//!
+//! Every command belongs to one tier; the active profile in `permissions.json` next to
+//! the database lists the tiers this instance may call. The file is edited outside the
+//! app, so a restricted profile cannot be lifted from the restricted UI. Commands not
+//! classified here count as admin. Guest mode further limits an instance to `read`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::RwLock;
+use tauri::ipc::Invoke;
+use tauri::{Manager, Runtime};
+
+use crate::guest::GuestMode;
+
+/// File in the data directory holding the active profile
+pub const PERMISSIONS_FILE: &str = "permissions.json";
+
+/// Group of commands allowed or refused together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    /// Browse workflows, jobs, galleries and settings
+    Read,
+    /// Edit and delete data, export files
+    Write,
+    /// Queue generations and spend provider credits
+    Generate,
+    /// Provider credentials, app settings and database maintenance
+    Admin,
+}
+
+/// `get_*` commands returning credentials, e.g. storage secrets or auth headers
+const CREDENTIAL_COMMANDS: &[&str] = &[
+    "get_storage_settings",
+    "get_provider_headers",
+    "get_openai_compatible_config",
+    "get_c2pa_settings",
+];
+
+/// Commands outside `get_*`/`list_*` that only read
+const READ_COMMANDS: &[&str] = &[
+    "estimate_generation",
+    "check_for_updates",
+    "check_port",
+    "detect_watermark",
+    "open_in_default_app",
+    "open_with_app",
+    "test_provider",
+    "subscribe_logs",
+    "unsubscribe_logs",
+    "preview_audio_notification",
+];
+
+const WRITE_COMMANDS: &[&str] = &[
+    "create_workflow",
+    "update_workflow",
+    "delete_workflow",
+    "create_scene",
+    "delete_scene",
+    "update_job",
+    "delete_job",
+    "create_version",
+    "create_notification_rule",
+    "update_notification_rule",
+    "delete_notification_rule",
+    "create_parameter_preset",
+    "update_parameter_preset",
+    "delete_parameter_preset",
+    "create_smart_collection",
+    "update_smart_collection",
+    "delete_smart_collection",
+    "start_refine_session",
+    "export_markdown",
+    "export_schedule_ics",
+    "export_usage_csv",
+    "export_social",
+    "compose_grid",
+    "export_assets_zip",
+    "import_invokeai_board",
+    "scan_asset_integrity",
+    "run_setup_checks",
+    "start_thumbnail_backfill",
+    "set_locale",
+];
+
+const GENERATE_COMMANDS: &[&str] = &[
+    "create_job",
+    "submit_generation",
+    "submit_pipeline",
+    "rerun_workflow",
+    "submit_sweep",
+    "judge_sweep",
+    "refine_prompt",
+    "bisect_refine",
+    "queue_midjourney_action",
+    "approve_job",
+    "retry_job",
+    "call_ai",
+    "lint_prompt",
+    "generate_stream",
+];
+
+/// Commands callable under any profile; they check their own preconditions
+const ALWAYS_ALLOWED: &[&str] = &["get_guest_mode", "set_guest_mode", "get_permissions"];
+
+/// Tier a command belongs to
+pub fn tier_of(command: &str) -> Tier {
+    let read = command.starts_with("get_") || command.starts_with("list_");
+    if CREDENTIAL_COMMANDS.contains(&command) {
+        Tier::Admin
+    } else if read || READ_COMMANDS.contains(&command) {
+        Tier::Read
+    } else if WRITE_COMMANDS.contains(&command) {
+        Tier::Write
+    } else if GENERATE_COMMANDS.contains(&command) {
+        Tier::Generate
+    } else {
+        Tier::Admin
+    }
+}
+
+/// Named set of callable tiers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PermissionProfile {
+    pub name: String,
+    /// Ignored for the built-in `full`, `review` and `kiosk` profiles
+    pub tiers: BTreeSet<Tier>,
+}
+
+impl Default for PermissionProfile {
+    fn default() -> Self {
+        Self::preset("full").unwrap()
+    }
+}
+
+impl PermissionProfile {
+    /// Built-in profiles: everything, review without spending, and generate-only kiosks
+    pub fn preset(name: &str) -> Option<Self> {
+        let tiers: &[Tier] = match name {
+            "full" => &[Tier::Read, Tier::Write, Tier::Generate, Tier::Admin],
+            "review" => &[Tier::Read, Tier::Write],
+            "kiosk" => &[Tier::Read, Tier::Generate],
+            _ => return None,
+        };
+        Some(Self {
+            name: name.to_string(),
+            tiers: tiers.iter().copied().collect(),
+        })
+    }
+
+    /// Read the workspace profile, falling back to `full`
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(PERMISSIONS_FILE);
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(profile) => Self::preset(&profile.name).unwrap_or(profile),
+            Err(e) => {
+                // A broken file must not silently grant everything
+                log_warn!(
+                    "[Permissions] Unreadable {}, allowing read only: {}",
+                    path.display(),
+                    e
+                );
+                Self {
+                    name: "invalid".to_string(),
+                    tiers: BTreeSet::from([Tier::Read]),
+                }
+            }
+        }
+    }
+}
+
+/// What the frontend may call, to hide controls that would be refused
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionStatus {
+    pub profile: String,
+    pub tiers: BTreeSet<Tier>,
+    pub guest_mode: bool,
+}
+
+/// Managed state holding the active profile
+#[derive(Default)]
+pub struct Permissions {
+    profile: RwLock<PermissionProfile>,
+}
+
+impl Permissions {
+    pub fn load(&self, data_dir: &Path) {
+        let profile = PermissionProfile::load(data_dir);
+        log_info!("[Permissions] Using profile {} ({:?})", profile.name, profile.tiers);
+        *self.profile.write().unwrap() = profile;
+    }
+
+    /// Profile tiers narrowed to `read` while guest mode is on
+    pub fn status(&self, guest_mode: bool) -> PermissionStatus {
+        let profile = self.profile.read().unwrap();
+        let tiers = profile
+            .tiers
+            .iter()
+            .copied()
+            .filter(|tier| !guest_mode || *tier == Tier::Read)
+            .collect();
+        PermissionStatus {
+            profile: profile.name.clone(),
+            tiers,
+            guest_mode,
+        }
+    }
+}
+
+/// Wrap the command handler so refused commands never run
+pub fn guard<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        let refusal = if ALWAYS_ALLOWED.contains(&command) {
+            None
+        } else {
+            let webview = invoke.message.webview_ref();
+            let guest_mode = webview
+                .try_state::<GuestMode>()
+                .is_some_and(|guest| guest.is_enabled());
+            let status = webview
+                .try_state::<Permissions>()
+                .map(|permissions| permissions.status(guest_mode));
+            let tier = tier_of(command);
+            match status {
+                Some(status) if !status.tiers.contains(&tier) => Some(if status.guest_mode {
+                    format!("{} is not available in read-only guest mode", command)
+                } else {
+                    format!(
+                        "{} needs the {:?} tier, which profile {} does not allow",
+                        command, tier, status.profile
+                    )
+                }),
+                _ => None,
+            }
+        };
+
+        if let Some(message) = refusal {
+            invoke.resolver.reject(message);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tier_of() {
+        assert_eq!(tier_of("list_jobs"), Tier::Read);
+        assert_eq!(tier_of("test_provider"), Tier::Read);
+        assert_eq!(tier_of("delete_workflow"), Tier::Write);
+        assert_eq!(tier_of("submit_generation"), Tier::Generate);
+        assert_eq!(tier_of("configure_provider"), Tier::Admin);
+        assert_eq!(tier_of("some_new_command"), Tier::Admin);
+        assert_eq!(tier_of("get_storage_settings"), Tier::Admin);
+    }
+
+    #[test]
+    fn test_read_commands_return_no_credentials() {
+        // Settings types holding keys, passwords or auth headers
+        const SECRET_TYPES: &[&str] = &[
+            "StorageSettings",
+            "HeaderSettings",
+            "OpenAICompatibleConfig",
+            "C2paSettings",
+        ];
+        let source = include_str!("commands.rs");
+        for item in source.split("pub async fn ").skip(1) {
+            let (name, rest) = item.split_once('(').unwrap();
+            let returns = rest.split_once('{').unwrap().0;
+            if SECRET_TYPES.iter().any(|ty| returns.contains(&format!("Result<{},", ty))) {
+                assert_eq!(tier_of(name), Tier::Admin, "{} returns credentials", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiered_commands_are_registered() {
+        let source = include_str!("lib.rs");
+        let handler = source.split("generate_handler![").nth(1).unwrap();
+        let handler = handler.split(']').next().unwrap();
+        let registered: BTreeSet<&str> = handler
+            .split(',')
+            .filter_map(|entry| entry.trim().strip_prefix("commands::"))
+            .collect();
+
+        let tiered = [
+            CREDENTIAL_COMMANDS,
+            READ_COMMANDS,
+            WRITE_COMMANDS,
+            GENERATE_COMMANDS,
+            ALWAYS_ALLOWED,
+        ];
+        for command in tiered.concat() {
+            assert!(registered.contains(command), "{} is not registered", command);
+        }
+    }
+
+    #[test]
+    fn test_guest_mode_narrows_to_read() {
+        let permissions = Permissions::default();
+        assert_eq!(permissions.status(false).tiers.len(), 4);
+        assert_eq!(permissions.status(true).tiers, BTreeSet::from([Tier::Read]));
+    }
+}
@@ -0,0 +1,236 @@
+//! Persistent storage for provider API keys.
+//!
+//! Keys are written to the OS credential store (Keychain, Windows Credential Manager
+//! or Secret Service). Sandboxed builds (snap, flatpak) often have no access to it, so
+//! keys fall back to a ChaCha20-Poly1305 encrypted file in the app data directory.
+//! The fallback key sits next to that file with owner-only permissions: it keeps keys
+//! out of plaintext backups and logs, not away from someone with the user's account.
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Service name of keyring entries; the provider name is the entry's user
+const KEYRING_SERVICE: &str = "promptcraft-desktop";
+
+const SECRETS_FILE: &str = "secrets.enc";
+const SECRETS_KEY_FILE: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+
+/// Cloud providers whose API keys are restored on startup
+pub const API_KEY_PROVIDERS: &[&str] = &[
+    "anthropic", "openai", "google", "grok", "flux", "fal", "recraft", "leonardo", "runway",
+    "pika", "minimax", "volcengine", "elevenlabs", "stability", "vertex",
+    "groq", "midjourney", "perplexity",
+];
+
+/// Where an API key was stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretLocation {
+    Keyring,
+    EncryptedFile,
+}
+
+/// Save a provider's API key, preferring the OS keyring
+pub fn store_api_key(data_dir: &Path, provider: &str, api_key: &str) -> Result<SecretLocation> {
+    if !is_sandboxed() {
+        match entry(provider).and_then(|entry| Ok(entry.set_password(api_key)?)) {
+            Ok(()) => {
+                // Do not leave an older copy behind in the fallback file
+                remove_from_file(data_dir, provider)?;
+                return Ok(SecretLocation::Keyring);
+            }
+            Err(e) => log_warn!("[Secrets] Keyring unavailable, using encrypted file: {}", e),
+        }
+    }
+
+    let mut secrets = read_file(data_dir)?;
+    secrets.insert(provider.to_string(), api_key.to_string());
+    write_file(data_dir, &secrets)?;
+    Ok(SecretLocation::EncryptedFile)
+}
+
+/// Load a provider's API key from the keyring or the fallback file
+pub fn load_api_key(data_dir: &Path, provider: &str) -> Result<Option<String>> {
+    if !is_sandboxed() {
+        match entry(provider).and_then(|entry| Ok(entry.get_password()?)) {
+            Ok(api_key) => return Ok(Some(api_key)),
+            Err(e) => {
+                if !is_no_entry(&e) {
+                    log_warn!("[Secrets] Could not read {} key from keyring: {}", provider, e);
+                }
+            }
+        }
+    }
+
+    Ok(read_file(data_dir)?.remove(provider))
+}
+
+/// Remove a provider's API key from every location
+pub fn delete_api_key(data_dir: &Path, provider: &str) -> Result<()> {
+    if !is_sandboxed() {
+        if let Err(e) = entry(provider).and_then(|entry| Ok(entry.delete_credential()?)) {
+            if !is_no_entry(&e) {
+                return Err(e);
+            }
+        }
+    }
+    remove_from_file(data_dir, provider)
+}
+
+fn entry(provider: &str) -> Result<keyring::Entry> {
+    Ok(keyring::Entry::new(KEYRING_SERVICE, provider)?)
+}
+
+fn is_no_entry(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<keyring::Error>(), Some(keyring::Error::NoEntry))
+}
+
+/// Snap and flatpak confinement usually blocks the credential store
+fn is_sandboxed() -> bool {
+    std::env::var_os("SNAP").is_some() || std::env::var_os("FLATPAK_ID").is_some()
+}
+
+fn remove_from_file(data_dir: &Path, provider: &str) -> Result<()> {
+    let mut secrets = read_file(data_dir)?;
+    if secrets.remove(provider).is_some() {
+        write_file(data_dir, &secrets)?;
+    }
+    Ok(())
+}
+
+fn read_file(data_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let path = data_dir.join(SECRETS_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let cipher = ChaCha20Poly1305::new(&file_key(data_dir)?);
+    let plaintext = decrypt(&cipher, &std::fs::read_to_string(&path)?)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+fn write_file(data_dir: &Path, secrets: &BTreeMap<String, String>) -> Result<()> {
+    let cipher = ChaCha20Poly1305::new(&file_key(data_dir)?);
+    let encrypted = encrypt(&cipher, &serde_json::to_vec(secrets)?)?;
+    write_private(&data_dir.join(SECRETS_FILE), encrypted.as_bytes())
+}
+
+/// Key of the fallback file, generated on first use
+fn file_key(data_dir: &Path) -> Result<Key> {
+    let path = data_dir.join(SECRETS_KEY_FILE);
+    if path.exists() {
+        let bytes = std::fs::read(&path)?;
+        if bytes.len() != 32 {
+            return Err(anyhow::anyhow!("Secrets key file is corrupt: {}", path.display()));
+        }
+        return Ok(*Key::from_slice(&bytes));
+    }
+
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    write_private(&path, key.as_slice())?;
+    Ok(key)
+}
+
+/// Derive a cipher key from a passphrase with PBKDF2-HMAC-SHA256
+pub fn passphrase_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key {
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(passphrase.as_bytes())
+        .expect("HMAC accepts keys of any length");
+
+    // One output block is exactly the 32 bytes of a key
+    let mut first = mac.clone();
+    first.update(salt);
+    first.update(&1u32.to_be_bytes());
+    let mut block = first.finalize().into_bytes();
+    let mut key = block;
+    for _ in 1..iterations {
+        let mut round = mac.clone();
+        round.update(&block);
+        block = round.finalize().into_bytes();
+        key.iter_mut().zip(block.iter()).for_each(|(k, b)| *k ^= b);
+    }
+    *Key::from_slice(&key)
+}
+
+/// Base64 of the nonce followed by the ciphertext
+pub fn encrypt(cipher: &ChaCha20Poly1305, plaintext: &[u8]) -> Result<String> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt secrets"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+pub fn decrypt(cipher: &ChaCha20Poly1305, encoded: &str) -> Result<Vec<u8>> {
+    let sealed = general_purpose::STANDARD.decode(encoded.trim())?;
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("Secrets file is corrupt"));
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Secrets file could not be decrypted"))
+}
+
+/// Write a file readable only by the current user
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(bytes)?;
+    }
+    #[cfg(not(unix))]
+    std::fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        let sealed = encrypt(&cipher, b"{\"openai\":\"sk-test\"}").unwrap();
+
+        assert!(!sealed.contains("sk-test"));
+        assert_eq!(decrypt(&cipher, &sealed).unwrap(), b"{\"openai\":\"sk-test\"}");
+
+        let other = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        assert!(decrypt(&other, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_key() {
+        // PBKDF2-HMAC-SHA256 test vectors ("password", "salt")
+        let hex = |key: Key| key.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        assert_eq!(
+            hex(passphrase_key("password", b"salt", 1)),
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+        );
+        assert_eq!(
+            hex(passphrase_key("password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Checks run by the first-run setup wizard.
+//!
+//! Each check reports a status the wizard can render as one line of a checklist. Local
+//! backends are optional and the machine may be offline, so a provider that cannot be
+//! reached is a warning; a stored API key the provider rejects is a failure, since
+//! generations with it would fail.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::generation::health::{ProviderErrorKind, ProviderTestResult};
+use crate::generation::{self, GenerationService};
+use crate::secrets::API_KEY_PROVIDERS;
+
+/// Backends probed on this machine
+const LOCAL_BACKENDS: &[&str] = &["a1111", "comfyui", "invokeai"];
+
+/// File written and removed to prove a directory is writable
+const PROBE_FILE: &str = ".promptcraft-write-test";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    /// Works, but something optional is missing
+    Warning,
+    Failed,
+    /// Nothing to check, e.g. a provider without a key
+    Skipped,
+}
+
+/// One line of the setup checklist
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupCheck {
+    /// Stable identifier, e.g. `data_dir` or `provider:openai`
+    pub id: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// Connection test behind a provider check
+    pub provider: Option<ProviderTestResult>,
+}
+
+/// Outcome of `run_setup_checks`
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupChecklist {
+    pub checks: Vec<SetupCheck>,
+    /// No check failed
+    pub ready: bool,
+}
+
+impl SetupCheck {
+    fn new(id: &str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            status,
+            message: message.into(),
+            provider: None,
+        }
+    }
+}
+
+/// Write and remove a probe file in `dir`, creating the directory first
+fn check_writable(id: &str, dir: &Path) -> SetupCheck {
+    let probe = dir.join(PROBE_FILE);
+    let outcome = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match outcome {
+        Ok(()) => SetupCheck::new(id, CheckStatus::Passed, dir.display().to_string()),
+        Err(e) => SetupCheck::new(
+            id,
+            CheckStatus::Failed,
+            format!("Cannot write to {}: {}", dir.display(), e),
+        ),
+    }
+}
+
+/// Checklist line of a provider connection test
+pub fn provider_check(result: ProviderTestResult) -> SetupCheck {
+    let status = match result.error_kind {
+        None if result.success => CheckStatus::Passed,
+        Some(ProviderErrorKind::NotConfigured) => CheckStatus::Skipped,
+        Some(
+            ProviderErrorKind::Network
+            | ProviderErrorKind::RateLimited
+            | ProviderErrorKind::Server,
+        ) => CheckStatus::Warning,
+        _ => CheckStatus::Failed,
+    };
+    let message = match (&result.message, result.models) {
+        (Some(message), _) => message.clone(),
+        (None, Some(models)) => format!("Connected, {} models", models),
+        (None, None) => "Connected".to_string(),
+    };
+    SetupCheck {
+        id: format!("provider:{}", result.provider),
+        status,
+        message,
+        provider: Some(result),
+    }
+}
+
+/// Check the data directory, the output folder, local backends and stored API keys
+pub async fn run(data_dir: &Path, service: &GenerationService) -> SetupChecklist {
+    let mut checks = vec![check_writable("data_dir", data_dir)];
+
+    checks.push(match generation::images_dir() {
+        Ok(dir) => check_writable("output_dir", &dir),
+        Err(e) => SetupCheck::new("output_dir", CheckStatus::Failed, e.to_string()),
+    });
+
+    let providers = service.list_providers();
+    let names = LOCAL_BACKENDS
+        .iter()
+        .chain(API_KEY_PROVIDERS)
+        .filter(|name| providers.iter().any(|p| p == *name));
+    let tests = names.map(|name| async move {
+        match service.test_provider(name).await {
+            Ok(result) => provider_check(result),
+            Err(e) => SetupCheck::new(
+                &format!("provider:{}", name),
+                CheckStatus::Failed,
+                e.to_string(),
+            ),
+        }
+    });
+    checks.extend(futures_util::future::join_all(tests).await);
+
+    let ready = checks.iter().all(|check| check.status != CheckStatus::Failed);
+    SetupChecklist { checks, ready }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_check_status() {
+        let result = |error_kind: Option<ProviderErrorKind>| ProviderTestResult {
+            provider: "a1111".to_string(),
+            success: error_kind.is_none(),
+            latency_ms: 5,
+            error_kind,
+            message: None,
+            models: Some(3),
+        };
+        let check = provider_check(result(None));
+        assert_eq!(check.status, CheckStatus::Passed);
+        assert_eq!(check.message, "Connected, 3 models");
+        assert_eq!(check.id, "provider:a1111");
+
+        let network = Some(ProviderErrorKind::Network);
+        assert_eq!(provider_check(result(network)).status, CheckStatus::Warning);
+        let bad_key = Some(ProviderErrorKind::BadKey);
+        assert_eq!(provider_check(result(bad_key)).status, CheckStatus::Failed);
+        let missing = Some(ProviderErrorKind::NotConfigured);
+        assert_eq!(provider_check(result(missing)).status, CheckStatus::Skipped);
+
+        let dir = std::env::temp_dir().join("promptcraft-setup-test");
+        assert_eq!(check_writable("data_dir", &dir).status, CheckStatus::Passed);
+        assert!(!dir.join(PROBE_FILE).exists());
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
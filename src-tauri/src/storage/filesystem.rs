@@ -0,0 +1,82 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+use super::{paths, StorageBackend, StoredAsset};
+use crate::generation::{audio_dir, images_dir};
+
+/// Stores files in a directory: the local asset store or a mounted network share
+pub struct FilesystemBackend {
+    name: &'static str,
+    /// `None` means the local asset store (`~/Pictures/Promptcraft`)
+    root: Option<PathBuf>,
+    /// Store in the local audio folder (`~/Music/Promptcraft`) instead
+    audio: bool,
+}
+
+impl FilesystemBackend {
+    pub fn local() -> Self {
+        Self {
+            name: "local",
+            root: None,
+            audio: false,
+        }
+    }
+
+    /// The local folder for generated speech and music
+    pub fn audio() -> Self {
+        Self {
+            name: "local",
+            root: None,
+            audio: true,
+        }
+    }
+
+    pub fn network_share(root: PathBuf) -> Self {
+        Self {
+            name: "network_share",
+            root: Some(root),
+            audio: false,
+        }
+    }
+
+    fn root(&self) -> Result<PathBuf> {
+        match &self.root {
+            Some(root) => Ok(root.clone()),
+            None if self.audio => audio_dir(),
+            None => images_dir(),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for FilesystemBackend {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn put(&self, file_name: &str, bytes: &[u8]) -> Result<StoredAsset> {
+        let file_path = paths::unique_file(&self.root()?, file_name)?;
+        paths::write_atomic(&file_path, bytes).await?;
+
+        Ok(StoredAsset {
+            file_path,
+            remote_url: None,
+        })
+    }
+
+    async fn check(&self) -> Result<()> {
+        let root = self.root()?;
+        // A share that is not mounted must not be silently created on the local disk
+        if !tokio::fs::try_exists(&root).await? {
+            return Err(anyhow::anyhow!("Storage path does not exist: {}", root.display()));
+        }
+
+        let probe = root.join(format!(".promptcraft-write-test-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&probe, b"ok")
+            .await
+            .map_err(|e| anyhow::anyhow!("Storage path is not writable ({}): {}", root.display(), e))?;
+        tokio::fs::remove_file(&probe).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub mod filesystem;
+pub mod paths;
+pub mod s3;
+
+use filesystem::FilesystemBackend;
+use crate::db::operations::SettingsOps;
+use crate::secrets;
+use s3::{S3Backend, S3Config};
+
+/// Settings key for the asset storage configuration
+pub const STORAGE_SETTINGS_KEY: &str = "storage_settings";
+
+/// Credential store entry of the S3 secret access key
+pub const S3_SECRET: &str = "s3_storage";
+
+/// Where a stored asset ended up
+#[derive(Debug, Clone)]
+pub struct StoredAsset {
+    /// Local file the app can display and open (a cached copy for remote backends)
+    pub file_path: PathBuf,
+    /// Location in the remote backend, if the asset was uploaded
+    pub remote_url: Option<String>,
+}
+
+/// Destination for generated output files
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Backend kind (e.g., "local", "network_share", "s3")
+    fn name(&self) -> &str;
+
+    /// Store `bytes` under `file_name`
+    async fn put(&self, file_name: &str, bytes: &[u8]) -> Result<StoredAsset>;
+
+    /// Verify the backend is reachable and writable
+    async fn check(&self) -> Result<()>;
+}
+
+/// Asset storage configuration, stored in the settings table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    /// `local`, `network_share` or `s3`
+    pub backend: String,
+    /// Root directory for `network_share` (a mounted NAS path or UNC path)
+    pub path: Option<String>,
+    pub s3: Option<S3Config>,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            backend: "local".to_string(),
+            path: None,
+            s3: None,
+        }
+    }
+}
+
+/// Load the storage settings with the S3 secret from the credential store
+///
+/// A secret still saved in the settings, from before it moved out, is moved on the way.
+pub async fn load_settings(pool: &SqlitePool, data_dir: &Path) -> Result<StorageSettings> {
+    let mut settings: StorageSettings =
+        SettingsOps::get_or_default(pool, STORAGE_SETTINGS_KEY).await?;
+    let Some(s3) = settings.s3.as_mut() else {
+        return Ok(settings);
+    };
+    if !s3.secret_access_key.is_empty() {
+        save_settings(pool, data_dir, &settings).await?;
+        return Ok(settings);
+    }
+    // Credential store calls can block (Secret Service goes over D-Bus)
+    let dir = data_dir.to_path_buf();
+    let secret = tokio::task::spawn_blocking(move || secrets::load_api_key(&dir, S3_SECRET))
+        .await??;
+    s3.secret_access_key = secret.unwrap_or_default();
+    Ok(settings)
+}
+
+/// Save the storage settings, keeping the S3 secret in the credential store
+pub async fn save_settings(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    settings: &StorageSettings,
+) -> Result<()> {
+    let mut stored = settings.clone();
+    if let Some(s3) = stored.s3.as_mut() {
+        let secret = std::mem::take(&mut s3.secret_access_key);
+        if !secret.is_empty() {
+            let dir = data_dir.to_path_buf();
+            tokio::task::spawn_blocking(move || secrets::store_api_key(&dir, S3_SECRET, &secret))
+                .await??;
+        }
+    }
+    SettingsOps::set(pool, STORAGE_SETTINGS_KEY, &stored).await
+}
+
+/// Build the backend described by the settings
+pub fn build_backend(settings: &StorageSettings) -> Result<Arc<dyn StorageBackend>> {
+    match settings.backend.as_str() {
+        "local" => Ok(Arc::new(FilesystemBackend::local())),
+        "network_share" => {
+            let path = settings
+                .path
+                .as_deref()
+                .filter(|p| !p.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("A network share needs a path"))?;
+            Ok(Arc::new(FilesystemBackend::network_share(PathBuf::from(path))))
+        }
+        "s3" => {
+            let config = settings
+                .s3
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("S3 storage needs a bucket configuration"))?;
+            Ok(Arc::new(S3Backend::new(config)?))
+        }
+        other => Err(anyhow::anyhow!("Unknown storage backend: {}", other)),
+    }
+}
@@ -0,0 +1,160 @@
+//! Path construction for files the app writes.
+//!
+//! Names often come from user content (workflow and scene names, provider file
+//! names), so every output path goes through here: names are sanitized for all
+//! platforms, kept within file name and path length limits, and files are written
+//! through a temp file so a failed write never leaves a truncated output behind.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Longest file name we produce, in bytes (most file systems allow 255)
+const MAX_FILE_NAME_BYTES: usize = 200;
+
+/// Longest full path we produce, in bytes
+#[cfg(windows)]
+const MAX_PATH_BYTES: usize = 259;
+#[cfg(not(windows))]
+const MAX_PATH_BYTES: usize = 4095;
+
+/// Device names Windows refuses as file names, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make a string safe to use as a file name on all platforms
+pub fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+
+    let trimmed = sanitized.trim().trim_matches('.').trim();
+    if trimmed.is_empty() {
+        return "untitled".to_string();
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or_default().trim_end();
+    let name = if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        format!("_{}", trimmed)
+    } else {
+        trimmed.to_string()
+    };
+
+    // Shorten the stem so the extension survives
+    let (stem, extension) = split_extension(&name);
+    let stem: String = stem.chars().take(120).collect();
+    let stem = truncate_bytes(&stem, MAX_FILE_NAME_BYTES - extension.len()).trim_end();
+    format!("{}{}", stem, extension)
+}
+
+/// Join a sanitized file name onto `dir`, shortening the name if the path gets too long
+pub fn join_file(dir: &Path, file_name: &str) -> Result<PathBuf> {
+    let file_name = sanitize_file_name(file_name);
+    let (stem, extension) = split_extension(&file_name);
+
+    // Leave room for the separator and a collision suffix such as " (12)"
+    let available = MAX_PATH_BYTES
+        .saturating_sub(dir.as_os_str().len() + 1 + extension.len() + 5);
+    if available < 8 {
+        return Err(anyhow::anyhow!("Output directory path is too long: {}", dir.display()));
+    }
+
+    let stem = truncate_bytes(stem, available).trim_end();
+    Ok(dir.join(format!("{}{}", stem, extension)))
+}
+
+/// Like [`join_file`], but adds a " (n)" suffix instead of overwriting an existing file
+pub fn unique_file(dir: &Path, file_name: &str) -> Result<PathBuf> {
+    let path = join_file(dir, file_name)?;
+    if !path.exists() {
+        return Ok(path);
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (stem, extension) = split_extension(&file_name);
+    for n in 2..1000 {
+        let candidate = dir.join(format!("{} ({}){}", stem, n, extension));
+        if !candidate.exists() {
+            return Ok(candidate);
+        }
+    }
+    Err(anyhow::anyhow!("Too many files named {} in {}", file_name, dir.display()))
+}
+
+/// Write a file through a temp file in the same directory, then rename it into place
+pub async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Not a file path: {}", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        truncate_bytes(&file_name.to_string_lossy(), 64),
+        uuid::Uuid::new_v4().simple()
+    ));
+
+    tokio::fs::write(&temp, bytes).await?;
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Split `name.ext` into `("name", ".ext")`; dotfiles and names without a dot have no extension
+fn split_extension(file_name: &str) -> (&str, &str) {
+    match file_name.rfind('.') {
+        Some(index) if index > 0 && file_name.len() - index <= 10 => file_name.split_at(index),
+        _ => (file_name, ""),
+    }
+}
+
+/// Cut a string to at most `max` bytes without splitting a character
+fn truncate_bytes(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(sanitize_file_name("Portrait: v2 / final?"), "Portrait- v2 - final-");
+        assert_eq!(sanitize_file_name("  ..  "), "untitled");
+        assert_eq!(sanitize_file_name("[[link]]#tag"), "--link---tag");
+        assert_eq!(sanitize_file_name("café ☕"), "café ☕");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "-..-etc-passwd");
+        assert_eq!(sanitize_file_name("con.png"), "_con.png");
+        assert_eq!(sanitize_file_name("Console"), "Console");
+
+        let long = sanitize_file_name(&"夜".repeat(120));
+        assert!(long.len() <= MAX_FILE_NAME_BYTES);
+        assert!(long.chars().all(|c| c == '夜'));
+    }
+
+    #[test]
+    fn test_join_file_keeps_extension() {
+        let dir = Path::new("/outputs");
+        assert_eq!(join_file(dir, "a/b.png").unwrap(), PathBuf::from("/outputs/a-b.png"));
+
+        let path = join_file(dir, &format!("{}.png", "x".repeat(300))).unwrap();
+        assert!(path.to_string_lossy().ends_with(".png"));
+        assert!(path.file_name().unwrap().len() <= MAX_FILE_NAME_BYTES);
+    }
+}
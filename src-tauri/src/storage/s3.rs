@@ -0,0 +1,232 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::filesystem::FilesystemBackend;
+use super::{paths, StorageBackend, StoredAsset};
+use crate::generation::timeouts::ProviderTimeouts;
+
+/// Bucket settings for S3 or an S3-compatible service (MinIO, R2, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    /// Custom endpoint for S3-compatible services; AWS is used when empty
+    pub endpoint: Option<String>,
+    /// Key prefix for uploaded files, e.g. "promptcraft/"
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key_id: String,
+    /// Saved in the credential store under [`super::S3_SECRET`], never in the settings
+    #[serde(default)]
+    pub secret_access_key: String,
+}
+
+/// Uploads outputs to a bucket, keeping a local copy so the app can show them
+pub struct S3Backend {
+    config: S3Config,
+    client: reqwest::Client,
+    cache: FilesystemBackend,
+}
+
+impl S3Backend {
+    pub fn new(config: S3Config) -> Result<Self> {
+        if config.bucket.trim().is_empty() || config.region.trim().is_empty() {
+            return Err(anyhow::anyhow!("S3 storage needs a bucket and region"));
+        }
+        if config.access_key_id.is_empty() || config.secret_access_key.is_empty() {
+            return Err(anyhow::anyhow!("S3 storage needs an access key"));
+        }
+
+        Ok(Self {
+            config,
+            client: ProviderTimeouts::default().client(),
+            cache: FilesystemBackend::local(),
+        })
+    }
+
+    /// Host and path of an object (or the bucket when `key` is empty)
+    fn location(&self, key: &str) -> (String, String) {
+        let key = uri_encode(key, false);
+        match self.config.endpoint.as_deref().filter(|e| !e.is_empty()) {
+            // Custom endpoints use path-style addressing, which every compatible service supports
+            Some(endpoint) => {
+                let host = endpoint
+                    .trim_start_matches("https://")
+                    .trim_start_matches("http://")
+                    .trim_end_matches('/')
+                    .to_string();
+                (host, format!("/{}/{}", self.config.bucket, key))
+            }
+            None => (
+                format!("{}.s3.{}.amazonaws.com", self.config.bucket, self.config.region),
+                format!("/{}", key),
+            ),
+        }
+    }
+
+    fn scheme(&self) -> &str {
+        match self.config.endpoint.as_deref() {
+            Some(endpoint) if endpoint.starts_with("http://") => "http",
+            _ => "https",
+        }
+    }
+
+    /// Send a request signed with AWS Signature Version 4
+    async fn send(&self, method: reqwest::Method, key: &str, body: Vec<u8>) -> Result<reqwest::Response> {
+        let (host, path) = self.location(key);
+        let payload_hash = hex_sha256(&body);
+        let headers = sign(
+            &self.config,
+            method.as_str(),
+            &host,
+            &path,
+            &payload_hash,
+            Utc::now(),
+        );
+
+        let mut request = self
+            .client
+            .request(method, format!("{}://{}{}", self.scheme(), host, path))
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        Ok(request.send().await?)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    fn name(&self) -> &str {
+        "s3"
+    }
+
+    async fn put(&self, file_name: &str, bytes: &[u8]) -> Result<StoredAsset> {
+        let file_name = paths::sanitize_file_name(file_name);
+        let key = format!("{}{}", self.config.prefix, file_name);
+        let response = self.send(reqwest::Method::PUT, &key, bytes.to_vec()).await?;
+        if !response.status().is_success() {
+            return Err(crate::generation::utils::api_error("S3", response).await);
+        }
+
+        let mut stored = self.cache.put(&file_name, bytes).await?;
+        let (host, path) = self.location(&key);
+        stored.remote_url = Some(format!("{}://{}{}", self.scheme(), host, path));
+        Ok(stored)
+    }
+
+    async fn check(&self) -> Result<()> {
+        let response = self.send(reqwest::Method::HEAD, "", Vec::new()).await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "S3 bucket {} is not accessible ({})",
+                self.config.bucket,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Headers (`host`, `x-amz-*`, `authorization`) for a SigV4-signed request without a query string
+fn sign(
+    config: &S3Config,
+    method: &str,
+    host: &str,
+    path: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex_sha256(canonical_request.as_bytes())
+    );
+
+    let key = signing_key(&config.secret_access_key, &date, &config.region, "s3");
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    vec![
+        ("x-amz-content-sha256", payload_hash.to_string()),
+        ("x-amz-date", amz_date),
+        (
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                config.access_key_id, scope, signed_headers, signature
+            ),
+        ),
+    ]
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encode per SigV4 rules; `/` is kept in object keys unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+
+        assert_eq!(uri_encode("renders/a b+c.png", false), "renders/a%20b%2Bc.png");
+    }
+}
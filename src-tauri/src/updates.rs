@@ -0,0 +1,101 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::compat::is_newer_version;
+use crate::db::models::APP_VERSION;
+
+/// GitHub releases endpoint for the latest published (non-draft, non-prerelease) release
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/c0dezer019/promptcraft-desktop/releases/latest";
+
+/// Result of an update check
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    pub release_name: Option<String>,
+    pub changelog: String,
+    pub published_at: Option<String>,
+    pub release_url: String,
+    /// Installer for the current platform, falling back to the release page
+    pub download_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Query GitHub for the latest release and compare it with the running version
+///
+/// The client honours the system proxy configuration (`HTTPS_PROXY`, `ALL_PROXY`, ...).
+pub async fn check_for_updates() -> Result<UpdateInfo> {
+    let client = reqwest::Client::builder()
+        .user_agent(format!("PromptCraft-Desktop/{}", APP_VERSION))
+        .timeout(Duration::from_secs(15))
+        .build()?;
+
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow::anyhow!(
+            "GitHub releases API error ({}): {}",
+            status,
+            error_text
+        ));
+    }
+
+    let release: GithubRelease = response.json().await?;
+    let latest_version = release.tag_name.trim_start_matches('v').to_string();
+
+    let download_url = platform_asset(&release.assets)
+        .map(|asset| asset.browser_download_url.clone())
+        .unwrap_or_else(|| release.html_url.clone());
+
+    Ok(UpdateInfo {
+        current_version: APP_VERSION.to_string(),
+        update_available: is_newer_version(&latest_version, APP_VERSION),
+        latest_version,
+        release_name: release.name,
+        changelog: release.body.unwrap_or_default(),
+        published_at: release.published_at,
+        release_url: release.html_url,
+        download_url,
+    })
+}
+
+/// Pick the installer asset matching the current OS, in order of preference
+fn platform_asset(assets: &[GithubAsset]) -> Option<&GithubAsset> {
+    let extensions: &[&str] = if cfg!(target_os = "windows") {
+        &[".msi", "-setup.exe", ".exe"]
+    } else if cfg!(target_os = "macos") {
+        &[".dmg", ".app.tar.gz"]
+    } else {
+        &[".AppImage", ".deb", ".rpm"]
+    };
+
+    extensions.iter().find_map(|ext| {
+        assets.iter().find(|asset| asset.name.ends_with(ext))
+    })
+}